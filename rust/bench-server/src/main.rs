@@ -1,12 +1,29 @@
+mod mtls;
+mod proxy;
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::iter::Peekable;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
 use bytes::Bytes;
 use http::{Method, Request, Response, StatusCode};
-use http_body_util::Full;
-use hyper::body::Incoming;
+use http_body_util::{BodyDataStream, Either, Full};
+use hyper::body::{Body, Frame, Incoming};
 use hyper::service::service_fn;
-use hyper_util::rt::TokioIo;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use rcgen::CertifiedKey;
 use tokio::net::TcpListener;
+use tokio::time::Sleep;
+use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
 
 static SMALL_BODY: &[u8] = b"OK";
 static JSON_BODY: &[u8] = b"{\"ok\":true,\"message\":\"hello\"}";
@@ -14,21 +31,153 @@ static JSON_BODY: &[u8] = b"{\"ok\":true,\"message\":\"hello\"}";
 const MAX_BINARY_LEN: usize = 1024 * 1024;
 const DEFAULT_BINARY_LEN: usize = 4096;
 
+const MAX_DRIP_CHUNKS: usize = 10_000;
+const DEFAULT_DRIP_CHUNKS: usize = 10;
+const DEFAULT_DRIP_DELAY_MS: usize = 100;
+const MAX_DRIP_CHUNK_SIZE: usize = 1024 * 1024;
+const DEFAULT_DRIP_CHUNK_SIZE: usize = 1024;
+
+const MAX_DELAY_MS: usize = 60_000;
+const DEFAULT_DELAY_MS: usize = 1000;
+
+const MAX_REDIRECT_HOPS: usize = 100;
+
+const DEFAULT_EVENT_COUNT: usize = 10;
+const DEFAULT_EVENT_INTERVAL_MS: usize = 1000;
+
+type BenchBody = Either<Full<Bytes>, Drip>;
+
+fn full(body: Bytes) -> BenchBody {
+    Either::Left(Full::new(body))
+}
+
+/// Body that sends `chunks` one at a time, waiting `delay` between them. A pending `sleep` also
+/// holds back the first one.
+struct Drip {
+    chunks: Peekable<Box<dyn Iterator<Item = Bytes> + Send>>,
+    delay: Duration,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl Drip {
+    fn new(chunks: impl Iterator<Item = Bytes> + Send + 'static, delay: Duration) -> Self {
+        let chunks: Box<dyn Iterator<Item = Bytes> + Send> = Box::new(chunks);
+        Self {
+            chunks: chunks.peekable(),
+            delay,
+            sleep: None,
+        }
+    }
+
+    /// Wait `delay` before the first chunk too.
+    fn delay_first(mut self) -> Self {
+        self.sleep = Some(Box::pin(tokio::time::sleep(self.delay)));
+        self
+    }
+}
+
+impl Body for Drip {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        // End right after the last chunk instead of waiting out another delay.
+        if self.chunks.peek().is_none() {
+            return Poll::Ready(None);
+        }
+        if let Some(sleep) = self.sleep.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        let delay = self.delay;
+        self.sleep = Some(Box::pin(tokio::time::sleep(delay)));
+        Poll::Ready(self.chunks.next().map(|chunk| Ok(Frame::data(chunk))))
+    }
+}
+
 fn parse_query_param(query: Option<&str>, key: &str) -> Option<usize> {
+    query_value(query, key)?.parse().ok()
+}
+
+/// Raw value of `key` in the query string. Values are not percent-decoded.
+fn query_value<'a>(query: Option<&'a str>, key: &str) -> Option<&'a str> {
     query?.split('&').find_map(|pair| {
         let (k, v) = pair.split_once('=')?;
         if k == key {
-            v.parse().ok()
+            Some(v)
         } else {
             None
         }
     })
 }
 
+/// `value` as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_or_null(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), json_string)
+}
+
+/// Describe each part of a multipart body as a JSON object with its name, file name, content
+/// type and size. Parts are counted as they stream in, never buffered.
+async fn describe_parts(body: Incoming, boundary: String) -> multer::Result<Vec<String>> {
+    let mut multipart = multer::Multipart::new(BodyDataStream::new(body), boundary);
+    let mut parts = Vec::new();
+    while let Some(mut field) = multipart.next_field().await? {
+        let name = json_or_null(field.name());
+        let filename = json_or_null(field.file_name());
+        let content_type = json_or_null(field.content_type().map(|mime| mime.as_ref()));
+        let mut size = 0;
+        while let Some(chunk) = field.chunk().await? {
+            size += chunk.len();
+        }
+        parts.push(format!(
+            "{{\"name\":{name},\"filename\":{filename},\"contentType\":{content_type},\"size\":{size}}}"
+        ));
+    }
+    Ok(parts)
+}
+
+fn json_response(body: String) -> Response<BenchBody> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json; charset=utf-8")
+        .header("content-length", body.len())
+        .body(full(Bytes::from(body)))
+        .unwrap()
+}
+
+/// What a request handler knows about the connection it arrived on.
+#[derive(Clone)]
+struct Conn {
+    scheme: &'static str,
+    /// Subject of the client certificate, on the mTLS listener.
+    client_subject: Option<Arc<str>>,
+}
+
 async fn handle(
     req: Request<Incoming>,
     binary_4k: Bytes,
-) -> Result<Response<Full<Bytes>>, hyper::Error> {
+    conn: Conn,
+) -> Result<Response<BenchBody>, hyper::Error> {
     let method = req.method();
     let path = req.uri().path();
     let query = req.uri().query();
@@ -38,7 +187,7 @@ async fn handle(
             .status(StatusCode::OK)
             .header("content-type", "text/plain; charset=utf-8")
             .header("content-length", SMALL_BODY.len())
-            .body(Full::new(Bytes::from_static(SMALL_BODY)))
+            .body(full(Bytes::from_static(SMALL_BODY)))
             .unwrap();
         return Ok(resp);
     }
@@ -48,7 +197,7 @@ async fn handle(
             .status(StatusCode::OK)
             .header("content-type", "application/json; charset=utf-8")
             .header("content-length", JSON_BODY.len())
-            .body(Full::new(Bytes::from_static(JSON_BODY)))
+            .body(full(Bytes::from_static(JSON_BODY)))
             .unwrap();
         return Ok(resp);
     }
@@ -68,11 +217,211 @@ async fn handle(
             .status(StatusCode::OK)
             .header("content-type", "application/octet-stream")
             .header("content-length", body.len())
-            .body(Full::new(body))
+            .body(full(body))
             .unwrap();
         return Ok(resp);
     }
 
+    if method == Method::GET && (path == "/delay" || path == "/delay-headers") {
+        let delay = parse_query_param(query, "ms")
+            .map(|n| n.min(MAX_DELAY_MS))
+            .unwrap_or(DEFAULT_DELAY_MS);
+        let delay = Duration::from_millis(delay as u64);
+
+        let builder = Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain; charset=utf-8")
+            .header("content-length", SMALL_BODY.len());
+
+        // /delay-headers holds back the whole response; /delay sends the headers right away and
+        // holds back the body.
+        let resp = if path == "/delay-headers" {
+            tokio::time::sleep(delay).await;
+            builder.body(full(Bytes::from_static(SMALL_BODY)))
+        } else {
+            let body = Drip::new(std::iter::once(Bytes::from_static(SMALL_BODY)), delay);
+            builder.body(Either::Right(body.delay_first()))
+        };
+        return Ok(resp.unwrap());
+    }
+
+    if method == Method::GET && path == "/drip" {
+        let chunks = parse_query_param(query, "chunks")
+            .map(|n| n.min(MAX_DRIP_CHUNKS))
+            .unwrap_or(DEFAULT_DRIP_CHUNKS);
+        let delay_ms = parse_query_param(query, "delay_ms")
+            .map(|n| n.min(MAX_DELAY_MS))
+            .unwrap_or(DEFAULT_DRIP_DELAY_MS);
+        let size = parse_query_param(query, "size")
+            .map(|n| n.clamp(1, MAX_DRIP_CHUNK_SIZE))
+            .unwrap_or(DEFAULT_DRIP_CHUNK_SIZE);
+
+        let chunk = Bytes::from(vec![0xab_u8; size]);
+        let body = Drip::new(
+            std::iter::repeat_n(chunk, chunks),
+            Duration::from_millis(delay_ms as u64),
+        );
+
+        // No content-length, so hyper sends the body chunked.
+        let resp = Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/octet-stream")
+            .body(Either::Right(body))
+            .unwrap();
+        return Ok(resp);
+    }
+
+    if method == Method::GET && path == "/events" {
+        let count = parse_query_param(query, "count").unwrap_or(DEFAULT_EVENT_COUNT);
+        let interval_ms = parse_query_param(query, "interval_ms")
+            .map(|n| n.min(MAX_DELAY_MS))
+            .unwrap_or(DEFAULT_EVENT_INTERVAL_MS);
+
+        // A count of 0 streams until the client goes away.
+        let ids: Box<dyn Iterator<Item = usize> + Send> = if count == 0 {
+            Box::new(1..)
+        } else {
+            Box::new(1..=count)
+        };
+        let events = ids.map(|id| {
+            Bytes::from(format!(
+                "id: {id}\nevent: message\ndata: {{\"id\":{id}}}\n\n"
+            ))
+        });
+
+        let resp = Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/event-stream")
+            .header("cache-control", "no-cache")
+            .body(Either::Right(Drip::new(
+                events,
+                Duration::from_millis(interval_ms as u64),
+            )))
+            .unwrap();
+        return Ok(resp);
+    }
+
+    if method == Method::GET && path == "/set-cookie" {
+        let (Some(name), Some(value)) = (query_value(query, "name"), query_value(query, "value"))
+        else {
+            let resp = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("content-type", "text/plain; charset=utf-8")
+                .body(full(Bytes::from_static(b"name and value are required")))
+                .unwrap();
+            return Ok(resp);
+        };
+
+        let resp = Response::builder()
+            .status(StatusCode::OK)
+            .header("set-cookie", format!("{name}={value}; Path=/"))
+            .header("content-type", "text/plain; charset=utf-8")
+            .header("content-length", SMALL_BODY.len())
+            .body(full(Bytes::from_static(SMALL_BODY)))
+            .unwrap();
+        return Ok(resp);
+    }
+
+    if method == Method::GET && path == "/cookies" {
+        // {"cookies":{"name":"value",...}}; HTTP/2 clients may split cookies over several headers.
+        let cookies: Vec<String> = req
+            .headers()
+            .get_all("cookie")
+            .iter()
+            .filter_map(|header| header.to_str().ok())
+            .flat_map(|header| header.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .map(|(name, value)| format!("{}:{}", json_string(name), json_string(value)))
+            .collect();
+        return Ok(json_response(format!(
+            "{{\"cookies\":{{{}}}}}",
+            cookies.join(",")
+        )));
+    }
+
+    let relative_hops = path.strip_prefix("/redirect/");
+    let absolute_hops = path.strip_prefix("/absolute-redirect/");
+    let redirect_hops = if method == Method::GET {
+        relative_hops.or(absolute_hops)
+    } else {
+        None
+    };
+    if let Some(hops) = redirect_hops {
+        let Some(hops) = hops
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n <= MAX_REDIRECT_HOPS)
+        else {
+            let resp = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("content-type", "text/plain; charset=utf-8")
+                .body(full(Bytes::from(format!(
+                    "hop count must be 0..={MAX_REDIRECT_HOPS}"
+                ))))
+                .unwrap();
+            return Ok(resp);
+        };
+
+        if hops == 0 {
+            return Ok(json_response(format!(
+                "{{\"redirected\":true,\"path\":{}}}",
+                json_string(path)
+            )));
+        }
+
+        let location = if relative_hops.is_some() {
+            format!("/redirect/{}", hops - 1)
+        } else {
+            let host = req
+                .uri()
+                .authority()
+                .map(|authority| authority.as_str())
+                .or_else(|| req.headers().get("host")?.to_str().ok())
+                .unwrap_or("127.0.0.1");
+            format!("{}://{host}/absolute-redirect/{}", conn.scheme, hops - 1)
+        };
+        let resp = Response::builder()
+            .status(StatusCode::FOUND)
+            .header("location", location)
+            .header("content-length", 0)
+            .body(full(Bytes::new()))
+            .unwrap();
+        return Ok(resp);
+    }
+
+    if method == Method::GET && path == "/client-cert" {
+        let subject = json_or_null(conn.client_subject.as_deref());
+        return Ok(json_response(format!("{{\"subject\":{subject}}}")));
+    }
+
+    if method == Method::POST && path == "/multipart" {
+        let boundary = req
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|content_type| multer::parse_boundary(content_type).ok());
+        let Some(boundary) = boundary else {
+            let resp = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("content-type", "text/plain; charset=utf-8")
+                .body(full(Bytes::from_static(
+                    b"expected a multipart/form-data body",
+                )))
+                .unwrap();
+            return Ok(resp);
+        };
+
+        let resp = match describe_parts(req.into_body(), boundary).await {
+            Ok(parts) => json_response(format!("{{\"parts\":[{}]}}", parts.join(","))),
+            Err(err) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("content-type", "text/plain; charset=utf-8")
+                .body(full(Bytes::from(err.to_string())))
+                .unwrap(),
+        };
+        return Ok(resp);
+    }
+
     if method == Method::POST && path == "/echo-len" {
         let expected_len = parse_query_param(query, "len").unwrap_or(0);
 
@@ -86,14 +435,14 @@ async fn handle(
             let resp = Response::builder()
                 .status(StatusCode::BAD_REQUEST)
                 .header("content-type", "text/plain; charset=utf-8")
-                .body(Full::new(Bytes::from(msg)))
+                .body(full(Bytes::from(msg)))
                 .unwrap();
             return Ok(resp);
         }
 
         let resp = Response::builder()
             .status(StatusCode::NO_CONTENT)
-            .body(Full::new(Bytes::new()))
+            .body(full(Bytes::new()))
             .unwrap();
         return Ok(resp);
     }
@@ -101,56 +450,183 @@ async fn handle(
     let resp = Response::builder()
         .status(StatusCode::NOT_FOUND)
         .header("content-type", "text/plain; charset=utf-8")
-        .body(Full::new(Bytes::from_static(b"not found")))
+        .body(full(Bytes::from_static(b"not found")))
         .unwrap();
     Ok(resp)
 }
 
 fn main() {
+    let args = Args::parse();
+
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
         .expect("failed to build runtime");
 
     let local = tokio::task::LocalSet::new();
-    local.block_on(&rt, server_loop());
+    local.block_on(&rt, server_loop(args));
+}
+
+/// Optional listeners, each started by a command-line flag.
+#[derive(Default)]
+struct Args {
+    /// `--tls-cert <path>`: serve HTTPS with a certificate written to this path.
+    tls_cert: Option<PathBuf>,
+    /// `--proxy`: run a CONNECT proxy.
+    proxy: bool,
+    /// `--proxy-auth <user:pass>`: credentials the proxy requires. Implies `--proxy`.
+    proxy_auth: Option<String>,
+    /// `--mtls-dir <dir>`: serve HTTPS that requires a client certificate, writing the
+    /// certificates to this directory.
+    mtls_dir: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tls-cert" => {
+                    parsed.tls_cert =
+                        Some(PathBuf::from(args.next().expect("--tls-cert needs a path")));
+                }
+                "--proxy" => parsed.proxy = true,
+                "--proxy-auth" => {
+                    parsed.proxy = true;
+                    parsed.proxy_auth = Some(args.next().expect("--proxy-auth needs user:pass"));
+                }
+                "--mtls-dir" => {
+                    parsed.mtls_dir = Some(PathBuf::from(
+                        args.next().expect("--mtls-dir needs a directory"),
+                    ));
+                }
+                other => panic!("unknown argument: {other}"),
+            }
+        }
+        parsed
+    }
+}
+
+/// Generate a self-signed certificate for localhost and 127.0.0.1 and write it to `cert_path` as
+/// PEM, so clients can trust it as a CA.
+fn tls_acceptor(cert_path: &Path) -> TlsAcceptor {
+    let CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])
+            .expect("failed to generate certificate");
+    std::fs::write(cert_path, cert.pem()).expect("failed to write certificate");
+
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(signing_key.serialize_der()));
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.der().clone()], key)
+        .expect("invalid certificate");
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    TlsAcceptor::from(Arc::new(config))
 }
 
-async fn server_loop() {
+async fn bind() -> TcpListener {
     let addr = SocketAddr::from(([127, 0, 0, 1], 0));
-    let listener = TcpListener::bind(addr).await.expect("failed to bind");
-    let local_addr = listener.local_addr().expect("failed to get local addr");
+    TcpListener::bind(addr).await.expect("failed to bind")
+}
+
+fn port(listener: &TcpListener) -> u16 {
+    listener
+        .local_addr()
+        .expect("failed to get local addr")
+        .port()
+}
+
+async fn server_loop(args: Args) {
+    let listener = bind().await;
 
     // Print port on first stdout line (read by JS launcher)
-    println!("{}", local_addr.port());
+    println!("{}", port(&listener));
 
     // Pre-allocate the 4KB binary body
     let binary_4k = Bytes::from(vec![0xab_u8; DEFAULT_BINARY_LEN]);
 
+    // Then one line per optional listener, in this order: HTTPS, proxy, mTLS. TLS listeners print
+    // theirs once the certificates have been written.
+    if let Some(path) = args.tls_cert {
+        let acceptor = tls_acceptor(&path);
+        let tls_listener = bind().await;
+        println!("{}", port(&tls_listener));
+        tokio::task::spawn_local(tls_loop(tls_listener, acceptor, binary_4k.clone()));
+    }
+
+    if args.proxy {
+        let proxy_listener = bind().await;
+        println!("{}", port(&proxy_listener));
+        tokio::task::spawn_local(proxy::proxy_loop(proxy_listener, args.proxy_auth));
+    }
+
+    if let Some(dir) = args.mtls_dir {
+        let acceptor = mtls::mtls_acceptor(&dir);
+        let mtls_listener = bind().await;
+        println!("{}", port(&mtls_listener));
+        tokio::task::spawn_local(tls_loop(mtls_listener, acceptor, binary_4k.clone()));
+    }
+
     loop {
         let (stream, _) = match listener.accept().await {
             Ok(conn) => conn,
             Err(_) => continue,
         };
 
-        let io = TokioIo::new(stream);
-        let binary_4k = binary_4k.clone();
+        let conn = Conn {
+            scheme: "http",
+            client_subject: None,
+        };
+        tokio::task::spawn_local(serve(TokioIo::new(stream), binary_4k.clone(), conn));
+    }
+}
 
+async fn tls_loop(listener: TcpListener, acceptor: TlsAcceptor, binary_4k: Bytes) {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(_) => continue,
+        };
+
+        let acceptor = acceptor.clone();
+        let binary_4k = binary_4k.clone();
         tokio::task::spawn_local(async move {
-            let service = service_fn(move |req| {
-                let binary_4k = binary_4k.clone();
-                handle(req, binary_4k)
-            });
-
-            if let Err(err) = hyper::server::conn::http1::Builder::new()
-                .keep_alive(true)
-                .serve_connection(io, service)
-                .await
-            {
-                if !err.is_incomplete_message() {
-                    eprintln!("connection error: {err}");
+            match acceptor.accept(stream).await {
+                Ok(stream) => {
+                    let conn = Conn {
+                        scheme: "https",
+                        client_subject: mtls::client_subject(
+                            stream.get_ref().1.peer_certificates(),
+                        ),
+                    };
+                    serve(TokioIo::new(stream), binary_4k, conn).await
                 }
+                Err(err) => eprintln!("tls handshake error: {err}"),
             }
         });
     }
 }
+
+async fn serve<I>(io: I, binary_4k: Bytes, conn: Conn)
+where
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + 'static,
+{
+    let service = service_fn(move |req| {
+        let binary_4k = binary_4k.clone();
+        handle(req, binary_4k, conn.clone())
+    });
+
+    // Speaks HTTP/1.1, or HTTP/2 when the client opens with the h2 preface (prior knowledge on
+    // the cleartext port, ALPN on the HTTPS one).
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder.http1().keep_alive(true);
+    if let Err(err) = builder.serve_connection(io, service).await {
+        let incomplete = err
+            .downcast_ref::<hyper::Error>()
+            .is_some_and(hyper::Error::is_incomplete_message);
+        if !incomplete {
+            eprintln!("connection error: {err}");
+        }
+    }
+}
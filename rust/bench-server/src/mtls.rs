@@ -0,0 +1,75 @@
+//! HTTPS listener that requires a client certificate, for testing client-certificate support.
+
+use rcgen::{
+    BasicConstraints, CertificateParams, CertifiedIssuer, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair,
+};
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// Generate a server certificate and a client certificate signed by a throwaway CA, and write
+/// them to `dir`:
+///
+/// - `server.crt`: the self-signed server certificate, to trust as a CA.
+/// - `client.crt` and `client.key`: the certificate and PKCS#8 key to present.
+///
+/// The acceptor only completes handshakes that present a certificate from that CA.
+pub fn mtls_acceptor(dir: &Path) -> TlsAcceptor {
+    let server =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])
+            .expect("failed to generate server certificate");
+
+    let mut ca_params = CertificateParams::default();
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "wreq-bench client CA");
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_key = KeyPair::generate().expect("failed to generate CA key");
+    let ca = CertifiedIssuer::self_signed(ca_params, ca_key).expect("failed to generate CA");
+
+    let mut client_params = CertificateParams::default();
+    client_params
+        .distinguished_name
+        .push(DnType::CommonName, "wreq-bench-client");
+    client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client_key = KeyPair::generate().expect("failed to generate client key");
+    let client = client_params
+        .signed_by(&client_key, &ca)
+        .expect("failed to sign client certificate");
+
+    std::fs::create_dir_all(dir).expect("failed to create mTLS directory");
+    std::fs::write(dir.join("server.crt"), server.cert.pem())
+        .expect("failed to write server certificate");
+    std::fs::write(dir.join("client.crt"), client.pem())
+        .expect("failed to write client certificate");
+    std::fs::write(dir.join("client.key"), client_key.serialize_pem())
+        .expect("failed to write client key");
+
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(ca.der().clone())
+        .expect("failed to trust client CA");
+    let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+        .build()
+        .expect("failed to build client verifier");
+
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(server.signing_key.serialize_der()));
+    let mut config = ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(vec![server.cert.der().clone()], key)
+        .expect("invalid certificate");
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    TlsAcceptor::from(Arc::new(config))
+}
+
+/// Subject of the certificate a client presented, e.g. `CN=wreq-bench-client`.
+pub fn client_subject(certificates: Option<&[CertificateDer<'_>]>) -> Option<Arc<str>> {
+    let certificate = certificates?.first()?;
+    let (_, parsed) = X509Certificate::from_der(certificate).ok()?;
+    Some(Arc::from(parsed.subject().to_string()))
+}
@@ -0,0 +1,103 @@
+//! Minimal HTTP CONNECT proxy, for testing proxy credentials and tunnel errors without an
+//! external proxy.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::rc::Rc;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Accept proxy connections forever. With `credentials` (`user:pass`), requests must carry
+/// matching Basic `Proxy-Authorization` or get a 407.
+pub async fn proxy_loop(listener: TcpListener, credentials: Option<String>) {
+    let expected = credentials
+        .map(|credentials| Rc::<str>::from(format!("Basic {}", STANDARD.encode(credentials))));
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(_) => continue,
+        };
+
+        let expected = expected.clone();
+        tokio::task::spawn_local(async move {
+            let service = service_fn(move |req| handle_proxy(req, expected.clone()));
+            if let Err(err) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades()
+                .await
+            {
+                if !err.is_incomplete_message() {
+                    eprintln!("proxy connection error: {err}");
+                }
+            }
+        });
+    }
+}
+
+async fn handle_proxy(
+    req: Request<Incoming>,
+    expected: Option<Rc<str>>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if let Some(expected) = expected {
+        let authorized = req
+            .headers()
+            .get("proxy-authorization")
+            .is_some_and(|value| value.as_bytes() == expected.as_bytes());
+        if !authorized {
+            return Ok(Response::builder()
+                .status(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+                .header("proxy-authenticate", "Basic realm=\"wreq-bench\"")
+                .body(Full::new(Bytes::from_static(
+                    b"proxy authentication required",
+                )))
+                .unwrap());
+        }
+    }
+
+    if req.method() != Method::CONNECT {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "CONNECT")
+            .body(Full::new(Bytes::from_static(b"only CONNECT is supported")))
+            .unwrap());
+    }
+
+    let Some(target) = req.uri().authority().map(|authority| authority.to_string()) else {
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Full::new(Bytes::from_static(b"CONNECT needs host:port")))
+            .unwrap());
+    };
+
+    // Connect before answering so an unreachable target shows up as a 502 on the CONNECT.
+    let mut upstream = match TcpStream::connect(&target).await {
+        Ok(upstream) => upstream,
+        Err(err) => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Full::new(Bytes::from(format!(
+                    "failed to connect to {target}: {err}"
+                ))))
+                .unwrap());
+        }
+    };
+
+    tokio::task::spawn_local(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                let mut client = TokioIo::new(upgraded);
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            }
+            Err(err) => eprintln!("proxy upgrade error: {err}"),
+        }
+    });
+
+    Ok(Response::new(Full::new(Bytes::new())))
+}
@@ -78,6 +78,28 @@ fn generate_typescript_types(profiles: &[String], operating_systems: &[String])
         }
     }
 
+    // Families are the profile labels without their version, e.g. `safari_ios` for `safari_ios_26`.
+    let mut families: Vec<&str> = Vec::new();
+    for profile in profiles {
+        let Some((family, _)) = profile.rsplit_once('_') else {
+            continue;
+        };
+        if !families.contains(&family) {
+            families.push(family);
+        }
+    }
+
+    ts_content.push_str("\n/**\n * Browser families that profile aliases resolve against\n */\n");
+    ts_content.push_str("export type BrowserFamily =\n");
+
+    for (i, family) in families.iter().enumerate() {
+        if i == families.len() - 1 {
+            ts_content.push_str(&format!("  | '{}';\n", family));
+        } else {
+            ts_content.push_str(&format!("  | '{}'\n", family));
+        }
+    }
+
     ts_content.push_str("\n/**\n * Operating systems supported for emulation\n */\n");
     ts_content.push_str("export type EmulationOS =\n");
 
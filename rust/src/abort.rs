@@ -0,0 +1,107 @@
+use anyhow::Error;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use tokio_util::sync::CancellationToken;
+
+/// A request stopped by its caller (`cancelRequest`, `cancelGroup`, `cancelAllRequests`)
+/// rather than by a failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aborted {
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.reason.as_deref().unwrap_or("Request aborted"))
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// A request that ran out of time: its `timeout`, `totalDeadline` or `writeTimeout`.
+#[derive(Debug)]
+pub struct TimedOut(pub String);
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Cancels a request and remembers why, so the request's error can say so.
+#[derive(Clone, Default)]
+pub struct AbortHandle {
+    token: CancellationToken,
+    reason: Arc<OnceLock<String>>,
+}
+
+impl AbortHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort the request. Only the first reason is kept.
+    pub fn abort(&self, reason: Option<String>) {
+        if let Some(reason) = reason {
+            let _ = self.reason.set(reason);
+        }
+        self.token.cancel();
+    }
+
+    /// Resolve with the abort error once [`AbortHandle::abort`] is called.
+    pub async fn aborted(&self) -> Error {
+        self.token.cancelled().await;
+        Aborted {
+            reason: self.reason.get().cloned(),
+        }
+        .into()
+    }
+}
+
+/// The `code` a failed request reports: `ERR_ABORTED` when the caller cancelled it and
+/// `ERR_TIMEOUT` when one of its timeouts fired, including connect and read timeouts.
+pub fn error_code(error: &Error) -> Option<&'static str> {
+    error.chain().find_map(|cause| {
+        if cause.is::<Aborted>() {
+            Some("ERR_ABORTED")
+        } else if cause.is::<TimedOut>()
+            || cause
+                .downcast_ref::<wreq::Error>()
+                .is_some_and(wreq::Error::is_timeout)
+        {
+            Some("ERR_TIMEOUT")
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AbortHandle, TimedOut, error_code};
+    use anyhow::anyhow;
+
+    #[tokio::test]
+    async fn abort_keeps_the_first_reason() {
+        let handle = AbortHandle::new();
+        handle.abort(Some("user navigated away".to_string()));
+        handle.abort(Some("shutdown".to_string()));
+
+        let error = handle.aborted().await.context("request-write failed");
+        assert_eq!(error_code(&error), Some("ERR_ABORTED"));
+        assert_eq!(error.root_cause().to_string(), "user navigated away");
+
+        let silent = AbortHandle::new();
+        silent.abort(None);
+        assert_eq!(silent.aborted().await.to_string(), "Request aborted");
+    }
+
+    #[test]
+    fn timeouts_are_told_apart_from_other_failures() {
+        let timed_out = anyhow::Error::new(TimedOut("Request timed out after 5 ms".to_string()));
+        assert_eq!(error_code(&timed_out), Some("ERR_TIMEOUT"));
+        assert_eq!(error_code(&anyhow!("connection refused")), None);
+    }
+}
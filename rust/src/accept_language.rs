@@ -0,0 +1,68 @@
+use wreq::header::HeaderValue;
+
+/// The `Accept-Language` value for `locales`, most preferred first, in the form Chromium sends:
+/// each region-qualified tag is followed by its bare language, and q-values step down by 0.1
+/// from the second entry, e.g. `de-DE,de;q=0.9,en;q=0.8`.
+pub fn accept_language(locales: &[String]) -> Option<HeaderValue> {
+    let mut tags: Vec<&str> = Vec::new();
+    for locale in locales {
+        let locale = locale.trim();
+        if locale.is_empty() {
+            continue;
+        }
+        let language = locale.split('-').next().unwrap_or(locale);
+        for tag in [locale, language] {
+            if !tags.iter().any(|seen| seen.eq_ignore_ascii_case(tag)) {
+                tags.push(tag);
+            }
+        }
+    }
+
+    let value = tags
+        .iter()
+        .enumerate()
+        .map(|(index, tag)| {
+            // Browsers stop lowering the weight at 0.1 however many languages there are.
+            let tenths = 10usize.saturating_sub(index).max(1);
+            match tenths {
+                10 => (*tag).to_owned(),
+                tenths => format!("{};q=0.{}", tag, tenths),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    if value.is_empty() {
+        return None;
+    }
+    HeaderValue::from_str(&value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::accept_language;
+
+    fn value(locales: &[&str]) -> String {
+        let locales: Vec<String> = locales.iter().map(|locale| locale.to_string()).collect();
+        accept_language(&locales)
+            .map(|value| value.to_str().unwrap().to_owned())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn adds_the_bare_language_after_each_region() {
+        assert_eq!(value(&["de-DE"]), "de-DE,de;q=0.9");
+        assert_eq!(
+            value(&["fr-CH", "en-US", "en"]),
+            "fr-CH,fr;q=0.9,en-US;q=0.8,en;q=0.7"
+        );
+        assert_eq!(value(&["ja"]), "ja");
+        assert_eq!(value(&[]), "");
+    }
+
+    #[test]
+    fn stops_lowering_weights_at_one_tenth() {
+        let locales: Vec<String> = (0..12).map(|index| format!("x{}", index)).collect();
+        let header = accept_language(&locales).unwrap();
+        assert!(header.to_str().unwrap().ends_with("x10;q=0.1,x11;q=0.1"));
+    }
+}
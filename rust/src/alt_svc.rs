@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connect_to::ConnectTo;
+
+// RFC 7838 §3.1: alternatives without `ma` are fresh for 24 hours.
+const DEFAULT_MAX_AGE_SECS: u64 = 24 * 60 * 60;
+
+/// An alternative service advertised by an origin (RFC 7838).
+#[derive(Debug, Clone)]
+pub struct AltSvcEntry {
+    pub origin: String,
+    pub protocol: String,
+    pub host: String,
+    pub port: u16,
+    pub expires_at: u64,
+    pub persist: bool,
+}
+
+/// Alt-Svc cache owned by a single transport, keyed by `scheme://host:port` origin.
+#[derive(Debug, Default)]
+pub struct AltSvcCache {
+    entries: RwLock<HashMap<String, Vec<AltSvcEntry>>>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Serialize a URI's origin as `scheme://host:port`, filling in the default port.
+pub fn origin_of(uri: &wreq::Uri) -> Option<String> {
+    let scheme = uri.scheme_str()?.to_ascii_lowercase();
+    let host = uri.host()?.to_ascii_lowercase();
+    let port = uri.port_u16().unwrap_or(match scheme.as_str() {
+        "https" | "wss" => 443,
+        _ => 80,
+    });
+    Some(format!("{}://{}:{}", scheme, host, port))
+}
+
+/// Parse one `protocol="host:port"; ma=N; persist=1` alternative.
+fn parse_alternative(origin: &str, origin_host: &str, raw: &str, now: u64) -> Option<AltSvcEntry> {
+    let mut params = raw.split(';').map(str::trim);
+    let (protocol, authority) = params.next()?.split_once('=')?;
+    let authority = authority.trim().trim_matches('"');
+    let (host, port) = authority.rsplit_once(':')?;
+    let port = port.parse::<u16>().ok()?;
+
+    let mut max_age = DEFAULT_MAX_AGE_SECS;
+    let mut persist = false;
+    for param in params {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        if name.trim().eq_ignore_ascii_case("ma") {
+            max_age = value.parse().ok()?;
+        } else if name.trim().eq_ignore_ascii_case("persist") {
+            persist = value == "1";
+        }
+    }
+
+    Some(AltSvcEntry {
+        origin: origin.to_string(),
+        protocol: protocol.trim().to_string(),
+        // An empty alt-authority host means "same host as the origin".
+        host: if host.is_empty() {
+            origin_host.to_string()
+        } else {
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_ascii_lowercase()
+        },
+        port,
+        expires_at: now.saturating_add(max_age.saturating_mul(1000)),
+        persist,
+    })
+}
+
+impl AltSvcCache {
+    /// Replace the cached alternatives for the response's origin with its `Alt-Svc` header.
+    pub fn record(&self, uri: &wreq::Uri, header_value: &str) {
+        let (Some(origin), Some(origin_host)) = (origin_of(uri), uri.host()) else {
+            return;
+        };
+
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if header_value.trim().eq_ignore_ascii_case("clear") {
+            entries.remove(&origin);
+            return;
+        }
+
+        let now = now_millis();
+        let alternatives: Vec<AltSvcEntry> = header_value
+            .split(',')
+            .filter_map(|raw| parse_alternative(&origin, origin_host, raw, now))
+            .filter(|entry| entry.expires_at > now)
+            .collect();
+
+        if alternatives.is_empty() {
+            entries.remove(&origin);
+        } else {
+            entries.insert(origin, alternatives);
+        }
+    }
+
+    /// Unexpired alternatives, optionally restricted to a single origin.
+    pub fn entries(&self, origin: Option<&str>) -> Vec<AltSvcEntry> {
+        let now = now_millis();
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        entries.retain(|_, alternatives| {
+            alternatives.retain(|entry| entry.expires_at > now);
+            !alternatives.is_empty()
+        });
+
+        match origin {
+            Some(origin) => entries.get(origin).cloned().unwrap_or_default(),
+            None => entries.values().flatten().cloned().collect(),
+        }
+    }
+
+    /// The first cached `h2` alternative for `uri`'s origin that lives somewhere else, with the
+    /// connectTo rule that sends the origin's connections there. The request keeps its URL, so
+    /// TLS is still verified against the origin, as RFC 7838 requires. Alternatives for IP
+    /// literals and for another port than one the URL names cannot be routed, since neither
+    /// goes through the resolver.
+    pub fn route(&self, uri: &wreq::Uri) -> Option<(AltSvcEntry, ConnectTo)> {
+        if uri.scheme_str() != Some("https") {
+            return None;
+        }
+        let origin = origin_of(uri)?;
+        let host = uri.host()?.to_ascii_lowercase();
+        if host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok()
+        {
+            return None;
+        }
+        let port = uri.port_u16().unwrap_or(443);
+
+        let entry = self.entries(Some(&origin)).into_iter().find(|entry| {
+            entry.protocol == "h2"
+                && (entry.host != host || entry.port != port)
+                && uri.port_u16().is_none_or(|named| named == entry.port)
+        })?;
+        let rule = ConnectTo {
+            from_host: host,
+            from_port: Some(port),
+            to_host: Some(entry.host.clone()),
+            to_port: Some(entry.port),
+        };
+        Some((entry, rule))
+    }
+
+    /// Drop one alternative, e.g. after it could not be reached.
+    pub fn forget(&self, entry: &AltSvcEntry) {
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(alternatives) = entries.get_mut(&entry.origin) {
+            alternatives.retain(|cached| {
+                (&cached.protocol, &cached.host, cached.port)
+                    != (&entry.protocol, &entry.host, entry.port)
+            });
+            if alternatives.is_empty() {
+                entries.remove(&entry.origin);
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AltSvcCache;
+
+    fn uri(url: &str) -> wreq::Uri {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn routes_to_an_h2_alternative_elsewhere() {
+        let cache = AltSvcCache::default();
+        let origin = uri("https://api.example.com/v1");
+        cache.record(&origin, r#"h3=":443", h2="alt.example.net:8443"; ma=60"#);
+
+        let (entry, rule) = cache.route(&origin).unwrap();
+        assert_eq!(entry.host, "alt.example.net");
+        assert_eq!(rule.from_host, "api.example.com");
+        assert_eq!(rule.from_port, Some(443));
+        assert_eq!(rule.to_host.as_deref(), Some("alt.example.net"));
+        assert_eq!(rule.to_port, Some(8443));
+
+        // A URL that names its port can only be sent to an alternative on that port.
+        let named = uri("https://api.example.com:443/v1");
+        assert!(cache.route(&named).is_none());
+
+        cache.forget(&entry);
+        assert!(cache.route(&origin).is_none());
+        assert_eq!(cache.entries(None).len(), 1);
+    }
+
+    #[test]
+    fn does_not_route_to_the_origin_itself() {
+        let cache = AltSvcCache::default();
+        let origin = uri("https://example.com/");
+        cache.record(&origin, r#"h2=":443""#);
+        assert!(cache.route(&origin).is_none());
+
+        let plain = uri("http://example.com/");
+        cache.record(&plain, r#"h2="alt.example.com:443""#);
+        assert!(cache.route(&plain).is_none());
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+// A token this close to its expiry is refreshed before use rather than sent to fail.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// An access token and what came with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessToken {
+    pub token: String,
+    /// Lifetime in seconds, counted from when the token was received.
+    pub expires_in: Option<u64>,
+    /// A rotated refresh token for the next refresh, when the grant returned one.
+    pub refresh_token: Option<String>,
+}
+
+/// Asks JS for a fresh token and answers through the sender.
+pub type RefreshHook = Arc<dyn Fn(oneshot::Sender<Result<AccessToken>>) + Send + Sync>;
+
+/// An OAuth2 token endpoint (RFC 6749). It is sent the refresh token grant when there is a
+/// refresh token, and the client credentials grant otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenEndpoint {
+    pub url: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// Where a session gets a fresh access token.
+#[derive(Clone)]
+pub enum TokenSource {
+    Endpoint(TokenEndpoint),
+    Callback(RefreshHook),
+}
+
+struct TokenState {
+    token: Option<String>,
+    expires_at: Option<Instant>,
+    refresh_token: Option<String>,
+}
+
+/// The bearer token of a session. Concurrent requests that find it stale share one refresh.
+pub struct SessionAuth {
+    source: TokenSource,
+    state: Mutex<TokenState>,
+    refreshing: tokio::sync::Mutex<()>,
+}
+
+impl SessionAuth {
+    pub fn new(
+        source: TokenSource,
+        initial: Option<AccessToken>,
+        refresh_token: Option<String>,
+    ) -> Self {
+        let mut state = TokenState {
+            token: None,
+            expires_at: None,
+            refresh_token,
+        };
+        if let Some(initial) = initial {
+            store(&mut state, initial);
+        }
+        Self {
+            source,
+            state: Mutex::new(state),
+            refreshing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn source(&self) -> &TokenSource {
+        &self.source
+    }
+
+    /// The current token, unless it is missing or about to expire.
+    pub fn token(&self) -> Option<String> {
+        let state = self.lock();
+        let fresh = state
+            .expires_at
+            .is_none_or(|at| at > Instant::now() + EXPIRY_MARGIN);
+        state.token.clone().filter(|_| fresh)
+    }
+
+    /// Replace `stale` (the token a request failed with, if any) by asking `fetch`, which is
+    /// given the current refresh token. Requests that wait here while another one refreshes get
+    /// its token instead of refreshing again.
+    pub async fn refresh<F, Fut>(&self, stale: Option<&str>, fetch: F) -> Result<String>
+    where
+        F: FnOnce(Option<String>) -> Fut,
+        Fut: Future<Output = Result<AccessToken>>,
+    {
+        let _refreshing = self.refreshing.lock().await;
+        if let Some(current) = self
+            .token()
+            .filter(|current| Some(current.as_str()) != stale)
+        {
+            return Ok(current);
+        }
+
+        let refresh_token = self.lock().refresh_token.clone();
+        let grant = fetch(refresh_token).await.context("Token refresh failed")?;
+        if grant.token.is_empty() {
+            bail!("Token refresh failed: no access token was returned");
+        }
+        let token = grant.token.clone();
+        store(&mut self.lock(), grant);
+        Ok(token)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TokenState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn store(state: &mut TokenState, grant: AccessToken) {
+    state.expires_at = grant
+        .expires_in
+        .map(|seconds| Instant::now() + Duration::from_secs(seconds));
+    state.token = Some(grant.token);
+    if grant.refresh_token.is_some() {
+        state.refresh_token = grant.refresh_token;
+    }
+}
+
+impl TokenEndpoint {
+    /// The `application/x-www-form-urlencoded` body of a token request.
+    pub fn request_body(&self, refresh_token: Option<&str>) -> Vec<u8> {
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        match refresh_token {
+            Some(refresh_token) => form
+                .append_pair("grant_type", "refresh_token")
+                .append_pair("refresh_token", refresh_token),
+            None => form.append_pair("grant_type", "client_credentials"),
+        };
+        if let Some(client_id) = &self.client_id {
+            form.append_pair("client_id", client_id);
+        }
+        if let Some(client_secret) = &self.client_secret {
+            form.append_pair("client_secret", client_secret);
+        }
+        if let Some(scope) = &self.scope {
+            form.append_pair("scope", scope);
+        }
+        form.finish().into_bytes()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Read a token endpoint's JSON answer, or the OAuth2 error it reported.
+pub fn parse_token_response(status: u16, body: &[u8]) -> Result<AccessToken> {
+    let response: Option<TokenResponse> = serde_json::from_slice(body).ok();
+    match response {
+        Some(TokenResponse {
+            access_token: Some(token),
+            expires_in,
+            refresh_token,
+            ..
+        }) if (200..300).contains(&status) => Ok(AccessToken {
+            token,
+            expires_in,
+            refresh_token,
+        }),
+        Some(TokenResponse {
+            error: Some(error),
+            error_description,
+            ..
+        }) => Err(match error_description {
+            Some(description) => anyhow!("{} ({}): {}", error, status, description),
+            None => anyhow!("{} ({})", error, status),
+        }),
+        _ => Err(anyhow!(
+            "Token endpoint answered {} without an access token",
+            status
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessToken, SessionAuth, TokenEndpoint, TokenSource, parse_token_response};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn endpoint() -> TokenEndpoint {
+        TokenEndpoint {
+            url: "https://auth.example.com/token".into(),
+            client_id: Some("app".into()),
+            client_secret: Some("s3cr&t".into()),
+            scope: None,
+        }
+    }
+
+    fn grant(token: &str, expires_in: Option<u64>) -> AccessToken {
+        AccessToken {
+            token: token.into(),
+            expires_in,
+            refresh_token: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_refreshes_share_one_fetch() {
+        let auth = Arc::new(SessionAuth::new(
+            TokenSource::Endpoint(endpoint()),
+            Some(grant("old", None)),
+            Some("r1".into()),
+        ));
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let refreshes = (0..8).map(|_| {
+            let auth = auth.clone();
+            let fetches = fetches.clone();
+            tokio::spawn(async move {
+                auth.refresh(Some("old"), |refresh_token| async move {
+                    assert_eq!(refresh_token.as_deref(), Some("r1"));
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    tokio::task::yield_now().await;
+                    Ok(AccessToken {
+                        token: "new".into(),
+                        expires_in: Some(3600),
+                        refresh_token: Some("r2".into()),
+                    })
+                })
+                .await
+            })
+        });
+        for refresh in refreshes {
+            assert_eq!(refresh.await.unwrap().unwrap(), "new");
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(auth.token().as_deref(), Some("new"));
+
+        // The rotated refresh token is used next time.
+        let token = auth
+            .refresh(Some("new"), |refresh_token| async move {
+                Ok(grant(&refresh_token.unwrap(), None))
+            })
+            .await
+            .unwrap();
+        assert_eq!(token, "r2");
+    }
+
+    #[tokio::test]
+    async fn expiring_tokens_are_not_used() {
+        let auth = SessionAuth::new(
+            TokenSource::Endpoint(endpoint()),
+            Some(grant("short", Some(10))),
+            None,
+        );
+        assert_eq!(auth.token(), None);
+
+        let failed = auth
+            .refresh(None, |_| async { Ok(grant("", None)) })
+            .await
+            .unwrap_err();
+        assert!(failed.to_string().contains("no access token"));
+    }
+
+    #[test]
+    fn builds_token_requests() {
+        let body = endpoint().request_body(Some("r 1"));
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "grant_type=refresh_token&refresh_token=r+1&client_id=app&client_secret=s3cr%26t"
+        );
+        let body = endpoint().request_body(None);
+        assert!(
+            String::from_utf8(body)
+                .unwrap()
+                .starts_with("grant_type=client_credentials&")
+        );
+    }
+
+    #[test]
+    fn reads_token_responses() {
+        let body = br#"{"access_token":"abc","token_type":"Bearer","expires_in":60}"#;
+        assert_eq!(
+            parse_token_response(200, body).unwrap(),
+            grant("abc", Some(60))
+        );
+
+        let body = br#"{"error":"invalid_grant","error_description":"expired"}"#;
+        let error = parse_token_response(400, body).unwrap_err();
+        assert_eq!(error.to_string(), "invalid_grant (400): expired");
+        assert!(parse_token_response(502, b"<html>").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result, anyhow};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use wreq::cookie::{CookieStore, Cookies};
+use wreq::header::{COOKIE, HeaderName, HeaderValue};
+
+use crate::client::header_text;
+
+/// A request as it is about to go out, as told to a [`BeforeSendHook`].
+pub struct OutgoingRequest {
+    pub method: String,
+    pub url: String,
+    /// Every header of the request in order, browser profile defaults and `Cookie` included.
+    pub headers: Vec<(String, String)>,
+}
+
+/// Asked for headers to append to a fully composed request, such as a signature over its other
+/// headers. Answering with an error, or not answering, fails the request.
+#[derive(Clone)]
+pub struct BeforeSendHook(Arc<dyn Fn(OutgoingRequest, BeforeSendAnswer) + Send + Sync>);
+
+/// Where a [`BeforeSendHook`] sends the headers to append.
+pub type BeforeSendAnswer = oneshot::Sender<Result<Vec<(String, HeaderValue)>>>;
+
+impl BeforeSendHook {
+    pub fn new(hook: impl Fn(OutgoingRequest, BeforeSendAnswer) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Show the hook `request` and append the headers it answers with.
+    pub async fn apply(&self, request: &mut wreq::Request) -> Result<()> {
+        let outgoing = OutgoingRequest {
+            method: request.method().to_string(),
+            url: request.uri().to_string(),
+            headers: request
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.as_str().to_owned(), header_text(value)))
+                .collect(),
+        };
+        let (answer, answered) = oneshot::channel();
+        (self.0)(outgoing, answer);
+        let extra = answered
+            .await
+            .map_err(|_| anyhow!("beforeSend did not answer"))??;
+        for (name, value) in extra {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("beforeSend returned an invalid header name: {name}"))?;
+            request.headers_mut().append(name, value);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BeforeSendHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BeforeSendHook")
+    }
+}
+
+/// Put the jar's cookies for `request` on it now rather than leaving that to wreq, which only
+/// does so as the request is written and skips requests that already carry a `Cookie` header.
+pub fn add_cookies(request: &mut wreq::Request, jar: &dyn CookieStore) {
+    if request.headers().contains_key(COOKIE) {
+        return;
+    }
+    match jar.cookies(request.uri()) {
+        Cookies::Compressed(value) => {
+            request.headers_mut().insert(COOKIE, value);
+        }
+        Cookies::Uncompressed(values) => {
+            for value in values {
+                request.headers_mut().append(COOKIE, value);
+            }
+        }
+        Cookies::Empty => {}
+    }
+}
@@ -0,0 +1,416 @@
+use anyhow::{Result, anyhow, bail};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::cli::{header_values, parse_header, parse_number, resolve_emulation};
+use crate::client::{
+    HTTP_RUNTIME, RequestOptions, SessionOptions, TransportOptions, create_managed_session,
+    create_managed_transport, drop_managed_session, drop_managed_transport, generate_session_id,
+    make_request, read_body_all,
+};
+
+const USAGE: &str = "\
+Usage: bench-client [options] <url>
+
+Options:
+  -c, --concurrency <n>   Requests in flight at once (default 16)
+  -n, --requests <n>      Requests to send (default 10000, or unlimited with --duration)
+  -d, --duration <s>      Stop after this many seconds
+      --qps <n>           Cap the request rate across all workers
+      --warmup <n>        Requests to send before measuring (default 100)
+  -X, --request <method>  Request method (default GET, or POST with --body)
+  -H, --header <h>        Add a `Name: value` request header; repeatable
+      --body <bytes>      Send a request body of this many bytes
+  -b, --browser <profile> Browser profile or alias (default chrome_142)
+      --os <os>           Operating system to emulate (default macos)
+  -k, --insecure          Skip TLS certificate verification
+      --timeout <ms>      Per-request timeout (default 30000)
+      --isolated          Send every request without connection reuse
+      --json              Print the report as JSON
+  -h, --help              Print this help";
+
+#[derive(Debug, PartialEq)]
+struct LoadOptions {
+    url: String,
+    concurrency: usize,
+    requests: Option<u64>,
+    duration: Option<Duration>,
+    qps: Option<f64>,
+    warmup: u64,
+    method: Option<String>,
+    headers: Vec<(String, String)>,
+    body_bytes: Option<usize>,
+    browser: String,
+    os: String,
+    insecure: bool,
+    timeout: u64,
+    isolated: bool,
+    json: bool,
+}
+
+/// Run `bench-client` with `args` (without the program name).
+pub fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let options = match parse_args(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("bench-client: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match HTTP_RUNTIME.block_on(drive(&options)) {
+        Ok(report) => {
+            if options.json {
+                println!("{}", report.to_json());
+            } else {
+                print!("{}", report.summary());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("bench-client: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<LoadOptions>> {
+    let mut args = args.into_iter();
+    let mut url = None;
+    let mut options = LoadOptions {
+        url: String::new(),
+        concurrency: 16,
+        requests: None,
+        duration: None,
+        qps: None,
+        warmup: 100,
+        method: None,
+        headers: Vec::new(),
+        body_bytes: None,
+        browser: "chrome_142".to_string(),
+        os: "macos".to_string(),
+        insecure: false,
+        timeout: 30000,
+        isolated: false,
+        json: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{name} needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-c" | "--concurrency" => options.concurrency = parse_number(&arg, &value(&arg)?)?,
+            "-n" | "--requests" => options.requests = Some(parse_number(&arg, &value(&arg)?)?),
+            "-d" | "--duration" => {
+                let seconds: f64 = parse_number(&arg, &value(&arg)?)?;
+                options.duration = Some(
+                    Duration::try_from_secs_f64(seconds)
+                        .map_err(|_| anyhow!("{arg} must be a positive number of seconds"))?,
+                );
+            }
+            "--qps" => options.qps = Some(parse_number(&arg, &value(&arg)?)?),
+            "--warmup" => options.warmup = parse_number(&arg, &value(&arg)?)?,
+            "-X" | "--request" => options.method = Some(value(&arg)?.to_ascii_uppercase()),
+            "-H" | "--header" => options.headers.push(parse_header(&value(&arg)?)?),
+            "--body" => options.body_bytes = Some(parse_number(&arg, &value(&arg)?)?),
+            "-b" | "--browser" => options.browser = value(&arg)?,
+            "--os" => options.os = value(&arg)?,
+            "-k" | "--insecure" => options.insecure = true,
+            "--timeout" => options.timeout = parse_number(&arg, &value(&arg)?)?,
+            "--isolated" => options.isolated = true,
+            "--json" => options.json = true,
+            flag if flag.starts_with('-') && flag.len() > 1 => bail!("unknown option '{flag}'"),
+            _ if url.is_some() => bail!("only one URL can be benchmarked at a time"),
+            _ => url = Some(arg),
+        }
+    }
+
+    if options.concurrency == 0 {
+        bail!("--concurrency must be at least 1");
+    }
+    if options.qps.is_some_and(|qps| qps.is_nan() || qps <= 0.0) {
+        bail!("--qps must be positive");
+    }
+    if options.duration.is_none() && options.requests.is_none() {
+        options.requests = Some(10_000);
+    }
+    options.url = url.ok_or_else(|| anyhow!("no URL given"))?;
+    Ok(Some(options))
+}
+
+/// What every worker shares: the request template and how far the run has got.
+struct Plan {
+    template: RequestOptions,
+    /// Index of the next request to send, counting from the start of the measured run.
+    next: AtomicU64,
+    requests: Option<u64>,
+    start: Instant,
+    deadline: Option<Instant>,
+    interval: Option<Duration>,
+}
+
+#[derive(Default)]
+struct WorkerStats {
+    latencies: Vec<Duration>,
+    bytes: u64,
+    errors: u64,
+    non_success: u64,
+    first_error: Option<String>,
+}
+
+async fn drive(options: &LoadOptions) -> Result<Report> {
+    let (emulation, emulation_os) = resolve_emulation(&options.browser, &options.os)?;
+    let body = options.body_bytes.map(|len| vec![b'x'; len]);
+    let method = match (&options.method, &body) {
+        (Some(method), _) => method.clone(),
+        (None, Some(_)) => "POST".to_string(),
+        (None, None) => "GET".to_string(),
+    };
+
+    // Pooled runs go through a session on a transport, like `wreq.session.*` in the JS bench;
+    // isolated runs match a plain `fetch()`.
+    let session_id = generate_session_id();
+    let transport_id = if options.isolated {
+        None
+    } else {
+        create_managed_session(session_id.clone(), SessionOptions::default())?;
+        let mut transport = TransportOptions::new(emulation, emulation_os);
+        transport.insecure = options.insecure;
+        Some(create_managed_transport(transport)?)
+    };
+
+    let mut template = RequestOptions::new(options.url.clone(), emulation, emulation_os);
+    template.headers = header_values(&options.headers)?;
+    template.method = method;
+    template.body = body;
+    template.timeout = options.timeout;
+    template.session_id = session_id.clone();
+    template.ephemeral = options.isolated;
+    template.insecure = options.insecure;
+    template.transport_id = transport_id.clone();
+
+    for _ in 0..options.warmup {
+        send(template.clone()).await?;
+    }
+
+    let start = Instant::now();
+    let plan = Arc::new(Plan {
+        template,
+        next: AtomicU64::new(0),
+        requests: options.requests,
+        start,
+        deadline: options.duration.map(|duration| start + duration),
+        interval: options.qps.map(|qps| Duration::from_secs_f64(1.0 / qps)),
+    });
+
+    let workers = (0..options.concurrency)
+        .map(|_| tokio::spawn(worker(plan.clone())))
+        .collect::<Vec<_>>();
+    let mut stats = Vec::with_capacity(workers.len());
+    for worker in workers {
+        stats.push(worker.await?);
+    }
+    let elapsed = start.elapsed();
+
+    if let Some(transport_id) = transport_id {
+        drop_managed_transport(&transport_id);
+        drop_managed_session(&session_id);
+    }
+    Ok(Report::new(stats, elapsed))
+}
+
+async fn worker(plan: Arc<Plan>) -> WorkerStats {
+    let mut stats = WorkerStats::default();
+    loop {
+        let index = plan.next.fetch_add(1, Ordering::Relaxed);
+        if plan.requests.is_some_and(|requests| index >= requests) {
+            break;
+        }
+        if let Some(interval) = plan.interval {
+            tokio::time::sleep_until(plan.start + interval.mul_f64(index as f64)).await;
+        }
+        if plan
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            break;
+        }
+
+        let sent = Instant::now();
+        match send(plan.template.clone()).await {
+            Ok((status, bytes)) => {
+                stats.latencies.push(sent.elapsed());
+                stats.bytes += bytes;
+                if !(200..300).contains(&status) {
+                    stats.non_success += 1;
+                }
+            }
+            Err(e) => {
+                stats.errors += 1;
+                stats.first_error.get_or_insert_with(|| format!("{e:#}"));
+            }
+        }
+    }
+    stats
+}
+
+/// Send one request and read its body to the end, returning the status and body size.
+async fn send(options: RequestOptions) -> Result<(u16, u64)> {
+    let response = make_request(options).await?;
+    let mut bytes = response.body_bytes.map_or(0, |body| body.len() as u64);
+    if let Some(handle) = response.body_handle {
+        bytes += read_body_all(handle).await?.len() as u64;
+    }
+    Ok((response.status, bytes))
+}
+
+struct Report {
+    /// Latencies of completed requests, sorted.
+    latencies: Vec<Duration>,
+    bytes: u64,
+    errors: u64,
+    non_success: u64,
+    first_error: Option<String>,
+    elapsed: Duration,
+}
+
+impl Report {
+    fn new(stats: Vec<WorkerStats>, elapsed: Duration) -> Self {
+        let mut report = Report {
+            latencies: Vec::new(),
+            bytes: 0,
+            errors: 0,
+            non_success: 0,
+            first_error: None,
+            elapsed,
+        };
+        for worker in stats {
+            report.latencies.extend(worker.latencies);
+            report.bytes += worker.bytes;
+            report.errors += worker.errors;
+            report.non_success += worker.non_success;
+            report.first_error = report.first_error.or(worker.first_error);
+        }
+        report.latencies.sort_unstable();
+        report
+    }
+
+    /// Nearest-rank percentile of the completed requests' latencies.
+    fn percentile(&self, percent: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percent / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+
+    fn mean(&self) -> Duration {
+        match self.latencies.len() {
+            0 => Duration::ZERO,
+            n => self.latencies.iter().sum::<Duration>() / n as u32,
+        }
+    }
+
+    fn per_second(&self, count: f64) -> f64 {
+        count / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn summary(&self) -> String {
+        let ms = |duration: Duration| format!("{:.2}ms", duration.as_secs_f64() * 1000.0);
+        let mut summary = format!(
+            "Requests:    {} ({} errors, {} non-2xx)\n\
+             Duration:    {:.3}s\n\
+             Throughput:  {:.1} req/s, {:.2} MiB/s\n\
+             Latency:     mean {}  p50 {}  p90 {}  p99 {}  p99.9 {}  max {}\n",
+            self.latencies.len(),
+            self.errors,
+            self.non_success,
+            self.elapsed.as_secs_f64(),
+            self.per_second(self.latencies.len() as f64),
+            self.per_second(self.bytes as f64) / (1024.0 * 1024.0),
+            ms(self.mean()),
+            ms(self.percentile(50.0)),
+            ms(self.percentile(90.0)),
+            ms(self.percentile(99.0)),
+            ms(self.percentile(99.9)),
+            ms(self.percentile(100.0)),
+        );
+        if let Some(error) = &self.first_error {
+            summary.push_str(&format!("First error: {error}\n"));
+        }
+        summary
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        serde_json::json!({
+            "requests": self.latencies.len(),
+            "errors": self.errors,
+            "nonSuccess": self.non_success,
+            "firstError": self.first_error,
+            "durationMs": ms(self.elapsed),
+            "requestsPerSec": self.per_second(self.latencies.len() as f64),
+            "bytesPerSec": self.per_second(self.bytes as f64),
+            "latencyMs": {
+                "mean": ms(self.mean()),
+                "p50": ms(self.percentile(50.0)),
+                "p90": ms(self.percentile(90.0)),
+                "p99": ms(self.percentile(99.0)),
+                "p999": ms(self.percentile(99.9)),
+                "max": ms(self.percentile(100.0)),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Report, WorkerStats, parse_args};
+    use std::time::Duration;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let worker = |millis: &[u64]| WorkerStats {
+            latencies: millis.iter().map(|ms| Duration::from_millis(*ms)).collect(),
+            ..WorkerStats::default()
+        };
+        let report = Report::new(
+            vec![worker(&[10, 1, 7, 3, 9]), worker(&[2, 8, 4, 6, 5])],
+            Duration::from_secs(2),
+        );
+
+        assert_eq!(report.percentile(50.0), Duration::from_millis(5));
+        assert_eq!(report.percentile(90.0), Duration::from_millis(9));
+        assert_eq!(report.percentile(99.0), Duration::from_millis(10));
+        assert_eq!(report.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(report.mean(), Duration::from_micros(5500));
+        assert_eq!(report.per_second(10.0), 5.0);
+    }
+
+    #[test]
+    fn defaults_to_a_fixed_request_count() {
+        let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+        let options = args(&["http://127.0.0.1:8080/"]).unwrap().unwrap();
+        assert_eq!(options.requests, Some(10_000));
+        assert_eq!(options.concurrency, 16);
+
+        let options = args(&["-d", "2.5", "-c", "4", "http://a"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.requests, None);
+        assert_eq!(options.duration, Some(Duration::from_millis(2500)));
+
+        assert!(args(&["-c", "0", "http://a"]).is_err());
+        assert!(args(&["--qps", "-1", "http://a"]).is_err());
+        assert!(args(&["-d", "-1", "http://a"]).is_err());
+        assert!(args(&["--help"]).unwrap().is_none());
+    }
+}
@@ -0,0 +1,5 @@
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    wreq_js::run_bench_client(std::env::args().skip(1))
+}
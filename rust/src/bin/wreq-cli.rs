@@ -0,0 +1,5 @@
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    wreq_js::run_cli(std::env::args().skip(1))
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::client::{BodyChunk, body_stream_open, read_body_chunk};
+
+// How often a body waiting for credit checks whether it was reaped, since a reader that stopped
+// granting credit never wakes it.
+const REAP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bytes JS is ready to take from a pushed body. JS grants credit as its queue drains, and each
+/// chunk delivered spends its length, so a chunk may leave the balance below zero until the next
+/// grant covers it.
+#[derive(Debug, Default)]
+pub struct BodyCredit {
+    bytes: AtomicI64,
+    closed: AtomicBool,
+    changed: Notify,
+}
+
+impl BodyCredit {
+    pub fn grant(&self, bytes: u64) {
+        self.bytes
+            .fetch_add(bytes.min(i64::MAX as u64) as i64, Ordering::AcqRel);
+        self.changed.notify_one();
+    }
+
+    /// Stop pushing, e.g. because JS cancelled the body. A chunk already being read is dropped.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.changed.notify_one();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Wait until there is credit to spend, or until the body is no longer `open` so reading it
+    /// reports how it ended. `false` once the credit is closed.
+    async fn available(&self, open: impl Fn() -> bool) -> bool {
+        loop {
+            if self.is_closed() {
+                return false;
+            }
+            if self.bytes.load(Ordering::Acquire) > 0 || !open() {
+                return true;
+            }
+            // `notify_one` keeps a permit when nothing is waiting, so a grant made between the
+            // check and this wait is not missed.
+            let _ = tokio::time::timeout(REAP_CHECK_INTERVAL, self.changed.notified()).await;
+        }
+    }
+}
+
+/// Read body `handle` while `credit` allows and hand each chunk to `deliver`, then the end of
+/// the body (`Ok(None)`) or the error that stopped it. Nothing is delivered after the credit is
+/// closed.
+pub async fn push_body<F>(handle: u64, credit: &BodyCredit, deliver: F)
+where
+    F: Fn(Result<Option<BodyChunk>>),
+{
+    while credit.available(|| body_stream_open(handle)).await {
+        let result = read_body_chunk(handle).await;
+        if credit.is_closed() {
+            return;
+        }
+        match result {
+            Ok(Some(chunk)) => {
+                credit.bytes.fetch_sub(chunk.len() as i64, Ordering::AcqRel);
+                deliver(Ok(Some(chunk)));
+            }
+            last => {
+                deliver(last);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn waits_for_credit_and_wakes_on_grant_or_close() {
+        let credit = Arc::new(BodyCredit::default());
+        let waiting = tokio::spawn({
+            let credit = credit.clone();
+            async move { credit.available(|| true).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished());
+        credit.grant(16);
+        assert!(waiting.await.unwrap());
+
+        credit.bytes.fetch_sub(64, Ordering::AcqRel);
+        let waiting = tokio::spawn({
+            let credit = credit.clone();
+            async move { credit.available(|| true).await }
+        });
+        credit.grant(16);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished(), "a deficit has to be paid off first");
+        credit.close();
+        assert!(!waiting.await.unwrap());
+
+        let reaped = BodyCredit::default();
+        assert!(reaped.available(|| false).await);
+    }
+}
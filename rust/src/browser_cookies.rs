@@ -0,0 +1,158 @@
+use anyhow::{Context, Result, anyhow};
+use cookie::time::OffsetDateTime;
+use cookie::{Cookie, SameSite};
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A cookie as Puppeteer's `page.cookies()`, Playwright's `context.cookies()` and CDP's
+/// `Network.getAllCookies` export it. Fields those tools add beyond these are ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    /// Playwright's `addCookies()` form scopes a cookie by URL instead of domain and path.
+    pub url: Option<String>,
+    /// Seconds since the Unix epoch; `-1` (or absent) for a session cookie.
+    pub expires: Option<f64>,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    pub same_site: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BrowserCookieExport {
+    Cookies(Vec<BrowserCookie>),
+    // Playwright's `storageState()`, which keeps the cookies next to local storage.
+    StorageState { cookies: Vec<BrowserCookie> },
+}
+
+/// Parse a browser cookie export: an array of cookies, or a Playwright storage state.
+pub fn parse_browser_cookies(json: &str) -> Result<Vec<BrowserCookie>> {
+    let export: BrowserCookieExport = serde_json::from_str(json)
+        .context("Expected a cookie array from Puppeteer, Playwright or CDP")?;
+    Ok(match export {
+        BrowserCookieExport::Cookies(cookies) => cookies,
+        BrowserCookieExport::StorageState { cookies } => cookies,
+    })
+}
+
+impl BrowserCookie {
+    /// The cookie to store, and the URL to store it for. `None` when it has already expired.
+    pub fn to_jar_cookie(&self, now: SystemTime) -> Result<Option<(Cookie<'static>, String)>> {
+        let expires = self.expires.filter(|expires| *expires >= 0.0);
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        if expires.is_some_and(|expires| expires <= now) {
+            return Ok(None);
+        }
+
+        let (domain, path) = match (&self.domain, &self.url) {
+            (Some(domain), _) => (
+                domain.trim_start_matches('.').to_owned(),
+                self.path.clone().unwrap_or_else(|| "/".to_owned()),
+            ),
+            (None, Some(url)) => {
+                let url =
+                    url::Url::parse(url).with_context(|| format!("Invalid cookie URL: {}", url))?;
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| anyhow!("Cookie URL has no host: {}", url))?;
+                (host.to_owned(), url.path().to_owned())
+            }
+            (None, None) => return Err(anyhow!("Cookie {} has neither domain nor url", self.name)),
+        };
+        if domain.is_empty() {
+            return Err(anyhow!("Cookie {} has an empty domain", self.name));
+        }
+
+        let mut cookie = Cookie::build((self.name.clone(), self.value.clone()))
+            .domain(domain.clone())
+            .path(path.clone())
+            .secure(self.secure)
+            .http_only(self.http_only);
+        if let Some(same_site) = self.same_site.as_deref().and_then(parse_same_site) {
+            cookie = cookie.same_site(same_site);
+        }
+        if let Some(expires) = expires {
+            let at = OffsetDateTime::from_unix_timestamp(expires as i64)
+                .with_context(|| format!("Cookie {} has an invalid expiry", self.name))?;
+            cookie = cookie.expires(at);
+        }
+
+        let scheme = if self.secure { "https" } else { "http" };
+        Ok(Some((
+            cookie.build(),
+            format!("{}://{}{}", scheme, domain, path),
+        )))
+    }
+}
+
+fn parse_same_site(value: &str) -> Option<SameSite> {
+    match value.to_ascii_lowercase().as_str() {
+        "strict" => Some(SameSite::Strict),
+        "lax" => Some(SameSite::Lax),
+        "none" | "no_restriction" => Some(SameSite::None),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn reads_puppeteer_and_playwright_exports() {
+        let puppeteer = parse_browser_cookies(
+            r#"[{"name":"sid","value":"abc","domain":".example.com","path":"/app",
+                 "expires":-1,"size":6,"httpOnly":true,"secure":true,"session":true,
+                 "sameSite":"Lax","priority":"Medium"}]"#,
+        )
+        .unwrap();
+        let (cookie, url) = puppeteer[0]
+            .to_jar_cookie(SystemTime::now())
+            .unwrap()
+            .unwrap();
+        assert_eq!(url, "https://example.com/app");
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert!(cookie.expires().is_none());
+
+        let playwright = parse_browser_cookies(
+            r#"{"cookies":[{"name":"id","value":"1","url":"http://example.org/a/b",
+                 "expires":2000000000.5}],"origins":[]}"#,
+        )
+        .unwrap();
+        let (cookie, url) = playwright[0]
+            .to_jar_cookie(SystemTime::now())
+            .unwrap()
+            .unwrap();
+        assert_eq!(url, "http://example.org/a/b");
+        assert_eq!(
+            cookie.expires_datetime().map(|at| at.unix_timestamp()),
+            Some(2_000_000_000)
+        );
+    }
+
+    #[test]
+    fn skips_expired_cookies_and_rejects_unscoped_ones() {
+        let cookies = parse_browser_cookies(
+            r#"[{"name":"old","value":"1","domain":"example.com","expires":1000},
+                {"name":"loose","value":"1"}]"#,
+        )
+        .unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(2_000);
+        assert!(cookies[0].to_jar_cookie(now).unwrap().is_none());
+        assert!(cookies[1].to_jar_cookie(now).is_err());
+        assert!(parse_browser_cookies(r#"{"name":"sid"}"#).is_err());
+    }
+}
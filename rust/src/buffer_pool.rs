@@ -0,0 +1,133 @@
+use bytes::BytesMut;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Size of the buffers small response body chunks are batched into.
+pub const BODY_CHUNK_SIZE: usize = 64 * 1024;
+
+// Enough for a few dozen concurrent downloads; buffers returned beyond this are freed.
+const MAX_FREE_BODY_CHUNKS: usize = 32;
+
+/// Staging buffers for body chunks on their way to JS.
+pub static BODY_CHUNK_POOL: BufferPool = BufferPool::new(BODY_CHUNK_SIZE, MAX_FREE_BODY_CHUNKS);
+
+/// Fixed-size byte buffers that are recycled instead of freed.
+pub struct BufferPool {
+    free: Mutex<Vec<BytesMut>>,
+    capacity: usize,
+    max_free: usize,
+    loaned: AtomicUsize,
+}
+
+impl BufferPool {
+    pub const fn new(capacity: usize, max_free: usize) -> Self {
+        Self {
+            free: Mutex::new(Vec::new()),
+            capacity,
+            max_free,
+            loaned: AtomicUsize::new(0),
+        }
+    }
+
+    /// Approximate bytes held by the pool: idle buffers plus buffers on loan.
+    pub fn retained_bytes(&self) -> usize {
+        let free = self
+            .free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len();
+        (free + self.loaned.load(Ordering::Relaxed)) * self.capacity
+    }
+
+    /// Borrow an empty buffer. It goes back to the pool when dropped.
+    pub fn take(&'static self) -> PooledBuffer {
+        let buf = self
+            .free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .pop()
+            .unwrap_or_else(|| BytesMut::with_capacity(self.capacity));
+        self.loaned.fetch_add(1, Ordering::Relaxed);
+        PooledBuffer { buf, pool: self }
+    }
+
+    fn put(&self, mut buf: BytesMut) {
+        self.loaned.fetch_sub(1, Ordering::Relaxed);
+        // A buffer that had to grow holds a different allocation than the pool hands out.
+        if buf.capacity() != self.capacity {
+            return;
+        }
+        buf.clear();
+        let mut free = self
+            .free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if free.len() < self.max_free {
+            free.push(buf);
+        }
+    }
+}
+
+/// A buffer on loan from a [`BufferPool`].
+pub struct PooledBuffer {
+    buf: BytesMut,
+    pool: &'static BufferPool,
+}
+
+impl PooledBuffer {
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsMut<[u8]> for PooledBuffer {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        self.pool.put(std::mem::take(&mut self.buf));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferPool;
+
+    static POOL: BufferPool = BufferPool::new(16, 1);
+
+    #[test]
+    fn recycles_buffers_up_to_the_limit() {
+        let mut first = POOL.take();
+        first.extend_from_slice(b"hello");
+        let address = first.as_ptr();
+        let second = POOL.take();
+        assert_eq!(POOL.retained_bytes(), 32);
+        drop(first);
+        drop(second);
+
+        // Only one buffer is kept, and it comes back empty.
+        let reused = POOL.take();
+        assert!(reused.is_empty());
+        assert_eq!(reused.as_ptr(), address);
+        assert_eq!(POOL.free.lock().unwrap().len(), 0);
+        assert_eq!(POOL.retained_bytes(), 16);
+
+        let mut grown = POOL.take();
+        grown.extend_from_slice(&[0; 32]);
+        drop(grown);
+        drop(reused);
+        assert_eq!(POOL.free.lock().unwrap().len(), 1);
+    }
+}
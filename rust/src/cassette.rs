@@ -0,0 +1,331 @@
+use anyhow::{Context, Result, anyhow, bail};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use crate::client::Response;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// Request properties compared when looking up a recorded interaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    Method,
+    Url,
+    Body,
+}
+
+/// A body stored as UTF-8 text when possible so cassettes stay readable and diffable.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StoredBody {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl StoredBody {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self {
+                body: Some(text.to_owned()),
+                body_base64: None,
+            },
+            Err(_) => Self {
+                body: None,
+                body_base64: Some(base64_encode(bytes)),
+            },
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        match (&self.body, &self.body_base64) {
+            (Some(text), _) => Ok(text.as_bytes().to_vec()),
+            (None, Some(encoded)) => base64_decode(encoded),
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    body: StoredBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    url: String,
+    headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    body: StoredBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// A response served from a cassette.
+#[derive(Debug, Clone)]
+pub struct ReplayedResponse {
+    pub status: u16,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+#[derive(Debug)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    // Replay serves each recorded interaction once, in order, before repeating the last match.
+    served: Vec<bool>,
+}
+
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    match_on: Vec<MatchField>,
+    state: Mutex<CassetteState>,
+}
+
+static ACTIVE_CASSETTE: LazyLock<RwLock<Option<Arc<Cassette>>>> = LazyLock::new(Default::default);
+
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(encoded: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+
+    for byte in encoded.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+        if byte == b'=' {
+            break;
+        }
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|candidate| *candidate == byte)
+            .ok_or_else(|| anyhow!("Invalid base64 body in cassette"))?;
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(out)
+}
+
+impl Cassette {
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    fn matches(&self, request: &RecordedRequest, method: &str, url: &str, body: &[u8]) -> bool {
+        self.match_on.iter().all(|field| match field {
+            MatchField::Method => request.method.eq_ignore_ascii_case(method),
+            MatchField::Url => request.url == url,
+            MatchField::Body => request
+                .body
+                .to_bytes()
+                .is_ok_and(|recorded| recorded == body),
+        })
+    }
+
+    /// Serve the next recorded interaction matching this request.
+    pub fn replay(&self, method: &str, url: &str, body: &[u8]) -> Result<ReplayedResponse> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let matching: Vec<usize> = state
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| self.matches(&interaction.request, method, url, body))
+            .map(|(index, _)| index)
+            .collect();
+
+        let index = matching
+            .iter()
+            .copied()
+            .find(|index| !state.served[*index])
+            .or_else(|| matching.last().copied())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Cassette '{}' has no recorded interaction for {} {}",
+                    self.path.display(),
+                    method,
+                    url
+                )
+            })?;
+
+        state.served[index] = true;
+        let response = &state.interactions[index].response;
+        Ok(ReplayedResponse {
+            status: response.status,
+            url: response.url.clone(),
+            headers: response.headers.clone(),
+            body: Bytes::from(response.body.to_bytes()?),
+        })
+    }
+
+    /// Append a completed request/response pair and persist the cassette.
+    pub fn record(
+        &self,
+        method: &str,
+        url: &str,
+        request_headers: &[(String, String)],
+        request_body: &[u8],
+        response: &Response,
+        response_body: &[u8],
+    ) -> Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        state.interactions.push(Interaction {
+            request: RecordedRequest {
+                method: method.to_owned(),
+                url: url.to_owned(),
+                headers: request_headers.to_vec(),
+                body: StoredBody::from_bytes(request_body),
+            },
+            response: RecordedResponse {
+                status: response.status,
+                url: response.url.clone(),
+                headers: response.headers.clone(),
+                body: StoredBody::from_bytes(response_body),
+            },
+        });
+        state.served.push(true);
+
+        // Write after every interaction so a crashed test run still leaves a usable cassette.
+        let file = CassetteFile {
+            interactions: state.interactions.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)?;
+        std::fs::write(&self.path, json)
+            .with_context(|| format!("Failed to write cassette '{}'", self.path.display()))
+    }
+}
+
+/// Make `path` the active cassette. Replay requires an existing cassette; record starts afresh.
+pub fn insert_cassette(path: String, mode: CassetteMode, match_on: Vec<MatchField>) -> Result<()> {
+    let path = PathBuf::from(path);
+    let interactions = match mode {
+        CassetteMode::Record => Vec::new(),
+        CassetteMode::Replay => {
+            let raw = std::fs::read(&path)
+                .with_context(|| format!("Failed to read cassette '{}'", path.display()))?;
+            serde_json::from_slice::<CassetteFile>(&raw)
+                .with_context(|| format!("Invalid cassette '{}'", path.display()))?
+                .interactions
+        }
+    };
+
+    let mut active = ACTIVE_CASSETTE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(current) = active.as_ref() {
+        bail!(
+            "Cassette '{}' is already in use; eject it first",
+            current.path.display()
+        );
+    }
+
+    *active = Some(Arc::new(Cassette {
+        path,
+        mode,
+        match_on,
+        state: Mutex::new(CassetteState {
+            served: vec![false; interactions.len()],
+            interactions,
+        }),
+    }));
+    Ok(())
+}
+
+/// Deactivate the current cassette, returning how many interactions it holds.
+pub fn eject_cassette() -> usize {
+    ACTIVE_CASSETTE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take()
+        .map(|cassette| {
+            cassette
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .interactions
+                .len()
+        })
+        .unwrap_or(0)
+}
+
+pub fn active_cassette() -> Option<Arc<Cassette>> {
+    ACTIVE_CASSETTE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_decode, base64_encode};
+
+    #[test]
+    fn base64_round_trips_binary_bodies() {
+        for input in [&b""[..], b"f", b"fo", b"foo", b"foob", &[0, 255, 128, 7, 9]] {
+            let encoded = base64_encode(input);
+            assert_eq!(base64_decode(&encoded).unwrap(), input);
+        }
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+    }
+}
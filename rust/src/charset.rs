@@ -0,0 +1,207 @@
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252, X_USER_DEFINED};
+use std::borrow::Cow;
+
+// Browsers only look for a `<meta>` declaration this far into a document.
+const PRESCAN_LIMIT: usize = 1024;
+
+/// Decode a response body the way a browser decodes a page: a byte order mark wins, then the
+/// `charset` of `Content-Type`, then, for HTML, a `<meta>` declaration near the top; anything
+/// else is UTF-8. Invalid sequences become U+FFFD.
+pub fn decode_text<'a>(bytes: &'a [u8], content_type: Option<&str>) -> Cow<'a, str> {
+    let declared = content_type.and_then(charset_param);
+    let sniffed = || {
+        content_type
+            .is_none_or(is_html)
+            .then(|| meta_charset(bytes))
+            .flatten()
+    };
+    let encoding = declared.or_else(sniffed).unwrap_or(UTF_8);
+    // `decode` sniffs the byte order mark itself and strips it.
+    encoding.decode(bytes).0
+}
+
+/// Whether a `Content-Type` value is `text/html`, whatever its parameters.
+pub fn is_html(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.eq_ignore_ascii_case("text/html")
+}
+
+/// The encoding named by the `charset` parameter of a `Content-Type` value.
+fn charset_param(value: &str) -> Option<&'static Encoding> {
+    value.split(';').skip(1).find_map(|param| {
+        let (name, label) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        let label = label.trim().trim_matches(|c| c == '"' || c == '\'');
+        Encoding::for_label(label.as_bytes())
+    })
+}
+
+/// The encoding a `<meta charset>` or `<meta http-equiv="Content-Type">` tag declares in the
+/// first [`PRESCAN_LIMIT`] bytes.
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(PRESCAN_LIMIT)];
+    let encoding = meta_tags(head).find_map(|attributes| meta_encoding(&attributes))?;
+    // A page cannot really be UTF-16 if its ASCII markup was readable.
+    Some(if encoding == UTF_16BE || encoding == UTF_16LE {
+        UTF_8
+    } else if encoding == X_USER_DEFINED {
+        WINDOWS_1252
+    } else {
+        encoding
+    })
+}
+
+fn meta_encoding(attributes: &[(String, String)]) -> Option<&'static Encoding> {
+    if let Some(label) = attribute(attributes, "charset") {
+        return Encoding::for_label(label.trim().as_bytes());
+    }
+    if attribute(attributes, "http-equiv")?.eq_ignore_ascii_case("content-type") {
+        return attribute(attributes, "content").and_then(charset_param);
+    }
+    None
+}
+
+/// The attributes of each `<meta>` tag in `html`, in order, skipping comments. Names are
+/// lowercased; values are kept as written.
+pub fn meta_tags(html: &[u8]) -> MetaTags<'_> {
+    MetaTags { html, pos: 0 }
+}
+
+pub struct MetaTags<'a> {
+    html: &'a [u8],
+    pos: usize,
+}
+
+impl Iterator for MetaTags<'_> {
+    type Item = Vec<(String, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.html.len() {
+            let rest = &self.html[self.pos..];
+            if rest.starts_with(b"<!--") {
+                self.pos += rest
+                    .windows(3)
+                    .position(|window| window == b"-->")
+                    .map_or(rest.len(), |end| end + 3);
+            } else if rest.len() > 5
+                && rest[..5].eq_ignore_ascii_case(b"<meta")
+                && (rest[5].is_ascii_whitespace() || rest[5] == b'/')
+            {
+                let (attributes, len) = tag_attributes(&rest[5..]);
+                self.pos += 5 + len;
+                return Some(attributes);
+            } else {
+                self.pos += 1;
+            }
+        }
+        None
+    }
+}
+
+/// The value of the attribute called `name`, which must be lowercase.
+pub fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Attributes of a tag up to its closing `>`, and how many bytes they took.
+fn tag_attributes(tag: &[u8]) -> (Vec<(String, String)>, usize) {
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+    let mut attributes = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < tag.len() && (tag[i].is_ascii_whitespace() || tag[i] == b'/') {
+            i += 1;
+        }
+        if i >= tag.len() || tag[i] == b'>' {
+            return (attributes, i);
+        }
+
+        let start = i;
+        while i < tag.len()
+            && !matches!(tag[i], b'=' | b'>' | b'/')
+            && !tag[i].is_ascii_whitespace()
+        {
+            i += 1;
+        }
+        let name = text(&tag[start..i]).to_ascii_lowercase();
+        while i < tag.len() && tag[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        let mut value = String::new();
+        if tag.get(i) == Some(&b'=') {
+            i += 1;
+            while i < tag.len() && tag[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            match tag.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = i + 1;
+                    let end = tag[start..]
+                        .iter()
+                        .position(|&byte| byte == quote)
+                        .map_or(tag.len(), |len| start + len);
+                    value = text(&tag[start..end]);
+                    i = end + 1;
+                }
+                _ => {
+                    let start = i;
+                    while i < tag.len() && tag[i] != b'>' && !tag[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    value = text(&tag[start..i]);
+                }
+            }
+        }
+        attributes.push((name, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_text;
+
+    #[test]
+    fn honors_the_declared_charset() {
+        let latin1 = b"caf\xe9";
+        assert_eq!(
+            decode_text(latin1, Some("text/plain; charset=\"ISO-8859-1\"")),
+            "café"
+        );
+        assert_eq!(decode_text(latin1, Some("text/plain")), "caf\u{fffd}");
+        assert_eq!(decode_text("café".as_bytes(), None), "café");
+    }
+
+    #[test]
+    fn sniffs_meta_declarations_in_html() {
+        let page = b"<!-- <meta charset=utf-8> --><html><head>\
+            <meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1251\">\
+            </head><body>\xcf\xf0\xe8\xe2\xe5\xf2</body></html>";
+        assert!(decode_text(page, Some("text/html")).ends_with("Привет</body></html>"));
+
+        let page = b"<meta charset='shift_jis'/><p>\x93\xfa\x96\x7b</p>";
+        assert_eq!(
+            decode_text(page, None),
+            "<meta charset='shift_jis'/><p>日本</p>"
+        );
+
+        // Only HTML is sniffed, and a header charset wins over the markup.
+        assert!(decode_text(page, Some("text/plain")).contains('\u{fffd}'));
+        assert!(decode_text(page, Some("text/html; charset=utf-8")).contains('\u{fffd}'));
+    }
+
+    #[test]
+    fn byte_order_marks_win() {
+        let utf16 = [0xff, 0xfe, b'h', 0, b'i', 0];
+        assert_eq!(
+            decode_text(&utf16, Some("text/html; charset=iso-8859-1")),
+            "hi"
+        );
+        assert_eq!(decode_text(b"\xef\xbb\xbfcaf\xc3\xa9", None), "café");
+    }
+}
@@ -0,0 +1,318 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::io::Write;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::Arc;
+use wreq::header::HeaderValue;
+use wreq_util::{Emulation, EmulationOS};
+
+use crate::client::{HTTP_RUNTIME, RedirectMode, RequestOptions, make_request, read_body_chunk};
+use crate::profiles::{emulation, emulation_os, supported_operating_systems, supported_profiles};
+
+const USAGE: &str = "\
+Usage: wreq-cli [options] <url>
+
+Options:
+  -X, --request <method>      Request method (default GET, or POST with --data)
+  -H, --header <name: value>  Add a request header; repeatable
+  -d, --data <body>           Send a request body; @path reads it from a file
+  -b, --browser <profile>     Browser profile or alias (default chrome_142)
+      --os <os>               Operating system to emulate (default macos)
+  -x, --proxy <url>           Send the request through a proxy
+  -k, --insecure              Skip TLS certificate verification
+      --redirect <mode>       follow, manual or error (default follow)
+      --timeout <ms>          Total request timeout (default 30000)
+      --connect-timeout <ms>  Connection timeout
+      --read-timeout <ms>     Timeout between reads
+      --raw                   Do not decode the response body
+  -i, --include               Print the status line and response headers
+  -o, --output <path>         Write the body to a file instead of stdout
+      --list-profiles         Print the browser profiles and operating systems
+  -h, --help                  Print this help";
+
+/// What the command line asked for.
+#[derive(Debug, PartialEq)]
+enum Command {
+    Request(Box<CliRequest>),
+    ListProfiles,
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+struct CliRequest {
+    url: String,
+    method: Option<String>,
+    headers: Vec<(String, String)>,
+    data: Option<String>,
+    browser: String,
+    os: String,
+    proxy: Option<String>,
+    insecure: bool,
+    redirect: RedirectMode,
+    timeout: u64,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+    raw: bool,
+    include: bool,
+    output: Option<String>,
+}
+
+/// Run `wreq-cli` with `args` (without the program name) and report how it went.
+pub fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let command = match parse_args(args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("wreq-cli: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match command {
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Command::ListProfiles => {
+            println!("Browser profiles: {}", supported_profiles().join(", "));
+            println!(
+                "Operating systems: {}",
+                supported_operating_systems().join(", ")
+            );
+            Ok(())
+        }
+        Command::Request(request) => HTTP_RUNTIME.block_on(send(*request)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("wreq-cli: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let mut args = args.into_iter();
+    let mut url = None;
+    let mut request = CliRequest {
+        url: String::new(),
+        method: None,
+        headers: Vec::new(),
+        data: None,
+        browser: "chrome_142".to_string(),
+        os: "macos".to_string(),
+        proxy: None,
+        insecure: false,
+        redirect: RedirectMode::Follow,
+        timeout: 30000,
+        connect_timeout: None,
+        read_timeout: None,
+        raw: false,
+        include: false,
+        output: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{name} needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--list-profiles" => return Ok(Command::ListProfiles),
+            "-X" | "--request" => request.method = Some(value(&arg)?.to_ascii_uppercase()),
+            "-H" | "--header" => {
+                request.headers.push(parse_header(&value(&arg)?)?);
+            }
+            "-d" | "--data" => request.data = Some(value(&arg)?),
+            "-b" | "--browser" => request.browser = value(&arg)?,
+            "--os" => request.os = value(&arg)?,
+            "-x" | "--proxy" => request.proxy = Some(value(&arg)?),
+            "-k" | "--insecure" => request.insecure = true,
+            "--redirect" => {
+                let mode = value(&arg)?;
+                request.redirect = RedirectMode::parse(&mode)
+                    .ok_or_else(|| anyhow!("unsupported redirect mode '{mode}'"))?;
+            }
+            "--timeout" => request.timeout = parse_number(&arg, &value(&arg)?)?,
+            "--connect-timeout" => {
+                request.connect_timeout = Some(parse_number(&arg, &value(&arg)?)?)
+            }
+            "--read-timeout" => request.read_timeout = Some(parse_number(&arg, &value(&arg)?)?),
+            "--raw" => request.raw = true,
+            "-i" | "--include" => request.include = true,
+            "-o" | "--output" => request.output = Some(value(&arg)?),
+            flag if flag.starts_with('-') && flag.len() > 1 => bail!("unknown option '{flag}'"),
+            _ if url.is_some() => bail!("only one URL can be requested at a time"),
+            _ => url = Some(arg),
+        }
+    }
+
+    request.url = url.ok_or_else(|| anyhow!("no URL given"))?;
+    Ok(Command::Request(Box::new(request)))
+}
+
+/// Split a `Name: value` header argument.
+pub(crate) fn parse_header(header: &str) -> Result<(String, String)> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| anyhow!("header '{header}' is not in 'Name: value' form"))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+pub(crate) fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("{flag} expects a number, got '{value}'"))
+}
+
+/// Resolve `--browser` and `--os`. Unlike `fetch`, which falls back to chrome_142/macos, a
+/// mistyped profile is an error on the command line.
+pub(crate) fn resolve_emulation(browser: &str, os: &str) -> Result<(Emulation, EmulationOS)> {
+    let emulation = emulation(browser)
+        .ok_or_else(|| anyhow!("unknown browser profile '{browser}' (see --list-profiles)"))?;
+    let emulation_os = emulation_os(os)
+        .ok_or_else(|| anyhow!("unknown operating system '{os}' (see --list-profiles)"))?;
+    Ok((emulation, emulation_os))
+}
+
+pub(crate) fn header_values(headers: &[(String, String)]) -> Result<Vec<(String, HeaderValue)>> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            HeaderValue::from_str(value)
+                .map(|value| (name.clone(), value))
+                .with_context(|| format!("invalid value for header '{name}'"))
+        })
+        .collect()
+}
+
+fn request_options(request: &CliRequest) -> Result<RequestOptions> {
+    let (emulation, emulation_os) = resolve_emulation(&request.browser, &request.os)?;
+    let headers = header_values(&request.headers)?;
+
+    let body = match request.data.as_deref() {
+        Some(data) => Some(match data.strip_prefix('@') {
+            Some(path) => std::fs::read(path).with_context(|| format!("cannot read {path}"))?,
+            None => data.as_bytes().to_vec(),
+        }),
+        None => None,
+    };
+    let method = match (&request.method, &body) {
+        (Some(method), _) => method.clone(),
+        (None, Some(_)) => "POST".to_string(),
+        (None, None) => "GET".to_string(),
+    };
+
+    let mut options = RequestOptions::new(request.url.clone(), emulation, emulation_os);
+    options.headers = headers;
+    options.method = method;
+    options.body = body;
+    options.proxy = request.proxy.as_deref().map(Arc::from);
+    options.timeout = request.timeout;
+    options.redirect = request.redirect;
+    options.insecure = request.insecure;
+    options.connect_timeout = request.connect_timeout;
+    options.read_timeout = request.read_timeout;
+    options.decode_body = !request.raw;
+    Ok(options)
+}
+
+async fn send(request: CliRequest) -> Result<()> {
+    let response = make_request(request_options(&request)?).await?;
+
+    if request.include {
+        // Headers always go to stdout, like curl, so `-o` still gets just the body.
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{} {}", response.status, response.url)?;
+        for (name, value) in &response.headers {
+            writeln!(stdout, "{name}: {value}")?;
+        }
+        writeln!(stdout)?;
+        stdout.flush()?;
+    }
+
+    let mut out: Box<dyn Write> = match &request.output {
+        Some(path) => {
+            Box::new(std::fs::File::create(path).with_context(|| format!("cannot create {path}"))?)
+        }
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    if let Some(bytes) = &response.body_bytes {
+        out.write_all(bytes)?;
+    }
+    if let Some(handle) = response.body_handle {
+        while let Some(chunk) = read_body_chunk(handle).await? {
+            out.write_all(&chunk)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, parse_args, request_options};
+    use crate::client::RedirectMode;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Command> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_a_curl_style_request() {
+        let Command::Request(request) = parse(&[
+            "--browser",
+            "firefox",
+            "--os",
+            "windows",
+            "-H",
+            "Accept: text/html",
+            "-H",
+            "X-Trace:  abc ",
+            "-d",
+            "a=1",
+            "-k",
+            "--redirect",
+            "manual",
+            "https://example.com",
+        ])
+        .unwrap() else {
+            panic!("expected a request");
+        };
+
+        assert_eq!(request.url, "https://example.com");
+        assert_eq!(request.browser, "firefox");
+        assert_eq!(request.os, "windows");
+        assert_eq!(
+            request.headers,
+            vec![
+                ("Accept".to_string(), "text/html".to_string()),
+                ("X-Trace".to_string(), "abc".to_string()),
+            ]
+        );
+        assert!(request.insecure);
+        assert_eq!(request.redirect, RedirectMode::Manual);
+
+        let options = request_options(&request).unwrap();
+        assert_eq!(options.method, "POST");
+        assert_eq!(options.body.as_deref(), Some(&b"a=1"[..]));
+        assert!(options.ephemeral);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--timeout"]).is_err());
+        assert!(parse(&["--timeout", "soon", "http://a"]).is_err());
+        assert!(parse(&["-H", "no-colon", "http://a"]).is_err());
+        assert!(parse(&["--frobnicate", "http://a"]).is_err());
+        assert!(parse(&["http://a", "http://b"]).is_err());
+        assert_eq!(parse(&["-h", "http://a"]).unwrap(), Command::Help);
+
+        let Command::Request(request) = parse(&["--browser", "netscape_4", "http://a"]).unwrap()
+        else {
+            panic!("expected a request");
+        };
+        assert!(request_options(&request).is_err());
+    }
+}
@@ -1,22 +1,71 @@
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use bytes::Bytes;
 use dashmap::DashMap;
-use futures_util::{Stream, StreamExt};
+use futures_util::stream::Peekable;
+use futures_util::{FutureExt, Stream, StreamExt, TryStreamExt};
+use moka::Expiry;
+use moka::notification::RemovalCause;
 use moka::sync::Cache;
 use std::borrow::Cow;
+use std::fs::File;
+use std::io::Write;
+use std::net::SocketAddr;
 use std::pin::Pin;
-use std::sync::Arc;
 use std::sync::LazyLock;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 use tokio::runtime::Runtime;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use wreq::cookie::Jar;
-use wreq::header::OrigHeaderMap;
-use wreq::{Client as HttpClient, Method, Proxy, redirect};
+use wreq::cookie::{CookieStore, Jar};
+use wreq::header::{
+    ACCEPT_ENCODING, ACCEPT_LANGUAGE, CONTENT_ENCODING, CONTENT_LENGTH, HeaderMap, HeaderValue,
+    OrigHeaderMap,
+};
+use wreq::http1::Http1Options;
+use wreq::tls::{KeyLog, TlsOptions};
+use wreq::{Client as HttpClient, ClientBuilder, EmulationFactory, Method, Proxy, redirect};
 use wreq_util::{Emulation, EmulationOS, EmulationOption};
 
+use crate::abort::Aborted;
+use crate::alt_svc::{AltSvcCache, AltSvcEntry};
+use crate::audit::AuditedRequest;
+use crate::auth::{SessionAuth, TokenSource, parse_token_response};
+use crate::before_send::{BeforeSendHook, add_cookies};
+use crate::browser_cookies::BrowserCookie;
+use crate::buffer_pool::{BODY_CHUNK_POOL, BODY_CHUNK_SIZE, PooledBuffer};
+use crate::cassette::{CassetteMode, active_cassette};
+use crate::charset::is_html;
+use crate::client_hints::{ClientHints, ClientHintsStore, hint_headers};
+use crate::connect_to::{ConnectTo, ConnectToResolver};
+use crate::cookie_hook::{NotifyingJar, SetCookieHook};
+use crate::cross_origin::{CrossOriginPolicy, MAX_CROSS_ORIGIN_HOPS};
+use crate::curl::{CurlRequest, render as render_curl};
+use crate::deadline::{RequestDeadlines, within};
+use crate::digest::{BodyDigest, DigestAlgorithm, digest_bytes};
+use crate::dns::{DnsAnswer, DnsRecorder, RecordingResolver};
+use crate::egress::EgressPolicy;
+use crate::encoding::{AcceptEncoding, BodySize, BodySizeSlot, ContentDecoder, decode_stream};
+use crate::flow_control::Http2Windows;
+use crate::header_limits::HeaderLimits;
+use crate::hsts::{HstsEntry, HstsStore};
+use crate::meta_refresh::{MAX_META_REFRESH_HOPS, refresh_target};
+use crate::mock::{MockResponse, match_mock};
+use crate::ntlm::{NtlmCredentials, NtlmScheme, authenticate_header, negotiate_header};
+use crate::ntlm_proxy::NtlmProxy;
+use crate::pacing::{Pacer, Pacing};
+use crate::phase::{BodyPhase, ConnectTimingLayer, Phase, PhaseFailure, RequestPhases};
+use crate::priority::Priority;
+use crate::proxy_rotation::{ProxyPool, ProxyRotation};
+use crate::reason_phrase::reason_phrase;
+use crate::request_mode::RequestMode;
+use crate::throttle::{RateLimiter, request_body_stream, throttle_stream};
+use crate::trace::{HopRecorder, RedirectHop, RequestTiming, TraceContext, now_ms};
+use crate::trailers::{TrailerSlot, data_frames, take_trailers};
+
 pub static HTTP_RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -27,15 +76,27 @@ pub static HTTP_RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
 static SESSION_MANAGER: LazyLock<SessionManager> = LazyLock::new(SessionManager::new);
 static EPHEMERAL_MANAGER: LazyLock<EphemeralClientManager> =
     LazyLock::new(EphemeralClientManager::new);
+static ORIGIN_POOL: LazyLock<OriginPoolManager> = LazyLock::new(OriginPoolManager::new);
 static TRANSPORT_MANAGER: LazyLock<TransportManager> = LazyLock::new(TransportManager::new);
 
+// Shutdown is terminal: once set, new requests, sessions, transports, and WebSockets are
+// rejected, and anything still running when the grace period expires is aborted.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static IN_FLIGHT_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+// Every request observes the epoch token current at its start. Cancelling everything swaps in
+// a fresh token, so requests without an individual cancellation token can still be aborted.
+static REQUEST_EPOCH: LazyLock<RwLock<CancellationToken>> =
+    LazyLock::new(|| RwLock::new(CancellationToken::new()));
+
 // Responses at or below this size (bytes) are fully buffered in Rust and returned
-// inline to Node, avoiding an extra round-trip to stream the body.
+// inline to Node, avoiding an extra round-trip to stream the body, unless the request
+// defaults set another limit.
 // Most API responses fit within 2 MiB; inlining them skips DashMap, Mutex, and an
 // additional FFI round-trip that the streaming path would otherwise require.
 const INLINE_BODY_MAX: u64 = 2 * 1024 * 1024;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum RedirectMode {
     #[default]
     Follow,
@@ -44,6 +105,23 @@ pub enum RedirectMode {
 }
 
 impl RedirectMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "follow" => Some(RedirectMode::Follow),
+            "manual" => Some(RedirectMode::Manual),
+            "error" => Some(RedirectMode::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RedirectMode::Follow => "follow",
+            RedirectMode::Manual => "manual",
+            RedirectMode::Error => "error",
+        }
+    }
+
     fn as_policy(self) -> redirect::Policy {
         match self {
             RedirectMode::Follow => redirect::Policy::default(),
@@ -53,6 +131,57 @@ impl RedirectMode {
             }),
         }
     }
+
+    /// Wrap a policy so every redirect response is recorded before the policy decides on it.
+    fn recording_hops(inner: redirect::Policy, hops: HopRecorder) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| {
+            hops.record(attempt.status.as_u16(), attempt.uri.to_string());
+            inner.redirect(attempt)
+        })
+    }
+
+    /// Wrap a policy so redirects to targets outside the transport's egress policy fail.
+    fn restricted(inner: redirect::Policy, egress: Arc<EgressPolicy>) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| match egress.violation(&attempt.uri) {
+            Some(message) => attempt.error(message),
+            None => inner.redirect(attempt),
+        })
+    }
+
+    /// Wrap a policy so redirects from an `https` URL to a plain `http` one fail.
+    fn secure_only(inner: redirect::Policy) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| {
+            let from_https = attempt
+                .previous
+                .last()
+                .is_some_and(|uri| uri.scheme_str() == Some("https"));
+
+            if from_https && attempt.uri.scheme_str() == Some("http") {
+                let message = format!("Refusing insecure redirect to {}", attempt.uri);
+                return attempt.error(message);
+            }
+
+            inner.redirect(attempt)
+        })
+    }
+
+    /// Wrap a policy so redirects that downgrade a known HSTS host fail.
+    fn hsts_guarded(inner: redirect::Policy, hsts: Arc<HstsStore>) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| {
+            let downgrade = attempt.uri.scheme_str() == Some("http")
+                && attempt
+                    .uri
+                    .host()
+                    .is_some_and(|host| hsts.is_known_host(host));
+
+            if downgrade {
+                let message = format!("Refusing HSTS downgrade redirect to {}", attempt.uri);
+                return attempt.error(message);
+            }
+
+            inner.redirect(attempt)
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -60,10 +189,16 @@ pub struct RequestOptions {
     pub url: String,
     pub emulation: Emulation,
     pub emulation_os: EmulationOS,
-    pub headers: Vec<(String, String)>,
+    pub headers: Vec<(String, HeaderValue)>,
+    /// Headers whose value is added to the browser profile's default instead of replacing it.
+    pub append_headers: Vec<String>,
+    /// Browser profile default headers left off this request.
+    pub remove_headers: Vec<String>,
     pub method: String,
     pub body: Option<Vec<u8>>,
     pub proxy: Option<Arc<str>>,
+    /// Connect without a proxy, even when the transport has one.
+    pub direct: bool,
     pub timeout: u64,
     pub redirect: RedirectMode,
     pub session_id: String,
@@ -76,17 +211,250 @@ pub struct RequestOptions {
     pub pool_max_size: Option<u32>,
     pub connect_timeout: Option<u64>,
     pub read_timeout: Option<u64>,
+    pub group_id: Option<Arc<str>>,
+    pub max_download_bytes_per_sec: Option<u64>,
+    pub max_upload_bytes_per_sec: Option<u64>,
+    pub body_digest: Option<DigestAlgorithm>,
+    pub trace: Option<TraceContext>,
+    pub write_timeout: Option<u64>,
+    pub total_deadline: Option<u64>,
+    pub block_insecure_redirects: bool,
+    pub strip_on_cross_origin: Option<Vec<String>>,
+    pub disable_cookies: bool,
+    pub inline_body_max: Option<u64>,
+    /// Overrides the profile's `Accept-Encoding` and the response decoders to match.
+    pub accept_encoding: Option<AcceptEncoding>,
+    /// When false the body is returned exactly as received, still content-encoded.
+    pub decode_body: bool,
+    /// Reuse a pooled client per origin instead of building a dedicated one.
+    pub origin_pooling: bool,
+    /// Replaces the profile's `Priority` header.
+    pub priority: Option<Priority>,
+    /// Replaces the profile's `Accept-Language` header.
+    pub accept_language: Option<HeaderValue>,
+    /// Follow `<meta http-equiv="refresh">` pages that redirect within this many seconds.
+    pub meta_refresh_max_delay: Option<u64>,
+    /// Windows credentials for servers that answer 401 with an NTLM or Negotiate challenge.
+    pub ntlm: Option<NtlmCredentials>,
+    /// Replaces the profile's `Sec-CH-UA` client hints.
+    pub client_hints: Option<ClientHints>,
+    /// Rewrites the profile's `Sec-Fetch-*`, `Accept` and `Priority` headers for this kind of
+    /// request.
+    pub mode: Option<RequestMode>,
+    /// Hand JS only the status, URL and body; see [`Response::minimal`].
+    pub minimal_response: bool,
+    /// Adds headers to the request once everything else is on it.
+    pub before_send: Option<BeforeSendHook>,
+    /// Hosts whose connections are opened somewhere else; the first rule for the URL applies.
+    pub connect_to: Vec<ConnectTo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransportOptions {
+    pub emulation: Emulation,
+    pub emulation_os: EmulationOS,
+    pub proxy: Option<Arc<str>>,
+    pub insecure: bool,
+    pub pool_idle_timeout: Option<u64>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub pool_max_size: Option<u32>,
+    /// Wall-clock lifetime of pooled connections, however busy they are.
+    pub max_connection_age: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub read_timeout: Option<u64>,
+    pub tls_session_tickets: Option<bool>,
+    pub tls_session_resumption: Option<bool>,
+    pub keylog_path: Option<String>,
+    pub max_download_bytes_per_sec: Option<u64>,
+    pub max_upload_bytes_per_sec: Option<u64>,
+    pub allowlist: Vec<String>,
+    pub denylist: Vec<String>,
+    pub accept_encoding: Option<AcceptEncoding>,
+    pub max_response_header_bytes: Option<usize>,
+    pub max_header_count: Option<usize>,
+    /// Accept the malformed HTTP/1 response heads browsers tolerate.
+    pub lenient_headers: bool,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub max_frame_size: Option<u32>,
+    pub tcp_nodelay: Option<bool>,
+    pub tcp_keepalive: Option<TcpKeepAlive>,
+    pub tcp_send_buffer_size: Option<usize>,
+    pub tcp_recv_buffer_size: Option<usize>,
+    /// Rotate through several proxies instead of using `proxy`.
+    pub proxy_pool: Option<ProxyPool>,
+    /// Windows credentials for a `proxy` that requires NTLM.
+    pub proxy_ntlm: Option<NtlmCredentials>,
+    /// Replaces the profile's `Sec-CH-UA` client hints on every request.
+    pub client_hints: Option<ClientHints>,
+}
+
+impl RequestOptions {
+    /// A GET of `url` with a fresh cookie jar and every other option left at its default.
+    pub fn new(url: String, emulation: Emulation, emulation_os: EmulationOS) -> Self {
+        Self {
+            url,
+            emulation,
+            emulation_os,
+            headers: Vec::new(),
+            append_headers: Vec::new(),
+            remove_headers: Vec::new(),
+            method: "GET".to_string(),
+            body: None,
+            proxy: None,
+            direct: false,
+            timeout: 30000,
+            redirect: RedirectMode::Follow,
+            session_id: generate_session_id(),
+            ephemeral: true,
+            disable_default_headers: false,
+            insecure: false,
+            transport_id: None,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            pool_max_size: None,
+            connect_timeout: None,
+            read_timeout: None,
+            group_id: None,
+            max_download_bytes_per_sec: None,
+            max_upload_bytes_per_sec: None,
+            body_digest: None,
+            trace: None,
+            write_timeout: None,
+            total_deadline: None,
+            block_insecure_redirects: false,
+            strip_on_cross_origin: None,
+            disable_cookies: false,
+            inline_body_max: None,
+            accept_encoding: None,
+            decode_body: true,
+            origin_pooling: false,
+            priority: None,
+            accept_language: None,
+            meta_refresh_max_delay: None,
+            ntlm: None,
+            client_hints: None,
+            mode: None,
+            minimal_response: false,
+            before_send: None,
+            connect_to: Vec::new(),
+        }
+    }
+}
+
+impl TransportOptions {
+    /// A transport for the profile with every other option left at wreq's defaults.
+    pub fn new(emulation: Emulation, emulation_os: EmulationOS) -> Self {
+        Self {
+            emulation,
+            emulation_os,
+            proxy: None,
+            insecure: false,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            pool_max_size: None,
+            max_connection_age: None,
+            connect_timeout: None,
+            read_timeout: None,
+            tls_session_tickets: None,
+            tls_session_resumption: None,
+            keylog_path: None,
+            max_download_bytes_per_sec: None,
+            max_upload_bytes_per_sec: None,
+            allowlist: Vec::new(),
+            denylist: Vec::new(),
+            accept_encoding: None,
+            max_response_header_bytes: None,
+            max_header_count: None,
+            lenient_headers: false,
+            initial_stream_window_size: None,
+            initial_connection_window_size: None,
+            max_frame_size: None,
+            tcp_nodelay: None,
+            tcp_keepalive: None,
+            tcp_send_buffer_size: None,
+            tcp_recv_buffer_size: None,
+            proxy_pool: None,
+            proxy_ntlm: None,
+            client_hints: None,
+        }
+    }
+}
+
+/// `SO_KEEPALIVE` probing for a transport's sockets. Unset fields keep wreq's defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpKeepAlive {
+    Disabled,
+    Enabled {
+        /// Idle time before the first probe.
+        idle: Option<Duration>,
+        /// Time between probes.
+        interval: Option<Duration>,
+        /// Unanswered probes before the connection is dropped.
+        retries: Option<u32>,
+    },
+}
+
+/// Which kind of native client served a request.
+#[derive(Debug, Clone, Copy)]
+pub enum ClientKind {
+    Transport,
+    Ephemeral,
+    Pooled,
+    Dedicated,
+}
+
+impl ClientKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ClientKind::Transport => "transport",
+            ClientKind::Ephemeral => "ephemeral",
+            ClientKind::Pooled => "pooled",
+            ClientKind::Dedicated => "dedicated",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    /// Whether the request went out on a pooled connection. `None` for responses that never
+    /// touched a connection.
+    pub reused: Option<bool>,
+    pub client: ClientKind,
+    pub transport_id: Option<String>,
+    pub local_addr: Option<SocketAddr>,
+    pub remote_addr: Option<SocketAddr>,
+    /// The lookup `remote_addr` was picked from, kept with the connection so reuses report it too.
+    pub dns: Option<Arc<DnsAnswer>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Response {
     pub status: u16,
+    /// Reason phrase the server sent instead of the canonical one for `status`.
+    pub reason: Option<String>,
     pub headers: Vec<(String, String)>,
     pub body_handle: Option<u64>,
     pub body_bytes: Option<Bytes>,
     pub cookies: Vec<(String, String)>,
     pub url: String,
     pub content_length: Option<u64>,
+    pub connection: ConnectionInfo,
+    /// Hex digest of `body_bytes`; streamed bodies report theirs through `take_body_digest`.
+    pub body_digest: Option<String>,
+    /// Trailer fields sent after `body_bytes`; streamed bodies report theirs through
+    /// `take_body_trailers`.
+    pub trailers: Option<Vec<(String, String)>>,
+    /// Size of the header block as HTTP/1.1 `name: value` lines.
+    pub header_bytes: u64,
+    /// Size of `body_bytes` on the wire and decoded; streamed bodies report theirs through
+    /// `take_body_size`.
+    pub body_size: Option<BodySize>,
+    /// Set for traced requests that went over the network.
+    pub timing: Option<RequestTiming>,
+    /// Leaves headers, cookies and the reason phrase unread, and tells JS to skip the rest of
+    /// the metadata, for callers that only look at the status and body.
+    pub minimal: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -97,6 +465,7 @@ struct SessionConfig {
     insecure: bool,
     connect_timeout: Option<Duration>,
     read_timeout: Option<Duration>,
+    connect_to: Option<ConnectTo>,
 }
 
 impl SessionConfig {
@@ -109,10 +478,42 @@ impl SessionConfig {
             insecure: options.insecure,
             connect_timeout: options.connect_timeout.map(Duration::from_millis),
             read_timeout: options.read_timeout.map(Duration::from_millis),
+            connect_to: request_connect_to(options),
         }
     }
 }
 
+/// What a request on a transport changes about the transport's client.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientOverrides {
+    /// The proxy in place of the transport's; `Some(None)` connects directly.
+    proxy: Option<Option<Arc<str>>>,
+    connect_to: Option<ConnectTo>,
+}
+
+impl ClientOverrides {
+    fn for_request(options: &RequestOptions, connect_to: Option<ConnectTo>) -> Self {
+        let proxy = if options.direct {
+            Some(None)
+        } else {
+            options.proxy.clone().map(Some)
+        };
+        Self { proxy, connect_to }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.proxy.is_none() && self.connect_to.is_none()
+    }
+}
+
+/// The `connectTo` rule for the request URL. Requests it rejects fail in `send_request` before
+/// a client is picked.
+fn request_connect_to(options: &RequestOptions) -> Option<ConnectTo> {
+    ConnectTo::select(&options.connect_to, &options.url)
+        .ok()
+        .flatten()
+}
+
 #[derive(Debug, Clone)]
 struct TransportConfig {
     emulation: Emulation,
@@ -122,8 +523,25 @@ struct TransportConfig {
     pool_idle_timeout: Option<Duration>,
     pool_max_idle_per_host: Option<usize>,
     pool_max_size: Option<u32>,
+    max_connection_age: Option<Duration>,
     connect_timeout: Option<Duration>,
     read_timeout: Option<Duration>,
+    tls_session_tickets: Option<bool>,
+    tls_session_resumption: Option<bool>,
+    keylog_path: Option<Arc<str>>,
+    max_download_bytes_per_sec: Option<u64>,
+    max_upload_bytes_per_sec: Option<u64>,
+    accept_encoding: Option<AcceptEncoding>,
+    header_limits: HeaderLimits,
+    lenient_headers: bool,
+    h2_windows: Http2Windows,
+    tcp_nodelay: Option<bool>,
+    tcp_keepalive: Option<TcpKeepAlive>,
+    tcp_send_buffer_size: Option<usize>,
+    tcp_recv_buffer_size: Option<usize>,
+    proxy_pool: Option<ProxyPool>,
+    client_hints: Option<ClientHints>,
+    connect_to: Option<ConnectTo>,
 }
 
 impl TransportConfig {
@@ -137,33 +555,64 @@ impl TransportConfig {
             pool_idle_timeout: options.pool_idle_timeout.map(Duration::from_millis),
             pool_max_idle_per_host: options.pool_max_idle_per_host,
             pool_max_size: options.pool_max_size,
+            max_connection_age: None,
             connect_timeout: options.connect_timeout.map(Duration::from_millis),
             read_timeout: options.read_timeout.map(Duration::from_millis),
+            tls_session_tickets: None,
+            tls_session_resumption: None,
+            keylog_path: None,
+            max_download_bytes_per_sec: None,
+            max_upload_bytes_per_sec: None,
+            accept_encoding: None,
+            header_limits: HeaderLimits::default(),
+            lenient_headers: false,
+            h2_windows: Http2Windows::default(),
+            tcp_nodelay: None,
+            tcp_keepalive: None,
+            tcp_send_buffer_size: None,
+            tcp_recv_buffer_size: None,
+            proxy_pool: None,
+            client_hints: None,
+            connect_to: request_connect_to(options),
         }
     }
 
     #[inline]
-    fn new(
-        emulation: Emulation,
-        emulation_os: EmulationOS,
-        proxy: Option<Arc<str>>,
-        insecure: bool,
-        pool_idle_timeout: Option<u64>,
-        pool_max_idle_per_host: Option<usize>,
-        pool_max_size: Option<u32>,
-        connect_timeout: Option<u64>,
-        read_timeout: Option<u64>,
-    ) -> Self {
+    fn from_transport_options(options: TransportOptions) -> Self {
         Self {
-            emulation,
-            emulation_os,
-            proxy,
-            insecure,
-            pool_idle_timeout: pool_idle_timeout.map(Duration::from_millis),
-            pool_max_idle_per_host,
-            pool_max_size,
-            connect_timeout: connect_timeout.map(Duration::from_millis),
-            read_timeout: read_timeout.map(Duration::from_millis),
+            emulation: options.emulation,
+            emulation_os: options.emulation_os,
+            proxy: options.proxy,
+            insecure: options.insecure,
+            pool_idle_timeout: options.pool_idle_timeout.map(Duration::from_millis),
+            pool_max_idle_per_host: options.pool_max_idle_per_host,
+            pool_max_size: options.pool_max_size,
+            max_connection_age: options.max_connection_age.map(Duration::from_millis),
+            connect_timeout: options.connect_timeout.map(Duration::from_millis),
+            read_timeout: options.read_timeout.map(Duration::from_millis),
+            tls_session_tickets: options.tls_session_tickets,
+            tls_session_resumption: options.tls_session_resumption,
+            keylog_path: options.keylog_path.map(Arc::from),
+            max_download_bytes_per_sec: options.max_download_bytes_per_sec,
+            max_upload_bytes_per_sec: options.max_upload_bytes_per_sec,
+            accept_encoding: options.accept_encoding,
+            header_limits: HeaderLimits {
+                max_bytes: options.max_response_header_bytes,
+                max_count: options.max_header_count,
+            },
+            lenient_headers: options.lenient_headers,
+            h2_windows: Http2Windows {
+                stream_window: options.initial_stream_window_size,
+                connection_window: options.initial_connection_window_size,
+                max_frame_size: options.max_frame_size,
+            },
+            tcp_nodelay: options.tcp_nodelay,
+            tcp_keepalive: options.tcp_keepalive,
+            tcp_send_buffer_size: options.tcp_send_buffer_size,
+            tcp_recv_buffer_size: options.tcp_recv_buffer_size,
+            proxy_pool: options.proxy_pool,
+            client_hints: options.client_hints,
+            connect_to: None,
         }
     }
 }
@@ -171,11 +620,147 @@ impl TransportConfig {
 #[derive(Clone)]
 struct TransportEntry {
     client: Arc<HttpClient>,
+    config: TransportConfig,
+    alt_svc: Arc<AltSvcCache>,
+    // Shared by every response body read and request body sent through this transport.
+    download_limiter: Option<Arc<RateLimiter>>,
+    upload_limiter: Option<Arc<RateLimiter>>,
+    egress: Option<Arc<EgressPolicy>>,
+    /// One client per proxy when the transport rotates through a proxy pool.
+    proxy_rotation: Option<Arc<ProxyRotation<Arc<HttpClient>>>>,
+    /// When the clients (and so every pooled connection) were built.
+    built_at: std::time::Instant,
+    /// The local proxy the clients use to reach an upstream proxy that requires NTLM.
+    ntlm_proxy: Option<Arc<NtlmProxy>>,
+    /// The transport's client for each proxy and `connectTo` rule its requests have asked for.
+    derived_clients: Cache<ClientOverrides, Arc<HttpClient>>,
+}
+
+impl TransportEntry {
+    /// Whether `maxConnectionAge` has passed, so the connections must be retired.
+    fn expired(&self) -> bool {
+        self.config
+            .max_connection_age
+            .is_some_and(|age| self.built_at.elapsed() >= age)
+    }
+
+    /// The same transport on freshly built clients, without their pooled connections and
+    /// cached TLS sessions.
+    fn rebuilt(&self) -> Result<Self> {
+        let proxy_rotation = match (&self.proxy_rotation, &self.config.proxy_pool) {
+            (Some(rotation), Some(pool)) => Some(Arc::new(
+                rotation.with_members(build_proxy_clients(&self.config, pool)?),
+            )),
+            _ => None,
+        };
+        let client = match &proxy_rotation {
+            Some(rotation) => rotation.current().1.clone(),
+            None => Arc::new(build_client(&self.config)?),
+        };
+        Ok(Self {
+            client,
+            config: self.config.clone(),
+            alt_svc: self.alt_svc.clone(),
+            download_limiter: self.download_limiter.clone(),
+            upload_limiter: self.upload_limiter.clone(),
+            egress: self.egress.clone(),
+            proxy_rotation,
+            built_at: std::time::Instant::now(),
+            ntlm_proxy: self.ntlm_proxy.clone(),
+            derived_clients: derived_client_cache(),
+        })
+    }
+
+    /// The transport's settings with `overrides` applied.
+    fn derived_config(&self, overrides: &ClientOverrides) -> TransportConfig {
+        let mut config = self.config.clone();
+        if let Some(proxy) = &overrides.proxy {
+            config.proxy = proxy.clone();
+            config.proxy_pool = None;
+        }
+        config.connect_to = overrides.connect_to.clone();
+        config
+    }
+
+    /// The transport's client with `overrides` applied. Each set of overrides gets its own, so
+    /// those connections are pooled apart from the transport's others.
+    fn derived_client(&self, overrides: &ClientOverrides) -> Result<Arc<HttpClient>> {
+        if let Some(client) = self.derived_clients.get(overrides) {
+            return Ok(client);
+        }
+        let client = Arc::new(build_client(&self.derived_config(overrides))?);
+        self.derived_clients
+            .insert(overrides.clone(), client.clone());
+        Ok(client)
+    }
+
+    /// The proxy a request with `overrides` goes through.
+    fn proxy_for(&self, overrides: &ClientOverrides) -> Option<Arc<str>> {
+        match &overrides.proxy {
+            Some(proxy) => proxy.clone(),
+            None => self.current_proxy(),
+        }
+    }
+
+    /// The client for work that is not a counted request (WebSockets, raw HTTP/2 streams).
+    fn current_client(&self) -> Arc<HttpClient> {
+        match &self.proxy_rotation {
+            Some(rotation) => rotation.current().1.clone(),
+            None => self.client.clone(),
+        }
+    }
+
+    fn current_proxy(&self) -> Option<Arc<str>> {
+        match &self.proxy_rotation {
+            Some(rotation) => Some(Arc::from(rotation.current().0)),
+            None => self.config.proxy.clone(),
+        }
+    }
+}
+
+/// Per-request state resolved from the transport and session before sending.
+struct RequestContext {
+    client: Arc<HttpClient>,
+    client_kind: ClientKind,
+    cookie_jar: Arc<dyn CookieStore>,
+    hsts: Option<Arc<HstsStore>>,
+    alt_svc: Option<Arc<AltSvcCache>>,
+    download_limiter: Option<Arc<RateLimiter>>,
+    upload_limiter: Option<Arc<RateLimiter>>,
+    egress: Option<Arc<EgressPolicy>>,
+    accept_encoding: Option<AcceptEncoding>,
+    header_limits: HeaderLimits,
+    /// Profile defaults sent in place of wreq's when some of them were removed.
+    profile_defaults: Option<HeaderMap>,
+    client_hints: Option<Arc<ClientHintsStore>>,
 }
 
 #[derive(Clone)]
 struct SessionEntry {
     cookie_jar: Arc<Jar>,
+    hsts: Option<Arc<HstsStore>>,
+    on_set_cookie: Option<SetCookieHook>,
+    auth: Option<Arc<SessionAuth>>,
+    client_hints: Option<Arc<ClientHintsStore>>,
+    pacer: Option<Arc<Pacer>>,
+}
+
+/// One incarnation of a session, so that something holding on to a session that was closed and
+/// created again under the same id cannot drop the new one.
+pub struct SessionToken(Weak<SessionEntry>);
+
+/// What a session is created with besides its id.
+#[derive(Clone, Default)]
+pub struct SessionOptions {
+    pub hsts: bool,
+    /// Told about every cookie the session's responses store.
+    pub on_set_cookie: Option<SetCookieHook>,
+    /// Bearer token attached to the session's requests and refreshed on 401.
+    pub auth: Option<Arc<SessionAuth>>,
+    /// Send the high-entropy client hints an origin asks for with `Accept-CH`.
+    pub accept_client_hints: bool,
+    /// Gaps kept between consecutive requests to the same host.
+    pub pacing: Option<Pacing>,
 }
 
 struct TransportManager {
@@ -190,9 +775,293 @@ struct EphemeralClientManager {
     cache: Cache<SessionConfig, Arc<HttpClient>>,
 }
 
-pub type ResponseBodyStream = Pin<Box<dyn Stream<Item = wreq::Result<Bytes>> + Send>>;
+/// Clients for session requests without a transport, shared per origin so their connection
+/// pools outlive a single request. Cookie jars stay per session; only connections are shared.
+struct OriginPoolManager {
+    cache: Cache<OriginPoolKey, Arc<HttpClient>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct OriginPoolKey {
+    origin: String,
+    config: SessionConfig,
+}
 
-static BODY_STREAMS: LazyLock<Cache<u64, Arc<Mutex<ResponseBodyStream>>>> = LazyLock::new(|| {
+impl OriginPoolKey {
+    fn from_request(options: &RequestOptions) -> Result<Self> {
+        let url = url::Url::parse(&options.url).context("Invalid request URL")?;
+        Ok(Self {
+            origin: url.origin().ascii_serialization(),
+            config: SessionConfig::from_request(options),
+        })
+    }
+}
+
+pub type ResponseBodyStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
+
+struct BodyStreamEntry {
+    stream: Mutex<Peekable<ResponseBodyStream>>,
+    group_id: Option<Arc<str>>,
+    tee: std::sync::Mutex<Option<BodyTee>>,
+    digest: std::sync::Mutex<Option<BodyDigest>>,
+    trailers: TrailerSlot,
+    size: BodySizeSlot,
+    /// Reads fail once this passes (request timeout or total deadline).
+    deadline: Option<tokio::time::Instant>,
+    phase: BodyPhase,
+    created_at: std::time::Instant,
+}
+
+impl BodyStreamEntry {
+    /// Whether a read is waiting on the stream.
+    fn reading(&self) -> bool {
+        self.stream.try_lock().is_err()
+    }
+}
+
+/// A piece of a response body handed to JS by [`read_body_chunk`].
+pub enum BodyChunk {
+    Single(Bytes),
+    /// Small chunks that were already waiting on the socket, merged into a pooled buffer that is
+    /// recycled once JS is done with it.
+    Batched(PooledBuffer),
+}
+
+impl std::ops::Deref for BodyChunk {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            BodyChunk::Single(bytes) => bytes,
+            BodyChunk::Batched(buffer) => buffer,
+        }
+    }
+}
+
+/// File that receives a copy of every chunk read from a body stream.
+struct BodyTee {
+    file: File,
+    path: String,
+}
+
+impl BodyStreamEntry {
+    fn write_tee(&self, bytes: &[u8]) -> Result<()> {
+        let mut tee = self
+            .tee
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(tee) = tee.as_mut() {
+            tee.file
+                .write_all(bytes)
+                .with_context(|| format!("Failed to write response body to '{}'", tee.path))?;
+        }
+        Ok(())
+    }
+
+    fn update_digest(&self, bytes: &[u8]) -> Result<()> {
+        let mut digest = self
+            .digest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match digest.as_mut() {
+            Some(digest) => digest.update(bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Record the finished digest so JS can collect it after the handle is gone.
+    fn finish_digest(&self, handle: u64) -> Result<()> {
+        let digest = self
+            .digest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(digest) = digest {
+            BODY_DIGESTS.insert(handle, digest.finish()?);
+        }
+        Ok(())
+    }
+
+    /// Record the body's trailers so JS can collect them after the handle is gone.
+    fn finish_trailers(&self, handle: u64) {
+        if let Some(trailers) = take_trailers(&self.trailers) {
+            BODY_TRAILERS.insert(handle, trailers);
+        }
+    }
+
+    /// Record how many bytes the body took so JS can collect it after the handle is gone.
+    fn finish_size(&self, handle: u64) {
+        let size = *self
+            .size
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        BODY_SIZES.insert(handle, size);
+    }
+}
+
+// The DNS answer each connection was opened from, so responses on a pooled connection can
+// report it too. Entries are written whenever a connection is dialed, so a recycled socket pair
+// never reports an older connection's lookup.
+static CONNECTION_DNS: LazyLock<Cache<(SocketAddr, SocketAddr), Arc<DnsAnswer>>> =
+    LazyLock::new(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_idle(Duration::from_secs(60 * 60))
+            .build()
+    });
+
+static BODY_STREAMS: LazyLock<Cache<u64, Arc<BodyStreamEntry>>> = LazyLock::new(|| {
+    Cache::builder()
+        .expire_after(BodyStreamExpiry)
+        .eviction_listener(|handle, entry: Arc<BodyStreamEntry>, cause| {
+            if matches!(cause, RemovalCause::Expired) {
+                report_body_stream_leak(*handle, &entry, BodyStreamLeakReason::Ttl);
+            }
+        })
+        .build()
+});
+static BODY_STREAM_TTL_MS: AtomicU64 = AtomicU64::new(300_000);
+// Zero means no limit.
+static MAX_BODY_STREAMS: AtomicUsize = AtomicUsize::new(0);
+static BODY_STREAM_LEAK_HOOK: RwLock<Option<BodyStreamLeakHook>> = RwLock::new(None);
+
+/// Why an unread body stream was dropped without JS reading it to the end or cancelling it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyStreamLeakReason {
+    /// Not read for longer than the body stream TTL.
+    Ttl,
+    /// Among the oldest handles when the max stream count was exceeded.
+    MaxStreams,
+}
+
+impl BodyStreamLeakReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BodyStreamLeakReason::Ttl => "ttl",
+            BodyStreamLeakReason::MaxStreams => "maxStreams",
+        }
+    }
+}
+
+/// An abandoned body stream that was reaped.
+pub struct BodyStreamLeak {
+    pub handle: u64,
+    pub reason: BodyStreamLeakReason,
+    pub group_id: Option<Arc<str>>,
+    /// Time since the response headers arrived.
+    pub age: Duration,
+}
+
+pub type BodyStreamLeakHook = Arc<dyn Fn(BodyStreamLeak) + Send + Sync>;
+
+// Unread streams expire once idle for the configured TTL; every read starts the clock again.
+// A stream with a read waiting on it is not idle, however long the server stays quiet.
+struct BodyStreamExpiry;
+
+impl Expiry<u64, Arc<BodyStreamEntry>> for BodyStreamExpiry {
+    fn expire_after_create(
+        &self,
+        _handle: &u64,
+        _entry: &Arc<BodyStreamEntry>,
+        _created_at: std::time::Instant,
+    ) -> Option<Duration> {
+        Some(body_stream_ttl())
+    }
+
+    fn expire_after_read(
+        &self,
+        _handle: &u64,
+        entry: &Arc<BodyStreamEntry>,
+        _read_at: std::time::Instant,
+        _duration_until_expiry: Option<Duration>,
+        _last_modified_at: std::time::Instant,
+    ) -> Option<Duration> {
+        if entry.reading() {
+            None
+        } else {
+            Some(body_stream_ttl())
+        }
+    }
+}
+
+fn body_stream_ttl() -> Duration {
+    Duration::from_millis(BODY_STREAM_TTL_MS.load(Ordering::Relaxed))
+}
+
+/// Reap body streams that go unread for `ttl`. Applies to streams the next time they are stored
+/// or read.
+pub fn set_body_stream_ttl(ttl: Duration) {
+    let millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+    BODY_STREAM_TTL_MS.store(millis.max(1), Ordering::Relaxed);
+}
+
+/// Cap the number of unread body streams; the oldest are reaped when a new one goes over.
+pub fn set_max_body_streams(max: Option<usize>) {
+    MAX_BODY_STREAMS.store(max.unwrap_or(0), Ordering::Relaxed);
+    enforce_max_body_streams();
+}
+
+/// Install (or remove) the hook told about every reaped body stream.
+pub fn set_body_stream_leak_hook(hook: Option<BodyStreamLeakHook>) {
+    *BODY_STREAM_LEAK_HOOK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = hook;
+}
+
+fn report_body_stream_leak(handle: u64, entry: &BodyStreamEntry, reason: BodyStreamLeakReason) {
+    let hook = BODY_STREAM_LEAK_HOOK
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    if let Some(hook) = hook {
+        hook(BodyStreamLeak {
+            handle,
+            reason,
+            group_id: entry.group_id.clone(),
+            age: entry.created_at.elapsed(),
+        });
+    }
+}
+
+fn enforce_max_body_streams() {
+    let max = MAX_BODY_STREAMS.load(Ordering::Relaxed);
+    if max == 0 {
+        return;
+    }
+    // Streams being read count towards the limit but are never the ones reaped.
+    let (reading, idle): (Vec<_>, Vec<_>) =
+        BODY_STREAMS.iter().partition(|(_, entry)| entry.reading());
+    let idle = idle.into_iter().map(|(handle, _)| *handle).collect();
+    for handle in handles_over_limit(idle, max.saturating_sub(reading.len())) {
+        if let Some(entry) = BODY_STREAMS.remove(&handle) {
+            report_body_stream_leak(handle, &entry, BodyStreamLeakReason::MaxStreams);
+        }
+    }
+}
+
+// Handles are allocated in increasing order, so the smallest ones belong to the oldest streams.
+fn handles_over_limit(mut handles: Vec<u64>, max: usize) -> Vec<u64> {
+    if handles.len() <= max {
+        return Vec::new();
+    }
+    handles.sort_unstable();
+    handles.truncate(handles.len() - max);
+    handles
+}
+// Digests of fully read body streams, waiting to be collected by `take_body_digest`.
+static BODY_DIGESTS: LazyLock<Cache<u64, String>> = LazyLock::new(|| {
+    Cache::builder()
+        .time_to_idle(Duration::from_secs(300))
+        .build()
+});
+// Trailers of fully read body streams, waiting to be collected by `take_body_trailers`.
+static BODY_TRAILERS: LazyLock<Cache<u64, Vec<(String, String)>>> = LazyLock::new(|| {
+    Cache::builder()
+        .time_to_idle(Duration::from_secs(300))
+        .build()
+});
+// Sizes of fully read body streams, waiting to be collected by `take_body_size`.
+static BODY_SIZES: LazyLock<Cache<u64, BodySize>> = LazyLock::new(|| {
     Cache::builder()
         .time_to_idle(Duration::from_secs(300))
         .build()
@@ -203,48 +1072,131 @@ fn next_body_handle() -> u64 {
     NEXT_BODY_HANDLE.fetch_add(1, Ordering::Relaxed)
 }
 
-pub fn store_body_stream(stream: ResponseBodyStream) -> u64 {
+pub fn store_body_stream(
+    stream: ResponseBodyStream,
+    group_id: Option<Arc<str>>,
+    digest: Option<BodyDigest>,
+    trailers: TrailerSlot,
+    size: BodySizeSlot,
+    deadline: Option<tokio::time::Instant>,
+    phase: BodyPhase,
+) -> u64 {
     let handle = next_body_handle();
-    BODY_STREAMS.insert(handle, Arc::new(Mutex::new(stream)));
+    let entry = BodyStreamEntry {
+        stream: Mutex::new(stream.peekable()),
+        group_id,
+        tee: std::sync::Mutex::new(None),
+        digest: std::sync::Mutex::new(digest),
+        trailers,
+        size,
+        deadline,
+        phase,
+        created_at: std::time::Instant::now(),
+    };
+    BODY_STREAMS.insert(handle, Arc::new(entry));
+    enforce_max_body_streams();
     handle
 }
 
-pub async fn read_body_chunk(handle: u64) -> Result<Option<Bytes>> {
+pub async fn read_body_chunk(handle: u64) -> Result<Option<BodyChunk>> {
     let stream = BODY_STREAMS
         .get(&handle)
         .ok_or_else(|| anyhow!("Body handle {} not found", handle))?;
 
-    let mut guard = stream.lock().await;
-    let next = guard.next().await;
+    let mut guard = stream.stream.lock().await;
+    // Looked up again now that the read holds the stream, so it cannot expire while waiting.
+    BODY_STREAMS.get(&handle);
+    let next = match within(stream.deadline, async { Ok(guard.next().await) }).await {
+        Ok(next) => next,
+        Err(err) => {
+            BODY_STREAMS.invalidate(&handle);
+            return Err(stream.phase.failed(err));
+        }
+    };
 
     match next {
-        Some(Ok(bytes)) => Ok(Some(bytes)),
+        Some(Ok(bytes)) => {
+            let chunk = batch_ready_chunks(&mut guard, bytes);
+            if let Err(err) = stream
+                .write_tee(&chunk)
+                .and_then(|()| stream.update_digest(&chunk))
+            {
+                BODY_STREAMS.invalidate(&handle);
+                return Err(err);
+            }
+            drop(guard);
+            // Start the TTL again from the end of this read.
+            BODY_STREAMS.get(&handle);
+            Ok(Some(chunk))
+        }
         Some(Err(err)) => {
             BODY_STREAMS.invalidate(&handle);
-            Err(err.into())
+            Err(stream.phase.failed(err))
         }
         None => {
             BODY_STREAMS.invalidate(&handle);
+            stream.finish_trailers(handle);
+            stream.finish_size(handle);
+            stream.finish_digest(handle)?;
             Ok(None)
         }
     }
 }
 
+/// Append the chunks `stream` already has ready to `first`, up to [`BODY_CHUNK_SIZE`], so bodies
+/// arriving in many small frames reach JS in fewer reads. Never waits for more data; errors and
+/// chunks that do not fit are left for the next read.
+fn batch_ready_chunks(stream: &mut Peekable<ResponseBodyStream>, first: Bytes) -> BodyChunk {
+    let mut batch: Option<PooledBuffer> = None;
+    let mut len = first.len();
+    loop {
+        let room = BODY_CHUNK_SIZE.saturating_sub(len);
+        let ready = Pin::new(&mut *stream)
+            .next_if(move |item| matches!(item, Ok(bytes) if bytes.len() <= room))
+            .now_or_never();
+        let Some(Some(Ok(bytes))) = ready else {
+            break;
+        };
+        let buffer = batch.get_or_insert_with(|| {
+            let mut buffer = BODY_CHUNK_POOL.take();
+            buffer.extend_from_slice(&first);
+            buffer
+        });
+        buffer.extend_from_slice(&bytes);
+        len += bytes.len();
+    }
+
+    match batch {
+        Some(buffer) => BodyChunk::Batched(buffer),
+        None => BodyChunk::Single(first),
+    }
+}
+
 /// Read entire body into a single buffer. More efficient than streaming for small bodies.
 pub async fn read_body_all(handle: u64) -> Result<Bytes> {
     let stream = BODY_STREAMS
         .remove(&handle)
         .ok_or_else(|| anyhow!("Body handle {} not found", handle))?;
 
-    let mut guard = stream.lock().await;
+    let mut guard = stream.stream.lock().await;
     let mut chunks: Vec<Bytes> = Vec::new();
     let mut total_len = 0usize;
 
-    while let Some(result) = guard.next().await {
-        let bytes = result?;
-        total_len += bytes.len();
-        chunks.push(bytes);
-    }
+    within(stream.deadline, async {
+        while let Some(result) = guard.next().await {
+            let bytes = result?;
+            stream.write_tee(&bytes)?;
+            stream.update_digest(&bytes)?;
+            total_len += bytes.len();
+            chunks.push(bytes);
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|err| stream.phase.failed(err))?;
+    stream.finish_trailers(handle);
+    stream.finish_size(handle);
+    stream.finish_digest(handle)?;
 
     // Fast path: single chunk or empty
     if chunks.is_empty() {
@@ -262,10 +1214,163 @@ pub async fn read_body_all(handle: u64) -> Result<Bytes> {
     Ok(Bytes::from(buf))
 }
 
+/// Copy every chunk read from now on into `path`, truncating any existing file.
+pub fn tee_body_to_file(handle: u64, path: &str) -> Result<()> {
+    let stream = BODY_STREAMS
+        .get(&handle)
+        .ok_or_else(|| anyhow!("Body handle {} not found", handle))?;
+
+    let mut tee = stream
+        .tee
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(existing) = tee.as_ref() {
+        bail!(
+            "Body handle {} is already being written to '{}'",
+            handle,
+            existing.path
+        );
+    }
+
+    let file = File::create(path).with_context(|| format!("Failed to create '{}'", path))?;
+    *tee = Some(BodyTee {
+        file,
+        path: path.to_owned(),
+    });
+    Ok(())
+}
+
+/// Collect the digest of a body stream that has been read to the end.
+pub fn take_body_digest(handle: u64) -> Option<String> {
+    BODY_DIGESTS.remove(&handle)
+}
+
+/// Collect the trailers of a body stream that has been read to the end.
+pub fn take_body_trailers(handle: u64) -> Option<Vec<(String, String)>> {
+    BODY_TRAILERS.remove(&handle)
+}
+
+/// Collect the size of a body stream that has been read to the end.
+pub fn take_body_size(handle: u64) -> Option<BodySize> {
+    BODY_SIZES.remove(&handle)
+}
+
 pub fn drop_body_stream(handle: u64) {
     BODY_STREAMS.invalidate(&handle);
 }
 
+/// Whether `handle` is a body stream that has not ended, been dropped, or expired.
+pub fn body_stream_open(handle: u64) -> bool {
+    BODY_STREAMS.contains_key(&handle)
+}
+
+/// Drop every unread body stream that belongs to the given cancellation group.
+pub fn drop_body_streams_in_group(group_id: &str) {
+    let handles: Vec<u64> = BODY_STREAMS
+        .iter()
+        .filter(|(_, entry)| entry.group_id.as_deref() == Some(group_id))
+        .map(|(handle, _)| *handle)
+        .collect();
+
+    for handle in handles {
+        BODY_STREAMS.invalidate(&handle);
+    }
+}
+
+fn body_stream_count() -> u64 {
+    BODY_STREAMS.run_pending_tasks();
+    BODY_STREAMS.entry_count()
+}
+
+/// Sizes of the native registries owned by the HTTP client.
+pub struct ClientStats {
+    pub body_streams: u64,
+    pub in_flight_requests: usize,
+    pub sessions: u64,
+    pub transports: usize,
+    pub ephemeral_clients: u64,
+    pub pooled_clients: u64,
+    /// Approximate bytes held in body chunk buffers, including those lent to JS.
+    pub buffered_bytes: usize,
+}
+
+pub fn client_stats() -> ClientStats {
+    SESSION_MANAGER.cache.run_pending_tasks();
+    EPHEMERAL_MANAGER.cache.run_pending_tasks();
+    ORIGIN_POOL.cache.run_pending_tasks();
+    ClientStats {
+        body_streams: body_stream_count(),
+        in_flight_requests: IN_FLIGHT_REQUESTS.load(Ordering::Acquire),
+        sessions: SESSION_MANAGER.cache.entry_count(),
+        transports: TRANSPORT_MANAGER.explicit.len(),
+        ephemeral_clients: EPHEMERAL_MANAGER.cache.entry_count(),
+        pooled_clients: ORIGIN_POOL.cache.entry_count(),
+        buffered_bytes: BODY_CHUNK_POOL.retained_bytes(),
+    }
+}
+
+/// Decrements the in-flight request counter when a request future completes or is dropped.
+struct InFlightGuard;
+
+impl InFlightGuard {
+    fn acquire() -> Self {
+        IN_FLIGHT_REQUESTS.fetch_add(1, Ordering::AcqRel);
+        Self
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT_REQUESTS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Fail fast when the client has been shut down.
+pub(crate) fn ensure_accepting() -> Result<()> {
+    if SHUTTING_DOWN.load(Ordering::Acquire) {
+        return Err(anyhow!("Client is shutting down"));
+    }
+    Ok(())
+}
+
+/// Stop accepting new work. Shutdown cannot be undone for the lifetime of the process.
+pub fn begin_shutdown() {
+    SHUTTING_DOWN.store(true, Ordering::Release);
+}
+
+/// Whether in-flight requests and unread body streams have drained.
+pub fn requests_drained() -> bool {
+    IN_FLIGHT_REQUESTS.load(Ordering::Acquire) == 0 && body_stream_count() == 0
+}
+
+fn current_request_epoch() -> CancellationToken {
+    REQUEST_EPOCH
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Abort every in-flight request and drop all unread body streams.
+pub fn cancel_all_requests() {
+    let previous = {
+        let mut epoch = REQUEST_EPOCH
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *epoch, CancellationToken::new())
+    };
+    previous.cancel();
+    BODY_STREAMS.invalidate_all();
+}
+
+/// Abort outstanding requests and release every native registry owned by the client.
+pub fn release_client_resources() {
+    cancel_all_requests();
+    EPHEMERAL_MANAGER.cache.invalidate_all();
+    ORIGIN_POOL.cache.invalidate_all();
+    SESSION_MANAGER.cache.invalidate_all();
+    TRANSPORT_MANAGER.explicit.clear();
+}
+
 impl TransportManager {
     fn new() -> Self {
         Self {
@@ -273,19 +1378,87 @@ impl TransportManager {
         }
     }
 
-    fn create_transport(&self, config: TransportConfig) -> Result<String> {
-        let client = Arc::new(build_client(&config)?);
-        let entry = Arc::new(TransportEntry { client });
+    fn create_transport(
+        &self,
+        config: TransportConfig,
+        egress: Option<Arc<EgressPolicy>>,
+        ntlm_proxy: Option<Arc<NtlmProxy>>,
+    ) -> Result<String> {
+        let proxy_rotation = match &config.proxy_pool {
+            Some(pool) => Some(Arc::new(ProxyRotation::new(
+                build_proxy_clients(&config, pool)?,
+                pool.strategy,
+            ))),
+            None => None,
+        };
+        let client = match &proxy_rotation {
+            Some(rotation) => rotation.current().1.clone(),
+            None => Arc::new(build_client(&config)?),
+        };
+        let download_limiter = config
+            .max_download_bytes_per_sec
+            .map(|rate| Arc::new(RateLimiter::new(rate)));
+        let upload_limiter = config
+            .max_upload_bytes_per_sec
+            .map(|rate| Arc::new(RateLimiter::new(rate)));
+        let entry = Arc::new(TransportEntry {
+            client,
+            config,
+            alt_svc: Arc::new(AltSvcCache::default()),
+            download_limiter,
+            upload_limiter,
+            egress,
+            proxy_rotation,
+            built_at: std::time::Instant::now(),
+            ntlm_proxy,
+            derived_clients: derived_client_cache(),
+        });
         let id = Uuid::new_v4().to_string();
         self.explicit.insert(id.clone(), entry);
         Ok(id)
     }
 
-    fn get_transport(&self, transport_id: &str) -> Result<Arc<HttpClient>> {
+    /// Swap in a freshly built client so cached TLS sessions (and pooled connections) are discarded.
+    fn reset_transport(&self, transport_id: &str) -> Result<()> {
+        let entry = self.get_entry(transport_id)?.rebuilt()?;
         self.explicit
+            .insert(transport_id.to_string(), Arc::new(entry));
+        Ok(())
+    }
+
+    fn get_entry(&self, transport_id: &str) -> Result<Arc<TransportEntry>> {
+        let entry = self
+            .explicit
             .get(transport_id)
-            .map(|entry| entry.client.clone())
-            .ok_or_else(|| anyhow!("Transport '{}' not found", transport_id))
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| anyhow!("Transport '{}' not found", transport_id))?;
+        if entry.expired() {
+            return self.renew_transport(transport_id, &entry);
+        }
+        Ok(entry)
+    }
+
+    /// Retire a transport's connections once they reach `maxConnectionAge`. Requests already
+    /// running keep the old clients until they finish; the connections close with them.
+    fn renew_transport(
+        &self,
+        transport_id: &str,
+        expired: &Arc<TransportEntry>,
+    ) -> Result<Arc<TransportEntry>> {
+        let renewed = Arc::new(expired.rebuilt()?);
+        match self.explicit.get_mut(transport_id) {
+            Some(mut current) if Arc::ptr_eq(&current, expired) => {
+                *current = renewed.clone();
+                Ok(renewed)
+            }
+            // Another request renewed it (or it was reset) first.
+            Some(current) => Ok(current.clone()),
+            None => Err(anyhow!("Transport '{}' not found", transport_id)),
+        }
+    }
+
+    fn get_transport(&self, transport_id: &str) -> Result<Arc<HttpClient>> {
+        Ok(self.get_entry(transport_id)?.current_client())
     }
 
     fn drop_transport(&self, transport_id: &str) {
@@ -309,14 +1482,75 @@ impl SessionManager {
 
         let entry = Arc::new(SessionEntry {
             cookie_jar: Arc::new(Jar::default()),
+            hsts: None,
+            on_set_cookie: None,
+            auth: None,
+            client_hints: None,
+            pacer: None,
         });
         self.cache.insert(session_id.to_string(), entry.clone());
         Ok(entry.cookie_jar.clone())
     }
 
-    fn create_session(&self, session_id: String) -> Result<String> {
+    /// The jar requests of the session store cookies in, reporting each one to the session's
+    /// `onSetCookie` hook when it has one.
+    fn cookie_store_for(&self, session_id: &str) -> Result<Arc<dyn CookieStore>> {
+        let jar = self.jar_for(session_id)?;
+        let hook = self
+            .cache
+            .get(session_id)
+            .and_then(|entry| entry.on_set_cookie.clone());
+        Ok(match hook {
+            Some(hook) => Arc::new(NotifyingJar::new(jar, hook)),
+            None => jar,
+        })
+    }
+
+    fn hsts_for(&self, session_id: &str) -> Option<Arc<HstsStore>> {
+        self.cache
+            .get(session_id)
+            .and_then(|entry| entry.hsts.clone())
+    }
+
+    fn require_hsts(&self, session_id: &str) -> Result<Arc<HstsStore>> {
+        let entry = self
+            .cache
+            .get(session_id)
+            .ok_or_else(|| anyhow!("Session '{}' not found", session_id))?;
+        entry
+            .hsts
+            .clone()
+            .ok_or_else(|| anyhow!("Session '{}' does not have HSTS enabled", session_id))
+    }
+
+    fn client_hints_for(&self, session_id: &str) -> Option<Arc<ClientHintsStore>> {
+        self.cache
+            .get(session_id)
+            .and_then(|entry| entry.client_hints.clone())
+    }
+
+    fn pacer_for(&self, session_id: &str) -> Option<Arc<Pacer>> {
+        self.cache
+            .get(session_id)
+            .and_then(|entry| entry.pacer.clone())
+    }
+
+    fn auth_for(&self, session_id: &str) -> Option<Arc<SessionAuth>> {
+        self.cache
+            .get(session_id)
+            .and_then(|entry| entry.auth.clone())
+    }
+
+    fn create_session(&self, session_id: String, options: SessionOptions) -> Result<String> {
         let entry = Arc::new(SessionEntry {
             cookie_jar: Arc::new(Jar::default()),
+            hsts: options.hsts.then(|| Arc::new(HstsStore::default())),
+            on_set_cookie: options.on_set_cookie,
+            auth: options.auth,
+            client_hints: options
+                .accept_client_hints
+                .then(|| Arc::new(ClientHintsStore::default())),
+            pacer: options.pacing.map(|pacing| Arc::new(Pacer::new(pacing))),
         });
         self.cache.insert(session_id.clone(), entry);
         Ok(session_id)
@@ -334,6 +1568,44 @@ impl SessionManager {
     fn drop_session(&self, session_id: &str) {
         self.cache.invalidate(session_id);
     }
+
+    fn token(&self, session_id: &str) -> Option<SessionToken> {
+        self.cache
+            .get(session_id)
+            .map(|entry| SessionToken(Arc::downgrade(&entry)))
+    }
+
+    fn release_session(&self, session_id: &str, token: &SessionToken) -> bool {
+        let current = self
+            .cache
+            .get(session_id)
+            .is_some_and(|entry| std::ptr::eq(Arc::as_ptr(&entry), token.0.as_ptr()));
+        if current {
+            self.cache.invalidate(session_id);
+        }
+        current
+    }
+}
+
+impl OriginPoolManager {
+    fn new() -> Self {
+        Self {
+            cache: Cache::builder()
+                .time_to_idle(Duration::from_secs(300))
+                .build(),
+        }
+    }
+
+    fn client_for(&self, options: &RequestOptions) -> Result<Arc<HttpClient>> {
+        let key = OriginPoolKey::from_request(options)?;
+        if let Some(client) = self.cache.get(&key) {
+            return Ok(client);
+        }
+
+        let client = Arc::new(build_client(&TransportConfig::from_request(options))?);
+        self.cache.insert(key, client.clone());
+        Ok(client)
+    }
 }
 
 impl EphemeralClientManager {
@@ -357,34 +1629,599 @@ impl EphemeralClientManager {
 }
 
 pub async fn make_request(options: RequestOptions) -> Result<Response> {
-    let transport_id = options.transport_id.clone();
+    ensure_accepting()?;
+    let _in_flight = InFlightGuard::acquire();
+    let epoch = current_request_epoch();
+    let audit = AuditedRequest::capture(&options);
+
+    let result = tokio::select! {
+        _ = epoch.cancelled() => {
+            ensure_accepting()?;
+            Err(Aborted { reason: None }.into())
+        }
+        result = make_request_with_auth(options) => result,
+    };
 
-    // Resolve client: explicit transport > ephemeral cache > fresh client
-    let client = if let Some(ref tid) = transport_id {
-        TRANSPORT_MANAGER.get_transport(tid)?
-    } else if options.ephemeral {
-        let config = SessionConfig::from_request(&options);
-        EPHEMERAL_MANAGER.client_for(config)?
+    if let Some(audit) = audit {
+        audit.finish(&result);
+    }
+    result
+}
+
+/// Send a session request with the session's bearer token. When the server answers 401, the
+/// token is refreshed once, shared with concurrent requests that failed with the same token, and
+/// the request is sent again. Requests with their own `Authorization` header are left alone.
+async fn make_request_with_auth(mut options: RequestOptions) -> Result<Response> {
+    let auth = if options.ephemeral {
+        None
+    } else {
+        SESSION_MANAGER.auth_for(&options.session_id)
+    };
+    let has_authorization = options
+        .headers
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case("authorization"));
+    let Some(auth) = auth.filter(|_| !has_authorization) else {
+        return make_request_with_ntlm(options).await;
+    };
+
+    let started = std::time::Instant::now();
+    let token = match auth.token() {
+        Some(token) => token,
+        None => refresh_access_token(&auth, None, &options).await?,
+    };
+    let mut retry = options.clone();
+    options.headers.push(bearer(&token)?);
+    let response = make_request_with_ntlm(options).await?;
+    if response.status != 401 {
+        return Ok(response);
+    }
+
+    if let Some(handle) = response.body_handle {
+        drop_body_stream(handle);
+    }
+    let token = refresh_access_token(&auth, Some(&token), &retry).await?;
+    retry.headers.push(bearer(&token)?);
+    // The total deadline covers the refresh and the replay.
+    shrink_total_deadline(&mut retry, started);
+    make_request_with_ntlm(retry).await
+}
+
+fn bearer(token: &str) -> Result<(String, HeaderValue)> {
+    let value = HeaderValue::from_str(&format!("Bearer {}", token))
+        .context("Access token is not a valid header value")?;
+    Ok(("authorization".to_owned(), value))
+}
+
+/// Answer an NTLM (or Negotiate) 401 with the request's Windows credentials. NTLM authenticates
+/// a connection rather than a request, so the handshake goes through a client of its own that
+/// keeps its legs on one HTTP/1.1 connection. Requests with their own `Authorization` header
+/// are left alone.
+async fn make_request_with_ntlm(options: RequestOptions) -> Result<Response> {
+    let has_authorization = options
+        .headers
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case("authorization"));
+    let Some(credentials) = options.ntlm.clone().filter(|_| !has_authorization) else {
+        return make_request_following_refresh(options).await;
+    };
+
+    let started = std::time::Instant::now();
+    let retry = options.clone();
+    let response = make_request_following_refresh(options).await?;
+    let scheme = match NtlmScheme::offered(challenges(&response)) {
+        Some(scheme) if response.status == 401 => scheme,
+        _ => return Ok(response),
+    };
+    if let Some(handle) = response.body_handle {
+        drop_body_stream(handle);
+    }
+
+    let client = ntlm_client(&retry)?;
+    let mut negotiate = retry.clone();
+    negotiate
+        .headers
+        .push(authorization(negotiate_header(scheme))?);
+    shrink_total_deadline(&mut negotiate, started);
+    let mut challenge = send_request(negotiate, Some(client.clone())).await?;
+    if challenge.status != 401 {
+        return Ok(challenge);
+    }
+    let answer = authenticate_header(&credentials, scheme, challenges(&challenge))?;
+    // Read the challenge to its end so its connection is free for the last leg.
+    buffer_body(&mut challenge).await?;
+
+    let mut authenticate = retry;
+    authenticate.headers.push(authorization(answer)?);
+    shrink_total_deadline(&mut authenticate, started);
+    send_request(authenticate, Some(client)).await
+}
+
+fn challenges(response: &Response) -> impl Iterator<Item = &str> {
+    response
+        .headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("www-authenticate"))
+        .map(|(_, value)| value.as_str())
+}
+
+fn authorization(value: String) -> Result<(String, HeaderValue)> {
+    let value = HeaderValue::from_str(&value).context("Invalid NTLM authorization header")?;
+    Ok(("authorization".to_owned(), value))
+}
+
+/// What `options` changes about the client of its transport `entry`.
+fn transport_overrides(
+    entry: &TransportEntry,
+    options: &RequestOptions,
+    connect_to: Option<ConnectTo>,
+) -> ClientOverrides {
+    let mut overrides = ClientOverrides::for_request(options, connect_to);
+    // Through a proxy the target is never looked up here, so the rule has no effect.
+    if entry.proxy_for(&overrides).is_some() {
+        overrides.connect_to = None;
+    }
+    overrides
+}
+
+/// The cached h2 alternative for `url` on the transport `entry`, if its egress policy allows it.
+fn alternative_route(entry: &TransportEntry, url: &str) -> Option<(AltSvcEntry, ConnectTo)> {
+    let uri: wreq::Uri = url.parse().ok()?;
+    let (alternative, rule) = entry.alt_svc.route(&uri)?;
+    if let Some(policy) = entry.egress.as_deref() {
+        let host = if alternative.host.contains(':') {
+            format!("[{}]", alternative.host)
+        } else {
+            alternative.host.clone()
+        };
+        let target = format!("https://{}:{}/", host, alternative.port);
+        policy.check_url(&target).ok()?;
+    }
+    Some((alternative, rule))
+}
+
+/// Count the total deadline of a follow-up request from `started`, when the first one was sent.
+fn shrink_total_deadline(options: &mut RequestOptions, started: std::time::Instant) {
+    if let Some(total) = options.total_deadline {
+        let elapsed = started.elapsed().as_millis() as u64;
+        options.total_deadline = Some(total.saturating_sub(elapsed).max(1));
+    }
+}
+
+/// A client for one NTLM handshake, with the request's transport settings and proxy.
+fn ntlm_client(options: &RequestOptions) -> Result<Arc<HttpClient>> {
+    let config = match options.transport_id.as_deref() {
+        Some(tid) => {
+            let entry = TRANSPORT_MANAGER.get_entry(tid)?;
+            let overrides = transport_overrides(&entry, options, request_connect_to(options));
+            TransportConfig {
+                proxy: entry.proxy_for(&overrides),
+                proxy_pool: None,
+                ..entry.derived_config(&overrides)
+            }
+        }
+        None => TransportConfig::from_request(options),
+    };
+    let client = client_builder(&config)?
+        .http1_only()
+        .build()
+        .context("Failed to build HTTP client")?;
+    Ok(Arc::new(client))
+}
+
+/// Get a new access token for `auth`, from its token endpoint through the request's transport
+/// and proxy, or from its JS callback.
+async fn refresh_access_token(
+    auth: &SessionAuth,
+    stale: Option<&str>,
+    request: &RequestOptions,
+) -> Result<String> {
+    auth.refresh(stale, |refresh_token| async move {
+        match auth.source() {
+            TokenSource::Callback(hook) => {
+                let (sender, receiver) = tokio::sync::oneshot::channel();
+                hook(sender);
+                receiver
+                    .await
+                    .map_err(|_| anyhow!("Refresh callback never answered"))?
+            }
+            TokenSource::Endpoint(endpoint) => {
+                let mut options = RequestOptions::new(
+                    endpoint.url.clone(),
+                    request.emulation,
+                    request.emulation_os,
+                );
+                options.method = "POST".to_string();
+                options.headers = vec![
+                    (
+                        "content-type".to_owned(),
+                        HeaderValue::from_static("application/x-www-form-urlencoded"),
+                    ),
+                    (
+                        "accept".to_owned(),
+                        HeaderValue::from_static("application/json"),
+                    ),
+                ];
+                options.body = Some(endpoint.request_body(refresh_token.as_deref()));
+                options.proxy = request.proxy.clone();
+                options.direct = request.direct;
+                options.insecure = request.insecure;
+                options.transport_id = request.transport_id.clone();
+                options.timeout = request.timeout;
+
+                let mut response = make_request_with_client(options).await?;
+                let body = buffer_body(&mut response).await?;
+                parse_token_response(response.status, &body)
+            }
+        }
+    })
+    .await
+}
+
+/// Send the request and, when it asked for it, follow `<meta http-equiv="refresh">` pages as if
+/// they were redirects. HTML responses are buffered to look for the tag, and each hop is added
+/// to the redirect history of traced requests.
+async fn make_request_following_refresh(options: RequestOptions) -> Result<Response> {
+    let Some(max_delay) = options
+        .meta_refresh_max_delay
+        .filter(|_| matches!(options.redirect, RedirectMode::Follow))
+    else {
+        return make_request_with_client(options).await;
+    };
+
+    let started = std::time::Instant::now();
+    let url = options.url.clone();
+    let total_deadline = options.total_deadline;
+    // Like a 303, the page the refresh points to is fetched with a plain GET.
+    let mut next = options.clone();
+    next.method = "GET".to_string();
+    next.body = None;
+
+    let mut response = make_request_with_client(options).await?;
+    let mut hops = 0;
+    loop {
+        let html = response
+            .headers
+            .iter()
+            .any(|(key, value)| key == "content-type" && is_html(value));
+        if !html {
+            return Ok(response);
+        }
+        let body = buffer_body(&mut response).await?;
+        let Some(target) = refresh_target(&body, &response.url, max_delay) else {
+            return Ok(response);
+        };
+
+        hops += 1;
+        if hops > MAX_META_REFRESH_HOPS {
+            bail!("Too many redirects following {}", url);
+        }
+        next.url = target.clone();
+        // The total deadline covers every hop, as it does for HTTP redirects.
+        if let Some(total) = total_deadline {
+            let elapsed = started.elapsed().as_millis() as u64;
+            next.total_deadline = Some(total.saturating_sub(elapsed).max(1));
+        }
+
+        let mut following = make_request_with_client(next.clone()).await?;
+        if let (Some(previous), Some(timing)) = (response.timing.take(), following.timing.as_mut())
+        {
+            let mut redirects = previous.redirects;
+            redirects.push(RedirectHop {
+                status: response.status,
+                location: target,
+                received_at: previous.response_start,
+            });
+            redirects.append(&mut timing.redirects);
+            timing.redirects = redirects;
+            timing.start = previous.start;
+        }
+        response = following;
+    }
+}
+
+/// Read a streamed body into `body_bytes`, along with what the stream reports at its end.
+async fn buffer_body(response: &mut Response) -> Result<Bytes> {
+    match response.body_handle.take() {
+        Some(handle) => {
+            let bytes = read_body_all(handle).await?;
+            response.content_length = Some(bytes.len() as u64);
+            response.body_bytes = Some(bytes.clone());
+            response.body_digest = take_body_digest(handle);
+            response.trailers = take_body_trailers(handle);
+            response.body_size = take_body_size(handle);
+            Ok(bytes)
+        }
+        None => Ok(response.body_bytes.clone().unwrap_or_default()),
+    }
+}
+
+async fn make_request_with_client(options: RequestOptions) -> Result<Response> {
+    send_request(options, None).await
+}
+
+/// Send the request through its transport, session or ephemeral client, or through `pinned`
+/// when a handshake needs its legs on one client.
+async fn send_request(
+    mut options: RequestOptions,
+    pinned: Option<Arc<HttpClient>>,
+) -> Result<Response> {
+    let method = if options.method.is_empty() {
+        "GET"
     } else {
-        let config = TransportConfig::from_request(&options);
-        Arc::new(build_client(&config)?)
+        options.method.as_str()
     };
+    if let Some(mock) = match_mock(method, &options.url) {
+        return mocked_response(options, mock).await;
+    }
+
+    let cassette = active_cassette();
+    if let Some(cassette) = cassette
+        .as_deref()
+        .filter(|cassette| cassette.mode() == CassetteMode::Replay)
+    {
+        let body = options.body.as_deref().unwrap_or_default();
+        let replayed = cassette.replay(method, &options.url, body)?;
+        return canned_response(
+            options,
+            replayed.url,
+            replayed.status,
+            replayed.headers,
+            replayed.body,
+        );
+    }
+
+    let client_hints = if options.ephemeral {
+        None
+    } else {
+        SESSION_MANAGER.client_hints_for(&options.session_id)
+    };
+    let requested_hints = client_hints
+        .as_deref()
+        .map(|store| store.requested(&options.url))
+        .unwrap_or_default();
+    let connect_to = ConnectTo::select(&options.connect_to, &options.url)?;
+    let transport_entry = match options.transport_id.as_deref() {
+        Some(tid) => Some(TRANSPORT_MANAGER.get_entry(tid)?),
+        None => None,
+    };
+    let hints = ClientHints::merged(
+        options.client_hints.as_ref(),
+        transport_entry
+            .as_ref()
+            .and_then(|entry| entry.config.client_hints.as_ref()),
+    );
+
+    let mut profile_defaults = None;
+    if (!options.append_headers.is_empty()
+        || !options.remove_headers.is_empty()
+        || hints.is_some()
+        || !requested_hints.is_empty()
+        || options.mode.is_some()
+        || options.before_send.is_some())
+        && !options.disable_default_headers
+    {
+        let (emulation, emulation_os) = match transport_entry.as_ref() {
+            Some(entry) => (entry.config.emulation, entry.config.emulation_os),
+            None => (options.emulation, options.emulation_os),
+        };
+        let mut defaults = profile_headers(emulation, emulation_os);
+        if let Some(mode) = options.mode {
+            mode.apply(&mut defaults, &options.url, &options.headers);
+        }
+        add_client_hints(
+            &mut options.headers,
+            &options.remove_headers,
+            &hints.unwrap_or_default(),
+            &defaults,
+            &requested_hints,
+        );
+        append_to_profile_headers(&mut options.headers, &options.append_headers, &defaults);
+        // wreq can only send all of the profile's headers or none, so a trimmed or rewritten
+        // copy is sent by hand instead. So is one a `beforeSend` hook has to see.
+        if !options.remove_headers.is_empty()
+            || options.mode.is_some()
+            || options.before_send.is_some()
+        {
+            for name in &options.remove_headers {
+                defaults.remove(name.as_str());
+            }
+            profile_defaults = Some(defaults);
+        }
+    }
+
+    // Capture the request as sent before the options are consumed.
+    let recording = cassette
+        .filter(|cassette| cassette.mode() == CassetteMode::Record)
+        .map(|cassette| {
+            (
+                cassette,
+                method.to_owned(),
+                options.url.clone(),
+                text_headers(&options.headers),
+                options.body.clone(),
+            )
+        });
+
+    // The cassette stores the headers even when the caller will not read them.
+    if recording.is_some() {
+        options.minimal_response = false;
+    }
+
+    let transport_id = options.transport_id.clone();
+
+    // Resolve client: explicit transport > ephemeral cache > origin pool > fresh client
+    let mut proxy_slot = None;
+    let mut alternative = None;
+    let (client, client_kind, alt_svc, download_limiter, upload_limiter, egress, transport_entry) =
+        if let Some(ref tid) = transport_id {
+            let entry = TRANSPORT_MANAGER.get_entry(tid)?;
+            // A cached h2 alternative is reached like a connectTo rule; the request's own wins.
+            let routed = if connect_to.is_none() && pinned.is_none() {
+                alternative_route(&entry, &options.url)
+            } else {
+                None
+            };
+            let overrides = transport_overrides(
+                &entry,
+                &options,
+                connect_to.or_else(|| routed.as_ref().map(|(_, rule)| rule.clone())),
+            );
+            // Through a proxy the rule is dropped, and so is the alternative.
+            alternative = routed
+                .filter(|(_, rule)| overrides.connect_to.as_ref() == Some(rule))
+                .map(|(entry, _)| entry);
+            let client = match &entry.proxy_rotation {
+                _ if !overrides.is_empty() => entry.derived_client(&overrides)?,
+                Some(rotation) => {
+                    let (index, client) = rotation.select();
+                    proxy_slot = Some((rotation.clone(), index));
+                    client.clone()
+                }
+                None => entry.client.clone(),
+            };
+            (
+                client,
+                ClientKind::Transport,
+                Some(entry.alt_svc.clone()),
+                entry.download_limiter.clone(),
+                entry.upload_limiter.clone(),
+                entry.egress.clone(),
+                Some(entry),
+            )
+        } else if options.ephemeral {
+            let config = SessionConfig::from_request(&options);
+            (
+                EPHEMERAL_MANAGER.client_for(config)?,
+                ClientKind::Ephemeral,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        } else if options.origin_pooling {
+            (
+                ORIGIN_POOL.client_for(&options)?,
+                ClientKind::Pooled,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        } else {
+            let config = TransportConfig::from_request(&options);
+            (
+                Arc::new(build_client(&config)?),
+                ClientKind::Dedicated,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+    let client = pinned.unwrap_or(client);
+    // A request's own setting wins over the transport's.
+    let accept_encoding = options.accept_encoding.or_else(|| {
+        transport_entry
+            .as_ref()
+            .and_then(|entry| entry.config.accept_encoding)
+    });
+    let header_limits = transport_entry
+        .map(|entry| entry.config.header_limits)
+        .unwrap_or_default();
 
     // Resolve cookie jar: ephemeral gets a fresh jar, sessions share one
-    let cookie_jar = if options.ephemeral {
-        Arc::new(Jar::default())
+    let (cookie_jar, hsts) = if options.ephemeral {
+        (Arc::new(Jar::default()) as Arc<dyn CookieStore>, None)
     } else {
-        SESSION_MANAGER.jar_for(&options.session_id)?
+        (
+            SESSION_MANAGER.cookie_store_for(&options.session_id)?,
+            SESSION_MANAGER.hsts_for(&options.session_id),
+        )
     };
 
-    make_request_inner(options, client, cookie_jar).await
+    let context = RequestContext {
+        client,
+        client_kind,
+        cookie_jar,
+        hsts,
+        alt_svc,
+        download_limiter,
+        upload_limiter,
+        egress,
+        accept_encoding,
+        header_limits,
+        profile_defaults,
+        client_hints,
+    };
+
+    // Paced sessions wait for their turn with the host before the request's timeouts start.
+    let pacer = if options.ephemeral {
+        None
+    } else {
+        SESSION_MANAGER.pacer_for(&options.session_id)
+    };
+    if let Some(pacer) = pacer {
+        if let Ok(url) = url::Url::parse(&options.url) {
+            pacer.wait(url.host_str().unwrap_or_default()).await;
+        }
+    }
+
+    let alt_svc = context.alt_svc.clone();
+    let result = make_request_inner(options, context).await;
+    // Like browsers, stop using an alternative that cannot be reached; the next request goes to
+    // the origin.
+    if let (Err(error), Some(cache), Some(entry)) = (&result, &alt_svc, &alternative) {
+        let phase = error
+            .downcast_ref::<PhaseFailure>()
+            .map(|failure| failure.phase);
+        if matches!(phase, Some(Phase::Dns | Phase::Connect | Phase::Tls)) {
+            cache.forget(entry);
+        }
+    }
+    // Only network failures count against a pooled proxy; policy and validation errors do not.
+    if let (Err(error), Some((rotation, index))) = (&result, &proxy_slot) {
+        if error.chain().any(|cause| cause.is::<wreq::Error>()) {
+            rotation.report_failure(*index);
+        }
+    }
+    let mut response = result?;
+
+    if let Some((cassette, method, url, headers, body)) = recording {
+        // Recorded responses are buffered so the body can be written to the cassette.
+        let response_body = buffer_body(&mut response).await?;
+        cassette.record(
+            &method,
+            &url,
+            &headers,
+            body.as_deref().unwrap_or_default(),
+            &response,
+            &response_body,
+        )?;
+    }
+
+    Ok(response)
 }
 
-async fn make_request_inner(
-    options: RequestOptions,
-    client: Arc<HttpClient>,
-    cookie_jar: Arc<Jar>,
-) -> Result<Response> {
+async fn make_request_inner(options: RequestOptions, context: RequestContext) -> Result<Response> {
+    let RequestContext {
+        client,
+        client_kind,
+        cookie_jar,
+        hsts,
+        alt_svc,
+        download_limiter,
+        upload_limiter,
+        egress,
+        accept_encoding,
+        header_limits,
+        profile_defaults,
+        client_hints,
+    } = context;
+
     let RequestOptions {
         url,
         headers,
@@ -393,6 +2230,23 @@ async fn make_request_inner(
         timeout,
         redirect,
         disable_default_headers,
+        group_id,
+        max_download_bytes_per_sec,
+        max_upload_bytes_per_sec,
+        transport_id,
+        body_digest,
+        trace,
+        write_timeout,
+        total_deadline,
+        block_insecure_redirects,
+        strip_on_cross_origin,
+        disable_cookies,
+        inline_body_max,
+        decode_body,
+        priority,
+        accept_language,
+        minimal_response,
+        before_send,
         ..
     } = options;
 
@@ -417,103 +2271,510 @@ async fn make_request_inner(
             .with_context(|| format!("Unsupported HTTP method: {}", method))?,
     };
 
+    // Known HSTS hosts are only ever contacted over HTTPS.
+    let url = match hsts.as_deref().and_then(|store| store.upgrade_url(&url)) {
+        Some(upgraded) => upgraded,
+        None => url,
+    };
+
+    if let Some(policy) = egress.as_deref() {
+        policy.check_url(&url)?;
+    }
+
     // Build request
     let mut request = client.request(request_method, &url);
 
+    // Caller headers and the Accept-Encoding, Priority and locale options replace these
+    // defaults, as they would wreq's own.
+    if let Some(mut defaults) = profile_defaults {
+        for (key, _) in headers.iter() {
+            defaults.remove(key.as_str());
+        }
+        if accept_encoding.is_some() {
+            defaults.remove(ACCEPT_ENCODING);
+        }
+        if priority.is_some() {
+            defaults.remove("priority");
+        }
+        if accept_language.is_some() {
+            defaults.remove(ACCEPT_LANGUAGE);
+        }
+        request = request.headers(defaults).default_headers(false);
+    }
+
     // Apply custom headers and preserve their original casing.
     // Without this, wreq's browser emulation title-cases all header names
     // (e.g. "X-ECG-Authorization-User" → "X-Ecg-Authorization-User").
     let mut orig = OrigHeaderMap::new();
     for (key, value) in headers.iter() {
-        request = request.header(key, value);
+        request = request.header(key, value.clone());
         orig.insert(key.clone());
     }
+
+    // Trace context goes on the request itself so redirect hops carry it too.
+    // Explicit traceparent/tracestate headers win over the injected context.
+    if let Some(context) = trace.as_ref() {
+        let has_header = |name: &str| {
+            headers
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case(name))
+        };
+        if !has_header("traceparent") {
+            request = request.header("traceparent", context.traceparent.as_str());
+        }
+        if let Some(state) = context
+            .tracestate
+            .as_deref()
+            .filter(|_| !has_header("tracestate"))
+        {
+            request = request.header("tracestate", state);
+        }
+    }
     request = request.orig_headers(orig);
 
+    // Advertise only the chosen codings, unless the caller wrote the header themselves, and only
+    // decode those; anything else the server sends is passed through as-is. Bodies are decoded
+    // below rather than by wreq so the bytes that crossed the wire can still be counted.
+    let has_accept_encoding = headers
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case("accept-encoding"));
+    if let Some(accept) = accept_encoding.filter(|_| !has_accept_encoding) {
+        request = request.header(ACCEPT_ENCODING, accept.header_value());
+    } else if disable_default_headers && decode_body && !has_accept_encoding {
+        // wreq's decoders would have asked for every coding they handle.
+        request = request.header(ACCEPT_ENCODING, AcceptEncoding::ALL.header_value());
+    }
+    request = request.gzip(false).deflate(false).brotli(false).zstd(false);
+    let decoded_codings = decode_body.then(|| accept_encoding.unwrap_or(AcceptEncoding::ALL));
+
+    // Like Accept-Encoding, an explicit header wins; otherwise the profile's value is replaced
+    // in place so the header order stays the browser's.
+    if let Some(priority) = priority.filter(|_| {
+        !headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("priority"))
+    }) {
+        request = request.header("priority", priority.header_value());
+    }
+    if let Some(language) = accept_language.filter(|_| {
+        !headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("accept-language"))
+    }) {
+        request = request.header(ACCEPT_LANGUAGE, language);
+    }
+
     // Disable default headers if requested to prevent emulation headers from being appended
     if disable_default_headers {
         request = request.default_headers(false);
     }
 
-    // Apply redirect policy
-    request = request.redirect(redirect.as_policy());
+    // Apply redirect policy. Cross-origin hops stop inside wreq, which would strip its own fixed
+    // header list, and are followed below with the configured one.
+    let cross_origin = match strip_on_cross_origin {
+        Some(strip) if matches!(redirect, RedirectMode::Follow) => {
+            Some(CrossOriginPolicy::new(&strip)?)
+        }
+        _ => None,
+    };
+    let policy = match cross_origin {
+        Some(_) => CrossOriginPolicy::stopping(redirect.as_policy()),
+        None => redirect.as_policy(),
+    };
+    let policy = match hsts.clone() {
+        Some(store) => RedirectMode::hsts_guarded(policy, store),
+        None => policy,
+    };
+    let policy = match egress {
+        Some(egress) => RedirectMode::restricted(policy, egress),
+        None => policy,
+    };
+    let policy = if block_insecure_redirects {
+        RedirectMode::secure_only(policy)
+    } else {
+        policy
+    };
+    let hops = trace.as_ref().map(|_| HopRecorder::default());
+    request = match hops.clone() {
+        Some(hops) => request.redirect(RedirectMode::recording_hops(policy, hops)),
+        None => request.redirect(policy),
+    };
+
+    let deadlines = RequestDeadlines::new(
+        timeout,
+        total_deadline,
+        write_timeout.filter(|_| body.is_some()),
+    );
 
-    // Apply body if present
-    if let Some(body) = body {
-        request = request.body(body);
-    }
+    let mut body = body.map(Bytes::from);
+    let upload_limiters: Vec<Arc<RateLimiter>> = max_upload_bytes_per_sec
+        .map(|rate| Arc::new(RateLimiter::new(rate)))
+        .into_iter()
+        .chain(upload_limiter)
+        .collect();
+    let has_content_length = headers
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case("content-length"));
 
-    // Apply timeout (0 means no timeout)
-    if timeout > 0 {
-        request = request.timeout(Duration::from_millis(timeout));
+    if let Some(timeout) = deadlines.native_timeout() {
+        request = request.timeout(timeout);
     }
 
-    request = request.cookie_provider(cookie_jar);
+    // Without a provider the jar is neither read nor written for this request.
+    if !disable_cookies {
+        request = request.cookie_provider(cookie_jar.clone());
+    }
+    let mut request = request
+        .build()
+        .with_context(|| format!("{} {}", method, url))?;
 
     // Execute request
-    let response = request
-        .send()
-        .await
-        .with_context(|| format!("{} {}", method, url))?;
+    let start = now_ms();
+    let phases = RequestPhases::new(format!("{} {}", method, url));
+    let dns = DnsRecorder::default();
+    let mut cross_origin_hops = 0;
+    let mut dialed;
+    let response = loop {
+        // The bodiless request is what a cross-origin redirect is derived from.
+        let previous = cross_origin.as_ref().and_then(|_| request.try_clone());
+
+        // The hook sees the request as it will be written, so its cookies are added here rather
+        // than by wreq. Cross-origin redirects are derived from the request as it was before.
+        if let Some(hook) = before_send.as_ref().filter(|_| cross_origin_hops == 0) {
+            if !disable_cookies {
+                add_cookies(&mut request, cookie_jar.as_ref());
+            }
+            tokio::select! {
+                signed = hook.apply(&mut request) => {
+                    signed.with_context(|| format!("{} {}", method, url))?;
+                }
+                error = deadlines.expired() => return Err(error),
+            }
+        }
+
+        // Apply body if present
+        if let Some(body) = body.clone() {
+            if upload_limiters.is_empty() && deadlines.upload_progress().is_none() {
+                *request.body_mut() = Some(body.into());
+            } else {
+                // Keep the exact length on the wire; a streamed body would otherwise go out chunked.
+                if !has_content_length {
+                    request
+                        .headers_mut()
+                        .insert(CONTENT_LENGTH, body.len().into());
+                }
+                let len = body.len();
+                let stream = request_body_stream(body, upload_limiters.clone());
+                *request.body_mut() = Some(match deadlines.upload_progress() {
+                    Some(progress) => wreq::Body::wrap_stream(progress.track(stream, len)),
+                    None => wreq::Body::wrap_stream(stream),
+                });
+            }
+        }
+
+        dialed = phases.dialed();
+        let response = tokio::select! {
+            response = phases.track(dns.track(client.execute(request))) => response.map_err(|error| {
+                phases.send_failed(header_limits.classify(error.into()), deadlines.upload_progress())
+            })?,
+            error = deadlines.expired() => {
+                return Err(phases.send_failed(error, deadlines.upload_progress()));
+            }
+        };
+
+        let next = cross_origin
+            .as_ref()
+            .zip(previous)
+            .and_then(|(policy, previous)| policy.next_hop(&response, previous));
+        let Some(next) = next else {
+            break response;
+        };
+
+        cross_origin_hops += 1;
+        if cross_origin_hops > MAX_CROSS_ORIGIN_HOPS {
+            return Err(phases.send_failed(
+                anyhow!("Too many redirects following {}", url),
+                deadlines.upload_progress(),
+            ));
+        }
+        if !next.keeps_body {
+            body = None;
+        }
+        request = next.request;
+        // wreq's timeout restarts with every request it is given; hand over what is left of it.
+        if let Some(timeout) = deadlines.remaining_native_timeout() {
+            request = wreq::RequestBuilder::from_parts((*client).clone(), request)
+                .timeout(timeout)
+                .build()?;
+        }
+    };
+    let body_phase = phases.body();
+    let timing = hops.map(|hops| RequestTiming {
+        start,
+        response_start: now_ms(),
+        redirects: hops.take(),
+    });
 
     // Extract response data
     let status = response.status().as_u16();
+    let reason = if minimal_response {
+        None
+    } else {
+        reason_phrase(&response)
+    };
     let final_url = response.uri().to_string();
 
+    let local_addr = response.local_addr();
+    let remote_addr = response.remote_addr();
+    // The connector runs in this request's task, so a send that dialed nothing reused a connection.
+    let reused = phases.dialed() == dialed;
+    let connection_dns = local_addr.zip(remote_addr).and_then(|(local, remote)| {
+        if reused {
+            CONNECTION_DNS.get(&(local, remote))
+        } else {
+            let answer = dns.answer_for(remote);
+            match &answer {
+                Some(answer) => CONNECTION_DNS.insert((local, remote), answer.clone()),
+                None => CONNECTION_DNS.invalidate(&(local, remote)),
+            }
+            answer
+        }
+    });
+    let connection = ConnectionInfo {
+        reused: Some(reused),
+        client: client_kind,
+        transport_id,
+        local_addr,
+        remote_addr,
+        dns: connection_dns,
+    };
+
+    // Browsers only honor Strict-Transport-Security when it arrives over a secure connection.
+    if let Some(store) = hsts
+        .as_deref()
+        .filter(|_| response.uri().scheme_str() == Some("https"))
+    {
+        let value = response
+            .headers()
+            .get("strict-transport-security")
+            .and_then(|value| value.to_str().ok());
+        if let (Some(host), Some(value)) = (response.uri().host(), value) {
+            store.record(host, value);
+        }
+    }
+
+    // Like browsers, only secure origins get to ask for client hints.
+    let accept_ch = response
+        .headers()
+        .get("accept-ch")
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| response.uri().scheme_str() == Some("https"));
+    if let (Some(store), Some(value)) = (client_hints.as_deref(), accept_ch) {
+        store.record(response.uri(), value);
+    }
+
+    let advertised = response
+        .headers()
+        .get("alt-svc")
+        .and_then(|value| value.to_str().ok());
+    if let (Some(cache), Some(value)) = (alt_svc.as_deref(), advertised) {
+        cache.record(response.uri(), value);
+    }
+
+    let mut content_length = response.content_length();
+    let allows_body = response_allows_body(status, method.as_ref());
+    let coding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok());
+    let decoder = match (decoded_codings, coding) {
+        (Some(accept), Some(coding)) if allows_body => ContentDecoder::for_coding(coding, accept)?,
+        _ => None,
+    };
+
     // Extract headers into a pre-allocated Vec (avoids IndexMap hashing overhead)
     let raw_headers = response.headers();
-    let mut response_headers = Vec::with_capacity(raw_headers.len());
-    for (key, value) in raw_headers {
-        if let Ok(value_str) = value.to_str() {
-            response_headers.push((key.as_str().to_owned(), value_str.to_owned()));
+    let header_bytes = header_block_len(
+        raw_headers
+            .iter()
+            .map(|(key, value)| (key.as_str().as_bytes(), value.as_bytes())),
+    );
+    let mut response_headers = Vec::new();
+    if !minimal_response {
+        response_headers.reserve_exact(raw_headers.len());
+        for (key, value) in raw_headers {
+            // Like wreq's decoders, drop what no longer describes the decoded body.
+            if decoder.is_some() && (key == CONTENT_ENCODING || key == CONTENT_LENGTH) {
+                continue;
+            }
+            if let Ok(value_str) = value.to_str() {
+                response_headers.push((key.as_str().to_owned(), value_str.to_owned()));
+            }
         }
     }
+    if decoder.is_some() {
+        content_length = None;
+    }
 
     // Extract cookies into a Vec
-    let cookies: Vec<(String, String)> = response
-        .cookies()
-        .map(|c| (c.name().to_owned(), c.value().to_owned()))
-        .collect();
+    let cookies: Vec<(String, String)> = if minimal_response {
+        Vec::new()
+    } else {
+        response
+            .cookies()
+            .map(|c| (c.name().to_owned(), c.value().to_owned()))
+            .collect()
+    };
 
-    let mut content_length = response.content_length();
-    let allows_body = response_allows_body(status, method.as_ref());
+    // Request-level and transport-level download caps both apply; the slower one wins.
+    let limiters: Vec<Arc<RateLimiter>> = max_download_bytes_per_sec
+        .map(|rate| Arc::new(RateLimiter::new(rate)))
+        .into_iter()
+        .chain(download_limiter)
+        .collect();
 
+    let body_deadline = deadlines.body_deadline();
+    let (frames, trailer_slot) = data_frames(response);
+    let size_slot = BodySizeSlot::default();
+    // Rate limits pace the bytes on the wire, not the decoded ones.
+    let mut stream = decode_stream(
+        throttle_stream(Box::pin(frames.map_err(anyhow::Error::from)), limiters),
+        decoder,
+        size_slot.clone(),
+    );
+    let mut trailers = None;
+    let mut body_size = Some(BodySize::default());
     let (body_handle, body_bytes) = if allows_body {
         let inline_eligible = content_length
-            .map(|len| len <= INLINE_BODY_MAX)
+            .map(|len| len <= inline_body_max.unwrap_or(INLINE_BODY_MAX))
             .unwrap_or(false);
 
         if inline_eligible {
-            let bytes = response.bytes().await?;
-            content_length = Some(bytes.len() as u64);
-            (None, Some(bytes))
+            let mut buf = Vec::with_capacity(content_length.unwrap_or(0) as usize);
+            within(body_deadline, async {
+                while let Some(chunk) = stream.next().await {
+                    buf.extend_from_slice(&chunk?);
+                }
+                Ok(())
+            })
+            .await
+            .map_err(|error| body_phase.failed(error))?;
+            content_length = Some(buf.len() as u64);
+            trailers = take_trailers(&trailer_slot);
+            body_size = Some(
+                *size_slot
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+            (None, Some(Bytes::from(buf)))
         } else {
-            let stream: ResponseBodyStream = Box::pin(response.bytes_stream());
-            (Some(store_body_stream(stream)), None)
+            body_size = None;
+            let digest = body_digest.map(BodyDigest::new).transpose()?;
+            (
+                Some(store_body_stream(
+                    stream,
+                    group_id,
+                    digest,
+                    trailer_slot,
+                    size_slot,
+                    body_deadline,
+                    body_phase,
+                )),
+                None,
+            )
         }
     } else {
         (None, None)
     };
 
+    let body_digest = match (body_digest, body_bytes.as_ref()) {
+        (Some(algorithm), Some(bytes)) => Some(digest_bytes(algorithm, bytes)?),
+        _ => None,
+    };
+
     Ok(Response {
         status,
+        reason,
         headers: response_headers,
         body_handle,
         body_bytes,
         cookies,
         url: final_url,
         content_length,
+        connection,
+        body_digest,
+        trailers,
+        header_bytes,
+        body_size,
+        timing,
+        minimal: minimal_response,
     })
 }
 
-/// Build a client for explicit transports (full pooling config).
-fn build_client(config: &TransportConfig) -> Result<HttpClient> {
-    let emulation = EmulationOption::builder()
+/// Resolve the browser emulation for a transport, applying header parsing, HTTP/2 flow-control
+/// and TLS resumption overrides on top of the profile's own settings.
+fn transport_emulation(config: &TransportConfig) -> wreq::Emulation {
+    let mut emulation = EmulationOption::builder()
         .emulation(config.emulation)
         .emulation_os(config.emulation_os)
-        .build();
+        .build()
+        .emulation();
+
+    config.header_limits.apply(&mut emulation);
+    config.h2_windows.apply(&mut emulation);
+
+    // Obsolete line folding, spaces before the colon and invalid header lines are tolerated by
+    // browsers, so legacy servers that send them still work.
+    if config.lenient_headers {
+        let h1 = emulation
+            .http1_options_mut()
+            .get_or_insert_with(Http1Options::default);
+        h1.allow_obsolete_multiline_headers_in_responses = true;
+        h1.allow_spaces_after_header_name_in_responses = true;
+        h1.ignore_invalid_headers_in_responses = true;
+    }
+
+    if config.tls_session_tickets.is_some() || config.tls_session_resumption.is_some() {
+        let tls = emulation
+            .tls_options_mut()
+            .get_or_insert_with(|| TlsOptions::builder().build());
+
+        if let Some(enabled) = config.tls_session_tickets {
+            tls.session_ticket = enabled;
+        }
+
+        // wreq only keeps a client session cache when PSK resumption is enabled.
+        if let Some(enabled) = config.tls_session_resumption {
+            tls.pre_shared_key = enabled;
+        }
+    }
+
+    emulation
+}
+
+fn derived_client_cache() -> Cache<ClientOverrides, Arc<HttpClient>> {
+    // Each entry is a whole client with its own pool, so only a few are kept.
+    Cache::builder()
+        .max_capacity(16)
+        .time_to_idle(Duration::from_secs(300))
+        .build()
+}
+
+/// Build a client for explicit transports (full pooling config).
+fn build_client(config: &TransportConfig) -> Result<HttpClient> {
+    client_builder(config)?
+        .build()
+        .context("Failed to build HTTP client")
+}
 
-    let mut client_builder = HttpClient::builder().emulation(emulation);
+fn client_builder(config: &TransportConfig) -> Result<ClientBuilder> {
+    let mut client_builder = HttpClient::builder()
+        .emulation(transport_emulation(config))
+        .connector_layer(ConnectTimingLayer);
+    client_builder = match &config.connect_to {
+        Some(rule) => client_builder.dns_resolver(ConnectToResolver(rule.clone())),
+        None => client_builder.dns_resolver(RecordingResolver),
+    };
 
     if let Some(proxy_url) = config.proxy.as_deref() {
         let proxy = Proxy::all(proxy_url).context("Failed to create proxy")?;
@@ -544,9 +2805,66 @@ fn build_client(config: &TransportConfig) -> Result<HttpClient> {
         client_builder = client_builder.read_timeout(read_timeout);
     }
 
-    client_builder
-        .build()
-        .context("Failed to build HTTP client")
+    if let Some(nodelay) = config.tcp_nodelay {
+        client_builder = client_builder.tcp_nodelay(nodelay);
+    }
+
+    match config.tcp_keepalive {
+        Some(TcpKeepAlive::Disabled) => {
+            client_builder = client_builder
+                .tcp_keepalive(None)
+                .tcp_keepalive_interval(None)
+                .tcp_keepalive_retries(None);
+        }
+        Some(TcpKeepAlive::Enabled {
+            idle,
+            interval,
+            retries,
+        }) => {
+            if let Some(idle) = idle {
+                client_builder = client_builder.tcp_keepalive(idle);
+            }
+            if let Some(interval) = interval {
+                client_builder = client_builder.tcp_keepalive_interval(interval);
+            }
+            if let Some(retries) = retries {
+                client_builder = client_builder.tcp_keepalive_retries(retries);
+            }
+        }
+        None => {}
+    }
+
+    if let Some(size) = config.tcp_send_buffer_size {
+        client_builder = client_builder.tcp_send_buffer_size(size);
+    }
+
+    if let Some(size) = config.tcp_recv_buffer_size {
+        client_builder = client_builder.tcp_recv_buffer_size(size);
+    }
+
+    // NSS key log lines are appended to this file so captures can be decrypted in Wireshark.
+    if let Some(keylog_path) = config.keylog_path.as_deref() {
+        client_builder = client_builder.keylog(KeyLog::from_file(keylog_path));
+    }
+
+    Ok(client_builder)
+}
+
+/// Build one client per proxy in the pool, each with the rest of the transport's settings.
+fn build_proxy_clients(
+    config: &TransportConfig,
+    pool: &ProxyPool,
+) -> Result<Vec<(Arc<str>, Arc<HttpClient>)>> {
+    pool.proxies
+        .iter()
+        .map(|proxy| {
+            let config = TransportConfig {
+                proxy: Some(proxy.clone()),
+                ..config.clone()
+            };
+            Ok((proxy.clone(), Arc::new(build_client(&config)?)))
+        })
+        .collect()
 }
 
 /// Build a client for ephemeral (stateless) requests - no connection pooling.
@@ -558,7 +2876,12 @@ fn build_ephemeral_client(config: &SessionConfig) -> Result<HttpClient> {
 
     let mut client_builder = HttpClient::builder()
         .emulation(emulation)
-        .pool_max_idle_per_host(0);
+        .pool_max_idle_per_host(0)
+        .connector_layer(ConnectTimingLayer);
+    client_builder = match &config.connect_to {
+        Some(rule) => client_builder.dns_resolver(ConnectToResolver(rule.clone())),
+        None => client_builder.dns_resolver(RecordingResolver),
+    };
 
     if let Some(proxy_url) = config.proxy.as_deref() {
         let proxy = Proxy::all(proxy_url).context("Failed to create proxy")?;
@@ -582,6 +2905,102 @@ fn build_ephemeral_client(config: &SessionConfig) -> Result<HttpClient> {
         .context("Failed to build HTTP client")
 }
 
+/// Answer a request from a registered mock without resolving a client or touching the network.
+async fn mocked_response(options: RequestOptions, mock: MockResponse) -> Result<Response> {
+    if mock.delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(mock.delay_ms)).await;
+    }
+
+    let url = options.url.clone();
+    canned_response(options, url, mock.status, mock.headers, mock.body)
+}
+
+/// Build a response that was produced locally (mock or cassette) rather than by a client.
+fn canned_response(
+    options: RequestOptions,
+    final_url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+) -> Result<Response> {
+    let method = if options.method.is_empty() {
+        "GET"
+    } else {
+        options.method.as_str()
+    };
+
+    let cookies = headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("set-cookie"))
+        .filter_map(|(_, value)| {
+            let pair = value.split(';').next()?;
+            let (name, value) = pair.split_once('=')?;
+            Some((name.trim().to_owned(), value.trim().to_owned()))
+        })
+        .collect();
+
+    let headers = headers
+        .into_iter()
+        .map(|(key, value)| (key.to_ascii_lowercase(), value))
+        .collect();
+
+    let header_bytes = header_block_len(
+        headers
+            .iter()
+            .map(|(key, value)| (key.as_bytes(), value.as_bytes())),
+    );
+    let body_bytes = response_allows_body(status, method).then_some(body);
+    let body_len = body_bytes.as_ref().map_or(0, |bytes| bytes.len() as u64);
+    let body_digest = match (options.body_digest, body_bytes.as_ref()) {
+        (Some(algorithm), Some(bytes)) => Some(digest_bytes(algorithm, bytes)?),
+        _ => None,
+    };
+    let client = if options.transport_id.is_some() {
+        ClientKind::Transport
+    } else if options.ephemeral {
+        ClientKind::Ephemeral
+    } else if options.origin_pooling {
+        ClientKind::Pooled
+    } else {
+        ClientKind::Dedicated
+    };
+
+    Ok(Response {
+        status,
+        reason: None,
+        headers,
+        body_handle: None,
+        content_length: body_bytes.as_ref().map(|bytes| bytes.len() as u64),
+        body_bytes,
+        cookies,
+        url: final_url,
+        connection: ConnectionInfo {
+            reused: None,
+            client,
+            transport_id: options.transport_id,
+            local_addr: None,
+            remote_addr: None,
+            dns: None,
+        },
+        body_digest,
+        trailers: None,
+        header_bytes,
+        body_size: Some(BodySize {
+            wire: body_len,
+            decoded: body_len,
+        }),
+        timing: None,
+        minimal: options.minimal_response,
+    })
+}
+
+/// Size of a header block written out as HTTP/1.1 `name: value\r\n` lines.
+fn header_block_len<'a>(fields: impl Iterator<Item = (&'a [u8], &'a [u8])>) -> u64 {
+    fields
+        .map(|(name, value)| (name.len() + value.len() + 4) as u64)
+        .sum()
+}
+
 fn response_allows_body(status: u16, method: &str) -> bool {
     if method.eq_ignore_ascii_case("HEAD") {
         return false;
@@ -593,8 +3012,24 @@ fn response_allows_body(status: u16, method: &str) -> bool {
     }
 }
 
-pub fn create_managed_session(session_id: String) -> Result<String> {
-    SESSION_MANAGER.create_session(session_id)
+/// Create a session with its own cookie jar.
+pub fn create_managed_session(session_id: String, options: SessionOptions) -> Result<String> {
+    ensure_accepting()?;
+    SESSION_MANAGER.create_session(session_id, options)
+}
+
+pub fn get_session_hsts(session_id: &str) -> Result<Vec<HstsEntry>> {
+    Ok(SESSION_MANAGER.require_hsts(session_id)?.entries())
+}
+
+pub fn import_session_hsts(session_id: &str, entries: Vec<HstsEntry>) -> Result<()> {
+    SESSION_MANAGER.require_hsts(session_id)?.import(entries);
+    Ok(())
+}
+
+pub fn clear_session_hsts(session_id: &str) -> Result<()> {
+    SESSION_MANAGER.require_hsts(session_id)?.clear();
+    Ok(())
 }
 
 pub fn clear_managed_session(session_id: &str) -> Result<()> {
@@ -605,29 +3040,55 @@ pub fn drop_managed_session(session_id: &str) {
     SESSION_MANAGER.drop_session(session_id);
 }
 
-pub fn create_managed_transport(
-    emulation: Emulation,
-    emulation_os: EmulationOS,
-    proxy: Option<Arc<str>>,
-    insecure: bool,
-    pool_idle_timeout: Option<u64>,
-    pool_max_idle_per_host: Option<usize>,
-    pool_max_size: Option<u32>,
-    connect_timeout: Option<u64>,
-    read_timeout: Option<u64>,
-) -> Result<String> {
-    let config = TransportConfig::new(
-        emulation,
-        emulation_os,
-        proxy,
-        insecure,
-        pool_idle_timeout,
-        pool_max_idle_per_host,
-        pool_max_size,
-        connect_timeout,
-        read_timeout,
-    );
-    TRANSPORT_MANAGER.create_transport(config)
+pub fn managed_session_token(session_id: &str) -> Option<SessionToken> {
+    SESSION_MANAGER.token(session_id)
+}
+
+/// Drop the session `token` was taken from, if it is still the one under `session_id`.
+pub fn release_managed_session(session_id: &str, token: &SessionToken) -> bool {
+    SESSION_MANAGER.release_session(session_id, token)
+}
+
+pub fn create_managed_transport(mut options: TransportOptions) -> Result<String> {
+    ensure_accepting()?;
+    let egress = EgressPolicy::new(&options.allowlist, &options.denylist)?;
+    let egress = (!egress.is_empty()).then(|| Arc::new(egress));
+    let proxy_ntlm = options.proxy_ntlm.take();
+    let mut config = TransportConfig::from_transport_options(options);
+
+    // The clients connect to a local proxy that does the NTLM handshake with the real one.
+    let ntlm_proxy = match proxy_ntlm {
+        Some(credentials) => {
+            if config.proxy_pool.is_some() {
+                bail!("proxyNtlm cannot be combined with proxyPool");
+            }
+            let upstream = config
+                .proxy
+                .as_deref()
+                .ok_or_else(|| anyhow!("proxyNtlm requires a proxy"))?;
+            let ntlm_proxy = NtlmProxy::spawn(HTTP_RUNTIME.handle(), upstream, credentials)?;
+            config.proxy = Some(Arc::from(ntlm_proxy.url()));
+            Some(Arc::new(ntlm_proxy))
+        }
+        None => None,
+    };
+    TRANSPORT_MANAGER.create_transport(config, egress, ntlm_proxy)
+}
+
+pub fn clear_managed_transport_tls_sessions(transport_id: &str) -> Result<()> {
+    TRANSPORT_MANAGER.reset_transport(transport_id)
+}
+
+pub fn get_transport_alt_svc(transport_id: &str, origin: Option<&str>) -> Result<Vec<AltSvcEntry>> {
+    Ok(TRANSPORT_MANAGER
+        .get_entry(transport_id)?
+        .alt_svc
+        .entries(origin))
+}
+
+pub fn clear_transport_alt_svc(transport_id: &str) -> Result<()> {
+    TRANSPORT_MANAGER.get_entry(transport_id)?.alt_svc.clear();
+    Ok(())
 }
 
 pub fn drop_managed_transport(transport_id: &str) {
@@ -648,9 +3109,12 @@ mod tests {
             emulation: Emulation::Chrome142,
             emulation_os: EmulationOS::MacOS,
             headers: Vec::new(),
+            append_headers: Vec::new(),
+            remove_headers: Vec::new(),
             method: "GET".to_string(),
             body: None,
             proxy: None,
+            direct: false,
             timeout: 5_000,
             redirect: RedirectMode::Follow,
             session_id: "test-session".to_string(),
@@ -663,6 +3127,29 @@ mod tests {
             pool_max_size: None,
             connect_timeout: None,
             read_timeout: None,
+            group_id: None,
+            max_download_bytes_per_sec: None,
+            max_upload_bytes_per_sec: None,
+            body_digest: None,
+            trace: None,
+            write_timeout: None,
+            total_deadline: None,
+            block_insecure_redirects: false,
+            strip_on_cross_origin: None,
+            disable_cookies: false,
+            inline_body_max: None,
+            accept_encoding: None,
+            decode_body: true,
+            origin_pooling: false,
+            priority: None,
+            accept_language: None,
+            meta_refresh_max_delay: None,
+            ntlm: None,
+            client_hints: None,
+            mode: None,
+            minimal_response: false,
+            before_send: None,
+            connect_to: Vec::new(),
         }
     }
 
@@ -685,6 +3172,164 @@ mod tests {
         assert_ne!(connect_config, read_config);
     }
 
+    #[test]
+    fn appended_headers_keep_the_profile_default() {
+        let mut defaults = HeaderMap::new();
+        defaults.insert("accept", "text/html".parse().unwrap());
+        let mut headers = vec![
+            (
+                "Accept".to_owned(),
+                HeaderValue::from_static("application/json"),
+            ),
+            ("accept-language".to_owned(), HeaderValue::from_static("de")),
+        ];
+
+        append_to_profile_headers(&mut headers, &["accept".to_owned()], &defaults);
+        assert_eq!(headers[0].1, "text/html, application/json");
+        assert_eq!(headers[1].1, "de");
+    }
+
+    #[test]
+    fn reaps_the_oldest_handles_over_the_limit() {
+        assert!(handles_over_limit(vec![3, 1, 2], 3).is_empty());
+        assert_eq!(handles_over_limit(vec![7, 2, 9, 4], 2), [2, 4]);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_quiet_stream_while_it_is_read() {
+        let quiet: ResponseBodyStream = Box::pin(futures_util::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok(Bytes::from_static(b"late"))
+        }));
+        let handle = store_body_stream(
+            quiet,
+            None,
+            None,
+            TrailerSlot::default(),
+            BodySizeSlot::default(),
+            None,
+            RequestPhases::new("GET http://quiet.test/".to_owned()).body(),
+        );
+
+        set_body_stream_ttl(Duration::from_millis(50));
+        let read = read_body_chunk(handle).await;
+        let end = read_body_chunk(handle).await;
+        set_body_stream_ttl(Duration::from_millis(300_000));
+
+        assert!(
+            matches!(read, Ok(Some(BodyChunk::Single(ref bytes))) if bytes.as_ref() == b"late")
+        );
+        assert!(matches!(end, Ok(None)));
+    }
+
+    #[test]
+    fn batches_ready_chunks_up_to_the_chunk_size() {
+        let chunks = [
+            Bytes::from_static(b"ab"),
+            Bytes::from_static(b"cd"),
+            Bytes::from(vec![0; BODY_CHUNK_SIZE]),
+            Bytes::from_static(b"ef"),
+        ];
+        let body: ResponseBodyStream = Box::pin(futures_util::stream::iter(
+            chunks.map(Ok::<_, anyhow::Error>),
+        ));
+        let mut stream = body.peekable();
+
+        let first = stream.next().now_or_never().flatten().unwrap().unwrap();
+        let chunk = batch_ready_chunks(&mut stream, first);
+        assert!(matches!(chunk, BodyChunk::Batched(_)));
+        assert_eq!(&*chunk, b"abcd");
+
+        // A chunk that would overflow the batch is left for the next read.
+        let first = stream.next().now_or_never().flatten().unwrap().unwrap();
+        let chunk = batch_ready_chunks(&mut stream, first);
+        assert!(matches!(chunk, BodyChunk::Single(_)));
+        assert_eq!(chunk.len(), BODY_CHUNK_SIZE);
+    }
+
+    /// Open one connection through a transport built from `options` and return a duplicate of
+    /// the client's socket, found among this process's descriptors by its local address.
+    #[cfg(target_os = "linux")]
+    async fn transport_socket(options: TransportOptions) -> socket2::Socket {
+        use std::os::fd::{BorrowedFd, RawFd};
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let client = build_client(&TransportConfig::from_transport_options(options)).unwrap();
+        let request = tokio::spawn(async move { client.get(url).send().await });
+
+        // Options set after connect are in place once the request bytes arrive.
+        let (mut server_side, peer) = listener.accept().await.unwrap();
+        assert!(server_side.read(&mut [0; 1024]).await.unwrap() > 0);
+
+        let socket = std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<RawFd>().ok())
+            .find_map(|raw| {
+                // SAFETY: the descriptor is only borrowed for this closure; the client keeps its
+                // connection open until the request is aborted below.
+                let fd = unsafe { BorrowedFd::borrow_raw(raw) };
+                let local = socket2::SockRef::from(&fd).local_addr().ok()?;
+                if local.as_socket() != Some(peer) {
+                    return None;
+                }
+                fd.try_clone_to_owned().ok().map(socket2::Socket::from)
+            })
+            .expect("client socket");
+        request.abort();
+        socket
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn applies_tcp_options_to_transport_sockets() {
+        let base = TransportOptions::new(Emulation::Chrome142, EmulationOS::MacOS);
+        let tuned = transport_socket(TransportOptions {
+            tcp_nodelay: Some(true),
+            tcp_keepalive: Some(TcpKeepAlive::Enabled {
+                idle: Some(Duration::from_secs(5)),
+                interval: Some(Duration::from_secs(1)),
+                retries: Some(2),
+            }),
+            tcp_send_buffer_size: Some(256 * 1024),
+            tcp_recv_buffer_size: Some(256 * 1024),
+            ..base.clone()
+        })
+        .await;
+
+        // The kernel rounds buffer sizes, so compare with a socket given the same sizes directly.
+        let reference =
+            socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+        reference.set_send_buffer_size(256 * 1024).unwrap();
+        reference.set_recv_buffer_size(256 * 1024).unwrap();
+
+        assert!(tuned.tcp_nodelay().unwrap());
+        assert!(tuned.keepalive().unwrap());
+        assert_eq!(tuned.tcp_keepalive_time().unwrap(), Duration::from_secs(5));
+        assert_eq!(
+            tuned.tcp_keepalive_interval().unwrap(),
+            Duration::from_secs(1)
+        );
+        assert_eq!(tuned.tcp_keepalive_retries().unwrap(), 2);
+        assert_eq!(
+            tuned.send_buffer_size().unwrap(),
+            reference.send_buffer_size().unwrap()
+        );
+        assert_eq!(
+            tuned.recv_buffer_size().unwrap(),
+            reference.recv_buffer_size().unwrap()
+        );
+
+        let plain = transport_socket(TransportOptions {
+            tcp_nodelay: Some(false),
+            tcp_keepalive: Some(TcpKeepAlive::Disabled),
+            ..base
+        })
+        .await;
+        assert!(!plain.tcp_nodelay().unwrap());
+        assert!(!plain.keepalive().unwrap());
+    }
 }
 
 /// Get cookies from a session's jar that would be sent to the given URL
@@ -693,7 +3338,9 @@ pub fn get_session_cookies(session_id: &str, url: &str) -> Result<Vec<(String, S
     use wreq::cookie::CookieStore;
 
     let jar = SESSION_MANAGER.jar_for(session_id)?;
-    let uri: wreq::Uri = url.parse().with_context(|| format!("Invalid URL: {}", url))?;
+    let uri: wreq::Uri = url
+        .parse()
+        .with_context(|| format!("Invalid URL: {}", url))?;
     let cookie_header = jar.cookies(&uri);
 
     let pairs = match cookie_header {
@@ -716,6 +3363,193 @@ pub fn get_session_cookies(session_id: &str, url: &str) -> Result<Vec<(String, S
     Ok(pairs)
 }
 
+/// Default headers a browser profile adds to every request.
+fn profile_headers(emulation: Emulation, emulation_os: EmulationOS) -> HeaderMap {
+    let mut profile = EmulationOption::builder()
+        .emulation(emulation)
+        .emulation_os(emulation_os)
+        .build()
+        .emulation();
+    std::mem::take(profile.headers_mut())
+}
+
+/// Add the client hint headers for `hints` and the hints the origin asked for to the request's
+/// headers, unless the caller set or removed them.
+fn add_client_hints(
+    headers: &mut Vec<(String, HeaderValue)>,
+    removed: &[String],
+    hints: &ClientHints,
+    defaults: &HeaderMap,
+    requested: &[String],
+) {
+    for (name, value) in hint_headers(hints, defaults, requested) {
+        let skipped = headers
+            .iter()
+            .map(|(key, _)| key)
+            .chain(removed)
+            .any(|key| key.eq_ignore_ascii_case(&name));
+        if !skipped {
+            headers.push((name, value));
+        }
+    }
+}
+
+// wreq drops a profile default once the request sets the same header, so headers in `append`
+// carry the default in front of the caller's value.
+fn append_to_profile_headers(
+    headers: &mut [(String, HeaderValue)],
+    append: &[String],
+    defaults: &HeaderMap,
+) {
+    for (name, value) in headers.iter_mut() {
+        if !append.iter().any(|key| key.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+        let mut combined = Vec::new();
+        for (_, default) in defaults
+            .iter()
+            .filter(|(key, _)| key.as_str().eq_ignore_ascii_case(name))
+        {
+            combined.extend_from_slice(default.as_bytes());
+            combined.extend_from_slice(b", ");
+        }
+        if combined.is_empty() {
+            continue;
+        }
+        combined.extend_from_slice(value.as_bytes());
+        // Both halves are valid header values, and so is their concatenation.
+        if let Ok(appended) = HeaderValue::from_bytes(&combined) {
+            *value = appended;
+        }
+    }
+}
+
+/// Header value as text for cassettes, curl commands and JS. Bytes that are not UTF-8 are
+/// replaced.
+pub fn header_text(value: &HeaderValue) -> String {
+    String::from_utf8_lossy(value.as_bytes()).into_owned()
+}
+
+fn text_headers(headers: &[(String, HeaderValue)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.clone(), header_text(value)))
+        .collect()
+}
+
+/// Render the request `make_request` would send for `options` as a curl command line. Nothing
+/// is sent and no session state is created.
+pub fn request_to_curl(options: &RequestOptions) -> Result<String> {
+    let (emulation, emulation_os, proxy, insecure) = match options.transport_id.as_deref() {
+        Some(tid) => {
+            let entry = TRANSPORT_MANAGER.get_entry(tid)?;
+            (
+                entry.config.emulation,
+                entry.config.emulation_os,
+                entry.proxy_for(&ClientOverrides::for_request(options, None)),
+                entry.config.insecure,
+            )
+        }
+        None => (
+            options.emulation,
+            options.emulation_os,
+            options.proxy.clone(),
+            options.insecure,
+        ),
+    };
+
+    let session = if options.ephemeral {
+        None
+    } else {
+        SESSION_MANAGER.cache.get(&options.session_id)
+    };
+
+    let url = match session
+        .as_ref()
+        .and_then(|entry| entry.hsts.as_deref())
+        .and_then(|store| store.upgrade_url(&options.url))
+    {
+        Some(upgraded) => upgraded,
+        None => options.url.clone(),
+    };
+
+    // Caller headers replace profile defaults of the same name, as they do on the wire.
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut caller_headers = options.headers.clone();
+    if !options.disable_default_headers {
+        let defaults = profile_headers(emulation, emulation_os);
+        let transport_hints = match options.transport_id.as_deref() {
+            Some(tid) => TRANSPORT_MANAGER
+                .get_entry(tid)?
+                .config
+                .client_hints
+                .clone(),
+            None => None,
+        };
+        let hints = ClientHints::merged(options.client_hints.as_ref(), transport_hints.as_ref());
+        let requested = session
+            .as_ref()
+            .and_then(|entry| entry.client_hints.as_deref())
+            .map(|store| store.requested(&url))
+            .unwrap_or_default();
+        add_client_hints(
+            &mut caller_headers,
+            &options.remove_headers,
+            &hints.unwrap_or_default(),
+            &defaults,
+            &requested,
+        );
+        append_to_profile_headers(&mut caller_headers, &options.append_headers, &defaults);
+        for (name, value) in defaults.iter() {
+            let overridden = caller_headers
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case(name.as_str()));
+            let removed = options
+                .remove_headers
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(name.as_str()));
+            if overridden || removed {
+                continue;
+            }
+            if let Ok(value) = value.to_str() {
+                headers.push((name.as_str().to_owned(), value.to_owned()));
+            }
+        }
+    }
+    headers.extend(text_headers(&caller_headers));
+
+    if session.is_some()
+        && !headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("cookie"))
+    {
+        let cookies = get_session_cookies(&options.session_id, &url)?;
+        if !cookies.is_empty() {
+            let value = cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ");
+            headers.push(("cookie".to_owned(), value));
+        }
+    }
+
+    Ok(render_curl(&CurlRequest {
+        method: if options.method.is_empty() {
+            "GET"
+        } else {
+            options.method.as_str()
+        },
+        url: &url,
+        headers: &headers,
+        body: options.body.as_deref(),
+        proxy: proxy.as_deref(),
+        insecure,
+        timeout_ms: options.timeout,
+        follow_redirects: matches!(options.redirect, RedirectMode::Follow),
+    }))
+}
+
 fn parse_cookie_pairs(s: &str) -> Vec<(String, String)> {
     s.split("; ")
         .filter_map(|pair| {
@@ -740,13 +3574,36 @@ pub fn set_session_cookie(session_id: &str, name: &str, value: &str, url: &str)
         .as_str()
         .into_cookie()
         .ok_or_else(|| anyhow!("Invalid cookie string: {}", cookie_str))?;
-    let uri: wreq::Uri = url.parse().with_context(|| format!("Invalid URL: {}", url))?;
+    let uri: wreq::Uri = url
+        .parse()
+        .with_context(|| format!("Invalid URL: {}", url))?;
 
     let jar = SESSION_MANAGER.jar_for(session_id)?;
     jar.add(cookie, uri);
     Ok(())
 }
 
+/// Load cookies exported from a browser into a session's jar, returning how many were stored.
+/// Cookies that have already expired are skipped.
+pub fn import_session_browser_cookies(
+    session_id: &str,
+    cookies: &[BrowserCookie],
+) -> Result<usize> {
+    let jar = SESSION_MANAGER.jar_for(session_id)?;
+    let now = std::time::SystemTime::now();
+    let mut imported = 0;
+    for cookie in cookies {
+        if let Some((cookie, url)) = cookie.to_jar_cookie(now)? {
+            let uri: wreq::Uri = url
+                .parse()
+                .with_context(|| format!("Invalid cookie domain or path: {}", url))?;
+            jar.add(cookie, uri);
+            imported += 1;
+        }
+    }
+    Ok(imported)
+}
+
 /// Get the cookie jar for a session. Used by websocket to share cookies.
 pub(crate) fn get_session_cookie_jar(session_id: &str) -> Result<Arc<Jar>> {
     SESSION_MANAGER.jar_for(session_id)
@@ -756,3 +3613,11 @@ pub(crate) fn get_session_cookie_jar(session_id: &str) -> Result<Arc<Jar>> {
 pub(crate) fn get_transport_client(transport_id: &str) -> Result<Arc<wreq::Client>> {
     TRANSPORT_MANAGER.get_transport(transport_id)
 }
+
+/// Fail if the transport's egress policy does not permit `uri`.
+pub(crate) fn check_transport_egress(transport_id: &str, uri: &wreq::Uri) -> Result<()> {
+    match TRANSPORT_MANAGER.get_entry(transport_id)?.egress.as_deref() {
+        Some(policy) => policy.check(uri),
+        None => Ok(()),
+    }
+}
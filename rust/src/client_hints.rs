@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use wreq::header::{HeaderMap, HeaderValue};
+
+use crate::alt_svc::origin_of;
+
+/// One entry of the `Sec-CH-UA` brand list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHintBrand {
+    pub brand: String,
+    pub version: String,
+}
+
+/// Replacements for the user-agent client hints a Chromium profile sends. Fields left unset
+/// keep the profile's value, or one derived from it for hints the profile does not send.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHints {
+    pub brands: Option<Vec<ClientHintBrand>>,
+    pub full_version: Option<String>,
+    pub platform: Option<String>,
+    pub platform_version: Option<String>,
+    pub mobile: Option<bool>,
+    pub arch: Option<String>,
+    pub model: Option<String>,
+}
+
+impl ClientHints {
+    /// `request`'s fields, falling back to `transport`'s.
+    pub fn merged(request: Option<&Self>, transport: Option<&Self>) -> Option<Self> {
+        match (request, transport) {
+            (Some(request), Some(transport)) => Some(Self {
+                brands: request.brands.clone().or_else(|| transport.brands.clone()),
+                full_version: request
+                    .full_version
+                    .clone()
+                    .or_else(|| transport.full_version.clone()),
+                platform: request
+                    .platform
+                    .clone()
+                    .or_else(|| transport.platform.clone()),
+                platform_version: request
+                    .platform_version
+                    .clone()
+                    .or_else(|| transport.platform_version.clone()),
+                mobile: request.mobile.or(transport.mobile),
+                arch: request.arch.clone().or_else(|| transport.arch.clone()),
+                model: request.model.clone().or_else(|| transport.model.clone()),
+            }),
+            (request, transport) => request.or(transport).cloned(),
+        }
+    }
+}
+
+/// Hints each origin asked a single session for with `Accept-CH`, keyed by
+/// `scheme://host:port` origin.
+#[derive(Debug, Default)]
+pub struct ClientHintsStore {
+    requested: RwLock<HashMap<String, Vec<String>>>,
+}
+
+impl ClientHintsStore {
+    /// Replace what `uri`'s origin asked for with the hints named in an `Accept-CH` value.
+    pub fn record(&self, uri: &wreq::Uri, value: &str) {
+        let Some(origin) = origin_of(uri) else {
+            return;
+        };
+        let hints: Vec<String> = value
+            .split(',')
+            .map(|hint| hint.trim().to_ascii_lowercase())
+            .filter(|hint| !hint.is_empty())
+            .collect();
+
+        let mut requested = self
+            .requested
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if hints.is_empty() {
+            requested.remove(&origin);
+        } else {
+            requested.insert(origin, hints);
+        }
+    }
+
+    /// The hints `url`'s origin asked for, lowercased.
+    pub fn requested(&self, url: &str) -> Vec<String> {
+        let Some(origin) = url
+            .parse::<wreq::Uri>()
+            .ok()
+            .and_then(|uri| origin_of(&uri))
+        else {
+            return Vec::new();
+        };
+        self.requested
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&origin)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquoted(value: &str) -> &str {
+    value.trim().trim_matches('"')
+}
+
+/// Parse a `"Brand";v="1", ...` list as the profile sends it.
+fn parse_brands(value: &str) -> Vec<ClientHintBrand> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let (brand, params) = item.split_once(';')?;
+            let (_, version) = params.split_once('=')?;
+            Some(ClientHintBrand {
+                brand: unquoted(brand).to_owned(),
+                version: unquoted(version).to_owned(),
+            })
+        })
+        .collect()
+}
+
+fn format_brands<'a>(brands: impl Iterator<Item = (&'a str, String)>) -> String {
+    brands
+        .map(|(brand, version)| format!("{};v={}", quoted(brand), quoted(&version)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn major_version(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+// Chromium's GREASE brands ("Not_A Brand", "Not)A;Brand", ...) keep their made-up version in
+// every list, padded like a real one.
+fn is_grease(brand: &str) -> bool {
+    brand.starts_with("Not")
+}
+
+fn full_version_of(version: &str) -> String {
+    if version.contains('.') {
+        version.to_owned()
+    } else {
+        format!("{}.0.0.0", version)
+    }
+}
+
+/// Platform values a browser on `platform` reports for the high-entropy hints it is asked for.
+fn platform_defaults(platform: &str) -> (&'static str, &'static str, &'static str) {
+    // (platform version, architecture, bitness)
+    match platform {
+        "Windows" => ("19.0.0", "x86", "64"),
+        "macOS" => ("15.0.0", "arm", "64"),
+        "Android" => ("14.0.0", "", ""),
+        "iOS" => ("18.0.0", "", ""),
+        _ => ("", "x86", "64"),
+    }
+}
+
+/// Client hint headers to send in place of, or besides, the profile's `defaults`: overrides of
+/// the `Sec-CH-UA` headers the profile sends, plus the high-entropy hints named in
+/// `requested`. Profiles that send no client hints get none.
+pub fn hint_headers(
+    hints: &ClientHints,
+    defaults: &HeaderMap,
+    requested: &[String],
+) -> Vec<(String, HeaderValue)> {
+    let Some(profile_brands) = defaults
+        .get("sec-ch-ua")
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Vec::new();
+    };
+
+    let brands = hints
+        .brands
+        .clone()
+        .unwrap_or_else(|| parse_brands(profile_brands));
+    let platform = match hints.platform.as_deref() {
+        Some(platform) => platform.to_owned(),
+        None => defaults
+            .get("sec-ch-ua-platform")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| unquoted(value).to_owned())
+            .unwrap_or_default(),
+    };
+    // The version every real brand reports, from the first brand that is not GREASE.
+    let full_version = hints.full_version.clone().unwrap_or_else(|| {
+        brands
+            .iter()
+            .find(|brand| !is_grease(&brand.brand))
+            .map(|brand| full_version_of(&brand.version))
+            .unwrap_or_default()
+    });
+    let (default_platform_version, default_arch, bitness) = platform_defaults(&platform);
+
+    let mut values: Vec<(&str, String)> = Vec::new();
+    if hints.brands.is_some() {
+        values.push((
+            "sec-ch-ua",
+            format_brands(brands.iter().map(|brand| {
+                (
+                    brand.brand.as_str(),
+                    major_version(&brand.version).to_owned(),
+                )
+            })),
+        ));
+    }
+    if let Some(mobile) = hints.mobile {
+        values.push((
+            "sec-ch-ua-mobile",
+            if mobile { "?1" } else { "?0" }.to_owned(),
+        ));
+    }
+    if hints.platform.is_some() {
+        values.push(("sec-ch-ua-platform", quoted(&platform)));
+    }
+
+    for hint in requested {
+        let value = match hint.as_str() {
+            "sec-ch-ua-full-version-list" => format_brands(brands.iter().map(|brand| {
+                let version = if is_grease(&brand.brand) {
+                    full_version_of(&brand.version)
+                } else {
+                    full_version.clone()
+                };
+                (brand.brand.as_str(), version)
+            })),
+            "sec-ch-ua-full-version" => quoted(&full_version),
+            "sec-ch-ua-platform-version" => quoted(
+                hints
+                    .platform_version
+                    .as_deref()
+                    .unwrap_or(default_platform_version),
+            ),
+            "sec-ch-ua-arch" => quoted(hints.arch.as_deref().unwrap_or(default_arch)),
+            "sec-ch-ua-bitness" => quoted(bitness),
+            "sec-ch-ua-model" => quoted(hints.model.as_deref().unwrap_or_default()),
+            "sec-ch-ua-wow64" => "?0".to_owned(),
+            _ => continue,
+        };
+        values.push((hint.as_str(), value));
+    }
+
+    values
+        .into_iter()
+        .filter_map(|(name, value)| {
+            HeaderValue::from_str(&value)
+                .ok()
+                .map(|value| (name.to_owned(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chrome_defaults() -> HeaderMap {
+        let mut defaults = HeaderMap::new();
+        defaults.insert(
+            "sec-ch-ua",
+            HeaderValue::from_static(
+                "\"Chromium\";v=\"142\", \"Google Chrome\";v=\"142\", \"Not_A Brand\";v=\"99\"",
+            ),
+        );
+        defaults.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
+        defaults.insert(
+            "sec-ch-ua-platform",
+            HeaderValue::from_static("\"Windows\""),
+        );
+        defaults
+    }
+
+    #[test]
+    fn keeps_the_profile_hints_without_overrides() {
+        let headers = hint_headers(&ClientHints::default(), &chrome_defaults(), &[]);
+        assert!(headers.is_empty());
+        assert!(hint_headers(&ClientHints::default(), &HeaderMap::new(), &[]).is_empty());
+    }
+
+    #[test]
+    fn overrides_the_low_entropy_hints() {
+        let hints = ClientHints {
+            brands: Some(vec![ClientHintBrand {
+                brand: "Brave".to_owned(),
+                version: "142.0.1.2".to_owned(),
+            }]),
+            platform: Some("Linux".to_owned()),
+            mobile: Some(true),
+            ..ClientHints::default()
+        };
+        let headers = hint_headers(&hints, &chrome_defaults(), &[]);
+        assert_eq!(
+            headers[0],
+            (
+                "sec-ch-ua".to_owned(),
+                HeaderValue::from_static("\"Brave\";v=\"142\"")
+            )
+        );
+        assert_eq!(headers[1].1, "?1");
+        assert_eq!(headers[2].1, "\"Linux\"");
+    }
+
+    #[test]
+    fn derives_requested_high_entropy_hints_from_the_profile() {
+        let requested = vec![
+            "sec-ch-ua-full-version-list".to_owned(),
+            "sec-ch-ua-platform-version".to_owned(),
+            "sec-ch-ua-unknown".to_owned(),
+        ];
+        let headers = hint_headers(&ClientHints::default(), &chrome_defaults(), &requested);
+        assert_eq!(headers.len(), 2);
+        assert_eq!(
+            headers[0].1,
+            "\"Chromium\";v=\"142.0.0.0\", \"Google Chrome\";v=\"142.0.0.0\", \"Not_A Brand\";v=\"99.0.0.0\""
+        );
+        assert_eq!(headers[1].1, "\"19.0.0\"");
+    }
+
+    #[test]
+    fn records_accept_ch_per_origin() {
+        let store = ClientHintsStore::default();
+        let uri: wreq::Uri = "https://example.com/page".parse().unwrap();
+        store.record(&uri, "Sec-CH-UA-Full-Version-List, Sec-CH-UA-Model");
+        assert_eq!(
+            store.requested("https://example.com:443/other"),
+            ["sec-ch-ua-full-version-list", "sec-ch-ua-model"]
+        );
+        assert!(store.requested("https://other.example/").is_empty());
+
+        store.record(&uri, "");
+        assert!(store.requested("https://example.com/").is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result, bail};
+use std::net::IpAddr;
+use wreq::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::dns::RecordingResolver;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Connections for one host and port opened somewhere else, like curl's
+/// `--connect-to HOST1:PORT1:HOST2:PORT2`. The request keeps its URL, so its `Host` header and
+/// TLS server name still name `from_host`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectTo {
+    pub from_host: String,
+    /// Any port when `None`.
+    pub from_port: Option<u16>,
+    /// `from_host` itself when `None`.
+    pub to_host: Option<String>,
+    /// The port of the request URL when `None`.
+    pub to_port: Option<u16>,
+}
+
+impl ConnectTo {
+    /// The first of `rules` for the host and port of `url`.
+    pub fn select(rules: &[ConnectTo], url: &str) -> Result<Option<ConnectTo>> {
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        let url = url::Url::parse(url).context("Invalid request URL")?;
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            return Ok(None);
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let Some(rule) = rules.iter().find(|rule| {
+            rule.from_host.eq_ignore_ascii_case(host) && rule.from_port.is_none_or(|p| p == port)
+        }) else {
+            return Ok(None);
+        };
+
+        // wreq connects to IP literals without a lookup, and always to a port the URL names, so
+        // neither can be redirected from the resolver.
+        if host.parse::<IpAddr>().is_ok() {
+            bail!("connectTo can only redirect host names, not {host}");
+        }
+        if let (Some(named), Some(to_port)) = (url.port(), rule.to_port) {
+            if named != to_port {
+                bail!("connectTo cannot replace port {named}, which the URL names");
+            }
+        }
+        Ok(Some(rule.clone()))
+    }
+}
+
+/// Resolves the rule's `from_host` to the addresses of its target, and every other name as
+/// [`RecordingResolver`] does.
+#[derive(Debug, Clone)]
+pub struct ConnectToResolver(pub ConnectTo);
+
+impl Resolve for ConnectToResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let rule = &self.0;
+        if !name.as_str().eq_ignore_ascii_case(&rule.from_host) {
+            return RecordingResolver.resolve(name);
+        }
+        let target = rule.to_host.as_deref().unwrap_or(name.as_str());
+        let resolving = RecordingResolver.resolve(Name::from(target));
+        // Port 0 leaves the choice to the URL, as the system resolver's answers do.
+        let port = rule.to_port.unwrap_or(0);
+        Box::pin(async move {
+            let addrs = resolving.await?;
+            let addrs = addrs.map(move |mut addr| {
+                addr.set_port(port);
+                addr
+            });
+            Ok::<Addrs, BoxError>(Box::new(addrs))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConnectTo;
+
+    fn rule(from_port: Option<u16>, to_port: Option<u16>) -> ConnectTo {
+        ConnectTo {
+            from_host: "api.example.com".into(),
+            from_port,
+            to_host: Some("staging.internal".into()),
+            to_port,
+        }
+    }
+
+    #[test]
+    fn selects_the_first_rule_for_the_url_host_and_port() {
+        let rules = [rule(Some(80), None), rule(Some(443), Some(8443))];
+        let selected = ConnectTo::select(&rules, "https://API.example.com/v1").unwrap();
+        assert_eq!(selected, Some(rules[1].clone()));
+        let selected = ConnectTo::select(&rules, "http://api.example.com/").unwrap();
+        assert_eq!(selected, Some(rules[0].clone()));
+        assert_eq!(
+            ConnectTo::select(&rules, "https://www.example.com/").unwrap(),
+            None
+        );
+        assert_eq!(
+            ConnectTo::select(&rules, "https://api.example.com:9443/").unwrap(),
+            None
+        );
+
+        let any_port = [rule(None, Some(8443))];
+        assert!(ConnectTo::select(&any_port, "https://api.example.com:9443/").is_err());
+        assert!(ConnectTo::select(&[rule(None, None)], "https://api.example.com:9443/").is_ok());
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Result, anyhow, bail};
+use dashmap::DashMap;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// One JS context (the main thread or a `worker_threads` worker) that loaded the addon.
+pub type ContextId = u64;
+
+/// Whether a context's sessions and transports can be used from other contexts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RegistryMode {
+    /// IDs work from any context, e.g. after being posted to a worker.
+    #[default]
+    Shared,
+    /// IDs only work in the context that created them.
+    Isolated,
+}
+
+impl RegistryMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "shared" => Some(RegistryMode::Shared),
+            "isolated" => Some(RegistryMode::Isolated),
+            _ => None,
+        }
+    }
+}
+
+/// Native registries whose entries belong to the context that created them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Session,
+    Transport,
+    /// Only tracked so they are closed when their context exits; their IDs are not checked.
+    WebSocket,
+    TlsSocket,
+    H2Stream,
+}
+
+impl Resource {
+    fn label(self) -> &'static str {
+        match self {
+            Resource::Session => "Session",
+            Resource::Transport => "Transport",
+            Resource::WebSocket => "WebSocket",
+            Resource::TlsSocket => "TLS socket",
+            Resource::H2Stream => "HTTP/2 stream",
+        }
+    }
+
+    /// Whether the registry mode decides who can use the resource's IDs.
+    fn shareable(self) -> bool {
+        matches!(self, Resource::Session | Resource::Transport)
+    }
+}
+
+static NEXT_CONTEXT_ID: AtomicU64 = AtomicU64::new(1);
+static CONTEXTS: LazyLock<DashMap<ContextId, RegistryMode>> = LazyLock::new(DashMap::new);
+static OWNERS: LazyLock<DashMap<(Resource, String), ContextId>> = LazyLock::new(DashMap::new);
+
+/// Register a newly loaded context with a shared registry.
+pub fn register_context() -> ContextId {
+    let context = NEXT_CONTEXT_ID.fetch_add(1, Ordering::Relaxed);
+    CONTEXTS.insert(context, RegistryMode::default());
+    context
+}
+
+/// Change a context's registry mode. Only allowed before it has created sessions or
+/// transports, so an ID never changes visibility while it is in use.
+pub fn set_registry_mode(context: ContextId, mode: RegistryMode) -> Result<()> {
+    if OWNERS
+        .iter()
+        .any(|entry| *entry.value() == context && entry.key().0.shareable())
+    {
+        bail!("The registry mode must be set before creating sessions or transports");
+    }
+    CONTEXTS.insert(context, mode);
+    Ok(())
+}
+
+/// Whether `context` has not exited yet.
+pub fn context_registered(context: ContextId) -> bool {
+    CONTEXTS.contains_key(&context)
+}
+
+fn registry_mode(context: ContextId) -> RegistryMode {
+    CONTEXTS.get(&context).map(|mode| *mode).unwrap_or_default()
+}
+
+/// Record that `context` created the resource `id`.
+pub fn claim(context: ContextId, resource: Resource, id: &str) {
+    OWNERS.insert((resource, id.to_owned()), context);
+}
+
+/// Forget the owner of `id` once it has been dropped.
+pub fn release(resource: Resource, id: &str) {
+    OWNERS.remove(&(resource, id.to_owned()));
+}
+
+/// Fail the way a missing ID does if `id` belongs to another context and either side is
+/// isolated. Unknown IDs are left to the registry itself to report.
+pub fn check_access(context: ContextId, resource: Resource, id: &str) -> Result<()> {
+    let Some(owner) = OWNERS.get(&(resource, id.to_owned())).map(|owner| *owner) else {
+        return Ok(());
+    };
+    let isolated = registry_mode(context) == RegistryMode::Isolated
+        || registry_mode(owner) == RegistryMode::Isolated;
+    if owner != context && isolated {
+        return Err(anyhow!("{} '{}' not found", resource.label(), id));
+    }
+    Ok(())
+}
+
+/// Forget a context that is going away and return the resources it still owned, for the
+/// caller to drop.
+pub fn unregister_context(context: ContextId) -> Vec<(Resource, String)> {
+    CONTEXTS.remove(&context);
+    let owned: Vec<(Resource, String)> = OWNERS
+        .iter()
+        .filter(|entry| *entry.value() == context)
+        .map(|entry| entry.key().clone())
+        .collect();
+    for key in &owned {
+        OWNERS.remove(key);
+    }
+    owned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RegistryMode, Resource, check_access, claim, context_registered, register_context, release,
+        set_registry_mode, unregister_context,
+    };
+
+    #[test]
+    fn shared_ids_work_across_contexts_until_one_side_is_isolated() {
+        let main = register_context();
+        let worker = register_context();
+        claim(main, Resource::Session, "shared-session");
+
+        assert!(check_access(worker, Resource::Session, "shared-session").is_ok());
+        // The same ID in another registry is a different resource.
+        assert!(check_access(worker, Resource::Transport, "shared-session").is_ok());
+
+        let isolated = register_context();
+        set_registry_mode(isolated, RegistryMode::Isolated).unwrap();
+        let error = check_access(isolated, Resource::Session, "shared-session").unwrap_err();
+        assert_eq!(error.to_string(), "Session 'shared-session' not found");
+
+        claim(isolated, Resource::Transport, "private-transport");
+        assert!(check_access(isolated, Resource::Transport, "private-transport").is_ok());
+        assert!(check_access(main, Resource::Transport, "private-transport").is_err());
+        assert!(set_registry_mode(isolated, RegistryMode::Shared).is_err());
+
+        release(Resource::Session, "shared-session");
+        unregister_context(main);
+        unregister_context(worker);
+        unregister_context(isolated);
+    }
+
+    #[test]
+    fn exiting_context_hands_back_what_it_owned() {
+        let worker = register_context();
+        let other = register_context();
+        claim(worker, Resource::Session, "worker-session");
+        claim(worker, Resource::Transport, "worker-transport");
+        claim(worker, Resource::Session, "closed-session");
+        release(Resource::Session, "closed-session");
+        claim(other, Resource::Session, "other-session");
+        claim(worker, Resource::WebSocket, "7");
+
+        assert!(context_registered(worker));
+        let mut owned = unregister_context(worker);
+        assert!(!context_registered(worker));
+        owned.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            owned,
+            vec![
+                (Resource::WebSocket, "7".to_owned()),
+                (Resource::Session, "worker-session".to_owned()),
+                (Resource::Transport, "worker-transport".to_owned()),
+            ]
+        );
+        assert!(unregister_context(worker).is_empty());
+        assert_eq!(
+            unregister_context(other),
+            vec![(Resource::Session, "other-session".to_owned())]
+        );
+    }
+
+    #[test]
+    fn open_sockets_do_not_pin_the_registry_mode() {
+        let context = register_context();
+        claim(context, Resource::WebSocket, "8");
+        assert!(set_registry_mode(context, RegistryMode::Isolated).is_ok());
+        release(Resource::WebSocket, "8");
+        unregister_context(context);
+    }
+}
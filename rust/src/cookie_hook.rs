@@ -0,0 +1,157 @@
+use cookie::{Cookie, Expiration, SameSite};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wreq::Uri;
+use wreq::cookie::{CookieStore, Cookies, Jar};
+use wreq::header::HeaderValue;
+
+/// A cookie a response stored in a session's jar, scoped the way the jar scoped it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    /// Milliseconds since the Unix epoch; `None` for a session cookie.
+    pub expires_at: Option<u64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<&'static str>,
+    /// The header deleted the cookie (`Max-Age=0` or an `Expires` in the past).
+    pub removed: bool,
+}
+
+/// Told about every cookie stored in a session's jar, with the URL of the response that set it.
+pub type SetCookieHook = Arc<dyn Fn(StoredCookie, &str) + Send + Sync>;
+
+impl StoredCookie {
+    /// Parse a `Set-Cookie` value received from `host` for a request to `request_path`.
+    pub fn parse(header: &str, host: &str, request_path: &str, now: SystemTime) -> Option<Self> {
+        let cookie = Cookie::parse(header).ok()?;
+        // Max-Age wins over Expires (RFC 6265, section 5.3).
+        let expires = match (cookie.max_age(), cookie.expires()) {
+            (Some(age), _) => Some(now + Duration::try_from(age).unwrap_or_default()),
+            (None, Some(Expiration::DateTime(at))) => Some(SystemTime::from(at)),
+            _ => None,
+        };
+        let removed = expires.is_some_and(|at| at <= now);
+        Some(Self {
+            name: cookie.name().to_owned(),
+            value: cookie.value().to_owned(),
+            domain: cookie
+                .domain()
+                .unwrap_or(host)
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .to_owned(),
+            path: cookie
+                .path()
+                .map_or_else(|| default_path(request_path), str::to_owned),
+            expires_at: expires.map(|at| {
+                let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+                u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX)
+            }),
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            same_site: cookie.same_site().map(|same_site| match same_site {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            }),
+            removed,
+        })
+    }
+}
+
+/// The default cookie path of RFC 6265, section 5.1.4: the request path up to its last `/`.
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(end) if end > 0 && request_path.starts_with('/') => request_path[..end].to_owned(),
+        _ => "/".to_owned(),
+    }
+}
+
+/// A session's jar that calls its [`SetCookieHook`] after storing each cookie.
+pub struct NotifyingJar {
+    jar: Arc<Jar>,
+    hook: SetCookieHook,
+}
+
+impl NotifyingJar {
+    pub fn new(jar: Arc<Jar>, hook: SetCookieHook) -> Self {
+        Self { jar, hook }
+    }
+}
+
+impl CookieStore for NotifyingJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, uri: &Uri) {
+        let headers: Vec<&HeaderValue> = cookie_headers.collect();
+        self.jar.set_cookies(&mut headers.iter().copied(), uri);
+
+        let url = uri.to_string();
+        let host = uri.host().unwrap_or_default();
+        let now = SystemTime::now();
+        for header in headers {
+            let stored = std::str::from_utf8(header.as_bytes())
+                .ok()
+                .and_then(|header| StoredCookie::parse(header, host, uri.path(), now));
+            if let Some(stored) = stored {
+                (self.hook)(stored, &url);
+            }
+        }
+    }
+
+    fn cookies(&self, uri: &Uri) -> Cookies {
+        self.jar.cookies(uri)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StoredCookie;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn scopes_cookies_like_the_jar() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let session = StoredCookie::parse("sid=abc; HttpOnly", "example.com", "/a/b", now).unwrap();
+        assert_eq!(session.name, "sid");
+        assert_eq!(session.value, "abc");
+        assert_eq!(session.domain, "example.com");
+        assert_eq!(session.path, "/a");
+        assert_eq!(session.expires_at, None);
+        assert!(session.http_only && !session.secure && !session.removed);
+
+        let scoped = StoredCookie::parse(
+            "token=1; Domain=.Example.com; Path=/api; Max-Age=60; \
+             Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure; SameSite=None",
+            "www.example.com",
+            "/",
+            now,
+        )
+        .unwrap();
+        assert_eq!(scoped.domain, "Example.com");
+        assert_eq!(scoped.path, "/api");
+        assert_eq!(scoped.expires_at, Some(1_060_000));
+        assert_eq!(scoped.same_site, Some("None"));
+        assert!(scoped.secure && !scoped.removed);
+    }
+
+    #[test]
+    fn reports_deletions() {
+        let now = SystemTime::now();
+        let cleared = StoredCookie::parse("sid=; Max-Age=0", "example.com", "/", now).unwrap();
+        assert!(cleared.removed);
+        let expired = StoredCookie::parse(
+            "sid=; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            "example.com",
+            "/",
+            now,
+        )
+        .unwrap();
+        assert!(expired.removed);
+        assert_eq!(expired.expires_at, Some(0));
+        assert!(StoredCookie::parse("no equals sign", "example.com", "/", now).is_none());
+    }
+}
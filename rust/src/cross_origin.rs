@@ -0,0 +1,179 @@
+use anyhow::{Result, anyhow};
+use url::Url;
+use wreq::header::{
+    CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, HeaderName, LOCATION, TRANSFER_ENCODING,
+};
+use wreq::{Method, StatusCode, Uri, redirect};
+
+/// Hops that may be followed for one request, matching wreq's default redirect limit.
+pub const MAX_CROSS_ORIGIN_HOPS: usize = 10;
+
+/// Which request headers survive a redirect to another origin.
+///
+/// wreq strips a fixed set of credential headers on its own. With a policy, cross-origin hops are
+/// stopped inside the redirect policy and followed by the caller instead, with exactly the
+/// configured headers removed.
+#[derive(Debug, Clone)]
+pub struct CrossOriginPolicy {
+    strip: Vec<HeaderName>,
+}
+
+/// The request to send for a cross-origin redirect.
+pub struct NextHop {
+    pub request: wreq::Request,
+    /// Whether the request body is sent again (307/308), rather than dropped for a GET.
+    pub keeps_body: bool,
+}
+
+impl CrossOriginPolicy {
+    pub fn new(strip: &[String]) -> Result<Self> {
+        let strip = strip
+            .iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.trim().as_bytes())
+                    .map_err(|_| anyhow!("Invalid header name in stripOnCrossOrigin: '{}'", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { strip })
+    }
+
+    /// Wrap a redirect policy so hops to another origin stop with the redirect response,
+    /// leaving them to [`CrossOriginPolicy::next_hop`].
+    pub fn stopping(inner: redirect::Policy) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| {
+            let crossing = attempt
+                .previous
+                .last()
+                .is_some_and(|from| is_cross_origin(from, &attempt.uri));
+            if crossing {
+                attempt.stop()
+            } else {
+                inner.redirect(attempt)
+            }
+        })
+    }
+
+    /// Build the follow-up for `response` when it redirects to another origin. `request` is the
+    /// request that produced it, without a body.
+    pub fn next_hop(
+        &self,
+        response: &wreq::Response,
+        mut request: wreq::Request,
+    ) -> Option<NextHop> {
+        let keeps_body = match response.status() {
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => request.method() != Method::POST,
+            StatusCode::SEE_OTHER => false,
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => true,
+            _ => return None,
+        };
+
+        let location = response.headers().get(LOCATION)?.to_str().ok()?;
+        let next = resolve_location(location, response.uri())?;
+        if !is_cross_origin(response.uri(), &next) {
+            return None;
+        }
+
+        // Same method changes as wreq's own redirect handling (RFC 9110 section 15.4).
+        if !keeps_body {
+            if request.method() != Method::HEAD {
+                *request.method_mut() = Method::GET;
+            }
+            for name in [
+                CONTENT_TYPE,
+                CONTENT_LENGTH,
+                CONTENT_ENCODING,
+                TRANSFER_ENCODING,
+            ] {
+                request.headers_mut().remove(name);
+            }
+        }
+        for name in &self.strip {
+            request.headers_mut().remove(name);
+        }
+        *request.uri_mut() = next;
+
+        Some(NextHop {
+            request,
+            keeps_body,
+        })
+    }
+}
+
+/// Origins differ when the scheme, host or effective port does.
+fn is_cross_origin(from: &Uri, to: &Uri) -> bool {
+    let port = |uri: &Uri| {
+        uri.port_u16().or(match uri.scheme_str() {
+            Some("https") => Some(443),
+            Some("http") => Some(80),
+            _ => None,
+        })
+    };
+    from.scheme_str() != to.scheme_str()
+        || !from
+            .host()
+            .zip(to.host())
+            .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b))
+        || port(from) != port(to)
+}
+
+fn resolve_location(location: &str, base: &Uri) -> Option<Uri> {
+    Url::parse(&base.to_string())
+        .ok()?
+        .join(location)
+        .ok()?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrossOriginPolicy, is_cross_origin, resolve_location};
+
+    fn uri(value: &str) -> wreq::Uri {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn compares_scheme_host_and_effective_port() {
+        assert!(!is_cross_origin(
+            &uri("https://Example.com/a"),
+            &uri("https://example.com:443/b")
+        ));
+        assert!(is_cross_origin(
+            &uri("https://example.com/"),
+            &uri("http://example.com/")
+        ));
+        assert!(is_cross_origin(
+            &uri("https://example.com/"),
+            &uri("https://api.example.com/")
+        ));
+        assert!(is_cross_origin(
+            &uri("http://127.0.0.1:8080/"),
+            &uri("http://127.0.0.1:8081/")
+        ));
+    }
+
+    #[test]
+    fn resolves_locations_against_the_response_url() {
+        let base = uri("https://example.com/a/b?q=1");
+        assert_eq!(
+            resolve_location("//cdn.example.com/x", &base).unwrap(),
+            "https://cdn.example.com/x"
+        );
+        assert_eq!(
+            resolve_location("../c", &base).unwrap(),
+            "https://example.com/c"
+        );
+        assert_eq!(
+            resolve_location("http://other.test/", &base).unwrap(),
+            "http://other.test/"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_header_names() {
+        assert!(CrossOriginPolicy::new(&["authorization".into(), "X-Api-Key".into()]).is_ok());
+        assert!(CrossOriginPolicy::new(&["bad header".into()]).is_err());
+    }
+}
@@ -0,0 +1,663 @@
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::cassette::base64_encode;
+
+/// A fully resolved request, ready to be rendered as a curl command line.
+#[derive(Debug)]
+pub struct CurlRequest<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+    pub headers: &'a [(String, String)],
+    pub body: Option<&'a [u8]>,
+    pub proxy: Option<&'a str>,
+    pub insecure: bool,
+    pub timeout_ms: u64,
+    pub follow_redirects: bool,
+}
+
+/// Quote a value for POSIX shells. Text is single-quoted; anything with control characters or
+/// invalid UTF-8 falls back to ANSI-C `$'...'` quoting so every byte survives a copy-paste.
+fn shell_quote(value: &[u8]) -> String {
+    let printable = std::str::from_utf8(value).ok().filter(|text| {
+        !text
+            .chars()
+            .any(|c| c.is_control() && c != '\n' && c != '\t')
+    });
+    if let Some(text) = printable {
+        return format!("'{}'", text.replace('\'', r"'\''"));
+    }
+
+    let mut quoted = String::from("$'");
+    for &byte in value {
+        match byte {
+            b'\\' => quoted.push_str(r"\\"),
+            b'\'' => quoted.push_str(r"\'"),
+            b'\n' => quoted.push_str(r"\n"),
+            b'\r' => quoted.push_str(r"\r"),
+            b'\t' => quoted.push_str(r"\t"),
+            0x20..=0x7e => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+pub fn render(request: &CurlRequest) -> String {
+    let mut args = vec![format!("curl {}", shell_quote(request.url.as_bytes()))];
+
+    match request.method {
+        "GET" => {}
+        "HEAD" => args.push("--head".to_string()),
+        method
+            if method
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-') =>
+        {
+            args.push(format!("-X {}", method))
+        }
+        method => args.push(format!("-X {}", shell_quote(method.as_bytes()))),
+    }
+
+    for (name, value) in request.headers {
+        args.push(format!(
+            "-H {}",
+            shell_quote(format!("{}: {}", name, value).as_bytes())
+        ));
+    }
+
+    // The profile advertises compressed encodings; let curl decode the response like wreq does.
+    if request
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("accept-encoding"))
+    {
+        args.push("--compressed".to_string());
+    }
+
+    if let Some(body) = request.body {
+        args.push(format!("--data-binary {}", shell_quote(body)));
+    }
+
+    if let Some(proxy) = request.proxy {
+        args.push(format!("--proxy {}", shell_quote(proxy.as_bytes())));
+    }
+
+    if request.insecure {
+        args.push("--insecure".to_string());
+    }
+
+    if request.follow_redirects {
+        args.push("--location".to_string());
+    }
+
+    if request.timeout_ms > 0 {
+        args.push(format!("--max-time {}", request.timeout_ms as f64 / 1000.0));
+    }
+
+    args.join(" \\\n  ")
+}
+
+/// Request options recovered from a curl command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedCurl {
+    pub url: String,
+    pub method: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    pub proxy: Option<String>,
+    pub insecure: bool,
+    pub follow_redirects: bool,
+    pub timeout_ms: Option<u64>,
+}
+
+// Flags that take a value but have no equivalent in the request options; their value is skipped.
+const IGNORED_VALUE_FLAGS: &[&str] = &[
+    "-o",
+    "--output",
+    "-w",
+    "--write-out",
+    "--connect-timeout",
+    "--retry",
+    "--cacert",
+    "--cert",
+    "--key",
+    "--resolve",
+    "--limit-rate",
+    "-c",
+    "--cookie-jar",
+    "--max-redirs",
+    "-D",
+    "--dump-header",
+    "--retry-delay",
+    "--retry-max-time",
+    "--capath",
+    "-E",
+];
+
+// Flags without a request-level meaning. Any other flag is rejected rather than guessed at, so
+// its value is never mistaken for the URL.
+const IGNORED_FLAGS: &[&str] = &[
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "-f",
+    "--fail",
+    "--fail-with-body",
+    "-#",
+    "--progress-bar",
+    "--no-progress-meter",
+    "-N",
+    "--no-buffer",
+    "-g",
+    "--globoff",
+    "-4",
+    "--ipv4",
+    "-6",
+    "--ipv6",
+    "--compressed",
+    "--http1.1",
+    "--http2",
+    "--http2-prior-knowledge",
+    "--http3",
+    "--tcp-nodelay",
+    "--no-keepalive",
+    "--path-as-is",
+    "--ssl-no-revoke",
+];
+
+// Short flags that take a value, which may be attached (`-XPOST`) or the next word. Other short
+// flags can be grouped (`-sSL`).
+const SHORT_VALUE_FLAGS: &str = "AbcCdDeEFHKmoPQrTtuUwxXyYz";
+
+/// The options in one word: a long option and its `=value`, one or more grouped short flags,
+/// or a plain argument.
+fn options_in(word: &str) -> Vec<(String, Option<String>)> {
+    if word.starts_with("--") {
+        return match word.split_once('=') {
+            Some((flag, value)) => vec![(flag.to_owned(), Some(value.to_owned()))],
+            None => vec![(word.to_owned(), None)],
+        };
+    }
+    let Some(letters) = word.strip_prefix('-').filter(|letters| letters.len() > 1) else {
+        return vec![(word.to_owned(), None)];
+    };
+
+    let mut options = Vec::new();
+    for (index, letter) in letters.char_indices() {
+        let flag = format!("-{letter}");
+        if SHORT_VALUE_FLAGS.contains(letter) {
+            let rest = &letters[index + letter.len_utf8()..];
+            options.push((flag, (!rest.is_empty()).then(|| rest.to_owned())));
+            break;
+        }
+        options.push((flag, None));
+    }
+    options
+}
+
+/// Encode `text` as curl's `--data-urlencode` does: everything but unreserved characters is
+/// percent-encoded, spaces included.
+fn url_encode(text: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(text.len());
+    for &byte in text {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte);
+        } else {
+            encoded.extend_from_slice(format!("%{byte:02X}").as_bytes());
+        }
+    }
+    encoded
+}
+
+/// The body part for one `--data-urlencode` value: `content`, `=content`, or `name=content`.
+fn data_urlencode(value: &[u8]) -> Result<Vec<u8>> {
+    let (name, content) = match value.iter().position(|&byte| byte == b'=') {
+        Some(at) => (&value[..at], &value[at + 1..]),
+        None => (&value[..0], value),
+    };
+    if value.first() == Some(&b'@') || name.last() == Some(&b'@') {
+        bail!(
+            "Reading curl data from a file ({}) is not supported",
+            lossy(value)
+        );
+    }
+    if name.is_empty() {
+        return Ok(url_encode(content));
+    }
+    let mut part = name.to_vec();
+    part.push(b'=');
+    part.extend(url_encode(content));
+    Ok(part)
+}
+
+/// Split a shell command line into words, honouring single quotes, double quotes, ANSI-C
+/// `$'...'` strings, backslash escapes and line continuations.
+fn split_words(command: &str) -> Result<Vec<Vec<u8>>> {
+    let bytes = command.as_bytes();
+    let mut words = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                if let Some(word) = current.take() {
+                    words.push(word);
+                }
+                i += 1;
+            }
+            b'\\' => {
+                match bytes.get(i + 1) {
+                    // Line continuation
+                    Some(b'\n') => {}
+                    Some(b'\r') if bytes.get(i + 2) == Some(&b'\n') => i += 1,
+                    Some(&next) => current.get_or_insert_with(Vec::new).push(next),
+                    None => {}
+                }
+                i += 2;
+            }
+            b'\'' => {
+                let end = command[i + 1..]
+                    .find('\'')
+                    .ok_or_else(|| anyhow!("Unterminated single quote in curl command"))?;
+                current
+                    .get_or_insert_with(Vec::new)
+                    .extend_from_slice(&bytes[i + 1..i + 1 + end]);
+                i += end + 2;
+            }
+            b'"' => {
+                let word = current.get_or_insert_with(Vec::new);
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None => bail!("Unterminated double quote in curl command"),
+                        Some(b'"') => break,
+                        Some(b'\\')
+                            if matches!(bytes.get(i + 1), Some(b'"' | b'\\' | b'$' | b'`')) =>
+                        {
+                            word.push(bytes[i + 1]);
+                            i += 2;
+                        }
+                        Some(b'\\') if bytes.get(i + 1) == Some(&b'\n') => i += 2,
+                        Some(&other) => {
+                            word.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+            }
+            b'$' if bytes.get(i + 1) == Some(&b'\'') => {
+                let word = current.get_or_insert_with(Vec::new);
+                i += 2;
+                loop {
+                    match bytes.get(i) {
+                        None => bail!("Unterminated $'...' string in curl command"),
+                        Some(b'\'') => break,
+                        Some(b'\\') => {
+                            let (decoded, consumed) = decode_ansi_c_escape(&bytes[i + 1..]);
+                            word.extend_from_slice(&decoded);
+                            i += 1 + consumed;
+                        }
+                        Some(&other) => {
+                            word.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+            }
+            _ => {
+                current.get_or_insert_with(Vec::new).push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    if let Some(word) = current {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Decode the escape following a backslash inside `$'...'`, returning the bytes and how many
+/// input bytes were consumed.
+fn decode_ansi_c_escape(rest: &[u8]) -> (Vec<u8>, usize) {
+    let Some(&kind) = rest.first() else {
+        return (vec![b'\\'], 0);
+    };
+
+    let simple = match kind {
+        b'n' => Some(b'\n'),
+        b'r' => Some(b'\r'),
+        b't' => Some(b'\t'),
+        b'0' => Some(0),
+        b'\\' => Some(b'\\'),
+        b'\'' => Some(b'\''),
+        b'"' => Some(b'"'),
+        _ => None,
+    };
+    if let Some(byte) = simple {
+        return (vec![byte], 1);
+    }
+
+    if kind == b'x' {
+        let digits = rest[1..]
+            .iter()
+            .take(2)
+            .take_while(|byte| byte.is_ascii_hexdigit())
+            .count();
+        let value = std::str::from_utf8(&rest[1..1 + digits])
+            .ok()
+            .filter(|_| digits > 0)
+            .and_then(|text| u8::from_str_radix(text, 16).ok());
+        if let Some(value) = value {
+            return (vec![value], 1 + digits);
+        }
+    }
+
+    (vec![b'\\', kind], 1)
+}
+
+fn lossy(word: &[u8]) -> String {
+    String::from_utf8_lossy(word).into_owned()
+}
+
+/// Parse a curl command line (as copied from browser devtools) into request options.
+pub fn parse(command: &str) -> Result<ParsedCurl> {
+    let mut words = split_words(command)?.into_iter();
+    match words.next() {
+        Some(first) if first == b"curl" => {}
+        _ => bail!("Command must start with `curl`"),
+    }
+
+    let mut parsed = ParsedCurl::default();
+    let mut method: Option<String> = None;
+    let mut data: Vec<Vec<u8>> = Vec::new();
+    let mut url: Option<String> = None;
+    let mut head = false;
+    let mut get = false;
+
+    while let Some(word) = words.next() {
+        let word = lossy(&word);
+        for (flag, inline_value) in options_in(&word) {
+            let mut value = |name: &str| -> Result<Vec<u8>> {
+                match &inline_value {
+                    Some(value) => Ok(value.clone().into_bytes()),
+                    None => words
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing value for curl option {}", name)),
+                }
+            };
+
+            match flag.as_str() {
+                "-X" | "--request" => method = Some(lossy(&value(&flag)?).to_ascii_uppercase()),
+                "-H" | "--header" => {
+                    let header = lossy(&value(&flag)?);
+                    let (name, header_value) = header
+                        .split_once(':')
+                        .ok_or_else(|| anyhow!("Invalid header in curl command: {}", header))?;
+                    parsed
+                        .headers
+                        .push((name.trim().to_owned(), header_value.trim().to_owned()));
+                }
+                "-d" | "--data" | "--data-ascii" | "--data-binary" | "--data-raw" => {
+                    let payload = value(&flag)?;
+                    if flag != "--data-raw" && payload.first() == Some(&b'@') {
+                        bail!(
+                            "Reading curl data from a file ({}) is not supported",
+                            lossy(&payload)
+                        );
+                    }
+                    data.push(payload);
+                }
+                "--data-urlencode" => data.push(data_urlencode(&value(&flag)?)?),
+                "-u" | "--user" => {
+                    let credentials = value(&flag)?;
+                    if !credentials.contains(&b':') {
+                        bail!("curl --user needs a password, as in user:password");
+                    }
+                    parsed.headers.push((
+                        "Authorization".to_owned(),
+                        format!("Basic {}", base64_encode(&credentials)),
+                    ));
+                }
+                "-F" | "--form" => {
+                    bail!("Multipart form data (-F) in curl commands is not supported")
+                }
+                "--json" => {
+                    data.push(value(&flag)?);
+                    parsed
+                        .headers
+                        .push(("Content-Type".to_owned(), "application/json".to_owned()));
+                    parsed
+                        .headers
+                        .push(("Accept".to_owned(), "application/json".to_owned()));
+                }
+                "-b" | "--cookie" => {
+                    let cookie = lossy(&value(&flag)?);
+                    if !cookie.contains('=') {
+                        bail!(
+                            "Reading curl cookies from a file ({}) is not supported",
+                            cookie
+                        );
+                    }
+                    parsed.headers.push(("Cookie".to_owned(), cookie));
+                }
+                "-A" | "--user-agent" => {
+                    let agent = lossy(&value(&flag)?);
+                    parsed.headers.push(("User-Agent".to_owned(), agent));
+                }
+                "-e" | "--referer" => {
+                    let referer = lossy(&value(&flag)?);
+                    parsed.headers.push(("Referer".to_owned(), referer));
+                }
+                "-x" | "--proxy" => parsed.proxy = Some(lossy(&value(&flag)?)),
+                "-m" | "--max-time" => {
+                    let seconds = lossy(&value(&flag)?);
+                    let seconds: f64 = seconds
+                        .parse()
+                        .with_context(|| format!("Invalid --max-time value: {}", seconds))?;
+                    parsed.timeout_ms = Some((seconds * 1000.0).round() as u64);
+                }
+                "--url" => url = Some(lossy(&value(&flag)?)),
+                "-k" | "--insecure" => parsed.insecure = true,
+                "-L" | "--location" | "--location-trusted" => parsed.follow_redirects = true,
+                "-I" | "--head" => head = true,
+                "-G" | "--get" => get = true,
+                flag if IGNORED_VALUE_FLAGS.contains(&flag) => {
+                    value(flag)?;
+                }
+                flag if IGNORED_FLAGS.contains(&flag) => {}
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    bail!("Unsupported curl option: {}", flag)
+                }
+                _ if url.is_none() => url = Some(word.clone()),
+                _ => bail!("Unexpected argument in curl command: {}", word),
+            }
+        }
+    }
+
+    parsed.url = url.ok_or_else(|| anyhow!("curl command does not contain a URL"))?;
+
+    let body = (!data.is_empty()).then(|| data.join(&b'&'));
+    if get {
+        if let Some(query) = body {
+            let separator = if parsed.url.contains('?') { '&' } else { '?' };
+            parsed.url = format!("{}{}{}", parsed.url, separator, lossy(&query));
+        }
+    } else if let Some(body) = body {
+        // curl sends form data unless the command already set a content type.
+        if !parsed
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        {
+            parsed.headers.push((
+                "Content-Type".to_owned(),
+                "application/x-www-form-urlencoded".to_owned(),
+            ));
+        }
+        parsed.body = Some(body);
+    }
+
+    parsed.method = match method {
+        Some(method) => method,
+        None if head => "HEAD".to_owned(),
+        None if parsed.body.is_some() => "POST".to_owned(),
+        None => "GET".to_owned(),
+    };
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CurlRequest, parse, render, shell_quote};
+
+    #[test]
+    fn quotes_text_and_binary_values() {
+        assert_eq!(shell_quote(b"it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(&[0x00, b'a', 0xff]), r"$'\x00a\xff'");
+    }
+
+    #[test]
+    fn renders_a_post_request() {
+        let headers = vec![
+            ("accept-encoding".to_string(), "gzip, br".to_string()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ];
+        let command = render(&CurlRequest {
+            method: "POST",
+            url: "https://example.com/api",
+            headers: &headers,
+            body: Some(b"{\"a\":1}"),
+            proxy: Some("http://127.0.0.1:8080"),
+            insecure: true,
+            timeout_ms: 1500,
+            follow_redirects: true,
+        });
+
+        assert_eq!(
+            command,
+            [
+                "curl 'https://example.com/api'",
+                "-X POST",
+                "-H 'accept-encoding: gzip, br'",
+                "-H 'Content-Type: application/json'",
+                "--compressed",
+                "--data-binary '{\"a\":1}'",
+                "--proxy 'http://127.0.0.1:8080'",
+                "--insecure",
+                "--location",
+                "--max-time 1.5",
+            ]
+            .join(" \\\n  ")
+        );
+    }
+
+    #[test]
+    fn parses_a_devtools_command() {
+        let parsed = parse(
+            "curl 'https://example.com/api?x=1' \\\n  -H 'accept: application/json' \\\n  -H \"X-Quote: a\\\"b\" \\\n  -b 'sid=abc' \\\n  --data-raw $'{\"a\":\"it\\'s\"}' \\\n  --compressed -k -L --proxy http://127.0.0.1:8080 -m 2.5",
+        )
+        .unwrap();
+
+        assert_eq!(parsed.url, "https://example.com/api?x=1");
+        assert_eq!(parsed.method, "POST");
+        assert_eq!(
+            parsed.headers,
+            vec![
+                ("accept".to_string(), "application/json".to_string()),
+                ("X-Quote".to_string(), "a\"b".to_string()),
+                ("Cookie".to_string(), "sid=abc".to_string()),
+                (
+                    "Content-Type".to_string(),
+                    "application/x-www-form-urlencoded".to_string()
+                ),
+            ]
+        );
+        assert_eq!(parsed.body.as_deref(), Some(&b"{\"a\":\"it's\"}"[..]));
+        assert_eq!(parsed.proxy.as_deref(), Some("http://127.0.0.1:8080"));
+        assert!(parsed.insecure);
+        assert!(parsed.follow_redirects);
+        assert_eq!(parsed.timeout_ms, Some(2500));
+    }
+
+    #[test]
+    fn parses_what_render_produces() {
+        let headers = vec![("X-Trace".to_string(), "it's me".to_string())];
+        let command = render(&CurlRequest {
+            method: "PUT",
+            url: "https://example.com/items/1",
+            headers: &headers,
+            body: Some(&[0x00, 0xff]),
+            proxy: None,
+            insecure: false,
+            timeout_ms: 0,
+            follow_redirects: false,
+        });
+
+        let parsed = parse(&command).unwrap();
+        assert_eq!(parsed.method, "PUT");
+        assert_eq!(parsed.headers[0], headers[0]);
+        assert_eq!(parsed.body.as_deref(), Some(&[0x00, 0xff][..]));
+        assert!(!parsed.follow_redirects);
+    }
+
+    #[test]
+    fn parses_attached_values_and_grouped_flags() {
+        let parsed = parse("curl -XPUT -sSL '-HX-Token: abc' -dx=1 https://example.com").unwrap();
+        assert_eq!(parsed.method, "PUT");
+        assert_eq!(parsed.body.as_deref(), Some(&b"x=1"[..]));
+        assert!(parsed.follow_redirects);
+        assert_eq!(
+            parsed.headers[0],
+            ("X-Token".to_string(), "abc".to_string())
+        );
+        assert_eq!(parsed.url, "https://example.com");
+
+        let parsed = parse("curl -sL https://example.com/a").unwrap();
+        assert!(parsed.follow_redirects);
+        assert_eq!(parsed.url, "https://example.com/a");
+    }
+
+    #[test]
+    fn keeps_option_values_out_of_the_url() {
+        let parsed = parse("curl -u user:pass https://example.com").unwrap();
+        assert_eq!(parsed.url, "https://example.com");
+        assert_eq!(
+            parsed.headers,
+            vec![(
+                "Authorization".to_string(),
+                "Basic dXNlcjpwYXNz".to_string()
+            )]
+        );
+
+        let parsed =
+            parse("curl --data-urlencode 'q=a b&c' --data-urlencode x https://example.com")
+                .unwrap();
+        assert_eq!(parsed.url, "https://example.com");
+        assert_eq!(parsed.body.as_deref(), Some(&b"q=a%20b%26c&x"[..]));
+
+        let error = parse("curl -F a=b https://example.com").unwrap_err();
+        assert!(error.to_string().contains("-F"));
+        let error = parse("curl --cert-type PEM https://example.com").unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported curl option: --cert-type");
+    }
+
+    #[test]
+    fn rejects_unsupported_commands() {
+        assert!(parse("wget https://example.com").is_err());
+        assert!(parse("curl -d @payload.json https://example.com").is_err());
+        assert!(parse("curl 'https://example.com").is_err());
+        assert!(parse("curl -H").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::{Error, Result};
+use bytes::Bytes;
+use futures_util::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::abort::TimedOut;
+use crate::throttle::RequestBodyStream;
+
+/// Per-phase limits of a single request.
+///
+/// - `timeout` covers the whole request, from connecting until the response body is read.
+///   With a write timeout it is paused while the request body is being sent.
+/// - `write_timeout` is how long the connection may sit on a chunk of the request body
+///   without asking for the next one.
+/// - `total` is a hard wall-clock deadline for everything, uploads included.
+pub struct RequestDeadlines {
+    start: Instant,
+    timeout: Option<Duration>,
+    total: Option<Duration>,
+    upload: Option<(UploadProgress, Duration)>,
+}
+
+impl RequestDeadlines {
+    /// `timeout_ms` of 0 disables the request timeout. `write_timeout_ms` only applies to
+    /// requests with a body.
+    pub fn new(timeout_ms: u64, total_ms: Option<u64>, write_timeout_ms: Option<u64>) -> Self {
+        Self {
+            start: Instant::now(),
+            timeout: (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms)),
+            total: total_ms.map(Duration::from_millis),
+            upload: write_timeout_ms.map(|ms| (UploadProgress::new(), Duration::from_millis(ms))),
+        }
+    }
+
+    /// The timeout wreq can enforce on its own. Uploads with a write timeout are timed here instead,
+    /// since wreq's timer cannot be paused.
+    pub fn native_timeout(&self) -> Option<Duration> {
+        self.upload.is_none().then_some(self.timeout).flatten()
+    }
+
+    /// What is left of [`RequestDeadlines::native_timeout`], for requests sent after the first.
+    pub fn remaining_native_timeout(&self) -> Option<Duration> {
+        self.native_timeout()
+            .map(|timeout| timeout.saturating_sub(self.start.elapsed()))
+    }
+
+    pub fn upload_progress(&self) -> Option<&UploadProgress> {
+        self.upload.as_ref().map(|(progress, _)| progress)
+    }
+
+    /// Resolve with an error once a limit is exceeded while waiting for the response headers.
+    /// Never resolves when no limit applies.
+    pub async fn expired(&self) -> Error {
+        let total = async {
+            match self.total {
+                Some(total) => {
+                    tokio::time::sleep_until(self.start + total).await;
+                    Error::new(TimedOut(format!(
+                        "Request exceeded its total deadline of {} ms",
+                        total.as_millis()
+                    )))
+                }
+                None => std::future::pending().await,
+            }
+        };
+
+        let upload = async {
+            match &self.upload {
+                Some((progress, write_timeout)) => {
+                    self.watch_upload(progress, *write_timeout).await
+                }
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            error = total => error,
+            error = upload => error,
+        }
+    }
+
+    async fn watch_upload(&self, progress: &UploadProgress, write_timeout: Duration) -> Error {
+        let mut changes = progress.state.subscribe();
+        loop {
+            let state = *changes.borrow_and_update();
+            let (check_at, error) = match state {
+                UploadState::Sending { since, .. } if since.elapsed() >= write_timeout => {
+                    return Error::new(TimedOut(format!(
+                        "Request body write stalled for more than {} ms",
+                        write_timeout.as_millis()
+                    )));
+                }
+                UploadState::Sending { since, .. } => (Some(since + write_timeout), None),
+                UploadState::Producing { .. } => (None, None),
+                UploadState::Waiting | UploadState::Finished { .. } => match self.timeout {
+                    Some(timeout) => (
+                        Some(self.timeout_deadline(state, timeout)),
+                        Some(Error::new(TimedOut(format!(
+                            "Request timed out after {} ms",
+                            timeout.as_millis()
+                        )))),
+                    ),
+                    None => (None, None),
+                },
+            };
+
+            let sleep = async {
+                match check_at {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            };
+            // `self` keeps a sender alive, so `changed()` never reports a closed channel.
+            tokio::select! {
+                _ = sleep => {
+                    if let Some(error) = error {
+                        return error;
+                    }
+                }
+                _ = changes.changed() => {}
+            }
+        }
+    }
+
+    // The request timeout skips the time spent sending the body.
+    fn timeout_deadline(&self, state: UploadState, timeout: Duration) -> Instant {
+        let uploading = match state {
+            UploadState::Finished { started, at } => at - started,
+            _ => Duration::ZERO,
+        };
+        self.start + timeout + uploading
+    }
+
+    /// Deadline for reading the response body, once the response headers have arrived.
+    pub fn body_deadline(&self) -> Option<Instant> {
+        let total = self.total.map(|total| self.start + total);
+        let timeout = match (&self.upload, self.timeout) {
+            (Some((progress, _)), Some(timeout)) => {
+                let state = match *progress.state.borrow() {
+                    // The server answered before the upload finished; stop pausing the clock.
+                    UploadState::Sending { started, .. } | UploadState::Producing { started } => {
+                        UploadState::Finished {
+                            started,
+                            at: Instant::now(),
+                        }
+                    }
+                    state => state,
+                };
+                Some(self.timeout_deadline(state, timeout))
+            }
+            _ => None,
+        };
+
+        match (total, timeout) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Run `future`, failing once `deadline` passes.
+pub async fn within<T>(
+    deadline: Option<Instant>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future)
+            .await
+            .map_err(|_| {
+                TimedOut("Request deadline exceeded while reading the response body".to_string())
+            })?,
+        None => future.await,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum UploadState {
+    /// The connection has not asked for body data yet.
+    Waiting,
+    /// The body source is preparing the next chunk (e.g. waiting on an upload rate limit).
+    Producing {
+        started: Instant,
+    },
+    /// A chunk was handed to the connection at `since` and it has not asked for more.
+    Sending {
+        started: Instant,
+        since: Instant,
+    },
+    Finished {
+        started: Instant,
+        at: Instant,
+    },
+}
+
+/// Upload progress shared between a request body and the watchdog that times it.
+#[derive(Debug, Clone)]
+pub struct UploadProgress {
+    state: watch::Sender<UploadState>,
+}
+
+impl UploadProgress {
+    fn new() -> Self {
+        Self {
+            state: watch::Sender::new(UploadState::Waiting),
+        }
+    }
+
+    /// Report progress as the connection pulls `len` bytes from `stream`. The connection only
+    /// asks for the next chunk once it has taken the previous one.
+    pub fn track(&self, stream: RequestBodyStream, len: usize) -> TrackedBody {
+        TrackedBody {
+            inner: stream,
+            remaining: len,
+            state: self.state.clone(),
+        }
+    }
+
+    /// When the connection started pulling the body, and when it finished if it has.
+    pub fn span(&self) -> Option<(Instant, Option<Instant>)> {
+        match *self.state.borrow() {
+            UploadState::Waiting => None,
+            UploadState::Producing { started } | UploadState::Sending { started, .. } => {
+                Some((started, None))
+            }
+            UploadState::Finished { started, at } => Some((started, Some(at))),
+        }
+    }
+}
+
+pub struct TrackedBody {
+    inner: RequestBodyStream,
+    remaining: usize,
+    state: watch::Sender<UploadState>,
+}
+
+impl Stream for TrackedBody {
+    type Item = std::io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let started = match *self.state.borrow() {
+            UploadState::Waiting => Instant::now(),
+            UploadState::Producing { started } | UploadState::Sending { started, .. } => started,
+            UploadState::Finished { .. } => return self.inner.as_mut().poll_next(cx),
+        };
+
+        let poll = self.inner.as_mut().poll_next(cx);
+        let next = match &poll {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.remaining = self.remaining.saturating_sub(chunk.len());
+                // With a known length the connection may never poll for the end of the stream.
+                if self.remaining == 0 {
+                    UploadState::Finished {
+                        started,
+                        at: Instant::now(),
+                    }
+                } else {
+                    UploadState::Sending {
+                        started,
+                        since: Instant::now(),
+                    }
+                }
+            }
+            Poll::Ready(_) => UploadState::Finished {
+                started,
+                at: Instant::now(),
+            },
+            Poll::Pending => UploadState::Producing { started },
+        };
+
+        // Only phase changes wake the watchdog; per-chunk timestamps are read when it checks.
+        self.state.send_if_modified(|state| {
+            let changed = std::mem::discriminant(state) != std::mem::discriminant(&next);
+            *state = next;
+            changed
+        });
+        poll
+    }
+}
@@ -0,0 +1,35 @@
+use crate::client::RedirectMode;
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Request options applied whenever a request leaves them unset, configured once with
+/// `setDefaults` instead of being passed to every call.
+#[derive(Debug, Clone, Default)]
+pub struct RequestDefaults {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub read_timeout: Option<u64>,
+    pub proxy: Option<Arc<str>>,
+    pub redirect: Option<RedirectMode>,
+    pub insecure: Option<bool>,
+    pub origin_pooling: Option<bool>,
+    /// Largest body (bytes) returned inline instead of through a body handle.
+    pub inline_body_max: Option<u64>,
+}
+
+static REQUEST_DEFAULTS: LazyLock<RwLock<Arc<RequestDefaults>>> = LazyLock::new(Default::default);
+
+/// Replace the process-wide request defaults.
+pub fn set_request_defaults(defaults: RequestDefaults) {
+    *REQUEST_DEFAULTS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(defaults);
+}
+
+pub fn request_defaults() -> Arc<RequestDefaults> {
+    REQUEST_DEFAULTS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
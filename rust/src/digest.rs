@@ -0,0 +1,87 @@
+use anyhow::{Result, bail};
+use boring2::hash::{Hasher, MessageDigest};
+use std::fmt::Write;
+
+/// Hash algorithms that can digest a response body while it is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Md5,
+}
+
+impl DigestAlgorithm {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "md5" => Ok(DigestAlgorithm::Md5),
+            other => bail!("Unsupported body digest: {}", other),
+        }
+    }
+
+    fn message_digest(self) -> MessageDigest {
+        match self {
+            DigestAlgorithm::Sha256 => MessageDigest::sha256(),
+            DigestAlgorithm::Md5 => MessageDigest::md5(),
+        }
+    }
+}
+
+/// Running hash over the chunks of a response body.
+pub struct BodyDigest {
+    hasher: Hasher,
+}
+
+impl BodyDigest {
+    pub fn new(algorithm: DigestAlgorithm) -> Result<Self> {
+        Ok(Self {
+            hasher: Hasher::new(algorithm.message_digest())?,
+        })
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) -> Result<()> {
+        self.hasher.update(bytes)?;
+        Ok(())
+    }
+
+    /// Finish hashing and return the digest as lowercase hex.
+    pub fn finish(mut self) -> Result<String> {
+        let digest = self.hasher.finish()?;
+        let mut hex = String::with_capacity(digest.len() * 2);
+        for byte in digest.iter() {
+            let _ = write!(hex, "{:02x}", byte);
+        }
+        Ok(hex)
+    }
+}
+
+/// Digest a body that is already fully buffered.
+pub fn digest_bytes(algorithm: DigestAlgorithm, bytes: &[u8]) -> Result<String> {
+    let mut digest = BodyDigest::new(algorithm)?;
+    digest.update(bytes)?;
+    digest.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BodyDigest, DigestAlgorithm, digest_bytes};
+
+    #[test]
+    fn incremental_digest_matches_known_vectors() {
+        let mut sha256 = BodyDigest::new(DigestAlgorithm::Sha256).unwrap();
+        sha256.update(b"a").unwrap();
+        sha256.update(b"bc").unwrap();
+        assert_eq!(
+            sha256.finish().unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            digest_bytes(DigestAlgorithm::Md5, b"abc").unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_algorithms() {
+        assert!(DigestAlgorithm::parse("sha1").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use wreq::dns::{Addrs, Name, Resolve, Resolving};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+tokio::task_local! {
+    // DNS answers of the request driving the current task, set while it is being sent.
+    static DNS_ANSWERS: DnsRecorder;
+}
+
+/// What a lookup of one host name returned, in the order the connector tries them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsAnswer {
+    pub hostname: String,
+    pub addresses: Vec<IpAddr>,
+}
+
+/// A failed lookup. It is a type of its own so a request's failure can be traced to DNS by its
+/// causes rather than by error text.
+#[derive(Debug)]
+pub struct DnsError {
+    hostname: String,
+    source: io::Error,
+}
+
+impl DnsError {
+    pub fn new(hostname: String, source: io::Error) -> Self {
+        Self { hostname, source }
+    }
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to resolve {}", self.hostname)
+    }
+}
+
+impl std::error::Error for DnsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Keeps the last answer [`RecordingResolver`] gave a request.
+#[derive(Debug, Clone, Default)]
+pub struct DnsRecorder(Arc<Mutex<Option<Arc<DnsAnswer>>>>);
+
+impl DnsRecorder {
+    /// Send the request with its lookups recorded. Pooled connections look nothing up.
+    pub async fn track<F: Future>(&self, send: F) -> F::Output {
+        DNS_ANSWERS.scope(self.clone(), send).await
+    }
+
+    /// The last answer, if the connection to `remote` was opened from it. An answer from an
+    /// earlier redirect hop does not match a connection to an IP literal, for instance.
+    pub fn answer_for(&self, remote: SocketAddr) -> Option<Arc<DnsAnswer>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+            .filter(|answer| answer.addresses.contains(&remote.ip()))
+    }
+
+    fn record(&self, answer: DnsAnswer) {
+        *self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(answer));
+    }
+}
+
+/// Resolves through the system resolver like wreq's default one, and records every answer for
+/// the request that asked for it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordingResolver;
+
+impl Resolve for RecordingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        // Connections are opened from the task sending the request, so its recorder is in scope.
+        let recorder = DNS_ANSWERS.try_with(DnsRecorder::clone).ok();
+        Box::pin(async move {
+            let hostname = name.as_str().to_owned();
+            let lookup = tokio::net::lookup_host((hostname.as_str(), 0)).await;
+            let addrs: Vec<SocketAddr> = match lookup {
+                Ok(addrs) => addrs.collect(),
+                Err(error) => return Err(DnsError::new(hostname, error).into()),
+            };
+            if let Some(recorder) = recorder {
+                recorder.record(DnsAnswer {
+                    hostname,
+                    addresses: addrs.iter().map(SocketAddr::ip).collect(),
+                });
+            }
+            Ok::<Addrs, BoxError>(Box::new(addrs.into_iter()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DnsRecorder, RecordingResolver};
+    use std::net::SocketAddr;
+    use wreq::dns::{Name, Resolve};
+
+    #[tokio::test]
+    async fn records_the_answer_for_the_tracked_request() {
+        let recorder = DnsRecorder::default();
+        let addrs: Vec<SocketAddr> = recorder
+            .track(RecordingResolver.resolve(Name::new("localhost".into())))
+            .await
+            .unwrap()
+            .collect();
+        let remote = SocketAddr::new(addrs[0].ip(), 443);
+
+        let answer = recorder.answer_for(remote).unwrap();
+        assert_eq!(answer.hostname, "localhost");
+        assert!(answer.addresses.contains(&remote.ip()));
+        assert!(
+            recorder
+                .answer_for("192.0.2.1:443".parse().unwrap())
+                .is_none()
+        );
+
+        // Lookups outside a tracked request are not recorded anywhere.
+        let untracked = DnsRecorder::default();
+        RecordingResolver
+            .resolve(Name::new("localhost".into()))
+            .await
+            .unwrap();
+        assert!(untracked.answer_for(remote).is_none());
+    }
+}
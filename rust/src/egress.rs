@@ -0,0 +1,277 @@
+use anyhow::{Result, anyhow, bail};
+
+/// Where a transport may send requests.
+///
+/// Deny patterns always win. When any allow pattern is configured, URLs that match none of
+/// them are rejected too. The policy is checked for the initial URL and every redirect target.
+#[derive(Debug, Default)]
+pub struct EgressPolicy {
+    allow: Vec<UrlPattern>,
+    deny: Vec<UrlPattern>,
+}
+
+impl EgressPolicy {
+    pub fn new(allow: &[String], deny: &[String]) -> Result<Self> {
+        let parse_all = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| UrlPattern::parse(pattern))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            allow: parse_all(allow)?,
+            deny: parse_all(deny)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Explain why `uri` may not be requested, or `None` when it is permitted.
+    pub fn violation(&self, uri: &wreq::Uri) -> Option<String> {
+        if let Some(pattern) = self.deny.iter().find(|pattern| pattern.matches(uri)) {
+            return Some(format!(
+                "Request to {} blocked by deny pattern '{}'",
+                uri, pattern.raw
+            ));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|pattern| pattern.matches(uri)) {
+            return Some(format!(
+                "Request to {} is not in the transport allowlist",
+                uri
+            ));
+        }
+        None
+    }
+
+    pub fn check(&self, uri: &wreq::Uri) -> Result<()> {
+        match self.violation(uri) {
+            Some(message) => Err(anyhow!(message)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let uri: wreq::Uri = url
+            .parse()
+            .map_err(|_| anyhow!("Failed to parse URL: {}", url))?;
+        self.check(&uri)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum HostPattern {
+    Any,
+    Exact(String),
+    /// `*.example.com`: any subdomain, but not `example.com` itself.
+    Subdomains(String),
+}
+
+impl HostPattern {
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Any => true,
+            HostPattern::Exact(expected) => host == expected,
+            HostPattern::Subdomains(parent) => host
+                .strip_suffix(parent.as_str())
+                .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+        }
+    }
+}
+
+/// A host pattern (`example.com`, `*.example.com:8443`) or a URL prefix
+/// (`https://api.example.com/v1/`). Ports only restrict when given explicitly.
+#[derive(Debug)]
+struct UrlPattern {
+    raw: String,
+    scheme: Option<String>,
+    host: HostPattern,
+    port: Option<u16>,
+    path_prefix: Option<String>,
+}
+
+impl UrlPattern {
+    fn parse(raw: &str) -> Result<Self> {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            bail!("Egress pattern must not be empty");
+        }
+
+        let (scheme, rest) = match trimmed.split_once("://") {
+            Some((scheme, rest)) if !scheme.is_empty() => (Some(scheme.to_ascii_lowercase()), rest),
+            Some(_) => bail!("Invalid egress pattern '{}'", raw),
+            None => (None, trimmed),
+        };
+
+        let authority_end = rest.find('/').unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(authority_end);
+        if scheme.is_none() && !path.is_empty() {
+            bail!(
+                "Egress pattern '{}' has a path; include the scheme to match URL prefixes",
+                raw
+            );
+        }
+
+        let (host, port) = split_authority(authority)
+            .ok_or_else(|| anyhow!("Invalid egress pattern '{}'", raw))?;
+        let host = if host == "*" {
+            HostPattern::Any
+        } else if let Some(parent) = host.strip_prefix("*.") {
+            HostPattern::Subdomains(normalize_host(parent))
+        } else {
+            HostPattern::Exact(normalize_host(host))
+        };
+        if matches!(&host, HostPattern::Exact(name) | HostPattern::Subdomains(name) if name.is_empty() || name.contains('*'))
+        {
+            bail!("Invalid host wildcard in egress pattern '{}'", raw);
+        }
+
+        let path = path.strip_suffix('*').unwrap_or(path);
+        let path_prefix = (!path.is_empty() && path != "/").then(|| path.to_string());
+
+        Ok(Self {
+            raw: raw.to_string(),
+            scheme,
+            host,
+            port,
+            path_prefix,
+        })
+    }
+
+    fn matches(&self, uri: &wreq::Uri) -> bool {
+        let Some(scheme) = uri.scheme_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let Some(host) = uri.host().map(normalize_host) else {
+            return false;
+        };
+
+        if self
+            .scheme
+            .as_ref()
+            .is_some_and(|expected| *expected != scheme)
+        {
+            return false;
+        }
+        if !self.host.matches(&host) {
+            return false;
+        }
+        if let Some(expected) = self.port {
+            let port = uri.port_u16().or(match scheme.as_str() {
+                "https" | "wss" => Some(443),
+                "http" | "ws" => Some(80),
+                _ => None,
+            });
+            if port != Some(expected) {
+                return false;
+            }
+        }
+
+        match self.path_prefix.as_deref() {
+            None => true,
+            Some(prefix) if prefix.ends_with('/') => uri.path().starts_with(prefix),
+            // `/v1` covers `/v1` and `/v1/...`, but not `/v10`.
+            Some(prefix) => uri
+                .path()
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+        }
+    }
+}
+
+fn split_authority(authority: &str) -> Option<(&str, Option<u16>)> {
+    if authority.contains('@') {
+        return None;
+    }
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (host, tail) = rest.split_once(']')?;
+        return match tail {
+            "" => Some((host, None)),
+            _ => Some((host, Some(tail.strip_prefix(':')?.parse().ok()?))),
+        };
+    }
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host, Some(port.parse().ok()?))),
+        None => Some((authority, None)),
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EgressPolicy;
+
+    fn policy(allow: &[&str], deny: &[&str]) -> EgressPolicy {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        EgressPolicy::new(&owned(allow), &owned(deny)).unwrap()
+    }
+
+    fn permits(policy: &EgressPolicy, url: &str) -> bool {
+        policy.violation(&url.parse().unwrap()).is_none()
+    }
+
+    #[test]
+    fn host_patterns_match_hosts_and_subdomains() {
+        let policy = policy(&["example.com", "*.example.org", "localhost:8080"], &[]);
+        assert!(permits(&policy, "https://example.com/a"));
+        assert!(permits(&policy, "http://EXAMPLE.com./"));
+        assert!(!permits(&policy, "https://www.example.com/"));
+        assert!(permits(&policy, "https://api.example.org/"));
+        assert!(!permits(&policy, "https://example.org/"));
+        assert!(!permits(&policy, "https://badexample.org/"));
+        assert!(permits(&policy, "http://localhost:8080/"));
+        assert!(!permits(&policy, "http://localhost:8081/"));
+    }
+
+    #[test]
+    fn url_patterns_match_scheme_port_and_path() {
+        let policy = policy(&["https://api.example.com/v1", "http://[::1]:3000/"], &[]);
+        assert!(permits(&policy, "https://api.example.com/v1"));
+        assert!(permits(&policy, "https://api.example.com:443/v1/users?q=1"));
+        assert!(!permits(&policy, "https://api.example.com/v10"));
+        assert!(!permits(&policy, "http://api.example.com/v1"));
+        assert!(permits(&policy, "http://[::1]:3000/anything"));
+        assert!(!permits(&policy, "http://[::1]:3001/"));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let policy = policy(&["*"], &["169.254.169.254", "*.internal"]);
+        assert!(permits(&policy, "https://example.com/"));
+        assert!(!permits(&policy, "http://169.254.169.254/latest/meta-data"));
+        assert!(!permits(&policy, "https://db.internal/"));
+
+        let deny_only =
+            super::EgressPolicy::new(&[], &["https://example.com/admin/".into()]).unwrap();
+        assert!(permits(&deny_only, "https://example.com/"));
+        assert!(!permits(&deny_only, "https://example.com/admin/users"));
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        for pattern in [
+            "",
+            "example.com/path",
+            "ex*ample.com",
+            "*.",
+            "host:port",
+            "://x",
+            "user@host",
+        ] {
+            assert!(
+                EgressPolicy::new(&[pattern.to_string()], &[]).is_err(),
+                "{pattern}"
+            );
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use anyhow::{Context as _, Result, bail};
+use bytes::Bytes;
+use futures_util::Stream;
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use wreq::header::HeaderValue;
+
+use crate::client::ResponseBodyStream;
+
+/// Content codings a request advertises in `Accept-Encoding` and decodes on the way back.
+/// Codings left out are neither asked for nor decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AcceptEncoding {
+    pub gzip: bool,
+    pub deflate: bool,
+    pub br: bool,
+    pub zstd: bool,
+}
+
+impl AcceptEncoding {
+    /// Every coding there is a decoder for; what a request decodes when it does not choose.
+    pub const ALL: Self = Self {
+        gzip: true,
+        deflate: true,
+        br: true,
+        zstd: true,
+    };
+
+    /// Parse coding names such as `["gzip", "br"]`. `identity` on its own, or an empty list,
+    /// asks for uncompressed responses.
+    pub fn parse<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        let mut accept = Self::default();
+        for name in names {
+            match name.as_ref().trim().to_ascii_lowercase().as_str() {
+                "gzip" => accept.gzip = true,
+                "deflate" => accept.deflate = true,
+                "br" => accept.br = true,
+                "zstd" => accept.zstd = true,
+                "identity" => {}
+                other => bail!(
+                    "Unsupported content coding '{}'. Expected gzip, deflate, br, zstd, or identity",
+                    other
+                ),
+            }
+        }
+        Ok(accept)
+    }
+
+    /// The `Accept-Encoding` value, listing codings in the order browsers send them.
+    pub fn header_value(&self) -> HeaderValue {
+        let codings = [
+            ("gzip", self.gzip),
+            ("deflate", self.deflate),
+            ("br", self.br),
+            ("zstd", self.zstd),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+        if codings.is_empty() {
+            HeaderValue::from_static("identity")
+        } else {
+            HeaderValue::from_str(&codings.join(", ")).expect("coding names are valid header text")
+        }
+    }
+}
+
+/// How many bytes of a response body came off the wire, and how many were left after decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BodySize {
+    pub wire: u64,
+    pub decoded: u64,
+}
+
+/// Where a response's [`BodySize`] is counted while its body is read.
+pub type BodySizeSlot = Arc<Mutex<BodySize>>;
+
+/// Decodes one response body as it arrives, like wreq's own decoders, which would hide how
+/// many bytes the server actually sent.
+pub enum ContentDecoder {
+    Gzip(flate2::write::MultiGzDecoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibDecoder<Vec<u8>>),
+    Br(Box<brotli::DecompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl ContentDecoder {
+    /// The decoder for a `Content-Encoding` value, if `accept` decodes that coding. Stacked
+    /// codings such as `gzip, br` are passed through undecoded, as wreq does.
+    pub fn for_coding(coding: &str, accept: AcceptEncoding) -> Result<Option<Self>> {
+        let coding = coding.trim();
+        let decoder = if accept.gzip && coding.eq_ignore_ascii_case("gzip") {
+            Self::Gzip(flate2::write::MultiGzDecoder::new(Vec::new()))
+        } else if accept.deflate && coding.eq_ignore_ascii_case("deflate") {
+            Self::Deflate(flate2::write::ZlibDecoder::new(Vec::new()))
+        } else if accept.br && coding.eq_ignore_ascii_case("br") {
+            Self::Br(Box::new(brotli::DecompressorWriter::new(Vec::new(), 4096)))
+        } else if accept.zstd && coding.eq_ignore_ascii_case("zstd") {
+            Self::Zstd(zstd::stream::write::Decoder::new(Vec::new())?)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(decoder))
+    }
+
+    /// Decode the next piece of the body, returning whatever output it completes.
+    pub fn decode(&mut self, bytes: &[u8]) -> io::Result<Bytes> {
+        match self {
+            Self::Gzip(decoder) => decoder.write_all(bytes)?,
+            Self::Deflate(decoder) => decoder.write_all(bytes)?,
+            Self::Br(decoder) => decoder.write_all(bytes)?,
+            Self::Zstd(decoder) => decoder.write_all(bytes)?,
+        }
+        Ok(self.take_output())
+    }
+
+    /// Flush the rest of the output once the body has ended. gzip and brotli bodies that were cut
+    /// short fail here.
+    pub fn finish(&mut self) -> io::Result<Bytes> {
+        match self {
+            Self::Gzip(decoder) => decoder.try_finish()?,
+            Self::Deflate(decoder) => decoder.try_finish()?,
+            Self::Br(decoder) => decoder.close()?,
+            Self::Zstd(decoder) => decoder.flush()?,
+        }
+        Ok(self.take_output())
+    }
+
+    fn take_output(&mut self) -> Bytes {
+        let output = match self {
+            Self::Gzip(decoder) => decoder.get_mut(),
+            Self::Deflate(decoder) => decoder.get_mut(),
+            Self::Br(decoder) => decoder.get_mut(),
+            Self::Zstd(decoder) => decoder.get_mut(),
+        };
+        Bytes::from(std::mem::take(output))
+    }
+}
+
+/// Decode `stream` with `decoder`, if any, counting its bytes before and after into `size`.
+pub fn decode_stream(
+    stream: ResponseBodyStream,
+    decoder: Option<ContentDecoder>,
+    size: BodySizeSlot,
+) -> ResponseBodyStream {
+    Box::pin(DecodedBody {
+        stream,
+        decoder,
+        size,
+        received: false,
+        done: false,
+    })
+}
+
+struct DecodedBody {
+    stream: ResponseBodyStream,
+    decoder: Option<ContentDecoder>,
+    size: BodySizeSlot,
+    /// Whether any body bytes came off the wire. An empty body has nothing to decode, even
+    /// when the response names a coding, as responses to `HEAD` and `204`s often do.
+    received: bool,
+    done: bool,
+}
+
+impl DecodedBody {
+    fn count(&self, wire: usize, decoded: usize) {
+        let mut size = self
+            .size
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        size.wire += wire as u64;
+        size.decoded += decoded as u64;
+    }
+}
+
+impl Stream for DecodedBody {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let decoded = match std::task::ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(bytes)) => {
+                    this.received |= !bytes.is_empty();
+                    this.count(bytes.len(), 0);
+                    match this.decoder.as_mut() {
+                        Some(decoder) => decoder.decode(&bytes),
+                        None => Ok(bytes),
+                    }
+                }
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => {
+                    this.done = true;
+                    match this.decoder.as_mut() {
+                        Some(decoder) if this.received => decoder.finish(),
+                        _ => return Poll::Ready(None),
+                    }
+                }
+            };
+            let decoded = match decoded.context("Failed to decode the response body") {
+                Ok(decoded) => decoded,
+                Err(error) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(error)));
+                }
+            };
+            // A decoder can take several chunks before it has any output.
+            if decoded.is_empty() {
+                continue;
+            }
+            this.count(0, decoded.len());
+            return Poll::Ready(Some(Ok(decoded)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AcceptEncoding, BodySize, BodySizeSlot, ContentDecoder, decode_stream};
+    use bytes::Bytes;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use futures_util::StreamExt;
+    use std::io::Write;
+
+    #[test]
+    fn advertises_only_the_chosen_codings() {
+        let accept = AcceptEncoding::parse(&["br", " GZIP "]).unwrap();
+        assert!(accept.gzip && accept.br && !accept.deflate && !accept.zstd);
+        assert_eq!(accept.header_value(), "gzip, br");
+
+        let identity = AcceptEncoding::parse(&["identity"]).unwrap();
+        assert_eq!(identity, AcceptEncoding::default());
+        assert_eq!(identity.header_value(), "identity");
+
+        assert!(AcceptEncoding::parse(&["compress"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn counts_wire_and_decoded_bytes() {
+        let text = "hello ".repeat(1000);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let chunks: Vec<anyhow::Result<Bytes>> = gzipped
+            .chunks(7)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+
+        let size = BodySizeSlot::default();
+        let decoder = ContentDecoder::for_coding("GZIP", AcceptEncoding::ALL).unwrap();
+        let mut stream = decode_stream(
+            Box::pin(futures_util::stream::iter(chunks)),
+            decoder,
+            size.clone(),
+        );
+        let mut decoded = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            decoded.extend_from_slice(&chunk.unwrap());
+        }
+
+        assert_eq!(decoded, text.as_bytes());
+        assert_eq!(
+            *size.lock().unwrap(),
+            BodySize {
+                wire: gzipped.len() as u64,
+                decoded: text.len() as u64,
+            }
+        );
+
+        // Codings that were not asked for, and stacked ones, are left alone.
+        let gzip_only = AcceptEncoding::parse(&["gzip"]).unwrap();
+        assert!(
+            ContentDecoder::for_coding("br", gzip_only)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            ContentDecoder::for_coding("gzip, br", AcceptEncoding::ALL)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_a_truncated_body() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[7; 4096]).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let truncated = Bytes::copy_from_slice(&gzipped[..gzipped.len() / 2]);
+
+        let decoder = ContentDecoder::for_coding("gzip", AcceptEncoding::ALL).unwrap();
+        let stream = decode_stream(
+            Box::pin(futures_util::stream::iter([Ok(truncated)])),
+            decoder,
+            BodySizeSlot::default(),
+        );
+        let results: Vec<_> = stream.collect().await;
+        assert!(results.iter().any(Result::is_err));
+    }
+
+    #[tokio::test]
+    async fn passes_an_empty_body_through() {
+        for coding in ["gzip", "br"] {
+            let decoder = ContentDecoder::for_coding(coding, AcceptEncoding::ALL).unwrap();
+            let size = BodySizeSlot::default();
+            let stream = decode_stream(
+                Box::pin(futures_util::stream::iter([Ok(Bytes::new())])),
+                decoder,
+                size.clone(),
+            );
+            let results: Vec<_> = stream.collect().await;
+            assert!(results.is_empty(), "{coding} body should be empty");
+            assert_eq!(*size.lock().unwrap(), BodySize::default());
+        }
+    }
+}
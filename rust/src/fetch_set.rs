@@ -0,0 +1,229 @@
+use anyhow::{Error, Result};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::abort::{AbortHandle, Aborted, TimedOut, error_code};
+use crate::client::{RequestOptions, Response, drop_body_stream, make_request};
+
+/// Statuses retried when the caller does not name its own.
+pub const DEFAULT_RETRY_STATUSES: [u16; 5] = [408, 429, 502, 503, 504];
+
+/// The longest wait between two attempts, whatever the backoff or `Retry-After` asks for.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// A retry about to be made, as told to a [`RetryHook`].
+pub struct RetryEvent {
+    /// Position of the request in the submitted list.
+    pub index: usize,
+    /// The attempt about to be made; 2 for the first retry.
+    pub attempt: u32,
+    /// Why the previous attempt failed.
+    pub message: String,
+    /// `ERR_TIMEOUT` when the previous attempt timed out.
+    pub code: Option<&'static str>,
+    /// The status that prompted the retry, when the previous attempt got a response.
+    pub status: Option<u16>,
+    pub delay: Duration,
+}
+
+/// Asked before each retry. Answering `false`, or not answering, gives up on the request and
+/// reports the failure that prompted the retry.
+pub type RetryHook = Arc<dyn Fn(RetryEvent, oneshot::Sender<bool>) + Send + Sync>;
+
+/// How a set of requests is scheduled and retried.
+#[derive(Clone)]
+pub struct FetchSetOptions {
+    /// Requests in flight at once.
+    pub concurrency: usize,
+    /// Attempts after the first one for a request that failed transiently.
+    pub retries: u32,
+    /// Wait before the first retry; it doubles for each retry after that.
+    pub retry_delay: Duration,
+    /// Response statuses retried like transient failures.
+    pub retry_statuses: Vec<u16>,
+    pub on_retry: Option<RetryHook>,
+}
+
+/// How one request of a set ended, after its retries.
+pub struct FetchSetResult {
+    /// Position of the request in the submitted list.
+    pub index: usize,
+    pub attempts: u32,
+    pub result: Result<Response>,
+}
+
+/// Send every request in `requests`, at most `options.concurrency` at once, and hand each
+/// one's final result to `report` as soon as it has one. Results arrive in completion order.
+/// Aborting `abort` fails the requests still pending with the abort error, so every request is
+/// reported exactly once.
+pub async fn run_fetch_set<F>(
+    requests: Vec<RequestOptions>,
+    options: FetchSetOptions,
+    abort: AbortHandle,
+    report: F,
+) where
+    F: Fn(FetchSetResult) + Send + Sync + 'static,
+{
+    let workers = options.concurrency.clamp(1, requests.len().max(1));
+    let requests = Arc::new(requests);
+    let options = Arc::new(options);
+    let report = Arc::new(report);
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let requests = requests.clone();
+            let options = options.clone();
+            let report = report.clone();
+            let next = next.clone();
+            let abort = abort.clone();
+            tokio::spawn(async move {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(request) = requests.get(index) else {
+                        break;
+                    };
+                    let (attempts, result) =
+                        fetch_with_retries(index, request, &options, &abort).await;
+                    report(FetchSetResult {
+                        index,
+                        attempts,
+                        result,
+                    });
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let _ = handle.await;
+    }
+}
+
+async fn fetch_with_retries(
+    index: usize,
+    request: &RequestOptions,
+    options: &FetchSetOptions,
+    abort: &AbortHandle,
+) -> (u32, Result<Response>) {
+    let mut attempt = 1;
+    loop {
+        let result = tokio::select! {
+            error = abort.aborted() => Err(error),
+            result = make_request(request.clone()) => result,
+        };
+        if attempt > options.retries {
+            return (attempt, result);
+        }
+
+        let delay = match &result {
+            Ok(response) if options.retry_statuses.contains(&response.status) => {
+                retry_after(response).unwrap_or_else(|| backoff(options.retry_delay, attempt))
+            }
+            Err(error) if is_transient(error) => backoff(options.retry_delay, attempt),
+            _ => return (attempt, result),
+        }
+        .min(MAX_RETRY_DELAY);
+
+        if let Some(hook) = &options.on_retry {
+            let (message, code, status) = match &result {
+                Ok(response) => (
+                    format!("Server responded with status {}", response.status),
+                    None,
+                    Some(response.status),
+                ),
+                Err(error) => (format!("{:#}", error), error_code(error), None),
+            };
+            let (answer, proceed) = oneshot::channel();
+            hook(
+                RetryEvent {
+                    index,
+                    attempt: attempt + 1,
+                    message,
+                    code,
+                    status,
+                    delay,
+                },
+                answer,
+            );
+            let proceed = tokio::select! {
+                error = abort.aborted() => return (attempt, Err(error)),
+                proceed = proceed => proceed.unwrap_or(false),
+            };
+            if !proceed {
+                return (attempt, result);
+            }
+        }
+
+        // The response being retried is never handed out, so nothing else will read its body.
+        if let Ok(Response {
+            body_handle: Some(handle),
+            ..
+        }) = result
+        {
+            drop_body_stream(handle);
+        }
+
+        tokio::select! {
+            error = abort.aborted() => return (attempt, Err(error)),
+            _ = tokio::time::sleep(delay) => {}
+        }
+        attempt += 1;
+    }
+}
+
+/// The wait before retry number `attempt` (1 for the first retry): `base`, doubled per retry.
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+}
+
+/// Failures worth another attempt: timeouts and connection-level errors, but not requests the
+/// caller aborted or that wreq could not build.
+fn is_transient(error: &Error) -> bool {
+    if error.chain().any(|cause| cause.is::<Aborted>()) {
+        return false;
+    }
+    error.chain().any(|cause| {
+        cause.is::<TimedOut>()
+            || cause
+                .downcast_ref::<wreq::Error>()
+                .is_some_and(|error| !error.is_builder())
+    })
+}
+
+/// The delay a `Retry-After` header in delta-seconds form asks for.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+        .and_then(|(_, value)| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_the_delay_for_each_retry() {
+        let base = Duration::from_millis(250);
+        assert_eq!(backoff(base, 1), base);
+        assert_eq!(backoff(base, 2), Duration::from_millis(500));
+        assert_eq!(backoff(base, 4), Duration::from_secs(2));
+        assert_eq!(backoff(Duration::ZERO, 3), Duration::ZERO);
+        assert!(backoff(base, u32::MAX) >= MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn retries_timeouts_but_not_aborts() {
+        assert!(is_transient(&TimedOut("Request timed out".into()).into()));
+        assert!(!is_transient(&Aborted { reason: None }.into()));
+        assert!(!is_transient(
+            &Error::from(TimedOut("Request timed out".into())).context(Aborted { reason: None })
+        ));
+        assert!(!is_transient(&anyhow::anyhow!("Invalid header value")));
+    }
+}
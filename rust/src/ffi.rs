@@ -0,0 +1,400 @@
+// C ABI over the request, session, and transport registries for runtimes that cannot load
+// Neon modules but can call into a shared library, such as Bun (`bun:ffi`) and Deno
+// (`Deno.dlopen`).
+//
+// Options and results cross the boundary as NUL-terminated JSON and bodies as raw bytes.
+// Requests complete on the native runtime's threads, so callbacks must be thread-safe
+// (`JSCallback` with `threadsafe: true` in Bun, `Deno.UnsafeCallback.threadSafe` in Deno).
+// Every pointer passed to a callback is only valid until it returns.
+
+use anyhow::{Result, anyhow};
+use dashmap::DashMap;
+use serde::Deserialize;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio_util::sync::CancellationToken;
+use wreq::header::HeaderValue;
+use wreq_util::{Emulation, EmulationOS};
+
+use crate::abort::Aborted;
+use crate::client::{
+    HTTP_RUNTIME, RedirectMode, RequestOptions, Response, SessionOptions, TransportOptions,
+    create_managed_session, create_managed_transport, drop_body_stream, drop_managed_session,
+    drop_managed_transport, generate_session_id, make_request, read_body_chunk,
+};
+use crate::profiles::{emulation, emulation_os};
+
+/// Receives a request's outcome: a JSON object, plus the body when it arrived in one piece.
+pub type ResponseCallback =
+    extern "C" fn(user_data: *mut c_void, result: *const c_char, body: *const u8, body_len: usize);
+
+/// Receives one body chunk, or a null chunk once the body is done. `error` is null unless
+/// reading failed.
+pub type ChunkCallback =
+    extern "C" fn(user_data: *mut c_void, error: *const c_char, chunk: *const u8, chunk_len: usize);
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+static REQUESTS: LazyLock<DashMap<u64, CancellationToken>> = LazyLock::new(DashMap::new);
+
+// The caller's context pointer, handed back untouched on whichever thread completes the call.
+struct UserData(*mut c_void);
+
+// SAFETY: the pointer is never dereferenced here, only passed back to the caller's callback,
+// which the caller has declared thread-safe.
+unsafe impl Send for UserData {}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct FfiRequest {
+    url: String,
+    method: Option<String>,
+    headers: Vec<(String, String)>,
+    browser: Option<String>,
+    os: Option<String>,
+    proxy: Option<String>,
+    timeout: Option<u64>,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+    redirect: Option<String>,
+    session_id: Option<String>,
+    transport_id: Option<String>,
+    insecure: bool,
+    disable_default_headers: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct FfiTransport {
+    browser: Option<String>,
+    os: Option<String>,
+    proxy: Option<String>,
+    insecure: bool,
+    pool_idle_timeout: Option<u64>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_max_size: Option<u32>,
+    max_connection_age: Option<u64>,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct FfiSession {
+    session_id: Option<String>,
+    hsts: bool,
+}
+
+// Unlike the JS bindings, a typo in a profile never falls back silently: FFI callers get no
+// validation from the TypeScript types.
+fn resolve(browser: Option<&str>, os: Option<&str>) -> Result<(Emulation, EmulationOS)> {
+    let browser = browser.unwrap_or("chrome_142");
+    let os = os.unwrap_or("macos");
+    let emulation =
+        emulation(browser).ok_or_else(|| anyhow!("Unknown browser profile '{}'", browser))?;
+    let emulation_os =
+        emulation_os(os).ok_or_else(|| anyhow!("Unknown operating system '{}'", os))?;
+    Ok((emulation, emulation_os))
+}
+
+fn request_options(request: FfiRequest, body: Option<Vec<u8>>) -> Result<RequestOptions> {
+    let (emulation, emulation_os) = resolve(request.browser.as_deref(), request.os.as_deref())?;
+    let mut options = RequestOptions::new(request.url, emulation, emulation_os);
+
+    options.headers = request
+        .headers
+        .into_iter()
+        .map(|(name, value)| {
+            HeaderValue::from_str(&value)
+                .map(|value| (name.clone(), value))
+                .map_err(|_| anyhow!("Invalid value for header '{}'", name))
+        })
+        .collect::<Result<_>>()?;
+    options.method = request.method.unwrap_or_else(|| "GET".to_string());
+    options.body = body;
+    options.proxy = request.proxy.map(Arc::from);
+    options.timeout = request.timeout.unwrap_or(options.timeout);
+    options.connect_timeout = request.connect_timeout;
+    options.read_timeout = request.read_timeout;
+    if let Some(name) = request.redirect {
+        options.redirect = RedirectMode::parse(&name)
+            .ok_or_else(|| anyhow!("Unsupported redirect mode: {}", name))?;
+    }
+    // Without a session the request gets its own cookie jar, like `fetch()` without one.
+    if let Some(session_id) = request.session_id {
+        options.session_id = session_id;
+        options.ephemeral = false;
+    }
+    options.transport_id = request.transport_id;
+    options.insecure = request.insecure;
+    options.disable_default_headers = request.disable_default_headers;
+    Ok(options)
+}
+
+fn transport_options(transport: FfiTransport) -> Result<TransportOptions> {
+    let (emulation, emulation_os) = resolve(transport.browser.as_deref(), transport.os.as_deref())?;
+    let mut options = TransportOptions::new(emulation, emulation_os);
+    options.proxy = transport.proxy.map(Arc::from);
+    options.insecure = transport.insecure;
+    options.pool_idle_timeout = transport.pool_idle_timeout;
+    options.pool_max_idle_per_host = transport.pool_max_idle_per_host;
+    options.pool_max_size = transport.pool_max_size;
+    options.max_connection_age = transport.max_connection_age;
+    options.connect_timeout = transport.connect_timeout;
+    options.read_timeout = transport.read_timeout;
+    Ok(options)
+}
+
+fn response_json(response: &Response) -> serde_json::Value {
+    serde_json::json!({
+        "status": response.status,
+        "url": response.url,
+        "headers": response.headers,
+        "bodyHandle": response.body_handle,
+        "trailers": response.trailers,
+    })
+}
+
+fn error_json(error: &anyhow::Error) -> serde_json::Value {
+    serde_json::json!({ "error": format!("{:#}", error) })
+}
+
+// JSON text never contains NUL, so this cannot fail.
+fn c_string(value: &serde_json::Value) -> CString {
+    CString::new(value.to_string()).expect("JSON text has no NUL bytes")
+}
+
+/// # Safety
+///
+/// `text` must be null or point to a NUL-terminated string.
+unsafe fn parse_json<T: for<'de> Deserialize<'de> + Default>(text: *const c_char) -> Result<T> {
+    if text.is_null() {
+        return Ok(T::default());
+    }
+    // SAFETY: guaranteed by the caller.
+    let text = unsafe { CStr::from_ptr(text) };
+    let text = text
+        .to_str()
+        .map_err(|_| anyhow!("Options are not UTF-8"))?;
+    serde_json::from_str(text).map_err(|e| anyhow!("Invalid options: {}", e))
+}
+
+/// Send a request. `options` is a JSON object with `url` and optionally `method`, `headers`
+/// (`[[name, value], ...]`), `browser`, `os`, `proxy`, `timeout`, `connectTimeout`,
+/// `readTimeout`, `redirect`, `sessionId`, `transportId`, `insecure`, and
+/// `disableDefaultHeaders`. `callback` is called exactly once, from a native thread, with
+/// `{"status","url","headers","bodyHandle","trailers"}` or `{"error"}`. Small bodies are
+/// passed to it directly and `bodyHandle` is null; larger ones are read with
+/// [`wreq_body_read`].
+///
+/// Returns an ID for [`wreq_request_cancel`].
+///
+/// # Safety
+///
+/// `options` must point to a NUL-terminated string. `body` must be null or point to
+/// `body_len` readable bytes; it is copied before this returns.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wreq_request(
+    options: *const c_char,
+    body: *const u8,
+    body_len: usize,
+    callback: ResponseCallback,
+    user_data: *mut c_void,
+) -> u64 {
+    let user_data = UserData(user_data);
+    let body = (!body.is_null()).then(|| {
+        // SAFETY: guaranteed by the caller.
+        unsafe { std::slice::from_raw_parts(body, body_len) }.to_vec()
+    });
+    // SAFETY: guaranteed by the caller.
+    let options = unsafe { parse_json::<FfiRequest>(options) }
+        .and_then(|request| request_options(request, body));
+
+    let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let token = CancellationToken::new();
+    REQUESTS.insert(request_id, token.clone());
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = match options {
+            Ok(options) => tokio::select! {
+                _ = token.cancelled() => Err(Aborted { reason: None }.into()),
+                result = make_request(options) => result,
+            },
+            Err(e) => Err(e),
+        };
+        REQUESTS.remove(&request_id);
+
+        let user_data = user_data;
+        match result {
+            Ok(response) => {
+                let json = c_string(&response_json(&response));
+                let body = response.body_bytes.unwrap_or_default();
+                callback(user_data.0, json.as_ptr(), body.as_ptr(), body.len());
+            }
+            Err(e) => {
+                let json = c_string(&error_json(&e));
+                callback(user_data.0, json.as_ptr(), std::ptr::null(), 0);
+            }
+        }
+    });
+
+    request_id
+}
+
+/// Abort a request started with [`wreq_request`]. Its callback reports "Request aborted".
+/// Unknown or finished IDs are ignored.
+#[unsafe(no_mangle)]
+pub extern "C" fn wreq_request_cancel(request_id: u64) {
+    if let Some((_, token)) = REQUESTS.remove(&request_id) {
+        token.cancel();
+    }
+}
+
+/// Read the next chunk of a streamed body. `callback` is called once, from a native thread,
+/// with the chunk, with a null chunk at the end of the body, or with an error.
+#[unsafe(no_mangle)]
+pub extern "C" fn wreq_body_read(handle: u64, callback: ChunkCallback, user_data: *mut c_void) {
+    let user_data = UserData(user_data);
+    HTTP_RUNTIME.spawn(async move {
+        let user_data = user_data;
+        match read_body_chunk(handle).await {
+            Ok(Some(chunk)) => callback(user_data.0, std::ptr::null(), chunk.as_ptr(), chunk.len()),
+            Ok(None) => callback(user_data.0, std::ptr::null(), std::ptr::null(), 0),
+            Err(e) => {
+                let message = CString::new(format!("{:#}", e).replace('\0', " "))
+                    .expect("NUL bytes were replaced");
+                callback(user_data.0, message.as_ptr(), std::ptr::null(), 0);
+            }
+        }
+    });
+}
+
+/// Discard the rest of a streamed body without reading it.
+#[unsafe(no_mangle)]
+pub extern "C" fn wreq_body_cancel(handle: u64) {
+    drop_body_stream(handle);
+}
+
+/// Create a session. `options` is null or `{"sessionId"?, "hsts"?}`. Returns
+/// `{"id"}` or `{"error"}`, to be released with [`wreq_string_free`].
+///
+/// # Safety
+///
+/// `options` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wreq_session_create(options: *const c_char) -> *mut c_char {
+    // SAFETY: guaranteed by the caller.
+    let result = unsafe { parse_json::<FfiSession>(options) }.and_then(|session| {
+        let id = session.session_id.unwrap_or_else(generate_session_id);
+        create_managed_session(
+            id,
+            SessionOptions {
+                hsts: session.hsts,
+                ..SessionOptions::default()
+            },
+        )
+    });
+    id_result(result)
+}
+
+/// Create a transport. `options` is null or a JSON object with `browser`, `os`, `proxy`,
+/// `insecure`, `poolIdleTimeout`, `poolMaxIdlePerHost`, `poolMaxSize`, `maxConnectionAge`,
+/// `connectTimeout`, and `readTimeout`. Returns `{"id"}` or `{"error"}`, to be released with [`wreq_string_free`].
+///
+/// # Safety
+///
+/// `options` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wreq_transport_create(options: *const c_char) -> *mut c_char {
+    // SAFETY: guaranteed by the caller.
+    let result = unsafe { parse_json::<FfiTransport>(options) }
+        .and_then(transport_options)
+        .and_then(create_managed_transport);
+    id_result(result)
+}
+
+fn id_result(result: Result<String>) -> *mut c_char {
+    let json = match result {
+        Ok(id) => serde_json::json!({ "id": id }),
+        Err(e) => error_json(&e),
+    };
+    c_string(&json).into_raw()
+}
+
+/// Drop a session and its cookies.
+///
+/// # Safety
+///
+/// `session_id` must point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wreq_session_drop(session_id: *const c_char) {
+    // SAFETY: guaranteed by the caller.
+    let session_id = unsafe { CStr::from_ptr(session_id) };
+    drop_managed_session(&session_id.to_string_lossy());
+}
+
+/// Drop a transport and close its pooled connections.
+///
+/// # Safety
+///
+/// `transport_id` must point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wreq_transport_drop(transport_id: *const c_char) {
+    // SAFETY: guaranteed by the caller.
+    let transport_id = unsafe { CStr::from_ptr(transport_id) };
+    drop_managed_transport(&transport_id.to_string_lossy());
+}
+
+/// Release a string returned by this library.
+///
+/// # Safety
+///
+/// `value` must be null or a pointer returned by a `wreq_*` function, not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wreq_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        // SAFETY: guaranteed by the caller.
+        drop(unsafe { CString::from_raw(value) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FfiRequest, parse_json, request_options};
+    use crate::client::RedirectMode;
+    use std::ffi::CString;
+
+    #[test]
+    fn parses_request_options_from_json() {
+        let text = CString::new(
+            r#"{"url":"https://example.com","method":"POST","headers":[["x-id","1"]],
+                "browser":"firefox","redirect":"manual","sessionId":"s1","timeout":500}"#,
+        )
+        .unwrap();
+        let request = unsafe { parse_json::<FfiRequest>(text.as_ptr()) }.unwrap();
+        let options = request_options(request, Some(b"a=1".to_vec())).unwrap();
+
+        assert_eq!(options.url, "https://example.com");
+        assert_eq!(options.method, "POST");
+        assert_eq!(options.headers[0].0, "x-id");
+        assert_eq!(options.redirect, RedirectMode::Manual);
+        assert_eq!(options.session_id, "s1");
+        assert!(!options.ephemeral);
+        assert_eq!(options.timeout, 500);
+        assert_eq!(options.body.as_deref(), Some(&b"a=1"[..]));
+    }
+
+    #[test]
+    fn rejects_unknown_profiles_and_bad_json() {
+        let unknown = CString::new(r#"{"url":"https://a","browser":"netscape_4"}"#).unwrap();
+        let request = unsafe { parse_json::<FfiRequest>(unknown.as_ptr()) }.unwrap();
+        assert!(request_options(request, None).is_err());
+
+        let invalid = CString::new("{").unwrap();
+        assert!(unsafe { parse_json::<FfiRequest>(invalid.as_ptr()) }.is_err());
+
+        let request = unsafe { parse_json::<FfiRequest>(std::ptr::null()) }.unwrap();
+        assert_eq!(request.url, "");
+    }
+}
@@ -0,0 +1,76 @@
+use wreq::http2::Http2Options;
+
+/// Largest flow-control window HTTP/2 allows (2^31 - 1).
+pub const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+/// Frame size bounds from RFC 9113 §6.5.2.
+pub const MIN_FRAME_SIZE: u32 = 1 << 14;
+pub const MAX_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// Per-transport HTTP/2 flow-control overrides. Unset values keep the browser profile's settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Http2Windows {
+    /// `SETTINGS_INITIAL_WINDOW_SIZE`: how much each stream may receive before a window update.
+    pub stream_window: Option<u32>,
+    /// How much the whole connection may receive before a window update.
+    pub connection_window: Option<u32>,
+    /// `SETTINGS_MAX_FRAME_SIZE`: largest frame payload the server may send.
+    pub max_frame_size: Option<u32>,
+}
+
+impl Http2Windows {
+    pub fn is_set(&self) -> bool {
+        self.stream_window.is_some()
+            || self.connection_window.is_some()
+            || self.max_frame_size.is_some()
+    }
+
+    /// Write the overrides into the emulation's HTTP/2 options. Fixed windows turn off adaptive
+    /// window sizing, which would otherwise replace them.
+    pub fn apply(&self, emulation: &mut wreq::Emulation) {
+        if !self.is_set() {
+            return;
+        }
+
+        let h2 = emulation
+            .http2_options_mut()
+            .get_or_insert_with(Http2Options::default);
+        if let Some(size) = self.stream_window {
+            h2.adaptive_window = false;
+            h2.initial_window_size = size;
+        }
+        if let Some(size) = self.connection_window {
+            h2.adaptive_window = false;
+            h2.initial_conn_window_size = size;
+        }
+        if let Some(size) = self.max_frame_size {
+            h2.max_frame_size = Some(size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Http2Windows;
+    use wreq::http2::Http2Options;
+
+    #[test]
+    fn overrides_only_the_configured_windows() {
+        let mut emulation = wreq::Emulation::builder()
+            .http2_options(Http2Options::builder().max_frame_size(32768).build())
+            .build();
+        Http2Windows {
+            stream_window: Some(8 << 20),
+            connection_window: None,
+            max_frame_size: None,
+        }
+        .apply(&mut emulation);
+
+        let h2 = emulation.http2_options_mut().as_ref().unwrap();
+        assert_eq!(h2.initial_window_size, 8 << 20);
+        assert_eq!(
+            h2.initial_conn_window_size,
+            Http2Options::default().initial_conn_window_size
+        );
+        assert_eq!(h2.max_frame_size, Some(32768));
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::{Context, Result, anyhow, bail};
+use bytes::Bytes;
+use dashmap::DashMap;
+use http_body::{Body as HttpBody, Frame};
+use std::convert::Infallible;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use wreq::header::{HeaderMap, HeaderName, HeaderValue, OrigHeaderMap};
+use wreq::{Method, Version, redirect};
+
+use crate::client::{HTTP_RUNTIME, check_transport_egress, ensure_accepting, get_transport_client};
+use crate::contexts::{Resource, release};
+
+// Outgoing DATA frames buffered ahead of the connection before writes wait.
+const OUTGOING_FRAME_BUFFER: usize = 16;
+
+// Global storage for open HTTP/2 streams
+static H2_STREAMS: LazyLock<DashMap<u64, Arc<H2Stream>>> = LazyLock::new(DashMap::new);
+
+static NEXT_H2_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone)]
+pub struct H2StreamOptions {
+    pub transport_id: String,
+    pub method: String,
+    pub scheme: String,
+    pub authority: String,
+    pub path: String,
+    pub headers: Vec<(String, HeaderValue)>,
+    /// Budget for receiving the response headers, in milliseconds (0 means none).
+    pub timeout: u64,
+}
+
+/// A frame received on the response side of a stream.
+#[derive(Debug)]
+pub enum H2Frame {
+    Headers {
+        status: u16,
+        headers: Vec<(String, String)>,
+    },
+    Data(Bytes),
+    Trailers(Vec<(String, String)>),
+}
+
+enum ResponseState {
+    Pending(JoinHandle<Result<wreq::Response>>),
+    Receiving(wreq::Response),
+    Finished,
+}
+
+struct H2Stream {
+    // Dropped once the request side ends, which sends END_STREAM.
+    sender: Mutex<Option<mpsc::Sender<Frame<Bytes>>>>,
+    response: Mutex<ResponseState>,
+    // Cancelled on close so a pending read does not outlive the stream.
+    closed: CancellationToken,
+}
+
+impl Drop for H2Stream {
+    fn drop(&mut self) {
+        // Abandoning the request future resets the stream with CANCEL.
+        if let ResponseState::Pending(handle) = self.response.get_mut() {
+            handle.abort();
+        }
+    }
+}
+
+/// Request body fed frame by frame from JS; the stream ends when the sender is dropped.
+struct ChannelBody {
+    frames: mpsc::Receiver<Frame<Bytes>>,
+}
+
+impl HttpBody for ChannelBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        self.frames.poll_recv(cx).map(|frame| frame.map(Ok))
+    }
+}
+
+fn header_map(headers: &[(String, HeaderValue)]) -> Result<HeaderMap> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name: {}", name))?;
+        map.append(name, value.clone());
+    }
+    Ok(map)
+}
+
+pub fn header_tuples(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+fn get_stream(id: u64) -> Result<Arc<H2Stream>> {
+    H2_STREAMS
+        .get(&id)
+        .map(|entry| entry.value().clone())
+        .ok_or_else(|| anyhow!("HTTP/2 stream not found"))
+}
+
+/// Open a stream on a transport's pooled HTTP/2 connection.
+///
+/// The pseudo-headers come straight from the options, and only the caller's headers follow
+/// them: the profile's default headers and Accept-Encoding are left out. Request DATA frames
+/// are sent as they are written, so the server may answer before the request side has ended.
+pub fn open_h2_stream(options: H2StreamOptions) -> Result<u64> {
+    ensure_accepting()?;
+    let client = get_transport_client(&options.transport_id)?;
+
+    let method = Method::from_bytes(options.method.as_bytes())
+        .with_context(|| format!("Unsupported HTTP method: {}", options.method))?;
+    let uri: wreq::Uri = format!("{}://{}{}", options.scheme, options.authority, options.path)
+        .parse()
+        .context("Invalid :scheme, :authority or :path")?;
+    check_transport_egress(&options.transport_id, &uri)?;
+
+    let mut orig = OrigHeaderMap::new();
+    for (name, _) in &options.headers {
+        orig.insert(name.clone());
+    }
+
+    let (sender, frames) = mpsc::channel(OUTGOING_FRAME_BUFFER);
+    let request = client
+        .request(method, uri.clone())
+        .version(Version::HTTP_2)
+        .headers(header_map(&options.headers)?)
+        .orig_headers(orig)
+        .default_headers(false)
+        .gzip(false)
+        .deflate(false)
+        .brotli(false)
+        .zstd(false)
+        .redirect(redirect::Policy::none())
+        .body(wreq::Body::wrap(ChannelBody { frames }));
+
+    let timeout = options.timeout;
+    let handle = HTTP_RUNTIME.spawn(async move {
+        let send = request.send();
+        let sent = if timeout > 0 {
+            tokio::time::timeout(Duration::from_millis(timeout), send)
+                .await
+                .map_err(|_| anyhow!("Timed out waiting for response headers"))?
+        } else {
+            send.await
+        };
+
+        // wreq refuses to put an HTTP/2 request on an HTTP/1 connection.
+        sent.with_context(|| format!("HTTP/2 stream to {}", uri))
+    });
+
+    let id = NEXT_H2_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    H2_STREAMS.insert(
+        id,
+        Arc::new(H2Stream {
+            sender: Mutex::new(Some(sender)),
+            response: Mutex::new(ResponseState::Pending(handle)),
+            closed: CancellationToken::new(),
+        }),
+    );
+    Ok(id)
+}
+
+/// Send one DATA frame on the request side.
+pub async fn write_h2_stream(id: u64, data: Vec<u8>) -> Result<()> {
+    let stream = get_stream(id)?;
+    let sender = stream.sender.lock().await;
+    let Some(sender) = sender.as_ref() else {
+        bail!("HTTP/2 stream request side has already ended");
+    };
+
+    tokio::select! {
+        sent = sender.send(Frame::data(Bytes::from(data))) => {
+            sent.map_err(|_| anyhow!("HTTP/2 stream was reset before the frame was sent"))
+        }
+        _ = stream.closed.cancelled() => bail!("HTTP/2 stream was closed"),
+    }
+}
+
+/// End the request side, optionally with trailers. Without trailers an empty DATA
+/// frame carries END_STREAM.
+pub async fn end_h2_stream(id: u64, trailers: Vec<(String, HeaderValue)>) -> Result<()> {
+    let stream = get_stream(id)?;
+    let Some(sender) = stream.sender.lock().await.take() else {
+        return Ok(());
+    };
+
+    if !trailers.is_empty() {
+        let trailers = header_map(&trailers)?;
+        sender
+            .send(Frame::trailers(trailers))
+            .await
+            .map_err(|_| anyhow!("HTTP/2 stream was reset before the trailers were sent"))?;
+    }
+    Ok(())
+}
+
+/// Receive the next response frame: headers first, then data, then trailers when the
+/// server sends them. Returns `None` once the response side has ended.
+pub async fn read_h2_stream(id: u64) -> Result<Option<H2Frame>> {
+    let stream = get_stream(id)?;
+    let mut state = stream.response.lock().await;
+
+    match &mut *state {
+        ResponseState::Pending(handle) => {
+            let joined = tokio::select! {
+                joined = handle => joined,
+                _ = stream.closed.cancelled() => return Ok(None),
+            };
+            let response = match joined {
+                Ok(Ok(response)) => response,
+                Ok(Err(error)) => {
+                    *state = ResponseState::Finished;
+                    return Err(error);
+                }
+                Err(error) => {
+                    *state = ResponseState::Finished;
+                    return Err(anyhow!("HTTP/2 stream task failed: {}", error));
+                }
+            };
+
+            let frame = H2Frame::Headers {
+                status: response.status().as_u16(),
+                headers: header_tuples(response.headers()),
+            };
+            *state = ResponseState::Receiving(response);
+            Ok(Some(frame))
+        }
+        ResponseState::Receiving(response) => {
+            let next = tokio::select! {
+                next = poll_fn(|cx| Pin::new(&mut *response).poll_frame(cx)) => next,
+                _ = stream.closed.cancelled() => return Ok(None),
+            };
+
+            match next {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => Ok(Some(H2Frame::Data(data))),
+                    Err(frame) => {
+                        *state = ResponseState::Finished;
+                        Ok(frame
+                            .into_trailers()
+                            .ok()
+                            .map(|trailers| H2Frame::Trailers(header_tuples(&trailers))))
+                    }
+                },
+                Some(Err(error)) => {
+                    *state = ResponseState::Finished;
+                    Err(error).context("Failed to read HTTP/2 stream")
+                }
+                None => {
+                    *state = ResponseState::Finished;
+                    Ok(None)
+                }
+            }
+        }
+        ResponseState::Finished => Ok(None),
+    }
+}
+
+/// Drop a stream. A stream that has not finished is reset with CANCEL.
+pub fn close_h2_stream(id: u64) {
+    if let Some((_, stream)) = H2_STREAMS.remove(&id) {
+        release(Resource::H2Stream, &id.to_string());
+        stream.closed.cancel();
+    }
+}
+
+pub fn close_all_h2_streams() {
+    let ids: Vec<u64> = H2_STREAMS.iter().map(|entry| *entry.key()).collect();
+    for id in ids {
+        close_h2_stream(id);
+    }
+}
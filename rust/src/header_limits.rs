@@ -0,0 +1,116 @@
+use anyhow::Error;
+use std::fmt;
+use wreq::http1::Http1Options;
+use wreq::http2::Http2Options;
+
+/// Smallest header buffer HTTP/1 accepts; hyper panics below it.
+pub const MIN_RESPONSE_HEADER_BYTES: usize = 8192;
+
+/// Per-transport caps on the response head. Unset limits keep the browser profile's settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeaderLimits {
+    /// Largest response head in bytes: the HTTP/1 read buffer and the HTTP/2
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub max_bytes: Option<usize>,
+    /// Most header fields an HTTP/1 response may carry.
+    pub max_count: Option<usize>,
+}
+
+impl HeaderLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_bytes.is_some() || self.max_count.is_some()
+    }
+
+    /// Write the limits into the emulation's HTTP/1 and HTTP/2 options, keeping the rest of the
+    /// profile's settings.
+    pub fn apply(&self, emulation: &mut wreq::Emulation) {
+        if !self.is_set() {
+            return;
+        }
+
+        let h1 = emulation
+            .http1_options_mut()
+            .get_or_insert_with(Http1Options::default);
+        if let Some(max_bytes) = self.max_bytes {
+            h1.h1_max_buf_size = Some(max_bytes);
+            h1.h1_read_buf_exact_size = None;
+        }
+        if let Some(max_count) = self.max_count {
+            h1.h1_max_headers = Some(max_count);
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            let h2 = emulation
+                .http2_options_mut()
+                .get_or_insert_with(Http2Options::default);
+            h2.max_header_list_size = Some(u32::try_from(max_bytes).unwrap_or(u32::MAX));
+        }
+    }
+
+    /// Tag a failed send whose response head went over the limits, so callers can tell it apart
+    /// from other protocol errors.
+    pub fn classify(&self, error: Error) -> Error {
+        if is_oversized_head(&error) {
+            error.context(HeaderLimitExceeded { limits: *self })
+        } else {
+            error
+        }
+    }
+}
+
+/// Context attached when a response head was rejected for being too large.
+#[derive(Debug)]
+pub struct HeaderLimitExceeded {
+    pub limits: HeaderLimits,
+}
+
+impl fmt::Display for HeaderLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Response headers exceeded the limit")?;
+        match (self.limits.max_bytes, self.limits.max_count) {
+            (Some(bytes), Some(count)) => write!(
+                f,
+                " (maxResponseHeaderBytes {}, maxHeaderCount {})",
+                bytes, count
+            ),
+            (Some(bytes), None) => write!(f, " (maxResponseHeaderBytes {})", bytes),
+            (None, Some(count)) => write!(f, " (maxHeaderCount {})", count),
+            (None, None) => write!(f, " (profile defaults)"),
+        }
+    }
+}
+
+// HTTP/1 reports both too many fields and an overfull buffer as an oversized head; HTTP/2 refuses
+// the stream when the header block exceeds the advertised list size.
+fn is_oversized_head(error: &Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string();
+        message == "message head is too large"
+            || message.contains("refused stream before processing any application logic")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeaderLimitExceeded, HeaderLimits};
+    use anyhow::anyhow;
+
+    #[test]
+    fn tags_only_oversized_response_heads() {
+        let limits = HeaderLimits {
+            max_bytes: Some(16384),
+            max_count: None,
+        };
+
+        let oversized = limits.classify(anyhow!("message head is too large").context("send"));
+        let tagged = oversized.downcast_ref::<HeaderLimitExceeded>().unwrap();
+        assert_eq!(tagged.limits, limits);
+        assert_eq!(
+            tagged.to_string(),
+            "Response headers exceeded the limit (maxResponseHeaderBytes 16384)"
+        );
+
+        let other = limits.classify(anyhow!("connection reset"));
+        assert!(other.downcast_ref::<HeaderLimitExceeded>().is_none());
+    }
+}
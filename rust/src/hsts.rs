@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A known HSTS host (RFC 6797), with its expiry as milliseconds since the Unix epoch.
+#[derive(Debug, Clone)]
+pub struct HstsEntry {
+    pub host: String,
+    pub expires_at: u64,
+    pub include_subdomains: bool,
+}
+
+/// In-memory HSTS cache owned by a single session.
+#[derive(Debug, Default)]
+pub struct HstsStore {
+    entries: RwLock<HashMap<String, HstsEntry>>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Parse a `Strict-Transport-Security` value into `(max_age_secs, include_subdomains)`.
+fn parse_header(value: &str) -> Option<(u64, bool)> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+
+    for directive in value.split(';') {
+        let directive = directive.trim();
+        if directive.eq_ignore_ascii_case("includesubdomains") {
+            include_subdomains = true;
+            continue;
+        }
+
+        if let Some((_, raw)) = directive
+            .split_once('=')
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("max-age"))
+        {
+            max_age = raw.trim().trim_matches('"').parse::<u64>().ok();
+        }
+    }
+
+    max_age.map(|secs| (secs, include_subdomains))
+}
+
+impl HstsStore {
+    /// Whether `host`, or a parent domain with `includeSubDomains`, is a live HSTS host.
+    pub fn is_known_host(&self, host: &str) -> bool {
+        let host = normalize_host(host);
+        let now = now_millis();
+        let entries = self
+            .entries
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if entries
+            .get(&host)
+            .is_some_and(|entry| entry.expires_at > now)
+        {
+            return true;
+        }
+
+        let mut domain = host.as_str();
+        while let Some((_, parent)) = domain.split_once('.') {
+            if entries
+                .get(parent)
+                .is_some_and(|entry| entry.include_subdomains && entry.expires_at > now)
+            {
+                return true;
+            }
+            domain = parent;
+        }
+
+        false
+    }
+
+    /// Rewrite an `http://` URL to `https://` when its host is a known HSTS host.
+    pub fn upgrade_url(&self, url: &str) -> Option<String> {
+        if url.len() < 7 || !url[..7].eq_ignore_ascii_case("http://") {
+            return None;
+        }
+
+        let uri: wreq::Uri = url.parse().ok()?;
+        if !self.is_known_host(uri.host()?) {
+            return None;
+        }
+
+        // RFC 6797 §8.3: an explicit port 80 becomes the default HTTPS port; others are kept.
+        let rest = &url[7..];
+        let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, tail) = rest.split_at(authority_end);
+        let authority = authority.strip_suffix(":80").unwrap_or(authority);
+        Some(format!("https://{}{}", authority, tail))
+    }
+
+    /// Record a `Strict-Transport-Security` header received over HTTPS from `host`.
+    pub fn record(&self, host: &str, header_value: &str) {
+        // RFC 6797 §8.1: IP-literal hosts are never noted as HSTS hosts.
+        let host = normalize_host(host.trim_start_matches('[').trim_end_matches(']'));
+        if host.is_empty() || host.parse::<IpAddr>().is_ok() {
+            return;
+        }
+
+        let Some((max_age, include_subdomains)) = parse_header(header_value) else {
+            return;
+        };
+
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if max_age == 0 {
+            entries.remove(&host);
+            return;
+        }
+
+        let expires_at = now_millis().saturating_add(max_age.saturating_mul(1000));
+        entries.insert(
+            host.clone(),
+            HstsEntry {
+                host,
+                expires_at,
+                include_subdomains,
+            },
+        );
+    }
+
+    /// Snapshot of every unexpired entry, suitable for persisting.
+    pub fn entries(&self) -> Vec<HstsEntry> {
+        let now = now_millis();
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries.values().cloned().collect()
+    }
+
+    /// Merge previously persisted entries into the store, skipping expired ones.
+    pub fn import(&self, imported: Vec<HstsEntry>) {
+        let now = now_millis();
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for entry in imported {
+            if entry.expires_at <= now {
+                continue;
+            }
+            let host = normalize_host(&entry.host);
+            entries.insert(host.clone(), HstsEntry { host, ..entry });
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+}
@@ -0,0 +1,177 @@
+use bytes::Bytes;
+use neon::prelude::*;
+use serde_json::Value;
+use std::fmt;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A response body that serde_json could not parse, with the position it gave up at.
+#[derive(Debug)]
+pub struct JsonSyntaxError {
+    message: String,
+    /// Byte offset into the body, BOM included.
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    body: Bytes,
+}
+
+impl fmt::Display for JsonSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid JSON at byte {} (line {}, column {}): {}",
+            self.offset, self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for JsonSyntaxError {}
+
+/// Parse a response body the way `JSON.parse` would parse its UTF-8 text. A leading BOM is
+/// skipped, as `TextDecoder` does.
+pub fn parse_json_body(body: Bytes) -> Result<Value, JsonSyntaxError> {
+    let bom = if body.starts_with(UTF8_BOM) {
+        UTF8_BOM.len()
+    } else {
+        0
+    };
+    let text = &body[bom..];
+
+    serde_json::from_slice(text).map_err(|error| {
+        // serde_json reports 1-based lines and byte columns; a truncated body fails at its end.
+        let offset = if error.is_eof() {
+            text.len()
+        } else {
+            let line_start = text
+                .split_inclusive(|byte| *byte == b'\n')
+                .take(error.line().saturating_sub(1))
+                .map(<[u8]>::len)
+                .sum::<usize>();
+            (line_start + error.column().saturating_sub(1)).min(text.len())
+        };
+        let message = error.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        JsonSyntaxError {
+            message,
+            offset: bom + offset,
+            line: error.line(),
+            column: error.column(),
+            body,
+        }
+    })
+}
+
+/// Build the JS value for a parsed body.
+pub fn json_to_js<'a, C: Context<'a>>(cx: &mut C, value: Value) -> JsResult<'a, JsValue> {
+    let value = match value {
+        Value::Null => cx.null().upcast(),
+        Value::Bool(value) => cx.boolean(value).upcast(),
+        Value::Number(number) => cx.number(number.as_f64().unwrap_or(f64::NAN)).upcast(),
+        Value::String(value) => cx.string(value).upcast(),
+        Value::Array(items) => {
+            let array = JsArray::new(cx, items.len());
+            for (index, item) in items.into_iter().enumerate() {
+                let item = json_to_js(cx, item)?;
+                array.set(cx, index as u32, item)?;
+            }
+            array.upcast()
+        }
+        Value::Object(entries) => {
+            let object = cx.empty_object();
+            for (key, item) in entries {
+                let item = json_to_js(cx, item)?;
+                if key == "__proto__" {
+                    define_own_property(cx, object, &key, item)?;
+                } else {
+                    object.set(cx, key.as_str(), item)?;
+                }
+            }
+            object.upcast()
+        }
+    };
+    Ok(value)
+}
+
+// Assigning `__proto__` would replace the prototype; JSON.parse creates an own property instead.
+fn define_own_property<'a, C: Context<'a>>(
+    cx: &mut C,
+    object: Handle<'a, JsObject>,
+    key: &str,
+    value: Handle<'a, JsValue>,
+) -> NeonResult<()> {
+    let constructor: Handle<JsObject> = cx.global("Object")?;
+    let define: Handle<JsFunction> = constructor.get(cx, "defineProperty")?;
+    let descriptor = cx.empty_object();
+    descriptor.set(cx, "value", value)?;
+    for flag in ["writable", "enumerable", "configurable"] {
+        let enabled = cx.boolean(true);
+        descriptor.set(cx, flag, enabled)?;
+    }
+    let key = cx.string(key);
+    define.call(
+        cx,
+        constructor,
+        [object.upcast(), key.upcast(), descriptor.upcast()],
+    )?;
+    Ok(())
+}
+
+/// Settle a body serde_json rejected. `JSON.parse` accepts a few inputs serde_json does not
+/// (numbers that overflow to `Infinity`, lone surrogates, invalid UTF-8, deep nesting), so the
+/// text is handed to it before giving up. When it fails too, a `SyntaxError` carrying
+/// `offset`, `line`, and `column` is thrown.
+pub fn parse_rejected_json<'a, C: Context<'a>>(
+    cx: &mut C,
+    error: &JsonSyntaxError,
+) -> JsResult<'a, JsValue> {
+    let text = String::from_utf8_lossy(&error.body);
+    let text = cx.string(text.strip_prefix('\u{feff}').unwrap_or(&text));
+    let json: Handle<JsObject> = cx.global("JSON")?;
+    let parse: Handle<JsFunction> = json.get(cx, "parse")?;
+    if let Ok(value) = cx.try_catch(|cx| parse.call(cx, json, [text.upcast()])) {
+        return Ok(value);
+    }
+
+    let constructor: Handle<JsFunction> = cx.global("SyntaxError")?;
+    let message = cx.string(error.to_string());
+    let js_error = constructor.construct(cx, [message.upcast()])?;
+    let offset = cx.number(error.offset as f64);
+    js_error.set(cx, "offset", offset)?;
+    let line = cx.number(error.line as f64);
+    js_error.set(cx, "line", line)?;
+    let column = cx.number(error.column as f64);
+    js_error.set(cx, "column", column)?;
+    cx.throw(js_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_json_body;
+    use bytes::Bytes;
+
+    #[test]
+    fn keeps_key_order_and_skips_a_bom() {
+        let value = parse_json_body(Bytes::from_static(
+            b"\xEF\xBB\xBF{\"b\":1,\"a\":[true,null]}",
+        ))
+        .unwrap();
+        let keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["b", "a"]);
+    }
+
+    #[test]
+    fn reports_the_byte_offset_of_syntax_errors() {
+        let error =
+            parse_json_body(Bytes::from_static(b"{\n  \"a\": 1,\n  \"b\": x\n}")).unwrap_err();
+        assert_eq!((error.line, error.column), (3, 8));
+        assert_eq!(error.offset, 19);
+        assert!(!error.to_string().contains(" at line "), "{error}");
+
+        let error = parse_json_body(Bytes::from_static(b"\xEF\xBB\xBF[1,")).unwrap_err();
+        assert_eq!(error.offset, 6);
+    }
+}
@@ -1,73 +1,600 @@
+mod abort;
+mod accept_language;
+mod alt_svc;
+mod audit;
+mod auth;
+mod before_send;
+mod bench_client;
+mod body_credit;
+mod browser_cookies;
+mod buffer_pool;
+mod cassette;
+mod charset;
+mod cli;
 mod client;
+mod client_hints;
+mod connect_to;
+mod contexts;
+mod cookie_hook;
+mod cross_origin;
+mod curl;
+mod deadline;
+mod defaults;
+mod digest;
+mod dns;
+mod egress;
+mod encoding;
+mod fetch_set;
+mod ffi;
+mod flow_control;
 mod generated_profiles;
+mod h2_stream;
+mod header_limits;
+mod hsts;
+mod json_body;
+mod memory;
+mod meta_refresh;
+mod mock;
+mod native_guard;
+mod ntlm;
+mod ntlm_proxy;
+mod pacing;
+mod phase;
+mod priority;
+mod probe;
+mod profiles;
+mod proxy_rotation;
+mod reason_phrase;
+mod request_mode;
+mod shared_ring;
+mod throttle;
+mod tls_socket;
+mod trace;
+mod trailers;
 mod websocket;
 
+pub use bench_client::run as run_bench_client;
+pub use cli::run as run_cli;
+
+use abort::{AbortHandle, error_code};
+use accept_language::accept_language;
 use anyhow::anyhow;
+use audit::{
+    AuditOptions, disable_audit_log as native_disable_audit_log,
+    enable_audit_log as native_enable_audit_log,
+};
+use auth::{AccessToken, RefreshHook, SessionAuth, TokenEndpoint, TokenSource};
+use before_send::{BeforeSendAnswer, BeforeSendHook, OutgoingRequest};
+use body_credit::{BodyCredit, push_body};
+use browser_cookies::parse_browser_cookies;
+use bytes::{Bytes, BytesMut};
+use cassette::{CassetteMode, MatchField, eject_cassette, insert_cassette};
 use client::{
-    HTTP_RUNTIME, RedirectMode, RequestOptions, Response, clear_managed_session,
-    create_managed_session, create_managed_transport, drop_body_stream, drop_managed_session,
-    drop_managed_transport, generate_session_id, get_session_cookies, make_request,
+    BodyChunk, BodyStreamLeak, HTTP_RUNTIME, RedirectMode, RequestOptions, Response,
+    SessionOptions, TcpKeepAlive, TransportOptions, begin_shutdown,
+    cancel_all_requests as native_cancel_all_requests, clear_managed_session,
+    clear_managed_transport_tls_sessions, clear_session_hsts, clear_transport_alt_svc,
+    client_stats, create_managed_session, create_managed_transport, drop_body_stream,
+    drop_body_streams_in_group, drop_managed_session, drop_managed_transport, generate_session_id,
+    get_session_cookies, get_session_hsts, get_transport_alt_svc, header_text,
+    import_session_browser_cookies, import_session_hsts, make_request, managed_session_token,
     read_body_all as native_read_body_all, read_body_chunk as native_read_body_chunk,
-    set_session_cookie,
+    release_client_resources, request_to_curl, requests_drained, set_body_stream_leak_hook,
+    set_body_stream_ttl, set_max_body_streams, set_session_cookie, take_body_digest,
+    take_body_size, take_body_trailers, tee_body_to_file,
 };
+use client_hints::{ClientHintBrand, ClientHints};
+use connect_to::ConnectTo;
+use contexts::{
+    ContextId, RegistryMode, Resource, check_access, claim, context_registered, register_context,
+    release, set_registry_mode as native_set_registry_mode, unregister_context,
+};
+use cookie_hook::{SetCookieHook, StoredCookie};
 use dashmap::DashMap;
+use defaults::{RequestDefaults, request_defaults, set_request_defaults};
+use digest::DigestAlgorithm;
+use encoding::{AcceptEncoding, BodySize};
+use fetch_set::{
+    DEFAULT_RETRY_STATUSES, FetchSetOptions, FetchSetResult, RetryEvent, RetryHook, run_fetch_set,
+};
+use flow_control::{MAX_FRAME_SIZE, MAX_WINDOW_SIZE, MIN_FRAME_SIZE};
 use futures_util::StreamExt;
+use h2_stream::{
+    H2Frame, H2StreamOptions, close_all_h2_streams, close_h2_stream, end_h2_stream, open_h2_stream,
+    read_h2_stream, write_h2_stream,
+};
+use header_limits::{HeaderLimitExceeded, MIN_RESPONSE_HEADER_BYTES};
+use hsts::HstsEntry;
+use json_body::{json_to_js, parse_json_body, parse_rejected_json};
+use memory::{Held, INLINE_BODIES, MemoryHold, QUEUED_BODY_CHUNKS, QUEUED_WS_MESSAGES};
+use mock::{MockResponse, MockRule, clear_mocks, mock_call_count, register_mock, remove_mock};
+use native_guard::NativeGuard;
 use neon::prelude::*;
+use neon::thread::LocalKey;
 use neon::types::{
-    JsArray, JsBoolean, JsBuffer, JsNull, JsObject, JsString, JsUndefined, JsValue,
+    JsArray, JsBoolean, JsBuffer, JsError, JsNull, JsObject, JsString, JsUndefined, JsValue,
     buffer::TypedArray,
 };
-use std::collections::HashMap;
+use ntlm::NtlmCredentials;
+use pacing::{Jitter, Pacing};
+use phase::PhaseFailure;
+use priority::{MAX_URGENCY, Priority};
+use probe::{ProbeOptions, probe as native_probe};
+use profiles::{resolve_profile, supported_operating_systems, supported_profiles};
+use proxy_rotation::{ProxyPool, RotationStrategy};
+use request_mode::RequestMode;
+use shared_ring::{SharedRing, pipe_body_to_ring};
 use std::sync::Arc;
 use std::sync::LazyLock;
-use tokio::sync::{Semaphore, mpsc};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tls_socket::{
+    TlsSocketOptions, close_all_tls_sockets, close_tls_socket, connect_tls_socket, end_tls_socket,
+    read_tls_socket, write_tls_socket,
+};
+use tokio::sync::{Semaphore, mpsc, oneshot};
 use tokio_util::sync::CancellationToken;
+use trace::TraceContext;
 use websocket::{
-    WebSocketOptions, WebSocketUpgradeMetadata, WsClosePayload, WsConnection, connect_websocket,
-    connect_websocket_with_session, get_connection, remove_connection, store_connection,
+    WebSocketOptions, WebSocketUpgradeMetadata, WsActivity, WsClosePayload, WsConnection,
+    connect_websocket, connect_websocket_with_session, connection_count, get_connection,
+    remove_connection, store_connection, take_all_connections, take_connection,
 };
+use wreq::header::HeaderValue;
 use wreq::ws::message::Message;
 use wreq_util::{Emulation, EmulationOS};
 
 const WS_EVENT_BUFFER: usize = 64;
-static REQUEST_CANCELLATIONS: LazyLock<DashMap<u64, CancellationToken>> =
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const SHUTDOWN_WS_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+// Request IDs come from one process-wide counter, so requests from different contexts or JS
+// wrappers never share one. The context in the key scopes cancelRequest() to its caller.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+static REQUEST_CANCELLATIONS: LazyLock<DashMap<(ContextId, u64), RequestCancellation>> =
     LazyLock::new(DashMap::new);
 
-// Parse browser string to Emulation enum using serde
-fn parse_emulation(browser: &str) -> Emulation {
-    static EMULATION_CACHE: LazyLock<HashMap<&'static str, Emulation>> = LazyLock::new(|| {
-        generated_profiles::BROWSER_PROFILES
-            .iter()
-            .filter_map(|label| {
-                // Populate cache once up-front; failures fall back to the default below.
-                serde_json::from_value::<Emulation>(serde_json::Value::String((*label).to_string()))
-                    .ok()
-                    .map(|emulation| (*label, emulation))
-            })
-            .collect()
-    });
+// Token refreshes waiting for a JS `onRefresh` callback to answer, by refresh id.
+static NEXT_AUTH_REFRESH_ID: AtomicU64 = AtomicU64::new(1);
+static AUTH_REFRESHES: LazyLock<DashMap<u64, oneshot::Sender<anyhow::Result<AccessToken>>>> =
+    LazyLock::new(DashMap::new);
+
+// Requests waiting for their JS `onBeforeSend` callback to answer, by sign id.
+static NEXT_BEFORE_SEND_ID: AtomicU64 = AtomicU64::new(1);
+static BEFORE_SENDS: LazyLock<DashMap<u64, BeforeSendAnswer>> = LazyLock::new(DashMap::new);
 
-    EMULATION_CACHE
-        .get(browser)
-        .cloned()
-        .unwrap_or(Emulation::Chrome142)
+// Credit of the bodies being pushed to JS, by body handle.
+static BODY_CREDITS: LazyLock<DashMap<u64, Arc<BodyCredit>>> = LazyLock::new(DashMap::new);
+
+struct RequestCancellation {
+    abort: AbortHandle,
+    group_id: Option<Arc<str>>,
 }
 
-fn parse_emulation_os(os: &str) -> EmulationOS {
-    static OS_CACHE: LazyLock<HashMap<&'static str, EmulationOS>> = LazyLock::new(|| {
-        generated_profiles::OPERATING_SYSTEMS
-            .iter()
-            .filter_map(|label| {
-                serde_json::from_value::<EmulationOS>(serde_json::Value::String(
-                    (*label).to_string(),
-                ))
-                .ok()
-                .map(|emulation_os| (*label, emulation_os))
-            })
-            .collect()
-    });
+// The context (main thread or worker) each instance of the addon was loaded into. Neon drops it
+// when the context's environment is torn down, e.g. when a worker exits.
+static CONTEXT: LocalKey<LoadedContext> = LocalKey::new();
+
+// The context whose `onLeak` callback is installed, so it can be removed when that context exits.
+static LEAK_HOOK_CONTEXT: Mutex<Option<ContextId>> = Mutex::new(None);
+
+struct LoadedContext(ContextId);
+
+impl Drop for LoadedContext {
+    // Release what the exiting context left behind: its callbacks would run on a dead
+    // environment, and nothing can reach its sessions, transports, sockets, streams, or
+    // requests anymore.
+    fn drop(&mut self) {
+        let context = self.0;
+        for (resource, id) in unregister_context(context) {
+            match resource {
+                Resource::Session => drop_managed_session(&id),
+                Resource::Transport => drop_managed_transport(&id),
+                Resource::WebSocket => {
+                    if let Some(connection) = id.parse().ok().and_then(take_connection) {
+                        connection.release();
+                        HTTP_RUNTIME.spawn(async move {
+                            let close = connection.close(None);
+                            let _ = tokio::time::timeout(SHUTDOWN_WS_CLOSE_TIMEOUT, close).await;
+                        });
+                    }
+                }
+                Resource::TlsSocket => {
+                    if let Ok(id) = id.parse() {
+                        close_tls_socket(id);
+                    }
+                }
+                Resource::H2Stream => {
+                    if let Ok(id) = id.parse() {
+                        close_h2_stream(id);
+                    }
+                }
+            }
+        }
+
+        REQUEST_CANCELLATIONS.retain(|(owner, _), cancellation| {
+            if *owner == context {
+                cancellation.abort.abort(None);
+            }
+            *owner != context
+        });
+
+        let mut leak_hook_context = LEAK_HOOK_CONTEXT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *leak_hook_context == Some(context) {
+            *leak_hook_context = None;
+            set_body_stream_leak_hook(None);
+        }
+    }
+}
+
+fn current_context<'a, C: Context<'a>>(cx: &mut C) -> ContextId {
+    CONTEXT
+        .get_or_init_with(cx, || LoadedContext(register_context()))
+        .0
+}
+
+// Throw the registry's own "not found" error for a session or transport that belongs to
+// another context under an isolated registry.
+fn ensure_visible<'a, C: Context<'a>>(cx: &mut C, resource: Resource, id: &str) -> NeonResult<()> {
+    let context = current_context(cx);
+    match check_access(context, resource, id) {
+        Ok(()) => Ok(()),
+        Err(e) => throw_anyhow(cx, e),
+    }
+}
+
+// When set, unknown browser profiles and operating systems throw instead of falling back to
+// chrome_142/macos. Requests can also opt in one at a time with `strictProfiles`.
+static STRICT_PROFILES: AtomicBool = AtomicBool::new(false);
+
+// Whether body bytes can be handed to JS without copying. Electron builds V8 with sandboxed
+// pointers, where creating an external buffer aborts the process, so it gets copies instead.
+static EXTERNAL_BUFFERS: AtomicBool = AtomicBool::new(false);
+
+// Parse browser string (a profile or an alias) to Emulation enum using serde
+fn parse_emulation(cx: &mut FunctionContext, browser: &str, strict: bool) -> NeonResult<Emulation> {
+    match profiles::emulation(browser) {
+        Some(emulation) => Ok(emulation),
+        None if strict || STRICT_PROFILES.load(Ordering::Relaxed) => cx.throw_type_error(format!(
+            "Unknown browser profile '{}'. Valid profiles: {} (or an alias like 'chrome:latest')",
+            browser,
+            supported_profiles().join(", ")
+        )),
+        None => Ok(Emulation::Chrome142),
+    }
+}
+
+fn parse_emulation_os(cx: &mut FunctionContext, os: &str, strict: bool) -> NeonResult<EmulationOS> {
+    match profiles::emulation_os(os) {
+        Some(emulation_os) => Ok(emulation_os),
+        None if strict || STRICT_PROFILES.load(Ordering::Relaxed) => cx.throw_type_error(format!(
+            "Unknown operating system '{}'. Valid operating systems: {}",
+            os,
+            supported_operating_systems().join(", ")
+        )),
+        None => Ok(EmulationOS::MacOS),
+    }
+}
+
+// A missing `acceptEncoding` keeps the profile's Accept-Encoding; an empty list asks for identity.
+fn get_accept_encoding(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+) -> NeonResult<Option<AcceptEncoding>> {
+    if obj
+        .get_opt(cx, "acceptEncoding")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsArray, _>(cx).ok())
+        .is_none()
+    {
+        return Ok(None);
+    }
+
+    let names = get_string_array(cx, obj, "acceptEncoding")?;
+    match AcceptEncoding::parse(&names) {
+        Ok(accept) => Ok(Some(accept)),
+        Err(e) => cx.throw_type_error(e.to_string()),
+    }
+}
+
+// `priority` is `{ urgency, incremental }` with urgency 0 (highest) to 7.
+fn get_priority(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<Option<Priority>> {
+    let Some(priority) = obj
+        .get_opt(cx, "priority")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok(None);
+    };
+
+    let urgency = priority
+        .get_opt(cx, "urgency")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx));
+    let urgency = match urgency {
+        Some(urgency)
+            if urgency.fract() == 0.0 && (0.0..=f64::from(MAX_URGENCY)).contains(&urgency) =>
+        {
+            urgency as u8
+        }
+        _ => {
+            return cx.throw_type_error(format!(
+                "priority.urgency must be an integer from 0 to {}",
+                MAX_URGENCY
+            ));
+        }
+    };
+    let incremental = priority
+        .get_opt(cx, "incremental")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
+
+    Ok(Some(Priority {
+        urgency,
+        incremental,
+    }))
+}
+
+// `proxyPool` is `{ proxies, strategy, requestsPerProxy }`; JS has already checked its shape.
+fn get_proxy_pool(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+) -> NeonResult<Option<ProxyPool>> {
+    let Some(pool) = obj
+        .get_opt(cx, "proxyPool")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok(None);
+    };
+
+    let proxies: Vec<Arc<str>> = get_string_array(cx, pool, "proxies")?
+        .into_iter()
+        .map(Arc::from)
+        .collect();
+    if proxies.is_empty() {
+        return cx.throw_type_error("proxyPool.proxies must not be empty");
+    }
+
+    let strategy = pool
+        .get_opt(cx, "strategy")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+        .map(|v| v.value(cx));
+    let strategy = match strategy.as_deref() {
+        None | Some("stickyUntilError") => RotationStrategy::StickyUntilError,
+        Some("rotateEveryNRequests") => {
+            let requests = pool
+                .get_opt(cx, "requestsPerProxy")?
+                .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+                .map(|v| v.value(cx) as u32)
+                .unwrap_or(1);
+            RotationStrategy::EveryNRequests(requests.max(1))
+        }
+        Some(other) => {
+            return cx.throw_type_error(format!("Unsupported proxy rotation strategy: {}", other));
+        }
+    };
+
+    Ok(Some(ProxyPool { proxies, strategy }))
+}
+
+// `ntlm` and `proxyNtlm` are `{ username, password, domain, workstation }`; JS has already
+// checked their shape.
+fn get_ntlm_credentials(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+    key: &str,
+) -> NeonResult<Option<NtlmCredentials>> {
+    let Some(credentials) = obj
+        .get_opt(cx, key)?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok(None);
+    };
+
+    let mut string = |key: &str| -> NeonResult<Option<String>> {
+        Ok(credentials
+            .get_opt(cx, key)?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+            .map(|v| v.value(cx)))
+    };
+    let (Some(username), Some(password)) = (string("username")?, string("password")?) else {
+        return cx.throw_type_error(format!("{} needs a username and a password", key));
+    };
+    let domain = string("domain")?;
+    let workstation = string("workstation")?;
+    Ok(Some(NtlmCredentials::new(
+        username,
+        password,
+        domain,
+        workstation,
+    )))
+}
+
+// `clientHints` is `{ brands, fullVersion, platform, platformVersion, mobile, arch, model }`;
+// JS has already checked its shape.
+// connectTo: [{ fromHost, fromPort?, toHost?, toPort? }], validated in JS.
+fn get_connect_to(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<Vec<ConnectTo>> {
+    let Some(array) = obj
+        .get_opt(cx, "connectTo")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsArray, _>(cx).ok())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut rules = Vec::new();
+    for value in array.to_vec(cx)? {
+        let rule = value.downcast_or_throw::<JsObject, _>(cx)?;
+        let from_host = rule.get::<JsString, _, _>(cx, "fromHost")?.value(cx);
+        let from_port = rule
+            .get_opt::<JsNumber, _, _>(cx, "fromPort")?
+            .map(|v| v.value(cx) as u16);
+        let to_host = rule
+            .get_opt::<JsString, _, _>(cx, "toHost")?
+            .map(|v| v.value(cx));
+        let to_port = rule
+            .get_opt::<JsNumber, _, _>(cx, "toPort")?
+            .map(|v| v.value(cx) as u16);
+        rules.push(ConnectTo {
+            from_host,
+            from_port,
+            to_host,
+            to_port,
+        });
+    }
+    Ok(rules)
+}
+
+fn get_client_hints(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+) -> NeonResult<Option<ClientHints>> {
+    let Some(hints) = obj
+        .get_opt(cx, "clientHints")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok(None);
+    };
+
+    let brands = match hints
+        .get_opt(cx, "brands")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsArray, _>(cx).ok())
+    {
+        Some(array) => {
+            let mut brands = Vec::new();
+            for value in array.to_vec(cx)? {
+                let brand = value.downcast_or_throw::<JsObject, _>(cx)?;
+                let name = brand.get::<JsString, _, _>(cx, "brand")?.value(cx);
+                let version = brand.get::<JsString, _, _>(cx, "version")?.value(cx);
+                brands.push(ClientHintBrand {
+                    brand: name,
+                    version,
+                });
+            }
+            Some(brands)
+        }
+        None => None,
+    };
+    let mut string = |key: &str| -> NeonResult<Option<String>> {
+        Ok(hints
+            .get_opt(cx, key)?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+            .map(|v| v.value(cx)))
+    };
+    let full_version = string("fullVersion")?;
+    let platform = string("platform")?;
+    let platform_version = string("platformVersion")?;
+    let arch = string("arch")?;
+    let model = string("model")?;
+    let mobile = hints
+        .get_opt(cx, "mobile")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx));
+
+    Ok(Some(ClientHints {
+        brands,
+        full_version,
+        platform,
+        platform_version,
+        mobile,
+        arch,
+        model,
+    }))
+}
+
+// HTTP/2 settings outside their protocol range would be rejected by the server or panic in the
+// connection, so they are checked before a transport is built.
+fn get_bounded_u32(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+    key: &str,
+    min: u32,
+    max: u32,
+) -> NeonResult<Option<u32>> {
+    let Some(value) = obj
+        .get_opt(cx, key)?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx))
+    else {
+        return Ok(None);
+    };
+
+    if value.fract() != 0.0 || value < f64::from(min) || value > f64::from(max) {
+        return cx.throw_type_error(format!(
+            "{} must be an integer between {} and {}",
+            key, min, max
+        ));
+    }
+    Ok(Some(value as u32))
+}
+
+// `tcpKeepAlive` is `false` to turn probing off, `true` for wreq's defaults, or an object
+// overriding the idle time, probe interval (both in ms) and retry count.
+fn get_tcp_keepalive(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+) -> NeonResult<Option<TcpKeepAlive>> {
+    let Some(value) = obj.get_opt::<JsValue, _, _>(cx, "tcpKeepAlive")? else {
+        return Ok(None);
+    };
+
+    if let Ok(enabled) = value.downcast::<JsBoolean, _>(cx) {
+        return Ok(Some(if enabled.value(cx) {
+            TcpKeepAlive::Enabled {
+                idle: None,
+                interval: None,
+                retries: None,
+            }
+        } else {
+            TcpKeepAlive::Disabled
+        }));
+    }
+
+    let Ok(settings) = value.downcast::<JsObject, _>(cx) else {
+        return Ok(None);
+    };
+    let mut number = |key: &str| -> NeonResult<Option<f64>> {
+        Ok(settings
+            .get_opt(cx, key)?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+            .map(|v| v.value(cx)))
+    };
+    let idle = number("idle")?.map(|ms| Duration::from_millis(ms as u64));
+    let interval = number("interval")?.map(|ms| Duration::from_millis(ms as u64));
+    let retries = number("retries")?.map(|count| count as u32);
+    Ok(Some(TcpKeepAlive::Enabled {
+        idle,
+        interval,
+        retries,
+    }))
+}
+
+fn parse_redirect_mode(cx: &mut FunctionContext, name: &str) -> NeonResult<RedirectMode> {
+    match RedirectMode::parse(name) {
+        Some(mode) => Ok(mode),
+        None => cx.throw_type_error(format!("Unsupported redirect mode: {}", name)),
+    }
+}
+
+// Read an optional array of strings from an options object, e.g. transport egress patterns
+fn get_string_array(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+    key: &str,
+) -> NeonResult<Vec<String>> {
+    let Some(array) = obj
+        .get_opt(cx, key)?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsArray, _>(cx).ok())
+    else {
+        return Ok(Vec::new());
+    };
 
-    OS_CACHE.get(os).cloned().unwrap_or(EmulationOS::MacOS)
+    let values = array.to_vec(cx)?;
+    let mut strings = Vec::with_capacity(values.len());
+    for value in values {
+        strings.push(value.downcast_or_throw::<JsString, _>(cx)?.value(cx));
+    }
+    Ok(strings)
 }
 
 fn coerce_header_value(cx: &mut FunctionContext, value: Handle<JsValue>) -> NeonResult<String> {
@@ -79,10 +606,28 @@ fn coerce_header_value(cx: &mut FunctionContext, value: Handle<JsValue>) -> Neon
     Ok(converted.value(cx))
 }
 
+// Buffers are taken as raw bytes, so latin-1 or other non-UTF-8 values are sent unchanged.
+fn parse_header_value(
+    cx: &mut FunctionContext,
+    name: &str,
+    value: Handle<JsValue>,
+) -> NeonResult<HeaderValue> {
+    let parsed = if let Ok(buffer) = value.downcast::<JsBuffer, _>(cx) {
+        HeaderValue::from_bytes(buffer.as_slice(cx))
+    } else {
+        HeaderValue::try_from(coerce_header_value(cx, value)?)
+    };
+
+    match parsed {
+        Ok(value) => Ok(value),
+        Err(_) => cx.throw_type_error(format!("Invalid value for header '{}'", name)),
+    }
+}
+
 fn parse_header_tuple(
     cx: &mut FunctionContext,
     tuple: Handle<JsArray>,
-) -> NeonResult<(String, String)> {
+) -> NeonResult<(String, HeaderValue)> {
     if tuple.len(cx) < 2 {
         return cx.throw_type_error("Header tuple must contain a name and a value");
     }
@@ -90,7 +635,7 @@ fn parse_header_tuple(
     let name_value = tuple.get(cx, 0)?;
     let value_value = tuple.get(cx, 1)?;
     let name = coerce_header_value(cx, name_value)?;
-    let value = coerce_header_value(cx, value_value)?;
+    let value = parse_header_value(cx, &name, value_value)?;
 
     Ok((name, value))
 }
@@ -98,7 +643,7 @@ fn parse_header_tuple(
 fn parse_headers_from_array(
     cx: &mut FunctionContext,
     array: Handle<JsArray>,
-) -> NeonResult<Vec<(String, String)>> {
+) -> NeonResult<Vec<(String, HeaderValue)>> {
     let len = array.len(cx);
     let mut headers = Vec::with_capacity(len as usize);
 
@@ -115,7 +660,7 @@ fn parse_headers_from_array(
 fn parse_headers_from_object(
     cx: &mut FunctionContext,
     obj: Handle<JsObject>,
-) -> NeonResult<Vec<(String, String)>> {
+) -> NeonResult<Vec<(String, HeaderValue)>> {
     let keys = obj.get_own_property_names(cx)?;
     let keys_vec = keys.to_vec(cx)?;
     let mut headers = Vec::with_capacity(keys_vec.len());
@@ -124,7 +669,7 @@ fn parse_headers_from_object(
         if let Ok(key_str) = key_val.downcast::<JsString, _>(cx) {
             let key = key_str.value(cx);
             let value = obj.get(cx, key.as_str())?;
-            let value = coerce_header_value(cx, value)?;
+            let value = parse_header_value(cx, &key, value)?;
             headers.push((key, value));
         }
     }
@@ -135,7 +680,7 @@ fn parse_headers_from_object(
 fn parse_headers_from_value(
     cx: &mut FunctionContext,
     value: Handle<JsValue>,
-) -> NeonResult<Vec<(String, String)>> {
+) -> NeonResult<Vec<(String, HeaderValue)>> {
     if value.is_a::<JsUndefined, _>(cx) || value.is_a::<JsNull, _>(cx) {
         return Ok(Vec::new());
     }
@@ -153,6 +698,51 @@ fn parse_headers_from_value(
     cx.throw_type_error("headers must be an array or object")
 }
 
+// Throw a JS Error carrying the full formatted message, with `cause` links mirroring the
+// anyhow context chain so callers can inspect each layer separately.
+fn throw_anyhow<'a, C: Context<'a>, T>(cx: &mut C, error: anyhow::Error) -> NeonResult<T> {
+    let js_error = anyhow_to_js_error(cx, error)?;
+    cx.throw(js_error)
+}
+
+fn anyhow_to_js_error<'a, C: Context<'a>>(
+    cx: &mut C,
+    error: anyhow::Error,
+) -> JsResult<'a, JsError> {
+    let layers = error.chain().len();
+    let mut cause: Option<Handle<'a, JsError>> = None;
+
+    for layer in error.chain().rev().take(layers.saturating_sub(1)) {
+        let js_error = cx.error(layer.to_string())?;
+        if let Some(inner) = cause {
+            js_error.set(cx, "cause", inner)?;
+        }
+        cause = Some(js_error);
+    }
+
+    let js_error = cx.error(format!("{:#}", error))?;
+    if let Some(inner) = cause {
+        js_error.set(cx, "cause", inner)?;
+    }
+    // Failed requests say which phase they failed in.
+    if let Some(failure) = error.downcast_ref::<PhaseFailure>() {
+        let phase = cx.string(failure.phase.as_str());
+        js_error.set(cx, "phase", phase)?;
+        let elapsed = cx.number(failure.elapsed.as_secs_f64() * 1000.0);
+        js_error.set(cx, "phaseElapsed", elapsed)?;
+    }
+    let code = if error.downcast_ref::<HeaderLimitExceeded>().is_some() {
+        Some("ERR_RESPONSE_HEADERS_TOO_LARGE")
+    } else {
+        error_code(&error)
+    };
+    if let Some(code) = code {
+        let code = cx.string(code);
+        js_error.set(cx, "code", code)?;
+    }
+    Ok(js_error)
+}
+
 // Convert JS object to RequestOptions
 fn js_object_to_request_options(
     cx: &mut FunctionContext,
@@ -162,21 +752,32 @@ fn js_object_to_request_options(
     let url: Handle<JsString> = obj.get(cx, "url")?;
     let url = url.value(cx);
 
+    // Options the request leaves unset fall back to `setDefaults`, then to the built-in defaults.
+    let defaults = request_defaults();
+
     // Get browser (optional, defaults to chrome_142)
     let browser_str = obj
         .get_opt(cx, "browser")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
         .map(|v| v.value(cx))
+        .or_else(|| defaults.browser.clone())
         .unwrap_or_else(|| "chrome_142".to_string());
 
-    let emulation = parse_emulation(&browser_str);
+    let strict_profiles = obj
+        .get_opt(cx, "strictProfiles")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
+
+    let emulation = parse_emulation(cx, &browser_str, strict_profiles)?;
     let os_str = obj
         .get_opt(cx, "os")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
         .map(|v| v.value(cx))
+        .or_else(|| defaults.os.clone())
         .unwrap_or_else(|| "macos".to_string());
 
-    let emulation_os = parse_emulation_os(&os_str);
+    let emulation_os = parse_emulation_os(cx, &os_str, strict_profiles)?;
 
     // Get method (optional, defaults to GET)
     let method = obj
@@ -207,10 +808,11 @@ fn js_object_to_request_options(
         None
     };
 
-    // Get proxy (optional)
-    let proxy = obj
-        .get_opt(cx, "proxy")?
-        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+    // Get proxy (optional); `null` connects directly
+    let proxy_value = obj.get_opt::<JsValue, _, _>(cx, "proxy")?;
+    let direct = proxy_value.is_some_and(|v| v.is_a::<JsNull, _>(cx));
+    let proxy = proxy_value
+        .and_then(|v| v.downcast::<JsString, _>(cx).ok())
         .map(|v| Arc::<str>::from(v.value(cx)));
 
     // Get timeout (optional, defaults to 30000ms)
@@ -218,20 +820,16 @@ fn js_object_to_request_options(
         .get_opt(cx, "timeout")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
         .map(|v| v.value(cx) as u64)
+        .or(defaults.timeout)
         .unwrap_or(30000);
 
     // Get redirect policy (optional, defaults to follow)
-    let redirect = obj
+    let redirect = match obj
         .get_opt(cx, "redirect")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
-        .map(|v| v.value(cx))
-        .unwrap_or_else(|| "follow".to_string());
-
-    let redirect = match redirect.as_str() {
-        "follow" => RedirectMode::Follow,
-        "manual" => RedirectMode::Manual,
-        "error" => RedirectMode::Error,
-        other => return cx.throw_type_error(format!("Unsupported redirect mode: {}", other)),
+    {
+        Some(name) => parse_redirect_mode(cx, &name.value(cx))?,
+        None => defaults.redirect.unwrap_or_default(),
     };
 
     // Get sessionId (optional)
@@ -248,6 +846,9 @@ fn js_object_to_request_options(
         .map(|v| v.value(cx))
         .unwrap_or(false);
 
+    let append_headers = get_string_array(cx, obj, "appendHeaders")?;
+    let remove_headers = get_string_array(cx, obj, "removeHeaders")?;
+
     let disable_default_headers = obj
         .get_opt(cx, "disableDefaultHeaders")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
@@ -258,6 +859,14 @@ fn js_object_to_request_options(
         .get_opt(cx, "insecure")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
         .map(|v| v.value(cx))
+        .or(defaults.insecure)
+        .unwrap_or(false);
+
+    let origin_pooling = obj
+        .get_opt(cx, "originPooling")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .or(defaults.origin_pooling)
         .unwrap_or(false);
 
     let transport_id = obj
@@ -266,6 +875,12 @@ fn js_object_to_request_options(
         .map(|v| v.value(cx))
         .filter(|v| !v.trim().is_empty());
 
+    // A transport has its own proxy; the default one only stands in for a request's own.
+    let proxy = match proxy {
+        None if !direct && transport_id.is_none() => defaults.proxy.clone(),
+        proxy => proxy,
+    };
+
     let pool_idle_timeout = obj
         .get_opt(cx, "poolIdleTimeout")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
@@ -284,21 +899,149 @@ fn js_object_to_request_options(
     let connect_timeout = obj
         .get_opt(cx, "connectTimeout")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
-        .map(|v| v.value(cx) as u64);
+        .map(|v| v.value(cx) as u64)
+        .or(defaults.connect_timeout);
 
     let read_timeout = obj
         .get_opt(cx, "readTimeout")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx) as u64)
+        .or(defaults.read_timeout);
+
+    let write_timeout = obj
+        .get_opt(cx, "writeTimeout")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx) as u64);
+
+    let total_deadline = obj
+        .get_opt(cx, "totalDeadline")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx) as u64);
+
+    let block_insecure_redirects = obj
+        .get_opt(cx, "blockInsecureRedirects")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
+
+    let minimal_response = obj
+        .get_opt(cx, "minimalResponse")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
+
+    let disable_cookies = obj
+        .get_opt(cx, "disableCookies")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
+
+    let decode_body = obj
+        .get_opt(cx, "decodeBody")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(true);
+
+    // An empty list is meaningful (strip nothing), so only a missing key falls back to wreq.
+    let strip_on_cross_origin = match obj
+        .get_opt(cx, "stripOnCrossOrigin")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsArray, _>(cx).ok())
+    {
+        Some(_) => Some(get_string_array(cx, obj, "stripOnCrossOrigin")?),
+        None => None,
+    };
+
+    let accept_encoding = get_accept_encoding(cx, obj)?;
+    let priority = get_priority(cx, obj)?;
+    let accept_language = accept_language(&get_string_array(cx, obj, "locale")?);
+
+    let meta_refresh_max_delay = obj
+        .get_opt(cx, "metaRefreshMaxDelay")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx) as u64);
+    let ntlm = get_ntlm_credentials(cx, obj, "ntlm")?;
+    let client_hints = get_client_hints(cx, obj)?;
+    let connect_to = get_connect_to(cx, obj)?;
+    let before_send = obj
+        .get_opt::<JsFunction, _, _>(cx, "onBeforeSend")?
+        .map(|callback| before_send_hook(cx, callback));
+    let mode = match obj
+        .get_opt(cx, "mode")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+    {
+        Some(name) => {
+            let name = name.value(cx);
+            match RequestMode::parse(&name) {
+                Some(mode) => Some(mode),
+                None => return cx.throw_type_error(format!("Unsupported request mode: {}", name)),
+            }
+        }
+        None => None,
+    };
+
+    let group_id = obj
+        .get_opt(cx, "groupId")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+        .map(|v| Arc::<str>::from(v.value(cx)));
+
+    let max_download_bytes_per_sec = obj
+        .get_opt(cx, "maxDownloadBytesPerSec")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx) as u64);
+
+    let max_upload_bytes_per_sec = obj
+        .get_opt(cx, "maxUploadBytesPerSec")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
         .map(|v| v.value(cx) as u64);
 
+    let body_digest = match obj
+        .get_opt(cx, "bodyDigest")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+    {
+        Some(name) => match DigestAlgorithm::parse(&name.value(cx)) {
+            Ok(algorithm) => Some(algorithm),
+            Err(e) => return cx.throw_type_error(e.to_string()),
+        },
+        None => None,
+    };
+
+    let trace = match obj
+        .get_opt(cx, "trace")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsObject, _>(cx).ok())
+    {
+        Some(trace_obj) => {
+            let traceparent: Handle<JsString> = trace_obj.get(cx, "traceparent")?;
+            let traceparent = traceparent.value(cx);
+            let tracestate = trace_obj
+                .get_opt(cx, "tracestate")?
+                .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+                .map(|v| v.value(cx));
+            match TraceContext::new(traceparent, tracestate) {
+                Ok(context) => Some(context),
+                Err(e) => return cx.throw_type_error(e.to_string()),
+            }
+        }
+        None => None,
+    };
+
+    if !ephemeral {
+        ensure_visible(cx, Resource::Session, &session_id)?;
+    }
+    if let Some(transport_id) = transport_id.as_deref() {
+        ensure_visible(cx, Resource::Transport, transport_id)?;
+    }
+
     Ok(RequestOptions {
         url,
         emulation,
         emulation_os,
         headers,
+        append_headers,
+        remove_headers,
         method,
         body,
         proxy,
+        direct,
         timeout,
         redirect,
         session_id,
@@ -311,6 +1054,29 @@ fn js_object_to_request_options(
         pool_max_size,
         connect_timeout,
         read_timeout,
+        group_id,
+        max_download_bytes_per_sec,
+        max_upload_bytes_per_sec,
+        body_digest,
+        trace,
+        write_timeout,
+        total_deadline,
+        block_insecure_redirects,
+        strip_on_cross_origin,
+        disable_cookies,
+        inline_body_max: defaults.inline_body_max,
+        accept_encoding,
+        decode_body,
+        origin_pooling,
+        priority,
+        accept_language,
+        meta_refresh_max_delay,
+        ntlm,
+        client_hints,
+        mode,
+        minimal_response,
+        before_send,
+        connect_to,
     })
 }
 
@@ -318,45 +1084,50 @@ fn js_object_to_request_options(
 fn response_to_js_object<'a, C: Context<'a>>(
     cx: &mut C,
     response: Response,
+    header_format: HeaderFormat,
 ) -> JsResult<'a, JsObject> {
     let obj = cx.empty_object();
 
     // Status
     let status = cx.number(response.status as f64);
     obj.set(cx, "status", status)?;
+    if let Some(reason) = &response.reason {
+        let reason = cx.string(reason);
+        obj.set(cx, "reason", reason)?;
+    }
 
     // URL
     let url = cx.string(&response.url);
     obj.set(cx, "url", url)?;
 
-    // Headers
-    let headers_arr = cx.empty_array();
-    for (i, (key, value)) in response.headers.iter().enumerate() {
-        let entry = cx.empty_array();
-        let key_str = cx.string(key);
-        let value_str = cx.string(value);
-        entry.set(cx, 0, key_str)?;
-        entry.set(cx, 1, value_str)?;
-        headers_arr.set(cx, i as u32, entry)?;
-    }
-    obj.set(cx, "headers", headers_arr)?;
-
-    // Cookies (as array of [key, value] tuples)
-    let cookies_arr = cx.empty_array();
-    for (i, (key, value)) in response.cookies.iter().enumerate() {
-        let entry = cx.empty_array();
-        let key_str = cx.string(key);
-        let value_str = cx.string(value);
-        entry.set(cx, 0, key_str)?;
-        entry.set(cx, 1, value_str)?;
-        cookies_arr.set(cx, i as u32, entry)?;
+    match header_format {
+        // Minimal responses read no headers or cookies.
+        _ if response.minimal => {}
+        // Headers and cookies as one JSON string of flat name/value lists, decoded once in JS
+        // instead of building a JS array per entry here.
+        HeaderFormat::Compact => {
+            let fields = compact_header_fields(&response.headers, &response.cookies);
+            let fields = cx.string(fields);
+            obj.set(cx, "headerFields", fields)?;
+        }
+        HeaderFormat::Object => {
+            let headers = header_object(cx, &response.headers)?;
+            obj.set(cx, "headerObject", headers)?;
+            let cookies = tuple_array(cx, &response.cookies)?;
+            obj.set(cx, "cookies", cookies)?;
+        }
+        HeaderFormat::Tuples => {
+            let headers = tuple_array(cx, &response.headers)?;
+            obj.set(cx, "headers", headers)?;
+            let cookies = tuple_array(cx, &response.cookies)?;
+            obj.set(cx, "cookies", cookies)?;
+        }
     }
-    obj.set(cx, "cookies", cookies_arr)?;
 
     // Inline body bytes for small responses (avoids a second native round-trip)
     match response.body_bytes {
         Some(bytes) => {
-            let buffer = JsBuffer::from_slice(cx, &bytes)?;
+            let buffer = inline_body_buffer(cx, bytes)?;
             obj.set(cx, "bodyBytes", buffer)?;
         }
         None => {
@@ -370,6 +1141,8 @@ fn response_to_js_object<'a, C: Context<'a>>(
         Some(handle) => {
             let handle_num = cx.number(handle as f64);
             obj.set(cx, "bodyHandle", handle_num)?;
+            let guard = cx.boxed(NativeGuard::Body(handle));
+            obj.set(cx, "bodyGuard", guard)?;
         }
         None => {
             let null_value = cx.null();
@@ -386,65 +1159,463 @@ fn response_to_js_object<'a, C: Context<'a>>(
         obj.set(cx, "contentLength", null_value)?;
     }
 
-    Ok(obj)
-}
+    // Connection details: pool reuse, the serving client, and socket addresses
+    let connection = response.connection;
+    let connection_obj = cx.empty_object();
+    let reused: Handle<JsValue> = match connection.reused {
+        Some(reused) => cx.boolean(reused).upcast(),
+        None => cx.null().upcast(),
+    };
+    connection_obj.set(cx, "reused", reused)?;
+    let client = cx.string(connection.client.as_str());
+    connection_obj.set(cx, "client", client)?;
+    let transport_id: Handle<JsValue> = match connection.transport_id {
+        Some(id) => cx.string(id).upcast(),
+        None => cx.null().upcast(),
+    };
+    connection_obj.set(cx, "transportId", transport_id)?;
+    let local_address: Handle<JsValue> = match connection.local_addr {
+        Some(addr) => cx.string(addr.to_string()).upcast(),
+        None => cx.null().upcast(),
+    };
+    connection_obj.set(cx, "localAddress", local_address)?;
+    let remote_address: Handle<JsValue> = match connection.remote_addr {
+        Some(addr) => cx.string(addr.to_string()).upcast(),
+        None => cx.null().upcast(),
+    };
+    connection_obj.set(cx, "remoteAddress", remote_address)?;
+    let dns: Handle<JsValue> = match connection.dns {
+        Some(answer) => {
+            let dns_obj = cx.empty_object();
+            let hostname = cx.string(&answer.hostname);
+            dns_obj.set(cx, "hostname", hostname)?;
+            let addresses = cx.empty_array();
+            for (i, address) in answer.addresses.iter().enumerate() {
+                let address = cx.string(address.to_string());
+                addresses.set(cx, i as u32, address)?;
+            }
+            dns_obj.set(cx, "addresses", addresses)?;
+            dns_obj.upcast()
+        }
+        None => cx.null().upcast(),
+    };
+    connection_obj.set(cx, "dns", dns)?;
+    obj.set(cx, "connection", connection_obj)?;
 
-// Main request function exported to Node.js
-fn request(mut cx: FunctionContext) -> JsResult<JsPromise> {
-    // Get the options object
-    let options_obj = cx.argument::<JsObject>(0)?;
-    let request_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u64;
-    let cancellable = cx
-        .argument_opt(2)
-        .and_then(|value| value.downcast::<JsBoolean, _>(&mut cx).ok())
+    // Header block size, which stays meaningful for a streamed body's sizes
+    let header_bytes = cx.number(response.header_bytes as f64);
+    obj.set(cx, "headerBytes", header_bytes)?;
+
+    if response.minimal {
+        return Ok(obj);
+    }
+
+    // Digest of the inline body, when one was requested
+    let body_digest: Handle<JsValue> = match response.body_digest {
+        Some(digest) => cx.string(digest).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "bodyDigest", body_digest)?;
+
+    // Trailers of the inline body
+    let trailers: Handle<JsValue> = match response.trailers {
+        Some(trailers) => header_tuples_to_js(cx, &trailers)?.upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "trailers", trailers)?;
+
+    // Inline body size, before and after decoding
+    let body_size: Handle<JsValue> = match response.body_size {
+        Some(size) => body_size_to_js(cx, size)?.upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "bodySize", body_size)?;
+
+    // Native timing of a traced request
+    let timing: Handle<JsValue> = match response.timing {
+        Some(timing) => {
+            let timing_obj = cx.empty_object();
+            let start = cx.number(timing.start);
+            timing_obj.set(cx, "startTime", start)?;
+            let response_start = cx.number(timing.response_start);
+            timing_obj.set(cx, "responseStartTime", response_start)?;
+            let redirects_arr = cx.empty_array();
+            for (i, hop) in timing.redirects.into_iter().enumerate() {
+                let hop_obj = cx.empty_object();
+                let status = cx.number(hop.status as f64);
+                hop_obj.set(cx, "status", status)?;
+                let location = cx.string(hop.location);
+                hop_obj.set(cx, "location", location)?;
+                let received_at = cx.number(hop.received_at);
+                hop_obj.set(cx, "time", received_at)?;
+                redirects_arr.set(cx, i as u32, hop_obj)?;
+            }
+            timing_obj.set(cx, "redirects", redirects_arr)?;
+            timing_obj.upcast()
+        }
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "timing", timing)?;
+
+    Ok(obj)
+}
+
+/// How a response's headers and cookies are handed to JS.
+#[derive(Clone, Copy)]
+enum HeaderFormat {
+    /// `headers` and `cookies` as arrays of `[name, value]` tuples.
+    Tuples,
+    /// One `headerFields` JSON string; see `compact_header_fields`.
+    Compact,
+    /// A Fetch-style `headerObject` of lower-cased names and comma-joined values, with `cookies`
+    /// as tuples.
+    Object,
+}
+
+fn header_format(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<HeaderFormat> {
+    let format = obj
+        .get_opt(cx, "headerFormat")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+        .map(|v| v.value(cx));
+    if format.as_deref() == Some("object") {
+        return Ok(HeaderFormat::Object);
+    }
+    let compact = obj
+        .get_opt(cx, "compactHeaders")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .is_some_and(|v| v.value(cx));
+    Ok(if compact {
+        HeaderFormat::Compact
+    } else {
+        HeaderFormat::Tuples
+    })
+}
+
+// `[[name, value, name, value, ...], [cookie, value, ...]]`
+fn compact_header_fields(headers: &[(String, String)], cookies: &[(String, String)]) -> String {
+    let flatten = |pairs: &[(String, String)]| {
+        pairs
+            .iter()
+            .flat_map(|(name, value)| [name.as_str(), value.as_str()])
+            .collect::<Vec<_>>()
+    };
+    serde_json::to_string(&[flatten(headers), flatten(cookies)])
+        .expect("string lists always serialize")
+}
+
+fn tuple_array<'a, C: Context<'a>>(
+    cx: &mut C,
+    pairs: &[(String, String)],
+) -> JsResult<'a, JsArray> {
+    let array = cx.empty_array();
+    for (i, (key, value)) in pairs.iter().enumerate() {
+        let entry = cx.empty_array();
+        let key_str = cx.string(key);
+        let value_str = cx.string(value);
+        entry.set(cx, 0, key_str)?;
+        entry.set(cx, 1, value_str)?;
+        array.set(cx, i as u32, entry)?;
+    }
+    Ok(array)
+}
+
+// Header names arrive lower-cased; repeated fields are joined with ", " as `Headers.get` does.
+fn header_object<'a, C: Context<'a>>(
+    cx: &mut C,
+    headers: &[(String, String)],
+) -> JsResult<'a, JsObject> {
+    let mut joined: Vec<(&str, String)> = Vec::with_capacity(headers.len());
+    for (name, value) in headers {
+        match joined
+            .iter_mut()
+            .find(|(existing, _)| *existing == name.as_str())
+        {
+            Some((_, existing)) => {
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            None => joined.push((name.as_str(), value.clone())),
+        }
+    }
+
+    let obj = cx.empty_object();
+    for (name, value) in joined {
+        let value = cx.string(value);
+        obj.set(cx, name, value)?;
+    }
+    Ok(obj)
+}
+
+// IDs stay far below 2^53, so they survive the trip through a JS number.
+fn next_request_id() -> u64 {
+    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Reserve an ID for a request that has to be cancellable before it starts.
+fn allocate_request_id(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    Ok(cx.number(next_request_id() as f64))
+}
+
+// The request ID from `allocateRequestId()`, or a fresh one when the caller passed none.
+fn request_id_argument(cx: &mut FunctionContext, index: usize) -> u64 {
+    cx.argument_opt(index)
+        .and_then(|value| value.downcast::<JsNumber, _>(cx).ok())
+        .map(|id| id.value(cx) as u64)
+        .unwrap_or_else(next_request_id)
+}
+
+// The resolved response says which ID the request ran under.
+fn response_with_request_id<'a, C: Context<'a>>(
+    cx: &mut C,
+    response: Response,
+    header_format: HeaderFormat,
+    request_id: u64,
+) -> JsResult<'a, JsObject> {
+    let obj = response_to_js_object(cx, response, header_format)?;
+    let request_id = cx.number(request_id as f64);
+    obj.set(cx, "requestId", request_id)?;
+    Ok(obj)
+}
+
+// Main request function exported to Node.js
+fn request(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    // Get the options object
+    let options_obj = cx.argument::<JsObject>(0)?;
+    let request_id = request_id_argument(&mut cx, 1);
+    let cancellable = cx
+        .argument_opt(2)
+        .and_then(|value| value.downcast::<JsBoolean, _>(&mut cx).ok())
         .map(|b| b.value(&mut cx))
         .unwrap_or(true);
 
     // Convert JS object to Rust struct
     let options = js_object_to_request_options(&mut cx, options_obj)?;
+    let header_format = header_format(&mut cx, options_obj)?;
+    let request_key = (current_context(&mut cx), request_id);
 
     // Create a promise
     let (deferred, promise) = cx.promise();
     let settle_channel = cx.channel();
 
-    if !cancellable {
-        HTTP_RUNTIME.spawn(async move {
-            let result = make_request(options).await;
+    spawn_request(options, request_key, cancellable, move |result| {
+        // Send result back to JS
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(response) => response_with_request_id(&mut cx, response, header_format, request_id),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
 
-            // Send result back to JS
-            deferred.settle_with(&settle_channel, move |mut cx| match result {
-                Ok(response) => response_to_js_object(&mut cx, response),
-                Err(e) => {
-                    // Format error with full chain for better debugging
-                    let error_msg = format!("{:#}", e);
-                    cx.throw_error(error_msg)
-                }
-            });
+    Ok(promise)
+}
+
+// Same as `request`, but reports the result through a Node-style `(error, response)` callback
+// instead of a promise.
+fn request_callback(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let options_obj = cx.argument::<JsObject>(0)?;
+    let request_id = request_id_argument(&mut cx, 1);
+    let cancellable = cx.argument::<JsBoolean>(2)?.value(&mut cx);
+    let callback = cx.argument::<JsFunction>(3)?.root(&mut cx);
+
+    let options = js_object_to_request_options(&mut cx, options_obj)?;
+    let header_format = header_format(&mut cx, options_obj)?;
+    let request_key = (current_context(&mut cx), request_id);
+    let channel = cx.channel();
+
+    spawn_request(options, request_key, cancellable, move |result| {
+        channel.send(move |mut cx| {
+            let cb = callback.into_inner(&mut cx);
+            let this = cx.undefined();
+            let args: Vec<Handle<JsValue>> = match result {
+                Ok(response) => vec![
+                    cx.null().upcast(),
+                    response_with_request_id(&mut cx, response, header_format, request_id)?
+                        .upcast(),
+                ],
+                Err(e) => vec![anyhow_to_js_error(&mut cx, e)?.upcast()],
+            };
+            cb.call(&mut cx, this, args)?;
+            Ok(())
         });
+    });
+
+    Ok(cx.undefined())
+}
 
-        return Ok(promise);
+// Run a request on the HTTP runtime and hand its result to `complete`. Cancellable requests are
+// registered under their context and `request_id` so cancelRequest() and cancelGroup() can
+// abort them.
+fn spawn_request<F>(
+    options: RequestOptions,
+    request_key: (ContextId, u64),
+    cancellable: bool,
+    complete: F,
+) where
+    F: FnOnce(anyhow::Result<Response>) + Send + 'static,
+{
+    if !cancellable {
+        HTTP_RUNTIME.spawn(async move {
+            complete(make_request(options).await);
+        });
+        return;
     }
 
-    let token = CancellationToken::new();
-    REQUEST_CANCELLATIONS.insert(request_id, token.clone());
+    let abort = AbortHandle::new();
+    REQUEST_CANCELLATIONS.insert(
+        request_key,
+        RequestCancellation {
+            abort: abort.clone(),
+            group_id: options.group_id.clone(),
+        },
+    );
 
     HTTP_RUNTIME.spawn(async move {
         let result = tokio::select! {
-            _ = token.cancelled() => Err(anyhow!("Request aborted")),
+            error = abort.aborted() => Err(error),
             res = make_request(options) => res,
         };
 
-        REQUEST_CANCELLATIONS.remove(&request_id);
+        REQUEST_CANCELLATIONS.remove(&request_key);
+        complete(result);
+    });
+}
 
-        // Send result back to JS
-        deferred.settle_with(&settle_channel, move |mut cx| match result {
-            Ok(response) => response_to_js_object(&mut cx, response),
-            Err(e) => {
-                // Format error with full chain for better debugging
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
+// Asks `callback(index, attempt, error, delayMs, status)` on the JS thread before each retry of a
+// fetch set; returning `false` gives up on that request.
+fn retry_hook(cx: &mut FunctionContext, callback: Handle<JsFunction>) -> RetryHook {
+    let callback = Arc::new(callback.root(cx));
+    let channel = cx.channel();
+    Arc::new(move |event: RetryEvent, answer: oneshot::Sender<bool>| {
+        let callback = callback.clone();
+        channel.send(move |mut cx| {
+            let cb = callback.to_inner(&mut cx);
+            let this = cx.undefined();
+            let error = cx.error(event.message)?;
+            if let Some(code) = event.code {
+                let code = cx.string(code);
+                error.set(&mut cx, "code", code)?;
+            }
+            let status: Handle<JsValue> = match event.status {
+                Some(status) => cx.number(status).upcast(),
+                None => cx.null().upcast(),
+            };
+            let args: Vec<Handle<JsValue>> = vec![
+                cx.number(event.index as f64).upcast(),
+                cx.number(event.attempt).upcast(),
+                error.upcast(),
+                cx.number(event.delay.as_millis() as f64).upcast(),
+                status,
+            ];
+            let proceed = cb.call(&mut cx, this, args)?;
+            let vetoed = proceed
+                .downcast::<JsBoolean, _>(&mut cx)
+                .is_ok_and(|proceed| !proceed.value(&mut cx));
+            let _ = answer.send(!vetoed);
+            Ok(())
+        });
+    })
+}
+
+// Send a list of requests with shared scheduling and retries. Each request's final result goes
+// to `onResult(index, attempts, error, response)` as it completes; the returned promise resolves
+// once every request has been reported. Cancelling `request_id` fails the ones still pending.
+fn fetch_set(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let requests_array = cx.argument::<JsArray>(0)?;
+    let set_obj = cx.argument::<JsObject>(1)?;
+    let request_id = request_id_argument(&mut cx, 2);
+    let on_result = Arc::new(cx.argument::<JsFunction>(3)?.root(&mut cx));
+    let on_retry = cx
+        .argument_opt(4)
+        .and_then(|value| value.downcast::<JsFunction, _>(&mut cx).ok())
+        .map(|callback| retry_hook(&mut cx, callback));
+
+    let mut requests = Vec::new();
+    let mut header_formats = Vec::new();
+    for value in requests_array.to_vec(&mut cx)? {
+        let obj = value.downcast_or_throw::<JsObject, _>(&mut cx)?;
+        requests.push(js_object_to_request_options(&mut cx, obj)?);
+        header_formats.push(header_format(&mut cx, obj)?);
+    }
+
+    let concurrency = get_bounded_u32(&mut cx, set_obj, "concurrency", 1, 10_000)?.unwrap_or(8);
+    let retries = get_bounded_u32(&mut cx, set_obj, "retries", 0, 100)?.unwrap_or(0);
+    let retry_delay = get_bounded_u32(&mut cx, set_obj, "retryDelay", 0, u32::MAX)?.unwrap_or(500);
+    let retry_statuses = match set_obj
+        .get_opt(&mut cx, "retryStatuses")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsArray, _>(&mut cx).ok())
+    {
+        Some(array) => {
+            let mut statuses = Vec::new();
+            for value in array.to_vec(&mut cx)? {
+                let status = value.downcast_or_throw::<JsNumber, _>(&mut cx)?;
+                statuses.push(status.value(&mut cx) as u16);
             }
+            statuses
+        }
+        None => DEFAULT_RETRY_STATUSES.to_vec(),
+    };
+    let options = FetchSetOptions {
+        concurrency: concurrency as usize,
+        retries,
+        retry_delay: Duration::from_millis(u64::from(retry_delay)),
+        retry_statuses,
+        on_retry,
+    };
+
+    let context = current_context(&mut cx);
+    let request_key = (context, request_id);
+    let abort = AbortHandle::new();
+    REQUEST_CANCELLATIONS.insert(
+        request_key,
+        RequestCancellation {
+            abort: abort.clone(),
+            group_id: requests
+                .first()
+                .and_then(|request| request.group_id.clone()),
+        },
+    );
+
+    let (deferred, promise) = cx.promise();
+    let channel = cx.channel();
+    let report_channel = channel.clone();
+    let report = move |FetchSetResult {
+                           index,
+                           attempts,
+                           result,
+                       }: FetchSetResult| {
+        // An exited context's set is aborted, and its results have nowhere to go.
+        if !context_registered(context) {
+            return;
+        }
+        let on_result = on_result.clone();
+        let header_format = header_formats[index];
+        report_channel.send(move |mut cx| {
+            let cb = on_result.to_inner(&mut cx);
+            let this = cx.undefined();
+            let index = cx.number(index as f64).upcast();
+            let attempts = cx.number(attempts as f64).upcast();
+            let args: Vec<Handle<JsValue>> = match result {
+                Ok(response) => vec![
+                    index,
+                    attempts,
+                    cx.null().upcast(),
+                    response_with_request_id(&mut cx, response, header_format, request_id)?
+                        .upcast(),
+                ],
+                Err(e) => vec![index, attempts, anyhow_to_js_error(&mut cx, e)?.upcast()],
+            };
+            cb.call(&mut cx, this, args)?;
+            Ok(())
         });
+    };
+
+    HTTP_RUNTIME.spawn(async move {
+        run_fetch_set(requests, options, abort, report).await;
+        REQUEST_CANCELLATIONS.remove(&request_key);
+        if !context_registered(context) {
+            return;
+        }
+        // Sent through the same channel as the results, so it settles after the last of them.
+        deferred.settle_with(&channel, |mut cx| Ok(cx.undefined()));
     });
 
     Ok(promise)
@@ -462,6 +1633,150 @@ fn get_profiles(mut cx: FunctionContext) -> JsResult<JsArray> {
     Ok(js_array)
 }
 
+// Choose whether this context's sessions and transports can be used from other contexts.
+fn set_registry_mode(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+    let Some(mode) = RegistryMode::parse(&name) else {
+        return cx.throw_type_error(format!("Unsupported registry mode: {}", name));
+    };
+
+    let context = current_context(&mut cx);
+    if let Err(e) = native_set_registry_mode(context, mode) {
+        return throw_anyhow(&mut cx, e);
+    }
+    Ok(cx.undefined())
+}
+
+fn set_strict_profiles(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+    STRICT_PROFILES.store(enabled, Ordering::Relaxed);
+    Ok(cx.undefined())
+}
+
+// Replace the request defaults. Profiles are checked strictly so a typo fails here rather
+// than silently falling back on every request.
+fn set_defaults(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let obj = cx.argument::<JsObject>(0)?;
+
+    let browser = obj
+        .get_opt(&mut cx, "browser")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx));
+    if let Some(browser) = &browser {
+        parse_emulation(&mut cx, browser, true)?;
+    }
+
+    let os = obj
+        .get_opt(&mut cx, "os")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx));
+    if let Some(os) = &os {
+        parse_emulation_os(&mut cx, os, true)?;
+    }
+
+    let redirect = match obj
+        .get_opt(&mut cx, "redirect")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+    {
+        Some(name) => {
+            let name = name.value(&mut cx);
+            Some(parse_redirect_mode(&mut cx, &name)?)
+        }
+        None => None,
+    };
+
+    let mut number = |key: &str| -> NeonResult<Option<u64>> {
+        Ok(obj
+            .get_opt(&mut cx, key)?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64))
+    };
+    let timeout = number("timeout")?;
+    let connect_timeout = number("connectTimeout")?;
+    let read_timeout = number("readTimeout")?;
+    let inline_body_max = number("inlineBodyMax")?;
+
+    let proxy = obj
+        .get_opt(&mut cx, "proxy")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| Arc::<str>::from(v.value(&mut cx)));
+
+    let mut boolean = |key: &str| -> NeonResult<Option<bool>> {
+        Ok(obj
+            .get_opt(&mut cx, key)?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx)))
+    };
+    let insecure = boolean("insecure")?;
+    let origin_pooling = boolean("originPooling")?;
+
+    set_request_defaults(RequestDefaults {
+        browser,
+        os,
+        timeout,
+        connect_timeout,
+        read_timeout,
+        proxy,
+        redirect,
+        insecure,
+        origin_pooling,
+        inline_body_max,
+    });
+    Ok(cx.undefined())
+}
+
+fn get_defaults(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let defaults = request_defaults();
+    let obj = cx.empty_object();
+
+    let strings = [
+        ("browser", defaults.browser.as_deref()),
+        ("os", defaults.os.as_deref()),
+        ("proxy", defaults.proxy.as_deref()),
+        ("redirect", defaults.redirect.map(RedirectMode::as_str)),
+    ];
+    for (key, value) in strings {
+        if let Some(value) = value {
+            let value = cx.string(value);
+            obj.set(&mut cx, key, value)?;
+        }
+    }
+
+    let numbers = [
+        ("timeout", defaults.timeout),
+        ("connectTimeout", defaults.connect_timeout),
+        ("readTimeout", defaults.read_timeout),
+        ("inlineBodyMax", defaults.inline_body_max),
+    ];
+    for (key, value) in numbers {
+        if let Some(value) = value {
+            let value = cx.number(value as f64);
+            obj.set(&mut cx, key, value)?;
+        }
+    }
+
+    let booleans = [
+        ("insecure", defaults.insecure),
+        ("originPooling", defaults.origin_pooling),
+    ];
+    for (key, value) in booleans {
+        if let Some(value) = value {
+            let value = cx.boolean(value);
+            obj.set(&mut cx, key, value)?;
+        }
+    }
+    Ok(obj)
+}
+
+// Resolve a browser profile alias such as "chrome:latest" to the profile it stands for
+fn resolve_profile_alias(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let alias = cx.argument::<JsString>(0)?.value(&mut cx);
+    match resolve_profile(&alias) {
+        Some(label) => Ok(cx.string(label).upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
 // Get list of available operating systems for emulation
 fn get_operating_systems(mut cx: FunctionContext) -> JsResult<JsArray> {
     let js_array = cx.empty_array();
@@ -474,162 +1789,678 @@ fn get_operating_systems(mut cx: FunctionContext) -> JsResult<JsArray> {
     Ok(js_array)
 }
 
-fn create_session(mut cx: FunctionContext) -> JsResult<JsString> {
+fn create_session(mut cx: FunctionContext) -> JsResult<JsObject> {
     let options_value = cx.argument_opt(0);
 
-    let session_id_opt = if let Some(value) = options_value {
+    let (session_id_opt, options) = if let Some(value) = options_value {
         if value.is_a::<JsUndefined, _>(&mut cx) || value.is_a::<JsNull, _>(&mut cx) {
-            None
+            (None, SessionOptions::default())
         } else {
             let obj = value.downcast_or_throw::<JsObject, _>(&mut cx)?;
             let session_id = obj
                 .get_opt(&mut cx, "sessionId")?
                 .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
                 .map(|v| v.value(&mut cx));
-            session_id
+            let hsts = obj
+                .get_opt(&mut cx, "hsts")?
+                .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+                .map(|v| v.value(&mut cx))
+                .unwrap_or(false);
+            let on_set_cookie = match obj.get_opt::<JsFunction, _, _>(&mut cx, "onSetCookie")? {
+                Some(callback) => Some(set_cookie_hook(&mut cx, callback)),
+                None => None,
+            };
+            let auth = get_session_auth(&mut cx, obj)?;
+            let accept_client_hints = obj
+                .get_opt(&mut cx, "acceptClientHints")?
+                .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+                .map(|v| v.value(&mut cx))
+                .unwrap_or(false);
+            let pacing = get_pacing(&mut cx, obj)?;
+            let options = SessionOptions {
+                hsts,
+                on_set_cookie,
+                auth,
+                accept_client_hints,
+                pacing,
+            };
+            (session_id, options)
         }
     } else {
-        None
+        (None, SessionOptions::default())
     };
 
     let session_id = session_id_opt.unwrap_or_else(generate_session_id);
 
-    match create_managed_session(session_id.clone()) {
-        Ok(id) => Ok(cx.string(id)),
-        Err(e) => {
-            let msg = format!("{:#}", e);
-            cx.throw_error(msg)
+    match create_managed_session(session_id.clone(), options) {
+        Ok(id) => {
+            claim(current_context(&mut cx), Resource::Session, &id);
+            let guard =
+                managed_session_token(&id).map(|token| NativeGuard::Session(id.clone(), token));
+            created_object(&mut cx, id, guard)
         }
+        Err(e) => throw_anyhow(&mut cx, e),
     }
 }
 
-fn create_transport(mut cx: FunctionContext) -> JsResult<JsString> {
-    let options_value = cx.argument_opt(0);
+// `pacing` is `{ minDelayMs, maxDelayMs, jitter }`; JS has already checked the range and fills
+// in `maxDelayMs`.
+fn get_pacing(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<Option<Pacing>> {
+    let Some(pacing) = obj.get_opt::<JsObject, _, _>(cx, "pacing")? else {
+        return Ok(None);
+    };
+    let min_delay = pacing.get::<JsNumber, _, _>(cx, "minDelayMs")?.value(cx);
+    let max_delay = pacing.get::<JsNumber, _, _>(cx, "maxDelayMs")?.value(cx);
+    let jitter = match pacing.get_opt::<JsString, _, _>(cx, "jitter")? {
+        Some(name) => {
+            let name = name.value(cx);
+            match Jitter::parse(&name) {
+                Some(jitter) => jitter,
+                None => return cx.throw_type_error(format!("Unsupported jitter: {}", name)),
+            }
+        }
+        None => Jitter::Uniform,
+    };
+    Ok(Some(Pacing {
+        min_delay: Duration::from_millis(min_delay as u64),
+        max_delay: Duration::from_millis(max_delay as u64),
+        jitter,
+    }))
+}
 
-    let (
-        browser_opt,
-        os_opt,
-        proxy_opt,
-        insecure_opt,
-        pool_idle_timeout_opt,
-        pool_max_idle_per_host_opt,
-        pool_max_size_opt,
-        connect_timeout_opt,
-        read_timeout_opt,
-    ) = if let Some(value) = options_value {
-        if value.is_a::<JsUndefined, _>(&mut cx) || value.is_a::<JsNull, _>(&mut cx) {
-            (None, None, None, None, None, None, None, None, None)
-        } else {
-            let obj = value.downcast_or_throw::<JsObject, _>(&mut cx)?;
-            let browser = obj
-                .get_opt(&mut cx, "browser")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx));
-            let os = obj
-                .get_opt(&mut cx, "os")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx));
-            let proxy = obj
-                .get_opt(&mut cx, "proxy")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
-                .map(|v| Arc::<str>::from(v.value(&mut cx)));
-            let insecure = obj
-                .get_opt(&mut cx, "insecure")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx));
-            let pool_idle_timeout = obj
-                .get_opt(&mut cx, "poolIdleTimeout")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx) as u64);
-            let pool_max_idle_per_host = obj
-                .get_opt(&mut cx, "poolMaxIdlePerHost")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx) as usize);
-            let pool_max_size = obj
-                .get_opt(&mut cx, "poolMaxSize")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx) as u32);
-            let connect_timeout = obj
-                .get_opt(&mut cx, "connectTimeout")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx) as u64);
-            let read_timeout = obj
-                .get_opt(&mut cx, "readTimeout")?
-                .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
-                .map(|v| v.value(&mut cx) as u64);
+// `auth` is `{ accessToken, expiresIn, refreshToken, tokenEndpoint, clientId, clientSecret, scope,
+// onRefresh }` with either `tokenEndpoint` or `onRefresh`; JS has already checked its shape.
+fn get_session_auth(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+) -> NeonResult<Option<Arc<SessionAuth>>> {
+    let Some(auth) = obj
+        .get_opt(cx, "auth")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok(None);
+    };
+
+    let mut string = |key: &str| -> NeonResult<Option<String>> {
+        Ok(auth
+            .get_opt(cx, key)?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(cx).ok())
+            .map(|v| v.value(cx)))
+    };
+    let access_token = string("accessToken")?;
+    let refresh_token = string("refreshToken")?;
+    let token_endpoint = string("tokenEndpoint")?;
+    let client_id = string("clientId")?;
+    let client_secret = string("clientSecret")?;
+    let scope = string("scope")?;
+    let expires_in = auth
+        .get_opt(cx, "expiresIn")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(cx).ok())
+        .map(|v| v.value(cx) as u64);
 
-            (
-                browser,
-                os,
-                proxy,
-                insecure,
-                pool_idle_timeout,
-                pool_max_idle_per_host,
-                pool_max_size,
-                connect_timeout,
-                read_timeout,
-            )
+    let source = match (
+        token_endpoint,
+        auth.get_opt::<JsFunction, _, _>(cx, "onRefresh")?,
+    ) {
+        (Some(url), _) => TokenSource::Endpoint(TokenEndpoint {
+            url,
+            client_id,
+            client_secret,
+            scope,
+        }),
+        (None, Some(callback)) => TokenSource::Callback(refresh_hook(cx, callback)),
+        (None, None) => {
+            return cx.throw_type_error("auth needs a tokenEndpoint or a refresh callback");
         }
-    } else {
-        (None, None, None, None, None, None, None, None, None)
     };
+    let initial = access_token.map(|token| AccessToken {
+        token,
+        expires_in,
+        refresh_token: None,
+    });
+    Ok(Some(Arc::new(SessionAuth::new(
+        source,
+        initial,
+        refresh_token,
+    ))))
+}
 
-    let browser_str = browser_opt.unwrap_or_else(|| "chrome_142".to_string());
-    let os_str = os_opt.unwrap_or_else(|| "macos".to_string());
-    let emulation = parse_emulation(&browser_str);
-    let emulation_os = parse_emulation_os(&os_str);
-    let insecure = insecure_opt.unwrap_or(false);
+// Calls `callback(refreshId)` on the JS thread; JS answers with `resolveAuthRefresh(refreshId, ...)`.
+fn refresh_hook(cx: &mut FunctionContext, callback: Handle<JsFunction>) -> RefreshHook {
+    let callback = Arc::new(callback.root(cx));
+    // Waiting requests keep the process alive; the hook itself must not.
+    let mut channel = cx.channel();
+    channel.unref(cx);
+    Arc::new(move |answer| {
+        let refresh_id = NEXT_AUTH_REFRESH_ID.fetch_add(1, Ordering::Relaxed);
+        AUTH_REFRESHES.insert(refresh_id, answer);
+        let callback = callback.clone();
+        channel.send(move |mut cx| {
+            let cb = callback.to_inner(&mut cx);
+            let this = cx.undefined();
+            let id = cx.number(refresh_id as f64);
+            if let Err(error) = cb.call(&mut cx, this, vec![id.upcast()]) {
+                AUTH_REFRESHES.remove(&refresh_id);
+                return Err(error);
+            }
+            Ok(())
+        });
+    })
+}
 
-    match create_managed_transport(
-        emulation,
-        emulation_os,
-        proxy_opt,
-        insecure,
-        pool_idle_timeout_opt,
-        pool_max_idle_per_host_opt,
-        pool_max_size_opt,
-        connect_timeout_opt,
-        read_timeout_opt,
-    ) {
-        Ok(id) => Ok(cx.string(id)),
-        Err(e) => {
-            let msg = format!("{:#}", e);
-            cx.throw_error(msg)
+// resolveAuthRefresh(refreshId, grant: { accessToken, expiresIn?, refreshToken? } | null, error?)
+fn resolve_auth_refresh(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let refresh_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let grant = cx.argument::<JsValue>(1)?;
+    let result = match grant.downcast::<JsObject, _>(&mut cx) {
+        Ok(grant) => {
+            let token = grant
+                .get::<JsString, _, _>(&mut cx, "accessToken")?
+                .value(&mut cx);
+            let expires_in = grant
+                .get_opt::<JsNumber, _, _>(&mut cx, "expiresIn")?
+                .map(|v| v.value(&mut cx) as u64);
+            let refresh_token = grant
+                .get_opt::<JsString, _, _>(&mut cx, "refreshToken")?
+                .map(|v| v.value(&mut cx));
+            Ok(AccessToken {
+                token,
+                expires_in,
+                refresh_token,
+            })
+        }
+        Err(_) => {
+            let error = cx
+                .argument_opt(2)
+                .and_then(|v| v.downcast::<JsString, _>(&mut cx).ok())
+                .map(|v| v.value(&mut cx))
+                .unwrap_or_else(|| "Refresh callback failed".to_owned());
+            Err(anyhow!(error))
         }
+    };
+    if let Some((_, answer)) = AUTH_REFRESHES.remove(&refresh_id) {
+        let _ = answer.send(result);
     }
+    Ok(cx.undefined())
 }
 
-fn clear_session(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+// Calls `callback(signId, method, url, headers)` on the JS thread; JS answers with
+// `resolveBeforeSend(signId, ...)`.
+fn before_send_hook(cx: &mut FunctionContext, callback: Handle<JsFunction>) -> BeforeSendHook {
+    let callback = Arc::new(callback.root(cx));
+    // The pending request keeps the process alive; the hook itself must not.
+    let mut channel = cx.channel();
+    channel.unref(cx);
+    BeforeSendHook::new(move |request: OutgoingRequest, answer| {
+        let sign_id = NEXT_BEFORE_SEND_ID.fetch_add(1, Ordering::Relaxed);
+        BEFORE_SENDS.insert(sign_id, answer);
+        let callback = callback.clone();
+        channel.send(move |mut cx| {
+            let cb = callback.to_inner(&mut cx);
+            let this = cx.undefined();
+            let args: Vec<Handle<JsValue>> = vec![
+                cx.number(sign_id as f64).upcast(),
+                cx.string(request.method).upcast(),
+                cx.string(request.url).upcast(),
+                tuple_array(&mut cx, &request.headers)?.upcast(),
+            ];
+            if let Err(error) = cb.call(&mut cx, this, args) {
+                BEFORE_SENDS.remove(&sign_id);
+                return Err(error);
+            }
+            Ok(())
+        });
+    })
+}
 
-    if let Err(e) = clear_managed_session(&session_id) {
-        let msg = format!("{:#}", e);
-        return cx.throw_error(msg);
+// resolveBeforeSend(signId, headers: [name, value][] | null, error?)
+fn resolve_before_send(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let sign_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let headers = cx.argument::<JsValue>(1)?;
+    let result = if headers.is_a::<JsArray, _>(&mut cx) {
+        // The request waits for an answer, so a bad header fails it rather than throwing here.
+        match cx.try_catch(|cx| parse_headers_from_value(cx, headers)) {
+            Ok(headers) => Ok(headers),
+            Err(error) => Err(anyhow!(error.to_string(&mut cx)?.value(&mut cx))),
+        }
+    } else {
+        let error = cx
+            .argument_opt(2)
+            .and_then(|v| v.downcast::<JsString, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx))
+            .unwrap_or_else(|| "beforeSend callback failed".to_owned());
+        Err(anyhow!(error))
+    };
+    if let Some((_, answer)) = BEFORE_SENDS.remove(&sign_id) {
+        let _ = answer.send(result);
     }
-
     Ok(cx.undefined())
 }
 
-fn drop_transport(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-    let transport_id = cx.argument::<JsString>(0)?.value(&mut cx);
-    drop_managed_transport(&transport_id);
-    Ok(cx.undefined())
+// Calls `callback(cookie, url)` on the JS thread for every cookie a session stores.
+fn set_cookie_hook(cx: &mut FunctionContext, callback: Handle<JsFunction>) -> SetCookieHook {
+    let callback = Arc::new(callback.root(cx));
+    // Cookie reports must not keep the process alive on their own.
+    let mut channel = cx.channel();
+    channel.unref(cx);
+    Arc::new(move |cookie: StoredCookie, url: &str| {
+        let callback = callback.clone();
+        let url = url.to_owned();
+        channel.send(move |mut cx| {
+            let cb = callback.to_inner(&mut cx);
+            let this = cx.undefined();
+            let info = stored_cookie_to_js(&mut cx, &cookie)?;
+            let url = cx.string(url);
+            cb.call(&mut cx, this, vec![info.upcast(), url.upcast()])?;
+            Ok(())
+        });
+    })
 }
 
-fn drop_session(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
-    drop_managed_session(&session_id);
-    Ok(cx.undefined())
+fn stored_cookie_to_js<'a, C: Context<'a>>(
+    cx: &mut C,
+    cookie: &StoredCookie,
+) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let name = cx.string(&cookie.name);
+    obj.set(cx, "name", name)?;
+    let value = cx.string(&cookie.value);
+    obj.set(cx, "value", value)?;
+    let domain = cx.string(&cookie.domain);
+    obj.set(cx, "domain", domain)?;
+    let path = cx.string(&cookie.path);
+    obj.set(cx, "path", path)?;
+    let expires_at: Handle<JsValue> = match cookie.expires_at {
+        Some(expires_at) => cx.number(expires_at as f64).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "expiresAt", expires_at)?;
+    let secure = cx.boolean(cookie.secure);
+    obj.set(cx, "secure", secure)?;
+    let http_only = cx.boolean(cookie.http_only);
+    obj.set(cx, "httpOnly", http_only)?;
+    let same_site: Handle<JsValue> = match cookie.same_site {
+        Some(same_site) => cx.string(same_site).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "sameSite", same_site)?;
+    let removed = cx.boolean(cookie.removed);
+    obj.set(cx, "removed", removed)?;
+    Ok(obj)
 }
 
-fn cancel_request(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-    let request_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+fn create_transport(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let options_value = cx.argument_opt(0);
+
+    let mut options = TransportOptions {
+        emulation: Emulation::Chrome142,
+        emulation_os: EmulationOS::MacOS,
+        proxy: None,
+        insecure: false,
+        pool_idle_timeout: None,
+        pool_max_idle_per_host: None,
+        pool_max_size: None,
+        max_connection_age: None,
+        connect_timeout: None,
+        read_timeout: None,
+        tls_session_tickets: None,
+        tls_session_resumption: None,
+        keylog_path: None,
+        max_download_bytes_per_sec: None,
+        max_upload_bytes_per_sec: None,
+        allowlist: Vec::new(),
+        denylist: Vec::new(),
+        accept_encoding: None,
+        max_response_header_bytes: None,
+        max_header_count: None,
+        lenient_headers: false,
+        initial_stream_window_size: None,
+        initial_connection_window_size: None,
+        max_frame_size: None,
+        tcp_nodelay: None,
+        tcp_keepalive: None,
+        tcp_send_buffer_size: None,
+        tcp_recv_buffer_size: None,
+        proxy_pool: None,
+        proxy_ntlm: None,
+        client_hints: None,
+    };
 
-    if let Some((_, token)) = REQUEST_CANCELLATIONS.remove(&request_id) {
-        token.cancel();
+    let options_value = options_value.filter(|value| {
+        !value.is_a::<JsUndefined, _>(&mut cx) && !value.is_a::<JsNull, _>(&mut cx)
+    });
+    if let Some(value) = options_value {
+        let obj = value.downcast_or_throw::<JsObject, _>(&mut cx)?;
+
+        if let Some(browser) = obj
+            .get_opt(&mut cx, "browser")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        {
+            let browser = browser.value(&mut cx);
+            options.emulation = parse_emulation(&mut cx, &browser, false)?;
+        }
+        if let Some(os) = obj
+            .get_opt(&mut cx, "os")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        {
+            let os = os.value(&mut cx);
+            options.emulation_os = parse_emulation_os(&mut cx, &os, false)?;
+        }
+        options.proxy = obj
+            .get_opt(&mut cx, "proxy")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+            .map(|v| Arc::<str>::from(v.value(&mut cx)));
+        options.insecure = obj
+            .get_opt(&mut cx, "insecure")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx))
+            .unwrap_or(false);
+        options.pool_idle_timeout = obj
+            .get_opt(&mut cx, "poolIdleTimeout")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64);
+        options.pool_max_idle_per_host = obj
+            .get_opt(&mut cx, "poolMaxIdlePerHost")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as usize);
+        options.pool_max_size = obj
+            .get_opt(&mut cx, "poolMaxSize")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u32);
+        options.max_connection_age = obj
+            .get_opt(&mut cx, "maxConnectionAge")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64);
+        options.connect_timeout = obj
+            .get_opt(&mut cx, "connectTimeout")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64);
+        options.read_timeout = obj
+            .get_opt(&mut cx, "readTimeout")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64);
+        options.tls_session_tickets = obj
+            .get_opt(&mut cx, "tlsSessionTickets")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx));
+        options.tls_session_resumption = obj
+            .get_opt(&mut cx, "tlsSessionResumption")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx));
+        options.keylog_path = obj
+            .get_opt(&mut cx, "keylogPath")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx));
+        options.max_download_bytes_per_sec = obj
+            .get_opt(&mut cx, "maxDownloadBytesPerSec")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64);
+        options.max_upload_bytes_per_sec = obj
+            .get_opt(&mut cx, "maxUploadBytesPerSec")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as u64);
+        options.allowlist = get_string_array(&mut cx, obj, "allowlist")?;
+        options.denylist = get_string_array(&mut cx, obj, "denylist")?;
+        options.accept_encoding = get_accept_encoding(&mut cx, obj)?;
+        options.max_response_header_bytes = obj
+            .get_opt(&mut cx, "maxResponseHeaderBytes")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as usize);
+        options.max_header_count = obj
+            .get_opt(&mut cx, "maxHeaderCount")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as usize);
+        options.lenient_headers = obj
+            .get_opt(&mut cx, "lenientHeaders")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx))
+            .unwrap_or(false);
+        options.initial_stream_window_size =
+            get_bounded_u32(&mut cx, obj, "initialStreamWindowSize", 1, MAX_WINDOW_SIZE)?;
+        options.initial_connection_window_size = get_bounded_u32(
+            &mut cx,
+            obj,
+            "initialConnectionWindowSize",
+            1,
+            MAX_WINDOW_SIZE,
+        )?;
+        options.max_frame_size =
+            get_bounded_u32(&mut cx, obj, "maxFrameSize", MIN_FRAME_SIZE, MAX_FRAME_SIZE)?;
+        options.tcp_nodelay = obj
+            .get_opt(&mut cx, "tcpNoDelay")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx));
+        options.tcp_keepalive = get_tcp_keepalive(&mut cx, obj)?;
+        options.tcp_send_buffer_size = obj
+            .get_opt(&mut cx, "sendBufferSize")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as usize);
+        options.tcp_recv_buffer_size = obj
+            .get_opt(&mut cx, "recvBufferSize")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx) as usize);
+        options.proxy_pool = get_proxy_pool(&mut cx, obj)?;
+        options.proxy_ntlm = get_ntlm_credentials(&mut cx, obj, "proxyNtlm")?;
+        options.client_hints = get_client_hints(&mut cx, obj)?;
     }
 
-    Ok(cx.undefined())
+    // HTTP/1 cannot read a response head into a smaller buffer than this.
+    if options
+        .max_response_header_bytes
+        .is_some_and(|max_bytes| max_bytes < MIN_RESPONSE_HEADER_BYTES)
+    {
+        return cx.throw_type_error(format!(
+            "maxResponseHeaderBytes must be at least {}",
+            MIN_RESPONSE_HEADER_BYTES
+        ));
+    }
+
+    match create_managed_transport(options) {
+        Ok(id) => {
+            claim(current_context(&mut cx), Resource::Transport, &id);
+            let guard = NativeGuard::Transport(id.clone());
+            created_object(&mut cx, id, Some(guard))
+        }
+        Err(e) => throw_anyhow(&mut cx, e),
+    }
+}
+
+// `{ id, guard }` for a new session or transport. JS keeps `guard` on the wrapper so the native
+// entry goes away with it if it is never closed.
+fn created_object<'a>(
+    cx: &mut FunctionContext<'a>,
+    id: String,
+    guard: Option<NativeGuard>,
+) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let id = cx.string(id);
+    obj.set(cx, "id", id)?;
+    if let Some(guard) = guard {
+        let guard = cx.boxed(guard);
+        obj.set(cx, "guard", guard)?;
+    }
+    Ok(obj)
+}
+
+// Drop cached TLS sessions for a transport so the next handshake is a full one.
+fn clear_transport_tls_sessions(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let transport_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Transport, &transport_id)?;
+
+    if let Err(e) = clear_managed_transport_tls_sessions(&transport_id) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn clear_session(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+
+    if let Err(e) = clear_managed_session(&session_id) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn drop_transport(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let transport_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Transport, &transport_id)?;
+    drop_managed_transport(&transport_id);
+    release(Resource::Transport, &transport_id);
+    Ok(cx.undefined())
+}
+
+fn drop_session(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+    drop_managed_session(&session_id);
+    release(Resource::Session, &session_id);
+    Ok(cx.undefined())
+}
+
+fn cancel_request(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let request_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let reason = abort_reason(&mut cx, 1);
+    let request_key = (current_context(&mut cx), request_id);
+
+    if let Some((_, cancellation)) = REQUEST_CANCELLATIONS.remove(&request_key) {
+        cancellation.abort.abort(reason);
+    }
+
+    Ok(cx.undefined())
+}
+
+// The optional reason string a cancel call rejects the request with, instead of "Request aborted".
+fn abort_reason(cx: &mut FunctionContext, index: usize) -> Option<String> {
+    cx.argument_opt(index)
+        .and_then(|value| value.downcast::<JsString, _>(cx).ok())
+        .map(|reason| reason.value(cx))
+        .filter(|reason| !reason.is_empty())
+}
+
+// Cancel every registered request this context tagged with the group, plus the group's unread
+// body streams.
+fn cancel_group(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let group_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    let reason = abort_reason(&mut cx, 1);
+    let context = current_context(&mut cx);
+
+    let request_keys: Vec<(ContextId, u64)> = REQUEST_CANCELLATIONS
+        .iter()
+        .filter(|entry| {
+            entry.key().0 == context && entry.value().group_id.as_deref() == Some(group_id.as_str())
+        })
+        .map(|entry| *entry.key())
+        .collect();
+
+    for request_key in request_keys {
+        if let Some((_, cancellation)) = REQUEST_CANCELLATIONS.remove(&request_key) {
+            cancellation.abort.abort(reason.clone());
+        }
+    }
+
+    drop_body_streams_in_group(&group_id);
+    Ok(cx.undefined())
+}
+
+fn cancel_all_requests(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    for entry in REQUEST_CANCELLATIONS.iter() {
+        entry.value().abort.abort(None);
+    }
+    REQUEST_CANCELLATIONS.clear();
+    native_cancel_all_requests();
+    Ok(cx.undefined())
+}
+
+// Snapshot the sizes of the native registries for leak triage.
+fn get_native_stats(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let client = client_stats();
+    let counts = [
+        ("bodyStreams", client.body_streams as f64),
+        ("inFlightRequests", client.in_flight_requests as f64),
+        ("pendingCancellations", REQUEST_CANCELLATIONS.len() as f64),
+        ("webSockets", connection_count() as f64),
+        ("sessions", client.sessions as f64),
+        ("transports", client.transports as f64),
+        ("ephemeralClients", client.ephemeral_clients as f64),
+        ("pooledClients", client.pooled_clients as f64),
+        ("bufferedBytes", client.buffered_bytes as f64),
+    ];
+
+    let obj = cx.empty_object();
+    for (key, count) in counts {
+        let count = cx.number(count);
+        obj.set(&mut cx, key, count)?;
+    }
+    Ok(obj)
+}
+
+// Bytes held natively on behalf of JS, by where they sit, plus the sizes of the client caches.
+fn get_memory_stats(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let client = client_stats();
+    let stats = [
+        ("inlineBodyBytes", INLINE_BODIES.bytes() as f64),
+        ("inlineBodies", INLINE_BODIES.items() as f64),
+        ("queuedBodyBytes", QUEUED_BODY_CHUNKS.bytes() as f64),
+        ("queuedBodyChunks", QUEUED_BODY_CHUNKS.items() as f64),
+        ("bodyBufferBytes", client.buffered_bytes as f64),
+        ("queuedWebSocketBytes", QUEUED_WS_MESSAGES.bytes() as f64),
+        ("queuedWebSocketMessages", QUEUED_WS_MESSAGES.items() as f64),
+        ("sessions", client.sessions as f64),
+        ("ephemeralClients", client.ephemeral_clients as f64),
+        ("pooledClients", client.pooled_clients as f64),
+    ];
+
+    let obj = cx.empty_object();
+    for (key, value) in stats {
+        let value = cx.number(value);
+        obj.set(&mut cx, key, value)?;
+    }
+    Ok(obj)
+}
+
+// Stop accepting work, wait up to the grace period for in-flight requests, body streams,
+// and WebSockets to drain, then cancel whatever is left and release all native registries.
+fn shutdown(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let grace_period_ms = cx
+        .argument_opt(0)
+        .and_then(|value| value.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|n| n.value(&mut cx).max(0.0) as u64)
+        .unwrap_or(0);
+
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        begin_shutdown();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(grace_period_ms);
+        while !(requests_drained() && connection_count() == 0)
+            && tokio::time::Instant::now() < deadline
+        {
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+
+        for entry in REQUEST_CANCELLATIONS.iter() {
+            entry.value().abort.abort(None);
+        }
+        REQUEST_CANCELLATIONS.clear();
+        release_client_resources();
+
+        for connection in take_all_connections() {
+            let _ = tokio::time::timeout(SHUTDOWN_WS_CLOSE_TIMEOUT, connection.close(None)).await;
+        }
+        close_all_tls_sockets();
+        close_all_h2_streams();
+        let _ = native_disable_audit_log();
+
+        deferred.settle_with(&settle_channel, move |mut cx| Ok(cx.undefined()));
+    });
+
+    Ok(promise)
 }
 
 fn read_body_chunk(mut cx: FunctionContext) -> JsResult<JsPromise> {
@@ -640,17 +2471,140 @@ fn read_body_chunk(mut cx: FunctionContext) -> JsResult<JsPromise> {
 
     HTTP_RUNTIME.spawn(async move {
         let result = native_read_body_chunk(handle_id).await;
+        let queued = queued_chunk(&result);
 
         deferred.settle_with(&settle_channel, move |mut cx| match result {
-            Ok(Some(bytes)) => {
-                let buffer = JsBuffer::from_slice(&mut cx, &bytes)?;
+            Ok(Some(chunk)) => {
+                let buffer = body_chunk_buffer(&mut cx, chunk)?;
+                drop(queued);
                 let value: Handle<JsValue> = buffer.upcast();
                 Ok(value)
             }
             Ok(None) => Ok(cx.null().upcast()),
-            Err(e) => {
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+/// Move response body bytes into a Buffer, sharing the memory with JS when that is allowed.
+/// Bytes still shared with another owner (e.g. a coalesced response) are copied.
+fn body_buffer<'a, C: Context<'a>>(cx: &mut C, bytes: Bytes) -> JsResult<'a, JsBuffer> {
+    if bytes.is_empty() || !EXTERNAL_BUFFERS.load(Ordering::Relaxed) {
+        return JsBuffer::from_slice(cx, &bytes);
+    }
+    Ok(JsBuffer::external(cx, BytesMut::from(bytes)))
+}
+
+// Inline bodies count towards `getMemoryStats()` for as long as JS holds their external Buffer.
+fn inline_body_buffer<'a, C: Context<'a>>(cx: &mut C, bytes: Bytes) -> JsResult<'a, JsBuffer> {
+    if bytes.is_empty() || !EXTERNAL_BUFFERS.load(Ordering::Relaxed) {
+        return JsBuffer::from_slice(cx, &bytes);
+    }
+    Ok(JsBuffer::external(
+        cx,
+        Held::new(BytesMut::from(bytes), &INLINE_BODIES),
+    ))
+}
+
+// A chunk read for JS counts as queued until the JS thread turns it into a Buffer.
+fn queued_chunk(result: &Result<Option<BodyChunk>>) -> Option<MemoryHold> {
+    match result {
+        Ok(Some(chunk)) => Some(QUEUED_BODY_CHUNKS.hold(chunk.len())),
+        _ => None,
+    }
+}
+
+// Pooled buffers handed over externally return to the pool when JS collects the Buffer.
+fn body_chunk_buffer<'a, C: Context<'a>>(cx: &mut C, chunk: BodyChunk) -> JsResult<'a, JsBuffer> {
+    match chunk {
+        BodyChunk::Single(bytes) => body_buffer(cx, bytes),
+        BodyChunk::Batched(buffer) if EXTERNAL_BUFFERS.load(Ordering::Relaxed) => {
+            Ok(JsBuffer::external(cx, buffer))
+        }
+        BodyChunk::Batched(buffer) => JsBuffer::from_slice(cx, &buffer),
+    }
+}
+
+/// Push a body to `onChunk(error, chunk)` as credit allows instead of one promise per chunk.
+/// Takes the body handle and the callback, which gets `(null, null)` at the end of the body.
+/// Nothing is read until `grantBodyCredit` gives credit.
+fn push_body_stream(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let handle_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let on_chunk = Arc::new(cx.argument::<JsFunction>(1)?.root(&mut cx));
+    let channel = cx.channel();
+
+    let credit = Arc::new(BodyCredit::default());
+    BODY_CREDITS.insert(handle_id, credit.clone());
+
+    HTTP_RUNTIME.spawn(async move {
+        push_body(handle_id, &credit, |result| {
+            let on_chunk = on_chunk.clone();
+            let queued = queued_chunk(&result);
+            channel.send(move |mut cx| {
+                let cb = on_chunk.to_inner(&mut cx);
+                let this = cx.undefined();
+                let args: Vec<Handle<JsValue>> = match result {
+                    Ok(Some(chunk)) => vec![
+                        cx.null().upcast(),
+                        body_chunk_buffer(&mut cx, chunk)?.upcast(),
+                    ],
+                    Ok(None) => vec![cx.null().upcast(), cx.null().upcast()],
+                    Err(e) => vec![anyhow_to_js_error(&mut cx, e)?.upcast()],
+                };
+                drop(queued);
+                cb.call(&mut cx, this, args)?;
+                Ok(())
+            });
+        })
+        .await;
+        BODY_CREDITS.remove_if(&handle_id, |_, entry| Arc::ptr_eq(entry, &credit));
+    });
+
+    Ok(cx.undefined())
+}
+
+/// Copy a body into a ring in a `SharedArrayBuffer` for a worker to read. Takes the body handle,
+/// a Buffer over the whole `SharedArrayBuffer`, and `onWake()`, called on this thread to
+/// `Atomics.notify` a reader that ran dry. Resolves once the body is in the ring or the reader
+/// cancelled.
+fn pipe_body_to_shared_ring(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let handle_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let mut view = cx.argument::<JsBuffer>(1)?;
+    let on_wake = Arc::new(cx.argument::<JsFunction>(2)?.root(&mut cx));
+
+    let bytes = view.as_mut_slice(&mut cx);
+    // SAFETY: a SharedArrayBuffer's memory never moves, and the root taken below keeps it alive
+    // until the promise settles. JS only touches it through the ring protocol meanwhile.
+    let ring = match unsafe { SharedRing::new(bytes.as_mut_ptr(), bytes.len()) } {
+        Ok(ring) => ring,
+        Err(e) => return throw_anyhow(&mut cx, e),
+    };
+    let keep_alive = view.root(&mut cx);
+
+    let (deferred, promise) = cx.promise();
+    let channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = pipe_body_to_ring(handle_id, &ring, || {
+            let on_wake = on_wake.clone();
+            channel.send(move |mut cx| {
+                let cb = on_wake.to_inner(&mut cx);
+                let this = cx.undefined();
+                cb.call(&mut cx, this, Vec::<Handle<JsValue>>::new())?;
+                Ok(())
+            });
+        })
+        .await;
+        // A reader that cancelled leaves the rest of the body unread.
+        drop_body_stream(handle_id);
+
+        deferred.settle_with(&channel, move |mut cx| {
+            keep_alive.drop(&mut cx);
+            match result {
+                Ok(()) => Ok(cx.undefined()),
+                Err(e) => throw_anyhow(&mut cx, e),
             }
         });
     });
@@ -658,8 +2612,21 @@ fn read_body_chunk(mut cx: FunctionContext) -> JsResult<JsPromise> {
     Ok(promise)
 }
 
+/// Let a pushed body deliver this many more bytes. Unknown or finished handles are ignored.
+fn grant_body_credit(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let handle_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let bytes = cx.argument::<JsNumber>(1)?.value(&mut cx);
+    if let Some(credit) = BODY_CREDITS.get(&handle_id).filter(|_| bytes > 0.0) {
+        credit.grant(bytes as u64);
+    }
+    Ok(cx.undefined())
+}
+
 fn cancel_body_stream(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let handle_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    if let Some((_, credit)) = BODY_CREDITS.remove(&handle_id) {
+        credit.close();
+    }
     drop_body_stream(handle_id);
     Ok(cx.undefined())
 }
@@ -675,57 +2642,154 @@ fn read_body_all(mut cx: FunctionContext) -> JsResult<JsPromise> {
         let result = native_read_body_all(handle_id).await;
 
         deferred.settle_with(&settle_channel, move |mut cx| match result {
-            Ok(bytes) => {
-                let buffer = JsBuffer::from_slice(&mut cx, &bytes)?;
-                Ok(buffer)
-            }
-            Err(e) => {
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
-            }
+            Ok(bytes) => body_buffer(&mut cx, bytes),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+/// Parse a body as JSON on a blocking thread and build the JS value from the parsed tree.
+/// Accepts a body handle or the inline body Buffer.
+fn read_body_json(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let source = cx.argument::<JsValue>(0)?;
+    let (inline, handle_id) = match source.downcast::<JsBuffer, _>(&mut cx) {
+        Ok(buffer) => (Some(Bytes::copy_from_slice(buffer.as_slice(&cx))), 0),
+        Err(_) => {
+            let handle = source.downcast_or_throw::<JsNumber, _>(&mut cx)?;
+            (None, handle.value(&mut cx) as u64)
+        }
+    };
+
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let body = match inline {
+            Some(bytes) => Ok(bytes),
+            None => native_read_body_all(handle_id).await,
+        };
+        let result = match body {
+            Ok(body) => tokio::task::spawn_blocking(move || parse_json_body(body))
+                .await
+                .map_err(|err| anyhow!("JSON parse task failed: {}", err)),
+            Err(e) => Err(e),
+        };
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(Ok(value)) => json_to_js(&mut cx, value),
+            Ok(Err(syntax_error)) => parse_rejected_json(&mut cx, &syntax_error),
+            Err(e) => throw_anyhow(&mut cx, e),
         });
     });
 
     Ok(promise)
 }
 
+/// Decode a body Buffer to a string, honoring its charset like a browser would.
+/// Takes the body and the response's `Content-Type` (or `null`).
+fn decode_text(mut cx: FunctionContext) -> JsResult<JsString> {
+    let bytes = cx.argument::<JsBuffer>(0)?;
+    let content_type = cx
+        .argument_opt(1)
+        .and_then(|value| value.downcast::<JsString, _>(&mut cx).ok())
+        .map(|value| value.value(&mut cx));
+
+    let text = charset::decode_text(bytes.as_slice(&cx), content_type.as_deref()).into_owned();
+    Ok(cx.string(text))
+}
+
+/// Configure how abandoned body streams are reaped. Takes `{ ttl?, maxStreams?, onLeak? }`;
+/// missing fields keep their current value and `null` clears the limit or callback.
+fn configure_body_streams(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let options = cx.argument::<JsObject>(0)?;
+
+    if let Some(ttl) = options.get_opt::<JsNumber, _, _>(&mut cx, "ttl")? {
+        let ttl = ttl.value(&mut cx);
+        set_body_stream_ttl(Duration::from_millis(ttl as u64));
+    }
+
+    let max_streams: Handle<JsValue> = options.get(&mut cx, "maxStreams")?;
+    if let Ok(max_streams) = max_streams.downcast::<JsNumber, _>(&mut cx) {
+        set_max_body_streams(Some(max_streams.value(&mut cx) as usize));
+    } else if max_streams.is_a::<JsNull, _>(&mut cx) {
+        set_max_body_streams(None);
+    }
+
+    let on_leak: Handle<JsValue> = options.get(&mut cx, "onLeak")?;
+    if let Ok(callback) = on_leak.downcast::<JsFunction, _>(&mut cx) {
+        let callback = Arc::new(callback.root(&mut cx));
+        // Leak reports must not keep the process alive on their own.
+        let mut channel = cx.channel();
+        channel.unref(&mut cx);
+        *LEAK_HOOK_CONTEXT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(current_context(&mut cx));
+        set_body_stream_leak_hook(Some(Arc::new(move |leak: BodyStreamLeak| {
+            let callback = callback.clone();
+            channel.send(move |mut cx| {
+                let cb = callback.to_inner(&mut cx);
+                let this = cx.undefined();
+                let info = cx.empty_object();
+                let handle = cx.number(leak.handle as f64);
+                info.set(&mut cx, "handle", handle)?;
+                let reason = cx.string(leak.reason.as_str());
+                info.set(&mut cx, "reason", reason)?;
+                let group_id: Handle<JsValue> = match leak.group_id.as_deref() {
+                    Some(group_id) => cx.string(group_id).upcast(),
+                    None => cx.null().upcast(),
+                };
+                info.set(&mut cx, "groupId", group_id)?;
+                let age = cx.number(leak.age.as_secs_f64() * 1000.0);
+                info.set(&mut cx, "ageMs", age)?;
+                cb.call(&mut cx, this, vec![info.upcast()])?;
+                Ok(())
+            });
+        })));
+    } else if on_leak.is_a::<JsNull, _>(&mut cx) {
+        *LEAK_HOOK_CONTEXT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        set_body_stream_leak_hook(None);
+    }
+
+    Ok(cx.undefined())
+}
+
 // Shared helper: wire up WebSocket receiver callbacks and return connection ID
 fn setup_ws_callbacks(
     connection: WsConnection,
     mut receiver: futures_util::stream::SplitStream<wreq::ws::WebSocket>,
-    on_message: Arc<neon::handle::Root<JsFunction>>,
-    on_close: Option<Arc<neon::handle::Root<JsFunction>>>,
-    on_error: Option<Arc<neon::handle::Root<JsFunction>>>,
+    callbacks: WsCallbacks,
     callbacks_channel: neon::event::Channel,
+    context: ContextId,
 ) -> u64 {
+    let activity = connection.activity();
+    let released = connection.released();
     let id = store_connection(connection);
+    claim(context, Resource::WebSocket, &id.to_string());
+    let binary_text = callbacks.binary_text;
+    let stream_threshold = callbacks
+        .streaming
+        .as_ref()
+        .map(|streaming| streaming.threshold);
 
     let (events_tx, mut events_rx) = mpsc::channel::<WsEvent>(WS_EVENT_BUFFER);
     let receiver_tx = events_tx.clone();
 
-    tokio::spawn(async move {
+    tokio::spawn(until_released(released.clone(), async move {
         let mut close_sent = false;
 
         while let Some(msg_result) = receiver.next().await {
-            match msg_result {
-                Ok(Message::Text(text)) => {
-                    if receiver_tx
-                        .send(WsEvent::Text(text.to_string()))
-                        .await
-                        .is_err()
-                    {
-                        break;
-                    }
-                }
-                Ok(Message::Binary(data)) => {
-                    if receiver_tx
-                        .send(WsEvent::Binary(data.to_vec()))
-                        .await
-                        .is_err()
-                    {
-                        break;
-                    }
-                }
+            if msg_result.is_ok() {
+                activity.record_received();
+            }
+            let message = match msg_result {
+                // The frame is valid UTF-8 either way; skip building strings when JS wants bytes.
+                Ok(Message::Text(text)) if binary_text => WsMessage::Binary(Bytes::from(text)),
+                Ok(Message::Text(text)) => WsMessage::Text(text.to_string()),
+                Ok(Message::Binary(data)) => WsMessage::Binary(data),
                 Ok(Message::Close(close_frame)) => {
                     let close_event = close_frame
                         .map(|frame| WsCloseEvent {
@@ -739,6 +2803,7 @@ fn setup_ws_callbacks(
                 }
                 Ok(_) => {
                     // Ignore Ping/Pong
+                    continue;
                 }
                 Err(e) => {
                     let _ = receiver_tx.send(WsEvent::Error(format!("{:#}", e))).await;
@@ -748,62 +2813,101 @@ fn setup_ws_callbacks(
                     close_sent = true;
                     break;
                 }
+            };
+            let queued = QUEUED_WS_MESSAGES.hold(message.len());
+            let event = match stream_threshold {
+                Some(threshold) if message.len() > threshold => WsEvent::Oversized(message, queued),
+                _ => WsEvent::Message(message, queued),
+            };
+            if receiver_tx.send(event).await.is_err() {
+                break;
             }
         }
 
+        activity.mark_closed();
         if !close_sent {
             let _ = receiver_tx
                 .send(WsEvent::Close(abnormal_ws_close_event()))
                 .await;
         }
-    });
+    }));
 
     drop(events_tx);
 
-    let on_message_clone = on_message.clone();
-    let on_close_clone = on_close.clone();
-    let on_error_clone = on_error.clone();
+    let WsCallbacks {
+        on_message,
+        on_close,
+        on_error,
+        batching,
+        streaming,
+        ..
+    } = callbacks;
     let channel_clone = callbacks_channel.clone();
     let permits_consumer = Arc::new(Semaphore::new(WS_EVENT_BUFFER));
 
-    tokio::spawn(async move {
+    tokio::spawn(until_released(released, async move {
         let mut close_emitted = false;
-        while let Some(event) = events_rx.recv().await {
-            match event {
-                WsEvent::Text(text) => {
+        // An event that ended a batch, handled on the next turn.
+        let mut pending: Option<WsEvent> = None;
+        loop {
+            let event = match pending.take() {
+                Some(event) => event,
+                None => match events_rx.recv().await {
+                    Some(event) => event,
+                    None => break,
+                },
+            };
+            match (event, &batching) {
+                (WsEvent::Message(first, queued), Some(batching)) => {
+                    let mut batch = vec![(first, queued)];
+                    pending = batching.collect(&mut events_rx, &mut batch).await;
                     let permit = match permits_consumer.clone().acquire_owned().await {
                         Ok(permit) => permit,
                         Err(_) => break,
                     };
-                    let on_message_ref = on_message_clone.clone();
+                    let on_messages = batching.on_messages.clone();
                     channel_clone.send(move |mut cx| {
                         let _permit = permit;
-                        let cb = on_message_ref.to_inner(&mut cx);
+                        let cb = on_messages.to_inner(&mut cx);
                         let this = cx.undefined();
-                        let args = vec![cx.string(text).upcast()];
-                        cb.call(&mut cx, this, args)?;
+                        let messages = cx.empty_array();
+                        for (index, (message, queued)) in batch.into_iter().enumerate() {
+                            let value = ws_message_to_js(&mut cx, message)?;
+                            drop(queued);
+                            messages.set(&mut cx, index as u32, value)?;
+                        }
+                        cb.call(&mut cx, this, vec![messages.upcast()])?;
                         Ok(())
                     });
                 }
-                WsEvent::Binary(data) => {
+                (WsEvent::Message(message, queued), None) => {
                     let permit = match permits_consumer.clone().acquire_owned().await {
                         Ok(permit) => permit,
                         Err(_) => break,
                     };
-                    let on_message_ref = on_message_clone.clone();
+                    let on_message_ref = on_message.clone();
                     channel_clone.send(move |mut cx| {
                         let _permit = permit;
                         let cb = on_message_ref.to_inner(&mut cx);
                         let this = cx.undefined();
-                        let mut buffer = cx.buffer(data.len())?;
-                        buffer.as_mut_slice(&mut cx).copy_from_slice(&data);
-                        let args = vec![buffer.upcast()];
+                        let args = vec![ws_message_to_js(&mut cx, message)?];
+                        drop(queued);
                         cb.call(&mut cx, this, args)?;
                         Ok(())
                     });
                 }
-                WsEvent::Error(error_msg) => {
-                    if let Some(on_error_ref) = on_error_clone.as_ref() {
+                (WsEvent::Oversized(message, queued), _) => {
+                    if let Some(streaming) = streaming.as_ref() {
+                        let delivered = streaming
+                            .deliver(&channel_clone, &permits_consumer, message, queued)
+                            .await;
+                        if !delivered {
+                            break;
+                        }
+                    }
+                }
+                (WsEvent::Error(error_msg), _) => {
+                    if let Some(on_error_ref) = on_error.as_ref() {
                         let on_error_ref = on_error_ref.clone();
                         channel_clone.send(move |mut cx| {
                             let cb = on_error_ref.to_inner(&mut cx);
@@ -814,9 +2918,9 @@ fn setup_ws_callbacks(
                         });
                     }
                 }
-                WsEvent::Close(close_event) => {
+                (WsEvent::Close(close_event), _) => {
                     if !close_emitted {
-                        if let Some(on_close_ref) = on_close_clone.as_ref() {
+                        if let Some(on_close_ref) = on_close.as_ref() {
                             let on_close_ref = on_close_ref.clone();
                             channel_clone.send(move |mut cx| {
                                 let cb = on_close_ref.to_inner(&mut cx);
@@ -836,7 +2940,7 @@ fn setup_ws_callbacks(
             }
         }
 
-        if !close_emitted && let Some(on_close_ref) = on_close_clone.as_ref() {
+        if let Some(on_close_ref) = on_close.as_ref().filter(|_| !close_emitted) {
             let on_close_ref = on_close_ref.clone();
             channel_clone.send(move |mut cx| {
                 let cb = on_close_ref.to_inner(&mut cx);
@@ -852,36 +2956,213 @@ fn setup_ws_callbacks(
         }
 
         remove_connection(id);
-    });
+    }));
 
     id
 }
 
-// Helper: extract callbacks from options object
+// Run one of a WebSocket's tasks until the connection is released, so none of its callbacks
+// outlive the context that opened it.
+async fn until_released(released: CancellationToken, task: impl Future<Output = ()>) {
+    tokio::select! {
+        () = task => {}
+        () = released.cancelled() => {}
+    }
+}
+
+fn ws_message_to_js<'a, C: Context<'a>>(cx: &mut C, message: WsMessage) -> JsResult<'a, JsValue> {
+    Ok(match message {
+        WsMessage::Text(text) => cx.string(text).upcast(),
+        WsMessage::Binary(data) => {
+            let mut buffer = cx.buffer(data.len())?;
+            buffer.as_mut_slice(cx).copy_from_slice(&data);
+            buffer.upcast()
+        }
+    })
+}
+
+struct WsCallbacks {
+    on_message: Arc<neon::handle::Root<JsFunction>>,
+    on_close: Option<Arc<neon::handle::Root<JsFunction>>>,
+    on_error: Option<Arc<neon::handle::Root<JsFunction>>>,
+    batching: Option<WsBatching>,
+    streaming: Option<WsStreaming>,
+    /// Deliver text frames as Buffers, like binary frames.
+    binary_text: bool,
+}
+
+/// Delivers received messages in arrays, one JS call per batch instead of per message.
+struct WsBatching {
+    max_messages: usize,
+    /// How long the first message of a batch waits for more. Zero only takes messages that
+    /// have already arrived.
+    flush_interval: Duration,
+    on_messages: Arc<neon::handle::Root<JsFunction>>,
+}
+
+impl WsBatching {
+    /// Add the messages that arrive within the flush interval to `batch`, up to the maximum.
+    /// Returns an error or close event that ended the batch early, to be handled after it.
+    async fn collect(
+        &self,
+        events: &mut mpsc::Receiver<WsEvent>,
+        batch: &mut Vec<(WsMessage, MemoryHold)>,
+    ) -> Option<WsEvent> {
+        let deadline = tokio::time::Instant::now() + self.flush_interval;
+        while batch.len() < self.max_messages {
+            let next = if self.flush_interval.is_zero() {
+                events.try_recv().ok()
+            } else {
+                tokio::time::timeout_at(deadline, events.recv())
+                    .await
+                    .ok()
+                    .flatten()
+            };
+            match next {
+                Some(WsEvent::Message(message, queued)) => batch.push((message, queued)),
+                Some(other) => return Some(other),
+                None => break,
+            }
+        }
+        None
+    }
+}
+
+/// Delivers messages above a size threshold as a start callback, chunk callbacks and an end
+/// callback, so JS never needs one contiguous buffer for the whole message.
+struct WsStreaming {
+    threshold: usize,
+    chunk_size: usize,
+    on_start: Arc<neon::handle::Root<JsFunction>>,
+    on_chunk: Arc<neon::handle::Root<JsFunction>>,
+    on_end: Arc<neon::handle::Root<JsFunction>>,
+}
+
+impl WsStreaming {
+    /// Send `message` to JS in chunks. Returns false once the connection's callbacks are gone.
+    async fn deliver(
+        &self,
+        channel: &neon::event::Channel,
+        permits: &Arc<Semaphore>,
+        message: WsMessage,
+        queued: MemoryHold,
+    ) -> bool {
+        let (text, data) = match message {
+            WsMessage::Text(text) => (true, Bytes::from(text)),
+            WsMessage::Binary(data) => (false, data),
+        };
+
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            return false;
+        };
+        let on_start = self.on_start.clone();
+        let size = data.len();
+        channel.send(move |mut cx| {
+            let _permit = permit;
+            let cb = on_start.to_inner(&mut cx);
+            let this = cx.undefined();
+            let args = vec![cx.boolean(text).upcast(), cx.number(size as f64).upcast()];
+            cb.call(&mut cx, this, args)?;
+            Ok(())
+        });
+
+        for start in (0..data.len()).step_by(self.chunk_size) {
+            let chunk = data.slice(start..(start + self.chunk_size).min(data.len()));
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                return false;
+            };
+            let on_chunk = self.on_chunk.clone();
+            channel.send(move |mut cx| {
+                let _permit = permit;
+                let cb = on_chunk.to_inner(&mut cx);
+                let this = cx.undefined();
+                let args = vec![ws_message_to_js(&mut cx, WsMessage::Binary(chunk))?];
+                cb.call(&mut cx, this, args)?;
+                Ok(())
+            });
+        }
+
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            return false;
+        };
+        let on_end = self.on_end.clone();
+        channel.send(move |mut cx| {
+            let _permit = permit;
+            drop(queued);
+            let cb = on_end.to_inner(&mut cx);
+            let this = cx.undefined();
+            cb.call(&mut cx, this, Vec::<Handle<JsValue>>::new())?;
+            Ok(())
+        });
+        true
+    }
+}
+
+// Helper: extract callbacks from options object. With `batch: { maxMessages, flushIntervalMs }`,
+// messages go to `onMessages` in arrays instead of to `onMessage`. With `binaryText: true`, text
+// frames arrive as Buffers. With `streamMessages: { threshold, chunkSize }`, larger messages go
+// to `onMessageStart`, `onMessageChunk` and `onMessageEnd`.
 fn extract_ws_callbacks(
     cx: &mut FunctionContext,
     options_obj: &Handle<JsObject>,
-) -> NeonResult<(
-    Arc<neon::handle::Root<JsFunction>>,
-    Option<Arc<neon::handle::Root<JsFunction>>>,
-    Option<Arc<neon::handle::Root<JsFunction>>>,
-)> {
+) -> NeonResult<WsCallbacks> {
     let on_message: Handle<JsFunction> = options_obj.get(cx, "onMessage")?;
     let on_close_opt = options_obj.get_opt::<JsFunction, _, _>(cx, "onClose")?;
     let on_error_opt = options_obj.get_opt::<JsFunction, _, _>(cx, "onError")?;
 
-    let on_message = Arc::new(on_message.root(cx));
-    let on_close = on_close_opt.map(|f| Arc::new(f.root(cx)));
-    let on_error = on_error_opt.map(|f| Arc::new(f.root(cx)));
+    let batching = match options_obj.get_opt::<JsObject, _, _>(cx, "batch")? {
+        Some(batch) => {
+            let max_messages = batch.get::<JsNumber, _, _>(cx, "maxMessages")?.value(cx);
+            let flush_interval = batch
+                .get::<JsNumber, _, _>(cx, "flushIntervalMs")?
+                .value(cx);
+            let on_messages: Handle<JsFunction> = options_obj.get(cx, "onMessages")?;
+            Some(WsBatching {
+                max_messages: (max_messages as usize).max(1),
+                flush_interval: Duration::from_secs_f64(flush_interval.max(0.0) / 1000.0),
+                on_messages: Arc::new(on_messages.root(cx)),
+            })
+        }
+        None => None,
+    };
+    let streaming = match options_obj.get_opt::<JsObject, _, _>(cx, "streamMessages")? {
+        Some(stream) => {
+            let threshold = stream.get::<JsNumber, _, _>(cx, "threshold")?.value(cx);
+            let chunk_size = stream.get::<JsNumber, _, _>(cx, "chunkSize")?.value(cx);
+            let on_start: Handle<JsFunction> = options_obj.get(cx, "onMessageStart")?;
+            let on_chunk: Handle<JsFunction> = options_obj.get(cx, "onMessageChunk")?;
+            let on_end: Handle<JsFunction> = options_obj.get(cx, "onMessageEnd")?;
+            Some(WsStreaming {
+                threshold: threshold as usize,
+                chunk_size: (chunk_size as usize).max(1),
+                on_start: Arc::new(on_start.root(cx)),
+                on_chunk: Arc::new(on_chunk.root(cx)),
+                on_end: Arc::new(on_end.root(cx)),
+            })
+        }
+        None => None,
+    };
+    let binary_text = options_obj
+        .get_opt(cx, "binaryText")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(cx).ok())
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
 
-    Ok((on_message, on_close, on_error))
+    Ok(WsCallbacks {
+        on_message: Arc::new(on_message.root(cx)),
+        on_close: on_close_opt.map(|f| Arc::new(f.root(cx))),
+        on_error: on_error_opt.map(|f| Arc::new(f.root(cx))),
+        batching,
+        streaming,
+        binary_text,
+    })
 }
 
 // Helper: extract headers from options object
 fn extract_ws_headers(
     cx: &mut FunctionContext,
     options_obj: &Handle<JsObject>,
-) -> NeonResult<Vec<(String, String)>> {
+) -> NeonResult<Vec<(String, HeaderValue)>> {
     if let Ok(Some(headers_value)) = options_obj.get_opt(cx, "headers") {
         parse_headers_from_value(cx, headers_value)
     } else {
@@ -922,8 +3203,843 @@ fn extract_ws_protocols(
     cx.throw_type_error("protocols must be a string or string array")
 }
 
-// WebSocket connection function (standalone, no session)
-fn websocket_connect(mut cx: FunctionContext) -> JsResult<JsPromise> {
+// WebSocket connection function (standalone, no session)
+fn websocket_connect(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let options_obj = cx.argument::<JsObject>(0)?;
+
+    let url: Handle<JsString> = options_obj.get(&mut cx, "url")?;
+    let url = url.value(&mut cx);
+
+    let browser_str = options_obj
+        .get_opt(&mut cx, "browser")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or_else(|| "chrome_142".to_string());
+
+    let emulation = parse_emulation(&mut cx, &browser_str, false)?;
+    let os_str = options_obj
+        .get_opt(&mut cx, "os")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or_else(|| "macos".to_string());
+    let emulation_os = parse_emulation_os(&mut cx, &os_str, false)?;
+
+    let headers = extract_ws_headers(&mut cx, &options_obj)?;
+    let protocols = extract_ws_protocols(&mut cx, &options_obj)?;
+
+    let proxy = options_obj
+        .get_opt(&mut cx, "proxy")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| Arc::<str>::from(v.value(&mut cx)));
+
+    let callbacks = extract_ws_callbacks(&mut cx, &options_obj)?;
+    let context = current_context(&mut cx);
+
+    let options = WebSocketOptions {
+        url,
+        emulation,
+        emulation_os,
+        headers,
+        protocols,
+        proxy,
+    };
+
+    let (deferred, promise) = cx.promise();
+    let callbacks_channel = cx.channel();
+    let settle_channel = callbacks_channel.clone();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result: Result<(u64, Arc<WsActivity>, WebSocketUpgradeMetadata), anyhow::Error> =
+            async {
+                let (connection, receiver, metadata) = connect_websocket(options).await?;
+                let activity = connection.activity();
+                let id =
+                    setup_ws_callbacks(connection, receiver, callbacks, callbacks_channel, context);
+                Ok((id, activity, metadata))
+            }
+            .await;
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok((id, activity, metadata)) => {
+                let obj = cx.empty_object();
+                let id_num = cx.number(id as f64);
+                obj.set(&mut cx, "_id", id_num)?;
+                let activity = cx.boxed(activity);
+                obj.set(&mut cx, "_activity", activity)?;
+                let guard = cx.boxed(NativeGuard::WebSocket(id));
+                obj.set(&mut cx, "_guard", guard)?;
+                if let Some(protocol) = metadata.protocol {
+                    let protocol_value = cx.string(protocol);
+                    obj.set(&mut cx, "protocol", protocol_value)?;
+                }
+                if let Some(extensions) = metadata.extensions {
+                    let extensions_value = cx.string(extensions);
+                    obj.set(&mut cx, "extensions", extensions_value)?;
+                }
+                Ok(obj)
+            }
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+// WebSocket connection with session (shares cookies and transport TLS config)
+fn websocket_connect_session(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let options_obj = cx.argument::<JsObject>(0)?;
+
+    let url: Handle<JsString> = options_obj.get(&mut cx, "url")?;
+    let url = url.value(&mut cx);
+
+    let session_id: Handle<JsString> = options_obj.get(&mut cx, "sessionId")?;
+    let session_id = session_id.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+
+    let transport_id: Handle<JsString> = options_obj.get(&mut cx, "transportId")?;
+    let transport_id = transport_id.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Transport, &transport_id)?;
+
+    let headers = extract_ws_headers(&mut cx, &options_obj)?;
+    let protocols = extract_ws_protocols(&mut cx, &options_obj)?;
+    let callbacks = extract_ws_callbacks(&mut cx, &options_obj)?;
+    let context = current_context(&mut cx);
+
+    let (deferred, promise) = cx.promise();
+    let callbacks_channel = cx.channel();
+    let settle_channel = callbacks_channel.clone();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result: Result<(u64, Arc<WsActivity>, WebSocketUpgradeMetadata), anyhow::Error> =
+            async {
+                let (connection, receiver, metadata) = connect_websocket_with_session(
+                    &session_id,
+                    &transport_id,
+                    &url,
+                    &headers,
+                    &protocols,
+                )
+                .await?;
+                let activity = connection.activity();
+                let id =
+                    setup_ws_callbacks(connection, receiver, callbacks, callbacks_channel, context);
+                Ok((id, activity, metadata))
+            }
+            .await;
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok((id, activity, metadata)) => {
+                let obj = cx.empty_object();
+                let id_num = cx.number(id as f64);
+                obj.set(&mut cx, "_id", id_num)?;
+                let activity = cx.boxed(activity);
+                obj.set(&mut cx, "_activity", activity)?;
+                let guard = cx.boxed(NativeGuard::WebSocket(id));
+                obj.set(&mut cx, "_guard", guard)?;
+                if let Some(protocol) = metadata.protocol {
+                    let protocol_value = cx.string(protocol);
+                    obj.set(&mut cx, "protocol", protocol_value)?;
+                }
+                if let Some(extensions) = metadata.extensions {
+                    let extensions_value = cx.string(extensions);
+                    obj.set(&mut cx, "extensions", extensions_value)?;
+                }
+                Ok(obj)
+            }
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+// WebSocket send function
+fn websocket_send(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let ws_obj = cx.argument::<JsObject>(0)?;
+    let data = cx.argument::<JsValue>(1)?;
+
+    // Get the connection ID from the object
+    let id_val: Handle<JsNumber> = ws_obj.get(&mut cx, "_id")?;
+    let id = id_val.value(&mut cx) as u64;
+
+    // Get connection from global storage
+    let connection = match get_connection(id) {
+        Some(conn) => conn,
+        None => return cx.throw_error("WebSocket connection not found"),
+    };
+
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    // Check if data is string or buffer
+    let is_text = data.is_a::<JsString, _>(&mut cx);
+    let send_data = if is_text {
+        let text = data.downcast_or_throw::<JsString, _>(&mut cx)?;
+        SendData::Text(text.value(&mut cx))
+    } else if let Ok(buffer) = data.downcast::<JsBuffer, _>(&mut cx) {
+        let data = buffer.as_slice(&cx).to_vec();
+        SendData::Binary(data)
+    } else {
+        return cx.throw_error("Data must be a string or Buffer");
+    };
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = match send_data {
+            SendData::Text(text) => connection.send_text(text).await,
+            SendData::Binary(data) => connection.send_binary(data).await,
+        };
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+enum SendData {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+enum WsEvent {
+    Message(WsMessage, MemoryHold),
+    /// A message above the streaming threshold, delivered to JS in chunks.
+    Oversized(WsMessage, MemoryHold),
+    Close(WsCloseEvent),
+    Error(String),
+}
+
+enum WsMessage {
+    Text(String),
+    Binary(Bytes),
+}
+
+impl WsMessage {
+    fn len(&self) -> usize {
+        match self {
+            WsMessage::Text(text) => text.len(),
+            WsMessage::Binary(data) => data.len(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WsCloseEvent {
+    code: u16,
+    reason: String,
+}
+
+fn default_ws_close_event() -> WsCloseEvent {
+    // RFC 6455 reserved "no status received" code used when no close frame payload exists.
+    WsCloseEvent {
+        code: 1005,
+        reason: String::new(),
+    }
+}
+
+fn abnormal_ws_close_event() -> WsCloseEvent {
+    // RFC 6455 abnormal closure code used when the connection drops unexpectedly.
+    WsCloseEvent {
+        code: 1006,
+        reason: String::new(),
+    }
+}
+
+// Read the state and last activity of a connection, also after it closed
+fn websocket_state(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let ws_obj = cx.argument::<JsObject>(0)?;
+    let activity: Handle<JsBox<Arc<WsActivity>>> = ws_obj.get(&mut cx, "_activity")?;
+    let (state, opened_at, last_sent_at, last_received_at) = (
+        activity.state(),
+        activity.opened_at(),
+        activity.last_sent_at(),
+        activity.last_received_at(),
+    );
+
+    let obj = cx.empty_object();
+    let state = cx.string(state.as_str());
+    obj.set(&mut cx, "state", state)?;
+    let opened_at = cx.number(opened_at as f64);
+    obj.set(&mut cx, "openedAt", opened_at)?;
+    let last_sent_at: Handle<JsValue> = match last_sent_at {
+        Some(at) => cx.number(at as f64).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(&mut cx, "lastSentAt", last_sent_at)?;
+    let last_received_at: Handle<JsValue> = match last_received_at {
+        Some(at) => cx.number(at as f64).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(&mut cx, "lastReceivedAt", last_received_at)?;
+    Ok(obj)
+}
+
+// WebSocket close function
+fn websocket_close(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let ws_obj = cx.argument::<JsObject>(0)?;
+    let close_payload = if let Some(close_value) = cx.argument_opt(1) {
+        if close_value.is_a::<JsUndefined, _>(&mut cx) || close_value.is_a::<JsNull, _>(&mut cx) {
+            None
+        } else {
+            let close_obj = close_value.downcast_or_throw::<JsObject, _>(&mut cx)?;
+            let code = close_obj
+                .get_opt::<JsNumber, _, _>(&mut cx, "code")?
+                .map(|num| num.value(&mut cx) as u16)
+                .unwrap_or(1000);
+            let reason = close_obj
+                .get_opt::<JsString, _, _>(&mut cx, "reason")?
+                .map(|value| value.value(&mut cx))
+                .unwrap_or_default();
+
+            Some(WsClosePayload { code, reason })
+        }
+    } else {
+        None
+    };
+
+    // Get the connection ID from the object
+    let id_val: Handle<JsNumber> = ws_obj.get(&mut cx, "_id")?;
+    let id = id_val.value(&mut cx) as u64;
+
+    // Get connection from global storage
+    let connection = match get_connection(id) {
+        Some(conn) => conn,
+        None => return cx.throw_error("WebSocket connection not found"),
+    };
+
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = connection.close(close_payload).await;
+
+        // Remove connection from storage after closing
+        remove_connection(id);
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+fn get_cookies(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+    let url = cx.argument::<JsString>(1)?.value(&mut cx);
+
+    match get_session_cookies(&session_id, &url) {
+        Ok(cookies) => {
+            let obj = cx.empty_object();
+            for (name, value) in cookies {
+                let js_value = cx.string(&value);
+                obj.set(&mut cx, name.as_str(), js_value)?;
+            }
+            Ok(obj)
+        }
+        Err(e) => throw_anyhow(&mut cx, e),
+    }
+}
+
+fn set_cookie(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+    let name = cx.argument::<JsString>(1)?.value(&mut cx);
+    let value = cx.argument::<JsString>(2)?.value(&mut cx);
+    let url = cx.argument::<JsString>(3)?.value(&mut cx);
+
+    if let Err(e) = set_session_cookie(&session_id, &name, &value, &url) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+// Load a Puppeteer, Playwright or CDP cookie export, passed as JSON, into a session's jar.
+fn import_browser_cookies(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+    let json = cx.argument::<JsString>(1)?.value(&mut cx);
+
+    let imported = parse_browser_cookies(&json)
+        .and_then(|cookies| import_session_browser_cookies(&session_id, &cookies));
+    match imported {
+        Ok(count) => Ok(cx.number(count as f64)),
+        Err(e) => throw_anyhow(&mut cx, e),
+    }
+}
+
+fn get_hsts(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+
+    let entries = match get_session_hsts(&session_id) {
+        Ok(entries) => entries,
+        Err(e) => return throw_anyhow(&mut cx, e),
+    };
+
+    let array = cx.empty_array();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let host = cx.string(&entry.host);
+        obj.set(&mut cx, "host", host)?;
+        let expires_at = cx.number(entry.expires_at as f64);
+        obj.set(&mut cx, "expiresAt", expires_at)?;
+        let include_subdomains = cx.boolean(entry.include_subdomains);
+        obj.set(&mut cx, "includeSubdomains", include_subdomains)?;
+        array.set(&mut cx, i as u32, obj)?;
+    }
+
+    Ok(array)
+}
+
+fn import_hsts(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+    let array = cx.argument::<JsArray>(1)?;
+
+    let mut entries = Vec::new();
+    for value in array.to_vec(&mut cx)? {
+        let obj = value.downcast_or_throw::<JsObject, _>(&mut cx)?;
+        let host = obj.get::<JsString, _, _>(&mut cx, "host")?.value(&mut cx);
+        let expires_at = obj
+            .get::<JsNumber, _, _>(&mut cx, "expiresAt")?
+            .value(&mut cx) as u64;
+        let include_subdomains = obj
+            .get_opt(&mut cx, "includeSubdomains")?
+            .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+            .map(|v| v.value(&mut cx))
+            .unwrap_or(false);
+        entries.push(HstsEntry {
+            host,
+            expires_at,
+            include_subdomains,
+        });
+    }
+
+    if let Err(e) = import_session_hsts(&session_id, entries) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn clear_hsts(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Session, &session_id)?;
+
+    if let Err(e) = clear_session_hsts(&session_id) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn get_alt_svc(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let transport_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Transport, &transport_id)?;
+    let origin = cx
+        .argument_opt(1)
+        .and_then(|v| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx));
+
+    let entries = match get_transport_alt_svc(&transport_id, origin.as_deref()) {
+        Ok(entries) => entries,
+        Err(e) => return throw_anyhow(&mut cx, e),
+    };
+
+    let array = cx.empty_array();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let origin = cx.string(&entry.origin);
+        obj.set(&mut cx, "origin", origin)?;
+        let protocol = cx.string(&entry.protocol);
+        obj.set(&mut cx, "protocol", protocol)?;
+        let host = cx.string(&entry.host);
+        obj.set(&mut cx, "host", host)?;
+        let port = cx.number(entry.port as f64);
+        obj.set(&mut cx, "port", port)?;
+        let expires_at = cx.number(entry.expires_at as f64);
+        obj.set(&mut cx, "expiresAt", expires_at)?;
+        let persist = cx.boolean(entry.persist);
+        obj.set(&mut cx, "persist", persist)?;
+        array.set(&mut cx, i as u32, obj)?;
+    }
+
+    Ok(array)
+}
+
+fn clear_alt_svc(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let transport_id = cx.argument::<JsString>(0)?.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Transport, &transport_id)?;
+
+    if let Err(e) = clear_transport_alt_svc(&transport_id) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+// Register a mock that answers matching requests without touching the network
+fn mock_request(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let matcher = cx.argument::<JsObject>(0)?;
+    let response = cx.argument::<JsObject>(1)?;
+
+    let method = matcher
+        .get_opt(&mut cx, "method")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx));
+    let url_pattern: Handle<JsString> = matcher.get(&mut cx, "urlPattern")?;
+    let url_pattern = url_pattern.value(&mut cx);
+
+    let status = response
+        .get_opt(&mut cx, "status")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx) as u16)
+        .unwrap_or(200);
+
+    let headers = match response.get_opt(&mut cx, "headers")? {
+        Some(value) => parse_headers_from_value(&mut cx, value)?
+            .into_iter()
+            .map(|(name, value)| (name, header_text(&value)))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let body = match response.get_opt::<JsValue, _, _>(&mut cx, "body")? {
+        Some(value)
+            if value.is_a::<JsUndefined, _>(&mut cx) || value.is_a::<JsNull, _>(&mut cx) =>
+        {
+            Vec::new()
+        }
+        Some(value) => {
+            if let Ok(buffer) = value.downcast::<JsBuffer, _>(&mut cx) {
+                buffer.as_slice(&cx).to_vec()
+            } else if let Ok(js_str) = value.downcast::<JsString, _>(&mut cx) {
+                js_str.value(&mut cx).into_bytes()
+            } else {
+                return cx.throw_type_error("mock body must be a string or Buffer");
+            }
+        }
+        None => Vec::new(),
+    };
+
+    let delay_ms = response
+        .get_opt(&mut cx, "delayMs")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx) as u64)
+        .unwrap_or(0);
+
+    let id = register_mock(MockRule {
+        method,
+        url_pattern,
+        response: MockResponse {
+            status,
+            headers,
+            body: body.into(),
+            delay_ms,
+        },
+    });
+
+    Ok(cx.number(id as f64))
+}
+
+// Number of requests a mock has answered (null once removed)
+fn mock_calls(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
+    match mock_call_count(id) {
+        Some(count) => Ok(cx.number(count as f64).upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn unmock(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    Ok(cx.boolean(remove_mock(id)))
+}
+
+fn clear_all_mocks(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    clear_mocks();
+    Ok(cx.undefined())
+}
+
+// Start recording to or replaying from a cassette file
+fn use_cassette(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let mode = match cx.argument::<JsString>(1)?.value(&mut cx).as_str() {
+        "record" => CassetteMode::Record,
+        "replay" => CassetteMode::Replay,
+        other => return cx.throw_type_error(format!("Unsupported cassette mode: {}", other)),
+    };
+
+    let fields = cx.argument::<JsArray>(2)?.to_vec(&mut cx)?;
+    let mut match_on = Vec::with_capacity(fields.len());
+    for field in fields {
+        let field = field
+            .downcast_or_throw::<JsString, _>(&mut cx)?
+            .value(&mut cx);
+        match_on.push(match field.as_str() {
+            "method" => MatchField::Method,
+            "url" => MatchField::Url,
+            "body" => MatchField::Body,
+            other => {
+                return cx.throw_type_error(format!("Unsupported cassette match field: {}", other));
+            }
+        });
+    }
+
+    if let Err(e) = insert_cassette(path, mode, match_on) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn eject_active_cassette(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let interactions = eject_cassette();
+    Ok(cx.number(interactions as f64))
+}
+
+// Append one JSON line per request to `path` until disabled
+fn enable_audit_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let include_bodies = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+    let rotate_bytes = cx
+        .argument_opt(2)
+        .and_then(|value| value.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|n| n.value(&mut cx) as u64);
+
+    let options = AuditOptions {
+        include_bodies,
+        rotate_bytes,
+    };
+    if let Err(e) = native_enable_audit_log(path, options) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn disable_audit_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    if let Err(e) = native_disable_audit_log() {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+// Render request options as a curl command without sending anything
+fn to_curl(mut cx: FunctionContext) -> JsResult<JsString> {
+    let options_obj = cx.argument::<JsObject>(0)?;
+    let options = js_object_to_request_options(&mut cx, options_obj)?;
+
+    match request_to_curl(&options) {
+        Ok(command) => Ok(cx.string(command)),
+        Err(e) => throw_anyhow(&mut cx, e),
+    }
+}
+
+// Parse a curl command line into request options
+fn from_curl(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let command = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    let parsed = match curl::parse(&command) {
+        Ok(parsed) => parsed,
+        Err(e) => return throw_anyhow(&mut cx, e),
+    };
+
+    let obj = cx.empty_object();
+    let url = cx.string(&parsed.url);
+    obj.set(&mut cx, "url", url)?;
+    let method = cx.string(&parsed.method);
+    obj.set(&mut cx, "method", method)?;
+
+    let headers = cx.empty_array();
+    for (i, (name, value)) in parsed.headers.iter().enumerate() {
+        let entry = cx.empty_array();
+        let name = cx.string(name);
+        let value = cx.string(value);
+        entry.set(&mut cx, 0, name)?;
+        entry.set(&mut cx, 1, value)?;
+        headers.set(&mut cx, i as u32, entry)?;
+    }
+    obj.set(&mut cx, "headers", headers)?;
+
+    // Text bodies stay strings so the result reads naturally; binary payloads become Buffers.
+    if let Some(body) = parsed.body {
+        match String::from_utf8(body) {
+            Ok(text) => {
+                let body = cx.string(text);
+                obj.set(&mut cx, "body", body)?;
+            }
+            Err(err) => {
+                let body = JsBuffer::from_slice(&mut cx, err.as_bytes())?;
+                obj.set(&mut cx, "body", body)?;
+            }
+        }
+    }
+
+    if let Some(proxy) = parsed.proxy {
+        let proxy = cx.string(proxy);
+        obj.set(&mut cx, "proxy", proxy)?;
+    }
+    if parsed.insecure {
+        let insecure = cx.boolean(true);
+        obj.set(&mut cx, "insecure", insecure)?;
+    }
+    let redirect = cx.string(if parsed.follow_redirects {
+        "follow"
+    } else {
+        "manual"
+    });
+    obj.set(&mut cx, "redirect", redirect)?;
+    if let Some(timeout) = parsed.timeout_ms {
+        let timeout = cx.number(timeout as f64);
+        obj.set(&mut cx, "timeout", timeout)?;
+    }
+
+    Ok(obj)
+}
+
+// Mirror every chunk read from a body stream into a file
+fn tee_body(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let handle = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let path = cx.argument::<JsString>(1)?.value(&mut cx);
+
+    if let Err(e) = tee_body_to_file(handle, &path) {
+        return throw_anyhow(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+// Collect the digest of a body stream that has been read to the end
+fn take_digest(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let handle = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
+    match take_body_digest(handle) {
+        Some(digest) => Ok(cx.string(digest).upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn take_trailers(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let handle = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
+    match take_body_trailers(handle) {
+        Some(trailers) => Ok(header_tuples_to_js(&mut cx, &trailers)?.upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn take_size(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let handle = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
+    match take_body_size(handle) {
+        Some(size) => Ok(body_size_to_js(&mut cx, size)?.upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+// Open a raw TLS socket whose ClientHello matches the emulated browser
+fn tls_connect(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let options_obj = cx.argument::<JsObject>(0)?;
+
+    let host: Handle<JsString> = options_obj.get(&mut cx, "host")?;
+    let host = host.value(&mut cx);
+
+    let port: Handle<JsNumber> = options_obj.get(&mut cx, "port")?;
+    let port = port.value(&mut cx);
+    if !(1.0..=65535.0).contains(&port) || port.fract() != 0.0 {
+        return cx.throw_type_error("port must be an integer between 1 and 65535");
+    }
+
+    let browser_str = options_obj
+        .get_opt(&mut cx, "browser")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or_else(|| "chrome_142".to_string());
+    let os_str = options_obj
+        .get_opt(&mut cx, "os")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or_else(|| "macos".to_string());
+
+    let proxy = options_obj
+        .get_opt(&mut cx, "proxy")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx));
+
+    let insecure = options_obj
+        .get_opt(&mut cx, "insecure")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or(false);
+
+    let timeout = options_obj
+        .get_opt(&mut cx, "timeout")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx) as u64)
+        .unwrap_or(30000);
+
+    let options = TlsSocketOptions {
+        host,
+        port: port as u16,
+        emulation: parse_emulation(&mut cx, &browser_str, false)?,
+        emulation_os: parse_emulation_os(&mut cx, &os_str, false)?,
+        proxy,
+        insecure,
+        timeout,
+    };
+
+    let context = current_context(&mut cx);
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = connect_tls_socket(options).await;
+        if let Ok(info) = &result {
+            claim(context, Resource::TlsSocket, &info.id.to_string());
+        }
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(info) => {
+                let obj = cx.empty_object();
+                let id = cx.number(info.id as f64);
+                obj.set(&mut cx, "id", id)?;
+                let alpn_protocol: Handle<JsValue> = match info.alpn_protocol {
+                    Some(protocol) => cx.string(protocol).upcast(),
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "alpnProtocol", alpn_protocol)?;
+                let tls_version = cx.string(info.tls_version);
+                obj.set(&mut cx, "tlsVersion", tls_version)?;
+                let local_address: Handle<JsValue> = match info.local_addr {
+                    Some(addr) => cx.string(addr.to_string()).upcast(),
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "localAddress", local_address)?;
+                let remote_address: Handle<JsValue> = match info.remote_addr {
+                    Some(addr) => cx.string(addr.to_string()).upcast(),
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "remoteAddress", remote_address)?;
+                Ok(obj)
+            }
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
+}
+
+// Connect to an origin without sending a request and report per-phase timings.
+fn probe(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let options_obj = cx.argument::<JsObject>(0)?;
 
     let url: Handle<JsString> = options_obj.get(&mut cx, "url")?;
@@ -934,331 +4050,428 @@ fn websocket_connect(mut cx: FunctionContext) -> JsResult<JsPromise> {
         .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
         .map(|v| v.value(&mut cx))
         .unwrap_or_else(|| "chrome_142".to_string());
-
-    let emulation = parse_emulation(&browser_str);
     let os_str = options_obj
         .get_opt(&mut cx, "os")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
         .map(|v| v.value(&mut cx))
         .unwrap_or_else(|| "macos".to_string());
-    let emulation_os = parse_emulation_os(&os_str);
-
-    let headers = extract_ws_headers(&mut cx, &options_obj)?;
-    let protocols = extract_ws_protocols(&mut cx, &options_obj)?;
 
     let proxy = options_obj
         .get_opt(&mut cx, "proxy")?
         .and_then(|v: Handle<JsValue>| v.downcast::<JsString, _>(&mut cx).ok())
-        .map(|v| Arc::<str>::from(v.value(&mut cx)));
+        .map(|v| v.value(&mut cx));
+
+    let insecure = options_obj
+        .get_opt(&mut cx, "insecure")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or(false);
+
+    let http2 = options_obj
+        .get_opt(&mut cx, "http2")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsBoolean, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx))
+        .unwrap_or(false);
 
-    let (on_message, on_close, on_error) = extract_ws_callbacks(&mut cx, &options_obj)?;
+    let timeout = options_obj
+        .get_opt(&mut cx, "timeout")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx) as u64)
+        .unwrap_or(30000);
 
-    let options = WebSocketOptions {
+    let options = ProbeOptions {
         url,
-        emulation,
-        emulation_os,
-        headers,
-        protocols,
+        emulation: parse_emulation(&mut cx, &browser_str, false)?,
+        emulation_os: parse_emulation_os(&mut cx, &os_str, false)?,
         proxy,
+        insecure,
+        http2,
+        timeout,
     };
 
     let (deferred, promise) = cx.promise();
-    let callbacks_channel = cx.channel();
-    let settle_channel = callbacks_channel.clone();
+    let settle_channel = cx.channel();
 
     HTTP_RUNTIME.spawn(async move {
-        let result: Result<(u64, WebSocketUpgradeMetadata), anyhow::Error> = async {
-            let (connection, receiver, metadata) = connect_websocket(options).await?;
-            let id = setup_ws_callbacks(
-                connection,
-                receiver,
-                on_message,
-                on_close,
-                on_error,
-                callbacks_channel,
-            );
-            Ok((id, metadata))
-        }
-        .await;
+        let result = native_probe(options).await;
 
         deferred.settle_with(&settle_channel, move |mut cx| match result {
-            Ok((id, metadata)) => {
+            Ok(result) => {
                 let obj = cx.empty_object();
-                let id_num = cx.number(id as f64);
-                obj.set(&mut cx, "_id", id_num)?;
-                if let Some(protocol) = metadata.protocol {
-                    let protocol_value = cx.string(protocol);
-                    obj.set(&mut cx, "protocol", protocol_value)?;
+
+                let timings = cx.empty_object();
+                let phases = [
+                    ("dns", Some(result.timings.dns)),
+                    ("connect", Some(result.timings.connect)),
+                    ("proxy", result.timings.proxy),
+                    ("tls", result.timings.tls),
+                    ("h2", result.timings.h2),
+                    ("total", Some(result.timings.total)),
+                ];
+                for (key, elapsed) in phases {
+                    let value: Handle<JsValue> = match elapsed {
+                        Some(elapsed) => cx.number(elapsed.as_secs_f64() * 1000.0).upcast(),
+                        None => cx.null().upcast(),
+                    };
+                    timings.set(&mut cx, key, value)?;
                 }
-                if let Some(extensions) = metadata.extensions {
-                    let extensions_value = cx.string(extensions);
-                    obj.set(&mut cx, "extensions", extensions_value)?;
+                obj.set(&mut cx, "timings", timings)?;
+
+                let strings = [
+                    (
+                        "localAddress",
+                        result.local_addr.map(|addr| addr.to_string()),
+                    ),
+                    (
+                        "remoteAddress",
+                        result.remote_addr.map(|addr| addr.to_string()),
+                    ),
+                    ("alpnProtocol", result.alpn_protocol),
+                    ("tlsVersion", result.tls_version),
+                    ("cipher", result.cipher),
+                ];
+                for (key, value) in strings {
+                    let value: Handle<JsValue> = match value {
+                        Some(value) => cx.string(value).upcast(),
+                        None => cx.null().upcast(),
+                    };
+                    obj.set(&mut cx, key, value)?;
                 }
+
+                let h2_settings: Handle<JsValue> = match result.h2_settings {
+                    Some(settings) => {
+                        let settings_obj = cx.empty_object();
+                        for (id, value) in settings {
+                            let value = cx.number(value);
+                            settings_obj.set(&mut cx, id.to_string().as_str(), value)?;
+                        }
+                        settings_obj.upcast()
+                    }
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "h2Settings", h2_settings)?;
                 Ok(obj)
             }
-            Err(e) => {
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
-            }
+            Err(e) => throw_anyhow(&mut cx, e),
         });
     });
 
     Ok(promise)
 }
 
-// WebSocket connection with session (shares cookies and transport TLS config)
-fn websocket_connect_session(mut cx: FunctionContext) -> JsResult<JsPromise> {
-    let options_obj = cx.argument::<JsObject>(0)?;
-
-    let url: Handle<JsString> = options_obj.get(&mut cx, "url")?;
-    let url = url.value(&mut cx);
-
-    let session_id: Handle<JsString> = options_obj.get(&mut cx, "sessionId")?;
-    let session_id = session_id.value(&mut cx);
-
-    let transport_id: Handle<JsString> = options_obj.get(&mut cx, "transportId")?;
-    let transport_id = transport_id.value(&mut cx);
-
-    let headers = extract_ws_headers(&mut cx, &options_obj)?;
-    let protocols = extract_ws_protocols(&mut cx, &options_obj)?;
-    let (on_message, on_close, on_error) = extract_ws_callbacks(&mut cx, &options_obj)?;
+fn tls_read(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
 
     let (deferred, promise) = cx.promise();
-    let callbacks_channel = cx.channel();
-    let settle_channel = callbacks_channel.clone();
+    let settle_channel = cx.channel();
 
     HTTP_RUNTIME.spawn(async move {
-        let result: Result<(u64, WebSocketUpgradeMetadata), anyhow::Error> = async {
-            let (connection, receiver, metadata) =
-                connect_websocket_with_session(&session_id, &transport_id, &url, &headers, &protocols)
-                    .await?;
-            let id = setup_ws_callbacks(
-                connection,
-                receiver,
-                on_message,
-                on_close,
-                on_error,
-                callbacks_channel,
-            );
-            Ok((id, metadata))
-        }
-        .await;
+        let result = read_tls_socket(id).await;
 
         deferred.settle_with(&settle_channel, move |mut cx| match result {
-            Ok((id, metadata)) => {
-                let obj = cx.empty_object();
-                let id_num = cx.number(id as f64);
-                obj.set(&mut cx, "_id", id_num)?;
-                if let Some(protocol) = metadata.protocol {
-                    let protocol_value = cx.string(protocol);
-                    obj.set(&mut cx, "protocol", protocol_value)?;
-                }
-                if let Some(extensions) = metadata.extensions {
-                    let extensions_value = cx.string(extensions);
-                    obj.set(&mut cx, "extensions", extensions_value)?;
-                }
-                Ok(obj)
-            }
-            Err(e) => {
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
+            Ok(Some(bytes)) => {
+                let buffer = JsBuffer::from_slice(&mut cx, &bytes)?;
+                let value: Handle<JsValue> = buffer.upcast();
+                Ok(value)
             }
+            Ok(None) => Ok(cx.null().upcast()),
+            Err(e) => throw_anyhow(&mut cx, e),
         });
     });
 
     Ok(promise)
 }
 
-// WebSocket send function
-fn websocket_send(mut cx: FunctionContext) -> JsResult<JsPromise> {
-    let ws_obj = cx.argument::<JsObject>(0)?;
-    let data = cx.argument::<JsValue>(1)?;
-
-    // Get the connection ID from the object
-    let id_val: Handle<JsNumber> = ws_obj.get(&mut cx, "_id")?;
-    let id = id_val.value(&mut cx) as u64;
-
-    // Get connection from global storage
-    let connection = match get_connection(id) {
-        Some(conn) => conn,
-        None => return cx.throw_error("WebSocket connection not found"),
-    };
+fn tls_write(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let data = cx.argument::<JsBuffer>(1)?.as_slice(&cx).to_vec();
 
     let (deferred, promise) = cx.promise();
     let settle_channel = cx.channel();
 
-    // Check if data is string or buffer
-    let is_text = data.is_a::<JsString, _>(&mut cx);
-    let send_data = if is_text {
-        let text = data.downcast_or_throw::<JsString, _>(&mut cx)?;
-        SendData::Text(text.value(&mut cx))
-    } else if let Ok(buffer) = data.downcast::<JsBuffer, _>(&mut cx) {
-        let data = buffer.as_slice(&cx).to_vec();
-        SendData::Binary(data)
-    } else {
-        return cx.throw_error("Data must be a string or Buffer");
-    };
-
     HTTP_RUNTIME.spawn(async move {
-        let result = match send_data {
-            SendData::Text(text) => connection.send_text(text).await,
-            SendData::Binary(data) => connection.send_binary(data).await,
-        };
+        let result = write_tls_socket(id, data).await;
 
         deferred.settle_with(&settle_channel, move |mut cx| match result {
             Ok(()) => Ok(cx.undefined()),
-            Err(e) => {
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
-            }
+            Err(e) => throw_anyhow(&mut cx, e),
         });
     });
 
     Ok(promise)
 }
 
-enum SendData {
-    Text(String),
-    Binary(Vec<u8>),
-}
+// Half-close: send close_notify but keep reading
+fn tls_end(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
 
-enum WsEvent {
-    Text(String),
-    Binary(Vec<u8>),
-    Close(WsCloseEvent),
-    Error(String),
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = end_tls_socket(id).await;
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
 }
 
-#[derive(Clone)]
-struct WsCloseEvent {
-    code: u16,
-    reason: String,
+fn tls_close(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    close_tls_socket(id);
+    Ok(cx.undefined())
 }
 
-fn default_ws_close_event() -> WsCloseEvent {
-    // RFC 6455 reserved "no status received" code used when no close frame payload exists.
-    WsCloseEvent {
-        code: 1005,
-        reason: String::new(),
-    }
+fn body_size_to_js<'a, C: Context<'a>>(cx: &mut C, size: BodySize) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let wire = cx.number(size.wire as f64);
+    obj.set(cx, "wire", wire)?;
+    let decoded = cx.number(size.decoded as f64);
+    obj.set(cx, "decoded", decoded)?;
+    Ok(obj)
 }
 
-fn abnormal_ws_close_event() -> WsCloseEvent {
-    // RFC 6455 abnormal closure code used when the connection drops unexpectedly.
-    WsCloseEvent {
-        code: 1006,
-        reason: String::new(),
+fn header_tuples_to_js<'a, C: Context<'a>>(
+    cx: &mut C,
+    headers: &[(String, String)],
+) -> JsResult<'a, JsArray> {
+    let headers_arr = cx.empty_array();
+    for (i, (key, value)) in headers.iter().enumerate() {
+        let entry = cx.empty_array();
+        let key_str = cx.string(key);
+        let value_str = cx.string(value);
+        entry.set(cx, 0, key_str)?;
+        entry.set(cx, 1, value_str)?;
+        headers_arr.set(cx, i as u32, entry)?;
     }
+    Ok(headers_arr)
 }
 
-// WebSocket close function
-fn websocket_close(mut cx: FunctionContext) -> JsResult<JsPromise> {
-    let ws_obj = cx.argument::<JsObject>(0)?;
-    let close_payload = if let Some(close_value) = cx.argument_opt(1) {
-        if close_value.is_a::<JsUndefined, _>(&mut cx) || close_value.is_a::<JsNull, _>(&mut cx) {
-            None
-        } else {
-            let close_obj = close_value.downcast_or_throw::<JsObject, _>(&mut cx)?;
-            let code = close_obj
-                .get_opt::<JsNumber, _, _>(&mut cx, "code")?
-                .map(|num| num.value(&mut cx) as u16)
-                .unwrap_or(1000);
-            let reason = close_obj
-                .get_opt::<JsString, _, _>(&mut cx, "reason")?
-                .map(|value| value.value(&mut cx))
-                .unwrap_or_default();
+fn h2_open(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let options_obj = cx.argument::<JsObject>(0)?;
 
-            Some(WsClosePayload { code, reason })
-        }
-    } else {
-        None
+    let transport_id: Handle<JsString> = options_obj.get(&mut cx, "transportId")?;
+    let transport_id = transport_id.value(&mut cx);
+    ensure_visible(&mut cx, Resource::Transport, &transport_id)?;
+    let method: Handle<JsString> = options_obj.get(&mut cx, "method")?;
+    let method = method.value(&mut cx);
+    let scheme: Handle<JsString> = options_obj.get(&mut cx, "scheme")?;
+    let scheme = scheme.value(&mut cx);
+    let authority: Handle<JsString> = options_obj.get(&mut cx, "authority")?;
+    let authority = authority.value(&mut cx);
+    let path: Handle<JsString> = options_obj.get(&mut cx, "path")?;
+    let path = path.value(&mut cx);
+
+    let headers = match options_obj.get_opt::<JsValue, _, _>(&mut cx, "headers")? {
+        Some(value) => parse_headers_from_value(&mut cx, value)?,
+        None => Vec::new(),
     };
 
-    // Get the connection ID from the object
-    let id_val: Handle<JsNumber> = ws_obj.get(&mut cx, "_id")?;
-    let id = id_val.value(&mut cx) as u64;
+    let timeout = options_obj
+        .get_opt(&mut cx, "timeout")?
+        .and_then(|v: Handle<JsValue>| v.downcast::<JsNumber, _>(&mut cx).ok())
+        .map(|v| v.value(&mut cx) as u64)
+        .unwrap_or(30000);
 
-    // Get connection from global storage
-    let connection = match get_connection(id) {
-        Some(conn) => conn,
-        None => return cx.throw_error("WebSocket connection not found"),
+    let options = H2StreamOptions {
+        transport_id,
+        method,
+        scheme,
+        authority,
+        path,
+        headers,
+        timeout,
     };
 
+    match open_h2_stream(options) {
+        Ok(id) => {
+            claim(
+                current_context(&mut cx),
+                Resource::H2Stream,
+                &id.to_string(),
+            );
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => throw_anyhow(&mut cx, e),
+    }
+}
+
+fn h2_read(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
     let (deferred, promise) = cx.promise();
     let settle_channel = cx.channel();
 
     HTTP_RUNTIME.spawn(async move {
-        let result = connection.close(close_payload).await;
-
-        // Remove connection from storage after closing
-        remove_connection(id);
+        let result = read_h2_stream(id).await;
 
         deferred.settle_with(&settle_channel, move |mut cx| match result {
-            Ok(()) => Ok(cx.undefined()),
-            Err(e) => {
-                let error_msg = format!("{:#}", e);
-                cx.throw_error(error_msg)
+            Ok(Some(frame)) => {
+                let obj = cx.empty_object();
+                match frame {
+                    H2Frame::Headers { status, headers } => {
+                        let kind = cx.string("headers");
+                        obj.set(&mut cx, "type", kind)?;
+                        let status = cx.number(status as f64);
+                        obj.set(&mut cx, "status", status)?;
+                        let headers = header_tuples_to_js(&mut cx, &headers)?;
+                        obj.set(&mut cx, "headers", headers)?;
+                    }
+                    H2Frame::Data(bytes) => {
+                        let kind = cx.string("data");
+                        obj.set(&mut cx, "type", kind)?;
+                        let data = JsBuffer::from_slice(&mut cx, &bytes)?;
+                        obj.set(&mut cx, "data", data)?;
+                    }
+                    H2Frame::Trailers(headers) => {
+                        let kind = cx.string("trailers");
+                        obj.set(&mut cx, "type", kind)?;
+                        let headers = header_tuples_to_js(&mut cx, &headers)?;
+                        obj.set(&mut cx, "headers", headers)?;
+                    }
+                }
+                Ok(obj.upcast::<JsValue>())
             }
+            Ok(None) => Ok(cx.null().upcast()),
+            Err(e) => throw_anyhow(&mut cx, e),
         });
     });
 
     Ok(promise)
 }
 
-fn get_cookies(mut cx: FunctionContext) -> JsResult<JsObject> {
-    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
-    let url = cx.argument::<JsString>(1)?.value(&mut cx);
+fn h2_write(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let data = cx.argument::<JsBuffer>(1)?.as_slice(&cx).to_vec();
 
-    match get_session_cookies(&session_id, &url) {
-        Ok(cookies) => {
-            let obj = cx.empty_object();
-            for (name, value) in cookies {
-                let js_value = cx.string(&value);
-                obj.set(&mut cx, name.as_str(), js_value)?;
-            }
-            Ok(obj)
-        }
-        Err(e) => {
-            let msg = format!("{:#}", e);
-            cx.throw_error(msg)
-        }
-    }
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = write_h2_stream(id, data).await;
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
+
+    Ok(promise)
 }
 
-fn set_cookie(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-    let session_id = cx.argument::<JsString>(0)?.value(&mut cx);
-    let name = cx.argument::<JsString>(1)?.value(&mut cx);
-    let value = cx.argument::<JsString>(2)?.value(&mut cx);
-    let url = cx.argument::<JsString>(3)?.value(&mut cx);
+// End the request side, with optional trailers
+fn h2_end(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let trailers = match cx.argument_opt(1) {
+        Some(value) => parse_headers_from_value(&mut cx, value)?,
+        None => Vec::new(),
+    };
 
-    if let Err(e) = set_session_cookie(&session_id, &name, &value, &url) {
-        let msg = format!("{:#}", e);
-        return cx.throw_error(msg);
-    }
+    let (deferred, promise) = cx.promise();
+    let settle_channel = cx.channel();
+
+    HTTP_RUNTIME.spawn(async move {
+        let result = end_h2_stream(id, trailers).await;
+
+        deferred.settle_with(&settle_channel, move |mut cx| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_anyhow(&mut cx, e),
+        });
+    });
 
+    Ok(promise)
+}
+
+fn h2_close(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    close_h2_stream(id);
     Ok(cx.undefined())
 }
 
 // Module initialization
 #[neon::main]
 fn main(mut cx: ModuleContext) -> NeonResult<()> {
+    let process = cx.global::<JsObject>("process")?;
+    let versions = process.get::<JsObject, _, _>(&mut cx, "versions")?;
+    let electron = versions.get_opt::<JsValue, _, _>(&mut cx, "electron")?;
+    EXTERNAL_BUFFERS.store(electron.is_none(), Ordering::Relaxed);
+    current_context(&mut cx);
+
     cx.export_function("request", request)?;
+    cx.export_function("requestCallback", request_callback)?;
+    cx.export_function("fetchSet", fetch_set)?;
+    cx.export_function("allocateRequestId", allocate_request_id)?;
     cx.export_function("cancelRequest", cancel_request)?;
+    cx.export_function("cancelGroup", cancel_group)?;
+    cx.export_function("cancelAllRequests", cancel_all_requests)?;
+    cx.export_function("shutdown", shutdown)?;
+    cx.export_function("getNativeStats", get_native_stats)?;
+    cx.export_function("getMemoryStats", get_memory_stats)?;
     cx.export_function("readBodyChunk", read_body_chunk)?;
     cx.export_function("readBodyAll", read_body_all)?;
+    cx.export_function("readBodyJson", read_body_json)?;
+    cx.export_function("decodeText", decode_text)?;
+    cx.export_function("pushBody", push_body_stream)?;
+    cx.export_function("grantBodyCredit", grant_body_credit)?;
+    cx.export_function("pipeBodyToRing", pipe_body_to_shared_ring)?;
     cx.export_function("cancelBody", cancel_body_stream)?;
+    cx.export_function("configureBodyStreams", configure_body_streams)?;
+    cx.export_function("teeBodyToFile", tee_body)?;
+    cx.export_function("takeBodyDigest", take_digest)?;
+    cx.export_function("takeBodyTrailers", take_trailers)?;
+    cx.export_function("takeBodySize", take_size)?;
     cx.export_function("getProfiles", get_profiles)?;
     cx.export_function("getOperatingSystems", get_operating_systems)?;
+    cx.export_function("setStrictProfiles", set_strict_profiles)?;
+    cx.export_function("setRegistryMode", set_registry_mode)?;
+    cx.export_function("setDefaults", set_defaults)?;
+    cx.export_function("getDefaults", get_defaults)?;
+    cx.export_function("resolveProfile", resolve_profile_alias)?;
     cx.export_function("createSession", create_session)?;
+    cx.export_function("resolveAuthRefresh", resolve_auth_refresh)?;
+    cx.export_function("resolveBeforeSend", resolve_before_send)?;
     cx.export_function("clearSession", clear_session)?;
     cx.export_function("dropSession", drop_session)?;
     cx.export_function("getCookies", get_cookies)?;
     cx.export_function("setCookie", set_cookie)?;
+    cx.export_function("importBrowserCookies", import_browser_cookies)?;
+    cx.export_function("getHsts", get_hsts)?;
+    cx.export_function("importHsts", import_hsts)?;
+    cx.export_function("clearHsts", clear_hsts)?;
     cx.export_function("createTransport", create_transport)?;
     cx.export_function("dropTransport", drop_transport)?;
+    cx.export_function("clearTransportTlsSessions", clear_transport_tls_sessions)?;
+    cx.export_function("getAltSvc", get_alt_svc)?;
+    cx.export_function("clearAltSvc", clear_alt_svc)?;
+    cx.export_function("mock", mock_request)?;
+    cx.export_function("mockCalls", mock_calls)?;
+    cx.export_function("unmock", unmock)?;
+    cx.export_function("clearMocks", clear_all_mocks)?;
+    cx.export_function("useCassette", use_cassette)?;
+    cx.export_function("ejectCassette", eject_active_cassette)?;
+    cx.export_function("enableAuditLog", enable_audit_log)?;
+    cx.export_function("disableAuditLog", disable_audit_log)?;
+    cx.export_function("toCurl", to_curl)?;
+    cx.export_function("fromCurl", from_curl)?;
     cx.export_function("websocketConnect", websocket_connect)?;
     cx.export_function("websocketConnectSession", websocket_connect_session)?;
     cx.export_function("websocketSend", websocket_send)?;
     cx.export_function("websocketClose", websocket_close)?;
+    cx.export_function("websocketState", websocket_state)?;
+    cx.export_function("tlsConnect", tls_connect)?;
+    cx.export_function("probe", probe)?;
+    cx.export_function("tlsRead", tls_read)?;
+    cx.export_function("tlsWrite", tls_write)?;
+    cx.export_function("tlsEnd", tls_end)?;
+    cx.export_function("tlsClose", tls_close)?;
+    cx.export_function("h2Open", h2_open)?;
+    cx.export_function("h2Read", h2_read)?;
+    cx.export_function("h2Write", h2_write)?;
+    cx.export_function("h2End", h2_end)?;
+    cx.export_function("h2Close", h2_close)?;
     Ok(())
 }
@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Inline response bodies lent to JS as external Buffers, until JS collects them.
+pub static INLINE_BODIES: MemoryGauge = MemoryGauge::new();
+/// Body stream chunks read from the network and waiting for the JS thread to take them.
+pub static QUEUED_BODY_CHUNKS: MemoryGauge = MemoryGauge::new();
+/// WebSocket messages received and not yet delivered to JS.
+pub static QUEUED_WS_MESSAGES: MemoryGauge = MemoryGauge::new();
+
+/// Bytes of one kind the addon holds on behalf of JS, and how many pieces they are in.
+#[derive(Default)]
+pub struct MemoryGauge {
+    bytes: AtomicUsize,
+    items: AtomicUsize,
+}
+
+impl MemoryGauge {
+    pub const fn new() -> Self {
+        Self {
+            bytes: AtomicUsize::new(0),
+            items: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count `bytes` as held here until the returned hold is dropped.
+    pub fn hold(&'static self, bytes: usize) -> MemoryHold {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.items.fetch_add(1, Ordering::Relaxed);
+        MemoryHold { gauge: self, bytes }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn items(&self) -> usize {
+        self.items.load(Ordering::Relaxed)
+    }
+}
+
+/// Bytes counted against a [`MemoryGauge`] for as long as this lives.
+pub struct MemoryHold {
+    gauge: &'static MemoryGauge,
+    bytes: usize,
+}
+
+impl Drop for MemoryHold {
+    fn drop(&mut self) {
+        self.gauge.bytes.fetch_sub(self.bytes, Ordering::Relaxed);
+        self.gauge.items.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A buffer that counts against a gauge until it is dropped, e.g. when JS collects the external
+/// Buffer made from it.
+pub struct Held<T> {
+    pub value: T,
+    _hold: MemoryHold,
+}
+
+impl<T: AsRef<[u8]>> Held<T> {
+    pub fn new(value: T, gauge: &'static MemoryGauge) -> Self {
+        let hold = gauge.hold(value.as_ref().len());
+        Self { value, _hold: hold }
+    }
+}
+
+impl<T: AsMut<[u8]>> AsMut<[u8]> for Held<T> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.value.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static GAUGE: MemoryGauge = MemoryGauge::new();
+
+    #[test]
+    fn counts_bytes_until_released() {
+        let first = GAUGE.hold(100);
+        let held = Held::new(vec![0u8; 28], &GAUGE);
+        assert_eq!((GAUGE.bytes(), GAUGE.items()), (128, 2));
+
+        drop(first);
+        assert_eq!((GAUGE.bytes(), GAUGE.items()), (28, 1));
+        assert_eq!(held.value.len(), 28);
+        drop(held);
+        assert_eq!((GAUGE.bytes(), GAUGE.items()), (0, 0));
+    }
+}
@@ -0,0 +1,110 @@
+use url::Url;
+
+use crate::charset::{attribute, meta_tags};
+
+/// Refresh pages that may be followed for one request, matching wreq's default redirect limit.
+pub const MAX_META_REFRESH_HOPS: usize = 10;
+
+// Interstitial pages declare the refresh in a short `<head>`; the rest of a page is not searched.
+const SCAN_LIMIT: usize = 64 * 1024;
+
+/// Where the `<meta http-equiv="refresh">` tag of `html` sends the browser, resolved against
+/// `base`, when it does so within `max_delay` seconds. A refresh that reloads the page itself is
+/// not a redirect and is ignored.
+pub fn refresh_target(html: &[u8], base: &str, max_delay: u64) -> Option<String> {
+    let head = &html[..html.len().min(SCAN_LIMIT)];
+    // Like browsers, only the first refresh tag counts.
+    let content = meta_tags(head).find_map(|attributes| {
+        attribute(&attributes, "http-equiv")
+            .filter(|value| value.trim().eq_ignore_ascii_case("refresh"))?;
+        attribute(&attributes, "content").map(str::to_owned)
+    })?;
+
+    let (delay, target) = parse_refresh(&content)?;
+    if delay > max_delay {
+        return None;
+    }
+    let base = Url::parse(base).ok()?;
+    let target = base.join(target?).ok()?;
+    let followable = matches!(target.scheme(), "http" | "https") && target != base;
+    followable.then(|| target.into())
+}
+
+/// Split a refresh `content` value such as `0; url='/next'` into its delay in seconds and URL,
+/// following the HTML spec's parsing rules.
+fn parse_refresh(content: &str) -> Option<(u64, Option<&str>)> {
+    let content = content.trim_start();
+    let end = content
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(content.len());
+    let (number, rest) = content.split_at(end);
+    if number.is_empty() {
+        return None;
+    }
+    let whole = number.split('.').next().unwrap_or_default();
+    let delay = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().unwrap_or(u64::MAX)
+    };
+
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix([';', ',']).unwrap_or(rest).trim_start();
+    if rest.is_empty() {
+        return Some((delay, None));
+    }
+
+    let rest = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            match rest[3..].trim_start().strip_prefix('=') {
+                Some(value) => value.trim_start(),
+                None => rest,
+            }
+        }
+        _ => rest,
+    };
+    let url = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let quoted = &rest[1..];
+            &quoted[..quoted.find(quote).unwrap_or(quoted.len())]
+        }
+        _ => rest,
+    };
+    let url = url.trim_end();
+    Some((delay, (!url.is_empty()).then_some(url)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_refresh, refresh_target};
+
+    #[test]
+    fn parses_refresh_content() {
+        assert_eq!(parse_refresh("0; url=/next"), Some((0, Some("/next"))));
+        assert_eq!(
+            parse_refresh("3,URL = 'https://example.com/a b' "),
+            Some((3, Some("https://example.com/a b")))
+        );
+        assert_eq!(parse_refresh(".5;/plain"), Some((0, Some("/plain"))));
+        assert_eq!(parse_refresh("10"), Some((10, None)));
+        assert_eq!(parse_refresh("soon; url=/next"), None);
+    }
+
+    #[test]
+    fn follows_only_quick_refreshes_elsewhere() {
+        let page = b"<html><head><!-- <meta http-equiv=refresh content='0;url=/wrong'> -->\
+            <META HTTP-EQUIV=\"Refresh\" CONTENT=\"1; URL=/Landing?from=gate\"></head></html>";
+        assert_eq!(
+            refresh_target(page, "https://example.com/gate/", 5).as_deref(),
+            Some("https://example.com/Landing?from=gate")
+        );
+        assert_eq!(refresh_target(page, "https://example.com/gate/", 0), None);
+
+        let reload = b"<meta http-equiv=refresh content=\"0\">";
+        assert_eq!(refresh_target(reload, "https://example.com/", 5), None);
+        let same = b"<meta http-equiv=refresh content=\"0; url=/\">";
+        assert_eq!(refresh_target(same, "https://example.com/", 5), None);
+        let script = b"<meta http-equiv=refresh content=\"0; url=javascript:go()\">";
+        assert_eq!(refresh_target(script, "https://example.com/", 5), None);
+    }
+}
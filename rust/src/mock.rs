@@ -0,0 +1,145 @@
+use bytes::Bytes;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Canned response served in place of a network round-trip.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+    pub delay_ms: u64,
+}
+
+/// A registered interception: requests matching `method` (any when `None`) and `url_pattern`
+/// are answered with `response`.
+#[derive(Debug, Clone)]
+pub struct MockRule {
+    pub method: Option<String>,
+    pub url_pattern: String,
+    pub response: MockResponse,
+}
+
+#[derive(Debug)]
+struct RegisteredMock {
+    id: u64,
+    rule: MockRule,
+    calls: AtomicU64,
+}
+
+static MOCKS: LazyLock<RwLock<Vec<Arc<RegisteredMock>>>> = LazyLock::new(Default::default);
+static NEXT_MOCK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Match `url` against a pattern where `*` stands for any run of characters.
+fn matches_pattern(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = url.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard at all: the pattern must match the whole URL.
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+pub fn register_mock(rule: MockRule) -> u64 {
+    let id = NEXT_MOCK_ID.fetch_add(1, Ordering::Relaxed);
+    let mock = Arc::new(RegisteredMock {
+        id,
+        rule: MockRule {
+            method: rule.method.map(|method| method.to_ascii_uppercase()),
+            ..rule
+        },
+        calls: AtomicU64::new(0),
+    });
+    MOCKS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(mock);
+    id
+}
+
+pub fn remove_mock(id: u64) -> bool {
+    let mut mocks = MOCKS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let before = mocks.len();
+    mocks.retain(|mock| mock.id != id);
+    mocks.len() != before
+}
+
+pub fn clear_mocks() {
+    MOCKS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clear();
+}
+
+/// Number of requests a mock has answered, or `None` once it has been removed.
+pub fn mock_call_count(id: u64) -> Option<u64> {
+    MOCKS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .find(|mock| mock.id == id)
+        .map(|mock| mock.calls.load(Ordering::Relaxed))
+}
+
+/// Find the mock answering this request and record the call. The most recently registered
+/// matching mock wins so tests can override broader patterns.
+pub fn match_mock(method: &str, url: &str) -> Option<MockResponse> {
+    let mocks = MOCKS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mock = mocks.iter().rev().find(|mock| {
+        mock.rule
+            .method
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(method))
+            && matches_pattern(&mock.rule.url_pattern, url)
+    })?;
+
+    mock.calls.fetch_add(1, Ordering::Relaxed);
+    Some(mock.rule.response.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches_pattern;
+
+    #[test]
+    fn exact_patterns_match_the_whole_url() {
+        assert!(matches_pattern("https://a.test/x", "https://a.test/x"));
+        assert!(!matches_pattern("https://a.test/x", "https://a.test/xy"));
+    }
+
+    #[test]
+    fn wildcards_match_any_run_of_characters() {
+        assert!(matches_pattern(
+            "https://a.test/*",
+            "https://a.test/users/1"
+        ));
+        assert!(matches_pattern(
+            "*/users/*/posts",
+            "https://a.test/users/1/posts"
+        ));
+        assert!(matches_pattern("*", "https://anything.test/"));
+        assert!(!matches_pattern(
+            "https://a.test/*/posts",
+            "https://a.test/users/1"
+        ));
+        assert!(!matches_pattern("https://b.test/*", "https://a.test/users"));
+    }
+}
@@ -0,0 +1,44 @@
+use neon::prelude::*;
+
+use crate::client::{
+    HTTP_RUNTIME, SessionToken, drop_body_stream, drop_managed_transport, release_managed_session,
+};
+use crate::contexts::{Resource, release};
+use crate::websocket::{get_connection, remove_connection};
+
+/// Handed to JS boxed alongside a native ID. When the JS object holding it is garbage collected
+/// without the resource having been closed, the finalizer releases the native entry, which would
+/// otherwise stay alive for as long as the process does. Releasing an entry that was already
+/// closed does nothing.
+pub enum NativeGuard {
+    Body(u64),
+    Session(String, SessionToken),
+    Transport(String),
+    WebSocket(u64),
+}
+
+impl Finalize for NativeGuard {
+    fn finalize<'a, C: Context<'a>>(self, _cx: &mut C) {
+        match self {
+            NativeGuard::Body(handle) => drop_body_stream(handle),
+            NativeGuard::Session(id, token) => {
+                // A session closed and created again under the same ID is not this one.
+                if release_managed_session(&id, &token) {
+                    release(Resource::Session, &id);
+                }
+            }
+            NativeGuard::Transport(id) => {
+                drop_managed_transport(&id);
+                release(Resource::Transport, &id);
+            }
+            NativeGuard::WebSocket(id) => {
+                if let Some(connection) = get_connection(id) {
+                    remove_connection(id);
+                    HTTP_RUNTIME.spawn(async move {
+                        let _ = connection.close(None).await;
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use anyhow::{Context, Result, anyhow, bail};
+use boring2::base64::{decode_block, encode_block};
+use boring2::hash::{MessageDigest, hash};
+use boring2::nid::Nid;
+use boring2::rand::rand_bytes;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Negotiate flags (MS-NLMP, section 2.2.2.5).
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const NEGOTIATE_OEM: u32 = 0x0000_0002;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_TARGET_INFO: u32 = 0x0080_0000;
+
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | NEGOTIATE_OEM
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+const AV_EOL: u16 = 0;
+const AV_TIMESTAMP: u16 = 7;
+// Seconds from 1601-01-01, where Windows FILETIME starts, to the Unix epoch.
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+const AUTHENTICATE_HEADER_LEN: usize = 64;
+
+/// Windows credentials for NTLM authentication.
+#[derive(Clone, PartialEq, Eq)]
+pub struct NtlmCredentials {
+    pub username: String,
+    pub password: String,
+    pub domain: String,
+    pub workstation: String,
+}
+
+impl NtlmCredentials {
+    /// A `DOMAIN\user` username carries its domain when none is given separately.
+    pub fn new(
+        username: String,
+        password: String,
+        domain: Option<String>,
+        workstation: Option<String>,
+    ) -> Self {
+        let (domain, username) = match (domain, username.split_once('\\')) {
+            (Some(domain), _) => (domain, username),
+            (None, Some((domain, user))) => (domain.to_owned(), user.to_owned()),
+            (None, None) => (String::new(), username),
+        };
+        Self {
+            username,
+            password,
+            domain,
+            workstation: workstation.unwrap_or_default(),
+        }
+    }
+}
+
+// Request options are debug-printed; the password must not be.
+impl std::fmt::Debug for NtlmCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NtlmCredentials")
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .field("workstation", &self.workstation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `WWW-Authenticate` (or `Proxy-Authenticate`) scheme that carries NTLM messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NtlmScheme {
+    Ntlm,
+    /// SPNEGO servers accept a raw NTLM token when Kerberos is not used.
+    Negotiate,
+}
+
+impl NtlmScheme {
+    fn as_str(self) -> &'static str {
+        match self {
+            NtlmScheme::Ntlm => "NTLM",
+            NtlmScheme::Negotiate => "Negotiate",
+        }
+    }
+
+    /// The scheme to answer a 401 or 407 with, preferring `NTLM` when both are offered.
+    pub fn offered<'a>(challenges: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut offered = None;
+        for challenge in challenges {
+            for scheme in challenge.split(',') {
+                let name = scheme.trim().split(' ').next().unwrap_or_default();
+                if name.eq_ignore_ascii_case("NTLM") {
+                    return Some(NtlmScheme::Ntlm);
+                }
+                if name.eq_ignore_ascii_case("Negotiate") {
+                    offered = Some(NtlmScheme::Negotiate);
+                }
+            }
+        }
+        offered
+    }
+
+    /// The base64 token of the server's challenge for this scheme.
+    fn token<'a>(self, challenges: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+        challenges.into_iter().find_map(|challenge| {
+            let (name, token) = challenge.trim().split_once(' ')?;
+            name.eq_ignore_ascii_case(self.as_str())
+                .then(|| token.trim())
+                .filter(|token| !token.is_empty())
+        })
+    }
+}
+
+/// The first leg: an `Authorization` value announcing NTLM.
+pub fn negotiate_header(scheme: NtlmScheme) -> String {
+    let mut message = Vec::with_capacity(32);
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    // Empty domain and workstation fields.
+    message.extend_from_slice(&[0; 16]);
+    format!("{} {}", scheme.as_str(), encode_block(&message))
+}
+
+/// The last leg: an `Authorization` value answering the challenge among `challenges` (the
+/// `WWW-Authenticate` values of the second response) with an NTLMv2 response.
+pub fn authenticate_header<'a>(
+    credentials: &NtlmCredentials,
+    scheme: NtlmScheme,
+    challenges: impl IntoIterator<Item = &'a str>,
+) -> Result<String> {
+    let token = scheme
+        .token(challenges)
+        .ok_or_else(|| anyhow!("Server did not send an NTLM challenge"))?;
+    let message = decode_block(token).context("Invalid NTLM challenge")?;
+    let challenge = Challenge::parse(&message)?;
+    let mut client_challenge = [0u8; 8];
+    rand_bytes(&mut client_challenge)?;
+    let message =
+        authenticate_message(credentials, &challenge, client_challenge, SystemTime::now())?;
+    Ok(format!("{} {}", scheme.as_str(), encode_block(&message)))
+}
+
+/// A CHALLENGE_MESSAGE (MS-NLMP, section 2.2.1.2).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl Challenge {
+    fn parse(message: &[u8]) -> Result<Self> {
+        if message.len() < 32 || &message[..8] != SIGNATURE || read_u32(message, 8) != 2 {
+            bail!("Invalid NTLM challenge");
+        }
+        let flags = read_u32(message, 20);
+        let mut server_challenge = [0u8; 8];
+        server_challenge.copy_from_slice(&message[24..32]);
+        let target_info = if message.len() >= 48 {
+            read_field(message, 40)?.to_vec()
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            flags,
+            server_challenge,
+            target_info,
+        })
+    }
+
+    /// The server's clock from `MsvAvTimestamp`, which the response must use when present.
+    fn timestamp(&self) -> Option<u64> {
+        let mut pairs = self.target_info.as_slice();
+        while pairs.len() >= 4 {
+            let id = u16::from_le_bytes([pairs[0], pairs[1]]);
+            let len = usize::from(u16::from_le_bytes([pairs[2], pairs[3]]));
+            let value = pairs.get(4..4 + len)?;
+            match id {
+                AV_EOL => return None,
+                AV_TIMESTAMP if len == 8 => {
+                    return Some(u64::from_le_bytes(value.try_into().ok()?));
+                }
+                _ => pairs = &pairs[4 + len..],
+            }
+        }
+        None
+    }
+}
+
+/// An AUTHENTICATE_MESSAGE (MS-NLMP, section 2.2.1.3) without a session key or MIC, which HTTP
+/// authentication does not use.
+fn authenticate_message(
+    credentials: &NtlmCredentials,
+    challenge: &Challenge,
+    client_challenge: [u8; 8],
+    now: SystemTime,
+) -> Result<Vec<u8>> {
+    let time = challenge.timestamp().unwrap_or_else(|| filetime(now));
+    let response_key = ntowfv2(credentials)?;
+    let (lm_response, nt_response) = ntlmv2_responses(
+        &response_key,
+        &challenge.server_challenge,
+        &client_challenge,
+        time,
+        &challenge.target_info,
+    )?;
+
+    let unicode = challenge.flags & NEGOTIATE_UNICODE != 0;
+    let text = |value: &str| {
+        if unicode {
+            utf16le(value)
+        } else {
+            value.as_bytes().to_vec()
+        }
+    };
+    let fields = [
+        lm_response,
+        nt_response,
+        text(&credentials.domain),
+        text(&credentials.username),
+        text(&credentials.workstation),
+        Vec::new(),
+    ];
+    let flags = challenge.flags & (NEGOTIATE_FLAGS | NEGOTIATE_TARGET_INFO);
+
+    let mut message =
+        Vec::with_capacity(AUTHENTICATE_HEADER_LEN + fields.iter().map(Vec::len).sum::<usize>());
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&3u32.to_le_bytes());
+    let mut offset = AUTHENTICATE_HEADER_LEN;
+    for field in &fields {
+        let len = u16::try_from(field.len()).context("NTLM credentials are too long")?;
+        message.extend_from_slice(&len.to_le_bytes());
+        message.extend_from_slice(&len.to_le_bytes());
+        message.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    message.extend_from_slice(&flags.to_le_bytes());
+    for field in &fields {
+        message.extend_from_slice(field);
+    }
+    Ok(message)
+}
+
+/// NTOWFv2: the NTLMv2 response key, from the NT hash of the password.
+fn ntowfv2(credentials: &NtlmCredentials) -> Result<[u8; 16]> {
+    let md4 = MessageDigest::from_nid(Nid::MD4).ok_or_else(|| anyhow!("MD4 is not available"))?;
+    let nt_hash = hash(md4, &utf16le(&credentials.password))?;
+    let identity = utf16le(&format!(
+        "{}{}",
+        credentials.username.to_uppercase(),
+        credentials.domain
+    ));
+    hmac_md5(&nt_hash, &[&identity])
+}
+
+/// The LMv2 and NTLMv2 responses (MS-NLMP, section 3.3.2).
+fn ntlmv2_responses(
+    response_key: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+    time: u64,
+    target_info: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut blob = Vec::with_capacity(32 + target_info.len());
+    blob.extend_from_slice(&[1, 1, 0, 0, 0, 0, 0, 0]);
+    blob.extend_from_slice(&time.to_le_bytes());
+    blob.extend_from_slice(client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let proof = hmac_md5(response_key, &[server_challenge, &blob])?;
+    let nt_response = [proof.as_slice(), &blob].concat();
+    let lm_proof = hmac_md5(response_key, &[server_challenge, client_challenge])?;
+    let lm_response = [lm_proof.as_slice(), client_challenge].concat();
+    Ok((lm_response, nt_response))
+}
+
+/// HMAC (RFC 2104) with MD5 over the concatenation of `parts`.
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> Result<[u8; 16]> {
+    const BLOCK: usize = 64;
+    let mut padded = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        padded[..16].copy_from_slice(&hash(MessageDigest::md5(), key)?);
+    } else {
+        padded[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = padded.iter().map(|byte| byte ^ 0x36).collect();
+    for part in parts {
+        inner.extend_from_slice(part);
+    }
+    let inner = hash(MessageDigest::md5(), &inner)?;
+    let mut outer: Vec<u8> = padded.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend_from_slice(&inner);
+
+    let mut mac = [0u8; 16];
+    mac.copy_from_slice(&hash(MessageDigest::md5(), &outer)?);
+    Ok(mac)
+}
+
+fn utf16le(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Tenths of a microsecond since 1601-01-01.
+fn filetime(now: SystemTime) -> u64 {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs() + FILETIME_UNIX_OFFSET) * 10_000_000
+        + u64::from(since_epoch.subsec_nanos() / 100)
+}
+
+fn read_u32(message: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([
+        message[at],
+        message[at + 1],
+        message[at + 2],
+        message[at + 3],
+    ])
+}
+
+/// The payload a security buffer at `at` points to (MS-NLMP, section 2.2.1).
+fn read_field(message: &[u8], at: usize) -> Result<&[u8]> {
+    let len = usize::from(u16::from_le_bytes([message[at], message[at + 1]]));
+    let offset = read_u32(message, at + 4) as usize;
+    message
+        .get(offset..offset + len)
+        .ok_or_else(|| anyhow!("Invalid NTLM challenge"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test values from MS-NLMP, section 4.2.4.
+    const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const CLIENT_CHALLENGE: [u8; 8] = [0xaa; 8];
+
+    fn credentials() -> NtlmCredentials {
+        NtlmCredentials::new(
+            "Domain\\User".into(),
+            "Password".into(),
+            None,
+            Some("COMPUTER".into()),
+        )
+    }
+
+    fn av_pair(id: u16, value: &[u8]) -> Vec<u8> {
+        let mut pair = id.to_le_bytes().to_vec();
+        pair.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        pair.extend_from_slice(value);
+        pair
+    }
+
+    fn target_info() -> Vec<u8> {
+        [
+            av_pair(2, &utf16le("Domain")),
+            av_pair(1, &utf16le("Server")),
+            av_pair(AV_EOL, &[]),
+        ]
+        .concat()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn computes_ntlmv2_responses() {
+        let credentials = credentials();
+        assert_eq!(credentials.domain, "Domain");
+        assert_eq!(credentials.username, "User");
+
+        let key = ntowfv2(&credentials).unwrap();
+        assert_eq!(hex(&key), "0c868a403bfd7a93a3001ef22ef02e3f");
+
+        let (lm, nt) = ntlmv2_responses(
+            &key,
+            &SERVER_CHALLENGE,
+            &CLIENT_CHALLENGE,
+            0,
+            &target_info(),
+        )
+        .unwrap();
+        assert_eq!(hex(&lm), "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa");
+        assert_eq!(hex(&nt[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+    }
+
+    #[test]
+    fn answers_challenges() {
+        let info = [av_pair(AV_TIMESTAMP, &42u64.to_le_bytes()), target_info()].concat();
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&2u32.to_le_bytes());
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&(NEGOTIATE_FLAGS | NEGOTIATE_TARGET_INFO).to_le_bytes());
+        message.extend_from_slice(&SERVER_CHALLENGE);
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&(info.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(info.len() as u16).to_le_bytes());
+        message.extend_from_slice(&48u32.to_le_bytes());
+        message.extend_from_slice(&info);
+
+        let challenge = Challenge::parse(&message).unwrap();
+        assert_eq!(challenge.server_challenge, SERVER_CHALLENGE);
+        assert_eq!(challenge.timestamp(), Some(42));
+
+        let header = format!("NTLM {}", encode_block(&message));
+        let answer =
+            authenticate_header(&credentials(), NtlmScheme::Ntlm, ["Basic", &header]).unwrap();
+        let answer = decode_block(answer.strip_prefix("NTLM ").unwrap()).unwrap();
+        assert_eq!(&answer[..8], SIGNATURE);
+        assert_eq!(read_u32(&answer, 8), 3);
+        assert_eq!(read_field(&answer, 28).unwrap(), utf16le("Domain"));
+        assert_eq!(read_field(&answer, 36).unwrap(), utf16le("User"));
+        assert_eq!(read_field(&answer, 44).unwrap(), utf16le("COMPUTER"));
+        // The blob carries the server's timestamp.
+        let nt = read_field(&answer, 20).unwrap();
+        assert_eq!(&nt[24..32], &42u64.to_le_bytes());
+
+        assert!(Challenge::parse(b"NTLMSSP\0").is_err());
+        assert!(authenticate_header(&credentials(), NtlmScheme::Ntlm, ["NTLM"]).is_err());
+    }
+
+    #[test]
+    fn picks_the_offered_scheme() {
+        assert_eq!(
+            NtlmScheme::offered(["Negotiate", "NTLM"]),
+            Some(NtlmScheme::Ntlm)
+        );
+        assert_eq!(
+            NtlmScheme::offered(["Basic realm=\"x\", Negotiate"]),
+            Some(NtlmScheme::Negotiate)
+        );
+        assert_eq!(NtlmScheme::offered(["Basic realm=\"x\""]), None);
+        assert!(negotiate_header(NtlmScheme::Negotiate).starts_with("Negotiate TlRMTVNTUAAB"));
+    }
+}
@@ -0,0 +1,349 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, copy_bidirectional};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+use crate::ntlm::{NtlmCredentials, NtlmScheme, authenticate_header, negotiate_header};
+
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// A local proxy that opens each tunnel through an upstream HTTP proxy requiring NTLM, doing
+/// the handshake on the tunnel's own connection. wreq only knows Basic proxy credentials, so a
+/// transport with `proxyNtlm` talks to this proxy instead of the upstream one.
+///
+/// `CONNECT` requests are tunneled as they are. Plain `http://` requests, which a client sends
+/// to its proxy in absolute form, are sent through a tunnel to the origin. The listener stops
+/// when this is dropped; tunnels already open run until either side closes them.
+pub struct NtlmProxy {
+    url: String,
+    listener: JoinHandle<()>,
+}
+
+struct Upstream {
+    addr: String,
+    credentials: NtlmCredentials,
+}
+
+impl NtlmProxy {
+    pub fn spawn(runtime: &Handle, upstream: &str, credentials: NtlmCredentials) -> Result<Self> {
+        let proxy = url::Url::parse(upstream).context("Invalid proxy URL")?;
+        if proxy.scheme() != "http" {
+            bail!("proxyNtlm requires an http:// proxy");
+        }
+        let host = proxy
+            .host_str()
+            .ok_or_else(|| anyhow!("Proxy URL has no host"))?;
+        let port = proxy.port_or_known_default().unwrap_or(80);
+        let upstream = Arc::new(Upstream {
+            addr: format!("{}:{}", host, port),
+            credentials,
+        });
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let listener = runtime.spawn(async move {
+            let Ok(listener) = TcpListener::from_std(listener) else {
+                return;
+            };
+            while let Ok((client, _)) = listener.accept().await {
+                let upstream = upstream.clone();
+                tokio::spawn(async move {
+                    let _ = forward(client, &upstream).await;
+                });
+            }
+        });
+        Ok(Self { url, listener })
+    }
+
+    /// The `http://127.0.0.1:port` URL clients use as their proxy.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for NtlmProxy {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+/// What the upstream proxy answered a `CONNECT` with.
+struct ProxyResponse {
+    status: u16,
+    reason: String,
+    authenticate: Vec<String>,
+    keep_alive: bool,
+    /// Bytes read past the head: the start of the tunnel after a 200.
+    rest: Vec<u8>,
+}
+
+enum Tunnel {
+    Open(TcpStream, Vec<u8>),
+    Refused(u16, String),
+}
+
+async fn forward(mut client: TcpStream, upstream: &Upstream) -> Result<()> {
+    let (head, rest) = read_head(&mut client).await?;
+    let head_text = String::from_utf8_lossy(&head);
+    let mut request_line = head_text.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+
+    // Absolute-form requests go to the origin as they are, which servers must accept.
+    let (authority, pending) = if method.eq_ignore_ascii_case("CONNECT") {
+        (target.to_owned(), rest)
+    } else {
+        let url = url::Url::parse(target).context("Invalid proxy request target")?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("Proxy request target has no host"))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        (
+            format!("{}:{}", host, port),
+            [head.as_slice(), &rest].concat(),
+        )
+    };
+
+    match open_tunnel(upstream, &authority).await? {
+        Tunnel::Open(mut server, early) => {
+            if method.eq_ignore_ascii_case("CONNECT") {
+                client
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .await?;
+            }
+            client.write_all(&early).await?;
+            server.write_all(&pending).await?;
+            copy_bidirectional(&mut client, &mut server).await?;
+        }
+        Tunnel::Refused(status, reason) => {
+            let response = format!(
+                "HTTP/1.1 {} {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status, reason
+            );
+            client.write_all(response.as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// `CONNECT` to `authority` through the upstream proxy, answering its NTLM challenge.
+async fn open_tunnel(upstream: &Upstream, authority: &str) -> Result<Tunnel> {
+    let mut server = TcpStream::connect(&upstream.addr).await?;
+    let mut response = connect(&mut server, authority, None).await?;
+    let scheme = match NtlmScheme::offered(response.authenticate.iter().map(String::as_str)) {
+        Some(scheme) if response.status == 407 => scheme,
+        _ => return Ok(settle(server, response)),
+    };
+
+    if !response.keep_alive {
+        server = TcpStream::connect(&upstream.addr).await?;
+    }
+    response = connect(&mut server, authority, Some(negotiate_header(scheme))).await?;
+    if response.status != 407 {
+        return Ok(settle(server, response));
+    }
+    if !response.keep_alive {
+        bail!("Proxy closed the connection during NTLM authentication");
+    }
+    let answer = authenticate_header(
+        &upstream.credentials,
+        scheme,
+        response.authenticate.iter().map(String::as_str),
+    )?;
+    response = connect(&mut server, authority, Some(answer)).await?;
+    Ok(settle(server, response))
+}
+
+fn settle(server: TcpStream, response: ProxyResponse) -> Tunnel {
+    if response.status == 200 {
+        Tunnel::Open(server, response.rest)
+    } else {
+        Tunnel::Refused(response.status, response.reason)
+    }
+}
+
+async fn connect(
+    server: &mut TcpStream,
+    authority: &str,
+    authorization: Option<String>,
+) -> Result<ProxyResponse> {
+    let mut request = format!(
+        "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\nProxy-Connection: Keep-Alive\r\n",
+        authority
+    );
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+    }
+    request.push_str("\r\n");
+    server.write_all(request.as_bytes()).await?;
+
+    let (head, mut rest) = read_head(server).await?;
+    let mut response = parse_response(&head)?;
+    if response.status != 200 {
+        // Skip the error page so the connection can carry the next leg.
+        match content_length(&head) {
+            Some(len) if len >= rest.len() => {
+                let mut body = vec![0; len - rest.len()];
+                server.read_exact(&mut body).await?;
+                rest.clear();
+            }
+            Some(_) => rest.clear(),
+            None => response.keep_alive = false,
+        }
+    }
+    response.rest = rest;
+    Ok(response)
+}
+
+fn parse_response(head: &[u8]) -> Result<ProxyResponse> {
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.lines();
+    let status_line = lines.next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    let status = parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| anyhow!("Invalid proxy response: {}", status_line))?;
+    let reason = parts.next().unwrap_or_default().to_owned();
+
+    let mut authenticate = Vec::new();
+    let mut keep_alive = version != "HTTP/1.0";
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("proxy-authenticate") {
+            authenticate.push(value.to_owned());
+        } else if name.eq_ignore_ascii_case("connection")
+            || name.eq_ignore_ascii_case("proxy-connection")
+        {
+            keep_alive = value.eq_ignore_ascii_case("keep-alive");
+        }
+    }
+    Ok(ProxyResponse {
+        status,
+        reason,
+        authenticate,
+        keep_alive,
+        rest: Vec::new(),
+    })
+}
+
+fn content_length(head: &[u8]) -> Option<usize> {
+    String::from_utf8_lossy(head)
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
+/// Read up to the blank line ending a message head; returns the head and what came after it.
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let rest = buffer.split_off(end + 4);
+            return Ok((buffer, rest));
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            bail!("Proxy message head is too large");
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("Connection closed before the message head ended");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NtlmProxy, read_head};
+    use crate::ntlm::NtlmCredentials;
+    use boring2::base64::{decode_block, encode_block};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::runtime::Handle;
+
+    fn authorization(head: &[u8]) -> Option<Vec<u8>> {
+        String::from_utf8_lossy(head)
+            .lines()
+            .find_map(|line| line.strip_prefix("Proxy-Authorization: NTLM "))
+            .map(|token| decode_block(token).unwrap())
+    }
+
+    /// An upstream proxy that wants NTLM and then echoes whatever goes through the tunnel.
+    async fn upstream_proxy() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (head, _) = read_head(&mut stream).await.unwrap();
+            assert!(head.starts_with(b"CONNECT example.com:443 "));
+            assert_eq!(authorization(&head), None);
+            stream
+                .write_all(
+                    b"HTTP/1.1 407 Proxy Authentication Required\r\n\
+                      Proxy-Authenticate: NTLM\r\nContent-Length: 5\r\n\r\nnope!",
+                )
+                .await
+                .unwrap();
+
+            let (head, _) = read_head(&mut stream).await.unwrap();
+            assert_eq!(authorization(&head).unwrap()[8], 1);
+            let mut challenge = b"NTLMSSP\0\x02\0\0\0".to_vec();
+            challenge.extend_from_slice(&[0; 8]);
+            challenge.extend_from_slice(&1u32.to_le_bytes());
+            challenge.extend_from_slice(&[7; 8]);
+            let response = format!(
+                "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                 Proxy-Authenticate: NTLM {}\r\nContent-Length: 0\r\n\r\n",
+                encode_block(&challenge)
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            let (head, _) = read_head(&mut stream).await.unwrap();
+            assert_eq!(authorization(&head).unwrap()[8], 3);
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            let mut buffer = [0u8; 4];
+            stream.read_exact(&mut buffer).await.unwrap();
+            stream.write_all(&buffer).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn tunnels_through_an_ntlm_proxy() {
+        let credentials = NtlmCredentials::new("CORP\\alice".into(), "pw".into(), None, None);
+        let proxy =
+            NtlmProxy::spawn(&Handle::current(), &upstream_proxy().await, credentials).unwrap();
+
+        let mut client = TcpStream::connect(proxy.url().trim_start_matches("http://"))
+            .await
+            .unwrap();
+        client
+            .write_all(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n")
+            .await
+            .unwrap();
+        let (head, _) = read_head(&mut client).await.unwrap();
+        assert!(head.starts_with(b"HTTP/1.1 200 "));
+
+        client.write_all(b"ping").await.unwrap();
+        let mut echoed = [0u8; 4];
+        client.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"ping");
+    }
+
+    #[test]
+    fn needs_a_plain_http_proxy() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let credentials = NtlmCredentials::new("alice".into(), "pw".into(), None, None);
+        assert!(NtlmProxy::spawn(runtime.handle(), "socks5://proxy:1080", credentials).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Hosts a pacer remembers before it forgets the ones whose next slot has already passed.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// How the gap between two requests is drawn from `[min_delay, max_delay]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// Every gap in the range is equally likely.
+    Uniform,
+    /// Gaps cluster around the middle of the range (a normal distribution clamped to it).
+    Normal,
+    /// Short gaps are common and long ones rare, like the pauses of a person reading.
+    Exponential,
+}
+
+impl Jitter {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "uniform" => Some(Self::Uniform),
+            "normal" => Some(Self::Normal),
+            "exponential" => Some(Self::Exponential),
+            _ => None,
+        }
+    }
+}
+
+/// Gaps a session keeps between the starts of consecutive requests to the same host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pacing {
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Jitter,
+}
+
+impl Pacing {
+    /// The gap for two uniform samples `u1` and `u2` in `[0, 1)`.
+    fn gap(&self, u1: f64, u2: f64) -> Duration {
+        let min = self.min_delay.as_secs_f64();
+        let range = self.max_delay.saturating_sub(self.min_delay).as_secs_f64();
+        let offset = match self.jitter {
+            Jitter::Uniform => u1 * range,
+            Jitter::Normal => {
+                // Box-Muller, with the range spanning three standard deviations either side.
+                let z = (-2.0 * (1.0 - u1).ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                range / 2.0 + z * range / 6.0
+            }
+            // A mean of a third of the range leaves about 5% of gaps clamped to the maximum.
+            Jitter::Exponential => -(1.0 - u1).ln() * range / 3.0,
+        };
+        Duration::from_secs_f64(min + offset.clamp(0.0, range))
+    }
+}
+
+/// Spaces out one session's requests per host.
+#[derive(Debug)]
+pub struct Pacer {
+    pacing: Pacing,
+    next_start: Mutex<HashMap<String, Instant>>,
+}
+
+impl Pacer {
+    pub fn new(pacing: Pacing) -> Self {
+        Self {
+            pacing,
+            next_start: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for the turn of a request to `host`, and draw the gap before the one after it.
+    pub async fn wait(&self, host: &str) {
+        let start = {
+            let mut next_start = self
+                .next_start
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let now = Instant::now();
+            if next_start.len() >= PRUNE_THRESHOLD {
+                next_start.retain(|_, start| *start > now);
+            }
+            let start = next_start.get(host).map_or(now, |next| (*next).max(now));
+            let gap = self.pacing.gap(rand::random(), rand::random());
+            next_start.insert(host.to_owned(), start + gap);
+            start
+        };
+
+        tokio::time::sleep_until(start).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pacing(jitter: Jitter) -> Pacing {
+        Pacing {
+            min_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(700),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn keeps_every_gap_in_range() {
+        for jitter in [Jitter::Uniform, Jitter::Normal, Jitter::Exponential] {
+            for u1 in [0.0, 0.25, 0.5, 0.999_999] {
+                for u2 in [0.0, 0.5, 0.999_999] {
+                    let gap = pacing(jitter).gap(u1, u2);
+                    assert!(
+                        (Duration::from_millis(100)..=Duration::from_millis(700)).contains(&gap)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_gaps_by_distribution() {
+        assert_eq!(
+            pacing(Jitter::Uniform).gap(0.5, 0.0),
+            Duration::from_millis(400)
+        );
+        // cos(TAU / 4) is zero, so the sample lands on the mean.
+        let middle = pacing(Jitter::Normal).gap(0.5, 0.25);
+        assert!(middle.abs_diff(Duration::from_millis(400)) < Duration::from_millis(1));
+        assert_eq!(
+            pacing(Jitter::Exponential).gap(0.0, 0.0),
+            Duration::from_millis(100)
+        );
+
+        let fixed = Pacing {
+            max_delay: Duration::from_millis(100),
+            ..pacing(Jitter::Normal)
+        };
+        assert_eq!(fixed.gap(0.9, 0.1), Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn spaces_requests_to_the_same_host() {
+        let gap = Duration::from_millis(50);
+        let pacer = Pacer::new(Pacing {
+            min_delay: gap,
+            max_delay: gap,
+            jitter: Jitter::Uniform,
+        });
+        let started = Instant::now();
+        pacer.wait("example.com").await;
+        pacer.wait("other.example").await;
+        assert!(started.elapsed() < gap);
+        pacer.wait("example.com").await;
+        pacer.wait("example.com").await;
+        assert!(started.elapsed() >= gap * 2);
+    }
+}
@@ -0,0 +1,365 @@
+use anyhow::Error;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::deadline::UploadProgress;
+use crate::dns::DnsError;
+
+/// The part of a request that was running when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Dns,
+    /// Opening the connection: TCP, proxy tunnels and the TLS handshake when they cannot be
+    /// told apart (e.g. on a timeout).
+    Connect,
+    Tls,
+    RequestWrite,
+    /// Waiting for the response headers once the request is on the wire.
+    Ttfb,
+    BodyRead,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Dns => "dns",
+            Phase::Connect => "connect",
+            Phase::Tls => "tls",
+            Phase::RequestWrite => "request-write",
+            Phase::Ttfb => "ttfb",
+            Phase::BodyRead => "body-read",
+        }
+    }
+}
+
+/// Outermost context of a failed request's error: where it failed and how long it had spent
+/// in that phase.
+#[derive(Debug)]
+pub struct PhaseFailure {
+    request: String,
+    pub phase: Phase,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for PhaseFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} failed during {} after {} ms",
+            self.request,
+            self.phase.as_str(),
+            self.elapsed.as_millis()
+        )
+    }
+}
+
+tokio::task_local! {
+    // Connection timing of the request driving the current task, set while it is being sent.
+    static CONNECT_TIMES: ConnectTimes;
+}
+
+#[derive(Debug, Clone, Default)]
+struct ConnectTimes(Arc<Mutex<ConnectState>>);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ConnectState {
+    started: Option<Instant>,
+    ready: Option<Instant>,
+    /// New connections the request has dialed so far.
+    dialed: u32,
+}
+
+impl ConnectTimes {
+    fn update(&self, update: impl FnOnce(&mut ConnectState)) {
+        update(
+            &mut self
+                .0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+    }
+
+    fn snapshot(&self) -> ConnectState {
+        *self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Follows one request through its phases so a failure can say where it happened.
+pub struct RequestPhases {
+    request: String,
+    start: Instant,
+    connect: ConnectTimes,
+}
+
+impl RequestPhases {
+    /// `request` is the request line (`GET https://...`) used in error messages.
+    pub fn new(request: String) -> Self {
+        Self {
+            request,
+            start: Instant::now(),
+            connect: ConnectTimes::default(),
+        }
+    }
+
+    /// Send the request with connection timing recorded by [`ConnectTimingLayer`]. Pooled
+    /// connections record nothing.
+    pub async fn track<F: Future>(&self, send: F) -> F::Output {
+        CONNECT_TIMES.scope(self.connect.clone(), send).await
+    }
+
+    /// How many new connections the request has dialed. A send that leaves this unchanged went
+    /// out on a pooled connection.
+    pub fn dialed(&self) -> u32 {
+        self.connect.snapshot().dialed
+    }
+
+    /// Attach the failed phase to an error raised before the response headers arrived.
+    pub fn send_failed(&self, error: Error, upload: Option<&UploadProgress>) -> Error {
+        let connect = self.connect.snapshot();
+        let connect_start = connect.started.unwrap_or(self.start);
+        let upload = upload.and_then(UploadProgress::span);
+
+        // The timing layer knows whether a connection was being opened; the error's causes
+        // only say which part of opening it failed.
+        let connecting = connect.started.is_some() && connect.ready.is_none();
+        let (phase, since) = if connecting || is_connect_error(&error) {
+            (connection_phase(&error), connect_start)
+        } else {
+            match upload {
+                Some((started, None)) => (Phase::RequestWrite, started),
+                Some((_, Some(finished))) => (Phase::Ttfb, finished),
+                None => {
+                    let sent = connect.ready.unwrap_or(self.start);
+                    if is_write_error(&error) {
+                        (Phase::RequestWrite, sent)
+                    } else {
+                        (Phase::Ttfb, sent)
+                    }
+                }
+            }
+        };
+
+        self.fail(error, phase, since)
+    }
+
+    /// The response headers arrived; what is left is reading the body.
+    pub fn body(self) -> BodyPhase {
+        BodyPhase {
+            request: self.request,
+            since: Instant::now(),
+        }
+    }
+
+    fn fail(&self, error: Error, phase: Phase, since: Instant) -> Error {
+        error.context(PhaseFailure {
+            request: self.request.clone(),
+            phase,
+            elapsed: since.elapsed(),
+        })
+    }
+}
+
+/// Body-read phase of a request whose response headers have arrived.
+#[derive(Debug, Clone)]
+pub struct BodyPhase {
+    request: String,
+    since: Instant,
+}
+
+impl BodyPhase {
+    pub fn failed(&self, error: Error) -> Error {
+        error.context(PhaseFailure {
+            request: self.request.clone(),
+            phase: Phase::BodyRead,
+            elapsed: self.since.elapsed(),
+        })
+    }
+}
+
+fn is_connect_error(error: &Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<wreq::Error>()
+            .is_some_and(|error| error.is_connect() || error.is_proxy_connect())
+    })
+}
+
+// Which part of opening a connection failed: the resolver and BoringSSL raise their own types.
+fn connection_phase(error: &Error) -> Phase {
+    for cause in error.chain() {
+        if cause.is::<DnsError>() {
+            return Phase::Dns;
+        }
+        if cause.is::<boring2::ssl::Error>() || cause.is::<boring2::error::ErrorStack>() {
+            return Phase::Tls;
+        }
+    }
+    Phase::Connect
+}
+
+// Writing to a connection the peer has closed fails with EPIPE; reads see a reset or EOF.
+fn is_write_error(error: &Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|error| error.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Connector layer that reports when new connections start and finish to the request
+/// that asked for them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectTimingLayer;
+
+impl<S> Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectTiming<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for ConnectTiming<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        // Connections are started from the task sending the request, so its timing is in scope.
+        let times = CONNECT_TIMES.try_with(ConnectTimes::clone).ok();
+        if let Some(times) = &times {
+            times.update(|state| {
+                *state = ConnectState {
+                    started: Some(Instant::now()),
+                    ready: None,
+                    dialed: state.dialed + 1,
+                }
+            });
+        }
+
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let result = connecting.await;
+            if let Some(times) = times.filter(|_| result.is_ok()) {
+                times.update(|state| state.ready = Some(Instant::now()));
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectTimingLayer, Phase, PhaseFailure, RequestPhases};
+    use crate::dns::DnsError;
+    use anyhow::anyhow;
+    use std::future::{Ready, ready};
+    use std::io;
+    use std::task::{Context, Poll};
+    use tokio::time::Instant;
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    struct Dial;
+
+    impl Service<()> for Dial {
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ready(Ok(()))
+        }
+    }
+
+    fn phase_of(error: &anyhow::Error) -> Phase {
+        error.downcast_ref::<PhaseFailure>().unwrap().phase
+    }
+
+    #[tokio::test]
+    async fn classifies_by_connection_progress() {
+        let phases = RequestPhases::new("GET http://example.com/".into());
+        let error = phases.send_failed(anyhow!("operation timed out"), None);
+        assert_eq!(phase_of(&error), Phase::Ttfb);
+        assert!(
+            error
+                .to_string()
+                .starts_with("GET http://example.com/ failed during ttfb after")
+        );
+
+        phases
+            .connect
+            .update(|state| state.started = Some(Instant::now()));
+        let error = phases.send_failed(anyhow!("operation timed out"), None);
+        assert_eq!(phase_of(&error), Phase::Connect);
+
+        let lookup = io::Error::new(io::ErrorKind::NotFound, "no such host");
+        let error = phases.send_failed(DnsError::new("example.com".into(), lookup).into(), None);
+        assert_eq!(phase_of(&error), Phase::Dns);
+
+        let error = phases.body().failed(anyhow!("connection reset"));
+        assert_eq!(phase_of(&error), Phase::BodyRead);
+    }
+
+    #[tokio::test]
+    async fn classifies_by_error_type_not_text() {
+        let phases = RequestPhases::new("POST http://example.com/".into());
+        phases.connect.update(|state| {
+            state.started = Some(Instant::now());
+            state.ready = Some(Instant::now());
+        });
+
+        // A message that only mentions TLS or DNS, e.g. from a proxy, says nothing about the phase.
+        let error = phases.send_failed(anyhow!("proxy answered: tls dns error"), None);
+        assert_eq!(phase_of(&error), Phase::Ttfb);
+
+        let closed = io::Error::from(io::ErrorKind::BrokenPipe);
+        let error = phases.send_failed(anyhow::Error::new(closed).context("sending body"), None);
+        assert_eq!(phase_of(&error), Phase::RequestWrite);
+    }
+
+    #[tokio::test]
+    async fn counts_the_connections_a_request_dials() {
+        let phases = RequestPhases::new("GET http://example.com/".into());
+        let mut connector = ConnectTimingLayer.layer(Dial);
+
+        phases
+            .track(async { connector.call(()).await })
+            .await
+            .unwrap();
+        assert_eq!(phases.dialed(), 1);
+
+        // Connections dialed outside the request's task are not counted.
+        connector.call(()).await.unwrap();
+        assert_eq!(phases.dialed(), 1);
+    }
+}
@@ -0,0 +1,44 @@
+use wreq::header::HeaderValue;
+
+/// Lowest urgency RFC 9218 defines; 0 is the highest.
+pub const MAX_URGENCY: u8 = 7;
+
+/// Extensible priority parameters (RFC 9218) sent in a request's `Priority` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority {
+    pub urgency: u8,
+    /// Whether the response can be processed as it arrives, as for images.
+    pub incremental: bool,
+}
+
+impl Priority {
+    /// The `Priority` value in the form browsers send, e.g. `u=0, i`.
+    pub fn header_value(&self) -> HeaderValue {
+        let value = if self.incremental {
+            format!("u={}, i", self.urgency)
+        } else {
+            format!("u={}", self.urgency)
+        };
+        HeaderValue::from_str(&value).expect("priority parameters are valid header text")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Priority;
+
+    #[test]
+    fn formats_parameters_like_browsers() {
+        let navigation = Priority {
+            urgency: 0,
+            incremental: true,
+        };
+        assert_eq!(navigation.header_value(), "u=0, i");
+
+        let script = Priority {
+            urgency: 1,
+            incremental: false,
+        };
+        assert_eq!(script.header_value(), "u=1");
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use wreq_util::{Emulation, EmulationOS};
+
+use crate::client::ensure_accepting;
+use crate::tls_socket::{Io, ProxyEndpoint, handshake, profile_tls_options, tunnel};
+
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const H2_FRAME_SETTINGS: u8 = 0x4;
+const H2_FLAG_ACK: u8 = 0x1;
+// Frames read while waiting for the server's SETTINGS before giving up.
+const H2_MAX_FRAMES: usize = 8;
+// Larger than the default SETTINGS_MAX_FRAME_SIZE, so a well-behaved server never exceeds it.
+const H2_MAX_FRAME_PAYLOAD: usize = 16 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ProbeOptions {
+    pub url: String,
+    pub emulation: Emulation,
+    pub emulation_os: EmulationOS,
+    pub proxy: Option<String>,
+    pub insecure: bool,
+    /// Also send the HTTP/2 connection preface when the server negotiates h2.
+    pub http2: bool,
+    /// Budget for the whole probe, in milliseconds (0 means none).
+    pub timeout: u64,
+}
+
+/// Time spent in each phase of a probe. Phases that did not run are `None`.
+#[derive(Debug, Clone, Default)]
+pub struct ProbeTimings {
+    pub dns: Duration,
+    pub connect: Duration,
+    pub proxy: Option<Duration>,
+    pub tls: Option<Duration>,
+    pub h2: Option<Duration>,
+    pub total: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProbeResult {
+    pub timings: ProbeTimings,
+    pub local_addr: Option<SocketAddr>,
+    /// Address of the first hop: the origin, or the proxy when one is used.
+    pub remote_addr: Option<SocketAddr>,
+    pub alpn_protocol: Option<String>,
+    pub tls_version: Option<String>,
+    pub cipher: Option<String>,
+    /// Server `SETTINGS` as (identifier, value) pairs, when the h2 preface was exchanged.
+    pub h2_settings: Option<Vec<(u16, u32)>>,
+}
+
+/// Connect to the origin of `options.url` the way a request would, without sending one:
+/// DNS, TCP, the proxy tunnel, the emulated TLS handshake and optionally the h2 preface.
+pub async fn probe(options: ProbeOptions) -> Result<ProbeResult> {
+    ensure_accepting()?;
+
+    match options.timeout {
+        0 => run_probe(options).await,
+        timeout => {
+            let url = options.url.clone();
+            tokio::time::timeout(Duration::from_millis(timeout), run_probe(options))
+                .await
+                .map_err(|_| anyhow!("Timed out probing {}", url))?
+        }
+    }
+}
+
+async fn run_probe(options: ProbeOptions) -> Result<ProbeResult> {
+    let url = url::Url::parse(&options.url).context("Invalid probe URL")?;
+    let secure = match url.scheme() {
+        "https" => true,
+        "http" => false,
+        other => bail!("Unsupported probe scheme: {}", other),
+    };
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("Probe URL is missing a host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_owned();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("Probe URL is missing a port"))?;
+
+    let proxy = options
+        .proxy
+        .as_deref()
+        .map(ProxyEndpoint::parse)
+        .transpose()?;
+    let (first_host, first_port) = match &proxy {
+        Some(proxy) => (proxy.host.as_str(), proxy.port),
+        None => (host.as_str(), port),
+    };
+
+    let mut result = ProbeResult::default();
+    let started = Instant::now();
+
+    let mut phase = Instant::now();
+    let addr = tokio::net::lookup_host((first_host, first_port))
+        .await
+        .with_context(|| format!("Failed to resolve {}", first_host))?
+        .next()
+        .ok_or_else(|| anyhow!("Failed to resolve {}", first_host))?;
+    result.timings.dns = phase.elapsed();
+
+    phase = Instant::now();
+    let tcp = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("Failed to connect to {}", addr))?;
+    result.timings.connect = phase.elapsed();
+    result.local_addr = tcp.local_addr().ok();
+    result.remote_addr = tcp.peer_addr().ok();
+
+    let io: Box<dyn Io> = match &proxy {
+        Some(proxy) => {
+            phase = Instant::now();
+            let io = tunnel(tcp, proxy, &host, port).await?;
+            result.timings.proxy = Some(phase.elapsed());
+            io
+        }
+        None => Box::new(tcp),
+    };
+
+    if secure {
+        phase = Instant::now();
+        let tls = profile_tls_options(options.emulation, options.emulation_os);
+        let mut stream = handshake(&tls, options.insecure, io, &host, port).await?;
+        result.timings.tls = Some(phase.elapsed());
+
+        let ssl = stream.ssl();
+        result.alpn_protocol = ssl
+            .selected_alpn_protocol()
+            .map(|protocol| String::from_utf8_lossy(protocol).into_owned());
+        result.tls_version = Some(ssl.version_str().to_owned());
+        result.cipher = ssl.current_cipher().map(|cipher| cipher.name().to_owned());
+
+        if options.http2 && result.alpn_protocol.as_deref() == Some("h2") {
+            phase = Instant::now();
+            result.h2_settings = Some(exchange_h2_preface(&mut stream).await?);
+            result.timings.h2 = Some(phase.elapsed());
+        }
+    }
+
+    result.timings.total = started.elapsed();
+    Ok(result)
+}
+
+/// Send the client preface with an empty SETTINGS frame and wait for the server's SETTINGS.
+async fn exchange_h2_preface<S>(stream: &mut S) -> Result<Vec<(u16, u32)>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    stream.write_all(H2_PREFACE).await?;
+    stream
+        .write_all(&[0, 0, 0, H2_FRAME_SETTINGS, 0, 0, 0, 0, 0])
+        .await?;
+    stream.flush().await?;
+
+    for _ in 0..H2_MAX_FRAMES {
+        let mut header = [0u8; 9];
+        stream
+            .read_exact(&mut header)
+            .await
+            .context("Server closed the connection during the HTTP/2 preface")?;
+        let length =
+            (usize::from(header[0]) << 16) | (usize::from(header[1]) << 8) | usize::from(header[2]);
+        if length > H2_MAX_FRAME_PAYLOAD {
+            bail!("HTTP/2 frame of {} bytes exceeds the preface limit", length);
+        }
+        let mut payload = vec![0u8; length];
+        stream.read_exact(&mut payload).await?;
+
+        if header[3] == H2_FRAME_SETTINGS && header[4] & H2_FLAG_ACK == 0 {
+            return Ok(parse_h2_settings(&payload));
+        }
+    }
+    bail!("Server did not send HTTP/2 SETTINGS")
+}
+
+fn parse_h2_settings(payload: &[u8]) -> Vec<(u16, u32)> {
+    payload
+        .chunks_exact(6)
+        .map(|entry| {
+            (
+                u16::from_be_bytes([entry[0], entry[1]]),
+                u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_h2_settings;
+
+    #[test]
+    fn parses_server_settings_entries() {
+        let payload = [
+            0x00, 0x03, 0x00, 0x00, 0x00, 0x64, // MAX_CONCURRENT_STREAMS = 100
+            0x00, 0x04, 0x00, 0x01, 0x00, 0x00, // INITIAL_WINDOW_SIZE = 65536
+            0x00, // trailing partial entry is ignored
+        ];
+        assert_eq!(parse_h2_settings(&payload), vec![(3, 100), (4, 65536)]);
+    }
+}
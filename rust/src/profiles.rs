@@ -0,0 +1,155 @@
+use crate::generated_profiles::{BROWSER_PROFILES, OPERATING_SYSTEMS};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use wreq_util::{Emulation, EmulationOS};
+
+/// Firefox majors released as ESR. `firefox:esr` picks the newest one wreq-util ships.
+const FIREFOX_ESR_MAJORS: &[u32] = &[91, 102, 115, 128, 140, 153];
+
+/// Resolve a browser profile or profile alias to a profile label.
+///
+/// A family (`chrome`, `safari_ios`, `firefox_private`) or `<family>:latest` resolves to the
+/// family's newest profile, and `firefox:esr` to the newest Firefox ESR. Profile labels resolve
+/// to themselves.
+pub fn resolve_profile(name: &str) -> Option<&'static str> {
+    if let Some(label) = BROWSER_PROFILES
+        .iter()
+        .copied()
+        .find(|label| *label == name)
+    {
+        return Some(label);
+    }
+
+    let (family, channel) = name.split_once(':').unwrap_or((name, "latest"));
+    let mut candidates = BROWSER_PROFILES
+        .iter()
+        .filter_map(|label| {
+            let (label_family, version) = split_label(label)?;
+            (label_family == family).then_some((version, *label))
+        })
+        .collect::<Vec<_>>();
+
+    match channel {
+        "latest" => {}
+        "esr" if family == "firefox" => candidates.retain(|(version, _)| {
+            version
+                .first()
+                .is_some_and(|major| FIREFOX_ESR_MAJORS.contains(major))
+        }),
+        _ => return None,
+    }
+    candidates.into_iter().max().map(|(_, label)| label)
+}
+
+// Profiles wreq-util can deserialize, built once up-front.
+static EMULATIONS: LazyLock<HashMap<&'static str, Emulation>> = LazyLock::new(|| {
+    BROWSER_PROFILES
+        .iter()
+        .filter_map(|label| {
+            serde_json::from_value::<Emulation>(serde_json::Value::String((*label).to_string()))
+                .ok()
+                .map(|emulation| (*label, emulation))
+        })
+        .collect()
+});
+
+static OPERATING_SYSTEM_EMULATIONS: LazyLock<HashMap<&'static str, EmulationOS>> =
+    LazyLock::new(|| {
+        OPERATING_SYSTEMS
+            .iter()
+            .filter_map(|label| {
+                serde_json::from_value::<EmulationOS>(serde_json::Value::String(
+                    (*label).to_string(),
+                ))
+                .ok()
+                .map(|emulation_os| (*label, emulation_os))
+            })
+            .collect()
+    });
+
+/// The emulation for a browser profile or profile alias.
+pub fn emulation(name: &str) -> Option<Emulation> {
+    resolve_profile(name).and_then(|label| EMULATIONS.get(label).copied())
+}
+
+/// The emulation for an operating system label such as `windows`.
+pub fn emulation_os(name: &str) -> Option<EmulationOS> {
+    OPERATING_SYSTEM_EMULATIONS.get(name).copied()
+}
+
+/// Browser profiles [`emulation`] accepts, in generated order.
+pub fn supported_profiles() -> Vec<&'static str> {
+    BROWSER_PROFILES
+        .iter()
+        .filter(|label| EMULATIONS.contains_key(*label))
+        .copied()
+        .collect()
+}
+
+/// Operating systems [`emulation_os`] accepts, in generated order.
+pub fn supported_operating_systems() -> Vec<&'static str> {
+    OPERATING_SYSTEMS
+        .iter()
+        .filter(|label| OPERATING_SYSTEM_EMULATIONS.contains_key(*label))
+        .copied()
+        .collect()
+}
+
+// `safari_ios_17.4.1` is the `safari_ios` family at version 17.4.1.
+fn split_label(label: &str) -> Option<(&str, Vec<u32>)> {
+    let (family, version) = label.rsplit_once('_')?;
+    let version = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    Some((family, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BROWSER_PROFILES, resolve_profile, split_label};
+
+    fn newest(family: &str) -> &'static str {
+        BROWSER_PROFILES
+            .iter()
+            .filter_map(|label| {
+                split_label(label)
+                    .filter(|(f, _)| *f == family)
+                    .map(|(_, v)| (v, *label))
+            })
+            .max()
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn families_resolve_to_their_newest_profile() {
+        for family in ["chrome", "firefox", "safari", "safari_ios", "okhttp"] {
+            assert_eq!(resolve_profile(family), Some(newest(family)), "{family}");
+            assert_eq!(
+                resolve_profile(&format!("{family}:latest")),
+                Some(newest(family)),
+                "{family}"
+            );
+        }
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(split_label("safari_17.4.1").unwrap().1 > split_label("safari_17.2.1").unwrap().1);
+        assert!(split_label("okhttp_3.14").unwrap().1 > split_label("okhttp_3.9").unwrap().1);
+        assert_eq!(split_label("safari_ios_26").unwrap().0, "safari_ios");
+    }
+
+    #[test]
+    fn resolves_labels_esr_and_rejects_unknown_aliases() {
+        assert_eq!(resolve_profile("chrome_142"), Some("chrome_142"));
+
+        let esr = resolve_profile("firefox:esr").unwrap();
+        assert!(esr.starts_with("firefox_"), "{esr}");
+
+        assert_eq!(resolve_profile("chrome:esr"), None);
+        assert_eq!(resolve_profile("chrome:beta"), None);
+        assert_eq!(resolve_profile("netscape"), None);
+    }
+}
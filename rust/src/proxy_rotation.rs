@@ -0,0 +1,130 @@
+use std::sync::{Arc, Mutex};
+
+/// When a transport's proxy pool moves on to the next proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationStrategy {
+    /// Keep the current proxy until a request through it fails.
+    StickyUntilError,
+    /// Move on after this many requests.
+    EveryNRequests(u32),
+}
+
+/// Proxies a transport rotates through, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyPool {
+    pub proxies: Vec<Arc<str>>,
+    pub strategy: RotationStrategy,
+}
+
+#[derive(Debug, Default)]
+struct RotationState {
+    index: usize,
+    /// Requests sent through the current proxy.
+    served: u32,
+}
+
+/// Rotation state for a proxy pool, with one member (a client) per proxy.
+pub struct ProxyRotation<T> {
+    members: Vec<(Arc<str>, T)>,
+    strategy: RotationStrategy,
+    state: Mutex<RotationState>,
+}
+
+impl<T> ProxyRotation<T> {
+    /// `members` must not be empty.
+    pub fn new(members: Vec<(Arc<str>, T)>, strategy: RotationStrategy) -> Self {
+        debug_assert!(!members.is_empty(), "proxy pool must not be empty");
+        Self {
+            members,
+            strategy,
+            state: Mutex::new(RotationState::default()),
+        }
+    }
+
+    /// Replace the members while keeping the position in the rotation, e.g. after rebuilding
+    /// every client.
+    pub fn with_members<U>(&self, members: Vec<(Arc<str>, U)>) -> ProxyRotation<U> {
+        let state = self.lock();
+        ProxyRotation {
+            members,
+            strategy: self.strategy,
+            state: Mutex::new(RotationState {
+                index: state.index,
+                served: state.served,
+            }),
+        }
+    }
+
+    /// Pick the member for the next request and count it against the current proxy.
+    pub fn select(&self) -> (usize, &T) {
+        let mut state = self.lock();
+        if matches!(self.strategy, RotationStrategy::EveryNRequests(limit) if state.served >= limit)
+        {
+            state.index = (state.index + 1) % self.members.len();
+            state.served = 0;
+        }
+        state.served += 1;
+        (state.index, &self.members[state.index].1)
+    }
+
+    /// The proxy and member in use, without counting a request.
+    pub fn current(&self) -> (&str, &T) {
+        let (proxy, member) = &self.members[self.lock().index];
+        (proxy, member)
+    }
+
+    /// Record that a request sent through `index` failed. Sticky pools move to the next proxy
+    /// unless a concurrent failure already did.
+    pub fn report_failure(&self, index: usize) {
+        if self.strategy != RotationStrategy::StickyUntilError {
+            return;
+        }
+        let mut state = self.lock();
+        if state.index == index {
+            state.index = (state.index + 1) % self.members.len();
+            state.served = 0;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RotationState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProxyRotation, RotationStrategy};
+    use std::sync::Arc;
+
+    fn members() -> Vec<(Arc<str>, &'static str)> {
+        ["http://a:1", "http://b:1", "http://c:1"]
+            .into_iter()
+            .map(|proxy| (Arc::from(proxy), proxy))
+            .collect()
+    }
+
+    #[test]
+    fn sticky_pool_moves_on_only_after_a_failure() {
+        let rotation = ProxyRotation::new(members(), RotationStrategy::StickyUntilError);
+        assert_eq!(rotation.select(), (0, &"http://a:1"));
+        assert_eq!(rotation.select(), (0, &"http://a:1"));
+
+        rotation.report_failure(0);
+        // A second failure from a request that was already in flight does not skip a proxy.
+        rotation.report_failure(0);
+        assert_eq!(rotation.select(), (1, &"http://b:1"));
+        assert_eq!(rotation.current().0, "http://b:1");
+    }
+
+    #[test]
+    fn counted_pool_rotates_every_n_requests() {
+        let rotation = ProxyRotation::new(members(), RotationStrategy::EveryNRequests(2));
+        let picked: Vec<usize> = (0..7).map(|_| rotation.select().0).collect();
+        assert_eq!(picked, vec![0, 0, 1, 1, 2, 2, 0]);
+
+        rotation.report_failure(0);
+        assert_eq!(rotation.select().0, 0);
+    }
+}
@@ -0,0 +1,86 @@
+use wreq::StatusCode;
+
+/// The reason phrase an HTTP/1 server sent in place of the canonical one for the status, such as
+/// `Quota Exceeded` from `HTTP/1.1 429 Quota Exceeded`.
+///
+/// wreq keeps a non-canonical phrase in the response extensions but does not export its type. The
+/// only public way to it is the status error, whose message embeds the phrase escaped, so this
+/// covers 4xx and 5xx responses: the ones legacy APIs put error text in.
+pub fn reason_phrase(response: &wreq::Response) -> Option<String> {
+    let error = response.error_for_status_ref().err()?;
+    parse_status_error(
+        &error.to_string(),
+        response.status(),
+        &response.uri().to_string(),
+    )
+}
+
+fn parse_status_error(message: &str, status: StatusCode, uri: &str) -> Option<String> {
+    let kind = if status.is_client_error() {
+        "client"
+    } else {
+        "server"
+    };
+    // Without a phrase of its own, the message names the status with its canonical reason.
+    if message == format!("HTTP status {kind} error ({status}) for uri ({uri})") {
+        return None;
+    }
+    let escaped = message
+        .strip_prefix(&format!("HTTP status {kind} error ({} ", status.as_str()))?
+        .strip_suffix(&format!(") for uri ({uri})"))?;
+    Some(unescape(escaped))
+}
+
+/// Undo wreq's byte escaping. Bytes above ASCII (obs-text) are read as Latin-1, as Node does.
+fn unescape(escaped: &str) -> String {
+    let mut phrase = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            phrase.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => phrase.push('\n'),
+            Some('r') => phrase.push('\r'),
+            Some('t') => phrase.push('\t'),
+            Some('0') => phrase.push('\0'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                    phrase.push(char::from(byte));
+                }
+            }
+            Some(other) => phrase.push(other),
+            None => phrase.push('\\'),
+        }
+    }
+    phrase
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_custom_phrases_from_status_errors() {
+        let uri = "http://example.com/a";
+        let status = StatusCode::TOO_MANY_REQUESTS;
+        let message = format!(
+            "HTTP status client error (429 Quota \\\"daily\\\" exceeded \\xe9) for uri ({uri})"
+        );
+        assert_eq!(
+            parse_status_error(&message, status, uri).as_deref(),
+            Some("Quota \"daily\" exceeded é")
+        );
+
+        let canonical = format!("HTTP status client error ({status}) for uri ({uri})");
+        assert_eq!(parse_status_error(&canonical, status, uri), None);
+
+        let server = format!("HTTP status server error (503 Back (soon)) for uri ({uri})");
+        assert_eq!(
+            parse_status_error(&server, StatusCode::SERVICE_UNAVAILABLE, uri).as_deref(),
+            Some("Back (soon)")
+        );
+    }
+}
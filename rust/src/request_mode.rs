@@ -0,0 +1,318 @@
+use url::Url;
+use wreq::header::{HeaderMap, HeaderValue, ORIGIN, REFERER};
+
+/// The kind of request a page makes, which decides the `Sec-Fetch-*`, `Accept` and `Priority`
+/// headers a browser sends with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestMode {
+    /// A top-level navigation, as from the address bar or a link.
+    Navigate,
+    /// An `XMLHttpRequest`. Browsers send it with the same headers as `fetch()`.
+    Xhr,
+    /// A `fetch()` call with its default `cors` mode.
+    Fetch,
+    /// An `<img>` load.
+    Image,
+}
+
+#[derive(Clone, Copy)]
+enum Family {
+    Chromium,
+    Firefox,
+    Safari,
+}
+
+impl RequestMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "navigate" => Some(Self::Navigate),
+            "xhr" => Some(Self::Xhr),
+            "fetch" => Some(Self::Fetch),
+            "image" => Some(Self::Image),
+            _ => None,
+        }
+    }
+
+    /// Rewrite the profile's `defaults` into the headers the browser sends for this kind of
+    /// request to `url`. The page making the request is taken from the caller's `Referer` or
+    /// `Origin` header in `headers`; without one, navigations are treated as typed into the
+    /// address bar and other requests as coming from a page on the target's own origin.
+    /// Profiles that send no `Sec-Fetch-*` headers are left alone.
+    pub fn apply(self, defaults: &mut HeaderMap, url: &str, headers: &[(String, HeaderValue)]) {
+        if !defaults.contains_key("sec-fetch-mode") {
+            return;
+        }
+        let Ok(target) = Url::parse(url) else {
+            return;
+        };
+        let initiator = [REFERER, ORIGIN].into_iter().find_map(|name| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name.as_str()))
+                .and_then(|(_, value)| value.to_str().ok())
+                .and_then(|value| Url::parse(value).ok())
+        });
+        let site = match &initiator {
+            Some(initiator) => fetch_site(initiator, &target),
+            None if self == Self::Navigate => "none",
+            None => "same-origin",
+        };
+        let family = family(defaults);
+
+        let set = |defaults: &mut HeaderMap, name: &'static str, value: &'static str| {
+            defaults.insert(name, HeaderValue::from_static(value));
+        };
+        set(defaults, "sec-fetch-site", site);
+        match self {
+            Self::Navigate => {
+                // Profiles already send a navigation's Accept and Priority, but not the header
+                // for a navigation the user started.
+                set(defaults, "sec-fetch-mode", "navigate");
+                set(defaults, "sec-fetch-dest", "document");
+                *defaults = rebuilt(defaults, &[], true);
+                return;
+            }
+            Self::Xhr | Self::Fetch => {
+                set(defaults, "sec-fetch-mode", "cors");
+                set(defaults, "sec-fetch-dest", "empty");
+                set(defaults, "accept", "*/*");
+            }
+            Self::Image => {
+                set(defaults, "sec-fetch-mode", "no-cors");
+                set(defaults, "sec-fetch-dest", "image");
+                set(defaults, "accept", image_accept(family));
+            }
+        }
+        if defaults.contains_key("priority") {
+            set(defaults, "priority", subresource_priority(self, family));
+        }
+        *defaults = rebuilt(
+            defaults,
+            &[
+                "sec-fetch-user",
+                "upgrade-insecure-requests",
+                "cache-control",
+                "pragma",
+            ],
+            false,
+        );
+
+        // A cross-origin CORS request names the page's origin.
+        let cors = matches!(self, Self::Xhr | Self::Fetch) && site != "same-origin";
+        if let Some(initiator) = initiator.filter(|_| cors) {
+            if let Ok(origin) = HeaderValue::from_str(&initiator.origin().ascii_serialization()) {
+                defaults.insert(ORIGIN, origin);
+            }
+        }
+    }
+}
+
+/// `defaults` in order without the headers in `dropped`, and with `Sec-Fetch-User: ?1` after
+/// `Sec-Fetch-Mode` when `user` is set. `HeaderMap::remove` would move the last header into the
+/// gap instead.
+fn rebuilt(defaults: &HeaderMap, dropped: &[&str], user: bool) -> HeaderMap {
+    let mut headers = HeaderMap::with_capacity(defaults.len() + 1);
+    for (name, value) in defaults.iter() {
+        if dropped.contains(&name.as_str()) || (user && name == "sec-fetch-user") {
+            continue;
+        }
+        headers.append(name.clone(), value.clone());
+        if user && name == "sec-fetch-mode" {
+            headers.insert("sec-fetch-user", HeaderValue::from_static("?1"));
+        }
+    }
+    headers
+}
+
+fn family(defaults: &HeaderMap) -> Family {
+    let agent = defaults
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if agent.contains("Firefox/") {
+        Family::Firefox
+    } else if agent.contains("Safari/") && !agent.contains("Chrome/") {
+        Family::Safari
+    } else {
+        Family::Chromium
+    }
+}
+
+fn image_accept(family: Family) -> &'static str {
+    match family {
+        Family::Chromium => "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8",
+        Family::Firefox => "image/avif,image/webp,image/png,image/svg+xml,image/*;q=0.8,*/*;q=0.5",
+        Family::Safari => concat!(
+            "image/webp,image/avif,image/jxl,image/heic,image/heic-sequence,video/*;q=0.8,",
+            "image/png,image/svg+xml,image/*;q=0.8,*/*;q=0.5"
+        ),
+    }
+}
+
+fn subresource_priority(mode: RequestMode, family: Family) -> &'static str {
+    match (mode, family) {
+        (RequestMode::Image, Family::Chromium) => "i",
+        (RequestMode::Image, _) => "u=5, i",
+        (_, Family::Chromium) => "u=1, i",
+        (_, Family::Firefox) => "u=4",
+        (_, Family::Safari) => "u=3, i",
+    }
+}
+
+/// `Sec-Fetch-Site` for a request from a page at `initiator` to `target`.
+fn fetch_site(initiator: &Url, target: &Url) -> &'static str {
+    let same_site = initiator.scheme() == target.scheme()
+        && initiator
+            .host_str()
+            .zip(target.host_str())
+            .is_some_and(|(from, to)| site_of(from).eq_ignore_ascii_case(site_of(to)));
+    if initiator.origin() == target.origin() {
+        "same-origin"
+    } else if same_site {
+        "same-site"
+    } else {
+        "cross-site"
+    }
+}
+
+/// The registrable domain of `host`, approximated without a public suffix list: the last two
+/// labels, or three under a short second-level label of a country code, as in `example.co.uk`.
+fn site_of(host: &str) -> &str {
+    if host.parse::<std::net::Ipv4Addr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels: Vec<&str> = host.rsplitn(4, '.').collect();
+    let keep = match labels.as_slice() {
+        [tld, second, ..] if tld.len() == 2 && second.len() <= 3 && labels.len() >= 3 => 3,
+        _ => 2,
+    };
+    if labels.len() <= keep {
+        return host;
+    }
+    let suffix_len: usize = labels[..keep]
+        .iter()
+        .map(|label| label.len() + 1)
+        .sum::<usize>()
+        - 1;
+    &host[host.len() - suffix_len..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                          (KHTML, like Gecko) Chrome/142.0.0.0 Safari/537.36";
+
+    fn navigation_defaults(agent: &'static str) -> HeaderMap {
+        let mut defaults = HeaderMap::new();
+        for (name, value) in [
+            ("upgrade-insecure-requests", "1"),
+            ("user-agent", agent),
+            ("accept", "text/html,*/*;q=0.8"),
+            ("sec-fetch-site", "none"),
+            ("sec-fetch-mode", "navigate"),
+            ("sec-fetch-user", "?1"),
+            ("sec-fetch-dest", "document"),
+            ("priority", "u=0, i"),
+        ] {
+            defaults.insert(name, HeaderValue::from_static(value));
+        }
+        defaults
+    }
+
+    #[test]
+    fn rewrites_a_navigation_into_a_cross_site_fetch() {
+        let mut defaults = navigation_defaults(CHROME);
+        let referer = vec![(
+            "Referer".to_owned(),
+            HeaderValue::from_static("https://shop.example/cart"),
+        )];
+        RequestMode::Fetch.apply(&mut defaults, "https://api.other.test/items", &referer);
+
+        assert_eq!(defaults["sec-fetch-site"], "cross-site");
+        assert_eq!(defaults["sec-fetch-mode"], "cors");
+        assert_eq!(defaults["sec-fetch-dest"], "empty");
+        assert_eq!(defaults["accept"], "*/*");
+        assert_eq!(defaults["priority"], "u=1, i");
+        assert_eq!(defaults["origin"], "https://shop.example");
+        assert!(!defaults.contains_key("sec-fetch-user"));
+        assert!(!defaults.contains_key("upgrade-insecure-requests"));
+        // Browser order is kept for the headers that stay.
+        let names: Vec<&str> = defaults.keys().map(|name| name.as_str()).collect();
+        assert_eq!(&names[..3], ["user-agent", "accept", "sec-fetch-site"]);
+    }
+
+    #[test]
+    fn marks_a_navigation_from_a_link_as_user_started() {
+        let mut defaults = navigation_defaults(CHROME);
+        defaults.remove("sec-fetch-user");
+        let referer = vec![(
+            "referer".to_owned(),
+            HeaderValue::from_static("https://www.example.com/"),
+        )];
+        RequestMode::Navigate.apply(&mut defaults, "https://shop.example.com/", &referer);
+
+        assert_eq!(defaults["sec-fetch-site"], "same-site");
+        let names: Vec<&str> = defaults.keys().map(|name| name.as_str()).collect();
+        let mode = names
+            .iter()
+            .position(|name| *name == "sec-fetch-mode")
+            .unwrap();
+        assert_eq!(names[mode + 1], "sec-fetch-user");
+        assert_eq!(defaults["accept"], "text/html,*/*;q=0.8");
+    }
+
+    #[test]
+    fn picks_image_headers_by_browser() {
+        let mut defaults = navigation_defaults(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:140.0) Gecko/20100101 Firefox/140.0",
+        );
+        RequestMode::Image.apply(&mut defaults, "https://example.com/a.png", &[]);
+        assert_eq!(defaults["sec-fetch-site"], "same-origin");
+        assert_eq!(defaults["sec-fetch-mode"], "no-cors");
+        assert_eq!(defaults["priority"], "u=5, i");
+        assert!(
+            defaults["accept"]
+                .to_str()
+                .unwrap()
+                .starts_with("image/avif,image/webp,image/png")
+        );
+        assert!(!defaults.contains_key("origin"));
+
+        let mut bare = HeaderMap::new();
+        bare.insert("user-agent", HeaderValue::from_static("okhttp/5.0.0"));
+        RequestMode::Image.apply(&mut bare, "https://example.com/a.png", &[]);
+        assert_eq!(bare.len(), 1);
+    }
+
+    #[test]
+    fn relates_the_page_to_the_target() {
+        let site =
+            |from: &str, to: &str| fetch_site(&Url::parse(from).unwrap(), &Url::parse(to).unwrap());
+        assert_eq!(
+            site("https://a.example.com/", "https://a.example.com/x"),
+            "same-origin"
+        );
+        assert_eq!(
+            site("https://www.example.com/", "https://api.example.com/"),
+            "same-site"
+        );
+        assert_eq!(
+            site("https://a.example.co.uk/", "https://b.example.co.uk/"),
+            "same-site"
+        );
+        assert_eq!(
+            site("https://a.example.co.uk/", "https://b.other.co.uk/"),
+            "cross-site"
+        );
+        assert_eq!(
+            site("http://example.com/", "https://example.com/"),
+            "cross-site"
+        );
+        assert_eq!(
+            site("http://127.0.0.1:8080/", "http://127.0.0.1:9090/"),
+            "same-site"
+        );
+    }
+}
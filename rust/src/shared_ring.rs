@@ -0,0 +1,195 @@
+use anyhow::{Result, anyhow};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+use crate::client::read_body_chunk;
+
+/// Bytes before the data region: four 32-bit words, indexed by the constants below.
+pub const HEADER_BYTES: usize = 16;
+
+/// Bytes written so far, wrapping at 2^32. Only the writer stores it.
+const WRITE: usize = 0;
+/// Bytes the reader has consumed, wrapping at 2^32. Only the reader stores it.
+const READ: usize = 1;
+const STATE: usize = 2;
+/// Set by a reader about to sleep on `WRITE`; the writer clears it and wakes the reader.
+const WAITING: usize = 3;
+
+const STREAMING: i32 = 0;
+const DONE: i32 = 1;
+const FAILED: i32 = 2;
+/// Set by the reader when it stops early.
+const CANCELLED: i32 = 3;
+
+// How long a writer facing a full ring waits before looking again, at most.
+const MAX_FULL_BACKOFF: Duration = Duration::from_millis(20);
+
+/// A single-producer, single-consumer byte ring in a caller's `SharedArrayBuffer`. The reader
+/// (usually a worker) and this writer only coordinate through the atomic header words, so the
+/// thread that owns the buffer is not involved except to wake a reader that ran dry.
+pub struct SharedRing {
+    base: *mut u8,
+    capacity: usize,
+}
+
+// SAFETY: the header words are only accessed atomically, and the data region is split by them
+// so that the writer and reader never touch the same bytes at once.
+unsafe impl Send for SharedRing {}
+unsafe impl Sync for SharedRing {}
+
+impl SharedRing {
+    /// # Safety
+    ///
+    /// `base` must point to `len` bytes, aligned to 4, that stay allocated and are not moved
+    /// while the ring is in use, and nothing but one ring reader may access them meanwhile.
+    pub unsafe fn new(base: *mut u8, len: usize) -> Result<Self> {
+        if len <= HEADER_BYTES || len - HEADER_BYTES > i32::MAX as usize {
+            return Err(anyhow!(
+                "Body ring must hold between 1 byte and 2 GiB after its {}-byte header",
+                HEADER_BYTES
+            ));
+        }
+        if base.align_offset(4) != 0 {
+            return Err(anyhow!("Body ring must start on a 4-byte boundary"));
+        }
+        Ok(Self {
+            base,
+            capacity: len - HEADER_BYTES,
+        })
+    }
+
+    fn word(&self, index: usize) -> &AtomicI32 {
+        // SAFETY: the header is in bounds and aligned, as checked in `new`.
+        unsafe { AtomicI32::from_ptr(self.base.cast::<i32>().add(index)) }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.word(STATE).load(Ordering::SeqCst) == CANCELLED
+    }
+
+    /// Copy as much of `bytes` as there is room for, and return how much that was.
+    fn write(&self, bytes: &[u8]) -> usize {
+        let written = self.word(WRITE).load(Ordering::Relaxed) as u32;
+        let read = self.word(READ).load(Ordering::Acquire) as u32;
+        let free = self
+            .capacity
+            .saturating_sub(written.wrapping_sub(read) as usize);
+        let start = written as usize % self.capacity;
+        // Up to the end of the data region; the rest wraps around on the next call.
+        let len = bytes.len().min(free).min(self.capacity - start);
+        if len == 0 {
+            return 0;
+        }
+        // SAFETY: `start..start + len` is in the data region and is free, so the reader is not
+        // looking at it until the store below publishes it.
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.base.add(HEADER_BYTES + start), len);
+        }
+        self.word(WRITE)
+            .store(written.wrapping_add(len as u32) as i32, Ordering::SeqCst);
+        len
+    }
+
+    /// Whether a reader went to sleep waiting for data, clearing the flag when it did.
+    fn take_waiting(&self) -> bool {
+        self.word(WAITING).swap(0, Ordering::SeqCst) == 1
+    }
+
+    fn finish(&self, state: i32) {
+        let _ =
+            self.word(STATE)
+                .compare_exchange(STREAMING, state, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
+
+/// Read body `handle` to the end into `ring`, calling `wake` whenever a reader that ran dry can
+/// go on. Marks the ring done or failed at the end, and stops early when the reader cancels.
+pub async fn pipe_body_to_ring<F>(handle: u64, ring: &SharedRing, wake: F) -> Result<()>
+where
+    F: Fn(),
+{
+    let result = pipe_chunks(handle, ring, &wake).await;
+    ring.finish(if result.is_ok() { DONE } else { FAILED });
+    if ring.take_waiting() {
+        wake();
+    }
+    result
+}
+
+async fn pipe_chunks(handle: u64, ring: &SharedRing, wake: &impl Fn()) -> Result<()> {
+    while let Some(chunk) = read_body_chunk(handle).await? {
+        let mut rest: &[u8] = &chunk;
+        let mut backoff = Duration::from_micros(100);
+        while !rest.is_empty() {
+            if ring.is_cancelled() {
+                return Ok(());
+            }
+            let written = ring.write(rest);
+            if written > 0 {
+                rest = &rest[written..];
+                backoff = Duration::from_micros(100);
+                if ring.take_waiting() {
+                    wake();
+                }
+                continue;
+            }
+            // The reader frees room without telling anyone, so a full ring is polled.
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_FULL_BACKOFF);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(words: &mut [u32]) -> SharedRing {
+        unsafe { SharedRing::new(words.as_mut_ptr().cast(), words.len() * 4).unwrap() }
+    }
+
+    fn consume(ring: &SharedRing, len: usize) {
+        let read = ring.word(READ).load(Ordering::SeqCst);
+        ring.word(READ).store(
+            (read as u32).wrapping_add(len as u32) as i32,
+            Ordering::SeqCst,
+        );
+    }
+
+    #[test]
+    fn wraps_around_and_never_overwrites_unread_bytes() {
+        // A 16-byte header and 8 bytes of data.
+        let mut words = [0u32; 6];
+        let ring = ring(&mut words);
+
+        assert_eq!(ring.write(b"abcdef"), 6);
+        assert_eq!(ring.write(b"ghijk"), 2, "only two bytes are free");
+        assert_eq!(ring.write(b"ijk"), 0);
+
+        consume(&ring, 5);
+        // The region is full up to its end, so the next bytes wrap around to its start.
+        assert_eq!(ring.write(b"ijklmno"), 5);
+        assert_eq!(ring.word(WRITE).load(Ordering::SeqCst), 13);
+        let data: Vec<u8> = words[4..]
+            .iter()
+            .flat_map(|word| word.to_ne_bytes())
+            .collect();
+        assert_eq!(&data, b"ijklmfgh");
+    }
+
+    #[test]
+    fn reports_sleeping_readers_once_and_keeps_the_first_final_state() {
+        let mut words = [0u32; 5];
+        let ring = ring(&mut words);
+        ring.word(WAITING).store(1, Ordering::SeqCst);
+        assert!(ring.take_waiting());
+        assert!(!ring.take_waiting());
+
+        ring.word(STATE).store(CANCELLED, Ordering::SeqCst);
+        ring.finish(DONE);
+        assert!(ring.is_cancelled());
+
+        assert!(unsafe { SharedRing::new(words.as_mut_ptr().cast(), HEADER_BYTES) }.is_err());
+    }
+}
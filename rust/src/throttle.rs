@@ -0,0 +1,87 @@
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::client::ResponseBodyStream;
+
+// Streamed request bodies are cut into slices of this size so a large upload is paced evenly
+// and its progress can be tracked.
+const UPLOAD_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Paces byte consumption to a fixed rate. Shared limiters (e.g. per transport) split the
+/// budget between every stream that draws from them.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    next_free: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next_free: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserve `bytes` worth of budget and wait until it has been earned.
+    pub async fn consume(&self, bytes: usize) {
+        let deadline = {
+            let mut next_free = self
+                .next_free
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let start = (*next_free).max(Instant::now());
+            *next_free = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+            *next_free
+        };
+
+        tokio::time::sleep_until(deadline).await;
+    }
+}
+
+/// Wrap a body stream so each chunk is only released once every limiter allows it.
+pub fn throttle_stream(
+    stream: ResponseBodyStream,
+    limiters: Vec<Arc<RateLimiter>>,
+) -> ResponseBodyStream {
+    if limiters.is_empty() {
+        return stream;
+    }
+
+    Box::pin(stream.then(move |chunk| {
+        let limiters = limiters.clone();
+        async move {
+            if let Ok(bytes) = &chunk {
+                for limiter in &limiters {
+                    limiter.consume(bytes.len()).await;
+                }
+            }
+            chunk
+        }
+    }))
+}
+
+/// Request body as a stream of chunks from a buffered body.
+pub type RequestBodyStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// Turn a buffered request body into a stream that is emitted no faster than every limiter allows.
+pub fn request_body_stream(body: Bytes, limiters: Vec<Arc<RateLimiter>>) -> RequestBodyStream {
+    let chunks: Vec<Bytes> = (0..body.len())
+        .step_by(UPLOAD_CHUNK_SIZE)
+        .map(|start| body.slice(start..(start + UPLOAD_CHUNK_SIZE).min(body.len())))
+        .collect();
+
+    Box::pin(futures_util::stream::iter(chunks).then(move |chunk| {
+        let limiters = limiters.clone();
+        async move {
+            for limiter in &limiters {
+                limiter.consume(chunk.len()).await;
+            }
+            Ok::<Bytes, std::io::Error>(chunk)
+        }
+    }))
+}
@@ -0,0 +1,550 @@
+use anyhow::{Context, Result, anyhow, bail};
+use boring2::ssl::{
+    CertificateCompressionAlgorithm, CertificateCompressor, SslConnector, SslMethod, SslOptions,
+    SslVerifyMode, SslVersion,
+};
+use boring2::x509::X509;
+use boring2::x509::store::{X509Store, X509StoreBuilder};
+use bytes::{BufMut, Bytes, BytesMut};
+use dashmap::DashMap;
+use percent_encoding::percent_decode_str;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_boring2::SslStream;
+use tokio_socks::tcp::Socks5Stream;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+use wreq::EmulationFactory;
+use wreq::tls::{AlpnProtocol, AlpsProtocol, TlsOptions, TlsVersion};
+use wreq_util::{Emulation, EmulationOS, EmulationOption};
+
+use crate::cassette::base64_encode;
+use crate::client::ensure_accepting;
+use crate::contexts::{Resource, release};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+const MAX_PROXY_RESPONSE_HEAD: usize = 16 * 1024;
+
+// Global storage for raw TLS sockets
+static TLS_SOCKETS: LazyLock<DashMap<u64, Arc<TlsSocket>>> = LazyLock::new(DashMap::new);
+
+static NEXT_TLS_SOCKET_ID: AtomicU64 = AtomicU64::new(1);
+
+// Mozilla's root certificates, the same trust store wreq uses for HTTP requests.
+static ROOT_STORE: LazyLock<X509Store> = LazyLock::new(|| {
+    let mut builder = X509StoreBuilder::new().expect("Failed to create certificate store");
+    for der in webpki_root_certs::TLS_SERVER_ROOT_CERTS {
+        if let Ok(cert) = X509::from_der(der.as_ref()) {
+            let _ = builder.add_cert(cert);
+        }
+    }
+    builder.build()
+});
+
+/// Byte stream underneath the TLS session: a direct TCP socket or a proxy tunnel.
+pub(crate) trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+pub(crate) type TlsStream = SslStream<Box<dyn Io>>;
+
+#[derive(Debug, Clone)]
+pub struct TlsSocketOptions {
+    pub host: String,
+    pub port: u16,
+    pub emulation: Emulation,
+    pub emulation_os: EmulationOS,
+    pub proxy: Option<String>,
+    pub insecure: bool,
+    /// Budget for connecting and completing the handshake, in milliseconds (0 means none).
+    pub timeout: u64,
+}
+
+/// Details about an established socket, reported back to JS.
+#[derive(Debug, Clone)]
+pub struct TlsSocketInfo {
+    pub id: u64,
+    pub alpn_protocol: Option<String>,
+    pub tls_version: String,
+    pub local_addr: Option<SocketAddr>,
+    pub remote_addr: Option<SocketAddr>,
+}
+
+struct TlsSocket {
+    reader: Mutex<ReadHalf<TlsStream>>,
+    writer: Mutex<WriteHalf<TlsStream>>,
+    // Cancelled on close so a pending read does not outlive the socket.
+    closed: CancellationToken,
+}
+
+/// Decompress-only certificate compressors. Advertising them is part of the browser
+/// fingerprint, and the server may then send a compressed certificate chain.
+struct BrotliCertificateDecompressor;
+struct ZlibCertificateDecompressor;
+struct ZstdCertificateDecompressor;
+
+impl CertificateCompressor for BrotliCertificateDecompressor {
+    const ALGORITHM: CertificateCompressionAlgorithm = CertificateCompressionAlgorithm::BROTLI;
+    const CAN_COMPRESS: bool = false;
+    const CAN_DECOMPRESS: bool = true;
+
+    fn decompress<W: Write>(&self, input: &[u8], output: &mut W) -> io::Result<()> {
+        let mut decoder = brotli::Decompressor::new(input, 4096);
+        io::copy(&mut decoder, output)?;
+        Ok(())
+    }
+}
+
+impl CertificateCompressor for ZlibCertificateDecompressor {
+    const ALGORITHM: CertificateCompressionAlgorithm = CertificateCompressionAlgorithm::ZLIB;
+    const CAN_COMPRESS: bool = false;
+    const CAN_DECOMPRESS: bool = true;
+
+    fn decompress<W: Write>(&self, input: &[u8], output: &mut W) -> io::Result<()> {
+        let mut decoder = flate2::read::ZlibDecoder::new(input);
+        io::copy(&mut decoder, output)?;
+        Ok(())
+    }
+}
+
+impl CertificateCompressor for ZstdCertificateDecompressor {
+    const ALGORITHM: CertificateCompressionAlgorithm = CertificateCompressionAlgorithm::ZSTD;
+    const CAN_COMPRESS: bool = false;
+    const CAN_DECOMPRESS: bool = true;
+
+    fn decompress<W: Write>(&self, input: &[u8], output: &mut W) -> io::Result<()> {
+        let mut decoder = zstd::stream::read::Decoder::new(input)?;
+        io::copy(&mut decoder, output)?;
+        Ok(())
+    }
+}
+
+// wreq keeps the raw protocol values private, so map its well-known constants back to bytes.
+fn alpn_bytes(protocol: &AlpnProtocol) -> Option<&'static [u8]> {
+    match *protocol {
+        AlpnProtocol::HTTP1 => Some(b"http/1.1"),
+        AlpnProtocol::HTTP2 => Some(b"h2"),
+        AlpnProtocol::HTTP3 => Some(b"h3"),
+        _ => None,
+    }
+}
+
+fn alps_bytes(protocol: &AlpsProtocol) -> Option<&'static [u8]> {
+    match *protocol {
+        AlpsProtocol::HTTP1 => Some(b"http/1.1"),
+        AlpsProtocol::HTTP2 => Some(b"h2"),
+        AlpsProtocol::HTTP3 => Some(b"h3"),
+        _ => None,
+    }
+}
+
+fn ssl_version(version: TlsVersion) -> SslVersion {
+    match version {
+        TlsVersion::TLS_1_0 => SslVersion::TLS1,
+        TlsVersion::TLS_1_1 => SslVersion::TLS1_1,
+        TlsVersion::TLS_1_2 => SslVersion::TLS1_2,
+        _ => SslVersion::TLS1_3,
+    }
+}
+
+/// Build a BoringSSL connector that produces the same ClientHello as the profile's HTTP client.
+/// Mirrors how wreq applies `TlsOptions` to its own connector.
+fn build_connector(tls: &TlsOptions, insecure: bool) -> Result<SslConnector> {
+    let mut builder = SslConnector::no_default_verify_builder(SslMethod::tls_client())?;
+
+    if insecure {
+        builder.set_verify(SslVerifyMode::NONE);
+    } else {
+        builder.set_cert_store_ref(&ROOT_STORE);
+        builder.set_verify(SslVerifyMode::PEER);
+    }
+
+    for algorithm in tls
+        .certificate_compression_algorithms
+        .as_deref()
+        .unwrap_or_default()
+    {
+        match *algorithm {
+            CertificateCompressionAlgorithm::BROTLI => {
+                builder.add_certificate_compression_algorithm(BrotliCertificateDecompressor)?
+            }
+            CertificateCompressionAlgorithm::ZLIB => {
+                builder.add_certificate_compression_algorithm(ZlibCertificateDecompressor)?
+            }
+            CertificateCompressionAlgorithm::ZSTD => {
+                builder.add_certificate_compression_algorithm(ZstdCertificateDecompressor)?
+            }
+            _ => {}
+        }
+    }
+
+    builder.set_min_proto_version(tls.min_tls_version.map(ssl_version))?;
+    builder.set_max_proto_version(tls.max_tls_version.map(ssl_version))?;
+
+    if tls.enable_ocsp_stapling {
+        builder.enable_ocsp_stapling();
+    }
+    if tls.enable_signed_cert_timestamps {
+        builder.enable_signed_cert_timestamps();
+    }
+    if !tls.session_ticket {
+        builder.set_options(SslOptions::NO_TICKET);
+    }
+    if !tls.psk_dhe_ke {
+        builder.set_options(SslOptions::NO_PSK_DHE_KE);
+    }
+    if !tls.renegotiation {
+        builder.set_options(SslOptions::NO_RENEGOTIATION);
+    }
+    if let Some(enabled) = tls.grease_enabled {
+        builder.set_grease_enabled(enabled);
+    }
+    if let Some(enabled) = tls.permute_extensions {
+        builder.set_permute_extensions(enabled);
+    }
+    if let Some(curves) = tls.curves_list.as_deref() {
+        builder.set_curves_list(curves)?;
+    }
+    if let Some(sigalgs) = tls.sigalgs_list.as_deref() {
+        builder.set_sigalgs_list(sigalgs)?;
+    }
+    if let Some(enabled) = tls.preserve_tls13_cipher_list {
+        builder.set_preserve_tls13_cipher_list(enabled);
+    }
+    if let Some(ciphers) = tls.cipher_list.as_deref() {
+        builder.set_cipher_list(ciphers)?;
+    }
+    if let Some(credentials) = tls.delegated_credentials.as_deref() {
+        builder.set_delegated_credentials(credentials)?;
+    }
+    if let Some(limit) = tls.record_size_limit {
+        builder.set_record_size_limit(limit);
+    }
+    if let Some(limit) = tls.key_shares_limit {
+        builder.set_key_shares_limit(limit);
+    }
+    if let Some(enabled) = tls.aes_hw_override {
+        builder.set_aes_hw_override(enabled);
+    }
+    if let Some(permutation) = tls.extension_permutation.as_deref() {
+        builder.set_extension_permutation(permutation)?;
+    }
+
+    Ok(builder.build())
+}
+
+/// Split `user:password@` off a proxy authority and percent-decode both parts, as wreq does.
+fn proxy_credentials(authority: &str) -> Option<(String, String)> {
+    let (userinfo, _) = authority.rsplit_once('@')?;
+    let (user, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+    let decode = |part: &str| percent_decode_str(part).decode_utf8_lossy().into_owned();
+    Some((decode(user), decode(password)))
+}
+
+/// A parsed proxy URL that raw sockets can tunnel through.
+pub(crate) struct ProxyEndpoint {
+    scheme: String,
+    pub host: String,
+    pub port: u16,
+    credentials: Option<(String, String)>,
+}
+
+impl ProxyEndpoint {
+    pub fn parse(proxy: &str) -> Result<Self> {
+        let uri: wreq::Uri = proxy.parse().context("Failed to parse proxy URL")?;
+        let scheme = uri.scheme_str().unwrap_or("http").to_ascii_lowercase();
+        let host = uri
+            .host()
+            .ok_or_else(|| anyhow!("Proxy URL is missing a host"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_owned();
+        let credentials = uri.authority().and_then(|a| proxy_credentials(a.as_str()));
+
+        let default_port = match scheme.as_str() {
+            "http" => 80,
+            "socks5" | "socks5h" => 1080,
+            other => bail!("Unsupported proxy scheme for TLS sockets: {}", other),
+        };
+        Ok(Self {
+            port: uri.port_u16().unwrap_or(default_port),
+            scheme,
+            host,
+            credentials,
+        })
+    }
+}
+
+/// Open the byte stream to `host:port`, tunnelling through `proxy` when one is configured.
+async fn open_stream(
+    proxy: Option<&str>,
+    host: &str,
+    port: u16,
+) -> Result<(Box<dyn Io>, Option<SocketAddr>, Option<SocketAddr>)> {
+    let Some(proxy) = proxy else {
+        let tcp = TcpStream::connect((host, port))
+            .await
+            .with_context(|| format!("Failed to connect to {}:{}", host, port))?;
+        let (local, remote) = (tcp.local_addr().ok(), tcp.peer_addr().ok());
+        let io: Box<dyn Io> = Box::new(tcp);
+        return Ok((io, local, remote));
+    };
+
+    let proxy = ProxyEndpoint::parse(proxy)?;
+    let tcp = TcpStream::connect((proxy.host.as_str(), proxy.port))
+        .await
+        .with_context(|| format!("Failed to connect to proxy {}", proxy.host))?;
+    let (local, remote) = (tcp.local_addr().ok(), tcp.peer_addr().ok());
+    let io = tunnel(tcp, &proxy, host, port).await?;
+    Ok((io, local, remote))
+}
+
+/// Ask the proxy on the other end of `tcp` for a tunnel to `host:port`.
+pub(crate) async fn tunnel(
+    mut tcp: TcpStream,
+    proxy: &ProxyEndpoint,
+    host: &str,
+    port: u16,
+) -> Result<Box<dyn Io>> {
+    let credentials = proxy
+        .credentials
+        .as_ref()
+        .map(|(user, password)| (user.as_str(), password.as_str()));
+
+    if proxy.scheme == "http" {
+        let mut head = format!(
+            "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+            host = host,
+            port = port
+        );
+        if let Some((user, password)) = credentials {
+            let token = base64_encode(format!("{}:{}", user, password).as_bytes());
+            head.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+        }
+        head.push_str("\r\n");
+        tcp.write_all(head.as_bytes()).await?;
+
+        // Read byte by byte so nothing after the response head is consumed.
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() >= MAX_PROXY_RESPONSE_HEAD {
+                bail!("Proxy response head is too large");
+            }
+            response.push(
+                tcp.read_u8()
+                    .await
+                    .context("Proxy closed the connection during CONNECT")?,
+            );
+        }
+        let status_line = String::from_utf8_lossy(&response);
+        let status_line = status_line.lines().next().unwrap_or_default();
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            bail!("Proxy refused CONNECT: {}", status_line);
+        }
+        return Ok(Box::new(tcp));
+    }
+
+    // socks5 resolves the target locally; socks5h leaves name resolution to the proxy.
+    let target = if proxy.scheme == "socks5" {
+        let addr = tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or_else(|| anyhow!("Failed to resolve {}", host))?;
+        tokio_socks::TargetAddr::Ip(addr)
+    } else {
+        tokio_socks::TargetAddr::Domain(host.to_owned().into(), port)
+    };
+    let stream = match credentials {
+        Some((user, password)) => {
+            Socks5Stream::connect_with_password_and_socket(tcp, target, user, password).await
+        }
+        None => Socks5Stream::connect_with_socket(tcp, target).await,
+    }
+    .context("SOCKS5 proxy handshake failed")?;
+    Ok(Box::new(stream))
+}
+
+/// The TLS settings of a browser profile's HTTP client.
+pub(crate) fn profile_tls_options(emulation: Emulation, emulation_os: EmulationOS) -> TlsOptions {
+    EmulationOption::builder()
+        .emulation(emulation)
+        .emulation_os(emulation_os)
+        .build()
+        .emulation()
+        .tls_options_mut()
+        .take()
+        .unwrap_or_else(|| TlsOptions::builder().build())
+}
+
+/// Run a TLS handshake over `io` with the same ClientHello as the profile's HTTP client.
+pub(crate) async fn handshake(
+    tls: &TlsOptions,
+    insecure: bool,
+    io: Box<dyn Io>,
+    host: &str,
+    port: u16,
+) -> Result<TlsStream> {
+    let connector = build_connector(tls, insecure)?;
+    let mut config = connector.configure()?;
+    config.set_use_server_name_indication(true);
+    config.set_verify_hostname(!insecure);
+    config.set_enable_ech_grease(tls.enable_ech_grease);
+    if tls.random_aes_hw_override {
+        config.set_aes_hw_override(Uuid::new_v4().as_bytes()[0] & 1 == 0);
+    }
+    if let Some(alps) = tls.alps_protocols.as_deref() {
+        for protocol in alps.iter().filter_map(alps_bytes) {
+            config.add_application_settings(protocol)?;
+        }
+        if !alps.is_empty() && tls.alps_use_new_codepoint {
+            config.set_alps_use_new_codepoint(true);
+        }
+    }
+    if let Some(alpn) = tls.alpn_protocols.as_deref() {
+        let mut encoded = BytesMut::new();
+        for protocol in alpn.iter().filter_map(alpn_bytes) {
+            encoded.put_u8(protocol.len() as u8);
+            encoded.extend_from_slice(protocol);
+        }
+        config.set_alpn_protos(&encoded)?;
+    }
+
+    let ssl = config.into_ssl(host)?;
+    let mut stream: TlsStream = SslStream::new(ssl, io)?;
+    Pin::new(&mut stream)
+        .connect()
+        .await
+        .with_context(|| format!("TLS handshake with {}:{} failed", host, port))?;
+    Ok(stream)
+}
+
+async fn establish(options: TlsSocketOptions) -> Result<TlsSocketInfo> {
+    let tls = profile_tls_options(options.emulation, options.emulation_os);
+    let host = options.host.trim_start_matches('[').trim_end_matches(']');
+    let (io, local_addr, remote_addr) =
+        open_stream(options.proxy.as_deref(), host, options.port).await?;
+    let stream = handshake(&tls, options.insecure, io, host, options.port).await?;
+
+    let alpn_protocol = stream
+        .ssl()
+        .selected_alpn_protocol()
+        .map(|protocol| String::from_utf8_lossy(protocol).into_owned());
+    let tls_version = stream.ssl().version_str().to_owned();
+
+    let (reader, writer) = tokio::io::split(stream);
+    let id = NEXT_TLS_SOCKET_ID.fetch_add(1, Ordering::Relaxed);
+    TLS_SOCKETS.insert(
+        id,
+        Arc::new(TlsSocket {
+            reader: Mutex::new(reader),
+            writer: Mutex::new(writer),
+            closed: CancellationToken::new(),
+        }),
+    );
+
+    Ok(TlsSocketInfo {
+        id,
+        alpn_protocol,
+        tls_version,
+        local_addr,
+        remote_addr,
+    })
+}
+
+/// Open a TLS connection whose ClientHello matches the emulated browser profile.
+pub async fn connect_tls_socket(options: TlsSocketOptions) -> Result<TlsSocketInfo> {
+    ensure_accepting()?;
+
+    let target = format!("{}:{}", options.host, options.port);
+    match options.timeout {
+        0 => establish(options).await,
+        timeout => tokio::time::timeout(Duration::from_millis(timeout), establish(options))
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to {}", target))?,
+    }
+}
+
+fn get_socket(id: u64) -> Result<Arc<TlsSocket>> {
+    TLS_SOCKETS
+        .get(&id)
+        .map(|entry| entry.value().clone())
+        .ok_or_else(|| anyhow!("TLS socket {} not found", id))
+}
+
+/// Read the next chunk of decrypted data. `None` means the peer closed the connection.
+pub async fn read_tls_socket(id: u64) -> Result<Option<Bytes>> {
+    let socket = get_socket(id)?;
+    let mut reader = socket.reader.lock().await;
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+
+    let read = tokio::select! {
+        _ = socket.closed.cancelled() => return Ok(None),
+        read = reader.read(&mut buf) => read?,
+    };
+
+    if read == 0 {
+        return Ok(None);
+    }
+    buf.truncate(read);
+    Ok(Some(Bytes::from(buf)))
+}
+
+pub async fn write_tls_socket(id: u64, data: Vec<u8>) -> Result<()> {
+    let socket = get_socket(id)?;
+    let mut writer = socket.writer.lock().await;
+    writer.write_all(&data).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Send close_notify and stop writing; the read side stays open until the peer closes.
+pub async fn end_tls_socket(id: u64) -> Result<()> {
+    let socket = get_socket(id)?;
+    let mut writer = socket.writer.lock().await;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+pub fn close_tls_socket(id: u64) {
+    if let Some((_, socket)) = TLS_SOCKETS.remove(&id) {
+        release(Resource::TlsSocket, &id.to_string());
+        socket.closed.cancel();
+    }
+}
+
+/// Close every open TLS socket.
+pub fn close_all_tls_sockets() {
+    let ids: Vec<u64> = TLS_SOCKETS.iter().map(|entry| *entry.key()).collect();
+    for id in ids {
+        close_tls_socket(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proxy_credentials;
+
+    #[test]
+    fn splits_proxy_credentials_from_authority() {
+        assert_eq!(
+            proxy_credentials("user:p%40ss@proxy.local:8080"),
+            Some(("user".to_owned(), "p@ss".to_owned()))
+        );
+        assert_eq!(
+            proxy_credentials("corp%5Calice:a%3Ab@proxy.local"),
+            Some(("corp\\alice".to_owned(), "a:b".to_owned()))
+        );
+        assert_eq!(
+            proxy_credentials("token@proxy.local"),
+            Some(("token".to_owned(), String::new()))
+        );
+        assert_eq!(proxy_credentials("proxy.local:8080"), None);
+    }
+}
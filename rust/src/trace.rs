@@ -0,0 +1,140 @@
+use anyhow::{Result, bail};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// W3C trace context sent as `traceparent` / `tracestate` on every hop of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub traceparent: String,
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    pub fn new(traceparent: String, tracestate: Option<String>) -> Result<Self> {
+        if !is_valid_traceparent(&traceparent) {
+            bail!("Invalid traceparent: {}", traceparent);
+        }
+        Ok(Self {
+            traceparent,
+            tracestate: tracestate.filter(|state| !state.is_empty()),
+        })
+    }
+}
+
+// version-traceid-parentid-flags, lowercase hex, with all-zero ids and version ff rejected.
+fn is_valid_traceparent(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [version, trace_id, parent_id, flags] = parts.as_slice() else {
+        return false;
+    };
+
+    let is_hex = |part: &str, len: usize| {
+        part.len() == len
+            && part
+                .bytes()
+                .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+    };
+
+    is_hex(version, 2)
+        && *version != "ff"
+        && is_hex(trace_id, 32)
+        && trace_id.bytes().any(|byte| byte != b'0')
+        && is_hex(parent_id, 16)
+        && parent_id.bytes().any(|byte| byte != b'0')
+        && is_hex(flags, 2)
+}
+
+/// A redirect response observed while the request was in flight.
+#[derive(Debug, Clone)]
+pub struct RedirectHop {
+    pub status: u16,
+    pub location: String,
+    pub received_at: f64,
+}
+
+/// Native timing of a traced request, in milliseconds since the Unix epoch.
+#[derive(Debug, Clone)]
+pub struct RequestTiming {
+    pub start: f64,
+    /// When the final response headers arrived.
+    pub response_start: f64,
+    pub redirects: Vec<RedirectHop>,
+}
+
+/// Collects redirect hops from inside a redirect policy, which only sees one attempt at a time.
+#[derive(Debug, Clone, Default)]
+pub struct HopRecorder(Arc<Mutex<Vec<RedirectHop>>>);
+
+impl HopRecorder {
+    pub fn record(&self, status: u16, location: String) {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(RedirectHop {
+                status,
+                location,
+                received_at: now_ms(),
+            });
+    }
+
+    pub fn take(&self) -> Vec<RedirectHop> {
+        std::mem::take(
+            &mut *self
+                .0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+}
+
+pub fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HopRecorder, TraceContext};
+
+    const VALID: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn accepts_w3c_traceparent() {
+        let context = TraceContext::new(VALID.to_string(), Some(String::new())).unwrap();
+        assert_eq!(context.traceparent, VALID);
+        assert_eq!(context.tracestate, None);
+    }
+
+    #[test]
+    fn rejects_malformed_traceparent() {
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(
+                TraceContext::new(value.to_string(), None).is_err(),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn hop_recorder_drains_in_order() {
+        let hops = HopRecorder::default();
+        hops.clone()
+            .record(302, "https://example.com/a".to_string());
+        hops.record(301, "https://example.com/b".to_string());
+
+        let taken = hops.take();
+        assert_eq!(taken.len(), 2);
+        assert_eq!(taken[0].status, 302);
+        assert_eq!(taken[1].location, "https://example.com/b");
+        assert!(hops.take().is_empty());
+    }
+}
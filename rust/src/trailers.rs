@@ -0,0 +1,112 @@
+use bytes::Bytes;
+use futures_util::Stream;
+use http_body::Body as HttpBody;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use crate::h2_stream::header_tuples;
+
+/// Where a response's trailer fields end up once its body has been read to the end.
+pub type TrailerSlot = Arc<Mutex<Option<Vec<(String, String)>>>>;
+
+/// The data frames of a response body. Unlike `wreq::Response::bytes_stream`, trailer frames
+/// are kept in a [`TrailerSlot`] instead of being dropped.
+pub struct DataFrames<B> {
+    body: B,
+    trailers: TrailerSlot,
+}
+
+/// Stream the body of `body`, recording its trailers in the returned slot.
+pub fn data_frames<B>(body: B) -> (DataFrames<B>, TrailerSlot) {
+    let trailers = TrailerSlot::default();
+    let frames = DataFrames {
+        body,
+        trailers: trailers.clone(),
+    };
+    (frames, trailers)
+}
+
+/// Move the trailers out of `slot`; `None` if the body had none or is not finished.
+pub fn take_trailers(slot: &TrailerSlot) -> Option<Vec<(String, String)>> {
+    slot.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take()
+}
+
+impl<B> Stream for DataFrames<B>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    type Item = Result<Bytes, B::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let frame = match std::task::ready!(Pin::new(&mut this.body).poll_frame(cx)) {
+                Some(Ok(frame)) => frame,
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => return Poll::Ready(None),
+            };
+            match frame.into_data() {
+                Ok(data) => return Poll::Ready(Some(Ok(data))),
+                Err(frame) => {
+                    if let Ok(trailers) = frame.into_trailers() {
+                        *this
+                            .trailers
+                            .lock()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+                            Some(header_tuples(&trailers));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{data_frames, take_trailers};
+    use bytes::Bytes;
+    use futures_util::StreamExt;
+    use http_body::{Body as HttpBody, Frame};
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use wreq::header::{HeaderMap, HeaderValue};
+
+    struct Frames(VecDeque<Frame<Bytes>>);
+
+    impl HttpBody for Frames {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+            Poll::Ready(self.0.pop_front().map(Ok))
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_trailers_out_of_the_data() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        let body = Frames(VecDeque::from([
+            Frame::data(Bytes::from_static(b"hello ")),
+            Frame::data(Bytes::from_static(b"world")),
+            Frame::trailers(trailers),
+        ]));
+
+        let (frames, slot) = data_frames(body);
+        let data: Vec<Bytes> = frames.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(data.concat(), b"hello world");
+        assert_eq!(
+            take_trailers(&slot),
+            Some(vec![("grpc-status".to_owned(), "0".to_owned())])
+        );
+        assert_eq!(take_trailers(&slot), None);
+    }
+}
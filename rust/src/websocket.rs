@@ -4,15 +4,21 @@ use futures_util::{SinkExt, StreamExt};
 use neon::prelude::*;
 use std::sync::Arc;
 use std::sync::LazyLock;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use wreq::cookie::{CookieStore, Cookies};
-use wreq::header::OrigHeaderMap;
+use wreq::header::{HeaderValue, OrigHeaderMap};
 use wreq::ws::WebSocket;
 use wreq::ws::message::{CloseCode, CloseFrame, Message};
 use wreq_util::{Emulation, EmulationOS, EmulationOption};
 
-use crate::client::{get_session_cookie_jar, get_transport_client};
+use crate::client::{
+    check_transport_egress, ensure_accepting, get_session_cookie_jar, get_transport_client,
+    header_text,
+};
+use crate::contexts::{Resource, release};
+use crate::trace::now_ms;
 
 // Global storage for WebSocket connections
 static WS_CONNECTIONS: LazyLock<DashMap<u64, Arc<WsConnection>>> = LazyLock::new(DashMap::new);
@@ -24,7 +30,7 @@ pub struct WebSocketOptions {
     pub url: String,
     pub emulation: Emulation,
     pub emulation_os: EmulationOS,
-    pub headers: Vec<(String, String)>,
+    pub headers: Vec<(String, HeaderValue)>,
     pub protocols: Vec<String>,
     pub proxy: Option<Arc<str>>,
 }
@@ -38,8 +44,97 @@ pub struct WebSocketUpgradeMetadata {
 /// WebSocket connection wrapper
 pub struct WsConnection {
     sender: Arc<Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
+    activity: Arc<WsActivity>,
+    /// Cancelled when the context that opened the connection exits, to stop its callbacks.
+    released: CancellationToken,
 }
 
+/// Lifecycle of an established connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsState {
+    Open,
+    Closing,
+    Closed,
+}
+
+impl WsState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WsState::Open => "open",
+            WsState::Closing => "closing",
+            WsState::Closed => "closed",
+        }
+    }
+}
+
+/// State and last activity of a connection, in milliseconds since the Unix epoch. It is shared
+/// with the JS handle, so it can still be read once the connection has been unregistered.
+#[derive(Debug)]
+pub struct WsActivity {
+    state: AtomicU8,
+    opened_at: u64,
+    // 0 until the first frame goes out or comes in.
+    last_sent_at: AtomicU64,
+    last_received_at: AtomicU64,
+}
+
+impl WsActivity {
+    fn new() -> Self {
+        Self {
+            state: AtomicU8::new(WsState::Open as u8),
+            opened_at: now_ms() as u64,
+            last_sent_at: AtomicU64::new(0),
+            last_received_at: AtomicU64::new(0),
+        }
+    }
+
+    pub fn state(&self) -> WsState {
+        match self.state.load(Ordering::Acquire) {
+            state if state == WsState::Open as u8 => WsState::Open,
+            state if state == WsState::Closing as u8 => WsState::Closing,
+            _ => WsState::Closed,
+        }
+    }
+
+    pub fn opened_at(&self) -> u64 {
+        self.opened_at
+    }
+
+    pub fn last_sent_at(&self) -> Option<u64> {
+        Some(self.last_sent_at.load(Ordering::Relaxed)).filter(|at| *at > 0)
+    }
+
+    pub fn last_received_at(&self) -> Option<u64> {
+        Some(self.last_received_at.load(Ordering::Relaxed)).filter(|at| *at > 0)
+    }
+
+    /// Record a frame from the peer, control frames included.
+    pub fn record_received(&self) {
+        self.last_received_at
+            .store(now_ms() as u64, Ordering::Relaxed);
+    }
+
+    fn record_sent(&self) {
+        self.last_sent_at.store(now_ms() as u64, Ordering::Relaxed);
+    }
+
+    fn mark_closing(&self) {
+        let _ = self.state.compare_exchange(
+            WsState::Open as u8,
+            WsState::Closing as u8,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+    }
+
+    /// The connection stopped receiving; nothing more can be sent or read.
+    pub fn mark_closed(&self) {
+        self.state.store(WsState::Closed as u8, Ordering::Release);
+    }
+}
+
+impl Finalize for WsActivity {}
+
 #[derive(Clone, Debug)]
 pub struct WsClosePayload {
     pub code: u16,
@@ -50,9 +145,25 @@ impl WsConnection {
     pub fn new(sender: futures_util::stream::SplitSink<WebSocket, Message>) -> Self {
         Self {
             sender: Arc::new(Mutex::new(sender)),
+            activity: Arc::new(WsActivity::new()),
+            released: CancellationToken::new(),
         }
     }
 
+    pub fn activity(&self) -> Arc<WsActivity> {
+        self.activity.clone()
+    }
+
+    /// Cancelled once the connection's events must no longer reach JS.
+    pub fn released(&self) -> CancellationToken {
+        self.released.clone()
+    }
+
+    /// Stop delivering the connection's events, for a context that has exited.
+    pub fn release(&self) {
+        self.released.cancel();
+    }
+
     /// Send a text message
     pub async fn send_text(&self, text: String) -> Result<()> {
         let mut sender = self.sender.lock().await;
@@ -60,6 +171,7 @@ impl WsConnection {
             .send(Message::text(text))
             .await
             .context("Failed to send text message")?;
+        self.activity.record_sent();
         Ok(())
     }
 
@@ -70,11 +182,13 @@ impl WsConnection {
             .send(Message::binary(data))
             .await
             .context("Failed to send binary message")?;
+        self.activity.record_sent();
         Ok(())
     }
 
     /// Close the WebSocket connection
     pub async fn close(&self, close_payload: Option<WsClosePayload>) -> Result<()> {
+        self.activity.mark_closing();
         let mut sender = self.sender.lock().await;
 
         let close_message = match close_payload {
@@ -110,7 +224,25 @@ pub fn get_connection(id: u64) -> Option<Arc<WsConnection>> {
 
 /// Remove a WebSocket connection
 pub fn remove_connection(id: u64) {
-    WS_CONNECTIONS.remove(&id);
+    take_connection(id);
+}
+
+/// Remove a WebSocket connection, returning it so the caller can close it.
+pub fn take_connection(id: u64) -> Option<Arc<WsConnection>> {
+    let (_, connection) = WS_CONNECTIONS.remove(&id)?;
+    release(Resource::WebSocket, &id.to_string());
+    Some(connection)
+}
+
+/// Number of WebSocket connections that are still registered.
+pub fn connection_count() -> usize {
+    WS_CONNECTIONS.len()
+}
+
+/// Remove every registered connection, returning them so the caller can close them.
+pub fn take_all_connections() -> Vec<Arc<WsConnection>> {
+    let ids: Vec<u64> = WS_CONNECTIONS.iter().map(|entry| *entry.key()).collect();
+    ids.into_iter().filter_map(take_connection).collect()
 }
 
 /// Create WebSocket connection
@@ -121,6 +253,8 @@ pub async fn connect_websocket(
     futures_util::stream::SplitStream<WebSocket>,
     WebSocketUpgradeMetadata,
 )> {
+    ensure_accepting()?;
+
     // Build client with emulation and proxy
     let emulation = EmulationOption::builder()
         .emulation(options.emulation)
@@ -147,25 +281,28 @@ pub async fn connect_websocket_with_session(
     session_id: &str,
     transport_id: &str,
     url: &str,
-    headers: &[(String, String)],
+    headers: &[(String, HeaderValue)],
     protocols: &[String],
 ) -> Result<(
     WsConnection,
     futures_util::stream::SplitStream<WebSocket>,
     WebSocketUpgradeMetadata,
 )> {
+    ensure_accepting()?;
     let client = get_transport_client(transport_id)?;
     let cookie_jar = get_session_cookie_jar(session_id)?;
 
     // Extract cookies from the jar for this URL and inject as a Cookie header
     let uri: wreq::Uri = url.parse().context("Failed to parse WebSocket URL")?;
+    check_transport_egress(transport_id, &uri)?;
     let cookies = cookie_jar.cookies(&uri);
 
-    let mut all_headers: Vec<(String, String)> = Vec::with_capacity(headers.len() + 1);
+    let mut all_headers: Vec<(String, HeaderValue)> = Vec::with_capacity(headers.len() + 1);
     let mut cookie_segments: Vec<String> = Vec::new();
 
     for (key, value) in headers.iter() {
         if key.eq_ignore_ascii_case("cookie") {
+            let value = header_text(value);
             if !value.trim().is_empty() {
                 cookie_segments.push(value.trim().to_string());
             }
@@ -199,7 +336,9 @@ pub async fn connect_websocket_with_session(
     }
 
     if !cookie_segments.is_empty() {
-        all_headers.push(("Cookie".to_string(), cookie_segments.join("; ")));
+        let cookie =
+            HeaderValue::try_from(cookie_segments.join("; ")).context("Invalid Cookie header")?;
+        all_headers.push(("Cookie".to_string(), cookie));
     }
 
     connect_websocket_with_client(&client, url, &all_headers, protocols).await
@@ -208,7 +347,7 @@ pub async fn connect_websocket_with_session(
 /// Build an OrigHeaderMap with Title-Case header names for HTTP/1.1 WebSocket
 /// upgrade requests. Without this, wreq writes lowercase header names which
 /// Cloudflare's bot detection flags as non-browser traffic (403).
-fn build_ws_orig_headers(user_headers: &[(String, String)]) -> OrigHeaderMap {
+fn build_ws_orig_headers(user_headers: &[(String, HeaderValue)]) -> OrigHeaderMap {
     let mut orig = OrigHeaderMap::new();
 
     // Standard headers in browser-typical order with Title-Case.
@@ -249,7 +388,7 @@ fn build_ws_orig_headers(user_headers: &[(String, String)]) -> OrigHeaderMap {
 async fn connect_websocket_with_client(
     client: &wreq::Client,
     url: &str,
-    headers: &[(String, String)],
+    headers: &[(String, HeaderValue)],
     protocols: &[String],
 ) -> Result<(
     WsConnection,
@@ -261,7 +400,7 @@ async fn connect_websocket_with_client(
 
     // Apply custom headers
     for (key, value) in headers.iter() {
-        request = request.header(key, value);
+        request = request.header(key, value.clone());
     }
 
     if !protocols.is_empty() {
@@ -1,8 +1,12 @@
 import { type ChildProcess, spawn } from "node:child_process";
 import { existsSync } from "node:fs";
+import { rm } from "node:fs/promises";
 import { createServer, type IncomingMessage, type ServerResponse } from "node:http";
 import type { AddressInfo, Socket } from "node:net";
-import { resolve } from "node:path";
+import { tmpdir } from "node:os";
+import { join, resolve } from "node:path";
+import { Readable } from "node:stream";
+import { setTimeout as delay } from "node:timers/promises";
 import { fileURLToPath } from "node:url";
 
 export type ServerKind = "rust" | "node";
@@ -10,9 +14,37 @@ export type ServerKind = "rust" | "node";
 export interface LocalBenchServer {
   baseUrl: string;
   kind: ServerKind;
+  /** HTTPS listener with a freshly generated self-signed certificate, when `tls` was requested. */
+  tls?: {
+    baseUrl: string;
+    /** PEM file with the certificate, to trust it as a CA. Removed on close. */
+    certPath: string;
+  };
+  /** `http://127.0.0.1:<port>` of the CONNECT proxy, when `proxy` was requested. */
+  proxyUrl?: string;
+  /** HTTPS listener that requires a client certificate, when `mtls` was requested. */
+  mtls?: {
+    baseUrl: string;
+    /** PEM files, removed on close: the server certificate to trust, and the client certificate and key. */
+    caPath: string;
+    certPath: string;
+    keyPath: string;
+  };
   close(): Promise<void>;
 }
 
+export interface BenchServerOptions {
+  /** Also start an HTTPS listener. Only the Rust bench server supports it. */
+  tls?: boolean;
+  /**
+   * Also start an HTTP CONNECT proxy, optionally requiring Basic credentials. Only the Rust bench
+   * server supports it.
+   */
+  proxy?: boolean | { username: string; password: string };
+  /** Also start an HTTPS listener that requires a client certificate. Only the Rust bench server supports it. */
+  mtls?: boolean;
+}
+
 type BenchRoute = {
   method: string;
   path: string;
@@ -22,6 +54,11 @@ const SMALL_BODY = Buffer.from("OK", "utf8");
 const JSON_BODY = Buffer.from('{"ok":true,"message":"hello"}', "utf8");
 const BINARY_4K_BODY = Buffer.alloc(4096, 0xab);
 
+const MAX_DRIP_CHUNKS = 10_000;
+const MAX_DRIP_CHUNK_SIZE = 1024 * 1024;
+const MAX_DELAY_MS = 60_000;
+const MAX_REDIRECT_HOPS = 100;
+
 function findBenchServerBinary(): string | null {
   const thisFile = fileURLToPath(import.meta.url);
   const projectRoot = resolve(thisFile, "..", "..", "..");
@@ -29,20 +66,34 @@ function findBenchServerBinary(): string | null {
   return existsSync(binary) ? binary : null;
 }
 
-function startRustBenchServer(): Promise<LocalBenchServer | null> {
+function startRustBenchServer(options: BenchServerOptions): Promise<LocalBenchServer | null> {
   const binary = findBenchServerBinary();
   if (!binary) return Promise.resolve(null);
 
+  // The server prints the HTTP port, then the port of each optional listener: HTTPS, proxy, mTLS.
+  const certPath = options.tls ? join(tmpdir(), `wreq-bench-server-${process.pid}.crt`) : null;
+  const mtlsDir = options.mtls ? join(tmpdir(), `wreq-bench-server-${process.pid}-mtls`) : null;
+  const args = certPath ? ["--tls-cert", certPath] : [];
+  if (typeof options.proxy === "object") {
+    args.push("--proxy-auth", `${options.proxy.username}:${options.proxy.password}`);
+  } else if (options.proxy) {
+    args.push("--proxy");
+  }
+  if (mtlsDir) {
+    args.push("--mtls-dir", mtlsDir);
+  }
+  const expectedLines = 1 + (certPath ? 1 : 0) + (options.proxy ? 1 : 0) + (mtlsDir ? 1 : 0);
+
   return new Promise<LocalBenchServer | null>((resolvePromise) => {
     const serverCpu = process.env.BENCH_SERVER_CPU;
     let proc: ChildProcess;
 
     if (serverCpu && process.platform === "linux") {
-      proc = spawn("taskset", ["-c", serverCpu, binary], {
+      proc = spawn("taskset", ["-c", serverCpu, binary, ...args], {
         stdio: ["ignore", "pipe", "pipe"],
       });
     } else {
-      proc = spawn(binary, [], {
+      proc = spawn(binary, args, {
         stdio: ["ignore", "pipe", "pipe"],
       });
     }
@@ -77,12 +128,11 @@ function startRustBenchServer(): Promise<LocalBenchServer | null> {
     proc.stdout?.on("data", (chunk: Buffer) => {
       if (settled) return;
       stdoutBuf += chunk.toString();
-      const newlineIdx = stdoutBuf.indexOf("\n");
-      if (newlineIdx === -1) return;
+      const lines = stdoutBuf.split("\n");
+      if (lines.length <= expectedLines) return;
 
-      const portStr = stdoutBuf.slice(0, newlineIdx).trim();
-      const port = Number(portStr);
-      if (!Number.isFinite(port) || port <= 0) {
+      const ports = lines.slice(0, expectedLines).map((line) => Number(line.trim()));
+      if (ports.some((port) => !Number.isFinite(port) || port <= 0)) {
         settled = true;
         clearTimeout(timeout);
         proc.kill("SIGKILL");
@@ -93,7 +143,7 @@ function startRustBenchServer(): Promise<LocalBenchServer | null> {
       settled = true;
       clearTimeout(timeout);
 
-      const baseUrl = `http://127.0.0.1:${port}`;
+      const baseUrl = `http://127.0.0.1:${ports[0]}`;
       const close = async () => {
         proc.kill("SIGTERM");
         await new Promise<void>((r) => {
@@ -105,16 +155,43 @@ function startRustBenchServer(): Promise<LocalBenchServer | null> {
             r();
           });
         });
+        if (certPath) {
+          await rm(certPath, { force: true });
+        }
+        if (mtlsDir) {
+          await rm(mtlsDir, { recursive: true, force: true });
+        }
       };
 
-      resolvePromise({ baseUrl, kind: "rust", close });
+      const server: LocalBenchServer = { baseUrl, kind: "rust", close };
+      const extraPorts = ports.slice(1);
+      if (certPath) {
+        server.tls = { baseUrl: `https://127.0.0.1:${extraPorts.shift()}`, certPath };
+      }
+      if (options.proxy) {
+        server.proxyUrl = `http://127.0.0.1:${extraPorts.shift()}`;
+      }
+      if (mtlsDir) {
+        server.mtls = {
+          baseUrl: `https://127.0.0.1:${extraPorts.shift()}`,
+          caPath: join(mtlsDir, "server.crt"),
+          certPath: join(mtlsDir, "client.crt"),
+          keyPath: join(mtlsDir, "client.key"),
+        };
+      }
+      resolvePromise(server);
     });
   });
 }
 
-export async function startBenchServer(): Promise<LocalBenchServer> {
-  const rust = await startRustBenchServer();
+export async function startBenchServer(options: BenchServerOptions = {}): Promise<LocalBenchServer> {
+  const rust = await startRustBenchServer(options);
   if (rust) return rust;
+  if (options.tls || options.proxy || options.mtls) {
+    throw new Error(
+      "The HTTPS, proxy and mTLS bench listeners need the Rust bench server; run `npm run build:bench-server`",
+    );
+  }
   return startLocalBenchServer();
 }
 
@@ -198,6 +275,130 @@ export async function startLocalBenchServer(): Promise<LocalBenchServer> {
       return sendBytes(res, 200, "application/octet-stream", payload);
     }
 
+    if (route.method === "GET" && (route.path === "/delay" || route.path === "/delay-headers")) {
+      const delayMs = clampParam(url.searchParams.get("ms"), 0, MAX_DELAY_MS, 1000);
+
+      // /delay-headers holds back the whole response; /delay sends the headers right away and
+      // holds back the body.
+      if (route.path === "/delay-headers") {
+        await delay(delayMs);
+        return sendBytes(res, 200, "text/plain; charset=utf-8", SMALL_BODY);
+      }
+
+      res.statusCode = 200;
+      res.setHeader("Content-Type", "text/plain; charset=utf-8");
+      res.setHeader("Content-Length", String(SMALL_BODY.length));
+      res.flushHeaders();
+      await delay(delayMs);
+      res.end(SMALL_BODY);
+      return;
+    }
+
+    if (route.method === "GET" && route.path === "/drip") {
+      const chunks = clampParam(url.searchParams.get("chunks"), 0, MAX_DRIP_CHUNKS, 10);
+      const delayMs = clampParam(url.searchParams.get("delay_ms"), 0, MAX_DELAY_MS, 100);
+      const size = clampParam(url.searchParams.get("size"), 1, MAX_DRIP_CHUNK_SIZE, 1024);
+      const chunk = Buffer.alloc(size, 0xab);
+
+      // No Content-Length, so the body goes out chunked.
+      res.statusCode = 200;
+      res.setHeader("Content-Type", "application/octet-stream");
+      for (let i = 0; i < chunks && !res.destroyed; i += 1) {
+        if (i > 0) {
+          await delay(delayMs);
+        }
+        res.write(chunk);
+      }
+      res.end();
+      return;
+    }
+
+    if (route.method === "GET" && route.path === "/events") {
+      // A count of 0 streams until the client goes away.
+      const count = clampParam(url.searchParams.get("count"), 0, Number.MAX_SAFE_INTEGER, 10);
+      const intervalMs = clampParam(url.searchParams.get("interval_ms"), 0, MAX_DELAY_MS, 1000);
+
+      res.statusCode = 200;
+      res.setHeader("Content-Type", "text/event-stream");
+      res.setHeader("Cache-Control", "no-cache");
+      for (let id = 1; (count === 0 || id <= count) && !res.destroyed; id += 1) {
+        if (id > 1) {
+          await delay(intervalMs);
+        }
+        res.write(`id: ${id}\nevent: message\ndata: {"id":${id}}\n\n`);
+      }
+      res.end();
+      return;
+    }
+
+    if (route.method === "GET" && route.path === "/set-cookie") {
+      const name = rawQueryValue(url, "name");
+      const value = rawQueryValue(url, "value");
+      if (name === null || value === null) {
+        return sendBytes(res, 400, "text/plain; charset=utf-8", Buffer.from("name and value are required"));
+      }
+      res.setHeader("Set-Cookie", `${name}=${value}; Path=/`);
+      return sendBytes(res, 200, "text/plain; charset=utf-8", SMALL_BODY);
+    }
+
+    if (route.method === "GET" && route.path === "/cookies") {
+      const cookies: Record<string, string> = {};
+      for (const pair of (req.headers.cookie ?? "").split(";")) {
+        const separator = pair.indexOf("=");
+        if (separator !== -1) {
+          cookies[pair.slice(0, separator).trim()] = pair.slice(separator + 1).trim();
+        }
+      }
+      return sendBytes(res, 200, "application/json; charset=utf-8", Buffer.from(JSON.stringify({ cookies })));
+    }
+
+    const redirect = /^\/(redirect|absolute-redirect)\/([^/]+)$/.exec(route.path);
+    if (route.method === "GET" && redirect) {
+      const [, kind, count] = redirect;
+      const hops = Number(count);
+      if (!Number.isInteger(hops) || hops < 0 || hops > MAX_REDIRECT_HOPS) {
+        const message = `hop count must be 0..=${MAX_REDIRECT_HOPS}`;
+        return sendBytes(res, 400, "text/plain; charset=utf-8", Buffer.from(message));
+      }
+
+      if (hops === 0) {
+        const body = JSON.stringify({ redirected: true, path: route.path });
+        return sendBytes(res, 200, "application/json; charset=utf-8", Buffer.from(body));
+      }
+
+      const location =
+        kind === "redirect" ? `/redirect/${hops - 1}` : `http://${req.headers.host}/absolute-redirect/${hops - 1}`;
+      res.setHeader("Location", location);
+      return sendBytes(res, 302, "text/plain; charset=utf-8", Buffer.alloc(0));
+    }
+
+    if (route.method === "POST" && route.path === "/multipart") {
+      const contentType = req.headers["content-type"] ?? "";
+      if (!contentType.startsWith("multipart/form-data")) {
+        return sendBytes(res, 400, "text/plain; charset=utf-8", Buffer.from("expected a multipart/form-data body"));
+      }
+
+      // Unlike the Rust server, this buffers the body before parsing it.
+      let form: FormData;
+      try {
+        form = await new Request(url, {
+          method: "POST",
+          headers: { "content-type": contentType },
+          body: Readable.toWeb(req) as ReadableStream<Uint8Array>,
+          duplex: "half",
+        }).formData();
+      } catch (error) {
+        return sendBytes(res, 400, "text/plain; charset=utf-8", Buffer.from(String(error)));
+      }
+
+      const parts = [...form.entries()].map(([name, value]) =>
+        typeof value === "string"
+          ? { name, filename: null, contentType: null, size: Buffer.byteLength(value) }
+          : { name, filename: value.name, contentType: value.type || null, size: value.size },
+      );
+      return sendBytes(res, 200, "application/json; charset=utf-8", Buffer.from(JSON.stringify({ parts })));
+    }
+
     if (route.method === "POST" && route.path === "/echo-len") {
       const expectedLength = Number(url.searchParams.get("len") ?? "0");
       const received = await drainBody(req, expectedLength);
@@ -227,6 +428,25 @@ function sendBytes(res: ServerResponse, status: number, contentType: string, bod
   res.end(body);
 }
 
+// Query value as sent, without percent-decoding, to match the Rust bench server.
+function rawQueryValue(url: URL, key: string): string | null {
+  for (const pair of url.search.slice(1).split("&")) {
+    const separator = pair.indexOf("=");
+    if (separator !== -1 && pair.slice(0, separator) === key) {
+      return pair.slice(separator + 1);
+    }
+  }
+  return null;
+}
+
+function clampParam(value: string | null, min: number, max: number, fallback: number): number {
+  const parsed = Number(value);
+  if (value === null || value === "" || !Number.isInteger(parsed)) {
+    return fallback;
+  }
+  return Math.min(Math.max(parsed, min), max);
+}
+
 async function drainBody(req: IncomingMessage, maxBytes: number): Promise<number> {
   let total = 0;
   for await (const chunk of req) {
@@ -116,6 +116,21 @@ export type BrowserProfile =
   | 'okhttp_4.12'
   | 'okhttp_5';
 
+/**
+ * Browser families that profile aliases resolve against
+ */
+export type BrowserFamily =
+  | 'chrome'
+  | 'edge'
+  | 'opera'
+  | 'firefox'
+  | 'firefox_private'
+  | 'firefox_android'
+  | 'safari_ios'
+  | 'safari'
+  | 'safari_ipad'
+  | 'okhttp';
+
 /**
  * Operating systems supported for emulation
  */
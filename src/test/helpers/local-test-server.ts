@@ -1,11 +1,13 @@
-import { createHash } from "node:crypto";
+import { createHash, createHmac, randomBytes } from "node:crypto";
 import { readFileSync } from "node:fs";
 import { createServer, type IncomingMessage, type ServerResponse } from "node:http";
+import { createSecureServer as createHttp2Server, type ServerHttp2Stream } from "node:http2";
 import { createServer as createHttpsServer } from "node:https";
 import type { AddressInfo, Socket } from "node:net";
 import { dirname, resolve } from "node:path";
 import { setTimeout as delay } from "node:timers/promises";
 import { fileURLToPath } from "node:url";
+import { gzipSync } from "node:zlib";
 
 const WS_MAGIC_STRING = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
 
@@ -17,6 +19,7 @@ export interface LocalTestServer {
   wsUrl: string;
   httpsSelfSignedUrl: string;
   httpsExpiredUrl: string;
+  httpsH2Url: string;
   close(): Promise<void>;
 }
 
@@ -27,6 +30,11 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
 
   const sockets = new Set<Socket>();
   const hangingRequests = new Map<string, { closed: boolean }>();
+  const requestCounters = new Map<string, number>();
+  // Bearer tokens issued by /auth/token, per realm, so concurrent tests do not share them.
+  const authRealms = new Map<string, { token: string | null; issued: number }>();
+  // NTLM challenges sent by /ntlm, per connection: the handshake must stay on one socket.
+  const ntlmChallenges = new WeakMap<Socket, Buffer>();
 
   // Load certificates for HTTPS servers
   const selfSignedKey = readFileSync(resolve(CERTS_DIR, "self-signed.key"));
@@ -70,6 +78,12 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
     createRequestHandler(() => expiredBaseUrl),
   );
 
+  // HTTP/2-only server with the self-signed certificate, for raw stream tests
+  const h2Server = createHttp2Server({ key: selfSignedKey, cert: selfSignedCert, allowHTTP1: false });
+  h2Server.on("stream", (stream, headers) => {
+    handleHttp2Stream(stream, headers);
+  });
+
   server.on("connection", trackSockets);
   selfSignedServer.on("connection", trackSockets);
   selfSignedServer.on("secureConnection", trackSockets);
@@ -180,6 +194,28 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
   }
   expiredBaseUrl = `https://127.0.0.1:${expiredAddress.port}`;
 
+  // Start HTTP/2 server
+  await new Promise<void>((resolve, reject) => {
+    const onError = (error: unknown) => {
+      h2Server.off("listening", onListening);
+      reject(error);
+    };
+    const onListening = () => {
+      h2Server.off("error", onError);
+      resolve();
+    };
+
+    h2Server.once("error", onError);
+    h2Server.once("listening", onListening);
+    h2Server.listen(0, "127.0.0.1");
+  });
+
+  const h2Address = h2Server.address() as AddressInfo | null;
+  if (!h2Address) {
+    throw new Error("Unable to determine HTTP/2 server address");
+  }
+  const h2BaseUrl = `https://127.0.0.1:${h2Address.port}`;
+
   const close = async () => {
     for (const socket of sockets) {
       socket.destroy();
@@ -195,6 +231,9 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
       new Promise<void>((resolve, reject) => {
         expiredServer.close((error) => (error ? reject(error) : resolve()));
       }),
+      new Promise<void>((resolve, reject) => {
+        h2Server.close((error) => (error ? reject(error) : resolve()));
+      }),
     ]);
   };
 
@@ -203,9 +242,113 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
     wsUrl,
     httpsSelfSignedUrl: selfSignedBaseUrl,
     httpsExpiredUrl: expiredBaseUrl,
+    httpsH2Url: h2BaseUrl,
     close,
   };
 
+  // NTLM over HTTP (MS-NLMP) for user "User" with password "Password", checking the NTLMv2 proof.
+  function handleNtlm(req: IncomingMessage, res: ServerResponse) {
+    const [scheme, token] = (req.headers.authorization ?? "").split(" ");
+    const message = scheme === "NTLM" && token ? Buffer.from(token, "base64") : null;
+    const challenge = (header: string) => {
+      res.statusCode = 401;
+      res.setHeader("WWW-Authenticate", header);
+      return json(res, { error: "unauthorized" });
+    };
+
+    if (message === null) {
+      return challenge("NTLM");
+    }
+    if (message.readUInt32LE(8) === 1) {
+      const serverChallenge = randomBytes(8);
+      ntlmChallenges.set(req.socket, serverChallenge);
+      const domain = Buffer.from("TEST", "utf16le");
+      const targetInfo = Buffer.concat([Buffer.from([2, 0, domain.length, 0]), domain, Buffer.alloc(4)]);
+      const header = Buffer.alloc(48);
+      header.write("NTLMSSP\0", 0, "latin1");
+      header.writeUInt32LE(2, 8);
+      header.writeUInt32LE(0x00808201, 20);
+      serverChallenge.copy(header, 24);
+      header.writeUInt16LE(targetInfo.length, 40);
+      header.writeUInt16LE(targetInfo.length, 42);
+      header.writeUInt32LE(48, 44);
+      return challenge(`NTLM ${Buffer.concat([header, targetInfo]).toString("base64")}`);
+    }
+
+    const serverChallenge = ntlmChallenges.get(req.socket);
+    if (serverChallenge === undefined) {
+      return challenge("NTLM");
+    }
+    const field = (at: number) => {
+      const length = message.readUInt16LE(at);
+      const offset = message.readUInt32LE(at + 4);
+      return message.subarray(offset, offset + length);
+    };
+    const ntResponse = field(20);
+    const domain = field(28).toString("utf16le");
+    const user = field(36).toString("utf16le");
+    // MD4("Password" in UTF-16LE); MD4 is not available in every OpenSSL build.
+    const ntHash = Buffer.from("a4f49c406510bdcab6824ee7c30fd852", "hex");
+    const key = createHmac("md5", ntHash)
+      .update(Buffer.from(user.toUpperCase() + domain, "utf16le"))
+      .digest();
+    const proof = createHmac("md5", key)
+      .update(Buffer.concat([serverChallenge, ntResponse.subarray(16)]))
+      .digest();
+    if (user !== "User" || !proof.equals(ntResponse.subarray(0, 16))) {
+      return challenge("NTLM");
+    }
+    return json(res, { user, domain, workstation: field(44).toString("utf16le") });
+  }
+
+  function handleHttp2Stream(stream: ServerHttp2Stream, headers: Record<string, string | string[] | undefined>) {
+    stream.on("error", () => {
+      // Clients may reset streams mid-flight.
+    });
+
+    if (headers[":path"] === "/headers") {
+      // Report the regular header names the stream arrived with.
+      stream.respond({ ":status": 200, "content-type": "application/json" });
+      stream.end(JSON.stringify(Object.keys(headers).filter((name) => !name.startsWith(":"))));
+      return;
+    }
+
+    if (headers[":path"] === "/settings") {
+      // Report the flow-control values the client announced on this connection.
+      const { remoteSettings, state } = stream.session ?? {};
+      stream.respond({ ":status": 200, "content-type": "application/json" });
+      stream.end(
+        JSON.stringify({
+          initialWindowSize: remoteSettings?.initialWindowSize,
+          maxFrameSize: remoteSettings?.maxFrameSize,
+          connectionWindowSize: state?.remoteWindowSize,
+        }),
+      );
+      return;
+    }
+
+    if (headers[":path"] !== "/echo.Echo/Stream") {
+      stream.respond({ ":status": 404 });
+      stream.end();
+      return;
+    }
+
+    // Echo every DATA frame as it arrives, then report what the client sent in trailers.
+    stream.respond(
+      { ":status": 200, "content-type": "application/grpc", "x-method": String(headers[":method"]) },
+      { waitForTrailers: true },
+    );
+    let clientTrailer = "";
+    stream.on("trailers", (trailers: Record<string, string | string[] | undefined>) => {
+      clientTrailer = String(trailers["x-client-trailer"] ?? "");
+    });
+    stream.on("data", (chunk: Buffer) => stream.write(chunk));
+    stream.on("end", () => stream.end());
+    stream.on("wantTrailers", () => {
+      stream.sendTrailers({ "grpc-status": "0", "x-client-trailer": clientTrailer });
+    });
+  }
+
   async function routeHttpRequest(req: IncomingMessage, res: ServerResponse, resolvedBase: string) {
     const url = new URL(req.url ?? "/", resolvedBase);
     const path = url.pathname;
@@ -226,6 +369,24 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
       return json(res, { "user-agent": req.headers["user-agent"] ?? "" });
     }
 
+    if (path === "/gzip") {
+      res.setHeader("Content-Type", "application/json");
+      res.setHeader("Content-Encoding", "gzip");
+      return res.end(gzipSync(JSON.stringify({ gzipped: true, method: req.method })));
+    }
+
+    if (path === "/legacy-charset") {
+      // "Привет" in windows-1251, declared only in the markup.
+      res.setHeader("Content-Type", "text/html");
+      return res.end(
+        Buffer.concat([
+          Buffer.from('<html><head><meta charset="windows-1251"></head><body>'),
+          Buffer.from([0xcf, 0xf0, 0xe8, 0xe2, 0xe5, 0xf2]),
+          Buffer.from("</body></html>"),
+        ]),
+      );
+    }
+
     if (path === "/headers") {
       return json(res, {
         headers: canonicalizeHeaders(req),
@@ -262,6 +423,10 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
     if (path === "/stream/chunks") {
       const chunkCount = Math.max(1, Math.min(Number(url.searchParams.get("n") ?? "3"), 16));
       const chunkSize = Math.max(1, Math.min(Number(url.searchParams.get("size") ?? "1024"), 65536));
+      const headersDelay = Math.max(0, Math.min(Number(url.searchParams.get("delay") ?? "0"), 1000));
+      if (headersDelay > 0) {
+        await delay(headersDelay);
+      }
 
       res.statusCode = 200;
       res.setHeader("Content-Type", "application/octet-stream");
@@ -276,6 +441,18 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
       return;
     }
 
+    if (path === "/trailers") {
+      res.statusCode = 200;
+      res.setHeader("Content-Type", "application/grpc-web+proto");
+      res.setHeader("Trailer", "grpc-status, grpc-message");
+      res.write("frame-1");
+      await delay(5);
+      res.write("frame-2");
+      res.addTrailers({ "grpc-status": "0", "grpc-message": "OK" });
+      res.end();
+      return;
+    }
+
     if (path === "/cookies") {
       return json(res, { cookies: parseCookies(req.headers.cookie) });
     }
@@ -298,6 +475,22 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
       return;
     }
 
+    if (path === "/meta-refresh") {
+      const delaySeconds = url.searchParams.get("delay") ?? "0";
+      const target = url.searchParams.get("url") ?? "/json";
+      res.setHeader("Content-Type", "text/html; charset=utf-8");
+      return res.end(
+        `<html><head><meta http-equiv="refresh" content="${delaySeconds}; url=${target}"></head></html>`,
+      );
+    }
+
+    if (path === "/redirect/to") {
+      res.statusCode = Number(url.searchParams.get("status") ?? "302");
+      res.setHeader("Location", url.searchParams.get("url") ?? "/");
+      res.end();
+      return;
+    }
+
     const delayMatch = path.match(/^\/delay\/(\d+)/);
     if (delayMatch) {
       const seconds = Number(delayMatch[1]);
@@ -305,6 +498,103 @@ export async function startLocalTestServer(): Promise<LocalTestServer> {
       return json(res, { delayed: seconds, ...createEchoPayload(req, url) });
     }
 
+    if (path === "/hsts") {
+      const maxAge = url.searchParams.get("maxAge") ?? "60";
+      res.setHeader("Strict-Transport-Security", `max-age=${maxAge}; includeSubDomains`);
+      return json(res, { hsts: true });
+    }
+
+    if (path === "/accept-ch") {
+      res.setHeader("Accept-CH", url.searchParams.get("hints") ?? "");
+      return json(res, { acceptCh: true });
+    }
+
+    if (path === "/alt-svc") {
+      const clear = url.searchParams.get("clear") === "1";
+      res.setHeader("Alt-Svc", clear ? "clear" : 'h3=":4433"; ma=60, h2="alt.example:8443"; ma=120; persist=1');
+      return json(res, { altSvc: !clear });
+    }
+
+    if (path === "/auth/token" || path === "/auth/protected") {
+      const realm = url.searchParams.get("realm") ?? "default";
+      const state = authRealms.get(realm) ?? { token: null, issued: 0 };
+      authRealms.set(realm, state);
+
+      if (path === "/auth/protected") {
+        if (state.token === null || req.headers.authorization !== `Bearer ${state.token}`) {
+          res.statusCode = 401;
+          res.setHeader("WWW-Authenticate", 'Bearer error="invalid_token"');
+          return json(res, { error: "invalid_token" });
+        }
+        return json(res, { token: state.token, issued: state.issued });
+      }
+
+      let raw = "";
+      for await (const chunk of req) {
+        raw += chunk;
+      }
+      const form = new URLSearchParams(raw);
+      if (form.get("grant_type") === "refresh_token" && form.get("refresh_token") !== "r1") {
+        res.statusCode = 400;
+        return json(res, { error: "invalid_grant", error_description: "unknown refresh token" });
+      }
+      // Slow enough that concurrent requests pile up behind one refresh.
+      await delay(50);
+      state.issued += 1;
+      state.token = `${realm}-${state.issued}`;
+      return json(res, { access_token: state.token, token_type: "Bearer", expires_in: 3600, refresh_token: "r1" });
+    }
+
+    if (path === "/ntlm") {
+      return handleNtlm(req, res);
+    }
+
+    if (path === "/upload") {
+      let received = 0;
+      for await (const chunk of req) {
+        received += (chunk as Buffer).length;
+      }
+      return json(res, { received, contentLength: req.headers["content-length"] ?? null });
+    }
+
+    if (path === "/counter") {
+      const id = url.searchParams.get("id");
+
+      if (!id) {
+        res.statusCode = 400;
+        return json(res, { error: "id query param required" });
+      }
+
+      const hits = (requestCounters.get(id) ?? 0) + 1;
+      requestCounters.set(id, hits);
+      await delay(200);
+      return json(res, { hits });
+    }
+
+    if (path === "/status/reason") {
+      res.statusCode = Number(url.searchParams.get("status") ?? "429");
+      res.statusMessage = url.searchParams.get("reason") ?? "Quota Exceeded";
+      return json(res, { status: res.statusCode });
+    }
+
+    if (path === "/flaky") {
+      const id = url.searchParams.get("id");
+
+      if (!id) {
+        res.statusCode = 400;
+        return json(res, { error: "id query param required" });
+      }
+
+      // Answers 503 to the first `failures` requests for an id, then succeeds.
+      const hits = (requestCounters.get(id) ?? 0) + 1;
+      requestCounters.set(id, hits);
+      if (hits <= Number(url.searchParams.get("failures") ?? "0")) {
+        res.statusCode = 503;
+        res.setHeader("Retry-After", "0");
+      }
+      return json(res, { hits });
+    }
+
     if (path === "/hang") {
       const id = url.searchParams.get("id");
 
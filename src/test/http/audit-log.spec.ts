@@ -0,0 +1,113 @@
+import assert from "node:assert";
+import { mkdtemp, readFile, rm } from "node:fs/promises";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { describe, test } from "node:test";
+import { createSession, disableAuditLog, enableAuditLog, RequestError, fetch as wreqFetch } from "../../wreq-js.js";
+import { httpUrl } from "../helpers/http.js";
+
+type AuditLine = {
+  timestamp: string;
+  method: string;
+  url: string;
+  status: number | null;
+  error: string | null;
+  requestBytes: number;
+  responseBytes: number | null;
+  durationMs: number;
+  proxy: string | null;
+  sessionId: string | null;
+  requestBody?: { body?: string };
+  responseBody?: { body?: string };
+};
+
+async function readLines(path: string): Promise<AuditLine[]> {
+  const text = await readFile(path, "utf8");
+  return text
+    .split("\n")
+    .filter((line) => line.length > 0)
+    .map((line) => JSON.parse(line) as AuditLine);
+}
+
+describe("Audit log", () => {
+  test("writes one JSON line per request", async () => {
+    const dir = await mkdtemp(join(tmpdir(), "wreq-audit-"));
+    const path = join(dir, "audit.jsonl");
+
+    try {
+      enableAuditLog(path);
+      try {
+        await (await wreqFetch(httpUrl("/json"))).text();
+        await (await wreqFetch(httpUrl("/upload"), { method: "POST", body: "hello" })).text();
+        await assert.rejects(wreqFetch("http://127.0.0.1:1/unreachable", { timeout: 2_000 }), RequestError);
+
+        const session = await createSession({ browser: "chrome_142" });
+        try {
+          await (await session.fetch(httpUrl("/json"))).text();
+        } finally {
+          await session.close();
+        }
+      } finally {
+        disableAuditLog();
+      }
+
+      const lines = await readLines(path);
+      assert.strictEqual(lines.length, 4);
+
+      const [get, upload, failed, sessioned] = lines;
+      assert.ok(get && upload && failed && sessioned);
+      assert.ok(!Number.isNaN(Date.parse(get.timestamp)));
+      assert.strictEqual(get.method, "GET");
+      assert.strictEqual(get.url, httpUrl("/json"));
+      assert.strictEqual(get.status, 200);
+      assert.strictEqual(get.error, null);
+      assert.strictEqual(get.sessionId, null);
+      assert.ok(get.durationMs >= 0);
+      assert.strictEqual(get.requestBody, undefined);
+
+      assert.strictEqual(upload.method, "POST");
+      assert.strictEqual(upload.requestBytes, 5);
+      assert.ok((upload.responseBytes ?? 0) > 0);
+
+      assert.strictEqual(failed.status, null);
+      assert.ok(failed.error);
+
+      assert.strictEqual(typeof sessioned.sessionId, "string");
+
+      await (await wreqFetch(httpUrl("/json"))).text();
+      assert.strictEqual((await readLines(path)).length, 4);
+    } finally {
+      await rm(dir, { recursive: true, force: true });
+    }
+  });
+
+  test("includes bodies and rotates by size", async () => {
+    const dir = await mkdtemp(join(tmpdir(), "wreq-audit-"));
+    const path = join(dir, "audit.jsonl");
+
+    try {
+      enableAuditLog(path, { includeBodies: true, rotateBytes: 1 });
+      try {
+        await (await wreqFetch(httpUrl("/upload"), { method: "POST", body: "first" })).text();
+        await (await wreqFetch(httpUrl("/upload"), { method: "POST", body: "second" })).text();
+      } finally {
+        disableAuditLog();
+      }
+
+      const [rotated] = await readLines(`${path}.1`);
+      const [current] = await readLines(path);
+      assert.strictEqual(rotated?.requestBody?.body, "first");
+      assert.strictEqual(current?.requestBody?.body, "second");
+      assert.deepStrictEqual(JSON.parse(current?.responseBody?.body ?? "{}").received, 6);
+    } finally {
+      await rm(dir, { recursive: true, force: true });
+    }
+  });
+
+  test("validates options", () => {
+    assert.throws(() => enableAuditLog(""), RequestError);
+    assert.throws(() => enableAuditLog("audit.jsonl", { rotateBytes: 0 }), RequestError);
+    assert.throws(() => enableAuditLog(join(tmpdir(), "missing-dir", "nested", "audit.jsonl")), RequestError);
+    disableAuditLog();
+  });
+});
@@ -0,0 +1,74 @@
+import assert from "node:assert";
+import { randomUUID } from "node:crypto";
+import { mkdtemp, readFile, rm } from "node:fs/promises";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { describe, test } from "node:test";
+import { RequestError, useCassette, fetch as wreqFetch } from "../../wreq-js.js";
+import { httpUrl } from "../helpers/http.js";
+
+describe("Cassettes", () => {
+  test("records interactions and replays them without the network", async () => {
+    const dir = await mkdtemp(join(tmpdir(), "wreq-cassette-"));
+    const path = join(dir, "cassette.json");
+    const counterUrl = httpUrl(`/counter?id=${randomUUID()}`);
+    const binaryUrl = httpUrl("/binary?len=300");
+    const uploadUrl = httpUrl("/upload");
+    let binary = Buffer.alloc(0);
+
+    try {
+      const recorder = useCassette(path, { mode: "record" });
+      try {
+        assert.throws(
+          () => useCassette(join(dir, "other.json"), { mode: "record" }),
+          (error: unknown) => error instanceof RequestError && /already in use/.test(error.message),
+        );
+
+        assert.deepStrictEqual(await (await wreqFetch(counterUrl)).json(), { hits: 1 });
+        assert.deepStrictEqual(await (await wreqFetch(counterUrl)).json(), { hits: 2 });
+        binary = Buffer.from(await (await wreqFetch(binaryUrl)).arrayBuffer());
+        assert.strictEqual(binary.length, 300);
+        await (await wreqFetch(uploadUrl, { method: "POST", body: "a" })).text();
+        await (await wreqFetch(uploadUrl, { method: "POST", body: "bb" })).text();
+      } finally {
+        assert.strictEqual(recorder.eject(), 5);
+      }
+
+      const saved = JSON.parse(await readFile(path, "utf8")) as { interactions: unknown[] };
+      assert.strictEqual(saved.interactions.length, 5);
+
+      const player = useCassette(path, { mode: "replay", matchOn: ["method", "url", "body"] });
+      try {
+        // Recorded order is preserved, then the last match repeats.
+        assert.deepStrictEqual(await (await wreqFetch(counterUrl)).json(), { hits: 1 });
+        assert.deepStrictEqual(await (await wreqFetch(counterUrl)).json(), { hits: 2 });
+        assert.deepStrictEqual(await (await wreqFetch(counterUrl)).json(), { hits: 2 });
+
+        const replayed = Buffer.from(await (await wreqFetch(binaryUrl)).arrayBuffer());
+        assert.deepStrictEqual(replayed, binary);
+
+        const upload = await wreqFetch(uploadUrl, { method: "POST", body: "bb" });
+        assert.strictEqual((await upload.json<{ received: number }>()).received, 2);
+
+        await assert.rejects(
+          wreqFetch(uploadUrl, { method: "POST", body: "ccc" }),
+          (error: unknown) => error instanceof RequestError && /no recorded interaction/.test(error.message),
+        );
+      } finally {
+        player.eject();
+      }
+    } finally {
+      await rm(dir, { recursive: true, force: true });
+    }
+  });
+
+  test("validates cassette options", async () => {
+    assert.throws(() => useCassette("", { mode: "record" }), RequestError);
+    assert.throws(() => useCassette("cassette.json", { mode: "once" as never }), RequestError);
+    assert.throws(
+      () => useCassette("cassette.json", { mode: "record", matchOn: ["headers" as never] }),
+      RequestError,
+    );
+    assert.throws(() => useCassette(join(tmpdir(), `${randomUUID()}.json`), { mode: "replay" }), RequestError);
+  });
+});
@@ -458,6 +458,19 @@ describe("Response helpers", () => {
     bodyHandle: null,
     bodyBytes: null,
     contentLength: null,
+    connection: {
+      reused: false,
+      client: "dedicated",
+      transportId: null,
+      localAddress: null,
+      remoteAddress: null,
+      dns: null,
+    },
+    bodyDigest: null,
+    trailers: null,
+    headerBytes: 0,
+    bodySize: null,
+    timing: null,
     cookies: [],
     url: "http://example.com/final",
     ...overrides,
@@ -499,6 +512,27 @@ describe("Response helpers", () => {
     assert.strictEqual(unknown.statusText, "");
   });
 
+  test("decodes compact header fields", () => {
+    const response = new Response(
+      makePayload({
+        headers: undefined,
+        cookies: undefined,
+        headerFields: JSON.stringify([
+          ["x-test", "alpha", "x-test", "beta"],
+          ["session", "one"],
+        ]),
+      }) as never,
+      "http://example.com/final",
+    );
+
+    assert.strictEqual(response.headers.get("x-test"), "alpha, beta");
+    assert.deepStrictEqual(response.cookies.session, ["one"]);
+
+    const clone = response.clone();
+    assert.strictEqual(clone.headers.get("x-test"), "alpha, beta");
+    assert.deepStrictEqual(clone.cookies.session, ["one"]);
+  });
+
   test("handles inline bodies, streams, and clone safety", async () => {
     const inline = new Response(
       makePayload({ bodyBytes: Buffer.from("hello"), contentLength: 5 }) as never,
@@ -1,6 +1,6 @@
 import assert from "node:assert";
 import { describe, test } from "node:test";
-import { fetch as wreqFetch } from "../../wreq-js.js";
+import { cancelAllRequests, cancelGroup, RequestError, fetch as wreqFetch } from "../../wreq-js.js";
 import { httpUrl } from "../helpers/http.js";
 
 describe("HTTP errors", () => {
@@ -14,6 +14,7 @@ describe("HTTP errors", () => {
       },
       {
         name: "RequestError",
+        code: "ERR_TIMEOUT",
       },
       "Should throw an error on timeout",
     );
@@ -35,4 +36,65 @@ describe("HTTP errors", () => {
       "Should reject with AbortError",
     );
   });
+
+  test("cancels every request in a group", async () => {
+    const grouped = [
+      wreqFetch(httpUrl("/delay/10"), { groupId: "cancel-me", timeout: 20_000 }),
+      wreqFetch(httpUrl("/delay/10"), { groupId: "cancel-me", timeout: 20_000 }),
+    ];
+    const other = wreqFetch(httpUrl("/get"), { groupId: "keep-me" });
+
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    cancelGroup("cancel-me");
+
+    for (const pending of grouped) {
+      await assert.rejects(pending, (error: unknown) => {
+        return error instanceof RequestError && /aborted/i.test(error.message);
+      });
+    }
+    assert.strictEqual((await other).status, 200);
+  });
+
+  test("cancelGroup rejects with the caller's reason", async () => {
+    const pending = wreqFetch(httpUrl("/delay/10"), { groupId: "with-reason", timeout: 20_000 });
+
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    cancelGroup("with-reason", "crawl budget exhausted");
+
+    await assert.rejects(pending, {
+      name: "RequestError",
+      code: "ERR_ABORTED",
+      message: "crawl budget exhausted",
+    });
+  });
+
+  test("cancelAllRequests aborts requests without a signal", async () => {
+    const pending = wreqFetch(httpUrl("/delay/10"), { timeout: 20_000 });
+
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    cancelAllRequests();
+
+    await assert.rejects(pending, (error: unknown) => {
+      return error instanceof RequestError && /aborted/i.test(error.message);
+    });
+  });
+
+  test("exposes the native error chain via cause", async () => {
+    const error = await wreqFetch("http://127.0.0.1:1/unreachable", { timeout: 5_000 }).then(
+      () => undefined,
+      (err: unknown) => err,
+    );
+
+    assert.ok(error instanceof RequestError, "Should reject with RequestError");
+    assert.ok(error.cause instanceof Error, "Top-level error should link to its cause");
+
+    let depth = 0;
+    let current: unknown = error.cause;
+    while (current instanceof Error) {
+      assert.ok(current.message.length > 0, "Every cause should carry a message");
+      depth += 1;
+      current = current.cause;
+    }
+    assert.ok(depth >= 1);
+  });
 });
@@ -0,0 +1,103 @@
+import assert from "node:assert";
+import { describe, test } from "node:test";
+import { createTransport, type H2StreamFrame, RequestError } from "../../wreq-js.js";
+
+const H2_URL = process.env.HTTPS_H2_URL;
+const SELF_SIGNED_URL = process.env.HTTPS_SELF_SIGNED_URL;
+
+if (!H2_URL || !SELF_SIGNED_URL) {
+  throw new Error("HTTPS_H2_URL and HTTPS_SELF_SIGNED_URL must be set by the test runner");
+}
+
+describe("HTTP/2 streams", () => {
+  test("streams DATA frames both ways and exchanges trailers", async () => {
+    const transport = await createTransport({ browser: "chrome_142", insecure: true });
+
+    try {
+      const stream = transport.openStream({
+        url: `${H2_URL}/echo.Echo/Stream`,
+        headers: { "content-type": "application/grpc", te: "trailers" },
+        timeout: 10_000,
+      });
+
+      await stream.write("ping");
+      const headers = await stream.read();
+      assert.ok(headers?.type === "headers");
+      assert.strictEqual(headers.status, 200);
+      assert.strictEqual(headers.headers.get("x-method"), "POST");
+
+      const echoed = await stream.read();
+      assert.ok(echoed?.type === "data");
+      assert.strictEqual(echoed.data.toString(), "ping");
+
+      await stream.write(new TextEncoder().encode("pong"));
+      await stream.end({ "x-client-trailer": "done" });
+
+      const rest: H2StreamFrame[] = [];
+      for await (const frame of stream) {
+        rest.push(frame);
+      }
+
+      const data = rest.flatMap((frame) => (frame.type === "data" ? [frame.data] : []));
+      assert.strictEqual(Buffer.concat(data).toString(), "pong");
+
+      const trailers = rest.at(-1);
+      assert.ok(trailers?.type === "trailers");
+      assert.strictEqual(trailers.headers.get("grpc-status"), "0");
+      assert.strictEqual(trailers.headers.get("x-client-trailer"), "done");
+      assert.strictEqual(stream.closed, true);
+    } finally {
+      await transport.close();
+    }
+  });
+
+  test("sends only the caller's headers", async () => {
+    const transport = await createTransport({ browser: "chrome_142", insecure: true });
+
+    try {
+      const stream = transport.openStream({
+        url: `${H2_URL}/headers`,
+        headers: { "content-type": "application/grpc", "x-caller": "1" },
+        timeout: 10_000,
+      });
+      await stream.end();
+
+      const frames: H2StreamFrame[] = [];
+      for await (const frame of stream) {
+        frames.push(frame);
+      }
+      const data = frames.flatMap((frame) => (frame.type === "data" ? [frame.data] : []));
+      const names = JSON.parse(Buffer.concat(data).toString()) as string[];
+      assert.deepStrictEqual(names.sort(), ["content-type", "x-caller"]);
+    } finally {
+      await transport.close();
+    }
+  });
+
+  test("rejects origins that do not negotiate HTTP/2", async () => {
+    const transport = await createTransport({ browser: "chrome_142", insecure: true });
+
+    try {
+      const stream = transport.openStream({ url: `${SELF_SIGNED_URL}/json`, timeout: 10_000 });
+      await stream.end();
+      await assert.rejects(stream.read(), RequestError);
+      assert.strictEqual(stream.closed, true);
+    } finally {
+      await transport.close();
+    }
+  });
+
+  test("validates stream options", async () => {
+    const transport = await createTransport({ browser: "chrome_142" });
+    await transport.close();
+    assert.throws(() => transport.openStream({ url: `${H2_URL}/echo.Echo/Stream` }), RequestError);
+
+    const open = await createTransport({ browser: "chrome_142" });
+    try {
+      assert.throws(() => open.openStream({ url: "not a url" }), RequestError);
+      assert.throws(() => open.openStream({ url: H2_URL, path: "relative" }), RequestError);
+    } finally {
+      await open.close();
+    }
+  });
+});
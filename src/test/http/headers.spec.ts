@@ -1,5 +1,6 @@
 import assert from "node:assert";
 import { describe, test } from "node:test";
+import type { RequestInit } from "../../wreq-js.js";
 import { Headers, fetch as wreqFetch } from "../../wreq-js.js";
 import { headerIndex, httpUrl } from "../helpers/http.js";
 
@@ -26,6 +27,70 @@ describe("HTTP headers", () => {
     );
   });
 
+  test("removes individual emulation headers", async () => {
+    const response = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      removeHeaders: ["Accept-Language", "sec-fetch-site"],
+      timeout: 10000,
+    });
+
+    const body = await response.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(body.headers["Accept-Language"], undefined, "Accept-Language should be removed");
+    assert.strictEqual(body.headers["Sec-Fetch-Site"], undefined, "Sec-Fetch-Site should be removed");
+    assert.ok(body.headers["User-Agent"], "Should keep the rest of the emulation headers");
+    assert.ok(body.headers["Accept-Encoding"], "Should keep the profile's Accept-Encoding");
+
+    await assert.rejects(
+      wreqFetch(httpUrl("/headers"), { removeHeaders: [""] }),
+      /removeHeaders must be an array of non-empty strings/,
+    );
+  });
+
+  test("rewrites Accept-Language from the locale option", async () => {
+    const german = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      locale: ["de-DE", "en"],
+      timeout: 10000,
+    });
+    const germanBody = await german.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(germanBody.headers["Accept-Language"], "de-DE,de;q=0.9,en;q=0.8");
+
+    const explicit = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      locale: "fr-FR",
+      headers: { "Accept-Language": "it" },
+      timeout: 10000,
+    });
+    const explicitBody = await explicit.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(explicitBody.headers["Accept-Language"], "it", "an explicit header should win");
+
+    await assert.rejects(wreqFetch(httpUrl("/headers"), { locale: "en_US" }), /locale must be a language tag/);
+  });
+
+  test("overrides the profile's client hints", async () => {
+    const response = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      os: "macos",
+      clientHints: {
+        brands: [{ brand: "Brave", version: "142.0.1.2" }],
+        platform: "Windows",
+        mobile: true,
+      },
+      timeout: 10000,
+    });
+
+    const body = await response.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(body.headers["Sec-Ch-Ua"], '"Brave";v="142"');
+    assert.strictEqual(body.headers["Sec-Ch-Ua-Platform"], '"Windows"');
+    assert.strictEqual(body.headers["Sec-Ch-Ua-Mobile"], "?1");
+    assert.strictEqual(body.headers["Sec-Ch-Ua-Full-Version-List"], undefined, "high-entropy hints need Accept-CH");
+
+    await assert.rejects(
+      wreqFetch(httpUrl("/headers"), { clientHints: { mobile: "yes" as unknown as boolean } }),
+      /clientHints.mobile must be a boolean/,
+    );
+  });
+
   test("appends emulation headers by default", async () => {
     const customAccept = "*/*";
     const response = await wreqFetch(httpUrl("/headers"), {
@@ -44,6 +109,125 @@ describe("HTTP headers", () => {
     assert.ok(accept.includes(customAccept), "Should include custom Accept header");
   });
 
+  test("overrides the advertised Accept-Encoding", async () => {
+    const gzipOnly = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      acceptEncoding: ["gzip"],
+      timeout: 10000,
+    });
+    const gzipBody = await gzipOnly.json<{ headers: Record<string, string> }>();
+
+    assert.strictEqual(gzipBody.headers["Accept-Encoding"], "gzip");
+    assert.ok(gzipBody.headers["User-Agent"], "Should keep the rest of the emulation headers");
+
+    const identity = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      acceptEncoding: ["identity"],
+      timeout: 10000,
+    });
+    const identityBody = await identity.json<{ headers: Record<string, string> }>();
+
+    assert.strictEqual(identityBody.headers["Accept-Encoding"], "identity");
+
+    await assert.rejects(
+      wreqFetch(httpUrl("/headers"), { acceptEncoding: ["compress" as "gzip"] }),
+      /acceptEncoding must be an array/,
+    );
+  });
+
+  test("replaces the profile's Priority header", async () => {
+    const navigation = await wreqFetch(httpUrl("/headers"), { browser: "chrome_142", timeout: 10000 });
+    const navigationBody = await navigation.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(navigationBody.headers.Priority, "u=0, i");
+
+    const script = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      priority: { urgency: 1 },
+      timeout: 10000,
+    });
+    const scriptBody = await script.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(scriptBody.headers.Priority, "u=1");
+
+    const image = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      priority: { urgency: 2, incremental: true },
+      headers: { Priority: "u=5" },
+      timeout: 10000,
+    });
+    const imageBody = await image.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(imageBody.headers.Priority, "u=5", "an explicit header should win");
+
+    await assert.rejects(
+      wreqFetch(httpUrl("/headers"), { priority: { urgency: 8 } }),
+      /priority.urgency must be an integer from 0 to 7/,
+    );
+  });
+
+  test("sends a browser's headers for the request mode", async () => {
+    const get = async (init: RequestInit) => {
+      const response = await wreqFetch(httpUrl("/headers"), { browser: "chrome_142", timeout: 10000, ...init });
+      return (await response.json<{ headers: Record<string, string> }>()).headers;
+    };
+
+    const api = await get({ mode: "fetch", headers: { Referer: "https://shop.example/cart" } });
+    assert.strictEqual(api["Sec-Fetch-Site"], "cross-site");
+    assert.strictEqual(api["Sec-Fetch-Mode"], "cors");
+    assert.strictEqual(api["Sec-Fetch-Dest"], "empty");
+    assert.strictEqual(api.Accept, "*/*");
+    assert.strictEqual(api.Origin, "https://shop.example");
+    assert.strictEqual(api["Upgrade-Insecure-Requests"], undefined);
+
+    const image = await get({ mode: "image" });
+    assert.strictEqual(image["Sec-Fetch-Site"], "same-origin");
+    assert.strictEqual(image["Sec-Fetch-Dest"], "image");
+    assert.ok(image.Accept?.startsWith("image/avif"), "should ask for images");
+
+    const page = await get({ mode: "navigate", headers: { "Sec-Fetch-Dest": "iframe" } });
+    assert.strictEqual(page["Sec-Fetch-Site"], "none");
+    assert.strictEqual(page["Sec-Fetch-User"], "?1");
+    assert.strictEqual(page["Sec-Fetch-Dest"], "iframe", "an explicit header should win");
+
+    await assert.rejects(get({ mode: "script" as "fetch" }), /mode must be one of/);
+  });
+
+  test("applies set and append modes from header tuples", async () => {
+    const defaults = await wreqFetch(httpUrl("/headers"), { browser: "chrome_142", timeout: 10000 });
+    const defaultAccept = (await defaults.json<{ headers: Record<string, string> }>()).headers.Accept;
+    assert.ok(defaultAccept, "profile should send an Accept header");
+
+    const response = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      headers: [
+        ["X-Mode", "first"],
+        ["X-Mode", "second", { mode: "set" }],
+        ["X-Joined", "one"],
+        ["X-Joined", "two"],
+        ["Accept", "application/json", { mode: "append" }],
+      ],
+      timeout: 10000,
+    });
+
+    const body = await response.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(body.headers["X-Mode"], "second", "set should replace earlier values");
+    assert.strictEqual(body.headers["X-Joined"], "one, two", "repeated names should be combined");
+    assert.strictEqual(body.headers.Accept, `${defaultAccept}, application/json`, "append should keep the default");
+  });
+
+  test("sends Buffer header values as raw bytes", async () => {
+    const filename = Buffer.from("résumé.pdf", "latin1");
+    const response = await wreqFetch(httpUrl("/headers"), {
+      browser: "chrome_142",
+      headers: [["X-Filename", filename]],
+      disableDefaultHeaders: true,
+      timeout: 10000,
+    });
+
+    // Node decodes header values as latin-1, so the bytes round-trip unchanged.
+    const body = await response.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(body.headers["X-Filename"], "résumé.pdf");
+    assert.strictEqual(new Headers({ "X-Filename": filename }).get("x-filename"), "résumé.pdf");
+  });
+
   test("maintains header ordering for Headers instances", async () => {
     const orderedHeaders = new Headers();
     orderedHeaders.append("X-First", "one");
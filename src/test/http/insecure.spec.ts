@@ -1,6 +1,6 @@
 import assert from "node:assert";
 import { describe, test } from "node:test";
-import { createSession, request, fetch as wreqFetch } from "../../wreq-js.js";
+import { createSession, RequestError, request, fetch as wreqFetch } from "../../wreq-js.js";
 
 // Local HTTPS test servers with certificate issues (provided by test runner)
 const SELF_SIGNED_URL = process.env.HTTPS_SELF_SIGNED_URL;
@@ -29,6 +29,14 @@ describe("Insecure certificate verification", () => {
     );
   });
 
+  test("reports certificate failures in the tls phase", async () => {
+    await assert.rejects(wreqFetch(SELF_SIGNED_URL, { timeout: 10_000 }), (error: unknown) => {
+      assert.ok(error instanceof RequestError);
+      assert.strictEqual(error.phase, "tls");
+      return true;
+    });
+  });
+
   test("accepts self-signed certificates when insecure is enabled", async () => {
     const response = await wreqFetch(`${SELF_SIGNED_URL}/json`, {
       browser: "chrome_142",
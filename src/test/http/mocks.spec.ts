@@ -0,0 +1,100 @@
+import assert from "node:assert";
+import { afterEach, describe, test } from "node:test";
+import { clearMocks, createTransport, mock, RequestError, fetch as wreqFetch } from "../../wreq-js.js";
+
+describe("Request mocks", () => {
+  afterEach(() => {
+    clearMocks();
+  });
+
+  test("answers matching requests without touching the network", async () => {
+    const users = mock(
+      { method: "get", urlPattern: "https://api.mock.test/users/*" },
+      {
+        status: 201,
+        headers: { "Content-Type": "application/json", "Set-Cookie": "sid=abc; Path=/" },
+        body: JSON.stringify({ id: 1 }),
+      },
+    );
+
+    const response = await wreqFetch("https://api.mock.test/users/1", { timeout: 5_000 });
+
+    assert.strictEqual(response.status, 201);
+    assert.strictEqual(response.headers.get("content-type"), "application/json");
+    assert.deepStrictEqual(response.cookies, { sid: "abc" });
+    assert.deepStrictEqual(await response.json(), { id: 1 });
+    assert.strictEqual(response.connection.reused, null);
+    assert.strictEqual(users.calls, 1);
+
+    await wreqFetch("https://api.mock.test/users/2", { timeout: 5_000 });
+    assert.strictEqual(users.calls, 2);
+  });
+
+  test("only intercepts the configured method and applies to transports", async () => {
+    const created = mock({ method: "POST", urlPattern: "https://api.mock.test/items" }, { status: 204 });
+    const transport = await createTransport();
+
+    try {
+      const response = await wreqFetch("https://api.mock.test/items", {
+        method: "POST",
+        body: "{}",
+        transport,
+        timeout: 5_000,
+      });
+      assert.strictEqual(response.status, 204);
+      assert.strictEqual(response.connection.transportId, transport.id);
+    } finally {
+      await transport.close();
+    }
+
+    await assert.rejects(wreqFetch("https://api.mock.test/items", { timeout: 5_000 }), RequestError);
+    assert.strictEqual(created.calls, 1);
+  });
+
+  test("later mocks override earlier ones and restore keeps the call count", async () => {
+    const fallback = mock({ urlPattern: "https://api.mock.test/*" }, { body: "fallback" });
+    const specific = mock({ urlPattern: "https://api.mock.test/special" }, { body: "special", delayMs: 50 });
+
+    const started = Date.now();
+    assert.strictEqual(await (await wreqFetch("https://api.mock.test/special")).text(), "special");
+    assert.ok(Date.now() - started >= 40);
+
+    specific.restore();
+    assert.strictEqual(specific.restored, true);
+    assert.strictEqual(await (await wreqFetch("https://api.mock.test/special")).text(), "fallback");
+    assert.strictEqual(specific.calls, 1);
+    assert.strictEqual(fallback.calls, 1);
+  });
+
+  test("validates mock definitions", () => {
+    assert.throws(() => mock({ urlPattern: "" }), RequestError);
+    assert.throws(() => mock({ urlPattern: "https://api.mock.test/" }, { status: 42 }), RequestError);
+    assert.throws(() => mock({ urlPattern: "https://api.mock.test/" }, { delayMs: -1 }), RequestError);
+  });
+
+  test("parses JSON bodies natively with JSON.parse semantics", async () => {
+    const jsonBody = async (body: string) => {
+      mock({ urlPattern: "https://api.mock.test/json" }, { body });
+      try {
+        return await (await wreqFetch("https://api.mock.test/json")).json<Record<string, unknown>>();
+      } finally {
+        clearMocks();
+      }
+    };
+
+    const text = '{"b":1,"a":[1.5,-0,"\u00e9",null],"__proto__":{"x":1}}';
+    const parsed = await jsonBody(text);
+    assert.deepStrictEqual(parsed, JSON.parse(text));
+    assert.deepStrictEqual(Object.keys(parsed), ["b", "a", "__proto__"]);
+    assert.strictEqual(Object.getPrototypeOf(parsed), Object.prototype);
+
+    // serde_json rejects numbers that overflow; JSON.parse turns them into Infinity.
+    assert.deepStrictEqual(await jsonBody("[1e400]"), [Number.POSITIVE_INFINITY]);
+
+    await assert.rejects(jsonBody('{"a": x}'), (error: Error & { offset?: number }) => {
+      assert.ok(error instanceof SyntaxError);
+      assert.strictEqual(error.offset, 6);
+      return true;
+    });
+  });
+});
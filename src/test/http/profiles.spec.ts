@@ -1,7 +1,14 @@
 import assert from "node:assert";
 import { describe, test } from "node:test";
 import type { BrowserProfile } from "../../wreq-js.js";
-import { getProfiles, RequestError, fetch as wreqFetch } from "../../wreq-js.js";
+import {
+  getOperatingSystems,
+  getProfiles,
+  RequestError,
+  resolveProfile,
+  toCurl,
+  fetch as wreqFetch,
+} from "../../wreq-js.js";
 import { httpUrl } from "../helpers/http.js";
 
 describe("HTTP profiles", () => {
@@ -30,4 +37,35 @@ describe("HTTP profiles", () => {
       "Should reject invalid browser profiles",
     );
   });
+
+  test("resolves every listed profile natively in strict mode", async () => {
+    for (const browser of getProfiles()) {
+      await toCurl(httpUrl("/get"), { browser, strictProfiles: true });
+    }
+    for (const os of getOperatingSystems()) {
+      await toCurl(httpUrl("/get"), { os, strictProfiles: true });
+    }
+  });
+
+  test("resolves profile aliases to the newest matching profile", async () => {
+    const chrome = resolveProfile("chrome");
+    assert.ok(chrome?.startsWith("chrome_"));
+    assert.strictEqual(resolveProfile("chrome:latest"), chrome);
+    const newestChrome = Math.max(
+      ...getProfiles()
+        .filter((profile) => /^chrome_\d+$/.test(profile))
+        .map((profile) => Number(profile.slice("chrome_".length))),
+    );
+    assert.strictEqual(chrome, `chrome_${newestChrome}`);
+
+    assert.match(resolveProfile("firefox:esr") ?? "", /^firefox_\d+$/);
+    assert.match(resolveProfile("safari_ios") ?? "", /^safari_ios_/);
+    assert.strictEqual(resolveProfile("chrome_142"), "chrome_142");
+    assert.strictEqual(resolveProfile("chrome:esr"), null);
+    assert.strictEqual(resolveProfile("netscape"), null);
+
+    const response = await wreqFetch(httpUrl("/get"), { browser: "firefox:latest", strictProfiles: true });
+    assert.strictEqual(response.status, 200);
+    await response.text();
+  });
 });
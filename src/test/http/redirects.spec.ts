@@ -0,0 +1,88 @@
+import assert from "node:assert";
+import { describe, test } from "node:test";
+import { createSession, RequestError, fetch as wreqFetch } from "../../wreq-js.js";
+import { httpUrl } from "../helpers/http.js";
+
+const HTTPS_URL = process.env.HTTPS_SELF_SIGNED_URL;
+
+if (!HTTPS_URL) {
+  throw new Error("HTTPS_SELF_SIGNED_URL must be set by the test runner");
+}
+
+const redirectTo = (base: string, target: string) =>
+  `${new URL("/redirect/to", base).toString()}?url=${encodeURIComponent(target)}`;
+
+describe("Redirect policies", () => {
+  test("blocks https to http redirects when asked", async () => {
+    const downgrade = redirectTo(HTTPS_URL, httpUrl("/json"));
+
+    const followed = await wreqFetch(downgrade, { insecure: true, timeout: 10_000 });
+    assert.strictEqual(followed.status, 200);
+    assert.strictEqual(followed.url, httpUrl("/json"));
+    await followed.text();
+
+    await assert.rejects(
+      wreqFetch(downgrade, { insecure: true, blockInsecureRedirects: true, timeout: 10_000 }),
+      (error: unknown) => error instanceof RequestError && /insecure redirect/.test(error.message),
+    );
+
+    const session = await createSession({ insecure: true, blockInsecureRedirects: true });
+    try {
+      await assert.rejects(session.fetch(downgrade, { timeout: 10_000 }), RequestError);
+
+      const upgrade = await session.fetch(redirectTo(httpUrl("/"), new URL("/json", HTTPS_URL).toString()), {
+        timeout: 10_000,
+      });
+      assert.strictEqual(upgrade.status, 200);
+      await upgrade.text();
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("strips the configured headers on cross-origin redirects", async () => {
+    const crossOrigin = redirectTo(httpUrl("/"), new URL("/headers", HTTPS_URL).toString());
+    const headers = { Authorization: "Bearer secret", "X-Api-Key": "key", "X-Trace": "kept" };
+    const received = async (init: Parameters<typeof wreqFetch>[1]) => {
+      const response = await wreqFetch(crossOrigin, { insecure: true, timeout: 10_000, headers, ...init });
+      assert.strictEqual(response.status, 200);
+      assert.strictEqual(response.redirected, true);
+      return ((await response.json()) as { headers: Record<string, string> }).headers;
+    };
+
+    const byDefault = await received({});
+    assert.strictEqual(byDefault.Authorization, undefined);
+    assert.strictEqual(byDefault["X-Api-Key"], "key");
+
+    const configured = await received({ stripOnCrossOrigin: ["X-Api-Key"] });
+    assert.strictEqual(configured.Authorization, "Bearer secret");
+    assert.strictEqual(configured["X-Api-Key"], undefined);
+    assert.strictEqual(configured["X-Trace"], "kept");
+
+    const sameOrigin = await wreqFetch(redirectTo(httpUrl("/"), httpUrl("/headers")), {
+      headers,
+      stripOnCrossOrigin: ["authorization"],
+    });
+    const sameOriginHeaders = ((await sameOrigin.json()) as { headers: Record<string, string> }).headers;
+    assert.strictEqual(sameOriginHeaders.Authorization, "Bearer secret");
+
+    await assert.rejects(wreqFetch(crossOrigin, { stripOnCrossOrigin: [""] }), RequestError);
+  });
+
+  test("follows meta refresh pages only when asked", async () => {
+    const interstitial = httpUrl("/meta-refresh?delay=1");
+
+    const plain = await wreqFetch(interstitial);
+    assert.strictEqual(plain.url, interstitial);
+    assert.match(await plain.text(), /http-equiv="refresh"/);
+
+    const followed = await wreqFetch(interstitial, { followMetaRefresh: true });
+    assert.strictEqual(followed.url, httpUrl("/json"));
+    assert.strictEqual(followed.redirected, true);
+    await followed.json();
+
+    const tooSlow = await wreqFetch(interstitial, { followMetaRefresh: 0 });
+    assert.strictEqual(tooSlow.url, interstitial);
+    await tooSlow.text();
+  });
+});
@@ -1,8 +1,35 @@
 import assert from "node:assert";
-import { randomUUID } from "node:crypto";
+import { createHash, randomUUID } from "node:crypto";
+import { mkdtemp, readFile, rm } from "node:fs/promises";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
 import { describe, test } from "node:test";
 import { setTimeout as delay } from "node:timers/promises";
-import { createSession, fetch as wreqFetch } from "../../wreq-js.js";
+import { isDeepStrictEqual } from "node:util";
+import { setFlagsFromString } from "node:v8";
+import { runInNewContext } from "node:vm";
+import { gunzipSync } from "node:zlib";
+import {
+  type BodyStreamLeak,
+  configureBodyStreams,
+  createBodyRing,
+  createSession,
+  createTransport,
+  fetchAll,
+  type FetchAllResult,
+  fromCurl,
+  getDefaults,
+  getMemoryStats,
+  getNativeStats,
+  RequestError,
+  readBodyRing,
+  type Response,
+  request,
+  requestCallback,
+  setDefaults,
+  toCurl,
+  fetch as wreqFetch,
+} from "../../wreq-js.js";
 import { httpUrl } from "../helpers/http.js";
 
 const isLocalHttpBase =
@@ -225,6 +252,25 @@ describe("HTTP requests", () => {
     assert.strictEqual(response.headers.get("location"), httpUrl("/json"));
   });
 
+  test("applies native request defaults to unset options", { skip: !isLocalHttpBase }, async () => {
+    setDefaults({ redirect: "manual", timeout: 10_000, inlineBodyMax: 0 });
+    try {
+      assert.deepStrictEqual(getDefaults(), { redirect: "manual", timeout: 10_000, inlineBodyMax: 0 });
+
+      const manual = await wreqFetch(httpUrl("/redirect"));
+      assert.strictEqual(manual.status, 302);
+
+      const followed = await wreqFetch(httpUrl("/redirect"), { redirect: "follow" });
+      assert.strictEqual(followed.status, 200);
+      assert.ok(await followed.json());
+    } finally {
+      setDefaults({});
+    }
+
+    assert.deepStrictEqual(getDefaults(), {});
+    assert.throws(() => setDefaults({ browser: "netscape_4" as never }), RequestError);
+  });
+
   test("rejects when redirect mode is error", { skip: !isLocalHttpBase }, async () => {
     await assert.rejects(
       wreqFetch(httpUrl("/redirect"), {
@@ -294,6 +340,109 @@ describe("HTTP requests", () => {
     assert.strictEqual(notFoundResponse.ok, false);
   });
 
+  test("exposes the reason phrase the server sent", { skip: !isLocalHttpBase }, async () => {
+    const custom = await wreqFetch(httpUrl("/status/reason?reason=Daily%20quota%20used%20up"));
+    assert.strictEqual(custom.status, 429);
+    assert.strictEqual(custom.statusText, "Daily quota used up");
+
+    const canonical = await wreqFetch(httpUrl("/status/reason?status=503&reason=Service%20Unavailable"));
+    assert.strictEqual(canonical.statusText, "Service Unavailable");
+
+    const clone = custom.clone();
+    assert.strictEqual(clone.statusText, "Daily quota used up");
+  });
+
+  test("builds response headers as an object with headerFormat", { skip: !isLocalHttpBase }, async () => {
+    const url = httpUrl("/cookies/set?a=1&b=2");
+    const asObject = await wreqFetch(url, { headerFormat: "object" });
+    const asTuples = await wreqFetch(url);
+
+    assert.strictEqual(asObject.headerObject["set-cookie"], "a=1; Path=/, b=2; Path=/");
+    assert.match(asObject.headerObject["content-type"] ?? "", /application\/json/);
+    assert.deepStrictEqual(asObject.headerObject, asTuples.headerObject);
+    assert.strictEqual(asObject.headers.get("Set-Cookie"), asTuples.headers.get("Set-Cookie"));
+    assert.deepStrictEqual(asObject.cookies, { a: "1", b: "2" });
+    assert.deepStrictEqual(asObject.clone().headerObject, asObject.headerObject);
+
+    await assert.rejects(wreqFetch(url, { headerFormat: "map" as never }), /headerFormat must be/);
+  });
+
+  test("returns only status and body with minimalResponse", { skip: !isLocalHttpBase }, async () => {
+    const session = await createSession({ browser: "chrome_142" });
+    try {
+      const response = await session.fetch(httpUrl("/cookies/set?sid=abc"), { minimalResponse: true });
+      assert.strictEqual(response.status, 200);
+      assert.strictEqual(response.statusText, "OK");
+      assert.deepStrictEqual([...response.headers], []);
+      assert.deepStrictEqual(response.cookies, {});
+      assert.strictEqual(response.bodyDigest, null);
+      assert.strictEqual(response.timing, null);
+      assert.deepStrictEqual(await response.json(), { cookies: { sid: "abc" } });
+
+      assert.deepStrictEqual(session.getCookies(httpUrl("/cookies")), { sid: "abc" });
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("signs the fully composed request with beforeSend", { skip: !isLocalHttpBase }, async () => {
+    const session = await createSession({ browser: "chrome_142" });
+    try {
+      session.setCookie("sid", "abc", httpUrl("/"));
+      let seen: [string, string][] = [];
+      const response = await session.fetch(httpUrl("/headers"), {
+        headers: { "X-Request": "1" },
+        beforeSend: async ({ method, url, headers }) => {
+          seen = headers;
+          await delay(5);
+          const canonical = `${method}\n${url}\n${headers.map(([name, value]) => `${name}:${value}`).join("\n")}`;
+          return { "X-Signature": createHash("sha256").update(canonical).digest("hex") };
+        },
+      });
+      const body = await response.json<{ headers: Record<string, string> }>();
+
+      const names = seen.map(([name]) => name);
+      assert.ok(names.includes("user-agent"), "profile defaults are visible to the hook");
+      assert.ok(names.includes("x-request"));
+      assert.deepStrictEqual(seen.find(([name]) => name === "cookie"), ["cookie", "sid=abc"]);
+      const canonical = `GET\n${httpUrl("/headers")}\n${seen.map(([name, value]) => `${name}:${value}`).join("\n")}`;
+      assert.strictEqual(body.headers["X-Signature"], createHash("sha256").update(canonical).digest("hex"));
+      assert.strictEqual(body.headers.Cookie, "sid=abc");
+    } finally {
+      await session.close();
+    }
+
+    await assert.rejects(
+      wreqFetch(httpUrl("/headers"), {
+        beforeSend: () => {
+          throw new Error("no signing key");
+        },
+      }),
+      /no signing key/,
+    );
+    await assert.rejects(wreqFetch(httpUrl("/headers"), { beforeSend: "sign" as never }), /beforeSend must be/);
+  });
+
+  test("connects to another host and port with connectTo", { skip: !isLocalHttpBase }, async () => {
+    const local = new URL(httpUrl("/"));
+    const connectTo = [{ fromHost: "api.wreq.test", toHost: local.hostname, toPort: Number(local.port) }];
+
+    const response = await wreqFetch("http://api.wreq.test/headers", { connectTo });
+    const body = await response.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(body.headers.Host, "api.wreq.test");
+
+    const session = await createSession({ browser: "chrome_142" });
+    try {
+      const viaSession = await session.fetch("http://api.wreq.test/headers", { connectTo });
+      assert.strictEqual(viaSession.status, 200);
+    } finally {
+      await session.close();
+    }
+
+    await assert.rejects(wreqFetch("http://api.wreq.test:8080/headers", { connectTo }), /cannot replace port 8080/);
+    await assert.rejects(wreqFetch(httpUrl("/"), { connectTo: [{ fromHost: "x" }] }), /needs a toHost or a toPort/);
+  });
+
   test("propagates AbortSignal to native I/O", { skip: !isLocalHttpBase }, async () => {
     const controller = new AbortController();
     const hangId = randomUUID();
@@ -322,4 +471,592 @@ describe("HTTP requests", () => {
     const status = await statusResponse.json<{ closed: boolean }>();
     assert.strictEqual(status.closed, true, "server should observe connection close after abort");
   });
+
+  test("coalesces identical concurrent GETs when opted in", { skip: !isLocalHttpBase }, async () => {
+    const url = httpUrl(`/counter?id=${randomUUID()}`);
+
+    const responses = await Promise.all([
+      wreqFetch(url, { coalesce: true }),
+      wreqFetch(url, { coalesce: true }),
+      wreqFetch(url, { coalesce: true }),
+    ]);
+    const bodies = await Promise.all(responses.map((response) => response.json<{ hits: number }>()));
+
+    assert.deepStrictEqual(bodies.map((body) => body.hits), [1, 1, 1]);
+
+    const later = await wreqFetch(url, { coalesce: true });
+    assert.strictEqual((await later.json<{ hits: number }>()).hits, 2, "Settled requests are not reused");
+
+    const uncoalesced = await Promise.all([wreqFetch(url), wreqFetch(url)]);
+    const hits = await Promise.all(uncoalesced.map(async (response) => (await response.json<{ hits: number }>()).hits));
+    assert.deepStrictEqual(hits.sort((a, b) => a - b), [3, 4]);
+  });
+
+  test("cancels a coalesced request once every caller aborts", { skip: !isLocalHttpBase }, async () => {
+    // The headers arrive after both callers have aborted, so a request left running would hold a body stream.
+    const url = httpUrl(`/stream/chunks?n=4&delay=100&id=${randomUUID()}`);
+    const before = getNativeStats().bodyStreams;
+    const controllers = [new AbortController(), new AbortController()];
+    const pending = controllers.map((controller) => wreqFetch(url, { coalesce: true, signal: controller.signal }));
+
+    await delay(20);
+    for (const controller of controllers) {
+      controller.abort();
+    }
+    for (const request of pending) {
+      await assert.rejects(request, (error: unknown) => error instanceof Error && error.name === "AbortError");
+    }
+
+    await delay(250);
+    assert.strictEqual(getNativeStats().bodyStreams, before);
+  });
+
+  test("does not coalesce requests signed by different beforeSend hooks", { skip: !isLocalHttpBase }, async () => {
+    const url = httpUrl(`/counter?id=${randomUUID()}`);
+    const signedBy = (signer: string) => () => ({ "x-signature": signer });
+
+    const responses = await Promise.all([
+      wreqFetch(url, { coalesce: true, beforeSend: signedBy("first") }),
+      wreqFetch(url, { coalesce: true, beforeSend: signedBy("second") }),
+    ]);
+    const hits = await Promise.all(responses.map(async (response) => (await response.json<{ hits: number }>()).hits));
+
+    assert.deepStrictEqual(hits.sort((a, b) => a - b), [1, 2], "each signer sends its own request");
+  });
+
+  test("fetches a list of URLs with shared retries", { skip: !isLocalHttpBase }, async () => {
+    const urls = [
+      httpUrl(`/flaky?id=${randomUUID()}&failures=2`),
+      httpUrl("/get"),
+      httpUrl("/missing"),
+      "http://127.0.0.1:1/",
+    ];
+    const results: FetchAllResult[] = [];
+
+    const summary = await fetchAll(urls, {
+      concurrency: 2,
+      retries: 2,
+      retryDelay: 10,
+      timeout: 10_000,
+      onResult: (result) => results.push(result),
+    });
+
+    assert.deepStrictEqual(summary, { succeeded: 3, failed: 1 });
+    results.sort((a, b) => a.index - b.index);
+    assert.deepStrictEqual(
+      results.map((result) => [result.url, result.response?.status ?? null, result.attempts]),
+      [
+        [urls[0], 200, 3],
+        [urls[1], 200, 1],
+        [urls[2], 404, 1],
+        [urls[3], null, 3],
+      ],
+    );
+    assert.deepStrictEqual(await results[0]?.response?.json(), { hits: 3 });
+    assert.ok(results[3]?.error instanceof RequestError);
+    await Promise.all(results.slice(1, 3).map((result) => result.response?.arrayBuffer()));
+
+    assert.deepStrictEqual(await fetchAll([], { onResult: () => {} }), { succeeded: 0, failed: 0 });
+    await assert.rejects(fetchAll(urls, { retryOn: [99], onResult: () => {} }), /retryOn/);
+  });
+
+  test("asks onRetry before each fetchAll retry and stops on false", { skip: !isLocalHttpBase }, async () => {
+    const url = httpUrl(`/flaky?id=${randomUUID()}&failures=5`);
+    const retries: Array<[number, string, number, number | null]> = [];
+    let result: FetchAllResult | undefined;
+
+    await fetchAll([url], {
+      retries: 5,
+      retryDelay: 10,
+      timeout: 10_000,
+      onRetry: (attempt, error, delayMs, target) => {
+        retries.push([attempt, error.message, delayMs, target.status]);
+        assert.deepStrictEqual([target.index, target.url], [0, url]);
+        return attempt < 3;
+      },
+      onResult: (final) => {
+        result = final;
+      },
+    });
+
+    assert.deepStrictEqual(retries, [
+      [2, "Server responded with status 503", 0, 503],
+      [3, "Server responded with status 503", 0, 503],
+    ]);
+    assert.strictEqual(result?.attempts, 2);
+    assert.strictEqual(result?.response?.status, 503);
+    assert.deepStrictEqual(await result?.response?.json(), { hits: 2 });
+
+    await assert.rejects(
+      fetchAll(["http://127.0.0.1:1/"], {
+        retries: 1,
+        retryDelay: 10,
+        onRetry: () => {
+          throw new Error("retry budget exhausted");
+        },
+        onResult: () => {},
+      }),
+      /retry budget exhausted/,
+    );
+  });
+
+  test("throttles downloads with maxDownloadBytesPerSec", { skip: !isLocalHttpBase }, async () => {
+    const startedAt = performance.now();
+    const response = await wreqFetch(httpUrl("/binary?len=4096"), {
+      maxDownloadBytesPerSec: 4096,
+      timeout: 10_000,
+    });
+    const body = await response.arrayBuffer();
+    const elapsed = performance.now() - startedAt;
+
+    assert.strictEqual(body.byteLength, 4096);
+    assert.ok(elapsed >= 800, `Expected throttled download to take ~1s, took ${Math.round(elapsed)}ms`);
+
+    await assert.rejects(wreqFetch(httpUrl("/get"), { maxDownloadBytesPerSec: 0 }), /maxDownloadBytesPerSec/);
+  });
+
+  test("paces uploads with maxUploadBytesPerSec", { skip: !isLocalHttpBase }, async () => {
+    const payload = "x".repeat(8192);
+    const startedAt = performance.now();
+    const response = await wreqFetch(httpUrl("/upload"), {
+      method: "POST",
+      body: payload,
+      maxUploadBytesPerSec: 8192,
+      timeout: 10_000,
+    });
+    const elapsed = performance.now() - startedAt;
+
+    assert.strictEqual(response.status, 200);
+    const echoed = await response.json<{ received: number; contentLength: string | null }>();
+    assert.strictEqual(echoed.received, 8192);
+    assert.strictEqual(echoed.contentLength, "8192");
+    assert.ok(elapsed >= 800, `Expected paced upload to take ~1s, took ${Math.round(elapsed)}ms`);
+  });
+
+  test("pauses the request timeout while a body is uploading", { skip: !isLocalHttpBase }, async () => {
+    const payload = "x".repeat(48 * 1024);
+    const paced = { method: "POST", body: payload, maxUploadBytesPerSec: 32 * 1024, timeout: 1_000 };
+
+    await assert.rejects(wreqFetch(httpUrl("/upload"), paced), RequestError);
+
+    const response = await wreqFetch(httpUrl("/upload"), { ...paced, writeTimeout: 1_000 });
+    const echoed = await response.json<{ received: number }>();
+    assert.strictEqual(echoed.received, payload.length);
+  });
+
+  test("fails uploads that stop making progress", { skip: !isLocalHttpBase }, async () => {
+    await assert.rejects(
+      wreqFetch(httpUrl(`/hang?id=${randomUUID()}`), {
+        method: "POST",
+        body: Buffer.alloc(64 * 1024 * 1024),
+        writeTimeout: 500,
+        timeout: 0,
+      }),
+      (error: unknown) => error instanceof RequestError && /write stalled/.test(error.message),
+    );
+  });
+
+  test("enforces totalDeadline across the whole request", async () => {
+    await assert.rejects(
+      wreqFetch(httpUrl("/delay/2"), { totalDeadline: 300, timeout: 0 }),
+      (error: unknown) => error instanceof RequestError && /total deadline of 300 ms/.test(error.message),
+    );
+
+    await assert.rejects(wreqFetch(httpUrl("/get"), { writeTimeout: 0 }), /writeTimeout/);
+    await assert.rejects(wreqFetch(httpUrl("/get"), { totalDeadline: -1 }), /totalDeadline/);
+  });
+
+  test("reports the phase a request failed in", { skip: !isLocalHttpBase }, async () => {
+    await assert.rejects(wreqFetch("http://127.0.0.1:1/unreachable", { timeout: 2_000 }), (error: unknown) => {
+      assert.ok(error instanceof RequestError);
+      assert.strictEqual(error.phase, "connect");
+      assert.ok(typeof error.phaseElapsed === "number" && error.phaseElapsed >= 0);
+      return true;
+    });
+
+    await assert.rejects(wreqFetch(httpUrl("/delay/2"), { timeout: 300 }), (error: unknown) => {
+      assert.ok(error instanceof RequestError);
+      assert.strictEqual(error.phase, "ttfb");
+      assert.match(error.message, /failed during ttfb after \d+ ms/);
+      return true;
+    });
+  });
+
+  test("renders the resolved request as a curl command", async () => {
+    const command = await toCurl(httpUrl("/post"), {
+      method: "POST",
+      headers: { "X-Trace": "it's-me" },
+      body: JSON.stringify({ hello: "world" }),
+      proxy: "http://127.0.0.1:9",
+      timeout: 2_500,
+    });
+
+    const lines = command.split(" \\\n  ");
+    assert.strictEqual(lines[0], `curl '${httpUrl("/post")}'`);
+    assert.ok(lines.includes("-X POST"));
+    assert.ok(lines.includes("-H 'X-Trace: it'\\''s-me'"));
+    assert.ok(lines.some((line) => /^-H 'user-agent: Mozilla\/5\.0/i.test(line)), "profile headers are included");
+    assert.ok(lines.includes(`--data-binary '{"hello":"world"}'`));
+    assert.ok(lines.includes("--proxy 'http://127.0.0.1:9'"));
+    assert.ok(lines.includes("--max-time 2.5"));
+
+    const session = await createSession();
+    try {
+      session.setCookie("sid", "abc", httpUrl("/"));
+      const withCookies = await toCurl(httpUrl("/get"), { session, disableDefaultHeaders: true });
+      assert.strictEqual(
+        withCookies,
+        [`curl '${httpUrl("/get")}'`, "-H 'cookie: sid=abc'", "--location", "--max-time 30"].join(" \\\n  "),
+      );
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("parses curl commands into request options", async () => {
+    const options = fromCurl(
+      [
+        `curl '${httpUrl("/headers")}'`,
+        "-H 'X-Trace: it'\\''s-me'",
+        "-b 'sid=abc'",
+        "--data-raw 'a=1' --compressed -L",
+      ].join(" \\\n  "),
+    );
+
+    assert.deepStrictEqual(options, {
+      url: httpUrl("/headers"),
+      method: "POST",
+      headers: [
+        ["X-Trace", "it's-me"],
+        ["Cookie", "sid=abc"],
+        ["Content-Type", "application/x-www-form-urlencoded"],
+      ],
+      body: "a=1",
+      redirect: "follow",
+    });
+
+    const response = await request({ ...options, method: "GET", body: undefined });
+    const echoed = await response.json<{ headers: Record<string, string> }>();
+    assert.strictEqual(echoed.headers["X-Trace"], "it's-me");
+
+    assert.throws(() => fromCurl("wget https://example.com"), RequestError);
+  });
+
+  test("tees response bodies to a file while they are read", async () => {
+    const dir = await mkdtemp(join(tmpdir(), "wreq-tee-"));
+
+    try {
+      const streamed = await wreqFetch(httpUrl("/stream/chunks?n=4&size=64"));
+      await streamed.teeToFile(join(dir, "streamed.bin"));
+      const chunks: Uint8Array[] = [];
+      const reader = streamed.body?.getReader();
+      assert.ok(reader);
+      for (;;) {
+        const { done, value } = await reader.read();
+        if (done) break;
+        chunks.push(value);
+      }
+      const received = Buffer.concat(chunks);
+      assert.strictEqual(received.length, 256);
+      assert.deepStrictEqual(await readFile(join(dir, "streamed.bin")), received);
+
+      const buffered = await wreqFetch(httpUrl("/stream/chunks?n=3&size=32"));
+      await buffered.teeToFile(join(dir, "buffered.bin"));
+      const bytes = Buffer.from(await buffered.arrayBuffer());
+      assert.deepStrictEqual(await readFile(join(dir, "buffered.bin")), bytes);
+
+      const inline = await wreqFetch(httpUrl("/json"));
+      await inline.teeToFile(join(dir, "inline.json"));
+      const text = await inline.text();
+      assert.strictEqual(await readFile(join(dir, "inline.json"), "utf8"), text);
+
+      await assert.rejects(inline.teeToFile(join(dir, "again.json")), TypeError);
+    } finally {
+      await rm(dir, { recursive: true, force: true });
+    }
+  });
+
+  test("digests response bodies natively while they are read", async () => {
+    const sha256 = (bytes: Uint8Array) => createHash("sha256").update(bytes).digest("hex");
+
+    const streamed = await wreqFetch(httpUrl("/stream/chunks?n=4&size=64"), { bodyDigest: "sha256" });
+    assert.strictEqual(streamed.bodyDigest, null);
+    const chunks: Uint8Array[] = [];
+    const reader = streamed.body?.getReader();
+    assert.ok(reader);
+    for (;;) {
+      const { done, value } = await reader.read();
+      if (done) break;
+      chunks.push(value);
+    }
+    assert.strictEqual(streamed.bodyDigest, sha256(Buffer.concat(chunks)));
+
+    const buffered = await wreqFetch(httpUrl("/stream/chunks?n=3&size=32"), { bodyDigest: "md5" });
+    const bytes = Buffer.from(await buffered.arrayBuffer());
+    assert.strictEqual(buffered.bodyDigest, createHash("md5").update(bytes).digest("hex"));
+
+    const inline = await wreqFetch(httpUrl("/json"), { bodyDigest: "sha256" });
+    assert.strictEqual(inline.bodyDigest, sha256(Buffer.from(await inline.text())));
+
+    const plain = await wreqFetch(httpUrl("/json"));
+    await plain.text();
+    assert.strictEqual(plain.bodyDigest, null);
+
+    await assert.rejects(wreqFetch(httpUrl("/json"), { bodyDigest: "sha1" as never }), RequestError);
+  });
+
+  test("exposes trailers once the body has been read", async () => {
+    const buffered = await wreqFetch(httpUrl("/trailers"));
+    assert.strictEqual(buffered.trailers, null);
+    assert.strictEqual(await buffered.text(), "frame-1frame-2");
+    assert.strictEqual(buffered.trailers?.get("grpc-status"), "0");
+    assert.strictEqual(buffered.trailers?.get("grpc-message"), "OK");
+
+    const streamed = await wreqFetch(httpUrl("/trailers"));
+    const reader = streamed.body?.getReader();
+    assert.ok(reader);
+    for (;;) {
+      const { done } = await reader.read();
+      if (done) break;
+    }
+    assert.strictEqual(streamed.trailers?.get("grpc-status"), "0");
+
+    const plain = await wreqFetch(httpUrl("/json"));
+    await plain.text();
+    assert.strictEqual(plain.trailers, null);
+  });
+
+  test("returns the raw encoded body when decodeBody is false", async () => {
+    const decoded = await wreqFetch(httpUrl("/gzip"));
+    assert.strictEqual(decoded.headers.get("content-encoding"), null);
+    assert.strictEqual((await decoded.json<{ gzipped: boolean }>()).gzipped, true);
+
+    const raw = await wreqFetch(httpUrl("/gzip"), { decodeBody: false });
+    assert.strictEqual(raw.headers.get("content-encoding"), "gzip");
+    const bytes = Buffer.from(await raw.arrayBuffer());
+    assert.strictEqual(JSON.parse(gunzipSync(bytes).toString("utf8")).gzipped, true);
+  });
+
+  test("sniffs the charset of HTML that does not declare one in Content-Type", async () => {
+    const response = await wreqFetch(httpUrl("/legacy-charset"));
+    assert.match(await response.text(), /<body>Привет<\/body>/);
+  });
+
+  test("reports wire and decoded body sizes", async () => {
+    const raw = await wreqFetch(httpUrl("/gzip"), { decodeBody: false });
+    const wireBytes = (await raw.arrayBuffer()).byteLength;
+    assert.strictEqual(raw.sizes?.wireBodyBytes, wireBytes);
+    assert.strictEqual(raw.sizes?.decodedBodyBytes, wireBytes);
+
+    const decoded = await wreqFetch(httpUrl("/gzip"));
+    const decodedBytes = (await decoded.arrayBuffer()).byteLength;
+    assert.ok(decoded.sizes);
+    assert.strictEqual(decoded.sizes.wireBodyBytes, wireBytes);
+    assert.strictEqual(decoded.sizes.decodedBodyBytes, decodedBytes);
+    assert.ok(decoded.sizes.headerBytes > 0);
+
+    const streamed = await wreqFetch(httpUrl("/stream/chunks?n=4&size=64"));
+    assert.strictEqual(streamed.sizes, null);
+    await streamed.arrayBuffer();
+    assert.strictEqual(streamed.sizes?.wireBodyBytes, 256);
+    assert.strictEqual(streamed.sizes?.decodedBodyBytes, 256);
+  });
+
+  test("reaps abandoned body streams over the limit and reports them", async () => {
+    const leaks: BodyStreamLeak[] = [];
+    let reported = () => {};
+    const leaked = new Promise<void>((resolve) => {
+      reported = resolve;
+    });
+    configureBodyStreams({
+      maxStreams: 1,
+      onLeak: (leak) => {
+        leaks.push(leak);
+        reported();
+      },
+    });
+
+    try {
+      const abandoned = await wreqFetch(httpUrl("/stream/chunks?n=4&size=65536"));
+      const kept = await wreqFetch(httpUrl("/stream/chunks?n=4&size=65536"));
+      await leaked;
+
+      assert.strictEqual(leaks.length, 1);
+      assert.strictEqual(leaks[0]?.reason, "maxStreams");
+      assert.strictEqual(leaks[0]?.groupId, null);
+      assert.strictEqual((await abandoned.arrayBuffer()).byteLength, 0);
+      assert.strictEqual((await kept.arrayBuffer()).byteLength, 4 * 65536);
+    } finally {
+      configureBodyStreams({ maxStreams: null, onLeak: null });
+    }
+
+    assert.throws(() => configureBodyStreams({ ttl: 0 }), RequestError);
+  });
+
+  test("pushes streamed bodies as credit allows", async () => {
+    configureBodyStreams({ delivery: "push", pushCredit: 65536 });
+
+    try {
+      const response = await wreqFetch(httpUrl("/stream/chunks?n=8&size=16384"), { bodyDigest: "sha256" });
+      const reader = response.body?.getReader();
+      assert.ok(reader, "body reader should be available");
+
+      // Without a reader the queue fills up to the credit and the rest waits natively.
+      await new Promise((resolve) => setTimeout(resolve, 100));
+      const seen = new Set<number>();
+      while (true) {
+        const { done, value } = await reader.read();
+        if (done) {
+          break;
+        }
+        for (const byte of value) {
+          seen.add(byte);
+        }
+      }
+      assert.deepStrictEqual([...seen], [0, 1, 2, 3, 4, 5, 6, 7], "chunks should arrive in order");
+      assert.strictEqual(response.sizes?.decodedBodyBytes, 8 * 16384);
+      assert.match(response.bodyDigest ?? "", /^[0-9a-f]{64}$/);
+
+      const cancelled = await wreqFetch(httpUrl("/stream/chunks?n=8&size=16384"));
+      const cancelledReader = cancelled.body?.getReader();
+      assert.strictEqual((await cancelledReader?.read())?.done, false);
+      await cancelledReader?.cancel();
+    } finally {
+      configureBodyStreams({ delivery: "pull" });
+    }
+
+    assert.throws(() => configureBodyStreams({ delivery: "eager" as "push" }), RequestError);
+    assert.throws(() => configureBodyStreams({ pushCredit: 0 }), RequestError);
+  });
+
+  test("pipes bodies through a shared ring", async () => {
+    const response = await wreqFetch(httpUrl("/stream/chunks?n=8&size=16384"), { bodyDigest: "sha256" });
+    // Far smaller than the body, so the writer has to wait for the reader to make room.
+    const ring = createBodyRing(4096);
+    const piped = response.pipeToRing(ring);
+    assert.strictEqual(response.bodyUsed, true);
+
+    const counts = new Array<number>(8).fill(0);
+    for (const bytes of readBodyRing(ring)) {
+      for (const byte of bytes) {
+        counts[byte] = (counts[byte] ?? 0) + 1;
+      }
+    }
+    await piped;
+    assert.deepStrictEqual(counts, new Array(8).fill(16384));
+    assert.match(response.bodyDigest ?? "", /^[0-9a-f]{64}$/);
+
+    // A reader that stops early makes the writer stop too.
+    const abandoned = await wreqFetch(httpUrl("/stream/chunks?n=8&size=16384"));
+    const small = createBodyRing(1024);
+    const abandonedPipe = abandoned.pipeToRing(small);
+    for (const _bytes of readBodyRing(small)) {
+      break;
+    }
+    await abandonedPipe;
+
+    const inline = await wreqFetch(httpUrl("/get"));
+    const inlineRing = createBodyRing(1 << 16);
+    await inline.pipeToRing(inlineRing);
+    const parts: Buffer[] = [];
+    for (const bytes of readBodyRing(inlineRing)) {
+      parts.push(Buffer.from(bytes));
+    }
+    assert.ok(Buffer.concat(parts).toString().includes("{"));
+
+    assert.throws(() => createBodyRing(0), RequestError);
+    await assert.rejects(async () => response.pipeToRing(ring), /already\s+.*used/i);
+  });
+
+  test("reports native resource counts", async () => {
+    const before = getNativeStats();
+    for (const value of Object.values(before)) {
+      assert.strictEqual(typeof value, "number");
+    }
+
+    const session = await createSession();
+    try {
+      assert.strictEqual(getNativeStats().sessions, before.sessions + 1);
+    } finally {
+      await session.close();
+    }
+    assert.strictEqual(getNativeStats().sessions, before.sessions);
+  });
+
+  test("reports native memory usage", async () => {
+    const response = await wreqFetch(httpUrl("/get"));
+    const body = new Uint8Array(await response.arrayBuffer());
+    const stats = getMemoryStats();
+    for (const value of Object.values(stats)) {
+      assert.ok(Number.isInteger(value) && value >= 0);
+    }
+    // The inline body stays lent to JS for as long as `body` views it.
+    assert.ok(stats.inlineBodies >= 1);
+    assert.ok(stats.inlineBodyBytes >= body.byteLength);
+  });
+
+  test("releases native entries of collected wrappers", async () => {
+    setFlagsFromString("--expose-gc");
+    const gc = runInNewContext("gc") as () => void;
+    const before = getNativeStats();
+
+    await (async () => {
+      await createSession();
+      await createTransport();
+      await wreqFetch(httpUrl("/stream/chunks?n=2&size=1024"));
+    })();
+    assert.strictEqual(getNativeStats().sessions, before.sessions + 1);
+
+    const counts = () => {
+      const { sessions, transports, bodyStreams } = getNativeStats();
+      return { sessions, transports, bodyStreams };
+    };
+    const expected = { sessions: before.sessions, transports: before.transports, bodyStreams: before.bodyStreams };
+    // Native finalizers run after the collection that found the wrappers unreachable.
+    for (let i = 0; i < 50 && !isDeepStrictEqual(counts(), expected); i += 1) {
+      gc();
+      await delay(10);
+    }
+    assert.deepStrictEqual(counts(), expected);
+  });
+
+  test("reuses connections per origin with originPooling", async () => {
+    const sessionId = `origin-pool-${randomUUID()}`;
+    const init = { sessionId, cookieMode: "session", originPooling: true, timeout: 10_000 } as const;
+
+    const first = await wreqFetch(httpUrl("/get"), init);
+    await first.text();
+    const second = await wreqFetch(httpUrl("/get"), init);
+    await second.text();
+
+    assert.strictEqual(first.connection.client, "pooled");
+    assert.strictEqual(second.connection.reused, true);
+    assert.ok(getNativeStats().pooledClients >= 1);
+
+    const dedicated = await wreqFetch(httpUrl("/get"), { sessionId, cookieMode: "session", timeout: 10_000 });
+    await dedicated.text();
+    assert.strictEqual(dedicated.connection.client, "dedicated");
+    assert.strictEqual(dedicated.connection.reused, false);
+  });
+
+  test("delivers responses through requestCallback", { skip: !isLocalHttpBase }, async () => {
+    const call = (options: Parameters<typeof requestCallback>[0]) =>
+      new Promise<{ error: Error | null; response?: Response }>((resolve) => {
+        requestCallback(options, (error, response) => resolve({ error, ...(response && { response }) }));
+      });
+
+    const posted = await call({ url: httpUrl("/upload"), method: "POST", body: "hello", cancellable: false });
+    assert.strictEqual(posted.error, null);
+    assert.strictEqual(posted.response?.status, 200);
+    const body = await posted.response?.json<{ received: number }>();
+    assert.strictEqual(body?.received, 5);
+
+    const invalid = await call({ url: httpUrl("/get"), method: "GET", body: "not allowed" });
+    assert.ok(invalid.error instanceof RequestError);
+
+    const controller = new AbortController();
+    controller.abort();
+    const aborted = await call({ url: httpUrl("/get"), signal: controller.signal });
+    assert.strictEqual(aborted.error?.name, "AbortError");
+  });
 });
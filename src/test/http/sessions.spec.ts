@@ -1,9 +1,24 @@
 import assert from "node:assert";
+import { createServer, type Server } from "node:http";
+import type { AddressInfo } from "node:net";
 import { describe, test } from "node:test";
-import type { Session } from "../../wreq-js.js";
+import type { Session, StoredCookie } from "../../wreq-js.js";
 import { createSession, RequestError, withSession, fetch as wreqFetch } from "../../wreq-js.js";
 import { httpUrl } from "../helpers/http.js";
 
+// A forward proxy that answers every request itself, naming which proxy served it and echoing
+// the cookies it was sent. The first request it sees sets a cookie.
+async function startNamedProxy(name: string): Promise<{ url: string; server: Server }> {
+  const server = createServer((req, res) => {
+    res.setHeader("Content-Type", "application/json");
+    res.setHeader("Set-Cookie", `via_${name}=1; Path=/`);
+    res.end(JSON.stringify({ proxy: name, cookie: req.headers.cookie ?? "" }));
+  });
+  await new Promise<void>((resolve) => server.listen(0, "127.0.0.1", resolve));
+  const { port } = server.address() as AddressInfo;
+  return { url: `http://127.0.0.1:${port}`, server };
+}
+
 describe("HTTP sessions", () => {
   test("setCookie/getCookies round-trips and affects outgoing requests", async () => {
     const session = await createSession({ browser: "chrome_142" });
@@ -24,6 +39,60 @@ describe("HTTP sessions", () => {
     }
   });
 
+  test("imports cookies exported by a headless browser", async () => {
+    const session = await createSession({ browser: "chrome_142" });
+    const cookiesUrl = httpUrl("/cookies");
+    const { hostname } = new URL(cookiesUrl);
+
+    try {
+      const imported = session.importBrowserCookies([
+        {
+          name: "sid",
+          value: "abc",
+          domain: `.${hostname}`,
+          path: "/",
+          expires: -1,
+          httpOnly: true,
+          sameSite: "Lax",
+        },
+        { name: "theme", value: "dark", url: cookiesUrl, expires: Date.now() / 1000 + 3600 },
+        { name: "stale", value: "1", domain: hostname, path: "/", expires: 1 },
+      ]);
+      assert.strictEqual(imported, 2);
+
+      const storageState = JSON.stringify({
+        cookies: [{ name: "pw", value: "1", domain: hostname, path: "/", expires: -1, secure: false }],
+        origins: [],
+      });
+      assert.strictEqual(session.importBrowserCookies(storageState), 1);
+
+      const response = await session.fetch(cookiesUrl, { timeout: 10_000 });
+      const body = await response.json<{ cookies: Record<string, string> }>();
+      assert.deepStrictEqual(body.cookies, { sid: "abc", theme: "dark", pw: "1" });
+
+      assert.throws(() => session.importBrowserCookies('{"name":"sid"}'), RequestError);
+      assert.throws(() => session.importBrowserCookies([{ name: "loose", value: "1" }]), /neither domain nor url/);
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("pacing spaces out requests to the same host", async () => {
+    const session = await createSession({ browser: "chrome_142", pacing: { minDelayMs: 150, jitter: "normal" } });
+
+    try {
+      const started = performance.now();
+      await Promise.all([1, 2, 3].map(() => session.fetch(httpUrl("/get"), { timeout: 10_000 })));
+      // The first request goes straight out and each later one waits a gap behind it.
+      assert.ok(performance.now() - started >= 290, "Paced requests should not start together");
+
+      await assert.rejects(createSession({ pacing: { minDelayMs: 200, maxDelayMs: 100 } }), RequestError);
+      await assert.rejects(createSession({ pacing: { minDelayMs: 0, jitter: "poisson" as "normal" } }), RequestError);
+    } finally {
+      await session.close();
+    }
+  });
+
   test("recreating a disposed session id starts with an empty cookie jar", async () => {
     const sessionId = `cookie-disposed-${Date.now()}-${Math.random().toString(16).slice(2)}`;
     const cookiesUrl = httpUrl("/cookies");
@@ -121,6 +190,162 @@ describe("HTTP sessions", () => {
     }
   });
 
+  test("skips the cookie jar for requests with disableCookies", async () => {
+    const session = await createSession({ browser: "chrome_142" });
+
+    try {
+      session.setCookie("token", "abc123", httpUrl("/cookies"));
+
+      const anonymous = await session.fetch(httpUrl("/cookies/set?probe=1"), { disableCookies: true, timeout: 10000 });
+      const anonymousBody = await anonymous.json<{ cookies: Record<string, string> }>();
+      assert.deepStrictEqual(anonymousBody.cookies, { probe: "1" }, "Jar cookies should not be sent");
+      assert.deepStrictEqual(
+        session.getCookies(httpUrl("/cookies")),
+        { token: "abc123" },
+        "Set-Cookie should not be stored",
+      );
+
+      const regular = await session.fetch(httpUrl("/cookies"), { timeout: 10000 });
+      const regularBody = await regular.json<{ cookies: Record<string, string> }>();
+      assert.deepStrictEqual(regularBody.cookies, { token: "abc123" });
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("reports stored cookies to onSetCookie", async () => {
+    const stored: Array<{ cookie: StoredCookie; url: string }> = [];
+    let notify = () => {};
+    const session = await createSession({
+      onSetCookie: (cookie, url) => {
+        stored.push({ cookie, url });
+        notify();
+      },
+    });
+
+    try {
+      const reported = new Promise<void>((resolve) => {
+        notify = resolve;
+      });
+      await session.fetch(httpUrl("/cookies/set?flavor=alpha"), { disableCookies: true, timeout: 10000 });
+      await session.fetch(httpUrl("/cookies/set?token=rotated"), { timeout: 10000 });
+      await reported;
+
+      assert.strictEqual(stored.length, 1, "Requests that skip the jar should not report cookies");
+      const [{ cookie, url }] = stored as [{ cookie: StoredCookie; url: string }];
+      assert.strictEqual(url, httpUrl("/cookies/set?token=rotated"));
+      assert.strictEqual(cookie.name, "token");
+      assert.strictEqual(cookie.value, "rotated");
+      assert.strictEqual(cookie.domain, new URL(url).hostname);
+      assert.strictEqual(cookie.path, "/");
+      assert.strictEqual(cookie.expiresAt, null);
+      assert.strictEqual(cookie.removed, false);
+    } finally {
+      await session.close();
+    }
+
+    await assert.rejects(
+      createSession({ onSetCookie: "nope" as unknown as () => void }),
+      /onSetCookie must be a function/,
+    );
+  });
+
+  test("refreshes the bearer token once for concurrent 401s", async () => {
+    const realm = `endpoint-${Date.now()}`;
+    const protectedUrl = httpUrl(`/auth/protected?realm=${realm}`);
+    const session = await createSession({
+      auth: { accessToken: "stale", refreshToken: "r1", tokenEndpoint: httpUrl(`/auth/token?realm=${realm}`) },
+    });
+
+    try {
+      const responses = await Promise.all(Array.from({ length: 5 }, () => session.fetch(protectedUrl)));
+      for (const response of responses) {
+        assert.strictEqual(response.status, 200);
+        assert.deepStrictEqual(await response.json(), { token: `${realm}-1`, issued: 1 });
+      }
+
+      const own = await session.fetch(protectedUrl, { headers: { Authorization: "Bearer mine" } });
+      assert.strictEqual(own.status, 401, "An explicit Authorization header should be sent as is");
+      await own.text();
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("refreshes the bearer token through a JS callback", async () => {
+    const realm = `callback-${Date.now()}`;
+    let refreshes = 0;
+    const session = await createSession({
+      auth: {
+        refresh: async () => {
+          refreshes += 1;
+          const issued = await wreqFetch(httpUrl(`/auth/token?realm=${realm}`), {
+            method: "POST",
+            body: "grant_type=client_credentials",
+          });
+          const { access_token } = await issued.json<{ access_token: string }>();
+          return { accessToken: access_token, expiresIn: 3600 };
+        },
+      },
+    });
+
+    try {
+      const responses = await Promise.all(
+        Array.from({ length: 3 }, () => session.fetch(httpUrl(`/auth/protected?realm=${realm}`))),
+      );
+      assert.deepStrictEqual(
+        responses.map((response) => response.status),
+        [200, 200, 200],
+      );
+      assert.strictEqual(refreshes, 1);
+    } finally {
+      await session.close();
+    }
+
+    const failing = await createSession({
+      auth: {
+        refresh: () => {
+          throw new Error("login required");
+        },
+      },
+    });
+    try {
+      await assert.rejects(failing.fetch(httpUrl("/auth/protected")), /login required/);
+    } finally {
+      await failing.close();
+    }
+
+    await assert.rejects(createSession({ auth: {} }), /exactly one of tokenEndpoint and refresh/);
+  });
+
+  test("answers NTLM challenges on one connection", async () => {
+    const response = await wreqFetch(httpUrl("/ntlm"), {
+      ntlm: { username: "CORP\\User", password: "Password", workstation: "BUILD01" },
+    });
+    assert.strictEqual(response.status, 200);
+    assert.deepStrictEqual(await response.json(), { user: "User", domain: "CORP", workstation: "BUILD01" });
+
+    const session = await createSession({ ntlm: { username: "User", password: "wrong" } });
+    try {
+      const rejected = await session.fetch(httpUrl("/ntlm"));
+      assert.strictEqual(rejected.status, 401, "Bad credentials should end with the server's 401");
+      await rejected.text();
+
+      const overridden = await session.fetch(httpUrl("/ntlm"), {
+        ntlm: { username: "User", password: "Password", domain: "TEST" },
+      });
+      assert.strictEqual(overridden.status, 200);
+      await overridden.text();
+    } finally {
+      await session.close();
+    }
+
+    await assert.rejects(
+      wreqFetch(httpUrl("/ntlm"), { ntlm: { username: "", password: "x" } }),
+      /ntlm.username must be a non-empty string/,
+    );
+  });
+
   test("clears session cookies on demand", async () => {
     const session = await createSession({ browser: "chrome_142" });
 
@@ -170,4 +395,146 @@ describe("HTTP sessions", () => {
       await session.close();
     }
   });
+
+  test("rotates a proxy pool while keeping the cookie jar", async () => {
+    const first = await startNamedProxy("a");
+    const second = await startNamedProxy("b");
+    const session = await createSession({
+      browser: "chrome_142",
+      proxyPool: { proxies: [first.url, second.url], strategy: "rotateEveryNRequests", requestsPerProxy: 2 },
+    });
+
+    try {
+      const seen: Array<{ proxy: string; cookie: string }> = [];
+      for (let i = 0; i < 5; i += 1) {
+        const response = await session.fetch("http://origin.test/", { timeout: 5_000 });
+        seen.push(await response.json<{ proxy: string; cookie: string }>());
+      }
+
+      assert.deepStrictEqual(seen.map((entry) => entry.proxy), ["a", "a", "b", "b", "a"]);
+      assert.match(seen[2]?.cookie ?? "", /via_a=1/, "cookies set through one proxy are sent through the next");
+    } finally {
+      await session.close();
+      first.server.close();
+      second.server.close();
+    }
+  });
+
+  test("keeps a sticky proxy until a request through it fails", async () => {
+    const dead = await startNamedProxy("dead");
+    const live = await startNamedProxy("live");
+    await new Promise<void>((resolve) => dead.server.close(() => resolve()));
+    const session = await createSession({
+      browser: "chrome_142",
+      proxyPool: { proxies: [dead.url, live.url], strategy: "stickyUntilError" },
+    });
+
+    try {
+      await assert.rejects(session.fetch("http://origin.test/", { timeout: 5_000 }), RequestError);
+
+      for (let i = 0; i < 2; i += 1) {
+        const response = await session.fetch("http://origin.test/", { timeout: 5_000 });
+        assert.strictEqual((await response.json<{ proxy: string }>()).proxy, "live");
+      }
+    } finally {
+      await session.close();
+      live.server.close();
+    }
+  });
+
+  test("validates proxy pools", async () => {
+    await assert.rejects(createSession({ proxyPool: { proxies: [] } }), /proxyPool.proxies must be a non-empty array/);
+    await assert.rejects(
+      createSession({ proxy: "http://127.0.0.1:1", proxyPool: { proxies: ["http://127.0.0.1:2"] } }),
+      /proxy and proxyPool cannot be combined/,
+    );
+    await assert.rejects(
+      createSession({ proxyPool: { proxies: ["http://127.0.0.1:1"], strategy: "random" as "stickyUntilError" } }),
+      /proxyPool.strategy must be one of/,
+    );
+  });
+
+  test("sends the client hints an origin asks for with Accept-CH", async () => {
+    const selfSignedUrl = process.env.HTTPS_SELF_SIGNED_URL;
+    assert.ok(selfSignedUrl, "HTTPS_SELF_SIGNED_URL must be set by the test runner");
+
+    const session = await createSession({
+      browser: "chrome_142",
+      os: "windows",
+      acceptClientHints: true,
+      clientHints: { platformVersion: "15.0.0" },
+      insecure: true,
+    });
+
+    try {
+      const before = await session.fetch(new URL("/headers", selfSignedUrl), { timeout: 10_000 });
+      const beforeBody = await before.json<{ headers: Record<string, string> }>();
+      assert.strictEqual(beforeBody.headers["Sec-Ch-Ua-Platform-Version"], undefined);
+
+      const hints = "Sec-CH-UA-Full-Version-List, Sec-CH-UA-Platform-Version";
+      await session.fetch(new URL(`/accept-ch?hints=${encodeURIComponent(hints)}`, selfSignedUrl), {
+        timeout: 10_000,
+      });
+
+      const after = await session.fetch(new URL("/headers", selfSignedUrl), { timeout: 10_000 });
+      const afterBody = await after.json<{ headers: Record<string, string> }>();
+      assert.strictEqual(afterBody.headers["Sec-Ch-Ua-Platform-Version"], '"15.0.0"');
+      assert.match(afterBody.headers["Sec-Ch-Ua-Full-Version-List"] ?? "", /"Google Chrome";v="142\.0\.0\.0"/);
+
+      const plain = await session.fetch(httpUrl("/headers"), { timeout: 10_000 });
+      const plainBody = await plain.json<{ headers: Record<string, string> }>();
+      assert.strictEqual(plainBody.headers["Sec-Ch-Ua-Full-Version-List"], undefined, "other origins did not ask");
+    } finally {
+      await session.close();
+    }
+  });
+
+  test("remembers HSTS hosts and upgrades later http:// requests", async () => {
+    const selfSignedUrl = process.env.HTTPS_SELF_SIGNED_URL;
+    assert.ok(selfSignedUrl, "HTTPS_SELF_SIGNED_URL must be set by the test runner");
+
+    // HSTS is never recorded for IP literals, so address the server by name.
+    const secureBase = new URL(selfSignedUrl);
+    secureBase.hostname = "localhost";
+    const insecureBase = new URL(secureBase);
+    insecureBase.protocol = "http:";
+
+    const session = await createSession({ hsts: true, insecure: true });
+
+    try {
+      const first = await session.fetch(new URL("/hsts", secureBase), { timeout: 10_000 });
+      assert.strictEqual(first.status, 200);
+
+      const entries = session.getHsts();
+      assert.strictEqual(entries.length, 1);
+      assert.strictEqual(entries[0]?.host, "localhost");
+      assert.strictEqual(entries[0]?.includeSubdomains, true);
+      assert.ok((entries[0]?.expiresAt ?? 0) > Date.now());
+
+      const upgraded = await session.fetch(new URL("/get", insecureBase), { timeout: 10_000 });
+      assert.strictEqual(upgraded.status, 200);
+      assert.ok(upgraded.url.startsWith("https://"), "Known HSTS hosts should be upgraded to https");
+
+      const restored = await createSession({ hsts: true, insecure: true });
+      try {
+        restored.importHsts(entries);
+        const response = await restored.fetch(new URL("/get", insecureBase), { timeout: 10_000 });
+        assert.ok(response.url.startsWith("https://"), "Imported HSTS entries should apply");
+      } finally {
+        await restored.close();
+      }
+
+      session.clearHsts();
+      assert.deepStrictEqual(session.getHsts(), []);
+    } finally {
+      await session.close();
+    }
+
+    const plain = await createSession();
+    try {
+      assert.throws(() => plain.getHsts(), RequestError);
+    } finally {
+      await plain.close();
+    }
+  });
 });
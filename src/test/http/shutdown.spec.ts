@@ -0,0 +1,30 @@
+import assert from "node:assert";
+import { describe, test } from "node:test";
+import { createSession, shutdown, fetch as wreqFetch } from "../../wreq-js.js";
+import { httpUrl } from "../helpers/http.js";
+
+// Shutdown is terminal for the process, so these tests live in their own spec file.
+describe("Graceful shutdown", () => {
+  test("cancels outstanding work after the grace period and rejects new requests", async () => {
+    const pending = wreqFetch(httpUrl("/delay/10"), { timeout: 0 });
+    const settled = pending.then(
+      () => "resolved",
+      (error: unknown) => error,
+    );
+
+    const startedAt = Date.now();
+    await shutdown({ gracePeriodMs: 100 });
+    assert.ok(Date.now() - startedAt < 5_000, "shutdown should not wait for the slow request");
+
+    const outcome = await settled;
+    assert.ok(outcome instanceof Error, "in-flight request should be cancelled");
+    assert.strictEqual(outcome.name, "RequestError");
+
+    await assert.rejects(wreqFetch(httpUrl("/get")), /shutting down/);
+    await assert.rejects(createSession(), /shutting down/);
+  });
+
+  test("validates the grace period", async () => {
+    await assert.rejects(shutdown({ gracePeriodMs: -1 }), { name: "RequestError" });
+  });
+});
@@ -0,0 +1,83 @@
+import assert from "node:assert";
+import { once } from "node:events";
+import { describe, test } from "node:test";
+import { probe, RequestError, TlsSocket, tlsConnect } from "../../wreq-js.js";
+import { httpUrl } from "../helpers/http.js";
+
+const SELF_SIGNED_URL = process.env.HTTPS_SELF_SIGNED_URL;
+const H2_URL = process.env.HTTPS_H2_URL;
+
+if (!SELF_SIGNED_URL || !H2_URL) {
+  throw new Error("HTTPS_SELF_SIGNED_URL and HTTPS_H2_URL must be set by the test runner");
+}
+
+const { hostname, port } = new URL(SELF_SIGNED_URL);
+
+describe("Raw TLS sockets", () => {
+  test("speaks a plain-text protocol over an emulated TLS connection", async () => {
+    const socket = await tlsConnect({ host: hostname, port: Number(port), insecure: true, timeout: 10_000 });
+    assert.ok(socket instanceof TlsSocket);
+    assert.match(socket.tlsVersion, /^TLSv1\.[23]$/);
+    assert.ok(socket.remoteAddress?.endsWith(`:${port}`));
+
+    const chunks: Buffer[] = [];
+    socket.on("data", (chunk: Buffer) => chunks.push(chunk));
+    socket.write(`GET /json HTTP/1.1\r\nHost: ${hostname}:${port}\r\nConnection: close\r\n\r\n`);
+    await once(socket, "end");
+    socket.destroy();
+
+    const raw = Buffer.concat(chunks).toString("utf8");
+    assert.match(raw, /^HTTP\/1\.1 200/);
+  });
+
+  test("verifies certificates unless insecure is set", async () => {
+    await assert.rejects(
+      tlsConnect({ host: hostname, port: Number(port), timeout: 10_000 }),
+      (error: unknown) => error instanceof RequestError && /handshake/i.test(error.message),
+    );
+  });
+
+  test("validates connection options", async () => {
+    await assert.rejects(tlsConnect({ host: "", port: 443 }), RequestError);
+    await assert.rejects(tlsConnect({ host: "example.com", port: 0 }), RequestError);
+    await assert.rejects(tlsConnect({ host: "example.com", port: 443, proxy: "ftp://proxy.local" }), RequestError);
+  });
+});
+
+describe("Connection probes", () => {
+  test("times each phase of a TLS connection without sending a request", async () => {
+    const result = await probe({ url: SELF_SIGNED_URL, insecure: true, timeout: 10_000 });
+
+    assert.match(result.tlsVersion ?? "", /^TLSv1\.[23]$/);
+    assert.ok(result.cipher, "should report the negotiated cipher");
+    assert.ok(result.remoteAddress?.endsWith(`:${port}`));
+    assert.ok(result.timings.dns >= 0);
+    assert.ok(result.timings.connect >= 0);
+    assert.ok(typeof result.timings.tls === "number");
+    assert.strictEqual(result.timings.proxy, null);
+    assert.strictEqual(result.timings.h2, null);
+    assert.strictEqual(result.h2Settings, null);
+    assert.ok(result.timings.total >= result.timings.connect);
+  });
+
+  test("exchanges the HTTP/2 preface when requested", async () => {
+    const result = await probe({ url: H2_URL, insecure: true, http2: true, timeout: 10_000 });
+
+    assert.strictEqual(result.alpnProtocol, "h2");
+    assert.ok(typeof result.timings.h2 === "number");
+    assert.ok(result.h2Settings && typeof result.h2Settings === "object");
+  });
+
+  test("skips TLS for plain HTTP origins", async () => {
+    const result = await probe({ url: new URL(httpUrl("/")), timeout: 10_000 });
+
+    assert.strictEqual(result.timings.tls, null);
+    assert.strictEqual(result.tlsVersion, null);
+    assert.strictEqual(result.alpnProtocol, null);
+  });
+
+  test("rejects invalid probe targets", async () => {
+    await assert.rejects(probe({ url: "" }), RequestError);
+    await assert.rejects(probe({ url: "ftp://example.com" }), RequestError);
+  });
+});
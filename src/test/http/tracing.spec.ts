@@ -0,0 +1,87 @@
+import assert from "node:assert";
+import { afterEach, describe, test } from "node:test";
+import {
+  RequestError,
+  setTraceProvider,
+  type TraceSpanEnd,
+  type TraceSpanStart,
+  fetch as wreqFetch,
+} from "../../wreq-js.js";
+import { httpUrl } from "../helpers/http.js";
+
+const TRACEPARENT = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+describe("Trace context propagation", () => {
+  afterEach(() => {
+    setTraceProvider(null);
+  });
+
+  test("injects trace headers supplied per request", async () => {
+    const response = await wreqFetch(httpUrl("/headers"), {
+      trace: { traceparent: TRACEPARENT, tracestate: "vendor=abc" },
+      timeout: 10_000,
+    });
+    const body = (await response.json()) as { headers: Record<string, string> };
+
+    assert.strictEqual(body.headers.Traceparent, TRACEPARENT);
+    assert.strictEqual(body.headers.Tracestate, "vendor=abc");
+    assert.ok(response.timing);
+    assert.ok(response.timing.startTime <= response.timing.responseStartTime);
+
+    const untraced = await wreqFetch(httpUrl("/json"), { timeout: 10_000 });
+    await untraced.text();
+    assert.strictEqual(untraced.timing, null);
+  });
+
+  test("reports spans with native timing and redirect hops", async () => {
+    const started: TraceSpanStart[] = [];
+    const ended: TraceSpanEnd[] = [];
+    setTraceProvider({
+      getContext: () => ({ traceparent: TRACEPARENT }),
+      onSpanStart: (span) => started.push(span),
+      onSpanEnd: (span) => ended.push(span),
+    });
+
+    const response = await wreqFetch(httpUrl("/redirect"), { timeout: 10_000 });
+    await response.text();
+
+    assert.strictEqual(started.length, 1);
+    assert.strictEqual(ended.length, 1);
+    const [span] = ended;
+    assert.ok(span);
+    assert.strictEqual(span.context.traceparent, TRACEPARENT);
+    assert.strictEqual(span.method, "GET");
+    assert.strictEqual(span.status, 200);
+    assert.ok(span.startTime <= span.endTime);
+    assert.strictEqual(span.redirects.length, 1);
+    assert.strictEqual(span.redirects[0]?.status, 302);
+    assert.ok(span.redirects[0]?.location.endsWith("/json"));
+
+    const skipped = await wreqFetch(httpUrl("/json"), { trace: false, timeout: 10_000 });
+    await skipped.text();
+    assert.strictEqual(ended.length, 1);
+  });
+
+  test("reports failed requests and ignores throwing hooks", async () => {
+    const ended: TraceSpanEnd[] = [];
+    setTraceProvider({
+      getContext: () => ({ traceparent: TRACEPARENT }),
+      onSpanStart: () => {
+        throw new Error("hook failure");
+      },
+      onSpanEnd: (span) => ended.push(span),
+    });
+
+    await assert.rejects(wreqFetch("http://127.0.0.1:1/unreachable", { timeout: 2_000 }), RequestError);
+    assert.strictEqual(ended.length, 1);
+    assert.ok(ended[0]?.error instanceof Error);
+    assert.strictEqual(ended[0]?.status, undefined);
+
+    const response = await wreqFetch(httpUrl("/json"), { timeout: 10_000 });
+    assert.strictEqual(response.status, 200);
+  });
+
+  test("rejects malformed trace contexts", async () => {
+    await assert.rejects(wreqFetch(httpUrl("/json"), { trace: { traceparent: "not-a-traceparent" } }), RequestError);
+  });
+});
@@ -49,6 +49,7 @@ async function main() {
   env.WS_TEST_URL = localServer.wsUrl;
   env.HTTPS_SELF_SIGNED_URL = localServer.httpsSelfSignedUrl;
   env.HTTPS_EXPIRED_URL = localServer.httpsExpiredUrl;
+  env.HTTPS_H2_URL = localServer.httpsH2Url;
 
   const nodeArgs = ["--import", "tsx", "--test", ...defaultTestFiles, ...normalizedExtraArgs];
   const testProcess = spawn(process.execPath, nodeArgs, {
@@ -1,14 +1,18 @@
 import assert from "node:assert";
 import { before, describe, test } from "node:test";
+import { ReadableStream } from "node:stream/web";
 import { setTimeout as sleep } from "node:timers/promises";
+import { Worker } from "node:worker_threads";
 import {
   createSession,
+  getNativeStats,
   RequestError,
   type WebSocketCloseEvent,
   type WebSocketMessageEvent,
   Headers as WreqHeaders,
   WebSocket as WreqWebSocket,
   websocket,
+  websocketState,
 } from "../wreq-js.js";
 import { httpUrl } from "./helpers/http.js";
 
@@ -18,7 +22,7 @@ if (!WS_TEST_URL) {
   throw new Error("WS_TEST_URL environment variable must be set by the test runner");
 }
 
-function dataToString(data: string | Buffer | ArrayBuffer | Blob): string {
+function dataToString(data: WebSocketMessageEvent["data"]): string {
   if (typeof data === "string") {
     return data;
   }
@@ -31,6 +35,9 @@ function dataToString(data: string | Buffer | ArrayBuffer | Blob): string {
   if (typeof Blob !== "undefined" && data instanceof Blob) {
     throw new TypeError("Blob payload requires async conversion");
   }
+  if (data instanceof ReadableStream) {
+    throw new TypeError("Streamed payload requires async conversion");
+  }
   throw new TypeError("Unsupported WebSocket message payload type");
 }
 
@@ -240,7 +247,7 @@ describe("WebSocket", () => {
   test("message event exposes .data and supports handler assignment after construction", async () => {
     const ws = await websocket(WS_TEST_URL, { browser: "chrome_142" });
 
-    const received: Array<string | Buffer | ArrayBuffer | Blob> = [];
+    const received: Array<WebSocketMessageEvent["data"]> = [];
     ws.onmessage = (event) => {
       received.push(event.data);
     };
@@ -249,7 +256,7 @@ describe("WebSocket", () => {
     await sleep(100);
 
     assert.ok(received.length >= 1, "should receive at least one message");
-    assert.strictEqual(dataToString(received[0] as string | Buffer | ArrayBuffer | Blob), "hello");
+    assert.strictEqual(dataToString(received[0] as WebSocketMessageEvent["data"]), "hello");
 
     ws.close();
     await waitForClose(ws);
@@ -269,6 +276,31 @@ describe("WebSocket", () => {
     assert.strictEqual(ws.readyState, WreqWebSocket.CLOSED);
   });
 
+  test("websocketState reports state and last activity", async () => {
+    const ws = new WreqWebSocket(WS_TEST_URL);
+    assert.strictEqual(websocketState(ws).state, "connecting");
+    await waitForOpen(ws);
+
+    const opened = websocketState(ws);
+    assert.strictEqual(opened.state, "open");
+    assert.ok(opened.openedAt !== null && opened.openedAt <= Date.now());
+    assert.strictEqual(opened.lastSentAt, null);
+
+    const messagePromise = waitForMessage(ws);
+    ws.send("ping");
+    await messagePromise;
+
+    const active = websocketState(ws);
+    assert.ok(active.lastSentAt !== null && active.lastSentAt >= (opened.openedAt ?? 0));
+    assert.ok(active.lastReceivedAt !== null && active.lastReceivedAt >= active.lastSentAt);
+
+    const closePromise = waitForClose(ws);
+    ws.close();
+    assert.strictEqual(websocketState(ws).state, "closing");
+    await closePromise;
+    assert.strictEqual(websocketState(ws).state, "closed");
+  });
+
   test("binaryType defaults to nodebuffer and can switch to arraybuffer", async () => {
     const wsNodeBuffer = await websocket(WS_TEST_URL, { browser: "chrome_142" });
     const bytes = new Uint8Array([1, 2, 3, 4, 5]);
@@ -771,4 +803,123 @@ describe("WebSocket", () => {
     const event = await waitForClose(ws);
     assert.strictEqual(event.code, 3000);
   });
+
+  test("batched delivery dispatches every message in order", async () => {
+    const ws = await websocket(WS_TEST_URL, {
+      browser: "chrome_142",
+      batch: { maxMessages: 4, flushIntervalMs: 20 },
+    });
+
+    const received: string[] = [];
+    const allReceived = new Promise<void>((resolve) => {
+      ws.addEventListener("message", (event) => {
+        received.push(dataToString(event.data));
+        if (received.length === 10) {
+          resolve();
+        }
+      });
+    });
+
+    const sent = Array.from({ length: 10 }, (_, index) => `batched-${index}`);
+    for (const message of sent) {
+      await ws.send(message);
+    }
+    await allReceived;
+    assert.deepStrictEqual(received, sent);
+
+    ws.close();
+    await waitForClose(ws);
+  });
+
+  test("binaryText delivers text frames as binary data", async () => {
+    const ws = await websocket(WS_TEST_URL, { browser: "chrome_142", binaryText: true });
+
+    const bufferMessage = waitForMessage(ws);
+    await ws.send("{\"feed\":1}");
+    const bufferData = (await bufferMessage).data;
+    assert.ok(Buffer.isBuffer(bufferData));
+    assert.strictEqual(bufferData.toString("utf8"), "{\"feed\":1}");
+
+    ws.binaryType = "arraybuffer";
+    const arrayBufferMessage = waitForMessage(ws);
+    await ws.send("héllo");
+    const arrayBufferData = (await arrayBufferMessage).data;
+    assert.ok(arrayBufferData instanceof ArrayBuffer);
+    assert.strictEqual(dataToString(arrayBufferData), "héllo");
+
+    ws.close();
+    await waitForClose(ws);
+  });
+
+  test("streams messages above the streamMessages threshold", async () => {
+    const ws = await websocket(WS_TEST_URL, {
+      browser: "chrome_142",
+      streamMessages: { threshold: 1024, chunkSize: 16 * 1024 },
+    });
+
+    const smallMessage = waitForMessage(ws);
+    await ws.send("small");
+    assert.strictEqual(dataToString((await smallMessage).data), "small");
+
+    const payload = Buffer.alloc(100 * 1024);
+    for (let index = 0; index < payload.length; index++) {
+      payload[index] = index % 251;
+    }
+    const largeMessage = waitForMessage(ws);
+    await ws.send(payload);
+    const data = (await largeMessage).data;
+    assert.ok(data instanceof ReadableStream);
+
+    const chunks: Uint8Array[] = [];
+    for await (const chunk of data) {
+      assert.ok(chunk.byteLength <= 16 * 1024);
+      chunks.push(chunk);
+    }
+    assert.strictEqual(chunks.length, 7);
+    assert.ok(Buffer.concat(chunks).equals(payload));
+
+    ws.close();
+    await waitForClose(ws);
+  });
+
+  test("rejects invalid batch and streamMessages options", async () => {
+    await assert.rejects(
+      websocket(WS_TEST_URL, { batch: { maxMessages: 0 } }),
+      (error: unknown) => error instanceof RequestError && /batch\.maxMessages/.test(error.message),
+    );
+    await assert.rejects(
+      websocket(WS_TEST_URL, { batch: { flushIntervalMs: -1 } }),
+      (error: unknown) => error instanceof RequestError && /batch\.flushIntervalMs/.test(error.message),
+    );
+    await assert.rejects(
+      websocket(WS_TEST_URL, { streamMessages: { threshold: 1024, chunkSize: 0 } }),
+      (error: unknown) => error instanceof RequestError && /streamMessages\.chunkSize/.test(error.message),
+    );
+  });
+
+  test("closes a worker's WebSockets when the worker exits", async () => {
+    const before = getNativeStats().webSockets;
+    const worker = new Worker(
+      `
+      const { parentPort, workerData } = require("node:worker_threads");
+      import(workerData.module).then(async ({ websocket }) => {
+        await websocket(workerData.url);
+        parentPort.postMessage("open");
+      });
+      `,
+      { eval: true, workerData: { module: new URL("../wreq-js.ts", import.meta.url).href, url: WS_TEST_URL } },
+    );
+
+    await new Promise((resolve, reject) => {
+      worker.once("message", resolve);
+      worker.once("error", reject);
+    });
+    assert.strictEqual(getNativeStats().webSockets, before + 1);
+
+    await worker.terminate();
+    for (let attempt = 0; attempt < 50 && getNativeStats().webSockets > before; attempt += 1) {
+      await sleep(20);
+    }
+    assert.strictEqual(getNativeStats().webSockets, before, "the exited worker's WebSocket is closed");
+  });
 });
@@ -1,7 +1,15 @@
 // Import and re-export the auto-generated BrowserProfile and EmulationOS types
-import type { BrowserProfile, EmulationOS } from "./generated-types.js";
-import type { Session, Transport, WebSocket } from "./wreq-js.js";
-export type { BrowserProfile, EmulationOS };
+import type { BrowserFamily, BrowserProfile, EmulationOS } from "./generated-types.js";
+import type { ReadableStream } from "node:stream/web";
+import type { Headers, Response, Session, Transport, WebSocket } from "./wreq-js.js";
+export type { BrowserFamily, BrowserProfile, EmulationOS };
+
+/**
+ * Alias resolved natively to a {@link BrowserProfile}: a family (`"chrome"`) or
+ * `"<family>:latest"` for the family's newest profile, or `"firefox:esr"` for the newest
+ * Firefox ESR. Aliases follow wreq-util updates instead of pinning a version.
+ */
+export type BrowserProfileAlias = BrowserFamily | `${BrowserFamily}:latest` | "firefox:esr";
 
 /**
  * Controls how cookies are scoped for a request.
@@ -10,6 +18,14 @@ export type { BrowserProfile, EmulationOS };
  */
 export type CookieMode = "session" | "ephemeral";
 
+/**
+ * Whether sessions and transports created in one JS context (the main thread or a
+ * `worker_threads` worker) can be used from another.
+ * - "shared": IDs work from any context, e.g. a session ID posted to a worker.
+ * - "isolated": IDs only work in the context that created them.
+ */
+export type RegistryMode = "shared" | "isolated";
+
 /**
  * Minimal handle implemented by {@link Session}. Exposed for integrations
  * that only need to carry a session id.
@@ -18,16 +34,42 @@ export interface SessionHandle {
   readonly id: string;
 }
 
+/**
+ * How a header tuple combines with values given before it.
+ *
+ * - `set`: replace earlier values of the same name, as `Headers.set()` does.
+ * - `append`: add to earlier values of the same name, and keep the browser profile's and the
+ *   session's default value instead of replacing it.
+ *
+ * Without a mode, repeated names in a list are combined as with `Headers.append()`, and the
+ * result replaces the profile and session defaults of that name.
+ */
+export interface HeaderTupleOptions {
+  mode: "set" | "append";
+}
+
 /**
  * A tuple of [name, value] pairs used for initializing headers.
- * Both name and value must be strings.
+ * Both name and value must be strings. An optional third element picks the header's mode.
  *
  * @example
  * ```typescript
  * const headers: HeaderTuple = ['Content-Type', 'application/json'];
+ * const accept: HeaderTuple = ['Accept', 'application/json', { mode: 'set' }];
+ * ```
+ */
+export type HeaderTuple = [string, string] | [string, string, HeaderTupleOptions];
+
+/**
+ * A header tuple accepted in request headers. Buffer values are sent as raw bytes, for values
+ * that are not UTF-8 text (e.g. latin-1 encoded filenames).
+ *
+ * @example
+ * ```typescript
+ * const headers: HeaderInitTuple = ['X-Filename', Buffer.from('r\xe9sum\xe9.pdf', 'latin1')];
  * ```
  */
-export type HeaderTuple = [string, string];
+export type HeaderInitTuple = [string, string | Buffer] | [string, string | Buffer, HeaderTupleOptions];
 
 /**
  * Represents various input types accepted when creating or initializing headers.
@@ -46,9 +88,9 @@ export type HeaderTuple = [string, string];
  * ```
  */
 export type HeadersInit =
-  | Iterable<HeaderTuple>
-  | Array<HeaderTuple>
-  | Record<string, string | number | boolean | null | undefined>;
+  | Iterable<HeaderInitTuple>
+  | Array<HeaderInitTuple>
+  | Record<string, string | number | boolean | Buffer | null | undefined>;
 
 /**
  * Represents the various types of data that can be used as a request body.
@@ -71,6 +113,88 @@ export type HeadersInit =
  */
 export type BodyInit = string | ArrayBuffer | ArrayBufferView | URLSearchParams | Buffer | Blob | FormData;
 
+/**
+ * Hash algorithm used to digest a response body as it is read.
+ */
+export type BodyDigestAlgorithm = "sha256" | "md5";
+
+/**
+ * The kind of request a page would be making, for {@link RequestInit.mode}.
+ */
+export type RequestMode = "navigate" | "xhr" | "fetch" | "image";
+
+/**
+ * How response headers cross from the native layer: as `[name, value]` tuples, or as one object
+ * of lower-cased names and comma-joined values.
+ */
+export type HeaderFormat = "tuples" | "object";
+
+/**
+ * Extensible priority parameters (RFC 9218) for {@link RequestInit.priority}.
+ */
+export interface RequestPriority {
+  /**
+   * From 0 (most urgent) to 7. Browsers use 0 for navigations, 1 for scripts and fetches, and
+   * higher values for images and prefetches.
+   */
+  urgency: number;
+  /**
+   * Whether the response is useful as it arrives, as for images.
+   * @default false
+   */
+  incremental?: boolean;
+}
+
+/**
+ * One entry of the `Sec-CH-UA` brand list.
+ */
+export interface ClientHintBrand {
+  brand: string;
+  /** Full version, such as `"142.0.7444.60"`; `Sec-CH-UA` only carries its major part. */
+  version: string;
+}
+
+/**
+ * A rule of {@link RequestInit.connectTo}.
+ */
+export interface ConnectToRule {
+  /** Host name the rule applies to, compared case-insensitively. IP addresses are not redirected. */
+  fromHost: string;
+  /** Port the rule applies to. Any port when unset. */
+  fromPort?: number;
+  /** Host name or IP address to connect to instead. `fromHost` itself when unset. */
+  toHost?: string;
+  /** Port to connect to instead. The URL's port when unset. */
+  toPort?: number;
+}
+
+/**
+ * User-agent client hints for {@link RequestInit.clientHints}. Fields left unset keep the
+ * browser profile's value, or one consistent with it for hints the profile does not send.
+ * Only Chromium profiles send client hints; other profiles ignore these.
+ */
+export interface ClientHintsOptions {
+  /** Replaces the brand list in `Sec-CH-UA` and `Sec-CH-UA-Full-Version-List`. */
+  brands?: ClientHintBrand[];
+  /** `Sec-CH-UA-Full-Version`, and the version of every brand that is not GREASE. */
+  fullVersion?: string;
+  /** `Sec-CH-UA-Platform`, such as `"Windows"` or `"macOS"`. */
+  platform?: string;
+  /** `Sec-CH-UA-Platform-Version`. */
+  platformVersion?: string;
+  /** `Sec-CH-UA-Mobile`. */
+  mobile?: boolean;
+  /** `Sec-CH-UA-Arch`, such as `"x86"` or `"arm"`. */
+  arch?: string;
+  /** `Sec-CH-UA-Model`. */
+  model?: string;
+}
+
+/**
+ * Content coding advertised in `Accept-Encoding`. `"identity"` asks for uncompressed responses.
+ */
+export type ContentCoding = "gzip" | "deflate" | "br" | "zstd" | "identity";
+
 /**
  * Details about why a WebSocket connection closed.
  */
@@ -90,7 +214,11 @@ export interface WebSocketMessageEvent {
   type: "message";
   isTrusted: false;
   timeStamp: number;
-  data: string | Buffer | ArrayBuffer | Blob;
+  /**
+   * The message payload. Messages above the `streamMessages` threshold arrive as a stream of their
+   * bytes instead, whatever the `binaryType`.
+   */
+  data: string | Buffer | ArrayBuffer | Blob | ReadableStream<Uint8Array>;
   target: WebSocket;
   currentTarget: WebSocket;
 }
@@ -124,6 +252,20 @@ export interface WebSocketCloseEvent {
   currentTarget: WebSocket;
 }
 
+/**
+ * State and last activity of a WebSocket, as tracked by the native connection.
+ * Times are milliseconds since the Unix epoch, or `null` when there is none yet.
+ */
+export interface WebSocketState {
+  state: "connecting" | "open" | "closing" | "closed";
+  /** When the handshake completed. */
+  openedAt: number | null;
+  /** When the last message was handed to the connection. */
+  lastSentAt: number | null;
+  /** When the last frame arrived from the peer, control frames included. */
+  lastReceivedAt: number | null;
+}
+
 /**
  * Options for configuring a fetch style request with wreq-specific extensions
  * for browser impersonation, proxies, sessions, and timeouts.
@@ -148,203 +290,1594 @@ export interface RequestInit {
   method?: string;
 
   /**
-   * A Headers object, an object literal, or an array of two-item arrays to set request's headers.
+   * A Headers object, an object literal, or an array of two-item arrays to set request's headers.
+   */
+  headers?: HeadersInit;
+
+  /**
+   * A BodyInit object or null to set request's body.
+   */
+  body?: BodyInit | null;
+
+  /**
+   * An AbortSignal to set request's signal.
+   */
+  signal?: AbortSignal | null;
+
+  /**
+   * A string indicating whether request follows redirects, results in an error upon
+   * encountering a redirect, or returns the redirect (in an opaque fashion).
+   * @default 'follow'
+   */
+  redirect?: "follow" | "manual" | "error";
+
+  /**
+   * Fail the request with a `RequestError` when a redirect would move it from `https` to
+   * plain `http`, instead of following the downgrade. Defaults to the session's setting.
+   * @default false
+   */
+  blockInsecureRedirects?: boolean;
+
+  /**
+   * Request headers to remove when a redirect leads to another origin (scheme, host, or port).
+   * Headers not listed are sent to the new origin as-is; an empty list keeps them all. When
+   * unset, the native client strips `authorization`, `cookie`, `cookie2`,
+   * `proxy-authorization`, and `www-authenticate`.
+   * @example ["authorization", "cookie", "x-api-key"]
+   */
+  stripOnCrossOrigin?: string[];
+
+  /**
+   * Follow `<meta http-equiv="refresh">` tags in HTML responses as if they were redirects, for
+   * interstitial pages that use them instead of a 3xx. `true` follows refreshes due within 5
+   * seconds; a number sets that limit in seconds. Only applies when `redirect` is `"follow"`.
+   * HTML responses are buffered to look for the tag, and traced requests record each hop in
+   * `timing.redirects`.
+   * @default false
+   */
+  followMetaRefresh?: boolean | number;
+
+  /**
+   * Windows credentials for servers that answer `401` with an `NTLM` or `Negotiate` challenge.
+   * The handshake runs natively on one connection, and the request is sent with each leg.
+   * Requests that set their own `Authorization` header are sent as they are.
+   */
+  ntlm?: NtlmCredentials;
+
+  /**
+   * Override the `Sec-CH-UA` client hints the browser profile sends. High-entropy hints such
+   * as `Sec-CH-UA-Full-Version-List` are only sent to origins that ask for them, through a
+   * session created with `acceptClientHints`.
+   */
+  clientHints?: ClientHintsOptions;
+
+  /**
+   * Open the connection for a host and port somewhere else, like curl's `--connect-to`, while the
+   * `Host` header and TLS server name still name the URL's host. The first rule for the URL's
+   * host and port applies, to redirects back to that host too. A port the URL names cannot be
+   * replaced, and the rules have no effect through a proxy.
+   * @example [{ fromHost: "api.example.com", toHost: "10.0.0.12", toPort: 8443 }]
+   */
+  connectTo?: ConnectToRule[];
+
+  /**
+   * Transport instance to use for this request. When provided, transport-level
+   * options such as `browser`, `os`, and `insecure` must not be set.
+   */
+  transport?: Transport;
+
+  /**
+   * Browser profile to impersonate for this request.
+   * Applies browser profile behavior handled by the native layer.
+   * Ignored when `transport` is provided.
+   * @default 'chrome_142'
+   */
+  browser?: BrowserProfile | BrowserProfileAlias;
+
+  /**
+   * Operating system to emulate for this request.
+   * Influences platform-specific behavior handled by the native layer.
+   * Ignored when `transport` is provided.
+   * @default 'macos'
+   */
+  os?: EmulationOS;
+
+  /**
+   * Throw when the native layer cannot resolve `browser` or `os`, listing the valid values,
+   * instead of falling back to the defaults. See `setStrictProfiles()` to turn this on globally.
+   * @default false
+   */
+  strictProfiles?: boolean;
+
+  /**
+   * Proxy URL to route the request through (e.g., 'http://proxy.example.com:8080').
+   * Proxy support depends on the native layer and proxy scheme.
+   * With a `transport`, it replaces the transport's proxy for this request only; `null`
+   * connects directly. A session's proxy cannot be changed per request.
+   */
+  proxy?: string | null;
+
+  /**
+   * Request timeout in milliseconds. If the request takes longer than this value,
+   * it will be aborted. Covers connecting, sending the body, waiting for the response,
+   * and reading the response body. When `writeTimeout` is set, time spent sending the
+   * request body is not counted.
+   * @default 30000
+   */
+  timeout?: number;
+
+  /**
+   * Fail the upload when the connection stops accepting request body data for this many
+   * milliseconds. Slow uploads that keep making progress are never cut off, and `timeout`
+   * is paused while the body is being sent. Ignored for requests without a body.
+   */
+  writeTimeout?: number;
+
+  /**
+   * Hard limit in milliseconds for the whole request, from connecting until the response body
+   * has been read, including time spent sending the body. Applies in addition to `timeout`.
+   */
+  totalDeadline?: number;
+
+  /**
+   * Controls how cookies are managed for this call.
+   * - "ephemeral": default when no session/sessionId is provided. Creates an isolated session per request.
+   * - "session": requires an explicit session or sessionId and reuses its cookie jar.
+   */
+  cookieMode?: CookieMode;
+
+  /**
+   * Session instance to bind this request to. When provided, {@link cookieMode}
+   * automatically behaves like `"session"`.
+   */
+  session?: Session;
+
+  /**
+   * Identifier of an existing session created elsewhere (e.g., via {@link createSession}).
+   */
+  sessionId?: string;
+
+  /**
+   * Leave the cookie jar out of this request: its cookies are not sent and `Set-Cookie`
+   * responses are not stored, while the session's connections, HSTS, and other state are still
+   * used. `response.cookies` still reports the cookies the server set.
+   * @default false
+   */
+  disableCookies?: boolean;
+
+  /**
+   * Content codings to advertise in `Accept-Encoding`, replacing the browser profile's value while
+   * leaving the rest of its headers intact. Only these codings are decoded; anything else the server
+   * sends is returned as-is. Use `["identity"]` to ask for uncompressed responses. An explicit
+   * `Accept-Encoding` header still takes precedence. Overrides the transport's setting.
+   */
+  acceptEncoding?: ContentCoding[];
+
+  /**
+   * Decompress the response body according to its `Content-Encoding`. When `false`, the body is
+   * returned exactly as received on the wire and the `Content-Encoding` header is left in place.
+   * @default true
+   */
+  decodeBody?: boolean;
+
+  /**
+   * Request priority sent in the `Priority` header (RFC 9218), replacing the browser profile's
+   * value. Browsers send `{ urgency: 0, incremental: true }` for navigations and lower urgencies
+   * for subresources. An explicit `Priority` header still takes precedence.
+   */
+  priority?: RequestPriority;
+
+  /**
+   * Send the `Sec-Fetch-Site`, `Sec-Fetch-Mode`, `Sec-Fetch-Dest`, `Sec-Fetch-User`, `Accept`,
+   * and `Priority` headers the emulated browser uses for this kind of request. `Sec-Fetch-Site`
+   * compares the target with the page named by a `Referer` or `Origin` header; without one, a
+   * navigation counts as typed into the address bar and other requests as coming from the
+   * target's own origin. `xhr` and `fetch` send the same headers, as browsers do. Explicit
+   * headers and the `priority` option still take precedence. Without a mode, the profile's
+   * headers are sent as they are: those of a navigation, less `Sec-Fetch-User`.
+   */
+  mode?: RequestMode;
+
+  /**
+   * With `"object"`, the native layer builds the response headers as one plain object, read
+   * through `response.headerObject`, instead of a list that JS has to convert. `response.headers`
+   * works either way.
+   * @default "tuples"
+   */
+  headerFormat?: HeaderFormat;
+
+  /**
+   * Return only the status, URL, connection and body, for high-rate polling that reads nothing
+   * else. Response headers, cookies, the reason phrase, trailers, body digest, sizes and timing
+   * are not read natively and come back empty or `null`. The session's cookie jar still stores
+   * cookies the server sets.
+   * @default false
+   */
+  minimalResponse?: boolean;
+
+  /**
+   * Called with the request as it is about to go out, after the browser profile's default headers
+   * and the session's cookies have been added, to return headers to append to it, such as a
+   * signature over the others. It runs once per call: redirects are not passed back through it. A
+   * throw or rejection fails the request.
+   */
+  beforeSend?: (request: BeforeSendRequest) => HeadersInit | undefined | Promise<HeadersInit | undefined>;
+
+  /**
+   * Locales to send in `Accept-Language` in place of the browser profile's value, most
+   * preferred first. Region-qualified tags are followed by their bare language and weighted
+   * like a browser does, so `["de-DE", "en"]` sends `de-DE,de;q=0.9,en;q=0.8`. An explicit
+   * `Accept-Language` header still takes precedence. Defaults to the session's `locale`.
+   * @example "fr-FR"
+   */
+  locale?: string | string[];
+
+  /**
+   * Serve session requests that have no transport from a client shared per origin (and per
+   * browser, OS, proxy, and TLS settings), so connections are reused across calls instead of
+   * being opened for each request. Cookie jars stay per session; only connections are shared.
+   * Requests without a session already share a cached client.
+   * @default false
+   */
+  originPooling?: boolean;
+
+  /**
+   * Disable default headers from browser emulation. When enabled, only explicitly
+   * provided headers will be sent with the request, preventing emulation headers
+   * from being automatically added or appended.
+   * @default false
+   */
+  disableDefaultHeaders?: boolean;
+
+  /**
+   * Browser emulation headers to leave off this request, matched case-insensitively. The rest
+   * of the profile's headers are still sent. Has no effect with `disableDefaultHeaders`.
+   * @example ["accept-language", "sec-ch-ua"]
+   */
+  removeHeaders?: string[];
+
+  /**
+   * Disable HTTPS certificate verification. When enabled, self-signed and invalid
+   * certificates will be accepted.
+   * Ignored when `transport` is provided.
+   *
+   * # Warning
+   *
+   * You should think very carefully before using this method. If invalid
+   * certificates are trusted, *any* certificate for *any* site will be
+   * trusted for use. This includes expired certificates. This introduces
+   * significant vulnerabilities, and should only be used as a last resort.
+   *
+   * @default false
+   */
+  insecure?: boolean;
+
+  /**
+   * Tag this request with a cancellation group. Every request sharing the same
+   * `groupId` can be cancelled at once with `cancelGroup()`, which also
+   * releases any response bodies that have not been fully read.
+   */
+  groupId?: string;
+
+  /**
+   * Share one upstream request between identical concurrent GET/HEAD calls.
+   * While a request with the same URL, headers, session, and transport settings is
+   * in flight, later callers wait for it and receive their own clone of the response.
+   * The shared request is cancelled once every caller has aborted.
+   * Other methods, requests with a body, and requests with a `beforeSend` hook ignore this flag.
+   * @default false
+   */
+  coalesce?: boolean;
+
+  /**
+   * Cap how fast the response body is read from the network, in bytes per second.
+   * The cap is enforced natively, so bytes beyond it are never pulled off the socket early.
+   * When the request also uses a transport with its own cap, the slower of the two applies.
+   */
+  maxDownloadBytesPerSec?: number;
+
+  /**
+   * Cap how fast the request body is sent, in bytes per second. The body is paced in
+   * 16 KiB slices on the native side and keeps its `Content-Length`.
+   * When the request also uses a transport with its own cap, the slower of the two applies.
+   */
+  maxUploadBytesPerSec?: number;
+
+  /**
+   * Hash the response body natively while it is read, without a second pass over the bytes in JS.
+   * The hex digest is available from `response.bodyDigest` once the body has been read to the end.
+   */
+  bodyDigest?: BodyDigestAlgorithm;
+
+  /**
+   * W3C trace context to inject as `traceparent`/`tracestate` on every hop of this request.
+   * Defaults to the context from the provider registered with `setTraceProvider()`; `false`
+   * disables tracing for this request. Explicit `traceparent`/`tracestate` headers take precedence.
+   */
+  trace?: TraceContext | false;
+}
+
+/**
+ * A request as {@link RequestInit.beforeSend} sees it.
+ */
+export interface BeforeSendRequest {
+  method: string;
+  url: string;
+  /**
+   * Headers in the order they are sent, names lower-cased. `Host` and `Content-Length` are only
+   * added as the request is written.
+   */
+  headers: [string, string][];
+}
+
+/**
+ * Options for {@link requestCallback}: the request URL plus the usual {@link RequestInit} fields.
+ * `coalesce` is ignored, and trace provider span hooks are not called.
+ */
+export interface RequestCallbackOptions extends RequestInit {
+  /**
+   * The URL to request.
+   */
+  url: string | URL;
+
+  /**
+   * Register the request natively so `signal` and `cancelGroup()` can stop it. `false` skips the
+   * registration for less per-request work; aborting `signal` then only settles the callback early.
+   * @default true when `signal` or `groupId` is set, false otherwise
+   */
+  cancellable?: boolean;
+}
+
+/**
+ * Node-style callback for {@link requestCallback}. Receives either an error or the response.
+ */
+export type RequestCallback = (error: Error | null, response?: Response) => void;
+
+/**
+ * W3C Trace Context propagated with a request.
+ */
+export interface TraceContext {
+  /**
+   * `traceparent` header value, e.g. `00-<trace-id>-<parent-id>-01`.
+   */
+  traceparent: string;
+
+  /**
+   * Optional vendor-specific `tracestate` header value.
+   */
+  tracestate?: string;
+}
+
+/**
+ * A redirect response received while a traced request was in flight.
+ */
+export interface TraceRedirect {
+  status: number;
+  /** URL the redirect pointed to. */
+  location: string;
+  /** When the redirect response arrived, in milliseconds since the Unix epoch. */
+  time: number;
+}
+
+/**
+ * Native timing of a traced request. Times are milliseconds since the Unix epoch.
+ */
+export interface RequestTiming {
+  /** When the native client started sending the request. */
+  startTime: number;
+  /** When the final response headers arrived. */
+  responseStartTime: number;
+  redirects: TraceRedirect[];
+}
+
+/**
+ * Byte counts of a response, for bandwidth accounting.
+ */
+export interface ResponseSizes {
+  /**
+   * Header block as HTTP/1.1 `name: value` lines. HTTP/2 and HTTP/3 compress headers, so fewer
+   * bytes may have crossed the wire.
+   */
+  headerBytes: number;
+  /** Body bytes as the server sent them, before content decoding. */
+  wireBodyBytes: number;
+  /** Body bytes after content decoding; the same as `wireBodyBytes` when the body was not encoded. */
+  decodedBodyBytes: number;
+}
+
+/**
+ * Emitted when a traced request starts.
+ */
+export interface TraceSpanStart {
+  context: TraceContext;
+  method: string;
+  url: string;
+  /** Milliseconds since the Unix epoch. */
+  startTime: number;
+}
+
+/**
+ * Emitted when a traced request has its response headers, or has failed.
+ */
+export interface TraceSpanEnd extends TraceSpanStart {
+  /** Milliseconds since the Unix epoch. */
+  endTime: number;
+  /** Final status code; absent when the request failed. */
+  status?: number;
+  error?: Error;
+  redirects: TraceRedirect[];
+}
+
+/**
+ * Hooks registered with `setTraceProvider()` to connect requests to a tracer such as OpenTelemetry.
+ */
+export interface TraceProvider {
+  /**
+   * Return the context to propagate for a request, or `undefined` to leave it untraced.
+   * Not called for requests that set `trace` themselves.
+   */
+  getContext?(request: { method: string; url: string }): TraceContext | undefined;
+
+  /**
+   * Called before a traced request is dispatched.
+   */
+  onSpanStart?(span: TraceSpanStart): void;
+
+  /**
+   * Called once a traced request has its response headers or has failed. Start and end times
+   * come from the native client when the request went over the network.
+   */
+  onSpanEnd?(span: TraceSpanEnd): void;
+}
+
+/**
+ * Configuration for {@link createSession}.
+ */
+export interface CreateSessionOptions {
+  /**
+   * Provide a custom identifier instead of an auto-generated random ID.
+   */
+  sessionId?: string;
+
+  /**
+   * Default headers applied to every request made through this session.
+   */
+  defaultHeaders?: HeadersInit;
+
+  /**
+   * Browser profile to bind to this session. Defaults to 'chrome_142'.
+   */
+  browser?: BrowserProfile | BrowserProfileAlias;
+
+  /**
+   * Operating system to bind to this session. Defaults to 'macos'.
+   */
+  os?: EmulationOS;
+  /**
+   * Optional proxy for every request made through the session.
+   */
+  proxy?: string;
+  /**
+   * Rotate the session's requests through several proxies instead of using `proxy`. The
+   * cookie jar is kept across proxies.
+   */
+  proxyPool?: ProxyPoolOptions;
+  /**
+   * Windows credentials for a `proxy` that requires NTLM. See
+   * {@link CreateTransportOptions.proxyNtlm}.
+   */
+  proxyNtlm?: NtlmCredentials;
+  /**
+   * Default timeout applied when {@link Session.fetch} is called without
+   * overriding `timeout`.
+   */
+  timeout?: number;
+
+  /**
+   * Disable HTTPS certificate verification. When enabled, self-signed and invalid
+   * certificates will be accepted for all requests made through this session.
+   *
+   * # Warning
+   *
+   * You should think very carefully before using this method. If invalid
+   * certificates are trusted, *any* certificate for *any* site will be
+   * trusted for use. This includes expired certificates. This introduces
+   * significant vulnerabilities, and should only be used as a last resort.
+   *
+   * @default false
+   */
+  insecure?: boolean;
+
+  /**
+   * Keep an HSTS store for this session, like a browser does. Hosts that send
+   * `Strict-Transport-Security` over HTTPS are remembered; later `http://` requests to
+   * them are upgraded to `https://`, and redirects that downgrade them are refused.
+   * @default false
+   */
+  hsts?: boolean;
+
+  /**
+   * Default for {@link RequestInit.blockInsecureRedirects} on requests made through this
+   * session, so redirects never carry its credentials from `https` to plain `http`.
+   * @default false
+   */
+  blockInsecureRedirects?: boolean;
+
+  /**
+   * Called whenever a response stores a cookie in the session's jar, including each redirect
+   * hop, with the URL of the response that set it. Cookies a response deletes are reported too,
+   * with `removed` set. Requests made with `disableCookies` report nothing.
+   */
+  onSetCookie?: (cookie: StoredCookie, url: string) => void;
+
+  /**
+   * Attach a bearer token to every request made through this session and refresh it natively
+   * when a response is 401. Concurrent requests that fail with the same token share a single
+   * refresh, then each is sent once more with the new token.
+   */
+  auth?: SessionAuthOptions;
+
+  /**
+   * Default for {@link RequestInit.ntlm} on requests made through this session.
+   */
+  ntlm?: NtlmCredentials;
+
+  /**
+   * Default for {@link RequestInit.locale} on requests made through this session, for example
+   * to match the country of its proxy.
+   */
+  locale?: string | string[];
+
+  /**
+   * Client hints for every request made through this session. See
+   * {@link RequestInit.clientHints}.
+   */
+  clientHints?: ClientHintsOptions;
+
+  /**
+   * Honor `Accept-CH` like a browser does. Origins that send it over HTTPS get the
+   * high-entropy client hints it names, such as `Sec-CH-UA-Full-Version-List`, on the
+   * session's later requests, with values consistent with the browser profile.
+   * @default false
+   */
+  acceptClientHints?: boolean;
+
+  /**
+   * Keep a randomized gap between the starts of consecutive requests to the same host, the way
+   * a person clicking through a site would. Requests wait natively for their turn before their
+   * timeout starts; requests to other hosts are not held back.
+   */
+  pacing?: SessionPacingOptions;
+}
+
+/**
+ * Gaps between consecutive requests of a session to one host.
+ */
+export interface SessionPacingOptions {
+  /** Shortest gap in milliseconds. */
+  minDelayMs: number;
+  /**
+   * Longest gap in milliseconds.
+   * @default minDelayMs
+   */
+  maxDelayMs?: number;
+  /**
+   * How gaps are drawn between the two: `uniform` spreads them evenly, `normal` clusters them
+   * around the middle, and `exponential` makes short gaps common and long ones rare.
+   * @default "uniform"
+   */
+  jitter?: PacingJitter;
+}
+
+export type PacingJitter = "uniform" | "normal" | "exponential";
+
+/**
+ * Windows credentials for NTLM authentication. Only NTLMv2 responses are sent; Kerberos is
+ * not supported, so `Negotiate` servers must accept NTLM.
+ */
+export interface NtlmCredentials {
+  /** User name; a `DOMAIN\user` name carries the domain when `domain` is not set. */
+  username: string;
+  password: string;
+  domain?: string;
+  /** Name of this machine, which some servers log. */
+  workstation?: string;
+}
+
+/**
+ * Bearer token handling for a session. Give either `tokenEndpoint` or `refresh`.
+ * Requests that set their own `Authorization` header are sent as they are.
+ */
+export interface SessionAuthOptions {
+  /** Token to start with. Without one, the first request fetches a token. */
+  accessToken?: string;
+  /** Lifetime of `accessToken` in seconds. Tokens are refreshed shortly before they expire. */
+  expiresIn?: number;
+  /**
+   * Refresh token sent to `tokenEndpoint` with the `refresh_token` grant. When the endpoint
+   * returns a new refresh token, it is used for the next refresh.
+   */
+  refreshToken?: string;
+  /**
+   * OAuth2 token endpoint. It is sent the `refresh_token` grant when there is a refresh token,
+   * and the `client_credentials` grant otherwise. The request goes through the session's
+   * transport.
+   */
+  tokenEndpoint?: string;
+  clientId?: string;
+  clientSecret?: string;
+  scope?: string;
+  /** Get a new token in JS instead of from a token endpoint. A rejection fails the request. */
+  refresh?: () => Promise<string | AccessTokenGrant> | string | AccessTokenGrant;
+}
+
+/**
+ * A token returned by {@link SessionAuthOptions.refresh}.
+ */
+export interface AccessTokenGrant {
+  accessToken: string;
+  /** Lifetime in seconds. */
+  expiresIn?: number;
+}
+
+/**
+ * A cookie stored in a session's jar, as reported to {@link CreateSessionOptions.onSetCookie}.
+ */
+export interface StoredCookie {
+  name: string;
+  value: string;
+  /** The `Domain` attribute, or the host of the response when it has none. */
+  domain: string;
+  /** The `Path` attribute, or the default path of the response URL when it has none. */
+  path: string;
+  /** Expiry time in milliseconds since the Unix epoch, or `null` for a session cookie. */
+  expiresAt: number | null;
+  secure: boolean;
+  httpOnly: boolean;
+  sameSite: "Strict" | "Lax" | "None" | null;
+  /** Whether the response deleted the cookie (`Max-Age=0` or an `Expires` in the past). */
+  removed: boolean;
+}
+
+/**
+ * A cookie as exported by Puppeteer (`page.cookies()`), Playwright (`context.cookies()`) or the
+ * Chrome DevTools Protocol (`Network.getAllCookies`). Other fields those tools include are
+ * ignored.
+ */
+export interface BrowserCookie {
+  name: string;
+  value: string;
+  /** A leading dot is accepted and ignored. Either `domain` or `url` is required. */
+  domain?: string;
+  /** @default '/' */
+  path?: string;
+  /** Scopes the cookie to this URL's host and path, as in Playwright's `addCookies()`. */
+  url?: string;
+  /** Expiry in seconds since the Unix epoch; `-1` or omitted for a session cookie. */
+  expires?: number;
+  httpOnly?: boolean;
+  secure?: boolean;
+  sameSite?: "Strict" | "Lax" | "None";
+}
+
+/**
+ * A host remembered by a session's HSTS store.
+ */
+export interface HstsEntry {
+  host: string;
+  /** Expiry time in milliseconds since the Unix epoch. */
+  expiresAt: number;
+  includeSubdomains: boolean;
+}
+
+/**
+ * An alternative service (RFC 7838) advertised through an `Alt-Svc` response header.
+ * Cached `h2` alternatives route later `https` requests on the transport; `h3` ones are informational.
+ */
+export interface AltSvcEntry {
+  /** Origin that advertised the alternative, as `scheme://host:port`. */
+  origin: string;
+  /** ALPN protocol id, e.g. `h3` or `h2`. */
+  protocol: string;
+  host: string;
+  port: number;
+  /** Expiry time in milliseconds since the Unix epoch. */
+  expiresAt: number;
+  persist: boolean;
+}
+
+/**
+ * Details about the connection that served a response.
+ */
+export interface ConnectionInfo {
+  /**
+   * Whether the request went out on a pooled connection rather than a freshly established one.
+   * `null` when the socket addresses were not reported.
+   */
+  reused: boolean | null;
+  /**
+   * Native client that served the request: an explicit transport, the ephemeral pool, the
+   * per-origin pool (`originPooling`), or a one-off client.
+   */
+  client: "transport" | "ephemeral" | "pooled" | "dedicated";
+  /** Id of the transport that served the request, when `client` is `"transport"`. */
+  transportId: string | null;
+  /** Local socket address as `ip:port`, when known. */
+  localAddress: string | null;
+  /** Remote socket address as `ip:port`, when known. */
+  remoteAddress: string | null;
+  /**
+   * The DNS lookup the connection was opened from: every address the host name resolved to,
+   * one of which is {@link remoteAddress}. Reused connections report the lookup that opened them.
+   * `null` for IP literals, requests answered without a connection, or when unknown.
+   * Through a proxy, this is the lookup of the proxy's host name.
+   */
+  dns: DnsAnswer | null;
+}
+
+/**
+ * Addresses a host name resolved to, in the order they were tried.
+ */
+export interface DnsAnswer {
+  hostname: string;
+  addresses: string[];
+}
+
+/**
+ * Selects the requests a mock answers.
+ */
+export interface MockMatcher {
+  /** HTTP method to match (case-insensitive). Matches every method when omitted. */
+  method?: string;
+  /**
+   * URL to match, compared against the request URL as passed to `fetch`.
+   * `*` matches any run of characters, e.g. `https://api.example.com/users/*`.
+   */
+  urlPattern: string | URL;
+}
+
+/**
+ * Canned response served by a mock.
+ */
+export interface MockResponseInit {
+  /** @default 200 */
+  status?: number;
+  headers?: HeadersInit;
+  body?: string | Buffer | ArrayBuffer | ArrayBufferView;
+  /** Delay before the response is delivered, in milliseconds. */
+  delayMs?: number;
+}
+
+/**
+ * Options for `enableAuditLog`.
+ */
+export interface AuditLogOptions {
+  /**
+   * Also log request bodies and buffered response bodies. Streamed response bodies are never logged.
+   * @default false
+   */
+  includeBodies?: boolean;
+  /**
+   * Move the log to `<path>.1` and start a new file once it would grow past this many bytes.
+   */
+  rotateBytes?: number;
+}
+
+/**
+ * Request property compared when replaying from a cassette.
+ */
+export type CassetteMatchField = "method" | "url" | "body";
+
+/**
+ * Options for `useCassette`.
+ */
+export interface CassetteOptions {
+  /**
+   * `record` sends requests over the network and writes each request/response pair to the cassette.
+   * `replay` serves requests from an existing cassette and fails requests it has no recording for.
+   */
+  mode: "record" | "replay";
+  /**
+   * Request properties that must match a recorded interaction during replay.
+   * @default ["method", "url"]
+   */
+  matchOn?: CassetteMatchField[];
+}
+
+/**
+ * Configuration for {@link createTransport}.
+ */
+export interface CreateTransportOptions {
+  /**
+   * Proxy URL to route requests through (e.g., 'http://proxy.example.com:8080').
+   */
+  proxy?: string;
+
+  /**
+   * Rotate requests through several proxies instead of using `proxy`. Each proxy keeps its
+   * own connection pool.
+   */
+  proxyPool?: ProxyPoolOptions;
+
+  /**
+   * Windows credentials for a `proxy` that requires NTLM. Requests connect through a local
+   * proxy that answers the upstream proxy's NTLM challenge on each tunnel's connection. Only
+   * `http://` proxies are supported, and `proxyPool` cannot be combined with it.
+   */
+  proxyNtlm?: NtlmCredentials;
+
+  /**
+   * Client hints for every request made through this transport. A request's own
+   * `clientHints` fields take precedence.
+   */
+  clientHints?: ClientHintsOptions;
+
+  /**
+   * Browser profile to impersonate for this transport.
+   */
+  browser?: BrowserProfile | BrowserProfileAlias;
+
+  /**
+   * Operating system to emulate for this transport.
+   */
+  os?: EmulationOS;
+
+  /**
+   * Disable HTTPS certificate verification for this transport.
+   */
+  insecure?: boolean;
+
+  /**
+   * Idle timeout for pooled connections (ms).
+   */
+  poolIdleTimeout?: number;
+
+  /**
+   * Maximum number of idle connections per host.
+   */
+  poolMaxIdlePerHost?: number;
+
+  /**
+   * Maximum total connections in the pool.
+   */
+  poolMaxSize?: number;
+
+  /**
+   * Retire pooled connections once they are this old (ms), however busy or idle they are.
+   * Requests already in flight finish on the old connections; later ones open new ones.
+   */
+  maxConnectionAge?: number;
+
+  /**
+   * TCP connect timeout (ms).
+   */
+  connectTimeout?: number;
+
+  /**
+   * Read timeout (ms).
+   */
+  readTimeout?: number;
+
+  /**
+   * Send the TLS session ticket extension so servers can issue resumption tickets.
+   * Defaults to the browser profile's behavior.
+   */
+  tlsSessionTickets?: boolean;
+
+  /**
+   * Cache TLS sessions and resume them on later handshakes to the same host.
+   * Defaults to the browser profile's behavior. Use `transport.clearTlsSessionCache()`
+   * to forget cached sessions.
+   */
+  tlsSessionResumption?: boolean;
+
+  /**
+   * Append NSS key log lines (the `SSLKEYLOGFILE` format) for every TLS handshake
+   * made by this transport to this file, so captured traffic can be decrypted in Wireshark.
+   * The file and its parent directories are created if needed.
+   */
+  keylogPath?: string;
+
+  /**
+   * Cap the combined download rate of every response body read through this
+   * transport, in bytes per second.
+   */
+  maxDownloadBytesPerSec?: number;
+
+  /**
+   * Cap the combined upload rate of every request body sent through this
+   * transport, in bytes per second.
+   */
+  maxUploadBytesPerSec?: number;
+
+  /**
+   * Only allow requests whose URL matches one of these patterns. Enforced natively for the
+   * initial URL and every redirect target, as well as for streams and WebSockets on this transport.
+   *
+   * A pattern is a host (`example.com`), a subdomain wildcard (`*.example.com`, which does not
+   * match `example.com` itself), `*` for any host, or a URL prefix with a scheme
+   * (`https://api.example.com/v1`). Hosts may include a port, which must then match exactly.
+   */
+  allowlist?: string[];
+
+  /**
+   * Reject requests whose URL matches one of these patterns, even if they are in `allowlist`.
+   * Uses the same pattern syntax as `allowlist`.
+   */
+  denylist?: string[];
+
+  /**
+   * Content codings to advertise in `Accept-Encoding` for every request on this transport, in place
+   * of the browser profile's value. Only these codings are decoded. Requests can override it.
+   */
+  acceptEncoding?: ContentCoding[];
+
+  /**
+   * Largest response head to accept, in bytes (at least 8192). Sets the HTTP/1 read buffer and the
+   * HTTP/2 `SETTINGS_MAX_HEADER_LIST_SIZE`, which changes the HTTP/2 fingerprint. Defaults to the
+   * browser profile's settings.
+   */
+  maxResponseHeaderBytes?: number;
+
+  /**
+   * Most header fields an HTTP/1 response may carry. Defaults to 100.
+   */
+  maxHeaderCount?: number;
+
+  /**
+   * Accept malformed HTTP/1 response heads that browsers tolerate: obsolete folded header lines,
+   * whitespace between a header name and its colon, and header lines that cannot be parsed
+   * (which are dropped). Bare LF line endings are always accepted.
+   * @default false
+   */
+  lenientHeaders?: boolean;
+
+  /**
+   * HTTP/2 `SETTINGS_INITIAL_WINDOW_SIZE`: bytes each stream may receive before the server waits
+   * for a window update (up to 2^31 - 1). Larger windows keep high-latency downloads flowing.
+   * Changes the HTTP/2 fingerprint. Defaults to the browser profile's setting.
+   */
+  initialStreamWindowSize?: number;
+
+  /**
+   * Bytes the whole HTTP/2 connection may receive before a window update (up to 2^31 - 1).
+   * Changes the HTTP/2 fingerprint. Defaults to the browser profile's setting.
+   */
+  initialConnectionWindowSize?: number;
+
+  /**
+   * HTTP/2 `SETTINGS_MAX_FRAME_SIZE`: largest frame payload the server may send, between 16384
+   * and 16777215. Changes the HTTP/2 fingerprint. Defaults to the browser profile's setting.
+   */
+  maxFrameSize?: number;
+
+  /**
+   * Set `TCP_NODELAY` on this transport's sockets, disabling Nagle's algorithm.
+   * @default true
+   */
+  tcpNoDelay?: boolean;
+
+  /**
+   * `SO_KEEPALIVE` probing for dead-peer detection. `false` turns it off, `true` keeps the
+   * defaults (15 s idle, 15 s interval, 3 retries), and an object overrides individual settings.
+   */
+  tcpKeepAlive?: boolean | TcpKeepAliveOptions;
+
+  /**
+   * Size of each socket's send buffer (`SO_SNDBUF`), in bytes. Defaults to the OS setting.
+   */
+  sendBufferSize?: number;
+
+  /**
+   * Size of each socket's receive buffer (`SO_RCVBUF`), in bytes. Defaults to the OS setting.
+   */
+  recvBufferSize?: number;
+}
+
+/**
+ * TCP keepalive settings for {@link CreateTransportOptions.tcpKeepAlive}. Omitted fields keep
+ * their defaults.
+ */
+export interface TcpKeepAliveOptions {
+  /**
+   * Idle time before the first probe is sent, in milliseconds.
+   */
+  idle?: number;
+  /**
+   * Time between unanswered probes, in milliseconds.
+   */
+  interval?: number;
+  /**
+   * Unanswered probes before the connection is considered dead.
+   */
+  retries?: number;
+}
+
+/**
+ * One exit for {@link mirror}: a proxy URL or an existing transport. A target with neither
+ * sends the request directly.
+ */
+export interface MirrorTarget {
+  /**
+   * Name reported in the results. Defaults to the proxy URL without its password, or
+   * `target <index>`.
+   */
+  label?: string;
+  proxy?: string;
+  transport?: Transport;
+}
+
+/**
+ * Configuration for {@link mirror}: the request to send plus where to send it from.
+ */
+export interface MirrorInit
+  extends Omit<RequestInit, "proxy" | "transport" | "session" | "sessionId" | "cookieMode" | "bodyDigest"> {
+  /**
+   * Exits to send the request through, concurrently.
+   */
+  targets: MirrorTarget[];
+
+  /**
+   * Header names left out of the header comparison because they change on every response.
+   * @default ['date', 'age']
+   */
+  ignoreHeaders?: string[];
+}
+
+/**
+ * What one {@link MirrorTarget} received.
+ */
+export interface MirrorResponse {
+  label: string;
+  /**
+   * `null` when the request failed; see `error`.
+   */
+  status: number | null;
+  /**
+   * Response headers keyed by lower-cased name.
+   */
+  headers: Record<string, string>;
+  /**
+   * Hex SHA-256 of the decoded body.
+   */
+  bodyDigest: string | null;
+  bodySize: number | null;
+  /**
+   * Milliseconds from sending the request to reading the whole body.
+   */
+  duration: number;
+  error: RequestError | null;
+}
+
+/**
+ * Where the responses of a {@link mirror} call disagree.
+ */
+export interface MirrorDiff {
+  /**
+   * Whether the targets got different status codes (a failed request counts as its own status).
+   */
+  status: boolean;
+  /**
+   * Lower-cased names of headers whose values differ, or that only some responses have.
+   */
+  headers: string[];
+  /**
+   * Whether the body digests differ.
+   */
+  body: boolean;
+}
+
+export interface MirrorResult {
+  /**
+   * One entry per target, in the order of `targets`.
+   */
+  responses: MirrorResponse[];
+  diff: MirrorDiff;
+}
+
+/**
+ * Configuration for {@link fetchAll}: request options shared by every URL, plus how the set is
+ * scheduled and retried.
+ */
+export interface FetchAllInit extends Omit<RequestInit, "coalesce"> {
+  /**
+   * Requests in flight at once.
+   * @default 8
+   */
+  concurrency?: number;
+
+  /**
+   * Extra attempts for a request that timed out, failed to connect, or got a status in
+   * `retryOn`.
+   * @default 0
+   */
+  retries?: number;
+
+  /**
+   * Milliseconds before the first retry, doubled for each retry after it. A numeric
+   * `Retry-After` header takes precedence. Waits are capped at 30 seconds.
+   * @default 500
+   */
+  retryDelay?: number;
+
+  /**
+   * Response statuses that are retried.
+   * @default [408, 429, 502, 503, 504]
+   */
+  retryOn?: number[];
+
+  /**
+   * Called with each request's final result as soon as it has one, in completion order.
+   */
+  onResult: (result: FetchAllResult) => void;
+
+  /**
+   * Called before each retry with the attempt about to be made (2 for the first retry), why
+   * the previous attempt failed, and the wait before the retry. Return `false` to give up on
+   * the request; its last failure or response is then reported to `onResult`.
+   */
+  onRetry?: (attempt: number, error: RequestError, delayMs: number, target: FetchAllRetryTarget) => boolean | void;
+}
+
+/**
+ * The request a {@link FetchAllInit.onRetry} call is about.
+ */
+export interface FetchAllRetryTarget {
+  /**
+   * Position of the URL in the list passed to `fetchAll()`.
+   */
+  index: number;
+  url: string;
+  /**
+   * The status that prompted the retry, or `null` when the previous attempt failed without a
+   * response.
+   */
+  status: number | null;
+}
+
+/**
+ * The final result of one URL passed to {@link fetchAll}.
+ */
+export interface FetchAllResult {
+  /**
+   * Position of the URL in the list passed to `fetchAll()`.
+   */
+  index: number;
+  url: string;
+  /**
+   * Attempts made, including the first one.
+   */
+  attempts: number;
+  /**
+   * `null` when the request failed; see `error`.
+   */
+  response: Response | null;
+  error: RequestError | null;
+}
+
+export interface FetchAllSummary {
+  /**
+   * Requests that got a response, whatever its status.
+   */
+  succeeded: number;
+  failed: number;
+}
+
+/**
+ * When a proxy pool moves on to its next proxy:
+ * - `stickyUntilError`: after a request through the current proxy fails at the network level
+ * - `rotateEveryNRequests`: after every `requestsPerProxy` requests
+ */
+export type ProxyRotationStrategy = "stickyUntilError" | "rotateEveryNRequests";
+
+/**
+ * A list of proxies rotated natively, for {@link CreateTransportOptions.proxyPool} and
+ * {@link CreateSessionOptions.proxyPool}.
+ */
+export interface ProxyPoolOptions {
+  /**
+   * Proxy URLs, used in order and wrapping around.
+   */
+  proxies: string[];
+  /**
+   * @default 'stickyUntilError'
+   */
+  strategy?: ProxyRotationStrategy;
+  /**
+   * Requests sent through each proxy before rotating. Only used by `rotateEveryNRequests`.
+   * @default 1
+   */
+  requestsPerProxy?: number;
+}
+
+/**
+ * Configuration for {@link tlsConnect}.
+ */
+export interface TlsConnectOptions {
+  /**
+   * Hostname or IP address to connect to. Also sent as SNI and used for certificate verification.
+   */
+  host: string;
+
+  /**
+   * TCP port to connect to.
+   */
+  port: number;
+
+  /**
+   * Browser profile whose TLS ClientHello is reproduced.
+   * @default 'chrome_142'
+   */
+  browser?: BrowserProfile | BrowserProfileAlias;
+
+  /**
+   * Operating system to emulate.
+   * @default 'macos'
+   */
+  os?: EmulationOS;
+
+  /**
+   * Proxy URL to tunnel through. Supports `http://` (CONNECT), `socks5://`, and `socks5h://`.
+   */
+  proxy?: string;
+
+  /**
+   * Disable certificate and hostname verification.
+   * @default false
+   */
+  insecure?: boolean;
+
+  /**
+   * Time limit (ms) for connecting and completing the TLS handshake. `0` disables it.
+   * @default 30000
+   */
+  timeout?: number;
+}
+
+/**
+ * Configuration for {@link probe}.
+ */
+export interface ProbeOptions {
+  /**
+   * URL whose origin is probed. Only the scheme, host, and port are used; `http:` skips TLS.
+   */
+  url: string | URL;
+
+  /**
+   * Browser profile whose TLS ClientHello is used for the handshake.
+   * @default 'chrome_142'
+   */
+  browser?: BrowserProfile | BrowserProfileAlias;
+
+  /**
+   * Operating system to emulate.
+   * @default 'macos'
+   */
+  os?: EmulationOS;
+
+  /**
+   * Proxy URL to tunnel through. Supports `http://` (CONNECT), `socks5://`, and `socks5h://`.
+   */
+  proxy?: string;
+
+  /**
+   * Disable certificate and hostname verification.
+   * @default false
+   */
+  insecure?: boolean;
+
+  /**
+   * When the server negotiates `h2`, also exchange the HTTP/2 connection preface and report its
+   * `SETTINGS`.
+   * @default false
+   */
+  http2?: boolean;
+
+  /**
+   * Time limit (ms) for the whole probe. `0` disables it.
+   * @default 30000
+   */
+  timeout?: number;
+}
+
+/**
+ * Milliseconds spent in each phase of a {@link probe}. Phases that did not run are `null`.
+ */
+export interface ProbeTimings {
+  dns: number;
+  connect: number;
+  /** Establishing the proxy tunnel. */
+  proxy: number | null;
+  tls: number | null;
+  /** Sending the HTTP/2 preface and receiving the server's `SETTINGS`. */
+  h2: number | null;
+  total: number;
+}
+
+/**
+ * Outcome of a {@link probe}: per-phase timings and what was negotiated with the server.
+ */
+export interface ProbeResult {
+  timings: ProbeTimings;
+  localAddress: string | null;
+  /** Address of the first hop: the origin, or the proxy when one is used. */
+  remoteAddress: string | null;
+  alpnProtocol: string | null;
+  tlsVersion: string | null;
+  cipher: string | null;
+  /** Server `SETTINGS` keyed by numeric identifier, when the HTTP/2 preface was exchanged. */
+  h2Settings: Record<string, number> | null;
+}
+
+/**
+ * Configuration for {@link Transport.openStream}.
+ */
+export interface H2StreamInit {
+  /**
+   * Target URL. Supplies `:scheme`, `:authority`, and `:path` unless they are set explicitly.
+   */
+  url: string | URL;
+
+  /**
+   * Value of the `:method` pseudo-header.
+   * @default 'POST'
+   */
+  method?: string;
+
+  /**
+   * Override the `:scheme` pseudo-header.
+   */
+  scheme?: string;
+
+  /**
+   * Override the `:authority` pseudo-header.
    */
-  headers?: HeadersInit;
+  authority?: string;
 
   /**
-   * A BodyInit object or null to set request's body.
+   * Override the `:path` pseudo-header. Must start with `/`.
    */
-  body?: BodyInit | null;
+  path?: string;
 
   /**
-   * An AbortSignal to set request's signal.
+   * Regular request headers, sent after the pseudo-headers. They are the only headers sent; the
+   * browser profile's default headers are left out.
    */
-  signal?: AbortSignal | null;
+  headers?: HeadersInit;
 
   /**
-   * A string indicating whether request follows redirects, results in an error upon
-   * encountering a redirect, or returns the redirect (in an opaque fashion).
-   * @default 'follow'
+   * Time limit (ms) for receiving the response headers. `0` disables it.
+   * @default 30000
    */
-  redirect?: "follow" | "manual" | "error";
+  timeout?: number;
+}
+
+/**
+ * A frame received on an HTTP/2 stream: response headers first, then data, then trailers
+ * when the server sends them.
+ */
+export type H2StreamFrame =
+  | { type: "headers"; status: number; headers: Headers }
+  | { type: "data"; data: Buffer }
+  | { type: "trailers"; headers: Headers };
 
+/**
+ * Configuration for {@link shutdown}.
+ */
+export interface ShutdownOptions {
   /**
-   * Transport instance to use for this request. When provided, transport-level
-   * options such as `browser`, `os`, `proxy`, and `insecure` must not be set.
+   * How long to wait (ms) for in-flight requests, body streams, and WebSockets to finish
+   * before they are cancelled. `0` cancels everything immediately.
+   * @default 5000
    */
-  transport?: Transport;
+  gracePeriodMs?: number;
+}
 
+/**
+ * Process-wide request defaults for {@link setDefaults}. Each applies to requests that leave the
+ * option unset; requests through a session or transport keep that session's or transport's own
+ * browser, OS, proxy, and connection settings.
+ */
+export interface RequestDefaults {
   /**
-   * Browser profile to impersonate for this request.
-   * Applies browser profile behavior handled by the native layer.
-   * Ignored when `transport` is provided.
+   * Browser profile to impersonate.
    * @default 'chrome_142'
    */
-  browser?: BrowserProfile;
-
+  browser?: BrowserProfile | BrowserProfileAlias;
   /**
-   * Operating system to emulate for this request.
-   * Influences platform-specific behavior handled by the native layer.
-   * Ignored when `transport` is provided.
+   * Operating system to emulate.
    * @default 'macos'
    */
   os?: EmulationOS;
-
-  /**
-   * Proxy URL to route the request through (e.g., 'http://proxy.example.com:8080').
-   * Proxy support depends on the native layer and proxy scheme.
-   * Ignored when `transport` is provided.
-   */
-  proxy?: string;
-
   /**
-   * Request timeout in milliseconds. If the request takes longer than this value,
-   * it will be aborted.
+   * Request timeout in milliseconds. `0` disables it.
    * @default 30000
    */
   timeout?: number;
-
   /**
-   * Controls how cookies are managed for this call.
-   * - "ephemeral": default when no session/sessionId is provided. Creates an isolated session per request.
-   * - "session": requires an explicit session or sessionId and reuses its cookie jar.
+   * Time allowed to establish a connection, in milliseconds.
    */
-  cookieMode?: CookieMode;
-
+  connectTimeout?: number;
   /**
-   * Session instance to bind this request to. When provided, {@link cookieMode}
-   * automatically behaves like `"session"`.
+   * Time allowed between reads from the socket, in milliseconds.
    */
-  session?: Session;
-
+  readTimeout?: number;
   /**
-   * Identifier of an existing session created elsewhere (e.g., via {@link createSession}).
+   * Proxy URL for requests that do not set `proxy`.
    */
-  sessionId?: string;
-
+  proxy?: string;
   /**
-   * Disable default headers from browser emulation. When enabled, only explicitly
-   * provided headers will be sent with the request, preventing emulation headers
-   * from being automatically added or appended.
-   * @default false
+   * Redirect handling.
+   * @default 'follow'
    */
-  disableDefaultHeaders?: boolean;
-
+  redirect?: "follow" | "manual" | "error";
   /**
-   * Disable HTTPS certificate verification. When enabled, self-signed and invalid
-   * certificates will be accepted.
-   * Ignored when `transport` is provided.
-   *
-   * # Warning
-   *
-   * You should think very carefully before using this method. If invalid
-   * certificates are trusted, *any* certificate for *any* site will be
-   * trusted for use. This includes expired certificates. This introduces
-   * significant vulnerabilities, and should only be used as a last resort.
-   *
+   * Skip TLS certificate verification.
    * @default false
    */
   insecure?: boolean;
+  /**
+   * Share clients per origin for session requests without a transport.
+   * @default false
+   */
+  originPooling?: boolean;
+  /**
+   * Largest response body, in bytes, returned in one piece with the response instead of being
+   * streamed. Bodies of unknown length are always streamed.
+   * @default 2097152
+   */
+  inlineBodyMax?: number;
 }
 
 /**
- * Configuration for {@link createSession}.
+ * Sizes of the native registries, returned by {@link getNativeStats}.
  */
-export interface CreateSessionOptions {
+export interface NativeStats {
   /**
-   * Provide a custom identifier instead of an auto-generated random ID.
+   * Streamed response bodies that have not been read to the end or cancelled.
    */
-  sessionId?: string;
-
+  bodyStreams: number;
   /**
-   * Default headers applied to every request made through this session.
+   * Requests currently being sent or waiting for response headers.
    */
-  defaultHeaders?: HeadersInit;
-
+  inFlightRequests: number;
   /**
-   * Browser profile to bind to this session. Defaults to 'chrome_142'.
+   * Requests registered for cancellation through `signal`, `groupId`, or `cancellable`.
    */
-  browser?: BrowserProfile;
-
+  pendingCancellations: number;
   /**
-   * Operating system to bind to this session. Defaults to 'macos'.
+   * Open WebSocket connections.
    */
-  os?: EmulationOS;
+  webSockets: number;
   /**
-   * Optional proxy for every request made through the session.
+   * Sessions created with `createSession()` and not yet closed.
    */
-  proxy?: string;
+  sessions: number;
   /**
-   * Default timeout applied when {@link Session.fetch} is called without
-   * overriding `timeout`.
+   * Transports created with `createTransport()` and not yet closed.
    */
-  timeout?: number;
-
+  transports: number;
   /**
-   * Disable HTTPS certificate verification. When enabled, self-signed and invalid
-   * certificates will be accepted for all requests made through this session.
-   *
-   * # Warning
-   *
-   * You should think very carefully before using this method. If invalid
-   * certificates are trusted, *any* certificate for *any* site will be
-   * trusted for use. This includes expired certificates. This introduces
-   * significant vulnerabilities, and should only be used as a last resort.
-   *
-   * @default false
+   * Cached clients used by requests that run without a session or transport.
    */
-  insecure?: boolean;
+  ephemeralClients: number;
+  /**
+   * Per-origin clients cached for requests made with `originPooling`.
+   */
+  pooledClients: number;
+  /**
+   * Approximate bytes held in native body buffers, including buffers still referenced from JS.
+   */
+  bufferedBytes: number;
 }
 
 /**
- * Configuration for {@link createTransport}.
+ * Memory the native side holds on behalf of JS, returned by {@link getMemoryStats}. Byte counts
+ * cover payloads only, not allocator or bookkeeping overhead.
  */
-export interface CreateTransportOptions {
+export interface MemoryStats {
   /**
-   * Proxy URL to route requests through (e.g., 'http://proxy.example.com:8080').
+   * Bytes of inline response bodies lent to JS as external Buffers and not yet garbage collected.
+   * Zero where body bytes are always copied into JS memory, as under Electron.
    */
-  proxy?: string;
-
+  inlineBodyBytes: number;
   /**
-   * Browser profile to impersonate for this transport.
+   * Inline response bodies counted in `inlineBodyBytes`.
    */
-  browser?: BrowserProfile;
-
+  inlineBodies: number;
   /**
-   * Operating system to emulate for this transport.
+   * Bytes of body stream chunks read from the network and waiting for the JS thread to take
+   * them. Grows when the JS thread is too busy to keep up with its downloads.
    */
-  os?: EmulationOS;
-
+  queuedBodyBytes: number;
   /**
-   * Disable HTTPS certificate verification for this transport.
+   * Body stream chunks counted in `queuedBodyBytes`.
    */
-  insecure?: boolean;
-
+  queuedBodyChunks: number;
   /**
-   * Idle timeout for pooled connections (ms).
+   * Bytes held by the pooled buffers small body chunks are batched into, idle or lent to JS.
    */
-  poolIdleTimeout?: number;
-
+  bodyBufferBytes: number;
   /**
-   * Maximum number of idle connections per host.
+   * Bytes of WebSocket messages received and not yet delivered to a handler.
    */
-  poolMaxIdlePerHost?: number;
-
+  queuedWebSocketBytes: number;
   /**
-   * Maximum total connections in the pool.
+   * WebSocket messages counted in `queuedWebSocketBytes`.
    */
-  poolMaxSize?: number;
+  queuedWebSocketMessages: number;
+  /**
+   * Sessions in the native session cache, including ones created implicitly by `sessionId`.
+   */
+  sessions: number;
+  /**
+   * Cached clients used by requests that run without a session or transport.
+   */
+  ephemeralClients: number;
+  /**
+   * Per-origin clients cached for requests made with `originPooling`.
+   */
+  pooledClients: number;
+}
 
+/**
+ * Configuration for {@link configureBodyStreams}. Omitted fields keep their current value.
+ */
+export interface BodyStreamOptions {
   /**
-   * TCP connect timeout (ms).
+   * Reap a response body that has not been read or cancelled for this many milliseconds.
+   * Each read starts the clock again, and a body with a read waiting on it is never reaped.
+   * @default 300000
    */
-  connectTimeout?: number;
+  ttl?: number;
+  /**
+   * Maximum number of unread response bodies. When a new response goes over it, the oldest are
+   * reaped, skipping bodies with a read waiting on them. `null` removes the limit.
+   * @default null
+   */
+  maxStreams?: number | null;
+  /**
+   * Called for every body that was reaped instead of being read or cancelled. `null` removes it.
+   */
+  onLeak?: ((leak: BodyStreamLeak) => void) | null;
+  /**
+   * How streamed bodies reach `response.body`. `pull` asks the native side for one chunk per
+   * read. `push` grants it credit in bytes as the stream's queue drains, and it sends chunks
+   * until the credit runs out, so a busy stream crosses into native code far less often.
+   * Applies to bodies streamed after the change.
+   * @default "pull"
+   */
+  delivery?: BodyDelivery;
+  /**
+   * Bytes a `push` stream buffers ahead of its reader, which is also the credit it grants.
+   * @default 262144
+   */
+  pushCredit?: number;
+}
 
+export type BodyDelivery = "pull" | "push";
+
+/**
+ * A response body reaped by the native layer because nothing read or cancelled it.
+ */
+export interface BodyStreamLeak {
   /**
-   * Read timeout (ms).
+   * Native body handle of the abandoned response.
    */
-  readTimeout?: number;
+  handle: number;
+  /**
+   * `ttl` when the body sat unread for too long, `maxStreams` when it was among the oldest
+   * over the limit.
+   */
+  reason: "ttl" | "maxStreams";
+  /**
+   * Cancellation group the request belonged to, if any.
+   */
+  groupId: string | null;
+  /**
+   * Milliseconds between the response headers arriving and the body being reaped.
+   */
+  ageMs: number;
 }
 
 /**
@@ -379,7 +1912,7 @@ export interface RequestOptions {
    * Applies browser profile behavior handled by the native layer.
    * @default 'chrome_142'
    */
-  browser?: BrowserProfile;
+  browser?: BrowserProfile | BrowserProfileAlias;
 
   /**
    * Operating system to emulate.
@@ -406,7 +1939,7 @@ export interface RequestOptions {
 
   /**
    * Transport instance to use for this request. When provided, transport-level
-   * options such as `browser`, `os`, `proxy`, and `insecure` must not be set.
+   * options such as `browser`, `os`, and `insecure` must not be set.
    */
   transport?: Transport;
 
@@ -465,6 +1998,13 @@ export interface RequestOptions {
    */
   disableDefaultHeaders?: boolean;
 
+  /**
+   * Browser emulation headers to leave off this request, matched case-insensitively. The rest
+   * of the profile's headers are still sent. Has no effect with `disableDefaultHeaders`.
+   * @example ["accept-language", "sec-ch-ua"]
+   */
+  removeHeaders?: string[];
+
   /**
    * Disable HTTPS certificate verification. When enabled, self-signed and invalid
    * certificates will be accepted.
@@ -488,17 +2028,50 @@ export interface RequestOptions {
  *
  * @internal
  */
+/**
+ * Body bytes as received (`wire`) and after content decoding (`decoded`).
+ */
+export interface NativeBodySize {
+  wire: number;
+  decoded: number;
+}
+
 export interface NativeResponse {
   /**
    * HTTP status code (e.g., 200, 404, 500).
    */
   status: number;
 
+  /**
+   * Reason phrase the server sent instead of the canonical one for `status`, read from HTTP/1
+   * 4xx and 5xx responses.
+   */
+  reason?: string;
+
+  /**
+   * ID the request ran under: the one the caller reserved with `allocateRequestId()`, or one the
+   * native layer picked.
+   */
+  requestId?: number;
+
   /**
    * Response headers as [name, value] tuples.
    * Header names are normalized to lowercase.
+   * Absent when the native layer returned `headerFields` instead.
+   */
+  headers?: HeaderTuple[];
+
+  /**
+   * Headers and cookies packed into one JSON string, `[[name, value, ...], [name, value, ...]]`,
+   * returned in place of `headers` and `cookies` when compact headers were requested.
+   */
+  headerFields?: string;
+
+  /**
+   * Headers as lower-cased names with comma-joined values, returned in place of `headers` when
+   * the request set `headerFormat: "object"`.
    */
-  headers: HeaderTuple[];
+  headerObject?: Record<string, string>;
 
   /**
    * Handle for streaming response body chunks from the native layer.
@@ -506,6 +2079,12 @@ export interface NativeResponse {
    */
   bodyHandle: number | null;
 
+  /**
+   * Native box that drops the body stream when it is garbage collected, set with `bodyHandle`.
+   * @internal
+   */
+  bodyGuard?: unknown;
+
   /**
    * Inline body buffer returned for small payloads. When present, `bodyHandle`
    * will be `null` to avoid a second native round-trip to read the body.
@@ -517,10 +2096,45 @@ export interface NativeResponse {
    */
   contentLength: number | null;
 
+  /**
+   * Connection reuse and socket details for the response.
+   */
+  connection: ConnectionInfo;
+
+  /**
+   * Hex digest of `bodyBytes` when a body digest was requested. Streamed bodies report
+   * their digest once the stream has been read to the end. This and the other optional
+   * metadata below are left out of `minimalResponse` responses.
+   */
+  bodyDigest?: string | null;
+
+  /**
+   * Trailer fields sent after `bodyBytes`, as [name, value] tuples. Streamed bodies report
+   * their trailers once the stream has been read to the end.
+   */
+  trailers?: HeaderTuple[] | null;
+
+  /**
+   * Size of the response header block as HTTP/1.1 `name: value` lines.
+   */
+  headerBytes: number;
+
+  /**
+   * Size of `bodyBytes` as received and after decoding. Streamed bodies report their size once
+   * the stream has been read to the end.
+   */
+  bodySize?: NativeBodySize | null;
+
+  /**
+   * Native timing for traced requests that went over the network; otherwise `null`.
+   */
+  timing?: RequestTiming | null;
+
   /**
    * Cookies set by the server as [name, value] tuples.
+   * Absent when the native layer returned `headerFields` instead.
    */
-  cookies: HeaderTuple[];
+  cookies?: HeaderTuple[];
 
   /**
    * Final URL after following any redirects.
@@ -551,7 +2165,7 @@ export interface WebSocketOptions {
    * Automatically applies browser-specific headers and TLS fingerprints.
    * @default 'chrome_142'
    */
-  browser?: BrowserProfile;
+  browser?: BrowserProfile | BrowserProfileAlias;
 
   /**
    * Operating system to emulate for the WebSocket handshake.
@@ -585,6 +2199,62 @@ export interface WebSocketOptions {
    * - "blob": delivers Blob instances
    */
   binaryType?: WebSocketBinaryType;
+
+  /**
+   * Deliver text frames like binary frames, in the form chosen by `binaryType`, instead of as
+   * strings. Skips decoding every frame into a JavaScript string, which helps JSON feeds parsed
+   * straight from bytes. Text and binary frames can no longer be told apart.
+   * @default false
+   */
+  binaryText?: boolean;
+
+  /**
+   * Deliver received messages to JavaScript in batches instead of one native callback per frame.
+   * Each message still dispatches its own `message` event, in order. Useful when a feed sends
+   * many small messages. Pass `true` for the defaults.
+   */
+  batch?: boolean | WebSocketBatchOptions;
+
+  /**
+   * Deliver messages larger than a threshold as a `ReadableStream` of chunks in `event.data`, so a
+   * huge message never needs one contiguous buffer in JavaScript. Legacy `onMessage` callbacks do
+   * not receive streamed messages.
+   */
+  streamMessages?: WebSocketStreamOptions;
+}
+
+/**
+ * When received WebSocket messages are streamed instead of delivered whole.
+ */
+export interface WebSocketStreamOptions {
+  /**
+   * Messages of more than this many bytes are streamed.
+   */
+  threshold: number;
+
+  /**
+   * Largest chunk a streamed message is split into, in bytes.
+   * @default 1048576
+   */
+  chunkSize?: number;
+}
+
+/**
+ * How received WebSocket messages are grouped before crossing into JavaScript.
+ */
+export interface WebSocketBatchOptions {
+  /**
+   * Most messages delivered in one batch.
+   * @default 64
+   */
+  maxMessages?: number;
+
+  /**
+   * How long the first message of a batch waits for more to arrive. With `0`, a batch holds
+   * only the messages that were already received, so no message is delayed.
+   * @default 0
+   */
+  flushIntervalMs?: number;
 }
 
 export interface LegacyWebSocketOptions extends WebSocketOptions {
@@ -653,26 +2323,84 @@ export interface NativeWebSocketConnection {
    * @internal
    */
   extensions?: string;
+
+  /**
+   * Native state and activity of the connection, readable after it closed.
+   * @internal
+   */
+  _activity?: unknown;
+
+  /**
+   * Native box that closes the connection when it is garbage collected while still open.
+   * @internal
+   */
+  _guard?: unknown;
+}
+
+/**
+ * Part of a request that was running when it failed.
+ *
+ * - `dns`: resolving the host name.
+ * - `connect`: opening the connection. Also covers proxy tunnels and the TLS handshake when a
+ *   failure (e.g. a timeout) cannot be pinned on either.
+ * - `tls`: the TLS handshake, e.g. a certificate error.
+ * - `request-write`: sending the request body.
+ * - `ttfb`: waiting for the response headers once the request was sent.
+ * - `body-read`: reading the response body.
+ */
+export type RequestPhase = "dns" | "connect" | "tls" | "request-write" | "ttfb" | "body-read";
+
+export interface RequestErrorOptions {
+  cause?: unknown;
+  code?: string;
+  phase?: RequestPhase;
+  phaseElapsed?: number;
 }
 
 /**
  * Error thrown when a request fails. This can occur due to network errors,
  * timeouts, invalid URLs, or other request-related issues.
  *
+ * When the failure originates in the native layer, `cause` links to an `Error`
+ * for each underlying context (outermost first), e.g. the failing request line,
+ * then the connection error, then the OS-level error. Network failures also carry the
+ * {@link RequestPhase} they happened in and how long that phase had been running. Responses
+ * whose headers go over the transport's limits fail with `code` set to
+ * `"ERR_RESPONSE_HEADERS_TOO_LARGE"`.
+ *
  * @example
  * ```typescript
  * try {
  *   const response = await fetch('https://api.example.com');
  * } catch (error) {
  *   if (error instanceof RequestError) {
- *     console.error('Request failed:', error.message);
+ *     console.error('Request failed:', error.message, error.phase);
  *   }
  * }
  * ```
  */
 export class RequestError extends TypeError {
-  constructor(message: string) {
-    super(message);
+  /**
+   * Machine-readable reason for failures that have one: `"ERR_ABORTED"` when the request was
+   * cancelled, `"ERR_TIMEOUT"` when one of its timeouts fired, or `"ERR_RESPONSE_HEADERS_TOO_LARGE"`.
+   */
+  readonly code?: string;
+  /** Phase the request failed in, when the failure came from sending it or reading its body. */
+  readonly phase?: RequestPhase;
+  /** Milliseconds the request had spent in {@link phase} when it failed. */
+  readonly phaseElapsed?: number;
+
+  constructor(message: string, options?: RequestErrorOptions) {
+    super(message, options);
     this.name = "RequestError";
+    if (options?.code !== undefined) {
+      this.code = options.code;
+    }
+    if (options?.phase !== undefined) {
+      this.phase = options.phase;
+    }
+    if (options?.phaseElapsed !== undefined) {
+      this.phaseElapsed = options.phaseElapsed;
+    }
   }
 }
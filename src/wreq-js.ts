@@ -1,29 +1,92 @@
 import { randomUUID } from "node:crypto";
+import { writeFile } from "node:fs/promises";
 import { STATUS_CODES } from "node:http";
 import { createRequire } from "node:module";
-import { ReadableStream } from "node:stream/web";
+import { Duplex } from "node:stream";
+import { ReadableStream, type ReadableStreamDefaultController } from "node:stream/web";
 import type {
+  AccessTokenGrant,
+  AltSvcEntry,
+  AuditLogOptions,
+  BeforeSendRequest,
+  BodyDelivery,
+  BodyDigestAlgorithm,
   BodyInit,
+  BodyStreamLeak,
+  BodyStreamOptions,
+  BrowserCookie,
+  BrowserFamily,
   BrowserProfile,
+  BrowserProfileAlias,
+  CassetteMatchField,
+  CassetteOptions,
+  ClientHintsOptions,
+  ConnectionInfo,
+  ConnectToRule,
+  ContentCoding,
   CookieMode,
   CreateSessionOptions,
   CreateTransportOptions,
   EmulationOS,
+  FetchAllInit,
+  FetchAllResult,
+  FetchAllRetryTarget,
+  FetchAllSummary,
+  H2StreamFrame,
+  H2StreamInit,
+  HeaderInitTuple,
   HeadersInit,
   HeaderTuple,
+  HeaderTupleOptions,
+  HstsEntry,
   LegacySessionWebSocketOptions,
   LegacyWebSocketOptions,
+  MemoryStats,
+  MirrorInit,
+  MirrorResponse,
+  MirrorResult,
+  MirrorTarget,
+  MockMatcher,
+  MockResponseInit,
+  NativeBodySize,
   NativeResponse,
+  NativeStats,
   NativeWebSocketConnection,
+  NtlmCredentials,
+  PacingJitter,
+  ProbeOptions,
+  ProbeResult,
+  ProxyPoolOptions,
+  ProxyRotationStrategy,
+  RegistryMode,
+  RequestCallback,
+  RequestCallbackOptions,
+  RequestDefaults,
+  RequestMode,
   RequestOptions,
+  RequestPriority,
+  RequestTiming,
+  ResponseSizes,
+  SessionAuthOptions,
   SessionHandle,
+  SessionPacingOptions,
   SessionWebSocketOptions,
+  ShutdownOptions,
+  StoredCookie,
+  TcpKeepAliveOptions,
+  TlsConnectOptions,
+  TraceContext,
+  TraceProvider,
+  TraceSpanEnd,
+  WebSocketBatchOptions,
   WebSocketBinaryType,
   WebSocketCloseEvent,
   WebSocketErrorEvent,
   WebSocketMessageEvent,
   WebSocketOpenEvent,
   WebSocketOptions,
+  WebSocketState,
+  WebSocketStreamOptions,
   RequestInit as WreqRequestInit,
 } from "./types.js";
 import { RequestError } from "./types.js";
@@ -40,79 +103,293 @@ interface NativeWebSocketCloseOptions {
 
 interface NativeWebSocketOptions {
   url: string;
-  browser: BrowserProfile;
+  browser: BrowserProfile | BrowserProfileAlias;
   os: EmulationOS;
-  headers: HeaderTuple[];
+  headers: HeaderInitTuple[];
   protocols?: string[];
   proxy?: string;
   onMessage: (data: string | Buffer) => void;
   onClose?: (event: NativeWebSocketCloseEvent) => void;
   onError?: (error: string) => void;
+  binaryText?: boolean;
+  batch?: Required<WebSocketBatchOptions>;
+  onMessages?: (messages: Array<string | Buffer>) => void;
+  streamMessages?: Required<WebSocketStreamOptions>;
+  onMessageStart?: (text: boolean, size: number) => void;
+  onMessageChunk?: (chunk: Buffer) => void;
+  onMessageEnd?: () => void;
 }
 
 interface NativeWebSocketSessionOptions {
   url: string;
   sessionId: string;
   transportId: string;
-  headers: HeaderTuple[];
+  headers: HeaderInitTuple[];
   protocols?: string[];
   onMessage: (data: string | Buffer) => void;
   onClose?: (event: NativeWebSocketCloseEvent) => void;
   onError?: (error: string) => void;
+  binaryText?: boolean;
+  batch?: Required<WebSocketBatchOptions>;
+  onMessages?: (messages: Array<string | Buffer>) => void;
+  streamMessages?: Required<WebSocketStreamOptions>;
+  onMessageStart?: (text: boolean, size: number) => void;
+  onMessageChunk?: (chunk: Buffer) => void;
+  onMessageEnd?: () => void;
 }
 
 interface NativeSessionOptions {
   sessionId: string;
+  hsts?: boolean;
+  acceptClientHints?: boolean;
+  onSetCookie?: (cookie: StoredCookie, url: string) => void;
+  auth?: NativeSessionAuth;
+  pacing?: Required<SessionPacingOptions>;
+}
+
+interface NativeSessionAuth {
+  accessToken?: string;
+  expiresIn?: number;
+  refreshToken?: string;
+  tokenEndpoint?: string;
+  clientId?: string;
+  clientSecret?: string;
+  scope?: string;
+  // Called with a refresh id; the answer goes back through `resolveAuthRefresh`.
+  onRefresh?: (refreshId: number) => void;
 }
 
 interface NativeTransportOptions {
-  browser: BrowserProfile;
+  browser: BrowserProfile | BrowserProfileAlias;
   os: EmulationOS;
   proxy?: string;
+  proxyPool?: ProxyPoolOptions;
+  proxyNtlm?: NtlmCredentials;
+  clientHints?: ClientHintsOptions;
   insecure?: boolean;
   poolIdleTimeout?: number;
   poolMaxIdlePerHost?: number;
   poolMaxSize?: number;
+  maxConnectionAge?: number;
   connectTimeout?: number;
   readTimeout?: number;
+  tlsSessionTickets?: boolean;
+  tlsSessionResumption?: boolean;
+  keylogPath?: string;
+  maxDownloadBytesPerSec?: number;
+  maxUploadBytesPerSec?: number;
+  allowlist?: string[];
+  denylist?: string[];
+  acceptEncoding?: ContentCoding[];
+  maxResponseHeaderBytes?: number;
+  maxHeaderCount?: number;
+  lenientHeaders?: boolean;
+  initialStreamWindowSize?: number;
+  initialConnectionWindowSize?: number;
+  maxFrameSize?: number;
+  tcpNoDelay?: boolean;
+  tcpKeepAlive?: boolean | TcpKeepAliveOptions;
+  sendBufferSize?: number;
+  recvBufferSize?: number;
 }
 
 interface NativeRequestOptions {
   url: string;
   method: string;
-  browser?: BrowserProfile;
+  browser?: BrowserProfile | BrowserProfileAlias;
   os?: EmulationOS;
-  headers?: HeaderTuple[];
+  headers?: HeaderInitTuple[];
+  appendHeaders?: string[];
+  removeHeaders?: string[];
   body?: Buffer;
-  proxy?: string;
+  proxy?: string | null;
   timeout?: number;
   redirect?: "follow" | "manual" | "error";
   sessionId: string;
   ephemeral: boolean;
   disableDefaultHeaders?: boolean;
+  disableCookies?: boolean;
+  acceptEncoding?: ContentCoding[];
+  decodeBody?: boolean;
+  priority?: RequestPriority;
+  mode?: RequestMode;
+  locale?: string[];
+  originPooling?: boolean;
   insecure?: boolean;
   transportId?: string;
+  groupId?: string;
+  maxDownloadBytesPerSec?: number;
+  maxUploadBytesPerSec?: number;
+  bodyDigest?: BodyDigestAlgorithm;
+  trace?: TraceContext;
+  writeTimeout?: number;
+  totalDeadline?: number;
+  blockInsecureRedirects?: boolean;
+  stripOnCrossOrigin?: string[];
+  metaRefreshMaxDelay?: number;
+  ntlm?: NtlmCredentials;
+  clientHints?: ClientHintsOptions;
+  strictProfiles?: boolean;
+  // Return headers and cookies as one `headerFields` JSON string instead of tuple arrays.
+  compactHeaders?: boolean;
+  // Return headers as one `headerObject`; takes precedence over `compactHeaders`.
+  headerFormat?: "object";
+  minimalResponse?: boolean;
+  connectTo?: ConnectToRule[];
+  // Called with a sign id; the headers to append go back through `resolveBeforeSend`.
+  onBeforeSend?: (signId: number, method: string, url: string, headers: [string, string][]) => void;
+}
+
+interface NativeTlsConnectOptions {
+  host: string;
+  port: number;
+  browser: BrowserProfile | BrowserProfileAlias;
+  os: EmulationOS;
+  proxy?: string;
+  insecure?: boolean;
+  timeout?: number;
+}
+
+interface NativeProbeOptions {
+  url: string;
+  browser: BrowserProfile | BrowserProfileAlias;
+  os: EmulationOS;
+  proxy?: string;
+  insecure?: boolean;
+  http2?: boolean;
+  timeout?: number;
+}
+
+interface NativeTlsSocket {
+  id: number;
+  alpnProtocol: string | null;
+  tlsVersion: string;
+  localAddress: string | null;
+  remoteAddress: string | null;
+}
+
+interface NativeH2StreamOptions {
+  transportId: string;
+  method: string;
+  scheme: string;
+  authority: string;
+  path: string;
+  headers: HeaderInitTuple[];
+  timeout?: number;
+}
+
+type NativeH2StreamFrame =
+  | { type: "headers"; status: number; headers: HeaderTuple[] }
+  | { type: "data"; data: Buffer }
+  | { type: "trailers"; headers: HeaderTuple[] };
+
+interface NativeMockMatcher {
+  method?: string;
+  urlPattern: string;
+}
+
+interface NativeFetchSetOptions {
+  concurrency?: number;
+  retries?: number;
+  retryDelay?: number;
+  retryStatuses?: number[];
+}
+
+interface NativeMockResponse {
+  status: number;
+  headers: HeaderInitTuple[];
+  body?: Buffer;
+  delayMs: number;
 }
 
 let nativeBinding: {
-  request: (options: NativeRequestOptions, requestId: number, enableCancellation?: boolean) => Promise<NativeResponse>;
-  cancelRequest: (requestId: number) => void;
+  request: (
+    options: NativeRequestOptions,
+    requestId: number | undefined,
+    enableCancellation?: boolean,
+  ) => Promise<NativeResponse>;
+  requestCallback: (
+    options: NativeRequestOptions,
+    requestId: number | undefined,
+    enableCancellation: boolean,
+    callback: (error: Error | null, response?: NativeResponse) => void,
+  ) => void;
+  fetchSet: (
+    requests: NativeRequestOptions[],
+    options: NativeFetchSetOptions,
+    requestId: number,
+    onResult: (index: number, attempts: number, error: Error | null, response?: NativeResponse) => void,
+    onRetry?: (index: number, attempt: number, error: Error, delayMs: number, status: number | null) => boolean,
+  ) => Promise<void>;
+  allocateRequestId: () => number;
+  cancelRequest: (requestId: number, reason?: string) => void;
+  cancelGroup: (groupId: string, reason?: string) => void;
+  cancelAllRequests: () => void;
+  shutdown: (gracePeriodMs: number) => Promise<void>;
   readBodyChunk: (handleId: number) => Promise<Buffer | null>;
   readBodyAll: (handleId: number) => Promise<Buffer>;
+  readBodyJson: (source: number | Buffer) => Promise<unknown>;
+  decodeText: (bytes: Buffer, contentType: string | null) => string;
+  pushBody: (handleId: number, onChunk: (error: Error | null, chunk: Buffer | null) => void) => void;
+  grantBodyCredit: (handleId: number, bytes: number) => void;
+  pipeBodyToRing: (handleId: number, ring: Buffer, onWake: () => void) => Promise<void>;
   cancelBody: (handleId: number) => void;
+  configureBodyStreams: (options: BodyStreamOptions) => void;
+  getNativeStats: () => NativeStats;
+  getMemoryStats: () => MemoryStats;
+  teeBodyToFile: (handleId: number, path: string) => void;
+  takeBodyDigest: (handleId: number) => string | null;
+  takeBodyTrailers: (handleId: number) => HeaderTuple[] | null;
+  takeBodySize: (handleId: number) => NativeBodySize | null;
   getProfiles: () => string[];
   websocketConnect: (options: NativeWebSocketOptions) => Promise<NativeWebSocketConnection>;
   websocketConnectSession: (options: NativeWebSocketSessionOptions) => Promise<NativeWebSocketConnection>;
   websocketSend: (ws: NativeWebSocketConnection, data: string | Buffer) => Promise<void>;
   websocketClose: (ws: NativeWebSocketConnection, options?: NativeWebSocketCloseOptions) => Promise<void>;
-  createSession: (options: NativeSessionOptions) => string;
+  websocketState: (ws: NativeWebSocketConnection) => WebSocketState;
+  tlsConnect: (options: NativeTlsConnectOptions) => Promise<NativeTlsSocket>;
+  probe: (options: NativeProbeOptions) => Promise<ProbeResult>;
+  tlsRead: (socketId: number) => Promise<Buffer | null>;
+  tlsWrite: (socketId: number, data: Buffer) => Promise<void>;
+  tlsEnd: (socketId: number) => Promise<void>;
+  tlsClose: (socketId: number) => void;
+  h2Open: (options: NativeH2StreamOptions) => number;
+  h2Read: (streamId: number) => Promise<NativeH2StreamFrame | null>;
+  h2Write: (streamId: number, data: Buffer) => Promise<void>;
+  h2End: (streamId: number, trailers?: HeaderInitTuple[]) => Promise<void>;
+  h2Close: (streamId: number) => void;
+  createSession: (options: NativeSessionOptions) => NativeCreated;
+  resolveAuthRefresh: (refreshId: number, grant: AccessTokenGrant | null, error?: string) => void;
+  resolveBeforeSend: (signId: number, headers: HeaderInitTuple[] | null, error?: string) => void;
   clearSession: (sessionId: string) => void;
   dropSession: (sessionId: string) => void;
   getCookies: (sessionId: string, url: string) => Record<string, string>;
   setCookie: (sessionId: string, name: string, value: string, url: string) => void;
-  createTransport: (options: NativeTransportOptions) => string;
+  importBrowserCookies: (sessionId: string, cookiesJson: string) => number;
+  getHsts: (sessionId: string) => HstsEntry[];
+  importHsts: (sessionId: string, entries: HstsEntry[]) => void;
+  clearHsts: (sessionId: string) => void;
+  createTransport: (options: NativeTransportOptions) => NativeCreated;
   dropTransport: (transportId: string) => void;
+  clearTransportTlsSessions: (transportId: string) => void;
+  getAltSvc: (transportId: string, origin?: string) => AltSvcEntry[];
+  clearAltSvc: (transportId: string) => void;
+  mock: (matcher: NativeMockMatcher, response: NativeMockResponse) => number;
+  mockCalls: (mockId: number) => number | null;
+  unmock: (mockId: number) => boolean;
+  clearMocks: () => void;
+  useCassette: (path: string, mode: CassetteOptions["mode"], matchOn: CassetteMatchField[]) => void;
+  ejectCassette: () => number;
+  enableAuditLog: (path: string, includeBodies: boolean, rotateBytes?: number) => void;
+  disableAuditLog: () => void;
+  toCurl: (options: NativeRequestOptions) => string;
+  fromCurl: (command: string) => RequestOptions;
   getOperatingSystems?: () => string[];
+  setStrictProfiles: (enabled: boolean) => void;
+  setRegistryMode: (mode: RegistryMode) => void;
+  setDefaults: (defaults: RequestDefaults) => void;
+  getDefaults: () => RequestDefaults;
+  resolveProfile: (alias: string) => string | null;
 };
 
 let cachedProfiles: BrowserProfile[] | undefined;
@@ -194,44 +471,48 @@ function loadNativeBinding() {
 
 nativeBinding = loadNativeBinding();
 
-const websocketFinalizer =
-  typeof FinalizationRegistry === "function"
-    ? new FinalizationRegistry<NativeWebSocketConnection>((connection: NativeWebSocketConnection) => {
-        void nativeBinding.websocketClose(connection).catch(() => undefined);
-      })
-    : undefined;
-
-type NativeBodyHandle = { id: number; released: boolean };
-
-const bodyHandleFinalizer =
-  typeof FinalizationRegistry === "function"
-    ? new FinalizationRegistry<NativeBodyHandle>((handle: NativeBodyHandle) => {
-        if (handle.released) {
-          return;
-        }
+// A new session or transport, with a native box whose finalizer drops the entry.
+type NativeCreated = {
+  id: string;
+  guard?: unknown;
+};
 
-        handle.released = true;
-        try {
-          nativeBinding.cancelBody(handle.id);
-        } catch {
-          // Best-effort cleanup; ignore binding-level failures.
-        }
-      })
-    : undefined;
+// Guards stay alive with the wrapper they belong to, so a wrapper garbage collected without
+// `close()` takes its native entries with it.
+const nativeGuards = new WeakMap<object, readonly unknown[]>();
+
+type NativeBodyHandle = {
+  id: number;
+  released: boolean;
+  // Drops the native stream once neither the response nor its body stream is reachable.
+  guard?: unknown;
+  digest?: string | null;
+  trailers?: HeaderTuple[] | null;
+  size?: NativeBodySize | null;
+};
 
 const DEFAULT_BROWSER: BrowserProfile = "chrome_142";
 const DEFAULT_OS: EmulationOS = "macos";
-const DEFAULT_REQUEST_TIMEOUT_MS = 30_000;
+const DEFAULT_SHUTDOWN_GRACE_PERIOD_MS = 5_000;
+// Longest refresh delay, in seconds, that `followMetaRefresh: true` treats as a redirect.
+const DEFAULT_META_REFRESH_MAX_DELAY = 5;
+const DEFAULT_WEBSOCKET_BATCH_MESSAGES = 64;
+const DEFAULT_WEBSOCKET_STREAM_CHUNK_SIZE = 1024 * 1024;
 const SUPPORTED_OSES: readonly EmulationOS[] = ["windows", "macos", "linux", "android", "ios"];
-const UTF8_DECODER = new TextDecoder("utf-8");
 
 type SessionDefaults = {
-  browser: BrowserProfile;
+  browser: BrowserProfile | BrowserProfileAlias;
   os: EmulationOS;
   proxy?: string;
+  proxyPool?: ProxyPoolOptions;
+  proxyNtlm?: NtlmCredentials;
   timeout?: number;
   insecure?: boolean;
-  defaultHeaders?: HeaderTuple[];
+  blockInsecureRedirects?: boolean;
+  ntlm?: NtlmCredentials;
+  clientHints?: ClientHintsOptions;
+  locale?: string[];
+  defaultHeaders?: HeaderInitTuple[];
   transportId?: string;
   ownsTransport?: boolean;
 };
@@ -245,9 +526,9 @@ type SessionResolution = {
 
 type TransportResolution = {
   transportId?: string;
-  browser?: BrowserProfile;
+  browser?: BrowserProfile | BrowserProfileAlias;
   os?: EmulationOS;
-  proxy?: string;
+  proxy?: string | null;
   insecure?: boolean;
 };
 
@@ -266,6 +547,10 @@ type InternalWebSocketInit = {
   openDispatchMode: WebSocketOpenDispatchMode;
   connect: (callbacks: {
     onMessage: (data: string | Buffer) => void;
+    onMessages: (messages: Array<string | Buffer>) => void;
+    onMessageStart: (text: boolean, size: number) => void;
+    onMessageChunk: (chunk: Buffer) => void;
+    onMessageEnd: () => void;
     onClose: (event: NativeWebSocketCloseEvent) => void;
     onError: (message: string) => void;
   }) => Promise<NativeWebSocketConnection>;
@@ -294,6 +579,16 @@ function normalizeSessionOptions(options?: CreateSessionOptions): { sessionId: s
     defaults.proxy = options.proxy;
   }
 
+  if (options?.proxyPool !== undefined) {
+    validateProxyPool(options.proxyPool, options.proxy);
+    defaults.proxyPool = options.proxyPool;
+  }
+
+  if (options?.proxyNtlm !== undefined) {
+    validateProxyNtlm(options.proxyNtlm, options);
+    defaults.proxyNtlm = options.proxyNtlm;
+  }
+
   if (options?.timeout !== undefined) {
     validateTimeout(options.timeout);
     defaults.timeout = options.timeout;
@@ -303,6 +598,24 @@ function normalizeSessionOptions(options?: CreateSessionOptions): { sessionId: s
     defaults.insecure = options.insecure;
   }
 
+  if (options?.blockInsecureRedirects !== undefined) {
+    defaults.blockInsecureRedirects = options.blockInsecureRedirects;
+  }
+
+  if (options?.ntlm !== undefined) {
+    validateNtlmCredentials(options.ntlm, "ntlm");
+    defaults.ntlm = options.ntlm;
+  }
+
+  if (options?.clientHints !== undefined) {
+    validateClientHints(options.clientHints);
+    defaults.clientHints = options.clientHints;
+  }
+
+  if (options?.locale !== undefined) {
+    defaults.locale = normalizeLocale(options.locale);
+  }
+
   if (options?.defaultHeaders !== undefined) {
     defaults.defaultHeaders = headersToTuples(options.defaultHeaders);
   }
@@ -312,7 +625,9 @@ function normalizeSessionOptions(options?: CreateSessionOptions): { sessionId: s
 
 type HeaderStoreEntry = {
   name: string;
-  values: string[];
+  values: Array<string | Buffer>;
+  // Set by `{ mode: "append" }` tuples: the values add to the profile and session defaults.
+  appendsToDefaults?: boolean;
 };
 
 function isIterable<T>(value: unknown): value is Iterable<T> {
@@ -328,8 +643,30 @@ function isPlainObject(value: unknown): value is Record<string, unknown> {
   return proto === Object.prototype || proto === null;
 }
 
-function coerceHeaderValue(value: unknown): string {
-  return String(value);
+function coerceHeaderValue(value: unknown): string | Buffer {
+  return Buffer.isBuffer(value) ? value : String(value);
+}
+
+// Values are combined as `Headers.append()` does. Once a Buffer is involved the result stays
+// raw bytes, with strings taken as UTF-8.
+function joinHeaderValues(values: Array<string | Buffer>): string | Buffer {
+  if (values.every((value) => typeof value === "string")) {
+    return values.join(", ");
+  }
+
+  const parts: Buffer[] = [];
+  for (const value of values) {
+    if (parts.length > 0) {
+      parts.push(Buffer.from(", "));
+    }
+    parts.push(typeof value === "string" ? Buffer.from(value) : value);
+  }
+  return Buffer.concat(parts);
+}
+
+// Binary values are read back byte for byte, as latin-1.
+function headerValueText(value: string | Buffer): string {
+  return typeof value === "string" ? value : value.toString("latin1");
 }
 
 export class Headers implements Iterable<[string, string]> {
@@ -343,19 +680,18 @@ export class Headers implements Iterable<[string, string]> {
 
   private applyInit(init: HeadersInit) {
     if (init instanceof Headers) {
-      for (const [name, value] of init) {
-        this.append(name, value);
+      for (const [key, entry] of init.store) {
+        this.store.set(key, { ...entry, values: [...entry.values] });
       }
       return;
     }
 
-    if (Array.isArray(init) || isIterable<[string, string]>(init)) {
-      for (const tuple of init as Iterable<[string, string]>) {
+    if (Array.isArray(init) || isIterable<HeaderInitTuple>(init)) {
+      for (const tuple of init as Iterable<HeaderInitTuple>) {
         if (!tuple) {
           continue;
         }
-        const [name, value] = tuple;
-        this.append(name, value);
+        this.applyTuple(tuple[0], tuple[1], tuple.length === 3 ? tuple[2] : undefined);
       }
       return;
     }
@@ -370,6 +706,24 @@ export class Headers implements Iterable<[string, string]> {
     }
   }
 
+  private applyTuple(name: string, value: unknown, options: HeaderTupleOptions | undefined): void {
+    const mode = options?.mode;
+    if (options !== undefined && mode !== "set" && mode !== "append") {
+      throw new TypeError('Header mode must be "set" or "append"');
+    }
+
+    if (mode === "set") {
+      this.set(name, value);
+      return;
+    }
+
+    this.append(name, value);
+    const entry = this.store.get(this.normalizeName(name).key);
+    if (mode === "append" && entry) {
+      entry.appendsToDefaults = true;
+    }
+  }
+
   private normalizeName(name: string): { key: string; display: string } {
     if (typeof name !== "string") {
       throw new TypeError("Header name must be a string");
@@ -381,7 +735,7 @@ export class Headers implements Iterable<[string, string]> {
     return { key: trimmed.toLowerCase(), display: trimmed };
   }
 
-  private assertValue(value: unknown): string {
+  private assertValue(value: unknown): string | Buffer {
     if (value === undefined || value === null) {
       throw new TypeError("Header value must not be null or undefined");
     }
@@ -418,7 +772,7 @@ export class Headers implements Iterable<[string, string]> {
   get(name: string): string | null {
     const normalized = this.normalizeName(name);
     const entry = this.store.get(normalized.key);
-    return entry ? entry.values.join(", ") : null;
+    return entry ? headerValueText(joinHeaderValues(entry.values)) : null;
   }
 
   has(name: string): boolean {
@@ -456,7 +810,7 @@ export class Headers implements Iterable<[string, string]> {
   [Symbol.iterator](): IterableIterator<[string, string]> {
     const generator = function* (store: Map<string, HeaderStoreEntry>) {
       for (const entry of store.values()) {
-        yield [entry.name, entry.values.join(", ")] as [string, string];
+        yield [entry.name, headerValueText(joinHeaderValues(entry.values))] as [string, string];
       }
     };
 
@@ -473,22 +827,27 @@ export class Headers implements Iterable<[string, string]> {
     return result;
   }
 
-  toTuples(): HeaderTuple[] {
-    const result: HeaderTuple[] = [];
+  toTuples(): HeaderInitTuple[] {
+    const result: HeaderInitTuple[] = [];
 
-    for (const [name, value] of this) {
-      result.push([name, value]);
+    for (const entry of this.store.values()) {
+      const value = joinHeaderValues(entry.values);
+      result.push(entry.appendsToDefaults ? [entry.name, value, { mode: "append" }] : [entry.name, value]);
     }
 
     return result;
   }
 }
 
-function headersToTuples(init: HeadersInit): HeaderTuple[] {
+function headersToTuples(init: HeadersInit): HeaderInitTuple[] {
   return new Headers(init).toTuples();
 }
 
-function hasHeaderName(tuples: HeaderTuple[] | undefined, name: string): boolean {
+function appendsToDefaults(tuple: HeaderInitTuple): boolean {
+  return tuple.length === 3 && tuple[2].mode === "append";
+}
+
+function hasHeaderName(tuples: HeaderInitTuple[] | undefined, name: string): boolean {
   if (!tuples) {
     return false;
   }
@@ -527,9 +886,9 @@ function normalizeWebSocketProtocolList(protocols?: string | string[]): string[]
 }
 
 function mergeHeaderTuples(
-  defaults: HeaderTuple[] | undefined,
+  defaults: HeaderInitTuple[] | undefined,
   overrides: HeadersInit | undefined,
-): HeaderTuple[] | undefined {
+): HeaderInitTuple[] | undefined {
   if (!defaults) {
     return overrides === undefined ? undefined : headersToTuples(overrides);
   }
@@ -547,14 +906,19 @@ function mergeHeaderTuples(
   for (const tuple of overrideTuples) {
     overrideKeys.add(tuple[0].toLowerCase());
   }
-  const merged: HeaderTuple[] = [];
+  const merged: HeaderInitTuple[] = [];
+  const replacedDefaults = new Map<string, string | Buffer>();
   for (const tuple of defaults) {
-    if (!overrideKeys.has(tuple[0].toLowerCase())) {
+    const key = tuple[0].toLowerCase();
+    if (overrideKeys.has(key)) {
+      replacedDefaults.set(key, tuple[1]);
+    } else {
       merged.push(tuple);
     }
   }
   for (const tuple of overrideTuples) {
-    merged.push(tuple);
+    const base = appendsToDefaults(tuple) ? replacedDefaults.get(tuple[0].toLowerCase()) : undefined;
+    merged.push(base === undefined ? tuple : [tuple[0], joinHeaderValues([base, tuple[1]]), { mode: "append" }]);
   }
   return merged;
 }
@@ -562,17 +926,47 @@ function mergeHeaderTuples(
 type ResponseType = "basic" | "cors" | "error" | "opaque" | "opaqueredirect";
 
 function cloneNativeResponse(payload: NativeResponse): NativeResponse {
+  const { headers, cookies } = expandHeaderFields(payload);
   return {
     status: payload.status,
-    headers: payload.headers.map(([name, value]): HeaderTuple => [name, value]),
+    ...(payload.reason !== undefined && { reason: payload.reason }),
+    ...(payload.headerObject !== undefined && { headerObject: { ...payload.headerObject } }),
+    headers: headers.map(([name, value]): HeaderTuple => [name, value]),
     bodyHandle: payload.bodyHandle,
     bodyBytes: payload.bodyBytes,
     contentLength: payload.contentLength,
-    cookies: payload.cookies.map(([name, value]): HeaderTuple => [name, value]),
+    connection: { ...payload.connection },
+    bodyDigest: payload.bodyDigest ?? null,
+    trailers: payload.trailers ?? null,
+    headerBytes: payload.headerBytes,
+    bodySize: payload.bodySize ?? null,
+    timing: payload.timing ?? null,
+    cookies: cookies.map(([name, value]): HeaderTuple => [name, value]),
     url: payload.url,
   };
 }
 
+// Decode the compact `headerFields` form into header and cookie tuples, once per payload.
+function expandHeaderFields(payload: NativeResponse): { headers: HeaderTuple[]; cookies: HeaderTuple[] } {
+  if (payload.headerFields !== undefined) {
+    const [headers, cookies] = JSON.parse(payload.headerFields) as [string[], string[]];
+    payload.headers = pairHeaderFields(headers);
+    payload.cookies = pairHeaderFields(cookies);
+    delete payload.headerFields;
+  } else if (payload.headers === undefined && payload.headerObject !== undefined) {
+    payload.headers = Object.entries(payload.headerObject);
+  }
+  return { headers: payload.headers ?? [], cookies: payload.cookies ?? [] };
+}
+
+function pairHeaderFields(fields: string[]): HeaderTuple[] {
+  const tuples: HeaderTuple[] = new Array(fields.length / 2);
+  for (let i = 0; i < fields.length; i += 2) {
+    tuples[i / 2] = [fields[i] as string, fields[i + 1] as string];
+  }
+  return tuples;
+}
+
 function releaseNativeBody(handle: NativeBodyHandle): void {
   if (handle.released) {
     return;
@@ -585,27 +979,75 @@ function releaseNativeBody(handle: NativeBodyHandle): void {
   } catch {
     // Best-effort cleanup; ignore binding errors.
   }
-
-  bodyHandleFinalizer?.unregister(handle);
 }
 
-function markNativeBodyReleased(handle: NativeBodyHandle): void {
-  if (handle.released) {
-    return;
-  }
+const DEFAULT_PUSH_CREDIT = 256 * 1024;
+let bodyDelivery: BodyDelivery = "pull";
+let pushCredit = DEFAULT_PUSH_CREDIT;
 
-  handle.released = true;
-  bodyHandleFinalizer?.unregister(handle);
+function finishNativeBody(handle: NativeBodyHandle): void {
+  handle.digest = nativeBinding.takeBodyDigest(handle.id);
+  handle.trailers = nativeBinding.takeBodyTrailers(handle.id);
+  handle.size = nativeBinding.takeBodySize(handle.id);
+  releaseNativeBody(handle);
 }
 
 function createNativeBodyStream(handle: NativeBodyHandle): ReadableStream<Uint8Array> {
-  const stream = new ReadableStream<Uint8Array>({
+  return bodyDelivery === "push" ? createPushedBodyStream(handle, pushCredit) : createPulledBodyStream(handle);
+}
+
+// The native side sends chunks while it has credit. Each pull grants what the queue has room for
+// beyond the credit already out, so the queue never holds much more than `credit` bytes.
+function createPushedBodyStream(handle: NativeBodyHandle, credit: number): ReadableStream<Uint8Array> {
+  // Granted but not yet delivered; below zero while a chunk larger than the credit is paid off.
+  let outstanding = 0;
+  let started = false;
+
+  return new ReadableStream<Uint8Array>(
+    {
+      pull(controller) {
+        if (!started) {
+          started = true;
+          nativeBinding.pushBody(handle.id, (error, chunk) => {
+            // Chunks already on their way when the stream was cancelled.
+            if (handle.released) {
+              return;
+            }
+            if (error) {
+              releaseNativeBody(handle);
+              controller.error(error);
+            } else if (chunk === null) {
+              finishNativeBody(handle);
+              controller.close();
+            } else {
+              outstanding -= chunk.byteLength;
+              controller.enqueue(chunk);
+            }
+          });
+        }
+
+        const wanted = (controller.desiredSize ?? 0) - outstanding;
+        if (wanted > 0) {
+          outstanding += wanted;
+          nativeBinding.grantBodyCredit(handle.id, wanted);
+        }
+      },
+      cancel() {
+        releaseNativeBody(handle);
+      },
+    },
+    new ByteLengthQueuingStrategy({ highWaterMark: credit }),
+  );
+}
+
+function createPulledBodyStream(handle: NativeBodyHandle): ReadableStream<Uint8Array> {
+  return new ReadableStream<Uint8Array>({
     async pull(controller) {
       try {
         const chunk = await nativeBinding.readBodyChunk(handle.id);
 
         if (chunk === null) {
-          releaseNativeBody(handle);
+          finishNativeBody(handle);
           controller.close();
           return;
         }
@@ -620,10 +1062,71 @@ function createNativeBodyStream(handle: NativeBodyHandle): ReadableStream<Uint8A
       releaseNativeBody(handle);
     },
   });
+}
 
-  bodyHandleFinalizer?.register(stream, handle, handle);
+// Layout of a body ring, shared with rust/src/shared_ring.rs: four Int32 header words, then data.
+const RING_HEADER_BYTES = 16;
+const RING_WRITE = 0;
+const RING_READ = 1;
+const RING_STATE = 2;
+const RING_WAITING = 3;
+const RING_STREAMING = 0;
+const RING_DONE = 1;
+const RING_FAILED = 2;
+const RING_CANCELLED = 3;
+// A writer that ran out of room polls, since the reader frees it without telling anyone. A
+// reader sleeps at most this long too, as the writer may finish just after it checked.
+const RING_MAX_WAIT_MS = 20;
+
+function ringControl(ring: SharedArrayBuffer): Int32Array {
+  if (!(ring instanceof SharedArrayBuffer) || ring.byteLength <= RING_HEADER_BYTES) {
+    throw new RequestError(`ring must be a SharedArrayBuffer of more than ${RING_HEADER_BYTES} bytes`);
+  }
+  return new Int32Array(ring, 0, RING_HEADER_BYTES / 4);
+}
 
-  return stream;
+// Copy a JS body into a ring, for bodies that arrived inline or were already turned into a stream.
+async function writeStreamToRing(
+  source: ReadableStream<Uint8Array>,
+  ring: SharedArrayBuffer,
+  control: Int32Array,
+): Promise<void> {
+  const data = new Uint8Array(ring, RING_HEADER_BYTES);
+  const reader = source.getReader();
+  try {
+    while (true) {
+      const { done, value } = await reader.read();
+      if (done) {
+        return;
+      }
+      let offset = 0;
+      let backoff = 1;
+      while (offset < value.byteLength) {
+        if (Atomics.load(control, RING_STATE) === RING_CANCELLED) {
+          await reader.cancel();
+          return;
+        }
+        const written = Atomics.load(control, RING_WRITE) >>> 0;
+        const free = data.byteLength - ((written - (Atomics.load(control, RING_READ) >>> 0)) >>> 0);
+        const start = written % data.byteLength;
+        const length = Math.min(value.byteLength - offset, free, data.byteLength - start);
+        if (length === 0) {
+          await new Promise((resolve) => setTimeout(resolve, backoff));
+          backoff = Math.min(backoff * 2, RING_MAX_WAIT_MS);
+          continue;
+        }
+        data.set(value.subarray(offset, offset + length), start);
+        Atomics.store(control, RING_WRITE, (written + length) | 0);
+        offset += length;
+        backoff = 1;
+        if (Atomics.exchange(control, RING_WAITING, 0) === 1) {
+          Atomics.notify(control, RING_WRITE);
+        }
+      }
+    }
+  } finally {
+    reader.releaseLock();
+  }
 }
 
 function wrapBodyStream(source: ReadableStream<Uint8Array>, onFirstUse: () => void): ReadableStream<Uint8Array> {
@@ -667,6 +1170,9 @@ export class Response {
   readonly status: number;
   readonly ok: boolean;
   readonly contentLength: number | null;
+  readonly connection: ConnectionInfo;
+  /** Native timing, set for traced requests that went over the network. */
+  readonly timing: RequestTiming | null;
   readonly url: string;
   readonly type: ResponseType = "basic";
   bodyUsed = false;
@@ -674,9 +1180,8 @@ export class Response {
   private readonly payload: NativeResponse;
   private readonly requestUrl: string;
   private redirectedMemo: boolean | undefined;
-  private readonly headersInit: HeaderTuple[];
   private headersInstance: Headers | null;
-  private readonly cookiesInit: HeaderTuple[];
+  private headerRecord: Record<string, string> | null;
   private cookiesRecord: Record<string, string | string[]> | null;
   private inlineBody: Buffer | null;
   private bodySource: ReadableStream<Uint8Array> | null;
@@ -684,20 +1189,32 @@ export class Response {
   // Track if we can use the fast path (native handle not yet wrapped in a stream)
   private nativeHandleAvailable: boolean;
   private nativeHandle: NativeBodyHandle | null;
+  private bodyDigestValue: string | null;
+  private trailerTuples: HeaderTuple[] | null;
+  private trailersInstance: Headers | null;
+  private bodySizeValue: NativeBodySize | null;
+  // Native stream whose digest, trailers and size apply to this body; shared with clones that tee from it.
+  private digestHandle: NativeBodyHandle | null;
 
   constructor(payload: NativeResponse, requestUrl: string, bodySource?: ReadableStream<Uint8Array> | null) {
     this.payload = payload;
     this.requestUrl = requestUrl;
     this.status = this.payload.status;
     this.ok = this.status >= 200 && this.status < 300;
-    this.headersInit = this.payload.headers;
     this.headersInstance = null;
+    this.headerRecord = this.payload.headerObject ?? null;
     this.url = this.payload.url;
-    this.cookiesInit = this.payload.cookies;
     this.cookiesRecord = null;
     this.contentLength = this.payload.contentLength ?? null;
+    this.connection = this.payload.connection;
+    this.timing = this.payload.timing ?? null;
     this.inlineBody = this.payload.bodyBytes ?? null;
     this.nativeHandle = null;
+    this.bodyDigestValue = this.payload.bodyDigest ?? null;
+    this.trailerTuples = this.payload.trailers ?? null;
+    this.trailersInstance = null;
+    this.bodySizeValue = this.payload.bodySize ?? null;
+    this.digestHandle = null;
 
     if (typeof bodySource !== "undefined") {
       // External stream provided (e.g., from clone) - no fast path
@@ -711,8 +1228,8 @@ export class Response {
       // Defer stream creation - we might use fast path instead
       this.bodySource = null;
       this.nativeHandleAvailable = true;
-      this.nativeHandle = { id: this.payload.bodyHandle, released: false };
-      bodyHandleFinalizer?.register(this, this.nativeHandle, this.nativeHandle);
+      this.nativeHandle = { id: this.payload.bodyHandle, released: false, guard: this.payload.bodyGuard };
+      this.digestHandle = this.nativeHandle;
     } else {
       this.bodySource = null;
       this.nativeHandleAvailable = false;
@@ -737,20 +1254,35 @@ export class Response {
   }
 
   get statusText(): string {
-    return STATUS_CODES[this.status] ?? "";
+    return this.payload.reason ?? STATUS_CODES[this.status] ?? "";
   }
 
   get headers(): Headers {
     if (!this.headersInstance) {
-      this.headersInstance = new Headers(this.headersInit);
+      this.headersInstance = new Headers(this.payload.headerObject ?? expandHeaderFields(this.payload).headers);
     }
     return this.headersInstance;
   }
 
+  /**
+   * Headers as one plain object of lower-cased names, with repeated fields joined by `, ` as
+   * `headers.get()` does. Built natively when the request set `headerFormat: "object"`.
+   */
+  get headerObject(): Record<string, string> {
+    if (!this.headerRecord) {
+      const record: Record<string, string> = {};
+      for (const [name, value] of this.headers) {
+        record[name.toLowerCase()] = value;
+      }
+      this.headerRecord = record;
+    }
+    return this.headerRecord;
+  }
+
   get cookies(): Record<string, string | string[]> {
     if (!this.cookiesRecord) {
       const record: Record<string, string | string[]> = Object.create(null);
-      for (const [name, value] of this.cookiesInit) {
+      for (const [name, value] of expandHeaderFields(this.payload).cookies) {
         const existing = record[name];
         if (existing === undefined) {
           record[name] = value;
@@ -766,6 +1298,38 @@ export class Response {
     return this.cookiesRecord;
   }
 
+  /**
+   * Hex digest of the body when the request set `bodyDigest`. Streamed bodies are hashed natively
+   * as they are read, so this is `null` until the body has been read to the end.
+   */
+  get bodyDigest(): string | null {
+    return this.bodyDigestValue ?? this.digestHandle?.digest ?? null;
+  }
+
+  /**
+   * Trailer fields the server sent after the body, e.g. `grpc-status`. Streamed bodies are only
+   * finished once read to the end, so this is `null` until then, and when the server sent none.
+   */
+  get trailers(): Headers | null {
+    if (!this.trailersInstance) {
+      const tuples = this.trailerTuples ?? this.digestHandle?.trailers ?? null;
+      this.trailersInstance = tuples === null ? null : new Headers(tuples);
+    }
+    return this.trailersInstance;
+  }
+
+  /**
+   * Header and body byte counts, with the body counted both as received and after decoding.
+   * Streamed bodies are only counted once read to the end, so this is `null` until then.
+   */
+  get sizes(): ResponseSizes | null {
+    const body = this.bodySizeValue ?? this.digestHandle?.size ?? null;
+    if (body === null) {
+      return null;
+    }
+    return { headerBytes: this.payload.headerBytes, wireBodyBytes: body.wire, decodedBodyBytes: body.decoded };
+  }
+
   get body(): ReadableStream<Uint8Array> | null {
     if (this.inlineBody && this.bodySource === null) {
       const bytes = this.inlineBody;
@@ -784,10 +1348,11 @@ export class Response {
 
     // Lazily create the stream if needed (disables fast path)
     if (this.bodySource === null && this.nativeHandleAvailable && this.payload.bodyHandle !== null) {
-      if (this.nativeHandle) {
-        bodyHandleFinalizer?.unregister(this.nativeHandle);
-      }
-      const handle = this.nativeHandle ?? { id: this.payload.bodyHandle, released: false };
+      const handle = this.nativeHandle ?? {
+        id: this.payload.bodyHandle,
+        released: false,
+        guard: this.payload.bodyGuard,
+      };
       this.nativeHandle = handle;
       this.bodySource = createNativeBodyStream(handle);
       this.nativeHandleAvailable = false;
@@ -806,7 +1371,27 @@ export class Response {
     return this.bodyStream;
   }
 
+  /**
+   * Parse the body as JSON. Bodies that have not been streamed are parsed natively, off the
+   * main thread. Invalid JSON throws a `SyntaxError` with the byte `offset`, `line`, and
+   * `column` where parsing stopped.
+   */
   async json<T = unknown>(): Promise<T> {
+    this.assertBodyAvailable();
+
+    if (this.inlineBody) {
+      const bytes = this.inlineBody;
+      this.inlineBody = null;
+      this.bodyUsed = true;
+      return (await nativeBinding.readBodyJson(bytes)) as T;
+    }
+
+    if (this.nativeHandleAvailable && this.payload.bodyHandle !== null) {
+      this.bodyUsed = true;
+      const value = await this.readNativeBody(this.payload.bodyHandle, nativeBinding.readBodyJson);
+      return (value === null ? await nativeBinding.readBodyJson(Buffer.alloc(0)) : value.result) as T;
+    }
+
     const text = await this.text();
     return JSON.parse(text) as T;
   }
@@ -828,9 +1413,13 @@ export class Response {
     return view.buffer;
   }
 
+  /**
+   * Decode the body the way a browser decodes a page: a byte order mark wins, then the
+   * `Content-Type` charset, then for HTML a `<meta charset>` declaration; otherwise UTF-8.
+   */
   async text(): Promise<string> {
     const bytes = await this.consumeBody();
-    return UTF8_DECODER.decode(bytes);
+    return nativeBinding.decodeText(bytes, this.headers.get("content-type"));
   }
 
   async blob(): Promise<Blob> {
@@ -856,10 +1445,11 @@ export class Response {
 
     // If we still have the native handle (fast path), we need to create the stream first
     if (this.nativeHandleAvailable && this.payload.bodyHandle !== null) {
-      if (this.nativeHandle) {
-        bodyHandleFinalizer?.unregister(this.nativeHandle);
-      }
-      const handle = this.nativeHandle ?? { id: this.payload.bodyHandle, released: false };
+      const handle = this.nativeHandle ?? {
+        id: this.payload.bodyHandle,
+        released: false,
+        guard: this.payload.bodyGuard,
+      };
       this.nativeHandle = handle;
       this.bodySource = createNativeBodyStream(handle);
       this.nativeHandleAvailable = false;
@@ -875,7 +1465,74 @@ export class Response {
     this.bodySource = branchA;
     this.bodyStream = undefined;
 
-    return new Response(cloneNativeResponse(this.payload), this.requestUrl, branchB);
+    const cloned = new Response(cloneNativeResponse(this.payload), this.requestUrl, branchB);
+    cloned.digestHandle = this.digestHandle;
+    return cloned;
+  }
+
+  /**
+   * Write the body to `path` as it is read, so a large response can be processed and archived
+   * without buffering it twice. Call this before reading the body; every chunk delivered
+   * afterwards through `body`, `text()`, `arrayBuffer()` and friends is also appended to the file.
+   */
+  async teeToFile(path: string): Promise<void> {
+    this.assertBodyAvailable();
+
+    if (typeof path !== "string" || path.length === 0) {
+      throw new RequestError("path must be a non-empty string");
+    }
+
+    if (this.nativeHandle !== null && !this.nativeHandle.released) {
+      try {
+        nativeBinding.teeBodyToFile(this.nativeHandle.id, path);
+      } catch (error) {
+        throw toRequestError(error);
+      }
+      return;
+    }
+
+    if (this.bodySource !== null) {
+      throw new TypeError("Cannot tee a Response body that has already been cloned or streamed");
+    }
+
+    // Small bodies arrive inline, so the whole payload can be written up front.
+    await writeFile(path, this.inlineBody ?? Buffer.alloc(0));
+  }
+
+  /**
+   * Copy the body into `ring`, a `SharedArrayBuffer` from `createBodyRing()`, for a worker to
+   * read with `readBodyRing()`. Bytes go straight from the native side into shared memory
+   * without allocating a Buffer per chunk. Resolves once the whole body is in the ring, or the
+   * reader stopped early; a download error rejects and also fails the reader.
+   */
+  async pipeToRing(ring: SharedArrayBuffer): Promise<void> {
+    this.assertBodyAvailable();
+    const control = ringControl(ring);
+    this.bodyUsed = true;
+
+    if (this.nativeHandleAvailable && this.payload.bodyHandle !== null) {
+      // The native side marks the ring done or failed itself.
+      await this.readNativeBody(this.payload.bodyHandle, (handleId) =>
+        nativeBinding.pipeBodyToRing(handleId, Buffer.from(ring), () => {
+          Atomics.notify(control, RING_WRITE);
+        }),
+      );
+      return;
+    }
+
+    try {
+      const source = this.body;
+      if (source) {
+        await writeStreamToRing(source, ring, control);
+      }
+      Atomics.compareExchange(control, RING_STATE, RING_STREAMING, RING_DONE);
+    } catch (error) {
+      Atomics.compareExchange(control, RING_STATE, RING_STREAMING, RING_FAILED);
+      throw error;
+    } finally {
+      Atomics.store(control, RING_WAITING, 0);
+      Atomics.notify(control, RING_WRITE);
+    }
   }
 
   private assertBodyAvailable(): void {
@@ -896,20 +1553,8 @@ export class Response {
 
     // Fast path: if native handle is still available, read entire body in one Rust call
     if (this.nativeHandleAvailable && this.payload.bodyHandle !== null) {
-      this.nativeHandleAvailable = false;
-      try {
-        return await nativeBinding.readBodyAll(this.payload.bodyHandle);
-      } catch (error) {
-        // Handle already consumed or error
-        if (String(error).includes("Body handle") && String(error).includes("not found")) {
-          return Buffer.alloc(0);
-        }
-        throw error;
-      } finally {
-        if (this.nativeHandle) {
-          markNativeBodyReleased(this.nativeHandle);
-        }
-      }
+      const bytes = await this.readNativeBody(this.payload.bodyHandle, nativeBinding.readBodyAll);
+      return bytes === null ? Buffer.alloc(0) : bytes.result;
     }
 
     // Slow path: stream was accessed, use streaming consumption
@@ -943,6 +1588,45 @@ export class Response {
 
     return chunks.length === 0 ? Buffer.alloc(0) : Buffer.concat(chunks);
   }
+
+  // Read the whole body through the native handle in one call. Resolves to null when the
+  // handle is already gone.
+  private async readNativeBody<T>(
+    handleId: number,
+    read: (handleId: number) => Promise<T>,
+  ): Promise<{ result: T } | null> {
+    this.nativeHandleAvailable = false;
+    try {
+      return { result: await read(handleId) };
+    } catch (error) {
+      // Handle already consumed or error
+      if (String(error).includes("Body handle") && String(error).includes("not found")) {
+        return null;
+      }
+      throw error;
+    } finally {
+      // A body that failed to parse as JSON has still been read, so its digest is ready.
+      this.bodyDigestValue = nativeBinding.takeBodyDigest(handleId);
+      this.trailerTuples = nativeBinding.takeBodyTrailers(handleId);
+      this.bodySizeValue = nativeBinding.takeBodySize(handleId);
+      if (this.nativeHandle) {
+        this.nativeHandle.released = true;
+      }
+    }
+  }
+}
+
+// Matches the native `scheme://host:port` origin key, with the default port filled in.
+function toOriginKey(origin: string | URL): string {
+  let url: URL;
+  try {
+    url = origin instanceof URL ? origin : new URL(origin);
+  } catch {
+    throw new RequestError(`Invalid origin: ${String(origin)}`);
+  }
+
+  const port = url.port || (url.protocol === "https:" || url.protocol === "wss:" ? "443" : "80");
+  return `${url.protocol}//${url.hostname}:${port}`;
 }
 
 export class Transport {
@@ -957,52 +1641,239 @@ export class Transport {
     return this.disposed;
   }
 
-  async close(): Promise<void> {
+  /**
+   * Forget cached TLS sessions so the next connection performs a full handshake.
+   * Idle pooled connections are discarded as well.
+   */
+  clearTlsSessionCache(): void {
     if (this.disposed) {
-      return;
+      throw new RequestError("Transport has been closed");
     }
 
-    this.disposed = true;
-
     try {
-      nativeBinding.dropTransport(this.id);
+      nativeBinding.clearTransportTlsSessions(this.id);
     } catch (error) {
-      throw new RequestError(String(error));
+      throw toRequestError(error);
     }
   }
-}
-
-export class Session implements SessionHandle {
-  readonly id: string;
-  private disposed = false;
-  private readonly defaults: SessionDefaults;
 
-  constructor(id: string, defaults: SessionDefaults) {
-    this.id = id;
-    this.defaults = defaults;
-  }
+  /**
+   * Inspect the Alt-Svc alternatives advertised to this transport, optionally for one origin.
+   * Expired alternatives are pruned before returning.
+   */
+  getAltSvc(origin?: string | URL): AltSvcEntry[] {
+    if (this.disposed) {
+      throw new RequestError("Transport has been closed");
+    }
 
-  get closed(): boolean {
-    return this.disposed;
+    try {
+      return nativeBinding.getAltSvc(this.id, origin === undefined ? undefined : toOriginKey(origin));
+    } catch (error) {
+      throw toRequestError(error);
+    }
   }
 
-  private ensureActive(): void {
+  /**
+   * Forget every cached Alt-Svc alternative.
+   */
+  clearAltSvc(): void {
     if (this.disposed) {
-      throw new RequestError("Session has been closed");
+      throw new RequestError("Transport has been closed");
     }
-  }
 
-  /** @internal */
-  getDefaults(): SessionDefaults {
-    const snapshot: SessionDefaults = { ...this.defaults };
-    if (this.defaults.defaultHeaders) {
-      snapshot.defaultHeaders = [...this.defaults.defaultHeaders];
+    try {
+      nativeBinding.clearAltSvc(this.id);
+    } catch (error) {
+      throw toRequestError(error);
     }
-    return snapshot;
   }
 
-  /** @internal */
-  _defaultsRef(): SessionDefaults {
+  /**
+   * Open a raw HTTP/2 stream on this transport's pooled connection, for protocols such as
+   * gRPC that need incremental DATA frames and trailers. The origin must negotiate h2.
+   *
+   * @example
+   * ```typescript
+   * const stream = transport.openStream({
+   *   url: 'https://api.example.com/pkg.Service/Method',
+   *   headers: { 'content-type': 'application/grpc', te: 'trailers' },
+   * });
+   * await stream.write(message);
+   * await stream.end();
+   * for await (const frame of stream) {
+   *   console.log(frame.type);
+   * }
+   * ```
+   */
+  openStream(init: H2StreamInit): H2Stream {
+    if (this.disposed) {
+      throw new RequestError("Transport has been closed");
+    }
+
+    let url: URL;
+    try {
+      url = new URL(coerceUrlInput(init.url));
+    } catch (error) {
+      throw error instanceof RequestError ? error : new RequestError(`Invalid URL: ${String(init.url)}`);
+    }
+
+    const path = init.path ?? `${url.pathname}${url.search}`;
+    if (!path.startsWith("/")) {
+      throw new RequestError("path must start with /");
+    }
+    validateTimeout(init.timeout);
+
+    try {
+      const id = nativeBinding.h2Open({
+        transportId: this.id,
+        method: ensureMethod(init.method ?? "POST"),
+        scheme: init.scheme ?? url.protocol.slice(0, -1),
+        authority: init.authority ?? url.host,
+        path,
+        headers: init.headers === undefined ? [] : headersToTuples(init.headers),
+        ...(init.timeout !== undefined && { timeout: init.timeout }),
+      });
+      return new H2Stream(id);
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+
+  async close(): Promise<void> {
+    if (this.disposed) {
+      return;
+    }
+
+    this.disposed = true;
+
+    try {
+      nativeBinding.dropTransport(this.id);
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+}
+
+export class H2Stream implements AsyncIterable<H2StreamFrame> {
+  readonly id: number;
+  private released = false;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  get closed(): boolean {
+    return this.released;
+  }
+
+  /**
+   * Send one DATA frame. Resolves once the frame is queued on the connection.
+   */
+  async write(chunk: string | Uint8Array): Promise<void> {
+    const data =
+      typeof chunk === "string" ? Buffer.from(chunk) : Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength);
+    try {
+      await nativeBinding.h2Write(this.id, data);
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+
+  /**
+   * End the request side, optionally sending trailers in a final HEADERS frame.
+   */
+  async end(trailers?: HeadersInit): Promise<void> {
+    try {
+      await nativeBinding.h2End(this.id, trailers === undefined ? undefined : headersToTuples(trailers));
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+
+  /**
+   * Receive the next frame, or `null` once the response side has ended.
+   */
+  async read(): Promise<H2StreamFrame | null> {
+    if (this.released) {
+      return null;
+    }
+
+    let frame: NativeH2StreamFrame | null;
+    try {
+      frame = await nativeBinding.h2Read(this.id);
+    } catch (error) {
+      this.close();
+      throw toRequestError(error);
+    }
+
+    if (frame === null) {
+      this.close();
+      return null;
+    }
+    if (frame.type === "data") {
+      return frame;
+    }
+    return { ...frame, headers: new Headers(frame.headers) };
+  }
+
+  /**
+   * Release the stream. A stream that has not finished is reset with CANCEL.
+   */
+  close(): void {
+    if (this.released) {
+      return;
+    }
+
+    this.released = true;
+    try {
+      nativeBinding.h2Close(this.id);
+    } catch {
+      // Best-effort cleanup; ignore binding errors.
+    }
+  }
+
+  async *[Symbol.asyncIterator](): AsyncIterator<H2StreamFrame> {
+    try {
+      for (let frame = await this.read(); frame !== null; frame = await this.read()) {
+        yield frame;
+      }
+    } finally {
+      this.close();
+    }
+  }
+}
+
+export class Session implements SessionHandle {
+  readonly id: string;
+  private disposed = false;
+  private readonly defaults: SessionDefaults;
+
+  constructor(id: string, defaults: SessionDefaults) {
+    this.id = id;
+    this.defaults = defaults;
+  }
+
+  get closed(): boolean {
+    return this.disposed;
+  }
+
+  private ensureActive(): void {
+    if (this.disposed) {
+      throw new RequestError("Session has been closed");
+    }
+  }
+
+  /** @internal */
+  getDefaults(): SessionDefaults {
+    const snapshot: SessionDefaults = { ...this.defaults };
+    if (this.defaults.defaultHeaders) {
+      snapshot.defaultHeaders = [...this.defaults.defaultHeaders];
+    }
+    return snapshot;
+  }
+
+  /** @internal */
+  _defaultsRef(): SessionDefaults {
     return this.defaults;
   }
 
@@ -1017,7 +1888,7 @@ export class Session implements SessionHandle {
     try {
       nativeBinding.clearSession(this.id);
     } catch (error) {
-      throw new RequestError(String(error));
+      throw toRequestError(error);
     }
   }
 
@@ -1026,7 +1897,7 @@ export class Session implements SessionHandle {
     try {
       return nativeBinding.getCookies(this.id, String(url));
     } catch (error) {
-      throw new RequestError(String(error));
+      throw toRequestError(error);
     }
   }
 
@@ -1035,7 +1906,58 @@ export class Session implements SessionHandle {
     try {
       nativeBinding.setCookie(this.id, name, value, String(url));
     } catch (error) {
-      throw new RequestError(String(error));
+      throw toRequestError(error);
+    }
+  }
+
+  /**
+   * Load cookies exported from a headless browser, e.g. after logging in with Puppeteer or
+   * Playwright, into this session. Takes the cookie array (or its JSON), or a Playwright
+   * `storageState()`. Expired cookies are skipped.
+   *
+   * @returns The number of cookies stored
+   */
+  importBrowserCookies(cookies: BrowserCookie[] | { cookies: BrowserCookie[] } | string): number {
+    this.ensureActive();
+    try {
+      const json = typeof cookies === "string" ? cookies : JSON.stringify(cookies);
+      return nativeBinding.importBrowserCookies(this.id, json);
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+
+  /**
+   * Snapshot the session's HSTS store so it can be persisted and restored with
+   * {@link Session.importHsts}. Requires the session to be created with `hsts: true`.
+   */
+  getHsts(): HstsEntry[] {
+    this.ensureActive();
+    try {
+      return nativeBinding.getHsts(this.id);
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+
+  /**
+   * Merge previously persisted HSTS entries into this session. Expired entries are skipped.
+   */
+  importHsts(entries: HstsEntry[]): void {
+    this.ensureActive();
+    try {
+      nativeBinding.importHsts(this.id, entries);
+    } catch (error) {
+      throw toRequestError(error);
+    }
+  }
+
+  clearHsts(): void {
+    this.ensureActive();
+    try {
+      nativeBinding.clearHsts(this.id);
+    } catch (error) {
+      throw toRequestError(error);
     }
   }
 
@@ -1081,6 +2003,9 @@ export class Session implements SessionHandle {
           onMessage: callbacks.onMessage,
           onClose: callbacks.onClose,
           onError: callbacks.onError,
+          ...(normalized.options.binaryText === true && { binaryText: true }),
+          ...nativeWebSocketBatch(normalized.options, callbacks),
+          ...nativeWebSocketStreaming(normalized.options, callbacks),
         }),
       legacyCallbacks: normalized.legacyCallbacks,
     });
@@ -1099,7 +2024,7 @@ export class Session implements SessionHandle {
       nativeBinding.dropSession(this.id);
     } catch (error) {
       if (!ownsTransport || !transportId) {
-        throw new RequestError(String(error));
+        throw toRequestError(error);
       }
       // Fall through to transport cleanup and surface the original error after.
       const originalError = error;
@@ -1115,7 +2040,7 @@ export class Session implements SessionHandle {
       try {
         nativeBinding.dropTransport(transportId);
       } catch (error) {
-        throw new RequestError(String(error));
+        throw toRequestError(error);
       }
     }
   }
@@ -1184,12 +2109,11 @@ function resolveTransportContext(config: WreqRequestInit, sessionDefaults?: Sess
       throw new RequestError("Transport has been closed");
     }
 
-    const hasProxy = config.proxy !== undefined;
-    if (config.browser !== undefined || config.os !== undefined || hasProxy || config.insecure !== undefined) {
-      throw new RequestError("`transport` cannot be combined with browser/os/proxy/insecure options");
+    if (config.browser !== undefined || config.os !== undefined || config.insecure !== undefined) {
+      throw new RequestError("`transport` cannot be combined with browser/os/insecure options");
     }
 
-    return { transportId: config.transport.id };
+    return { transportId: config.transport.id, ...(config.proxy !== undefined && { proxy: config.proxy }) };
   }
 
   if (sessionDefaults?.transportId) {
@@ -1223,13 +2147,17 @@ function resolveTransportContext(config: WreqRequestInit, sessionDefaults?: Sess
     return { transportId: sessionDefaults.transportId };
   }
 
-  const browser = config.browser ?? DEFAULT_BROWSER;
-  const os = config.os ?? DEFAULT_OS;
-
-  validateBrowserProfile(browser);
-  validateOperatingSystem(os);
+  validateBrowserProfile(config.browser);
+  validateOperatingSystem(config.os);
 
-  const resolved: TransportResolution = { browser, os };
+  // Unset options are left to the native layer, which applies setDefaults() before its own defaults.
+  const resolved: TransportResolution = {};
+  if (config.browser !== undefined) {
+    resolved.browser = config.browser;
+  }
+  if (config.os !== undefined) {
+    resolved.os = config.os;
+  }
   if (config.proxy !== undefined) {
     resolved.proxy = config.proxy;
   }
@@ -1267,22 +2195,43 @@ function createAbortError(reason?: unknown): Error {
   return error;
 }
 
+// Preserve the native cause chain (one Error per anyhow context layer) when wrapping.
+function toRequestError(error: unknown): RequestError {
+  if (error instanceof RequestError) {
+    return error;
+  }
+
+  if (!(error instanceof Error)) {
+    return new RequestError(String(error));
+  }
+
+  // Native request failures carry the phase they failed in.
+  const { code, phase, phaseElapsed } = error as Error & Pick<RequestError, "code" | "phase" | "phaseElapsed">;
+  return new RequestError(String(error), {
+    ...(error.cause !== undefined && { cause: error.cause }),
+    ...(code !== undefined && { code }),
+    ...(phase !== undefined && { phase }),
+    ...(phaseElapsed !== undefined && { phaseElapsed }),
+  });
+}
+
+// The text native cancellation rejects with, so the reason survives when the native error is what surfaces.
+function abortReasonText(reason: unknown): string | undefined {
+  if (typeof reason === "string") {
+    return reason;
+  }
+
+  return reason instanceof Error ? reason.message : undefined;
+}
+
 function isAbortError(error: unknown): error is Error {
   return Boolean(error) && typeof (error as Error).name === "string" && (error as Error).name === "AbortError";
 }
 
-// Request IDs must stay below 2^48 to preserve integer precision across the bridge.
-const REQUEST_ID_MAX = 2 ** 48;
-// Seed with a monotonic-ish value derived from hrtime to avoid collisions after reloads.
-let requestIdCounter = Math.trunc(Number(process.hrtime.bigint() % BigInt(REQUEST_ID_MAX - 1))) + 1;
-
+// Request IDs come from the native side, so they never collide across workers or with other
+// wrappers around the same addon.
 function generateRequestId(): number {
-  requestIdCounter += 1;
-  if (requestIdCounter >= REQUEST_ID_MAX) {
-    requestIdCounter = 1;
-  }
-
-  return requestIdCounter;
+  return nativeBinding.allocateRequestId();
 }
 
 function setupAbort(signal: AbortSignal | null | undefined, cancelNative: () => void): AbortHandler | null {
@@ -1427,6 +2376,30 @@ type SerializedBody = {
 };
 
 async function serializeBody(body?: BodyInit | null): Promise<SerializedBody> {
+  const serialized = serializeBodySync(body);
+  if (serialized) {
+    return serialized;
+  }
+
+  if (typeof Blob !== "undefined" && body instanceof Blob) {
+    const buffer = Buffer.from(await body.arrayBuffer());
+    return { body: buffer, ...(body.type ? { contentType: body.type } : {}) };
+  }
+
+  if (typeof FormData !== "undefined" && body instanceof FormData) {
+    const encoded = new globalThis.Response(body);
+    const contentType = encoded.headers.get("content-type") ?? undefined;
+    const buffer = Buffer.from(await encoded.arrayBuffer());
+    return { body: buffer, ...(contentType ? { contentType } : {}) };
+  }
+
+  throw new TypeError(
+    "Unsupported body type; expected string, Buffer, ArrayBuffer, ArrayBufferView, URLSearchParams, Blob, or FormData",
+  );
+}
+
+// Serialize the body types that need no await. Returns null for everything else.
+function serializeBodySync(body?: BodyInit | null): SerializedBody | null {
   if (body === null || body === undefined) {
     return {};
   }
@@ -1454,21 +2427,7 @@ async function serializeBody(body?: BodyInit | null): Promise<SerializedBody> {
     return { body: Buffer.from(body.buffer, body.byteOffset, body.byteLength) };
   }
 
-  if (typeof Blob !== "undefined" && body instanceof Blob) {
-    const buffer = Buffer.from(await body.arrayBuffer());
-    return { body: buffer, ...(body.type ? { contentType: body.type } : {}) };
-  }
-
-  if (typeof FormData !== "undefined" && body instanceof FormData) {
-    const encoded = new globalThis.Response(body);
-    const contentType = encoded.headers.get("content-type") ?? undefined;
-    const buffer = Buffer.from(await encoded.arrayBuffer());
-    return { body: buffer, ...(contentType ? { contentType } : {}) };
-  }
-
-  throw new TypeError(
-    "Unsupported body type; expected string, Buffer, ArrayBuffer, ArrayBufferView, URLSearchParams, Blob, or FormData",
-  );
+  return null;
 }
 
 function ensureMethod(method?: string): string {
@@ -1511,8 +2470,11 @@ function validateBrowserProfile(browser?: BrowserProfile | string): void {
     throw new RequestError("Browser profile must not be empty");
   }
 
-  if (!getProfileSet().has(browser)) {
-    throw new RequestError(`Invalid browser profile: ${browser}. Available profiles: ${getProfiles().join(", ")}`);
+  if (!getProfileSet().has(browser) && nativeBinding.resolveProfile(browser) === null) {
+    throw new RequestError(
+      `Invalid browser profile: ${browser}. Available profiles: ${getProfiles().join(", ")}, ` +
+        'or an alias such as "chrome", "safari:latest", or "firefox:esr"',
+    );
   }
 }
 
@@ -1554,6 +2516,20 @@ function validatePositiveNumber(value: number, label: string): void {
   }
 }
 
+function validatePatternList(patterns: string[], label: string): void {
+  if (!Array.isArray(patterns) || patterns.some((pattern) => typeof pattern !== "string" || pattern.length === 0)) {
+    throw new RequestError(`${label} must be an array of non-empty strings`);
+  }
+}
+
+const CONTENT_CODINGS: readonly ContentCoding[] = ["gzip", "deflate", "br", "zstd", "identity"];
+
+function validateContentCodings(codings: ContentCoding[]): void {
+  if (!Array.isArray(codings) || codings.some((coding) => !CONTENT_CODINGS.includes(coding))) {
+    throw new RequestError(`acceptEncoding must be an array of ${CONTENT_CODINGS.join(", ")}`);
+  }
+}
+
 function validateNonNegativeInteger(value: number, label: string): void {
   if (typeof value !== "number" || !Number.isFinite(value) || !Number.isInteger(value)) {
     throw new RequestError(`${label} must be an integer`);
@@ -1574,57 +2550,272 @@ function validatePositiveInteger(value: number, label: string): void {
   }
 }
 
-async function dispatchRequest(
-  options: NativeRequestOptions,
-  requestUrl: string,
-  signal?: AbortSignal | null,
-): Promise<Response> {
-  // Fast path when no abort signal is provided: avoid Promise.race/allocation overhead.
-  if (!signal) {
-    const requestId = generateRequestId();
-    let payload: NativeResponse;
+const PROXY_ROTATION_STRATEGIES: readonly ProxyRotationStrategy[] = ["stickyUntilError", "rotateEveryNRequests"];
 
-    try {
-      payload = (await nativeBinding.request(options, requestId, false)) as NativeResponse;
-    } catch (error) {
-      if (error instanceof RequestError) {
-        throw error;
-      }
-      throw new RequestError(String(error));
-    }
+function validateProxyPool(pool: ProxyPoolOptions, proxy: string | undefined): void {
+  if (proxy !== undefined) {
+    throw new RequestError("proxy and proxyPool cannot be combined");
+  }
+  if (typeof pool !== "object" || pool === null) {
+    throw new RequestError("proxyPool must be an object");
+  }
+  if (
+    !Array.isArray(pool.proxies) ||
+    pool.proxies.length === 0 ||
+    !pool.proxies.every((url) => typeof url === "string" && url.length > 0)
+  ) {
+    throw new RequestError("proxyPool.proxies must be a non-empty array of proxy URLs");
+  }
+  if (pool.strategy !== undefined && !PROXY_ROTATION_STRATEGIES.includes(pool.strategy)) {
+    throw new RequestError(`proxyPool.strategy must be one of: ${PROXY_ROTATION_STRATEGIES.join(", ")}`);
+  }
+  if (pool.requestsPerProxy !== undefined) {
+    validatePositiveInteger(pool.requestsPerProxy, "proxyPool.requestsPerProxy");
+  }
+}
 
-    return new Response(payload, requestUrl);
+function validateProxyNtlm(credentials: NtlmCredentials, options: { proxy?: string; proxyPool?: unknown }): void {
+  if (options.proxy === undefined) {
+    throw new RequestError("proxyNtlm requires a proxy");
+  }
+  if (options.proxyPool !== undefined) {
+    throw new RequestError("proxyNtlm cannot be combined with proxyPool");
   }
+  validateNtlmCredentials(credentials, "proxyNtlm");
+}
 
-  const requestId = generateRequestId();
-  const cancelNative = () => {
-    try {
-      nativeBinding.cancelRequest(requestId);
-    } catch {
-      // Cancellation is best-effort; ignore binding errors here.
+function validateNtlmCredentials(credentials: NtlmCredentials, label: string): void {
+  if (typeof credentials !== "object" || credentials === null) {
+    throw new RequestError(`${label} must be an object`);
+  }
+  if (typeof credentials.username !== "string" || credentials.username.length === 0) {
+    throw new RequestError(`${label}.username must be a non-empty string`);
+  }
+  if (typeof credentials.password !== "string") {
+    throw new RequestError(`${label}.password must be a string`);
+  }
+  for (const key of ["domain", "workstation"] as const) {
+    if (credentials[key] !== undefined && typeof credentials[key] !== "string") {
+      throw new RequestError(`${label}.${key} must be a string`);
     }
-  };
+  }
+}
 
-  // setupAbort throws if signal is already aborted and returns null only when signal is falsy
-  // (impossible here since we checked `!signal` above). Cast is safe; avoids non-null assertion lint.
-  const abortHandler = setupAbort(signal, cancelNative) as AbortHandler;
+function validateClientHints(hints: ClientHintsOptions): void {
+  if (typeof hints !== "object" || hints === null) {
+    throw new RequestError("clientHints must be an object");
+  }
+  if (hints.brands !== undefined) {
+    if (
+      !Array.isArray(hints.brands) ||
+      hints.brands.some(
+        (entry) =>
+          typeof entry !== "object" ||
+          entry === null ||
+          typeof entry.brand !== "string" ||
+          typeof entry.version !== "string",
+      )
+    ) {
+      throw new RequestError("clientHints.brands must be an array of { brand, version } strings");
+    }
+  }
+  for (const key of ["fullVersion", "platform", "platformVersion", "arch", "model"] as const) {
+    if (hints[key] !== undefined && typeof hints[key] !== "string") {
+      throw new RequestError(`clientHints.${key} must be a string`);
+    }
+  }
+  if (hints.mobile !== undefined && typeof hints.mobile !== "boolean") {
+    throw new RequestError("clientHints.mobile must be a boolean");
+  }
+}
 
-  const pending = Promise.race([nativeBinding.request(options, requestId, true), abortHandler.promise]);
+function validateConnectTo(rules: ConnectToRule[]): void {
+  if (!Array.isArray(rules)) {
+    throw new RequestError("connectTo must be an array");
+  }
+  for (const [index, rule] of rules.entries()) {
+    if (typeof rule !== "object" || rule === null) {
+      throw new RequestError(`connectTo[${index}] must be an object`);
+    }
+    if (typeof rule.fromHost !== "string" || rule.fromHost.length === 0) {
+      throw new RequestError(`connectTo[${index}].fromHost must be a non-empty string`);
+    }
+    if (rule.toHost !== undefined && (typeof rule.toHost !== "string" || rule.toHost.length === 0)) {
+      throw new RequestError(`connectTo[${index}].toHost must be a non-empty string`);
+    }
+    for (const key of ["fromPort", "toPort"] as const) {
+      const port = rule[key];
+      if (port !== undefined && (!Number.isInteger(port) || port < 1 || port > 65535)) {
+        throw new RequestError(`connectTo[${index}].${key} must be a port number`);
+      }
+    }
+    if (rule.toHost === undefined && rule.toPort === undefined) {
+      throw new RequestError(`connectTo[${index}] needs a toHost or a toPort`);
+    }
+  }
+}
+
+const LANGUAGE_TAG = /^[A-Za-z]{1,8}(-[A-Za-z0-9]{1,8})*$/;
+
+function normalizeLocale(locale: string | string[]): string[] {
+  const locales = typeof locale === "string" ? [locale] : locale;
+  if (
+    !Array.isArray(locales) ||
+    locales.length === 0 ||
+    locales.some((tag) => typeof tag !== "string" || !LANGUAGE_TAG.test(tag))
+  ) {
+    throw new RequestError("locale must be a language tag such as 'en-US', or a non-empty array of them");
+  }
+  return locales;
+}
+
+const REQUEST_MODES: readonly RequestMode[] = ["navigate", "xhr", "fetch", "image"];
+
+function validateRequestPriority(priority: RequestPriority): void {
+  if (typeof priority !== "object" || priority === null) {
+    throw new RequestError("priority must be an object");
+  }
+  if (!Number.isInteger(priority.urgency) || priority.urgency < 0 || priority.urgency > 7) {
+    throw new RequestError("priority.urgency must be an integer from 0 to 7");
+  }
+  if (priority.incremental !== undefined && typeof priority.incremental !== "boolean") {
+    throw new RequestError("priority.incremental must be a boolean");
+  }
+}
+
+interface CoalescedRequest {
+  promise: Promise<Response>;
+  waiters: number;
+  // Aborts the upstream request once every waiter has detached.
+  controller: AbortController;
+}
+
+// Upstream requests currently shared by `coalesce: true` callers, keyed by their native options.
+const coalescedRequests = new Map<string, CoalescedRequest>();
+
+function getCoalesceKey(options: NativeRequestOptions): string {
+  // Ephemeral sessions never carry state between calls, so their per-call ids must not split the key.
+  const sessionId = options.ephemeral ? undefined : options.sessionId;
+  return JSON.stringify({ ...options, sessionId });
+}
+
+function detachCoalesceWaiter(key: string, shared: CoalescedRequest): void {
+  shared.waiters -= 1;
+  if (shared.waiters > 0) {
+    return;
+  }
+
+  // Nobody is left to read the response: stop the request, or release a body that already arrived.
+  if (coalescedRequests.get(key) === shared) {
+    coalescedRequests.delete(key);
+  }
+  shared.controller.abort();
+  shared.promise.then(
+    (response) => response.body?.cancel().catch(() => {}),
+    () => {},
+  );
+}
+
+async function coalesceRequest(
+  key: string,
+  start: (signal: AbortSignal) => Promise<Response>,
+  signal: AbortSignal | null,
+): Promise<Response> {
+  if (signal?.aborted) {
+    throw createAbortError(signal.reason);
+  }
+
+  let entry = coalescedRequests.get(key);
+  if (!entry) {
+    const controller = new AbortController();
+    const created: CoalescedRequest = { promise: start(controller.signal), waiters: 0, controller };
+    const settle = () => {
+      if (coalescedRequests.get(key) === created) {
+        coalescedRequests.delete(key);
+      }
+    };
+    // Registered before any waiter awaits, so late callers never join a settled entry.
+    created.promise.then(settle, settle);
+    coalescedRequests.set(key, created);
+    entry = created;
+  }
+
+  const shared = entry;
+  shared.waiters += 1;
+
+  // Aborting only detaches this waiter; the shared upstream request keeps serving the others.
+  let detached = false;
+  const abortHandler = setupAbort(signal, () => {
+    detached = true;
+    detachCoalesceWaiter(key, shared);
+  });
+
+  let response: Response;
+
+  try {
+    response = abortHandler ? await Promise.race([shared.promise, abortHandler.promise]) : await shared.promise;
+  } finally {
+    abortHandler?.cleanup();
+  }
+
+  // An abort that lands after the response has already detached this waiter.
+  if (detached) {
+    throw createAbortError(signal?.reason);
+  }
+
+  // Every waiter but the last receives a clone; the last one takes the original response.
+  shared.waiters -= 1;
+  return shared.waiters === 0 ? response : response.clone();
+}
+
+async function dispatchRequest(
+  options: NativeRequestOptions,
+  requestUrl: string,
+  signal?: AbortSignal | null,
+): Promise<Response> {
+  // Fast path when no abort signal is provided: avoid Promise.race/allocation overhead.
+  // Grouped requests still register a native cancellation token so cancelGroup() can reach them.
+  // Nothing here cancels by ID, so the native side picks one.
+  if (!signal) {
+    let payload: NativeResponse;
+
+    try {
+      const cancellable = options.groupId !== undefined;
+      payload = (await nativeBinding.request(options, undefined, cancellable)) as NativeResponse;
+    } catch (error) {
+      throw toRequestError(error);
+    }
+
+    return new Response(payload, requestUrl);
+  }
+
+  const requestId = generateRequestId();
+  const cancelNative = () => {
+    try {
+      nativeBinding.cancelRequest(requestId, abortReasonText(signal.reason));
+    } catch {
+      // Cancellation is best-effort; ignore binding errors here.
+    }
+  };
+
+  // setupAbort throws if signal is already aborted and returns null only when signal is falsy
+  // (impossible here since we checked `!signal` above). Cast is safe; avoids non-null assertion lint.
+  const abortHandler = setupAbort(signal, cancelNative) as AbortHandler;
 
   let payload: NativeResponse;
 
   try {
-    payload = (await pending) as NativeResponse;
+    // Invalid options throw synchronously from the binding; keep them inside the try.
+    payload = (await Promise.race([
+      nativeBinding.request(options, requestId, true),
+      abortHandler.promise,
+    ])) as NativeResponse;
   } catch (error) {
     if (isAbortError(error)) {
       throw error;
     }
 
-    if (error instanceof RequestError) {
-      throw error;
-    }
-
-    throw new RequestError(String(error));
+    throw toRequestError(error);
   } finally {
     abortHandler.cleanup();
   }
@@ -1632,41 +2823,87 @@ async function dispatchRequest(
   return new Response(payload, requestUrl);
 }
 
-/**
- * Fetch-compatible entry point that adds browser impersonation controls.
- *
- * **Important:** The default fetch path is isolated and non-persistent by design.
- * Each call uses an isolated request context, so cookies are not shared across calls.
- * Connection and TLS reuse behavior is handled by the native layer.
- *
- * **Use {@link createSession} or {@link withSession} if you need:**
- * - Cookie persistence across requests
- * - Shared session defaults across requests
- * - A single session context for multi-step flows
- *
- * **Concurrency:** The core is unthrottled by design. Callers are expected to implement
- * their own concurrency control (e.g., p-limit) if needed. Built-in throttling would
- * reduce performance for high-throughput workloads.
- *
- * @param input - Request URL (string or URL) or a Request object
- * @param init - Fetch-compatible init options
- *
- * @example
- * ```typescript
- * // Isolated request (no state persistence)
- * const response = await fetch('https://example.com');
- *
- * // For persistent cookies and connection reuse, use a session:
- * await withSession(async (session) => {
- *   await session.fetch('https://example.com/login', { method: 'POST', body: loginData });
- *   await session.fetch('https://example.com/protected'); // Cookies from login are sent
- * });
- * ```
- */
-export async function fetch(input: string | URL | Request, init?: WreqRequestInit): Promise<Response> {
+// Callback counterpart of dispatchRequest that keeps promises out of the request path.
+function dispatchRequestCallback(
+  prepared: PreparedRequest,
+  cancellable: boolean | undefined,
+  callback: RequestCallback,
+): void {
+  const { url, config, requestOptions } = prepared;
+  const signal = config.signal ?? null;
+  // Only aborting `signal` cancels by ID; otherwise the native side picks one.
+  let requestId: number | undefined;
+  let settled = false;
+  let onAbort: (() => void) | undefined;
+
+  const finish = (error: Error | null, response?: Response) => {
+    if (settled) {
+      return;
+    }
+    settled = true;
+    if (onAbort) {
+      signal?.removeEventListener("abort", onAbort);
+    }
+    if (error) {
+      callback(error);
+    } else {
+      callback(null, response);
+    }
+  };
+
+  if (signal?.aborted) {
+    process.nextTick(finish, createAbortError(signal.reason));
+    return;
+  }
+
+  if (signal) {
+    const abortId = generateRequestId();
+    requestId = abortId;
+    onAbort = () => {
+      try {
+        nativeBinding.cancelRequest(abortId, abortReasonText(signal.reason));
+      } catch {
+        // Cancellation is best-effort; ignore binding errors here.
+      }
+      finish(createAbortError(signal.reason));
+    };
+    signal.addEventListener("abort", onAbort, { once: true });
+  }
+
+  try {
+    nativeBinding.requestCallback(
+      requestOptions,
+      requestId,
+      cancellable ?? (signal !== null || requestOptions.groupId !== undefined),
+      (error, payload) => {
+        if (error) {
+          finish(toRequestError(error));
+        } else {
+          finish(null, new Response(payload as NativeResponse, url));
+        }
+      },
+    );
+  } catch (error) {
+    // Invalid options throw synchronously from the binding; report them like any other failure.
+    process.nextTick(finish, toRequestError(error));
+  }
+}
+
+interface PreparedRequest {
+  url: string;
+  method: string;
+  body: Buffer | undefined;
+  config: WreqRequestInit;
+  requestOptions: NativeRequestOptions;
+}
+
+// Validate fetch() arguments and resolve them into the options passed to the native layer.
+async function prepareRequest(input: string | URL | Request, init?: WreqRequestInit): Promise<PreparedRequest> {
   const resolved = await resolveFetchArgs(input, init);
-  const url = resolved.url;
-  const config = resolved.init;
+  return buildRequest(resolved.url, resolved.init, await serializeBody(resolved.init.body ?? null));
+}
+
+function buildRequest(url: string, config: WreqRequestInit, serializedBody: SerializedBody): PreparedRequest {
   const sessionContext = resolveSessionContext(config);
   const sessionDefaults = sessionContext.defaults;
 
@@ -1677,7 +2914,6 @@ export async function fetch(input: string | URL | Request, init?: WreqRequestIni
   }
 
   const method = ensureMethod(config.method);
-  const serializedBody = await serializeBody(config.body ?? null);
   const body = serializedBody.body;
 
   ensureBodyAllowed(method, body);
@@ -1693,216 +2929,1679 @@ export async function fetch(input: string | URL | Request, init?: WreqRequestIni
   }
 
   const transport = resolveTransportContext(config, sessionDefaults);
-  const timeout = config.timeout ?? sessionDefaults?.timeout ?? DEFAULT_REQUEST_TIMEOUT_MS;
+  const timeout = config.timeout ?? sessionDefaults?.timeout;
 
   const requestOptions: NativeRequestOptions = {
     url,
     method,
     sessionId: sessionContext.sessionId,
     ephemeral: sessionContext.dropAfterRequest,
+    compactHeaders: true,
   };
 
   if (body !== undefined) {
     requestOptions.body = body;
   }
 
+  if (transport.proxy !== undefined) {
+    requestOptions.proxy = transport.proxy;
+  }
   if (transport.transportId) {
     requestOptions.transportId = transport.transportId;
   } else {
-    requestOptions.browser = transport.browser ?? DEFAULT_BROWSER;
-    requestOptions.os = transport.os ?? DEFAULT_OS;
-    if (transport.proxy !== undefined) {
-      requestOptions.proxy = transport.proxy;
+    if (transport.browser !== undefined) {
+      requestOptions.browser = transport.browser;
+    }
+    if (transport.os !== undefined) {
+      requestOptions.os = transport.os;
     }
     if (transport.insecure !== undefined) {
       requestOptions.insecure = transport.insecure;
     }
   }
 
-  requestOptions.timeout = timeout;
+  if (timeout !== undefined) {
+    requestOptions.timeout = timeout;
+  }
+  if (config.writeTimeout !== undefined) {
+    validatePositiveNumber(config.writeTimeout, "writeTimeout");
+    requestOptions.writeTimeout = config.writeTimeout;
+  }
+  if (config.totalDeadline !== undefined) {
+    validatePositiveNumber(config.totalDeadline, "totalDeadline");
+    requestOptions.totalDeadline = config.totalDeadline;
+  }
   if (config.redirect !== undefined) {
     requestOptions.redirect = config.redirect;
   }
+  const blockInsecureRedirects = config.blockInsecureRedirects ?? sessionDefaults?.blockInsecureRedirects;
+  if (blockInsecureRedirects !== undefined) {
+    requestOptions.blockInsecureRedirects = blockInsecureRedirects;
+  }
+  if (config.strictProfiles !== undefined) {
+    requestOptions.strictProfiles = config.strictProfiles;
+  }
+  if (config.stripOnCrossOrigin !== undefined) {
+    validatePatternList(config.stripOnCrossOrigin, "stripOnCrossOrigin");
+    requestOptions.stripOnCrossOrigin = config.stripOnCrossOrigin.map((name) => name.toLowerCase());
+  }
+  if (typeof config.followMetaRefresh === "number") {
+    validateNonNegativeInteger(config.followMetaRefresh, "followMetaRefresh");
+    requestOptions.metaRefreshMaxDelay = config.followMetaRefresh;
+  } else if (config.followMetaRefresh === true) {
+    requestOptions.metaRefreshMaxDelay = DEFAULT_META_REFRESH_MAX_DELAY;
+  }
+  if (config.ntlm !== undefined) {
+    validateNtlmCredentials(config.ntlm, "ntlm");
+  }
+  const ntlm = config.ntlm ?? sessionDefaults?.ntlm;
+  if (ntlm !== undefined) {
+    requestOptions.ntlm = ntlm;
+  }
+  if (config.clientHints !== undefined) {
+    validateClientHints(config.clientHints);
+    requestOptions.clientHints = config.clientHints;
+  }
+  if (config.connectTo !== undefined) {
+    validateConnectTo(config.connectTo);
+    if (config.connectTo.length > 0) {
+      requestOptions.connectTo = config.connectTo;
+    }
+  }
   if (config.disableDefaultHeaders !== undefined) {
     requestOptions.disableDefaultHeaders = config.disableDefaultHeaders;
   }
+  if (config.removeHeaders !== undefined) {
+    validatePatternList(config.removeHeaders, "removeHeaders");
+    if (config.removeHeaders.length > 0) {
+      requestOptions.removeHeaders = config.removeHeaders.map((name) => name.toLowerCase());
+    }
+  }
+  if (config.disableCookies !== undefined) {
+    requestOptions.disableCookies = config.disableCookies;
+  }
+  if (config.acceptEncoding !== undefined) {
+    validateContentCodings(config.acceptEncoding);
+    requestOptions.acceptEncoding = config.acceptEncoding;
+  }
+  if (config.decodeBody !== undefined) {
+    requestOptions.decodeBody = config.decodeBody;
+  }
+  if (config.priority !== undefined) {
+    validateRequestPriority(config.priority);
+    requestOptions.priority = config.priority;
+  }
+  if (config.mode !== undefined) {
+    if (!REQUEST_MODES.includes(config.mode)) {
+      throw new RequestError(`mode must be one of ${REQUEST_MODES.join(", ")}`);
+    }
+    requestOptions.mode = config.mode;
+  }
+  if (config.headerFormat !== undefined) {
+    if (config.headerFormat !== "tuples" && config.headerFormat !== "object") {
+      throw new RequestError('headerFormat must be "tuples" or "object"');
+    }
+    if (config.headerFormat === "object") {
+      requestOptions.headerFormat = "object";
+    }
+  }
+  if (config.minimalResponse !== undefined) {
+    requestOptions.minimalResponse = config.minimalResponse;
+  }
+  if (config.beforeSend !== undefined) {
+    if (typeof config.beforeSend !== "function") {
+      throw new RequestError("beforeSend must be a function");
+    }
+    requestOptions.onBeforeSend = beforeSendHook(config.beforeSend);
+  }
+  const locale = config.locale !== undefined ? normalizeLocale(config.locale) : sessionDefaults?.locale;
+  if (locale !== undefined) {
+    requestOptions.locale = locale;
+  }
+  if (config.originPooling !== undefined) {
+    requestOptions.originPooling = config.originPooling;
+  }
+  if (config.groupId !== undefined) {
+    if (typeof config.groupId !== "string" || config.groupId.length === 0) {
+      throw new RequestError("groupId must be a non-empty string");
+    }
+    requestOptions.groupId = config.groupId;
+  }
+  if (config.maxDownloadBytesPerSec !== undefined) {
+    validatePositiveNumber(config.maxDownloadBytesPerSec, "maxDownloadBytesPerSec");
+    requestOptions.maxDownloadBytesPerSec = config.maxDownloadBytesPerSec;
+  }
+  if (config.maxUploadBytesPerSec !== undefined) {
+    validatePositiveNumber(config.maxUploadBytesPerSec, "maxUploadBytesPerSec");
+    requestOptions.maxUploadBytesPerSec = config.maxUploadBytesPerSec;
+  }
+  if (config.bodyDigest !== undefined) {
+    if (config.bodyDigest !== "sha256" && config.bodyDigest !== "md5") {
+      throw new RequestError("bodyDigest must be 'sha256' or 'md5'");
+    }
+    requestOptions.bodyDigest = config.bodyDigest;
+  }
+
+  const trace = resolveTraceContext(config.trace, method, url);
+  if (trace !== undefined) {
+    requestOptions.trace = trace;
+  }
 
   if (headerTuples && headerTuples.length > 0) {
     requestOptions.headers = headerTuples;
+    const appendHeaders = headerTuples.filter(appendsToDefaults).map(([name]) => name);
+    if (appendHeaders.length > 0) {
+      requestOptions.appendHeaders = appendHeaders;
+    }
   }
 
-  return dispatchRequest(requestOptions, url, config.signal ?? null);
+  return { url, method, body, config, requestOptions };
 }
 
-export async function createTransport(options?: CreateTransportOptions): Promise<Transport> {
-  const browser = options?.browser ?? DEFAULT_BROWSER;
-  const os = options?.os ?? DEFAULT_OS;
-
-  validateBrowserProfile(browser);
-  validateOperatingSystem(os);
+let traceProvider: TraceProvider | null = null;
 
-  if (options?.poolIdleTimeout !== undefined) {
-    validatePositiveNumber(options.poolIdleTimeout, "poolIdleTimeout");
-  }
-  if (options?.poolMaxIdlePerHost !== undefined) {
-    validateNonNegativeInteger(options.poolMaxIdlePerHost, "poolMaxIdlePerHost");
+function resolveTraceContext(
+  trace: WreqRequestInit["trace"],
+  method: string,
+  url: string,
+): TraceContext | undefined {
+  if (trace === false) {
+    return undefined;
   }
-  if (options?.poolMaxSize !== undefined) {
-    validatePositiveInteger(options.poolMaxSize, "poolMaxSize");
+
+  const context = trace ?? traceProvider?.getContext?.({ method, url });
+  if (context === undefined) {
+    return undefined;
   }
-  if (options?.connectTimeout !== undefined) {
-    validatePositiveNumber(options.connectTimeout, "connectTimeout");
+  if (typeof context !== "object" || context === null || typeof context.traceparent !== "string") {
+    throw new RequestError("trace must be an object with a traceparent string");
   }
-  if (options?.readTimeout !== undefined) {
-    validatePositiveNumber(options.readTimeout, "readTimeout");
+  if (context.tracestate !== undefined && typeof context.tracestate !== "string") {
+    throw new RequestError("tracestate must be a string");
   }
 
-  try {
-    const id = nativeBinding.createTransport({
-      browser,
-      os,
-      ...(options?.proxy !== undefined && { proxy: options.proxy }),
-      ...(options?.insecure !== undefined && { insecure: options.insecure }),
-      ...(options?.poolIdleTimeout !== undefined && { poolIdleTimeout: options.poolIdleTimeout }),
-      ...(options?.poolMaxIdlePerHost !== undefined && { poolMaxIdlePerHost: options.poolMaxIdlePerHost }),
-      ...(options?.poolMaxSize !== undefined && { poolMaxSize: options.poolMaxSize }),
-      ...(options?.connectTimeout !== undefined && { connectTimeout: options.connectTimeout }),
-      ...(options?.readTimeout !== undefined && { readTimeout: options.readTimeout }),
-    });
-
-    return new Transport(id);
-  } catch (error) {
-    throw new RequestError(String(error));
-  }
+  return context.tracestate === undefined
+    ? { traceparent: context.traceparent }
+    : { traceparent: context.traceparent, tracestate: context.tracestate };
 }
 
-export async function createSession(options?: CreateSessionOptions): Promise<Session> {
-  const { sessionId, defaults } = normalizeSessionOptions(options);
+function now(): number {
+  return performance.timeOrigin + performance.now();
+}
 
-  validateBrowserProfile(defaults.browser);
-  validateOperatingSystem(defaults.os);
+// Tracing hooks observe requests; a throwing hook must not fail the request it observes.
+function callTraceHook(hook: () => void): void {
+  try {
+    hook();
+  } catch {
+    // Ignore hook errors.
+  }
+}
 
-  let createdId: string;
-  let transportId: string;
+async function dispatchTraced(
+  context: TraceContext,
+  method: string,
+  url: string,
+  dispatch: () => Promise<Response>,
+): Promise<Response> {
+  const provider = traceProvider;
+  const startTime = now();
+  callTraceHook(() => provider?.onSpanStart?.({ context, method, url, startTime }));
 
   try {
-    transportId = nativeBinding.createTransport({
-      browser: defaults.browser,
-      os: defaults.os,
-      ...(defaults.proxy !== undefined && { proxy: defaults.proxy }),
-      ...(defaults.insecure !== undefined && { insecure: defaults.insecure }),
-    });
+    const response = await dispatch();
+    const span: TraceSpanEnd = {
+      context,
+      method,
+      url,
+      startTime: response.timing?.startTime ?? startTime,
+      endTime: response.timing?.responseStartTime ?? now(),
+      status: response.status,
+      redirects: response.timing?.redirects ?? [],
+    };
+    callTraceHook(() => provider?.onSpanEnd?.(span));
+    return response;
   } catch (error) {
-    throw new RequestError(String(error));
+    const span: TraceSpanEnd = {
+      context,
+      method,
+      url,
+      startTime,
+      endTime: now(),
+      error: error instanceof Error ? error : new Error(String(error)),
+      redirects: [],
+    };
+    callTraceHook(() => provider?.onSpanEnd?.(span));
+    throw error;
   }
+}
+
+/**
+ * Fetch-compatible entry point that adds browser impersonation controls.
+ *
+ * **Important:** The default fetch path is isolated and non-persistent by design.
+ * Each call uses an isolated request context, so cookies are not shared across calls.
+ * Connection and TLS reuse behavior is handled by the native layer.
+ *
+ * **Use {@link createSession} or {@link withSession} if you need:**
+ * - Cookie persistence across requests
+ * - Shared session defaults across requests
+ * - A single session context for multi-step flows
+ *
+ * **Concurrency:** The core is unthrottled by design. Callers are expected to implement
+ * their own concurrency control (e.g., p-limit) if needed. Built-in throttling would
+ * reduce performance for high-throughput workloads.
+ *
+ * @param input - Request URL (string or URL) or a Request object
+ * @param init - Fetch-compatible init options
+ *
+ * @example
+ * ```typescript
+ * // Isolated request (no state persistence)
+ * const response = await fetch('https://example.com');
+ *
+ * // For persistent cookies and connection reuse, use a session:
+ * await withSession(async (session) => {
+ *   await session.fetch('https://example.com/login', { method: 'POST', body: loginData });
+ *   await session.fetch('https://example.com/protected'); // Cookies from login are sent
+ * });
+ * ```
+ */
+export async function fetch(input: string | URL | Request, init?: WreqRequestInit): Promise<Response> {
+  const { url, method, body, config, requestOptions } = await prepareRequest(input, init);
+
+  const dispatch = () => {
+    // A beforeSend hook may sign each request differently, so those are never shared.
+    const shareable = body === undefined && requestOptions.onBeforeSend === undefined;
+    if (config.coalesce === true && (method === "GET" || method === "HEAD") && shareable) {
+      return coalesceRequest(
+        getCoalesceKey(requestOptions),
+        (upstream) => dispatchRequest(requestOptions, url, upstream),
+        config.signal ?? null,
+      );
+    }
+
+    return dispatchRequest(requestOptions, url, config.signal ?? null);
+  };
+
+  if (requestOptions.trace === undefined) {
+    return dispatch();
+  }
+
+  return dispatchTraced(requestOptions.trace, method, url, dispatch);
+}
+
+/**
+ * Callback-based variant of {@link fetch} for high request rates. The result is delivered
+ * straight from the native layer to `callback`, without creating promises along the way.
+ *
+ * The callback is always called asynchronously, exactly once. Option errors are passed to it
+ * rather than thrown. `coalesce` is ignored, and trace provider span hooks are not called.
+ * Set `cancellable: false` to also skip registering the request for cancellation.
+ *
+ * @example
+ * ```typescript
+ * import { requestCallback } from 'wreq-js';
+ *
+ * requestCallback({ url: 'https://example.com/api', cancellable: false }, (error, response) => {
+ *   if (error) {
+ *     console.error(error);
+ *     return;
+ *   }
+ *   console.log(response?.status);
+ * });
+ * ```
+ */
+export function requestCallback(options: RequestCallbackOptions, callback: RequestCallback): void {
+  if (typeof callback !== "function") {
+    throw new TypeError("requestCallback() requires a callback function");
+  }
+
+  const { url, cancellable, ...init } = options;
+  const fail = (error: unknown) => {
+    process.nextTick(callback, error instanceof Error ? error : toRequestError(error));
+  };
+  const start = (serializedBody: SerializedBody) => {
+    let prepared: PreparedRequest;
+    try {
+      prepared = buildRequest(coerceUrlInput(url), init, serializedBody);
+    } catch (error) {
+      fail(error);
+      return;
+    }
+    dispatchRequestCallback(prepared, cancellable, callback);
+  };
+
+  // Only Blob and FormData bodies need an await to serialize.
+  const serializedBody = serializeBodySync(init.body);
+  if (serializedBody) {
+    start(serializedBody);
+  } else {
+    serializeBody(init.body).then(start, fail);
+  }
+}
+
+/**
+ * Register hooks that connect requests to a distributed tracer such as OpenTelemetry.
+ *
+ * `getContext` supplies the `traceparent`/`tracestate` to inject into each request that does not
+ * set `trace` itself. The headers are added natively, so every redirect hop carries them.
+ * `onSpanStart` and `onSpanEnd` report each traced request with native timing and the redirects
+ * it followed. Pass `null` to remove the provider.
+ *
+ * @example
+ * ```typescript
+ * import { setTraceProvider } from 'wreq-js';
+ *
+ * setTraceProvider({
+ *   getContext: () => ({ traceparent: currentTraceparent() }),
+ *   onSpanEnd: (span) => console.log(span.method, span.url, span.endTime - span.startTime),
+ * });
+ * ```
+ */
+export function setTraceProvider(provider: TraceProvider | null): void {
+  if (provider !== null && typeof provider !== "object") {
+    throw new RequestError("Trace provider must be an object or null");
+  }
+
+  traceProvider = provider;
+}
+
+/**
+ * Render the request `fetch(input, init)` would send as a copy-pasteable curl command.
+ *
+ * The output includes the final URL (after HSTS upgrades), the browser profile's default headers
+ * merged with your own, session cookies, the body, and the proxy. curl cannot reproduce the
+ * TLS and HTTP/2 fingerprint, so servers that check it may still respond differently.
+ *
+ * @example
+ * ```typescript
+ * import { toCurl } from 'wreq-js';
+ *
+ * console.log(await toCurl('https://example.com/api', { method: 'POST', body: '{}' }));
+ * ```
+ */
+export async function toCurl(input: string | URL | Request, init?: WreqRequestInit): Promise<string> {
+  const { requestOptions } = await prepareRequest(input, init);
+
+  try {
+    return nativeBinding.toCurl(requestOptions);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+/**
+ * Parse a curl command (e.g. from the browser's "Copy as cURL") into options for {@link request}.
+ *
+ * Supports `-X`, `-H`, `-d`/`--data`/`--data-raw`/`--data-binary`, `--json`, `-b`, `-A`, `-e`,
+ * `-x`/`--proxy`, `-k`/`--insecure`, `-L`, `-I`, `-G`, and `-m`. Other flags are ignored.
+ * Like curl, redirects are only followed when `-L` is present.
+ *
+ * @example
+ * ```typescript
+ * import { fromCurl, request } from 'wreq-js';
+ *
+ * const response = await request(fromCurl(`curl 'https://example.com/api' -H 'accept: application/json'`));
+ * ```
+ */
+export function fromCurl(command: string): RequestOptions {
+  if (typeof command !== "string" || command.trim().length === 0) {
+    throw new RequestError("curl command must be a non-empty string");
+  }
+
+  try {
+    return nativeBinding.fromCurl(command);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+const DEFAULT_MIRROR_IGNORED_HEADERS = ["date", "age"];
+
+function mirrorTargetLabel(target: MirrorTarget, index: number): string {
+  if (target.label !== undefined) {
+    return target.label;
+  }
+  if (target.proxy !== undefined) {
+    try {
+      const proxy = new URL(target.proxy);
+      proxy.password = "";
+      return proxy.href.replace(/\/$/, "");
+    } catch {
+      return target.proxy;
+    }
+  }
+  return `target ${index}`;
+}
+
+/**
+ * Send the same request concurrently through several proxies or transports and compare what
+ * came back: status, headers, and a SHA-256 of each body. Useful for spotting geo-blocks and
+ * exits that are served different content.
+ *
+ * Failed requests are reported in their entry's `error` instead of rejecting the call.
+ *
+ * @example
+ * ```typescript
+ * import { mirror } from 'wreq-js';
+ *
+ * const { responses, diff } = await mirror('https://example.com/', {
+ *   targets: [{ proxy: 'http://us.proxy.local:8080' }, { proxy: 'http://de.proxy.local:8080' }],
+ * });
+ * if (diff.body) console.log(responses.map((r) => [r.label, r.status, r.bodyDigest]));
+ * ```
+ */
+export async function mirror(input: string | URL, init: MirrorInit): Promise<MirrorResult> {
+  const { targets, ignoreHeaders = DEFAULT_MIRROR_IGNORED_HEADERS, ...requestInit } = init;
+  if (!Array.isArray(targets) || targets.length === 0) {
+    throw new RequestError("targets must be a non-empty array");
+  }
+  for (const target of targets) {
+    if (target?.proxy !== undefined && target.transport !== undefined) {
+      throw new RequestError("A mirror target takes either proxy or transport, not both");
+    }
+  }
+
+  const responses = await Promise.all(
+    targets.map(async (target, index): Promise<MirrorResponse> => {
+      const label = mirrorTargetLabel(target, index);
+      const started = performance.now();
+      try {
+        const response = await fetch(input, {
+          ...requestInit,
+          ...(target.proxy !== undefined && { proxy: target.proxy }),
+          ...(target.transport !== undefined && { transport: target.transport }),
+          bodyDigest: "sha256",
+        });
+        const body = await response.arrayBuffer();
+        return {
+          label,
+          status: response.status,
+          headers: Object.fromEntries([...response.headers].map(([name, value]) => [name.toLowerCase(), value])),
+          bodyDigest: response.bodyDigest,
+          bodySize: body.byteLength,
+          duration: performance.now() - started,
+          error: null,
+        };
+      } catch (error) {
+        return {
+          label,
+          status: null,
+          headers: {},
+          bodyDigest: null,
+          bodySize: null,
+          duration: performance.now() - started,
+          error: toRequestError(error),
+        };
+      }
+    }),
+  );
+
+  const ignored = new Set(ignoreHeaders.map((name) => name.toLowerCase()));
+  const headerNames = new Set(responses.flatMap((response) => Object.keys(response.headers)));
+  const differs = (values: unknown[]) => new Set(values).size > 1;
+
+  return {
+    responses,
+    diff: {
+      status: differs(responses.map((response) => response.status)),
+      headers: [...headerNames]
+        .filter((name) => !ignored.has(name) && differs(responses.map((response) => response.headers[name])))
+        .sort(),
+      body: differs(responses.map((response) => response.bodyDigest)),
+    },
+  };
+}
+
+function validateRetryStatuses(statuses: number[]): void {
+  if (
+    !Array.isArray(statuses) ||
+    statuses.some((status) => !Number.isInteger(status) || status < 100 || status > 599)
+  ) {
+    throw new RequestError("retryOn must be an array of HTTP status codes");
+  }
+}
+
+/**
+ * Fetch a list of URLs with shared options. The native layer schedules the requests, at most
+ * `concurrency` at a time, retries transient failures, and hands each final result to
+ * `onResult` as soon as it is ready, so one slow URL never holds back the others.
+ *
+ * Failed requests are reported through `onResult` instead of rejecting the call. Read or cancel
+ * each response body there. Aborting `signal` fails the requests still pending, then rejects
+ * with an `AbortError`. If `onResult` or `onRetry` throws, the remaining requests are cancelled
+ * without being reported and the call rejects with that error.
+ *
+ * @example
+ * ```typescript
+ * import { fetchAll } from 'wreq-js';
+ *
+ * const summary = await fetchAll(urls, {
+ *   browser: 'chrome_142',
+ *   concurrency: 16,
+ *   retries: 2,
+ *   onResult: ({ url, response, error }) => {
+ *     if (response) {
+ *       response.text().then((html) => pages.set(url, html));
+ *     } else {
+ *       console.error(url, error?.code);
+ *     }
+ *   },
+ * });
+ * ```
+ */
+export async function fetchAll(inputs: Iterable<string | URL>, init: FetchAllInit): Promise<FetchAllSummary> {
+  const { concurrency, retries, retryDelay, retryOn, onResult, onRetry, ...requestInit } = init ?? {};
+  if (typeof onResult !== "function") {
+    throw new TypeError("fetchAll() requires an onResult callback");
+  }
+  if (onRetry !== undefined && typeof onRetry !== "function") {
+    throw new TypeError("onRetry must be a function");
+  }
+  if (concurrency !== undefined) {
+    validatePositiveInteger(concurrency, "concurrency");
+  }
+  if (retries !== undefined) {
+    validateNonNegativeInteger(retries, "retries");
+  }
+  if (retryDelay !== undefined) {
+    validateNonNegativeInteger(retryDelay, "retryDelay");
+  }
+  if (retryOn !== undefined) {
+    validateRetryStatuses(retryOn);
+  }
+
+  const urls = Array.from(inputs, coerceUrlInput);
+  const serializedBody = await serializeBody(requestInit.body ?? null);
+  const requests = urls.map((url) => buildRequest(url, requestInit, serializedBody).requestOptions);
+  const summary: FetchAllSummary = { succeeded: 0, failed: 0 };
+  if (requests.length === 0) {
+    return summary;
+  }
+
+  const signal = requestInit.signal ?? null;
+  if (signal?.aborted) {
+    throw createAbortError(signal.reason);
+  }
+
+  const requestId = generateRequestId();
+  const cancel = (reason?: string) => {
+    try {
+      nativeBinding.cancelRequest(requestId, reason);
+    } catch {
+      // Cancellation is best-effort; ignore binding errors here.
+    }
+  };
+  const onAbort = () => cancel(abortReasonText(signal?.reason));
+  signal?.addEventListener("abort", onAbort, { once: true });
+
+  let callbackFailed = false;
+  let callbackError: unknown;
+  const failCallback = (thrown: unknown) => {
+    callbackFailed = true;
+    callbackError = thrown;
+    cancel();
+  };
+  const options: NativeFetchSetOptions = {
+    ...(concurrency !== undefined && { concurrency }),
+    ...(retries !== undefined && { retries }),
+    ...(retryDelay !== undefined && { retryDelay }),
+    ...(retryOn !== undefined && { retryStatuses: retryOn }),
+  };
+
+  const handleResult = (index: number, attempts: number, error: Error | null, payload?: NativeResponse) => {
+    if (error) {
+      summary.failed += 1;
+    } else {
+      summary.succeeded += 1;
+    }
+    if (callbackFailed) {
+      if (payload && payload.bodyHandle !== null) {
+        nativeBinding.cancelBody(payload.bodyHandle);
+      }
+      return;
+    }
+
+    const url = urls[index] as string;
+    try {
+      onResult(
+        error
+          ? { index, url, attempts, response: null, error: toRequestError(error) }
+          : { index, url, attempts, response: new Response(payload as NativeResponse, url), error: null },
+      );
+    } catch (thrown) {
+      failCallback(thrown);
+    }
+  };
+  const handleRetry =
+    onRetry &&
+    ((index: number, attempt: number, error: Error, delayMs: number, status: number | null) => {
+      if (callbackFailed) {
+        return false;
+      }
+      try {
+        const target: FetchAllRetryTarget = { index, url: urls[index] as string, status };
+        return onRetry(attempt, toRequestError(error), delayMs, target) !== false;
+      } catch (thrown) {
+        failCallback(thrown);
+        return false;
+      }
+    });
+
+  try {
+    await nativeBinding.fetchSet(requests, options, requestId, handleResult, handleRetry);
+  } finally {
+    signal?.removeEventListener("abort", onAbort);
+  }
+
+  if (callbackFailed) {
+    throw callbackError;
+  }
+  if (signal?.aborted) {
+    throw createAbortError(signal.reason);
+  }
+  return summary;
+}
+
+export async function createTransport(options?: CreateTransportOptions): Promise<Transport> {
+  const browser = options?.browser ?? DEFAULT_BROWSER;
+  const os = options?.os ?? DEFAULT_OS;
+
+  validateBrowserProfile(browser);
+  validateOperatingSystem(os);
+
+  if (options?.poolIdleTimeout !== undefined) {
+    validatePositiveNumber(options.poolIdleTimeout, "poolIdleTimeout");
+  }
+  if (options?.poolMaxIdlePerHost !== undefined) {
+    validateNonNegativeInteger(options.poolMaxIdlePerHost, "poolMaxIdlePerHost");
+  }
+  if (options?.poolMaxSize !== undefined) {
+    validatePositiveInteger(options.poolMaxSize, "poolMaxSize");
+  }
+  if (options?.maxConnectionAge !== undefined) {
+    validatePositiveNumber(options.maxConnectionAge, "maxConnectionAge");
+  }
+  if (options?.connectTimeout !== undefined) {
+    validatePositiveNumber(options.connectTimeout, "connectTimeout");
+  }
+  if (options?.readTimeout !== undefined) {
+    validatePositiveNumber(options.readTimeout, "readTimeout");
+  }
+  if (options?.maxDownloadBytesPerSec !== undefined) {
+    validatePositiveNumber(options.maxDownloadBytesPerSec, "maxDownloadBytesPerSec");
+  }
+  if (options?.maxUploadBytesPerSec !== undefined) {
+    validatePositiveNumber(options.maxUploadBytesPerSec, "maxUploadBytesPerSec");
+  }
+  if (options?.keylogPath !== undefined) {
+    if (typeof options.keylogPath !== "string" || options.keylogPath.length === 0) {
+      throw new RequestError("keylogPath must be a non-empty string");
+    }
+  }
+  if (options?.allowlist !== undefined) {
+    validatePatternList(options.allowlist, "allowlist");
+  }
+  if (options?.denylist !== undefined) {
+    validatePatternList(options.denylist, "denylist");
+  }
+  if (options?.acceptEncoding !== undefined) {
+    validateContentCodings(options.acceptEncoding);
+  }
+  if (options?.maxResponseHeaderBytes !== undefined) {
+    validatePositiveInteger(options.maxResponseHeaderBytes, "maxResponseHeaderBytes");
+  }
+  if (options?.maxHeaderCount !== undefined) {
+    validatePositiveInteger(options.maxHeaderCount, "maxHeaderCount");
+  }
+  if (options?.initialStreamWindowSize !== undefined) {
+    validatePositiveInteger(options.initialStreamWindowSize, "initialStreamWindowSize");
+  }
+  if (options?.initialConnectionWindowSize !== undefined) {
+    validatePositiveInteger(options.initialConnectionWindowSize, "initialConnectionWindowSize");
+  }
+  if (options?.maxFrameSize !== undefined) {
+    validatePositiveInteger(options.maxFrameSize, "maxFrameSize");
+  }
+  if (options?.tcpKeepAlive !== undefined && typeof options.tcpKeepAlive !== "boolean") {
+    if (typeof options.tcpKeepAlive !== "object" || options.tcpKeepAlive === null) {
+      throw new RequestError("tcpKeepAlive must be a boolean or an options object");
+    }
+    const { idle, interval, retries } = options.tcpKeepAlive;
+    if (idle !== undefined) {
+      validatePositiveInteger(idle, "tcpKeepAlive.idle");
+    }
+    if (interval !== undefined) {
+      validatePositiveInteger(interval, "tcpKeepAlive.interval");
+    }
+    if (retries !== undefined) {
+      validatePositiveInteger(retries, "tcpKeepAlive.retries");
+    }
+  }
+  if (options?.sendBufferSize !== undefined) {
+    validatePositiveInteger(options.sendBufferSize, "sendBufferSize");
+  }
+  if (options?.recvBufferSize !== undefined) {
+    validatePositiveInteger(options.recvBufferSize, "recvBufferSize");
+  }
+  if (options?.proxyPool !== undefined) {
+    validateProxyPool(options.proxyPool, options.proxy);
+  }
+  if (options?.proxyNtlm !== undefined) {
+    validateProxyNtlm(options.proxyNtlm, options);
+  }
+  if (options?.clientHints !== undefined) {
+    validateClientHints(options.clientHints);
+  }
+
+  try {
+    const { id, guard } = nativeBinding.createTransport({
+      browser,
+      os,
+      ...(options?.proxy !== undefined && { proxy: options.proxy }),
+      ...(options?.proxyPool !== undefined && { proxyPool: options.proxyPool }),
+      ...(options?.proxyNtlm !== undefined && { proxyNtlm: options.proxyNtlm }),
+      ...(options?.clientHints !== undefined && { clientHints: options.clientHints }),
+      ...(options?.insecure !== undefined && { insecure: options.insecure }),
+      ...(options?.poolIdleTimeout !== undefined && { poolIdleTimeout: options.poolIdleTimeout }),
+      ...(options?.poolMaxIdlePerHost !== undefined && { poolMaxIdlePerHost: options.poolMaxIdlePerHost }),
+      ...(options?.poolMaxSize !== undefined && { poolMaxSize: options.poolMaxSize }),
+      ...(options?.maxConnectionAge !== undefined && { maxConnectionAge: options.maxConnectionAge }),
+      ...(options?.connectTimeout !== undefined && { connectTimeout: options.connectTimeout }),
+      ...(options?.readTimeout !== undefined && { readTimeout: options.readTimeout }),
+      ...(options?.tlsSessionTickets !== undefined && { tlsSessionTickets: options.tlsSessionTickets }),
+      ...(options?.tlsSessionResumption !== undefined && { tlsSessionResumption: options.tlsSessionResumption }),
+      ...(options?.keylogPath !== undefined && { keylogPath: options.keylogPath }),
+      ...(options?.maxDownloadBytesPerSec !== undefined && { maxDownloadBytesPerSec: options.maxDownloadBytesPerSec }),
+      ...(options?.maxUploadBytesPerSec !== undefined && { maxUploadBytesPerSec: options.maxUploadBytesPerSec }),
+      ...(options?.allowlist !== undefined && { allowlist: options.allowlist }),
+      ...(options?.denylist !== undefined && { denylist: options.denylist }),
+      ...(options?.acceptEncoding !== undefined && { acceptEncoding: options.acceptEncoding }),
+      ...(options?.maxResponseHeaderBytes !== undefined && { maxResponseHeaderBytes: options.maxResponseHeaderBytes }),
+      ...(options?.maxHeaderCount !== undefined && { maxHeaderCount: options.maxHeaderCount }),
+      ...(options?.lenientHeaders !== undefined && { lenientHeaders: options.lenientHeaders }),
+      ...(options?.initialStreamWindowSize !== undefined && {
+        initialStreamWindowSize: options.initialStreamWindowSize,
+      }),
+      ...(options?.initialConnectionWindowSize !== undefined && {
+        initialConnectionWindowSize: options.initialConnectionWindowSize,
+      }),
+      ...(options?.maxFrameSize !== undefined && { maxFrameSize: options.maxFrameSize }),
+      ...(options?.tcpNoDelay !== undefined && { tcpNoDelay: options.tcpNoDelay }),
+      ...(options?.tcpKeepAlive !== undefined && { tcpKeepAlive: options.tcpKeepAlive }),
+      ...(options?.sendBufferSize !== undefined && { sendBufferSize: options.sendBufferSize }),
+      ...(options?.recvBufferSize !== undefined && { recvBufferSize: options.recvBufferSize }),
+    });
+
+    const transport = new Transport(id);
+    nativeGuards.set(transport, [guard]);
+    return transport;
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+export async function createSession(options?: CreateSessionOptions): Promise<Session> {
+  const { sessionId, defaults } = normalizeSessionOptions(options);
+
+  validateBrowserProfile(defaults.browser);
+  validateOperatingSystem(defaults.os);
+
+  const onSetCookie = options?.onSetCookie;
+  if (onSetCookie !== undefined && typeof onSetCookie !== "function") {
+    throw new RequestError("onSetCookie must be a function");
+  }
+  const auth = options?.auth !== undefined ? buildSessionAuth(options.auth) : undefined;
+  const pacing = options?.pacing !== undefined ? buildSessionPacing(options.pacing) : undefined;
+
+  let created: NativeCreated;
+  let transport: NativeCreated;
+
+  try {
+    transport = nativeBinding.createTransport({
+      browser: defaults.browser,
+      os: defaults.os,
+      ...(defaults.proxy !== undefined && { proxy: defaults.proxy }),
+      ...(defaults.proxyPool !== undefined && { proxyPool: defaults.proxyPool }),
+      ...(defaults.proxyNtlm !== undefined && { proxyNtlm: defaults.proxyNtlm }),
+      ...(defaults.clientHints !== undefined && { clientHints: defaults.clientHints }),
+      ...(defaults.insecure !== undefined && { insecure: defaults.insecure }),
+    });
+  } catch (error) {
+    throw toRequestError(error);
+  }
+
+  try {
+    created = nativeBinding.createSession({
+      sessionId,
+      ...(options?.hsts === true && { hsts: true }),
+      ...(options?.acceptClientHints === true && { acceptClientHints: true }),
+      ...(onSetCookie && {
+        onSetCookie: (cookie: StoredCookie, url: string) => {
+          try {
+            onSetCookie(cookie, url);
+          } catch {
+            // A throwing callback must not surface as an uncaught native error.
+          }
+        },
+      }),
+      ...(auth !== undefined && { auth }),
+      ...(pacing !== undefined && { pacing }),
+    });
+  } catch (error) {
+    try {
+      nativeBinding.dropTransport(transport.id);
+    } catch {
+      // Best-effort cleanup; prefer surfacing the original error.
+    }
+    throw toRequestError(error);
+  }
+
+  defaults.transportId = transport.id;
+  defaults.ownsTransport = true;
+
+  const session = new Session(created.id, defaults);
+  nativeGuards.set(session, [created.guard, transport.guard]);
+  return session;
+}
+
+const PACING_JITTERS: readonly PacingJitter[] = ["uniform", "normal", "exponential"];
+
+function buildSessionPacing(pacing: SessionPacingOptions): Required<SessionPacingOptions> {
+  if (typeof pacing !== "object" || pacing === null) {
+    throw new RequestError("pacing must be an object");
+  }
+  const { minDelayMs, maxDelayMs = minDelayMs, jitter = "uniform" } = pacing;
+  validateNonNegativeInteger(minDelayMs, "pacing.minDelayMs");
+  validateNonNegativeInteger(maxDelayMs, "pacing.maxDelayMs");
+  if (maxDelayMs < minDelayMs) {
+    throw new RequestError("pacing.maxDelayMs must be greater than or equal to pacing.minDelayMs");
+  }
+  if (!PACING_JITTERS.includes(jitter)) {
+    throw new RequestError(`pacing.jitter must be one of ${PACING_JITTERS.join(", ")}`);
+  }
+  return { minDelayMs, maxDelayMs, jitter };
+}
+
+function beforeSendHook(
+  beforeSend: NonNullable<WreqRequestInit["beforeSend"]>,
+): NonNullable<NativeRequestOptions["onBeforeSend"]> {
+  return (signId, method, url, headers) => {
+    // The native side holds the request until exactly one answer arrives for this sign id.
+    Promise.resolve()
+      .then(() => beforeSend({ method, url, headers }))
+      .then((extra) => {
+        nativeBinding.resolveBeforeSend(signId, extra === undefined ? [] : headersToTuples(extra));
+      })
+      .catch((error: unknown) => {
+        nativeBinding.resolveBeforeSend(signId, null, error instanceof Error ? error.message : String(error));
+      });
+  };
+}
+
+function buildSessionAuth(auth: SessionAuthOptions): NativeSessionAuth {
+  const { refresh, tokenEndpoint, expiresIn, ...strings } = auth;
+  if ((tokenEndpoint === undefined) === (refresh === undefined)) {
+    throw new RequestError("auth needs exactly one of tokenEndpoint and refresh");
+  }
+  if (refresh !== undefined && typeof refresh !== "function") {
+    throw new RequestError("auth.refresh must be a function");
+  }
+  if (
+    tokenEndpoint !== undefined &&
+    (typeof tokenEndpoint !== "string" || normalizeUrlForComparison(tokenEndpoint) === null)
+  ) {
+    throw new RequestError("auth.tokenEndpoint must be a valid URL");
+  }
+  for (const [key, value] of Object.entries(strings)) {
+    if (value !== undefined && typeof value !== "string") {
+      throw new RequestError(`auth.${key} must be a string`);
+    }
+  }
+  if (expiresIn !== undefined) {
+    validateNonNegativeInteger(expiresIn, "auth.expiresIn");
+  }
+
+  const native: NativeSessionAuth = { ...strings };
+  if (tokenEndpoint !== undefined) {
+    native.tokenEndpoint = tokenEndpoint;
+  }
+  if (expiresIn !== undefined) {
+    native.expiresIn = expiresIn;
+  }
+  if (refresh) {
+    native.onRefresh = (refreshId) => {
+      // The native side waits for exactly one answer per refresh id, whatever the callback does.
+      Promise.resolve()
+        .then(refresh)
+        .then((result) => {
+          const grant = typeof result === "string" ? { accessToken: result } : result;
+          if (typeof grant?.accessToken !== "string") {
+            throw new RequestError("auth.refresh must return a token string or { accessToken }");
+          }
+          nativeBinding.resolveAuthRefresh(refreshId, {
+            accessToken: grant.accessToken,
+            ...(grant.expiresIn !== undefined && { expiresIn: grant.expiresIn }),
+          });
+        })
+        .catch((error: unknown) => {
+          nativeBinding.resolveAuthRefresh(refreshId, null, error instanceof Error ? error.message : String(error));
+        });
+    };
+  }
+  return native;
+}
+
+export async function withSession<T>(
+  fn: (session: Session) => Promise<T> | T,
+  options?: CreateSessionOptions,
+): Promise<T> {
+  const session = await createSession(options);
+
+  try {
+    return await fn(session);
+  } finally {
+    await session.close();
+  }
+}
+
+/**
+ * @deprecated Use {@link fetch} instead.
+ */
+export async function request(options: RequestOptions): Promise<Response> {
+  if (!options.url) {
+    throw new RequestError("URL is required");
+  }
+
+  const { url, ...rest } = options;
+  const init: WreqRequestInit = {};
+  const legacy = rest as Partial<WreqRequestInit> & { ephemeral?: boolean };
+
+  if (rest.method !== undefined) {
+    init.method = rest.method;
+  }
+
+  if (rest.headers !== undefined) {
+    init.headers = rest.headers;
+  }
+
+  if (rest.body !== undefined) {
+    init.body = rest.body;
+  }
+
+  if (rest.browser !== undefined) {
+    init.browser = rest.browser;
+  }
+
+  if (rest.os !== undefined) {
+    init.os = rest.os;
+  }
+
+  if (rest.proxy !== undefined) {
+    init.proxy = rest.proxy;
+  }
+
+  if (rest.timeout !== undefined) {
+    init.timeout = rest.timeout;
+  }
+
+  if (rest.sessionId !== undefined) {
+    init.sessionId = rest.sessionId;
+  }
+
+  if (rest.transport !== undefined) {
+    init.transport = rest.transport;
+  }
+
+  if (rest.insecure !== undefined) {
+    init.insecure = rest.insecure;
+  }
+
+  if (rest.disableDefaultHeaders !== undefined) {
+    init.disableDefaultHeaders = rest.disableDefaultHeaders;
+  }
+
+  if (rest.removeHeaders !== undefined) {
+    init.removeHeaders = rest.removeHeaders;
+  }
+
+  if (rest.redirect !== undefined) {
+    init.redirect = rest.redirect;
+  }
+
+  if (legacy.signal !== undefined) {
+    init.signal = legacy.signal;
+  }
+
+  if (legacy.session !== undefined) {
+    init.session = legacy.session;
+  }
+
+  if (legacy.cookieMode !== undefined) {
+    init.cookieMode = legacy.cookieMode;
+  } else if (legacy.ephemeral === true) {
+    init.cookieMode = "ephemeral";
+  }
+
+  return fetch(url, init);
+}
+
+/**
+ * Cancel every in-flight request tagged with `groupId` and release any of their
+ * response bodies that have not been fully read yet.
+ *
+ * The requests reject with a `RequestError` whose `code` is `"ERR_ABORTED"` and whose
+ * message is `reason`, or "Request aborted" without one.
+ *
+ * @example
+ * ```typescript
+ * import { cancelGroup, fetch } from 'wreq-js';
+ *
+ * const pages = urls.map((url) => fetch(url, { groupId: 'crawl:example.com' }));
+ * cancelGroup('crawl:example.com', 'crawl budget exhausted');
+ * ```
+ */
+export function cancelGroup(groupId: string, reason?: string): void {
+  if (typeof groupId !== "string" || groupId.length === 0) {
+    throw new RequestError("groupId must be a non-empty string");
+  }
+  if (reason !== undefined && typeof reason !== "string") {
+    throw new RequestError("reason must be a string");
+  }
+
+  nativeBinding.cancelGroup(groupId, reason);
+}
+
+/**
+ * Cancel every in-flight request and release all unread response bodies.
+ */
+export function cancelAllRequests(): void {
+  nativeBinding.cancelAllRequests();
+}
+
+/**
+ * A registered request mock. Matching requests are answered natively without touching the network.
+ */
+export class Mock {
+  readonly id: number;
+  private restoredCalls: number | null = null;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * Number of requests this mock has answered so far.
+   */
+  get calls(): number {
+    if (this.restoredCalls !== null) {
+      return this.restoredCalls;
+    }
+
+    // clearMocks() removes the native registration without going through restore().
+    return nativeBinding.mockCalls(this.id) ?? 0;
+  }
+
+  get restored(): boolean {
+    return this.restoredCalls !== null;
+  }
+
+  /**
+   * Stop intercepting requests. The call count stays readable afterwards.
+   */
+  restore(): void {
+    if (this.restoredCalls !== null) {
+      return;
+    }
+
+    this.restoredCalls = this.calls;
+    nativeBinding.unmock(this.id);
+  }
+}
+
+/**
+ * Intercept matching requests and answer them with a canned response instead of the network.
+ * Intended for tests. When several mocks match, the most recently registered one wins.
+ *
+ * @example
+ * ```typescript
+ * import { fetch, mock } from 'wreq-js';
+ *
+ * const users = mock({ method: 'GET', urlPattern: 'https://api.example.com/users/*' }, {
+ *   status: 200,
+ *   headers: { 'content-type': 'application/json' },
+ *   body: JSON.stringify({ id: 1 }),
+ * });
+ *
+ * await fetch('https://api.example.com/users/1');
+ * users.calls; // 1
+ * users.restore();
+ * ```
+ */
+export function mock(matcher: MockMatcher, response: MockResponseInit = {}): Mock {
+  const urlPattern = matcher?.urlPattern instanceof URL ? matcher.urlPattern.href : matcher?.urlPattern;
+  if (typeof urlPattern !== "string" || urlPattern.length === 0) {
+    throw new RequestError("urlPattern must be a non-empty string or URL");
+  }
+
+  const status = response.status ?? 200;
+  if (!Number.isInteger(status) || status < 100 || status > 599) {
+    throw new RequestError("status must be an integer between 100 and 599");
+  }
+
+  const delayMs = response.delayMs ?? 0;
+  validateNonNegativeInteger(delayMs, "delayMs");
+
+  let body: Buffer | undefined;
+  if (typeof response.body === "string") {
+    body = Buffer.from(response.body, "utf8");
+  } else if (response.body instanceof ArrayBuffer) {
+    body = Buffer.from(response.body);
+  } else if (ArrayBuffer.isView(response.body)) {
+    body = Buffer.from(response.body.buffer, response.body.byteOffset, response.body.byteLength);
+  }
+
+  const id = nativeBinding.mock(
+    {
+      ...(matcher.method !== undefined ? { method: ensureMethod(matcher.method) } : {}),
+      urlPattern,
+    },
+    {
+      status,
+      headers: response.headers ? headersToTuples(response.headers) : [],
+      ...(body !== undefined ? { body } : {}),
+      delayMs,
+    },
+  );
+
+  return new Mock(id);
+}
+
+/**
+ * Remove every registered mock.
+ */
+export function clearMocks(): void {
+  nativeBinding.clearMocks();
+}
+
+const CASSETTE_MATCH_FIELDS = new Set<CassetteMatchField>(["method", "url", "body"]);
+
+/**
+ * The active record/replay cassette.
+ */
+export class Cassette {
+  readonly path: string;
+  readonly mode: CassetteOptions["mode"];
+  private ejected = false;
+
+  constructor(path: string, mode: CassetteOptions["mode"]) {
+    this.path = path;
+    this.mode = mode;
+  }
+
+  get active(): boolean {
+    return !this.ejected;
+  }
+
+  /**
+   * Stop recording or replaying. Returns the number of interactions in the cassette.
+   */
+  eject(): number {
+    if (this.ejected) {
+      return 0;
+    }
+
+    this.ejected = true;
+    return nativeBinding.ejectCassette();
+  }
+}
+
+/**
+ * Record requests to, or replay them from, a cassette file. Only one cassette can be active at a time.
+ *
+ * In record mode every request goes over the network and is appended to the file as soon as it
+ * completes; response bodies are buffered so they can be stored. In replay mode matching requests
+ * are served from the file in recorded order and nothing touches the network.
+ *
+ * @example
+ * ```typescript
+ * import { fetch, useCassette } from 'wreq-js';
+ *
+ * const cassette = useCassette('fixtures/github.json', {
+ *   mode: process.env.RECORD ? 'record' : 'replay',
+ * });
+ *
+ * try {
+ *   await fetch('https://api.github.com/repos/nodejs/node');
+ * } finally {
+ *   cassette.eject();
+ * }
+ * ```
+ */
+export function useCassette(path: string, options: CassetteOptions): Cassette {
+  if (typeof path !== "string" || path.length === 0) {
+    throw new RequestError("Cassette path must be a non-empty string");
+  }
+
+  if (options?.mode !== "record" && options?.mode !== "replay") {
+    throw new RequestError('Cassette mode must be "record" or "replay"');
+  }
+
+  const matchOn = options.matchOn ?? ["method", "url"];
+  for (const field of matchOn) {
+    if (!CASSETTE_MATCH_FIELDS.has(field)) {
+      throw new RequestError(`Unsupported cassette match field: ${String(field)}`);
+    }
+  }
+
+  try {
+    nativeBinding.useCassette(path, options.mode, matchOn);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+
+  return new Cassette(path, options.mode);
+}
+
+/**
+ * Append one JSON line per request to `path`: timestamp, method, URL, status or error, request and
+ * response byte counts, duration, proxy (password redacted), session and transport. Lines are
+ * buffered natively; call {@link disableAuditLog} to flush them. Enabling again switches files.
+ *
+ * @example
+ * ```typescript
+ * import { disableAuditLog, enableAuditLog, fetch } from 'wreq-js';
+ *
+ * enableAuditLog('logs/requests.jsonl', { rotateBytes: 10 * 1024 * 1024 });
+ * await fetch('https://example.com');
+ * disableAuditLog();
+ * ```
+ */
+export function enableAuditLog(path: string, options: AuditLogOptions = {}): void {
+  if (typeof path !== "string" || path.length === 0) {
+    throw new RequestError("Audit log path must be a non-empty string");
+  }
+
+  if (options.rotateBytes !== undefined) {
+    validatePositiveNumber(options.rotateBytes, "rotateBytes");
+  }
+
+  try {
+    nativeBinding.enableAuditLog(path, options.includeBodies ?? false, options.rotateBytes);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+/**
+ * Flush and close the audit log opened by {@link enableAuditLog}. Safe to call when none is open.
+ */
+export function disableAuditLog(): void {
+  try {
+    nativeBinding.disableAuditLog();
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+/**
+ * A raw TLS connection opened by {@link tlsConnect}. It is a regular Node.js `Duplex`:
+ * written data is encrypted and sent, and decrypted data from the peer is readable.
+ * `end()` sends a TLS close_notify while keeping the read side open.
+ */
+export class TlsSocket extends Duplex {
+  readonly id: number;
+  /** Protocol selected through ALPN, or `null` when the server did not pick one. */
+  readonly alpnProtocol: string | null;
+  /** Negotiated protocol version, e.g. `"TLSv1.3"`. */
+  readonly tlsVersion: string;
+  readonly localAddress: string | null;
+  readonly remoteAddress: string | null;
+
+  constructor(info: NativeTlsSocket) {
+    super();
+    this.id = info.id;
+    this.alpnProtocol = info.alpnProtocol;
+    this.tlsVersion = info.tlsVersion;
+    this.localAddress = info.localAddress;
+    this.remoteAddress = info.remoteAddress;
+  }
+
+  override _read(): void {
+    nativeBinding.tlsRead(this.id).then(
+      (chunk) => {
+        this.push(chunk);
+      },
+      (error: unknown) => {
+        this.destroy(toRequestError(error));
+      },
+    );
+  }
+
+  override _write(chunk: Buffer, _encoding: BufferEncoding, callback: (error?: Error | null) => void): void {
+    nativeBinding.tlsWrite(this.id, chunk).then(
+      () => callback(),
+      (error: unknown) => callback(toRequestError(error)),
+    );
+  }
+
+  override _final(callback: (error?: Error | null) => void): void {
+    nativeBinding.tlsEnd(this.id).then(
+      () => callback(),
+      (error: unknown) => callback(toRequestError(error)),
+    );
+  }
+
+  override _destroy(error: Error | null, callback: (error?: Error | null) => void): void {
+    try {
+      nativeBinding.tlsClose(this.id);
+    } catch {
+      // Best-effort cleanup; ignore binding errors.
+    }
+    callback(error);
+  }
+}
+
+/**
+ * Open a raw TLS connection whose ClientHello matches a browser profile, for non-HTTP
+ * protocols (SMTP, custom binary APIs) that are fingerprinted too.
+ *
+ * @example
+ * ```typescript
+ * import { tlsConnect } from 'wreq-js';
+ *
+ * const socket = await tlsConnect({ host: 'smtp.example.com', port: 465, browser: 'chrome_142' });
+ * socket.on('data', (chunk) => console.log(chunk.toString()));
+ * socket.write('EHLO example.com\r\n');
+ * ```
+ */
+export async function tlsConnect(options: TlsConnectOptions): Promise<TlsSocket> {
+  if (typeof options?.host !== "string" || options.host.length === 0) {
+    throw new RequestError("host must be a non-empty string");
+  }
+  if (!Number.isInteger(options.port) || options.port < 1 || options.port > 65535) {
+    throw new RequestError("port must be an integer between 1 and 65535");
+  }
+
+  const browser = options.browser ?? DEFAULT_BROWSER;
+  const os = options.os ?? DEFAULT_OS;
+  validateBrowserProfile(browser);
+  validateOperatingSystem(os);
+
+  validateTimeout(options.timeout);
+
+  try {
+    const info = await nativeBinding.tlsConnect({
+      host: options.host,
+      port: options.port,
+      browser,
+      os,
+      ...(options.proxy !== undefined && { proxy: options.proxy }),
+      ...(options.insecure !== undefined && { insecure: options.insecure }),
+      ...(options.timeout !== undefined && { timeout: options.timeout }),
+    });
+    return new TlsSocket(info);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+/**
+ * Connect to an origin the way a request would — DNS, TCP, the proxy tunnel, the emulated TLS
+ * handshake, and optionally the HTTP/2 preface — then close the connection without sending a
+ * request. Useful for measuring latency per phase and checking what a server negotiates.
+ *
+ * @example
+ * ```typescript
+ * import { probe } from 'wreq-js';
+ *
+ * const { timings, alpnProtocol } = await probe({ url: 'https://example.com', browser: 'chrome_142' });
+ * console.log(alpnProtocol, timings.tls);
+ * ```
+ */
+export async function probe(options: ProbeOptions): Promise<ProbeResult> {
+  const url = options?.url instanceof URL ? options.url.href : options?.url;
+  if (typeof url !== "string" || url.length === 0) {
+    throw new RequestError("url must be a non-empty string or URL");
+  }
+
+  const browser = options.browser ?? DEFAULT_BROWSER;
+  const os = options.os ?? DEFAULT_OS;
+  validateBrowserProfile(browser);
+  validateOperatingSystem(os);
+
+  validateTimeout(options.timeout);
 
   try {
-    createdId = nativeBinding.createSession({
-      sessionId,
+    return await nativeBinding.probe({
+      url,
+      browser,
+      os,
+      ...(options.proxy !== undefined && { proxy: options.proxy }),
+      ...(options.insecure !== undefined && { insecure: options.insecure }),
+      ...(options.http2 !== undefined && { http2: options.http2 }),
+      ...(options.timeout !== undefined && { timeout: options.timeout }),
     });
   } catch (error) {
-    try {
-      nativeBinding.dropTransport(transportId);
-    } catch {
-      // Best-effort cleanup; prefer surfacing the original error.
-    }
-    throw new RequestError(String(error));
+    throw toRequestError(error);
   }
-
-  defaults.transportId = transportId;
-  defaults.ownsTransport = true;
-
-  return new Session(createdId, defaults);
 }
 
-export async function withSession<T>(
-  fn: (session: Session) => Promise<T> | T,
-  options?: CreateSessionOptions,
-): Promise<T> {
-  const session = await createSession(options);
+/**
+ * Gracefully shut down the native client.
+ *
+ * New requests, sessions, transports, and WebSockets are rejected immediately. In-flight
+ * requests, unread body streams, and open WebSockets are given `gracePeriodMs` to finish;
+ * anything still outstanding afterwards is cancelled. All native registries are then released
+ * so the process can exit cleanly. Shutdown is terminal for the lifetime of the process.
+ *
+ * @example
+ * ```typescript
+ * import { shutdown } from 'wreq-js';
+ *
+ * process.once('SIGTERM', async () => {
+ *   await shutdown({ gracePeriodMs: 2_000 });
+ *   process.exit(0);
+ * });
+ * ```
+ */
+export async function shutdown(options?: ShutdownOptions): Promise<void> {
+  const gracePeriodMs = options?.gracePeriodMs ?? DEFAULT_SHUTDOWN_GRACE_PERIOD_MS;
+  if (typeof gracePeriodMs !== "number" || !Number.isFinite(gracePeriodMs) || gracePeriodMs < 0) {
+    throw new RequestError("gracePeriodMs must be a non-negative finite number");
+  }
 
   try {
-    return await fn(session);
-  } finally {
-    await session.close();
+    await nativeBinding.shutdown(gracePeriodMs);
+  } catch (error) {
+    throw toRequestError(error);
   }
 }
 
 /**
- * @deprecated Use {@link fetch} instead.
+ * Control how response bodies that are never read or cancelled get cleaned up. An abandoned body
+ * pins its stream and connection natively, so it is reaped once it sits unread for `ttl`, or
+ * when it is among the oldest over `maxStreams`. A reaped body reads as empty. `onLeak` reports
+ * each reaped body so the code that dropped the response can be found.
+ *
+ * @example
+ * ```typescript
+ * import { configureBodyStreams } from 'wreq-js';
+ *
+ * configureBodyStreams({
+ *   ttl: 60_000,
+ *   maxStreams: 1_000,
+ *   onLeak: (leak) => console.warn(`body ${leak.handle} leaked (${leak.reason})`),
+ * });
+ * ```
  */
-export async function request(options: RequestOptions): Promise<Response> {
-  if (!options.url) {
-    throw new RequestError("URL is required");
+export function configureBodyStreams(options: BodyStreamOptions): void {
+  if (typeof options !== "object" || options === null) {
+    throw new RequestError("configureBodyStreams expects an options object");
   }
 
-  const { url, ...rest } = options;
-  const init: WreqRequestInit = {};
-  const legacy = rest as Partial<WreqRequestInit> & { ephemeral?: boolean };
-
-  if (rest.method !== undefined) {
-    init.method = rest.method;
-  }
+  const { ttl, maxStreams, onLeak, delivery, pushCredit: credit } = options;
+  const nativeOptions: BodyStreamOptions = {};
 
-  if (rest.headers !== undefined) {
-    init.headers = rest.headers;
+  if (delivery !== undefined && delivery !== "pull" && delivery !== "push") {
+    throw new RequestError('delivery must be "pull" or "push"');
   }
-
-  if (rest.body !== undefined) {
-    init.body = rest.body;
+  if (credit !== undefined) {
+    validatePositiveInteger(credit, "pushCredit");
   }
 
-  if (rest.browser !== undefined) {
-    init.browser = rest.browser;
+  if (ttl !== undefined) {
+    validatePositiveNumber(ttl, "ttl");
+    nativeOptions.ttl = ttl;
   }
 
-  if (rest.os !== undefined) {
-    init.os = rest.os;
+  if (maxStreams !== undefined) {
+    if (maxStreams !== null) {
+      validatePositiveInteger(maxStreams, "maxStreams");
+    }
+    nativeOptions.maxStreams = maxStreams;
   }
 
-  if (rest.proxy !== undefined) {
-    init.proxy = rest.proxy;
+  if (onLeak !== undefined) {
+    if (onLeak !== null && typeof onLeak !== "function") {
+      throw new RequestError("onLeak must be a function or null");
+    }
+    nativeOptions.onLeak =
+      onLeak &&
+      ((leak) => {
+        try {
+          onLeak(leak);
+        } catch {
+          // A throwing diagnostic callback must not surface as an uncaught native error.
+        }
+      });
   }
 
-  if (rest.timeout !== undefined) {
-    init.timeout = rest.timeout;
-  }
+  nativeBinding.configureBodyStreams(nativeOptions);
 
-  if (rest.sessionId !== undefined) {
-    init.sessionId = rest.sessionId;
+  // Delivery is chosen in JS when a body stream is created, so the native side never sees it.
+  if (delivery !== undefined) {
+    bodyDelivery = delivery;
   }
-
-  if (rest.transport !== undefined) {
-    init.transport = rest.transport;
+  if (credit !== undefined) {
+    pushCredit = credit;
   }
+}
 
-  if (rest.insecure !== undefined) {
-    init.insecure = rest.insecure;
+/**
+ * Allocate a ring for `Response.pipeToRing()` with room for `capacity` body bytes in flight.
+ * Share it with a worker, which reads the body with `readBodyRing()`. A ring carries one body.
+ *
+ * @example
+ * ```typescript
+ * import { createBodyRing, fetch } from 'wreq-js';
+ *
+ * const ring = createBodyRing(1 << 20);
+ * worker.postMessage(ring);
+ * const response = await fetch('https://example.com/dump.ndjson');
+ * await response.pipeToRing(ring);
+ * ```
+ */
+export function createBodyRing(capacity: number): SharedArrayBuffer {
+  validatePositiveInteger(capacity, "capacity");
+  if (capacity > 0x7fffffff) {
+    throw new RequestError("capacity must be less than 2 GiB");
   }
+  return new SharedArrayBuffer(RING_HEADER_BYTES + capacity);
+}
 
-  if (rest.disableDefaultHeaders !== undefined) {
-    init.disableDefaultHeaders = rest.disableDefaultHeaders;
-  }
+/**
+ * Read a body from a ring filled by `Response.pipeToRing()`, usually in a worker. Yields views
+ * straight into the shared memory, each valid only until the next one is requested, so copy
+ * what must outlive it. Blocks with `Atomics.wait()` while the ring is empty. Throws a
+ * `RequestError` if the download failed. Stopping early tells the writer to stop too.
+ *
+ * @example
+ * ```typescript
+ * import { readBodyRing } from 'wreq-js';
+ *
+ * parentPort.once('message', (ring) => {
+ *   for (const bytes of readBodyRing(ring)) {
+ *     parser.write(bytes);
+ *   }
+ * });
+ * ```
+ */
+export function* readBodyRing(ring: SharedArrayBuffer): Generator<Uint8Array, void, undefined> {
+  const control = ringControl(ring);
+  const data = new Uint8Array(ring, RING_HEADER_BYTES);
+  let finished = false;
 
-  if (rest.redirect !== undefined) {
-    init.redirect = rest.redirect;
-  }
+  try {
+    while (true) {
+      // State before position: a writer publishes its last bytes before it marks the ring done.
+      const state = Atomics.load(control, RING_STATE);
+      const written = Atomics.load(control, RING_WRITE);
+      const read = Atomics.load(control, RING_READ) >>> 0;
+      const available = ((written >>> 0) - read) >>> 0;
+
+      if (available > 0) {
+        const start = read % data.byteLength;
+        const length = Math.min(available, data.byteLength - start);
+        yield data.subarray(start, start + length);
+        Atomics.store(control, RING_READ, (read + length) | 0);
+        continue;
+      }
 
-  if (legacy.signal !== undefined) {
-    init.signal = legacy.signal;
-  }
+      if (state === RING_DONE) {
+        finished = true;
+        return;
+      }
+      if (state === RING_FAILED) {
+        finished = true;
+        throw new RequestError("Response body failed before it was fully read");
+      }
 
-  if (legacy.session !== undefined) {
-    init.session = legacy.session;
+      Atomics.store(control, RING_WAITING, 1);
+      Atomics.wait(control, RING_WRITE, written, RING_MAX_WAIT_MS);
+    }
+  } finally {
+    if (!finished) {
+      Atomics.compareExchange(control, RING_STATE, RING_STREAMING, RING_CANCELLED);
+    }
   }
+}
 
-  if (legacy.cookieMode !== undefined) {
-    init.cookieMode = legacy.cookieMode;
-  } else if (legacy.ephemeral === true) {
-    init.cookieMode = "ephemeral";
-  }
+/**
+ * Count the native resources that are currently alive: unread body streams, in-flight requests,
+ * pending cancellations, WebSockets, sessions, transports, cached ephemeral clients, and buffered
+ * body bytes. Poll it to spot leaks, e.g. a `bodyStreams` count that only ever grows.
+ *
+ * @example
+ * ```typescript
+ * import { getNativeStats } from 'wreq-js';
+ *
+ * setInterval(() => console.log(getNativeStats()), 60_000).unref();
+ * ```
+ */
+export function getNativeStats(): NativeStats {
+  return nativeBinding.getNativeStats();
+}
 
-  return fetch(url, init);
+/**
+ * Measure the memory the native side holds on behalf of JS: inline response bodies lent to JS,
+ * body chunks and WebSocket messages queued for the JS thread, pooled body buffers, and the
+ * sizes of the session and client caches. Unlike process RSS, a figure that only grows points at
+ * the code that holds on to it.
+ *
+ * @example
+ * ```typescript
+ * import { getMemoryStats } from 'wreq-js';
+ *
+ * const { queuedWebSocketBytes } = getMemoryStats();
+ * if (queuedWebSocketBytes > 64 * 1024 * 1024) {
+ *   console.warn('WebSocket handlers are falling behind');
+ * }
+ * ```
+ */
+export function getMemoryStats(): MemoryStats {
+  return nativeBinding.getMemoryStats();
 }
 
 /**
@@ -1926,6 +4625,28 @@ export function getProfiles(): BrowserProfile[] {
   return cachedProfiles;
 }
 
+/**
+ * Resolve a browser profile alias to the profile it currently stands for, the same way requests
+ * resolve it natively. Profile names resolve to themselves.
+ *
+ * @returns The profile name, or `null` when `alias` is neither a profile nor a known alias
+ *
+ * @example
+ * ```typescript
+ * import { resolveProfile } from 'wreq-js';
+ *
+ * resolveProfile('chrome'); // e.g. 'chrome_145'
+ * resolveProfile('firefox:esr'); // e.g. 'firefox_128'
+ * ```
+ */
+export function resolveProfile(alias: string): BrowserProfile | null {
+  if (typeof alias !== "string") {
+    throw new RequestError("resolveProfile expects a string");
+  }
+
+  return nativeBinding.resolveProfile(alias) as BrowserProfile | null;
+}
+
 function getProfileSet(): Set<string> {
   if (!cachedProfileSet) {
     cachedProfileSet = new Set(getProfiles());
@@ -1956,6 +4677,112 @@ function getOperatingSystemSet(): Set<string> {
   return cachedOperatingSystemSet;
 }
 
+/**
+ * Make the native layer throw on browser profiles and operating systems it cannot resolve,
+ * listing the valid values, instead of falling back to `chrome_142`/`macos`. Applies to every
+ * request, session, transport, WebSocket, and TLS socket created afterwards. Requests can opt
+ * in individually with {@link RequestInit.strictProfiles}.
+ *
+ * @example
+ * ```typescript
+ * import { setStrictProfiles } from 'wreq-js';
+ *
+ * setStrictProfiles(true);
+ * ```
+ */
+export function setStrictProfiles(enabled: boolean): void {
+  if (typeof enabled !== "boolean") {
+    throw new RequestError("setStrictProfiles expects a boolean");
+  }
+
+  nativeBinding.setStrictProfiles(enabled);
+}
+
+/**
+ * Choose whether the sessions and transports this JS context creates can be used from other
+ * contexts. Each `worker_threads` worker that imports wreq-js is its own context; the default
+ * is `"shared"`, so a session or transport ID can be posted to a worker and used there. With
+ * `"isolated"`, IDs from other contexts behave as if they did not exist.
+ *
+ * Call it before creating any session or transport in this context, e.g. right after importing
+ * wreq-js in a worker. Whatever the mode, sessions, transports, and requests a worker still
+ * holds are released when it exits.
+ *
+ * @example
+ * ```typescript
+ * import { setRegistryMode } from 'wreq-js';
+ *
+ * setRegistryMode('isolated');
+ * ```
+ */
+export function setRegistryMode(mode: RegistryMode): void {
+  if (mode !== "shared" && mode !== "isolated") {
+    throw new RequestError('setRegistryMode expects "shared" or "isolated"');
+  }
+
+  try {
+    nativeBinding.setRegistryMode(mode);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+/**
+ * Set options that apply to every request that leaves them unset, so they do not have to be
+ * passed to each call. The defaults are stored natively and replace any set before; call
+ * `setDefaults({})` to go back to the built-in defaults. Sessions and transports keep the
+ * browser, OS, proxy, and connection settings they were created with.
+ *
+ * @example
+ * ```typescript
+ * import { fetch, setDefaults } from 'wreq-js';
+ *
+ * setDefaults({ browser: 'firefox_139', timeout: 10_000, proxy: 'http://proxy.local:8080' });
+ * await fetch('https://example.com');
+ * ```
+ */
+export function setDefaults(defaults: RequestDefaults): void {
+  if (typeof defaults !== "object" || defaults === null) {
+    throw new RequestError("setDefaults expects an options object");
+  }
+
+  validateBrowserProfile(defaults.browser);
+  validateOperatingSystem(defaults.os);
+  validateTimeout(defaults.timeout);
+  validateRedirectMode(defaults.redirect);
+  if (defaults.connectTimeout !== undefined) {
+    validatePositiveNumber(defaults.connectTimeout, "connectTimeout");
+  }
+  if (defaults.readTimeout !== undefined) {
+    validatePositiveNumber(defaults.readTimeout, "readTimeout");
+  }
+  if (defaults.proxy !== undefined && typeof defaults.proxy !== "string") {
+    throw new RequestError("proxy must be a string");
+  }
+  if (defaults.insecure !== undefined && typeof defaults.insecure !== "boolean") {
+    throw new RequestError("insecure must be a boolean");
+  }
+  if (defaults.originPooling !== undefined && typeof defaults.originPooling !== "boolean") {
+    throw new RequestError("originPooling must be a boolean");
+  }
+  if (defaults.inlineBodyMax !== undefined) {
+    validateNonNegativeInteger(defaults.inlineBodyMax, "inlineBodyMax");
+  }
+
+  try {
+    nativeBinding.setDefaults(defaults);
+  } catch (error) {
+    throw toRequestError(error);
+  }
+}
+
+/**
+ * Return the defaults last passed to {@link setDefaults}. Options that were not set are omitted.
+ */
+export function getDefaults(): RequestDefaults {
+  return nativeBinding.getDefaults();
+}
+
 /**
  * Convenience helper for GET requests using {@link fetch}.
  */
@@ -1998,7 +4825,7 @@ function normalizeWebSocketUrl(url: string | URL): string {
   try {
     parsed = new URL(normalized);
   } catch (error) {
-    throw new RequestError(String(error));
+    throw toRequestError(error);
   }
 
   if (parsed.hash) {
@@ -2041,6 +4868,55 @@ function validateWebSocketProtocols(protocols?: string | string[]): void {
   }
 }
 
+function normalizeWebSocketBatch(batch: true | WebSocketBatchOptions): Required<WebSocketBatchOptions> {
+  const options = batch === true ? {} : batch;
+  const maxMessages = options.maxMessages ?? DEFAULT_WEBSOCKET_BATCH_MESSAGES;
+  if (!Number.isInteger(maxMessages) || maxMessages < 1) {
+    throw new RequestError("batch.maxMessages must be a positive integer");
+  }
+  const flushIntervalMs = options.flushIntervalMs ?? 0;
+  if (!Number.isFinite(flushIntervalMs) || flushIntervalMs < 0) {
+    throw new RequestError("batch.flushIntervalMs must be a non-negative number");
+  }
+  return { maxMessages, flushIntervalMs };
+}
+
+function nativeWebSocketBatch(
+  options: Pick<WebSocketOptions, "batch">,
+  callbacks: { onMessages: (messages: Array<string | Buffer>) => void },
+): Pick<NativeWebSocketOptions, "batch" | "onMessages"> {
+  if (options.batch === undefined || options.batch === false) {
+    return {};
+  }
+  return { batch: normalizeWebSocketBatch(options.batch), onMessages: callbacks.onMessages };
+}
+
+function normalizeWebSocketStreaming(options: WebSocketStreamOptions): Required<WebSocketStreamOptions> {
+  if (!isPlainObject(options) || !Number.isInteger(options.threshold) || options.threshold < 0) {
+    throw new RequestError("streamMessages.threshold must be a non-negative integer");
+  }
+  const chunkSize = options.chunkSize ?? DEFAULT_WEBSOCKET_STREAM_CHUNK_SIZE;
+  if (!Number.isInteger(chunkSize) || chunkSize < 1) {
+    throw new RequestError("streamMessages.chunkSize must be a positive integer");
+  }
+  return { threshold: options.threshold, chunkSize };
+}
+
+function nativeWebSocketStreaming(
+  options: Pick<WebSocketOptions, "streamMessages">,
+  callbacks: Required<Pick<NativeWebSocketOptions, "onMessageStart" | "onMessageChunk" | "onMessageEnd">>,
+): Pick<NativeWebSocketOptions, "streamMessages" | "onMessageStart" | "onMessageChunk" | "onMessageEnd"> {
+  if (options.streamMessages === undefined) {
+    return {};
+  }
+  return {
+    streamMessages: normalizeWebSocketStreaming(options.streamMessages),
+    onMessageStart: callbacks.onMessageStart,
+    onMessageChunk: callbacks.onMessageChunk,
+    onMessageEnd: callbacks.onMessageEnd,
+  };
+}
+
 function normalizeStandaloneWebSocketOptions(options?: Partial<WebSocketOptions>): WebSocketOptions {
   const normalized: WebSocketOptions = {};
   if (!options) {
@@ -2068,6 +4944,18 @@ function normalizeStandaloneWebSocketOptions(options?: Partial<WebSocketOptions>
     }
     normalized.binaryType = options.binaryType;
   }
+  if (options.binaryText !== undefined) {
+    if (typeof options.binaryText !== "boolean") {
+      throw new RequestError("binaryText must be a boolean");
+    }
+    normalized.binaryText = options.binaryText;
+  }
+  if (options.batch !== undefined && options.batch !== false) {
+    normalized.batch = normalizeWebSocketBatch(options.batch);
+  }
+  if (options.streamMessages !== undefined) {
+    normalized.streamMessages = normalizeWebSocketStreaming(options.streamMessages);
+  }
 
   return normalized;
 }
@@ -2103,6 +4991,18 @@ function normalizeSessionWebSocketOptions(options?: Partial<SessionWebSocketOpti
     }
     normalized.binaryType = options.binaryType;
   }
+  if (options.binaryText !== undefined) {
+    if (typeof options.binaryText !== "boolean") {
+      throw new RequestError("binaryText must be a boolean");
+    }
+    normalized.binaryText = options.binaryText;
+  }
+  if (options.batch !== undefined && options.batch !== false) {
+    normalized.batch = normalizeWebSocketBatch(options.batch);
+  }
+  if (options.streamMessages !== undefined) {
+    normalized.streamMessages = normalizeWebSocketStreaming(options.streamMessages);
+  }
 
   return normalized;
 }
@@ -2213,12 +5113,12 @@ export class WebSocket {
   private _connection: NativeWebSocketConnection | undefined;
   private _connectPromise!: Promise<void>;
   private _closeOptions: NativeWebSocketCloseOptions | undefined;
-  private _finalizerToken: NativeWebSocketConnection | undefined;
   private _openEventDispatched = false;
   private _openEventQueued = false;
   private _closeEventDispatched = false;
   private _nativeCloseStarted = false;
-  private _pendingMessages: Array<string | Buffer> = [];
+  private _pendingMessages: Array<string | Buffer | ReadableStream<Uint8Array>> = [];
+  private _messageStream: ReadableStreamDefaultController<Uint8Array> | undefined;
   private _sendChain: Promise<void> = Promise.resolve();
 
   constructor(init: InternalWebSocketInit);
@@ -2302,6 +5202,28 @@ export class WebSocket {
     return ws;
   }
 
+  static _stateOf(ws: WebSocket): WebSocketState {
+    if (!ws._connection) {
+      return {
+        state: ws.readyState === WebSocket.CLOSED ? "closed" : "connecting",
+        openedAt: null,
+        lastSentAt: null,
+        lastReceivedAt: null,
+      };
+    }
+
+    // close() and close events reach the native side and JS at different times; report
+    // whichever is further along.
+    const state = nativeBinding.websocketState(ws._connection);
+    if (ws.readyState === WebSocket.CLOSED) {
+      return { ...state, state: "closed" };
+    }
+    if (ws.readyState === WebSocket.CLOSING && state.state === "open") {
+      return { ...state, state: "closing" };
+    }
+    return state;
+  }
+
   private static buildStandaloneInit(
     url: string | URL,
     protocolsOrOptions?: string | string[] | WebSocketOptions,
@@ -2344,6 +5266,9 @@ export class WebSocket {
           onMessage: callbacks.onMessage,
           onClose: callbacks.onClose,
           onError: callbacks.onError,
+          ...(normalizedOptions.binaryText === true && { binaryText: true }),
+          ...nativeWebSocketBatch(normalizedOptions, callbacks),
+          ...nativeWebSocketStreaming(normalizedOptions, callbacks),
         }),
       legacyCallbacks: extractLegacyWebSocketCallbacks(optionsCandidate),
     };
@@ -2352,6 +5277,10 @@ export class WebSocket {
   private async connect(
     connectFn: (callbacks: {
       onMessage: (data: string | Buffer) => void;
+      onMessages: (messages: Array<string | Buffer>) => void;
+      onMessageStart: (text: boolean, size: number) => void;
+      onMessageChunk: (chunk: Buffer) => void;
+      onMessageEnd: () => void;
       onClose: (event: NativeWebSocketCloseEvent) => void;
       onError: (message: string) => void;
     }) => Promise<NativeWebSocketConnection>,
@@ -2361,6 +5290,21 @@ export class WebSocket {
         onMessage: (data) => {
           this.handleNativeMessage(data);
         },
+        onMessages: (messages) => {
+          for (const data of messages) {
+            this.handleNativeMessage(data);
+          }
+        },
+        onMessageStart: () => {
+          this.handleNativeMessageStart();
+        },
+        onMessageChunk: (chunk) => {
+          this._messageStream?.enqueue(chunk);
+        },
+        onMessageEnd: () => {
+          this._messageStream?.close();
+          this._messageStream = undefined;
+        },
         onClose: (event) => {
           this.handleNativeClose(event);
         },
@@ -2372,10 +5316,6 @@ export class WebSocket {
       this._connection = connection;
       this.protocol = connection.protocol ?? "";
       this.extensions = connection.extensions ?? "";
-      if (websocketFinalizer) {
-        this._finalizerToken = connection;
-        websocketFinalizer.register(this, connection, connection);
-      }
 
       if (this.readyState === WebSocket.CLOSING) {
         this.startNativeClose();
@@ -2389,7 +5329,7 @@ export class WebSocket {
     } catch (error) {
       this.handleNativeError(String(error));
       this.finalizeClosed({ code: 1006, reason: "" }, false);
-      throw new RequestError(String(error));
+      throw toRequestError(error);
     }
   }
 
@@ -2430,16 +5370,10 @@ export class WebSocket {
     queue(depth);
   }
 
-  private releaseConnectionTracking(): void {
-    if (!this._finalizerToken || !websocketFinalizer) {
-      return;
-    }
-    websocketFinalizer.unregister(this._finalizerToken);
-    this._finalizerToken = undefined;
-  }
-
-  private toMessageEventData(data: string | Buffer): string | Buffer | ArrayBuffer | Blob {
-    if (typeof data === "string") {
+  private toMessageEventData(
+    data: string | Buffer | ReadableStream<Uint8Array>,
+  ): string | Buffer | ArrayBuffer | Blob | ReadableStream<Uint8Array> {
+    if (typeof data === "string" || data instanceof ReadableStream) {
       return data;
     }
     if (this._binaryType === "arraybuffer") {
@@ -2544,7 +5478,7 @@ export class WebSocket {
     }
   }
 
-  private dispatchMessageEvent(data: string | Buffer | ArrayBuffer | Blob): void {
+  private dispatchMessageEvent(data: string | Buffer | ArrayBuffer | Blob | ReadableStream<Uint8Array>): void {
     const event: WebSocketMessageEvent = {
       ...this.createBaseEvent("message"),
       data,
@@ -2577,6 +5511,26 @@ export class WebSocket {
     this.dispatchMessageEvent(this.toMessageEventData(data));
   }
 
+  private handleNativeMessageStart(): void {
+    if (this._closeEventDispatched) {
+      return;
+    }
+
+    // Chunks are queued as they arrive; reading the stream slowly does not hold the connection back.
+    let controller!: ReadableStreamDefaultController<Uint8Array>;
+    const stream = new ReadableStream<Uint8Array>({
+      start: (streamController) => {
+        controller = streamController;
+      },
+    });
+    this._messageStream = controller;
+    if (!this._openEventDispatched && this.readyState === WebSocket.OPEN) {
+      this._pendingMessages.push(stream);
+      return;
+    }
+    this.dispatchMessageEvent(stream);
+  }
+
   private handleNativeError(message: string): void {
     this._legacyCallbacks?.onError?.(message);
     this.dispatchErrorEvent(message);
@@ -2595,7 +5549,8 @@ export class WebSocket {
     this.readyState = WebSocket.CLOSED;
     this._closeEventDispatched = true;
     this._pendingMessages = [];
-    this.releaseConnectionTracking();
+    this._messageStream?.error(new RequestError("WebSocket closed before the message was fully received"));
+    this._messageStream = undefined;
 
     const closeEvent: WebSocketCloseEvent = {
       ...this.createBaseEvent("close"),
@@ -2877,23 +5832,103 @@ export async function websocket(
         onMessage: callbacks.onMessage,
         onClose: callbacks.onClose,
         onError: callbacks.onError,
+        ...(normalized.options.binaryText === true && { binaryText: true }),
+        ...nativeWebSocketBatch(normalized.options, callbacks),
+        ...nativeWebSocketStreaming(normalized.options, callbacks),
       }),
     legacyCallbacks: normalized.legacyCallbacks,
   });
 }
 
+/**
+ * Report whether a WebSocket is connecting, open, closing, or closed, with when it last sent
+ * and received data. The state is read from the native connection, so it is accurate even
+ * before the matching `close` event has been dispatched.
+ */
+export function websocketState(ws: WebSocket): WebSocketState {
+  return WebSocket._stateOf(ws);
+}
+
 export type {
+  AccessTokenGrant,
+  AltSvcEntry,
+  AuditLogOptions,
+  BeforeSendRequest,
+  BodyDelivery,
+  BodyDigestAlgorithm,
   BodyInit,
+  BodyStreamLeak,
+  BodyStreamOptions,
+  BrowserCookie,
+  BrowserFamily,
   BrowserProfile,
+  BrowserProfileAlias,
+  CassetteMatchField,
+  CassetteOptions,
+  ClientHintBrand,
+  ClientHintsOptions,
+  ConnectionInfo,
+  ConnectToRule,
+  ContentCoding,
   CookieMode,
   CreateSessionOptions,
   CreateTransportOptions,
+  DnsAnswer,
   EmulationOS,
+  FetchAllInit,
+  FetchAllResult,
+  FetchAllRetryTarget,
+  FetchAllSummary,
+  H2StreamFrame,
+  H2StreamInit,
+  HeaderFormat,
+  HeaderInitTuple,
   HeadersInit,
+  HeaderTuple,
+  HeaderTupleOptions,
+  HstsEntry,
+  MemoryStats,
+  MirrorDiff,
+  MirrorInit,
+  MirrorResponse,
+  MirrorResult,
+  MirrorTarget,
+  MockMatcher,
+  MockResponseInit,
+  NativeStats,
+  NtlmCredentials,
+  PacingJitter,
+  ProbeOptions,
+  ProbeResult,
+  ProbeTimings,
+  ProxyPoolOptions,
+  ProxyRotationStrategy,
+  RegistryMode,
+  RequestCallback,
+  RequestCallbackOptions,
+  RequestDefaults,
+  RequestErrorOptions,
   RequestInit,
+  RequestMode,
   RequestOptions,
+  RequestPhase,
+  RequestPriority,
+  RequestTiming,
+  ResponseSizes,
+  SessionAuthOptions,
   SessionHandle,
+  SessionPacingOptions,
   SessionWebSocketOptions,
+  ShutdownOptions,
+  StoredCookie,
+  TcpKeepAliveOptions,
+  TlsConnectOptions,
+  TraceContext,
+  TraceProvider,
+  TraceRedirect,
+  TraceSpanEnd,
+  TraceSpanStart,
+  WebSocketBatchOptions,
   WebSocketBinaryType,
   WebSocketCloseEvent,
   WebSocketErrorEvent,
@@ -2901,6 +5936,8 @@ export type {
   WebSocketMessageEvent,
   WebSocketOpenEvent,
   WebSocketOptions,
+  WebSocketState,
+  WebSocketStreamOptions,
 } from "./types.js";
 
 export { RequestError };
@@ -2908,18 +5945,49 @@ export { RequestError };
 export default {
   fetch,
   request,
+  requestCallback,
   get,
   post,
   getProfiles,
   getOperatingSystems,
+  resolveProfile,
+  setStrictProfiles,
+  setRegistryMode,
+  setDefaults,
+  getDefaults,
   createTransport,
   createSession,
   withSession,
+  cancelGroup,
+  cancelAllRequests,
+  configureBodyStreams,
+  createBodyRing,
+  readBodyRing,
+  getNativeStats,
+  getMemoryStats,
+  setTraceProvider,
+  mock,
+  clearMocks,
+  useCassette,
+  enableAuditLog,
+  disableAuditLog,
+  toCurl,
+  fromCurl,
+  mirror,
+  fetchAll,
+  tlsConnect,
+  probe,
+  shutdown,
   websocket,
+  websocketState,
   WebSocket,
   Headers,
   Response,
   Transport,
   Session,
+  Mock,
+  Cassette,
+  TlsSocket,
+  H2Stream,
   RequestError,
 };